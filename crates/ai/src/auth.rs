@@ -1,5 +1,7 @@
-use futures::future::BoxFuture;
+use futures::{future::BoxFuture, FutureExt};
 use gpui::AppContext;
+use std::env;
+use util::ResultExt;
 
 #[derive(Clone, Debug)]
 pub enum ProviderCredential {
@@ -21,3 +23,57 @@ pub trait CredentialProvider: Send + Sync {
     #[must_use]
     fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()>;
 }
+
+/// Reads the API key of `api_url` from the given environment variable if it's set, or else from
+/// the keychain. Providers only pass the variable for their own API, so that the key isn't sent
+/// to other servers.
+pub fn read_api_key(
+    api_url: &str,
+    env_var_name: Option<&str>,
+    cx: &mut AppContext,
+) -> BoxFuture<'static, ProviderCredential> {
+    if let Some(api_key) = env_var_name.and_then(|name| env::var(name).log_err()) {
+        return async move { ProviderCredential::Credentials { api_key } }.boxed();
+    }
+
+    let credentials = cx.read_credentials(api_url);
+    async move {
+        if let Some(Some((_, api_key))) = credentials.await.log_err() {
+            if let Some(api_key) = String::from_utf8(api_key).log_err() {
+                return ProviderCredential::Credentials { api_key };
+            }
+        }
+        ProviderCredential::NoCredentials
+    }
+    .boxed()
+}
+
+/// Saves the API key of `api_url` to the keychain, if the credential has one.
+pub fn write_api_key(
+    api_url: &str,
+    credential: &ProviderCredential,
+    cx: &mut AppContext,
+) -> BoxFuture<'static, ()> {
+    let write_credentials = match credential {
+        ProviderCredential::Credentials { api_key } => {
+            Some(cx.write_credentials(api_url, "Bearer", api_key.as_bytes()))
+        }
+        _ => None,
+    };
+
+    async move {
+        if let Some(write_credentials) = write_credentials {
+            write_credentials.await.log_err();
+        }
+    }
+    .boxed()
+}
+
+/// Deletes the API key of `api_url` from the keychain.
+pub fn delete_api_key(api_url: &str, cx: &mut AppContext) -> BoxFuture<'static, ()> {
+    let delete_credentials = cx.delete_credentials(api_url);
+    async move {
+        delete_credentials.await.log_err();
+    }
+    .boxed()
+}
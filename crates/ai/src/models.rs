@@ -1,9 +1,13 @@
+use anyhow::anyhow;
+use tiktoken_rs::CoreBPE;
+use util::ResultExt;
+
 pub enum TruncationDirection {
     Start,
     End,
 }
 
-pub trait LanguageModel {
+pub trait LanguageModel: Send + Sync {
     fn name(&self) -> String;
     fn count_tokens(&self, content: &str) -> anyhow::Result<usize>;
     fn truncate(
@@ -14,3 +18,91 @@ pub trait LanguageModel {
     ) -> anyhow::Result<String>;
     fn capacity(&self) -> anyhow::Result<usize>;
 }
+
+/// A model whose tokens are counted with a tiktoken encoding. The tokens of models unknown to
+/// tiktoken, such as those served by other OpenAI-compatible servers or Anthropic's, whose
+/// tokenizer isn't published, are approximated with the encoding of the recent OpenAI models.
+#[derive(Clone)]
+pub struct TiktokenLanguageModel {
+    name: String,
+    bpe: Option<CoreBPE>,
+    capacity: usize,
+}
+
+impl TiktokenLanguageModel {
+    pub fn load(model_name: &str, capacity: usize) -> Self {
+        let bpe = tiktoken_rs::get_bpe_from_model(model_name)
+            .or_else(|_| tiktoken_rs::cl100k_base())
+            .log_err();
+        Self {
+            name: model_name.to_string(),
+            bpe,
+            capacity,
+        }
+    }
+
+    fn bpe(&self) -> anyhow::Result<&CoreBPE> {
+        self.bpe
+            .as_ref()
+            .ok_or_else(|| anyhow!("bpe for model {} was not retrieved", self.name))
+    }
+}
+
+impl LanguageModel for TiktokenLanguageModel {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn count_tokens(&self, content: &str) -> anyhow::Result<usize> {
+        Ok(self.bpe()?.encode_with_special_tokens(content).len())
+    }
+    fn truncate(
+        &self,
+        content: &str,
+        length: usize,
+        direction: TruncationDirection,
+    ) -> anyhow::Result<String> {
+        let bpe = self.bpe()?;
+        let tokens = bpe.encode_with_special_tokens(content);
+        if tokens.len() > length {
+            match direction {
+                TruncationDirection::End => bpe.decode(tokens[..length].to_vec()),
+                TruncationDirection::Start => bpe.decode(tokens[tokens.len() - length..].to_vec()),
+            }
+        } else {
+            bpe.decode(tokens)
+        }
+    }
+    fn capacity(&self) -> anyhow::Result<usize> {
+        Ok(self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        let model = TiktokenLanguageModel::load("gpt-4", 8192);
+        let content = "one two three four five";
+        assert_eq!(model.count_tokens(content).unwrap(), 5);
+        assert_eq!(
+            model
+                .truncate(content, 2, TruncationDirection::End)
+                .unwrap(),
+            "one two"
+        );
+        assert_eq!(
+            model
+                .truncate(content, 2, TruncationDirection::Start)
+                .unwrap(),
+            " four five"
+        );
+        assert_eq!(
+            model
+                .truncate(content, 10, TruncationDirection::Start)
+                .unwrap(),
+            content
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use anyhow::{anyhow, Result};
+use futures::{
+    future::BoxFuture, io::BufReader, stream::BoxStream, AsyncBufReadExt, AsyncReadExt, FutureExt,
+    Stream, StreamExt,
+};
+use gpui::{AppContext, BackgroundExecutor};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{io, sync::Arc};
+use util::http::{HttpClient, Request, StatusCode};
+
+use crate::{
+    auth::{delete_api_key, read_api_key, write_api_key, CredentialProvider, ProviderCredential},
+    completion::{CompletionProvider, CompletionRequest},
+    models::{LanguageModel, TiktokenLanguageModel},
+    providers::{
+        anthropic::{load_anthropic_model, ANTHROPIC_API_URL, ANTHROPIC_API_VERSION},
+        open_ai::{OpenAIRequest, Role},
+    },
+};
+
+/// The most tokens a completion can generate, which the messages API requires.
+const MAX_TOKENS_TO_SAMPLE: u32 = 4096;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnthropicMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AnthropicRequest {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub messages: Vec<AnthropicMessage>,
+    pub max_tokens: u32,
+    pub stream: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    pub temperature: f32,
+}
+
+impl AnthropicRequest {
+    /// Converts a chat completion request of the OpenAI API. The messages API takes the system
+    /// prompt separately and requires the roles of the other messages to alternate, starting
+    /// with the user, so the system messages are joined into the system prompt, consecutive
+    /// messages of the same role are merged and assistant messages before the first user message
+    /// are dropped.
+    pub fn from_open_ai(request: OpenAIRequest) -> Self {
+        let mut system_prompts = Vec::new();
+        let mut messages = Vec::<AnthropicMessage>::new();
+        for message in request.messages {
+            if message.content.trim().is_empty() {
+                continue;
+            }
+
+            if message.role == Role::System {
+                system_prompts.push(message.content);
+            } else if messages.is_empty() && message.role == Role::Assistant {
+                continue;
+            } else if let Some(last_message) = messages
+                .last_mut()
+                .filter(|last_message| last_message.role == message.role)
+            {
+                last_message.content.push_str("\n\n");
+                last_message.content.push_str(&message.content);
+            } else {
+                messages.push(AnthropicMessage {
+                    role: message.role,
+                    content: message.content,
+                });
+            }
+        }
+
+        Self {
+            model: request.model,
+            system: (!system_prompts.is_empty()).then(|| system_prompts.join("\n\n")),
+            messages,
+            max_tokens: MAX_TOKENS_TO_SAMPLE,
+            stream: request.stream,
+            stop_sequences: request.stop,
+            temperature: request.temperature.clamp(0., 1.),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicError {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnthropicTextDelta {
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicResponseStreamEvent {
+    ContentBlockDelta {
+        delta: AnthropicTextDelta,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+pub async fn stream_completion(
+    api_url: &str,
+    credential: ProviderCredential,
+    client: Arc<dyn HttpClient>,
+    executor: BackgroundExecutor,
+    request: AnthropicRequest,
+) -> Result<impl Stream<Item = Result<String>>> {
+    let api_key = match credential {
+        ProviderCredential::Credentials { api_key } => api_key,
+        _ => {
+            return Err(anyhow!("no credentials provider for completion"));
+        }
+    };
+
+    let (tx, rx) = futures::channel::mpsc::unbounded::<Result<String>>();
+
+    let json_data = serde_json::to_string(&request)?;
+    let request = Request::post(format!("{api_url}/messages"))
+        .header("Content-Type", "application/json")
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_API_VERSION)
+        .body(json_data.into())?;
+    let mut response = client.send(request).await?;
+
+    let status = response.status();
+    if status == StatusCode::OK {
+        executor
+            .spawn(async move {
+                let mut lines = BufReader::new(response.body_mut()).lines();
+
+                fn parse_line(
+                    line: Result<String, io::Error>,
+                ) -> Result<Option<AnthropicResponseStreamEvent>> {
+                    if let Some(data) = line?.strip_prefix("data: ") {
+                        let event = serde_json::from_str(data)?;
+                        Ok(Some(event))
+                    } else {
+                        Ok(None)
+                    }
+                }
+
+                while let Some(line) = lines.next().await {
+                    let text =
+                        match parse_line(line) {
+                            Ok(Some(AnthropicResponseStreamEvent::ContentBlockDelta { delta })) => {
+                                Ok(delta.text)
+                            }
+                            Ok(Some(AnthropicResponseStreamEvent::MessageStop)) => break,
+                            Ok(Some(AnthropicResponseStreamEvent::Error { error })) => Err(
+                                anyhow!("Anthropic API error: {} {}", error.kind, error.message),
+                            ),
+                            Ok(Some(AnthropicResponseStreamEvent::Other) | None) => continue,
+                            Err(error) => Err(error),
+                        };
+                    let done = text.is_err();
+                    if tx.unbounded_send(text).is_err() || done {
+                        break;
+                    }
+                }
+
+                anyhow::Ok(())
+            })
+            .detach();
+
+        Ok(rx)
+    } else {
+        let mut body = String::new();
+        response.body_mut().read_to_string(&mut body).await?;
+
+        #[derive(Deserialize)]
+        struct AnthropicResponse {
+            error: AnthropicError,
+        }
+
+        match serde_json::from_str::<AnthropicResponse>(&body) {
+            Ok(response) if !response.error.message.is_empty() => Err(anyhow!(
+                "Failed to connect to Anthropic API: {}",
+                response.error.message,
+            )),
+
+            _ => Err(anyhow!(
+                "Failed to connect to Anthropic API: {} {}",
+                response.status(),
+                body,
+            )),
+        }
+    }
+}
+
+/// Completes prompts with the messages endpoint of the Anthropic API.
+#[derive(Clone)]
+pub struct AnthropicCompletionProvider {
+    api_url: String,
+    model: TiktokenLanguageModel,
+    credential: Arc<RwLock<ProviderCredential>>,
+    client: Arc<dyn HttpClient>,
+    executor: BackgroundExecutor,
+}
+
+impl AnthropicCompletionProvider {
+    pub async fn new(
+        api_url: String,
+        model_name: String,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let model = executor
+            .spawn(async move { load_anthropic_model(&model_name) })
+            .await;
+        let credential = Arc::new(RwLock::new(ProviderCredential::NoCredentials));
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            model,
+            credential,
+            client,
+            executor,
+        }
+    }
+}
+
+impl CredentialProvider for AnthropicCompletionProvider {
+    fn has_credentials(&self) -> bool {
+        match *self.credential.read() {
+            ProviderCredential::Credentials { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        let existing_credential = self.credential.read().clone();
+        let retrieved_credential = match existing_credential {
+            ProviderCredential::Credentials { .. } => {
+                return async move { existing_credential }.boxed()
+            }
+            _ => {
+                // The key of the environment is only sent to Anthropic, not to other servers.
+                let env_var_name =
+                    (self.api_url == ANTHROPIC_API_URL).then_some("ANTHROPIC_API_KEY");
+                read_api_key(&self.api_url, env_var_name, cx)
+            }
+        };
+
+        async move {
+            let retrieved_credential = retrieved_credential.await;
+            *self.credential.write() = retrieved_credential.clone();
+            retrieved_credential
+        }
+        .boxed()
+    }
+
+    fn save_credentials(
+        &self,
+        cx: &mut AppContext,
+        credential: ProviderCredential,
+    ) -> BoxFuture<()> {
+        let write_credentials = write_api_key(&self.api_url, &credential, cx);
+        *self.credential.write() = credential;
+        write_credentials
+    }
+
+    fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
+        *self.credential.write() = ProviderCredential::NoCredentials;
+        delete_api_key(&self.api_url, cx)
+    }
+}
+
+impl CompletionProvider for AnthropicCompletionProvider {
+    fn base_model(&self) -> Box<dyn LanguageModel> {
+        let model: Box<dyn LanguageModel> = Box::new(self.model.clone());
+        model
+    }
+    fn complete(
+        &self,
+        prompt: Box<dyn CompletionRequest>,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        // The assistant builds its requests for the OpenAI API, so they are converted to
+        // requests of the messages API here.
+        let credential = self.credential.read().clone();
+        let api_url = self.api_url.clone();
+        let client = self.client.clone();
+        let executor = self.executor.clone();
+        async move {
+            let request = serde_json::from_str::<OpenAIRequest>(&prompt.data()?)?;
+            let request = AnthropicRequest::from_open_ai(request);
+            let stream = stream_completion(&api_url, credential, client, executor, request)
+                .await?
+                .boxed();
+            Ok(stream)
+        }
+        .boxed()
+    }
+    fn box_clone(&self) -> Box<dyn CompletionProvider> {
+        Box::new((*self).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::open_ai::RequestMessage;
+    use gpui::TestAppContext;
+    use util::http::{FakeHttpClient, Response};
+
+    #[gpui::test]
+    async fn test_streaming_completion(cx: &mut TestAppContext) {
+        let client = FakeHttpClient::create(|request| async move {
+            assert_eq!(
+                request.uri().to_string(),
+                "https://api.anthropic.com/v1/messages"
+            );
+            assert_eq!(request.headers().get("x-api-key").unwrap(), "secret");
+            assert_eq!(
+                request.headers().get("anthropic-version").unwrap(),
+                ANTHROPIC_API_VERSION
+            );
+            let mut body = String::new();
+            request.into_body().read_to_string(&mut body).await.unwrap();
+            assert_eq!(
+                serde_json::from_str::<AnthropicRequest>(&body).unwrap(),
+                AnthropicRequest {
+                    model: "claude-2.1".into(),
+                    system: Some("Be brief.".into()),
+                    messages: vec![
+                        AnthropicMessage {
+                            role: Role::User,
+                            content: "Hello\n\nAre you there?".into(),
+                        },
+                        AnthropicMessage {
+                            role: Role::Assistant,
+                            content: "Yes.".into(),
+                        },
+                        AnthropicMessage {
+                            role: Role::User,
+                            content: "Say hi".into(),
+                        },
+                    ],
+                    max_tokens: MAX_TOKENS_TO_SAMPLE,
+                    stream: true,
+                    stop_sequences: vec!["|END|>".into()],
+                    temperature: 1.,
+                }
+            );
+
+            let events = [
+                "event: message_start",
+                r#"data: {"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"claude-2.1"}}"#,
+                "",
+                "event: content_block_start",
+                r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+                "",
+                "event: ping",
+                r#"data: {"type":"ping"}"#,
+                "",
+                "event: content_block_delta",
+                r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+                "",
+                "event: content_block_delta",
+                r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"!"}}"#,
+                "",
+                "event: message_stop",
+                r#"data: {"type":"message_stop"}"#,
+            ];
+            Ok(Response::builder()
+                .status(200)
+                .body(events.join("\n").into())
+                .unwrap())
+        });
+
+        let provider = AnthropicCompletionProvider::new(
+            ANTHROPIC_API_URL.into(),
+            "claude-2.1".into(),
+            client,
+            cx.executor(),
+        )
+        .await;
+        assert!(!provider.has_credentials());
+        *provider.credential.write() = ProviderCredential::Credentials {
+            api_key: "secret".into(),
+        };
+
+        let request = Box::new(OpenAIRequest {
+            model: "claude-2.1".into(),
+            messages: vec![
+                RequestMessage {
+                    role: Role::System,
+                    content: "Be brief.".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "Hello".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "Are you there?".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "Yes.".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "Say hi".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "".into(),
+                },
+            ],
+            stream: true,
+            stop: vec!["|END|>".into()],
+            temperature: 1.,
+        });
+        let completion = provider
+            .complete(request)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<String>>()
+            .unwrap();
+        assert_eq!(completion, "Hi!");
+    }
+
+    #[test]
+    fn test_request_from_open_ai_drops_leading_assistant_messages() {
+        let request = AnthropicRequest::from_open_ai(OpenAIRequest {
+            model: "claude-2.1".into(),
+            messages: vec![
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "How can I help?".into(),
+                },
+                RequestMessage {
+                    role: Role::System,
+                    content: "Be brief.".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "Anything else?".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "Say hi".into(),
+                },
+            ],
+            stream: true,
+            stop: Vec::new(),
+            temperature: 1.,
+        });
+        assert_eq!(request.system.as_deref(), Some("Be brief."));
+        assert_eq!(
+            request.messages,
+            &[AnthropicMessage {
+                role: Role::User,
+                content: "Say hi".into(),
+            }]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_error_event(cx: &mut TestAppContext) {
+        let client = FakeHttpClient::create(|_| async move {
+            let events = [
+                "event: error",
+                r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            ];
+            Ok(Response::builder()
+                .status(200)
+                .body(events.join("\n").into())
+                .unwrap())
+        });
+
+        let provider = AnthropicCompletionProvider::new(
+            ANTHROPIC_API_URL.into(),
+            "claude-2.1".into(),
+            client,
+            cx.executor(),
+        )
+        .await;
+        *provider.credential.write() = ProviderCredential::Credentials {
+            api_key: "secret".into(),
+        };
+
+        let chunks = provider
+            .complete(Box::new(OpenAIRequest {
+                model: "claude-2.1".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            chunks[0].as_ref().unwrap_err().to_string(),
+            "Anthropic API error: overloaded_error Overloaded"
+        );
+    }
+}
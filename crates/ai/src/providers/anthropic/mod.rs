@@ -0,0 +1,8 @@
+pub mod completion;
+pub mod model;
+
+pub use completion::*;
+pub use model::load_anthropic_model;
+
+pub const ANTHROPIC_API_URL: &'static str = "https://api.anthropic.com/v1";
+pub const ANTHROPIC_API_VERSION: &'static str = "2023-06-01";
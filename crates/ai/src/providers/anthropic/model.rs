@@ -0,0 +1,11 @@
+use crate::models::TiktokenLanguageModel;
+
+/// Loads the Claude model with the given name.
+pub fn load_anthropic_model(model_name: &str) -> TiktokenLanguageModel {
+    let capacity = if model_name.starts_with("claude-2.1") {
+        200_000
+    } else {
+        100_000
+    };
+    TiktokenLanguageModel::load(model_name, capacity)
+}
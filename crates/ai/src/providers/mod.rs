@@ -1 +1,5 @@
+pub mod anthropic;
 pub mod open_ai;
+pub mod registry;
+
+pub use registry::*;
@@ -4,24 +4,22 @@ use futures::{
     Stream, StreamExt,
 };
 use gpui::{AppContext, BackgroundExecutor};
-use isahc::{http::StatusCode, Request, RequestExt};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::{
-    env,
     fmt::{self, Display},
     io,
     sync::Arc,
 };
-use util::ResultExt;
+use util::http::{HttpClient, Request, StatusCode};
 
 use crate::{
-    auth::{CredentialProvider, ProviderCredential},
+    auth::{delete_api_key, read_api_key, write_api_key, CredentialProvider, ProviderCredential},
     completion::{CompletionProvider, CompletionRequest},
-    models::LanguageModel,
+    models::{LanguageModel, TiktokenLanguageModel},
 };
 
-use crate::providers::open_ai::{OpenAILanguageModel, OPENAI_API_URL};
+use crate::providers::open_ai::{load_open_ai_model, OPENAI_API_URL};
 
 #[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -57,7 +55,7 @@ pub struct RequestMessage {
     pub content: String,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct OpenAIRequest {
     pub model: String,
     pub messages: Vec<RequestMessage>,
@@ -103,26 +101,29 @@ pub struct OpenAIResponseStreamEvent {
 }
 
 pub async fn stream_completion(
+    api_url: &str,
     credential: ProviderCredential,
+    client: Arc<dyn HttpClient>,
     executor: BackgroundExecutor,
     request: Box<dyn CompletionRequest>,
 ) -> Result<impl Stream<Item = Result<OpenAIResponseStreamEvent>>> {
-    let api_key = match credential {
-        ProviderCredential::Credentials { api_key } => api_key,
-        _ => {
+    let mut request_builder = Request::post(format!("{api_url}/chat/completions"))
+        .header("Content-Type", "application/json");
+    match credential {
+        ProviderCredential::Credentials { api_key } => {
+            request_builder =
+                request_builder.header("Authorization", format!("Bearer {}", api_key));
+        }
+        ProviderCredential::NotNeeded => {}
+        ProviderCredential::NoCredentials => {
             return Err(anyhow!("no credentials provider for completion"));
         }
-    };
+    }
 
     let (tx, rx) = futures::channel::mpsc::unbounded::<Result<OpenAIResponseStreamEvent>>();
 
     let json_data = request.data()?;
-    let mut response = Request::post(format!("{OPENAI_API_URL}/chat/completions"))
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .body(json_data)?
-        .send_async()
-        .await?;
+    let mut response = client.send(request_builder.body(json_data.into())?).await?;
 
     let status = response.status();
     if status == StatusCode::OK {
@@ -193,22 +194,42 @@ pub async fn stream_completion(
     }
 }
 
+/// Completes prompts with the chat completions endpoint of the OpenAI API, or of any server
+/// compatible with it, such as llama.cpp's server, vLLM or Ollama.
 #[derive(Clone)]
 pub struct OpenAICompletionProvider {
-    model: OpenAILanguageModel,
+    api_url: String,
+    requires_api_key: bool,
+    model: TiktokenLanguageModel,
     credential: Arc<RwLock<ProviderCredential>>,
+    client: Arc<dyn HttpClient>,
     executor: BackgroundExecutor,
 }
 
 impl OpenAICompletionProvider {
-    pub async fn new(model_name: String, executor: BackgroundExecutor) -> Self {
+    /// Creates a provider for the server at `api_url`. Local servers usually don't need an API
+    /// key, in which case requests are sent without one.
+    pub async fn new(
+        api_url: String,
+        model_name: String,
+        requires_api_key: bool,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Self {
         let model = executor
-            .spawn(async move { OpenAILanguageModel::load(&model_name) })
+            .spawn(async move { load_open_ai_model(&model_name) })
             .await;
-        let credential = Arc::new(RwLock::new(ProviderCredential::NoCredentials));
+        let credential = if requires_api_key {
+            ProviderCredential::NoCredentials
+        } else {
+            ProviderCredential::NotNeeded
+        };
         Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            requires_api_key,
             model,
-            credential,
+            credential: Arc::new(RwLock::new(credential)),
+            client,
             executor,
         }
     }
@@ -217,35 +238,21 @@ impl OpenAICompletionProvider {
 impl CredentialProvider for OpenAICompletionProvider {
     fn has_credentials(&self) -> bool {
         match *self.credential.read() {
-            ProviderCredential::Credentials { .. } => true,
-            _ => false,
+            ProviderCredential::Credentials { .. } | ProviderCredential::NotNeeded => true,
+            ProviderCredential::NoCredentials => false,
         }
     }
 
     fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
         let existing_credential = self.credential.read().clone();
         let retrieved_credential = match existing_credential {
-            ProviderCredential::Credentials { .. } => {
+            ProviderCredential::Credentials { .. } | ProviderCredential::NotNeeded => {
                 return async move { existing_credential }.boxed()
             }
-            _ => {
-                if let Some(api_key) = env::var("OPENAI_API_KEY").log_err() {
-                    async move { ProviderCredential::Credentials { api_key } }.boxed()
-                } else {
-                    let credentials = cx.read_credentials(OPENAI_API_URL);
-                    async move {
-                        if let Some(Some((_, api_key))) = credentials.await.log_err() {
-                            if let Some(api_key) = String::from_utf8(api_key).log_err() {
-                                ProviderCredential::Credentials { api_key }
-                            } else {
-                                ProviderCredential::NoCredentials
-                            }
-                        } else {
-                            ProviderCredential::NoCredentials
-                        }
-                    }
-                    .boxed()
-                }
+            ProviderCredential::NoCredentials => {
+                // The key of the environment is only sent to OpenAI, not to other servers.
+                let env_var_name = (self.api_url == OPENAI_API_URL).then_some("OPENAI_API_KEY");
+                read_api_key(&self.api_url, env_var_name, cx)
             }
         };
 
@@ -262,30 +269,18 @@ impl CredentialProvider for OpenAICompletionProvider {
         cx: &mut AppContext,
         credential: ProviderCredential,
     ) -> BoxFuture<()> {
-        *self.credential.write() = credential.clone();
-        let credential = credential.clone();
-        let write_credentials = match credential {
-            ProviderCredential::Credentials { api_key } => {
-                Some(cx.write_credentials(OPENAI_API_URL, "Bearer", api_key.as_bytes()))
-            }
-            _ => None,
-        };
-
-        async move {
-            if let Some(write_credentials) = write_credentials {
-                write_credentials.await.log_err();
-            }
-        }
-        .boxed()
+        let write_credentials = write_api_key(&self.api_url, &credential, cx);
+        *self.credential.write() = credential;
+        write_credentials
     }
 
     fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
-        *self.credential.write() = ProviderCredential::NoCredentials;
-        let delete_credentials = cx.delete_credentials(OPENAI_API_URL);
-        async move {
-            delete_credentials.await.log_err();
-        }
-        .boxed()
+        *self.credential.write() = if self.requires_api_key {
+            ProviderCredential::NoCredentials
+        } else {
+            ProviderCredential::NotNeeded
+        };
+        delete_api_key(&self.api_url, cx)
     }
 }
 
@@ -303,9 +298,12 @@ impl CompletionProvider for OpenAICompletionProvider {
         // which is currently model based, due to the language model.
         // At some point in the future we should rectify this.
         let credential = self.credential.read().clone();
-        let request = stream_completion(credential, self.executor.clone(), prompt);
+        let api_url = self.api_url.clone();
+        let client = self.client.clone();
+        let executor = self.executor.clone();
         async move {
-            let response = request.await?;
+            let response =
+                stream_completion(&api_url, credential, client, executor, prompt).await?;
             let stream = response
                 .filter_map(|response| async move {
                     match response {
@@ -322,3 +320,67 @@ impl CompletionProvider for OpenAICompletionProvider {
         Box::new((*self).clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use util::http::{FakeHttpClient, Response};
+
+    #[gpui::test]
+    async fn test_completion_with_local_server(cx: &mut TestAppContext) {
+        let client = FakeHttpClient::create(|request| async move {
+            assert_eq!(
+                request.uri().to_string(),
+                "http://localhost:8080/v1/chat/completions"
+            );
+            assert!(request.headers().get("Authorization").is_none());
+            let mut body = String::new();
+            request.into_body().read_to_string(&mut body).await.unwrap();
+            let request = serde_json::from_str::<OpenAIRequest>(&body).unwrap();
+            assert_eq!(request.model, "llama-2-7b-chat");
+            assert_eq!(request.messages[0].content, "Hello");
+
+            let events = [
+                r#"data: {"object":"chat.completion.chunk","created":0,"model":"llama-2-7b-chat","choices":[{"index":0,"delta":{"role":"assistant","content":"Hi"},"finish_reason":null}]}"#,
+                r#"data: {"object":"chat.completion.chunk","created":0,"model":"llama-2-7b-chat","choices":[{"index":0,"delta":{"content":" there"},"finish_reason":null}]}"#,
+                r#"data: {"object":"chat.completion.chunk","created":0,"model":"llama-2-7b-chat","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+                "data: [DONE]",
+            ];
+            Ok(Response::builder()
+                .status(200)
+                .body(events.join("\n\n").into())
+                .unwrap())
+        });
+
+        let provider = OpenAICompletionProvider::new(
+            "http://localhost:8080/v1/".into(),
+            "llama-2-7b-chat".into(),
+            false,
+            client,
+            cx.executor(),
+        )
+        .await;
+        assert!(provider.has_credentials());
+
+        let request = Box::new(OpenAIRequest {
+            model: "llama-2-7b-chat".into(),
+            messages: vec![RequestMessage {
+                role: Role::User,
+                content: "Hello".into(),
+            }],
+            stream: true,
+            ..Default::default()
+        });
+        let completion = provider
+            .complete(request)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<String>>()
+            .unwrap();
+        assert_eq!(completion, "Hi there");
+    }
+}
@@ -14,18 +14,18 @@ use parse_duration::parse;
 use postage::watch;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::env;
 use std::ops::Add;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tiktoken_rs::{cl100k_base, CoreBPE};
 use util::http::{HttpClient, Request};
-use util::ResultExt;
 
-use crate::auth::{CredentialProvider, ProviderCredential};
+use crate::auth::{
+    delete_api_key, read_api_key, write_api_key, CredentialProvider, ProviderCredential,
+};
 use crate::embedding::{Embedding, EmbeddingProvider};
-use crate::models::LanguageModel;
-use crate::providers::open_ai::OpenAILanguageModel;
+use crate::models::{LanguageModel, TiktokenLanguageModel};
+use crate::providers::open_ai::load_open_ai_model;
 
 use crate::providers::open_ai::OPENAI_API_URL;
 
@@ -33,9 +33,12 @@ lazy_static! {
     static ref OPENAI_BPE_TOKENIZER: CoreBPE = cl100k_base().unwrap();
 }
 
+/// Embeds text with the embeddings endpoint of the OpenAI API, or of any server compatible with it.
 #[derive(Clone)]
 pub struct OpenAIEmbeddingProvider {
-    model: OpenAILanguageModel,
+    api_url: String,
+    requires_api_key: bool,
+    model: TiktokenLanguageModel,
     credential: Arc<RwLock<ProviderCredential>>,
     pub client: Arc<dyn HttpClient>,
     pub executor: BackgroundExecutor,
@@ -45,7 +48,7 @@ pub struct OpenAIEmbeddingProvider {
 
 #[derive(Serialize)]
 struct OpenAIEmbeddingRequest<'a> {
-    model: &'static str,
+    model: &'a str,
     input: Vec<&'a str>,
 }
 
@@ -69,19 +72,31 @@ struct OpenAIEmbeddingUsage {
 }
 
 impl OpenAIEmbeddingProvider {
-    pub async fn new(client: Arc<dyn HttpClient>, executor: BackgroundExecutor) -> Self {
+    pub async fn new(
+        api_url: String,
+        model_name: String,
+        requires_api_key: bool,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Self {
         let (rate_limit_count_tx, rate_limit_count_rx) = watch::channel_with(None);
         let rate_limit_count_tx = Arc::new(Mutex::new(rate_limit_count_tx));
 
         // Loading the model is expensive, so ensure this runs off the main thread.
         let model = executor
-            .spawn(async move { OpenAILanguageModel::load("text-embedding-ada-002") })
+            .spawn(async move { load_open_ai_model(&model_name) })
             .await;
-        let credential = Arc::new(RwLock::new(ProviderCredential::NoCredentials));
+        let credential = if requires_api_key {
+            ProviderCredential::NoCredentials
+        } else {
+            ProviderCredential::NotNeeded
+        };
 
         OpenAIEmbeddingProvider {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            requires_api_key,
             model,
-            credential,
+            credential: Arc::new(RwLock::new(credential)),
             client,
             executor,
             rate_limit_count_rx,
@@ -89,10 +104,11 @@ impl OpenAIEmbeddingProvider {
         }
     }
 
-    fn get_api_key(&self) -> Result<String> {
+    fn get_api_key(&self) -> Result<Option<String>> {
         match self.credential.read().clone() {
-            ProviderCredential::Credentials { api_key } => Ok(api_key),
-            _ => Err(anyhow!("api credentials not provided")),
+            ProviderCredential::Credentials { api_key } => Ok(Some(api_key)),
+            ProviderCredential::NotNeeded => Ok(None),
+            ProviderCredential::NoCredentials => Err(anyhow!("api credentials not provided")),
         }
     }
 
@@ -130,23 +146,26 @@ impl OpenAIEmbeddingProvider {
     }
     async fn send_request(
         &self,
-        api_key: &str,
+        api_key: Option<&str>,
         spans: Vec<&str>,
         request_timeout: u64,
     ) -> Result<Response<AsyncBody>> {
-        let request = Request::post("https://api.openai.com/v1/embeddings")
+        let mut request = Request::post(format!("{}/embeddings", self.api_url))
             .redirect_policy(isahc::config::RedirectPolicy::Follow)
             .timeout(Duration::from_secs(request_timeout))
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .body(
-                serde_json::to_string(&OpenAIEmbeddingRequest {
-                    input: spans.clone(),
-                    model: "text-embedding-ada-002",
-                })
-                .unwrap()
-                .into(),
-            )?;
+            .header("Content-Type", "application/json");
+        if let Some(api_key) = api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let model_name = self.model.name();
+        let request = request.body(
+            serde_json::to_string(&OpenAIEmbeddingRequest {
+                input: spans.clone(),
+                model: &model_name,
+            })
+            .unwrap()
+            .into(),
+        )?;
 
         Ok(self.client.send(request).await?)
     }
@@ -155,35 +174,21 @@ impl OpenAIEmbeddingProvider {
 impl CredentialProvider for OpenAIEmbeddingProvider {
     fn has_credentials(&self) -> bool {
         match *self.credential.read() {
-            ProviderCredential::Credentials { .. } => true,
-            _ => false,
+            ProviderCredential::Credentials { .. } | ProviderCredential::NotNeeded => true,
+            ProviderCredential::NoCredentials => false,
         }
     }
 
     fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
         let existing_credential = self.credential.read().clone();
         let retrieved_credential = match existing_credential {
-            ProviderCredential::Credentials { .. } => {
+            ProviderCredential::Credentials { .. } | ProviderCredential::NotNeeded => {
                 return async move { existing_credential }.boxed()
             }
-            _ => {
-                if let Some(api_key) = env::var("OPENAI_API_KEY").log_err() {
-                    async move { ProviderCredential::Credentials { api_key } }.boxed()
-                } else {
-                    let credentials = cx.read_credentials(OPENAI_API_URL);
-                    async move {
-                        if let Some(Some((_, api_key))) = credentials.await.log_err() {
-                            if let Some(api_key) = String::from_utf8(api_key).log_err() {
-                                ProviderCredential::Credentials { api_key }
-                            } else {
-                                ProviderCredential::NoCredentials
-                            }
-                        } else {
-                            ProviderCredential::NoCredentials
-                        }
-                    }
-                    .boxed()
-                }
+            ProviderCredential::NoCredentials => {
+                // The key of the environment is only sent to OpenAI, not to other servers.
+                let env_var_name = (self.api_url == OPENAI_API_URL).then_some("OPENAI_API_KEY");
+                read_api_key(&self.api_url, env_var_name, cx)
             }
         };
 
@@ -200,30 +205,18 @@ impl CredentialProvider for OpenAIEmbeddingProvider {
         cx: &mut AppContext,
         credential: ProviderCredential,
     ) -> BoxFuture<()> {
-        *self.credential.write() = credential.clone();
-        let credential = credential.clone();
-        let write_credentials = match credential {
-            ProviderCredential::Credentials { api_key } => {
-                Some(cx.write_credentials(OPENAI_API_URL, "Bearer", api_key.as_bytes()))
-            }
-            _ => None,
-        };
-
-        async move {
-            if let Some(write_credentials) = write_credentials {
-                write_credentials.await.log_err();
-            }
-        }
-        .boxed()
+        let write_credentials = write_api_key(&self.api_url, &credential, cx);
+        *self.credential.write() = credential;
+        write_credentials
     }
 
     fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
-        *self.credential.write() = ProviderCredential::NoCredentials;
-        let delete_credentials = cx.delete_credentials(OPENAI_API_URL);
-        async move {
-            delete_credentials.await.log_err();
-        }
-        .boxed()
+        *self.credential.write() = if self.requires_api_key {
+            ProviderCredential::NoCredentials
+        } else {
+            ProviderCredential::NotNeeded
+        };
+        delete_api_key(&self.api_url, cx)
     }
 }
 
@@ -255,7 +248,7 @@ impl EmbeddingProvider for OpenAIEmbeddingProvider {
         while request_number < MAX_RETRIES {
             response = self
                 .send_request(
-                    &api_key,
+                    api_key.as_deref(),
                     spans.iter().map(|x| &**x).collect(),
                     request_timeout,
                 )
@@ -334,3 +327,73 @@ impl EmbeddingProvider for OpenAIEmbeddingProvider {
         Err(anyhow!("openai max retries"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use util::http::FakeHttpClient;
+
+    #[gpui::test]
+    async fn test_embedding_with_configured_model(cx: &mut TestAppContext) {
+        let client = FakeHttpClient::create(|request| async move {
+            assert_eq!(
+                request.uri().to_string(),
+                "http://localhost:11434/v1/embeddings"
+            );
+            assert_eq!(
+                request.headers().get("Authorization").unwrap(),
+                "Bearer secret"
+            );
+            let mut body = String::new();
+            request.into_body().read_to_string(&mut body).await.unwrap();
+            let body = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+            assert_eq!(body["model"], "nomic-embed-text");
+            assert_eq!(
+                body["input"],
+                serde_json::json!(["fn main() {}", "struct A;"])
+            );
+
+            let response = serde_json::json!({
+                "data": [
+                    { "embedding": [0.5, 0.5], "index": 0, "object": "embedding" },
+                    { "embedding": [1.0, 0.0], "index": 1, "object": "embedding" },
+                ],
+                "usage": { "prompt_tokens": 8, "total_tokens": 8 },
+            });
+            Ok(Response::builder()
+                .status(200)
+                .body(response.to_string().into())
+                .unwrap())
+        });
+
+        let provider = OpenAIEmbeddingProvider::new(
+            "http://localhost:11434/v1".into(),
+            "nomic-embed-text".into(),
+            true,
+            client,
+            cx.executor(),
+        )
+        .await;
+        assert!(!provider.has_credentials());
+        assert!(provider
+            .embed_batch(vec!["fn main() {}".into()])
+            .await
+            .is_err());
+
+        *provider.credential.write() = ProviderCredential::Credentials {
+            api_key: "secret".into(),
+        };
+        let embeddings = provider
+            .embed_batch(vec!["fn main() {}".into(), "struct A;".into()])
+            .await
+            .unwrap();
+        assert_eq!(
+            embeddings,
+            vec![
+                Embedding::from(vec![0.5, 0.5]),
+                Embedding::from(vec![1.0, 0.0])
+            ]
+        );
+    }
+}
@@ -4,6 +4,6 @@ pub mod model;
 
 pub use completion::*;
 pub use embedding::*;
-pub use model::OpenAILanguageModel;
+pub use model::load_open_ai_model;
 
 pub const OPENAI_API_URL: &'static str = "https://api.openai.com/v1";
@@ -0,0 +1,337 @@
+use anyhow::{anyhow, Result};
+use gpui::BackgroundExecutor;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use std::{collections::BTreeMap, sync::Arc};
+use util::http::HttpClient;
+
+use crate::{
+    completion::CompletionProvider,
+    embedding::EmbeddingProvider,
+    models::LanguageModel,
+    providers::{
+        anthropic::{load_anthropic_model, AnthropicCompletionProvider},
+        open_ai::{load_open_ai_model, OpenAICompletionProvider, OpenAIEmbeddingProvider},
+    },
+};
+
+/// The API spoken by a language model provider.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// The OpenAI API, also served by llama.cpp's server, vLLM and Ollama.
+    OpenAi,
+    /// The messages API of Anthropic.
+    Anthropic,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ProviderSettings {
+    pub kind: ProviderKind,
+    pub api_url: String,
+    #[serde(default = "default_requires_api_key")]
+    pub requires_api_key: bool,
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+}
+
+fn default_requires_api_key() -> bool {
+    true
+}
+
+/// A language model provider.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ProviderSettingsContent {
+    /// The API spoken by the provider, either "open_ai" or "anthropic".
+    pub kind: Option<ProviderKind>,
+    /// The base URL of the API, such as "http://localhost:8080/v1" for a local server.
+    pub api_url: Option<String>,
+    /// Whether the provider needs an API key. Local servers usually don't.
+    ///
+    /// Default: true
+    pub requires_api_key: Option<bool>,
+    /// The models that conversations can use, the first one being the default.
+    pub models: Option<Vec<String>>,
+    /// The model used to embed code for the semantic index, if the provider has one.
+    pub embedding_model: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ModelSelection {
+    pub provider: String,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// The provider and model used by a feature.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ModelSelectionContent {
+    /// The name of the provider, among the configured ones.
+    pub provider: Option<String>,
+    /// The model of the provider. When omitted, the provider's first model is used.
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LanguageModelSettings {
+    pub providers: BTreeMap<String, ProviderSettings>,
+    pub assistant_panel: ModelSelection,
+    pub inline_assist: ModelSelection,
+    pub embeddings: ModelSelection,
+}
+
+/// The language model providers, and which of them each feature uses.
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct LanguageModelSettingsContent {
+    /// The providers that can be selected, by name.
+    pub providers: Option<BTreeMap<String, ProviderSettingsContent>>,
+    /// The provider and model of new conversations in the assistant panel.
+    ///
+    /// Default: the first model of "openai"
+    pub assistant_panel: Option<ModelSelectionContent>,
+    /// The provider and model of the inline assistant.
+    ///
+    /// Default: the first model of "openai"
+    pub inline_assist: Option<ModelSelectionContent>,
+    /// The provider and model embedding code for the semantic index.
+    ///
+    /// Default: the embedding model of "openai"
+    pub embeddings: Option<ModelSelectionContent>,
+}
+
+impl Settings for LanguageModelSettings {
+    const KEY: Option<&'static str> = Some("language_models");
+
+    type FileContent = LanguageModelSettingsContent;
+
+    fn load(
+        default_value: &Self::FileContent,
+        user_values: &[&Self::FileContent],
+        _: &mut gpui::AppContext,
+    ) -> Result<Self> {
+        Self::load_via_json_merge(default_value, user_values)
+    }
+}
+
+impl LanguageModelSettings {
+    pub fn assistant_panel_model(&self) -> Result<SelectedModel> {
+        self.completion_model(&self.assistant_panel)
+    }
+
+    pub fn inline_assist_model(&self) -> Result<SelectedModel> {
+        self.completion_model(&self.inline_assist)
+    }
+
+    pub fn embedding_model(&self) -> Result<SelectedModel> {
+        let (provider_name, provider) = self.provider(&self.embeddings)?;
+        if provider.kind == ProviderKind::Anthropic {
+            return Err(anyhow!("provider {provider_name:?} doesn't embed text"));
+        }
+        let name = self
+            .embeddings
+            .model
+            .clone()
+            .or_else(|| provider.embedding_model.clone())
+            .ok_or_else(|| anyhow!("no embedding model configured for {provider_name:?}"))?;
+        Ok(SelectedModel {
+            provider_name,
+            provider,
+            name,
+        })
+    }
+
+    fn completion_model(&self, selection: &ModelSelection) -> Result<SelectedModel> {
+        let (provider_name, provider) = self.provider(selection)?;
+        let name = selection
+            .model
+            .clone()
+            .or_else(|| provider.models.first().cloned())
+            .ok_or_else(|| anyhow!("no model configured for {provider_name:?}"))?;
+        Ok(SelectedModel {
+            provider_name,
+            provider,
+            name,
+        })
+    }
+
+    fn provider(&self, selection: &ModelSelection) -> Result<(String, ProviderSettings)> {
+        let provider = self
+            .providers
+            .get(&selection.provider)
+            .ok_or_else(|| anyhow!("unknown language model provider {:?}", selection.provider))?;
+        Ok((selection.provider.clone(), provider.clone()))
+    }
+}
+
+/// A model of a configured provider, from which completion and embedding providers are built.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectedModel {
+    pub provider_name: String,
+    pub provider: ProviderSettings,
+    pub name: String,
+}
+
+impl SelectedModel {
+    /// Whether both models are served by the same provider, which can then be shared.
+    pub fn is_same_provider(&self, other: &SelectedModel) -> bool {
+        self.provider_name == other.provider_name && self.provider == other.provider
+    }
+
+    /// Another model of the same provider, if it has it.
+    pub fn with_name(&self, name: &str) -> Option<Self> {
+        self.provider
+            .models
+            .iter()
+            .any(|model| model == name)
+            .then(|| Self {
+                name: name.to_string(),
+                ..self.clone()
+            })
+    }
+
+    /// The model following this one in the provider's models.
+    pub fn cycle(&self) -> Self {
+        let models = &self.provider.models;
+        let next_ix = models
+            .iter()
+            .position(|model| *model == self.name)
+            .map_or(0, |ix| ix + 1);
+        match models.get(next_ix).or(models.first()) {
+            Some(name) => Self {
+                name: name.clone(),
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// Loads the tokenizer of the model, which is expensive.
+    pub fn language_model(&self) -> Box<dyn LanguageModel> {
+        match self.provider.kind {
+            ProviderKind::OpenAi => Box::new(load_open_ai_model(&self.name)),
+            ProviderKind::Anthropic => Box::new(load_anthropic_model(&self.name)),
+        }
+    }
+
+    pub async fn completion_provider(
+        &self,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Arc<dyn CompletionProvider> {
+        let api_url = self.provider.api_url.clone();
+        match self.provider.kind {
+            ProviderKind::OpenAi => Arc::new(
+                OpenAICompletionProvider::new(
+                    api_url,
+                    self.name.clone(),
+                    self.provider.requires_api_key,
+                    client,
+                    executor,
+                )
+                .await,
+            ),
+            ProviderKind::Anthropic => Arc::new(
+                AnthropicCompletionProvider::new(api_url, self.name.clone(), client, executor)
+                    .await,
+            ),
+        }
+    }
+
+    pub async fn embedding_provider(
+        &self,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Result<Arc<dyn EmbeddingProvider>> {
+        match self.provider.kind {
+            ProviderKind::OpenAi => Ok(Arc::new(
+                OpenAIEmbeddingProvider::new(
+                    self.provider.api_url.clone(),
+                    self.name.clone(),
+                    self.provider.requires_api_key,
+                    client,
+                    executor,
+                )
+                .await,
+            )),
+            ProviderKind::Anthropic => Err(anyhow!(
+                "provider {:?} doesn't embed text",
+                self.provider_name
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_selection() {
+        let settings = serde_json::from_value::<LanguageModelSettings>(serde_json::json!({
+            "providers": {
+                "openai": {
+                    "kind": "open_ai",
+                    "api_url": "https://api.openai.com/v1",
+                    "models": ["gpt-4-1106-preview", "gpt-3.5-turbo-0613"],
+                    "embedding_model": "text-embedding-ada-002"
+                },
+                "anthropic": {
+                    "kind": "anthropic",
+                    "api_url": "https://api.anthropic.com/v1",
+                    "models": ["claude-2.1", "claude-instant-1.2"]
+                },
+                "ollama": {
+                    "kind": "open_ai",
+                    "api_url": "http://localhost:11434/v1",
+                    "requires_api_key": false
+                }
+            },
+            "assistant_panel": { "provider": "anthropic" },
+            "inline_assist": { "provider": "ollama", "model": "codellama" },
+            "embeddings": { "provider": "anthropic" }
+        }))
+        .unwrap();
+
+        let panel_model = settings.assistant_panel_model().unwrap();
+        assert_eq!(panel_model.provider_name, "anthropic");
+        assert_eq!(panel_model.name, "claude-2.1");
+        assert_eq!(panel_model.cycle().name, "claude-instant-1.2");
+        assert_eq!(panel_model.cycle().cycle().name, "claude-2.1");
+        assert_eq!(
+            panel_model.with_name("claude-instant-1.2").unwrap().name,
+            "claude-instant-1.2"
+        );
+        assert_eq!(panel_model.with_name("gpt-4-0613"), None);
+
+        let inline_model = settings.inline_assist_model().unwrap();
+        assert_eq!(inline_model.name, "codellama");
+        assert!(!inline_model.provider.requires_api_key);
+        assert_eq!(inline_model.cycle(), inline_model);
+        assert!(!inline_model.is_same_provider(&panel_model));
+
+        assert!(settings.embedding_model().is_err());
+        let settings = LanguageModelSettings {
+            embeddings: ModelSelection {
+                provider: "openai".into(),
+                model: None,
+            },
+            ..settings
+        };
+        assert_eq!(
+            settings.embedding_model().unwrap().name,
+            "text-embedding-ada-002"
+        );
+
+        let settings = LanguageModelSettings {
+            assistant_panel: ModelSelection {
+                provider: "llama.cpp".into(),
+                model: None,
+            },
+            ..settings
+        };
+        assert!(settings.assistant_panel_model().is_err());
+    }
+}
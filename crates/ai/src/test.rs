@@ -44,9 +44,12 @@ impl LanguageModel for FakeLanguageModel {
             TruncationDirection::End => content.chars().collect::<Vec<char>>()[..length]
                 .into_iter()
                 .collect::<String>(),
-            TruncationDirection::Start => content.chars().collect::<Vec<char>>()[length..]
-                .into_iter()
-                .collect::<String>(),
+            TruncationDirection::Start => {
+                let chars = content.chars().collect::<Vec<char>>();
+                chars[chars.len() - length..]
+                    .into_iter()
+                    .collect::<String>()
+            }
         })
     }
     fn capacity(&self) -> anyhow::Result<usize> {
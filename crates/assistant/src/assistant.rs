@@ -7,7 +7,6 @@ mod streaming_diff;
 use ai::providers::open_ai::Role;
 use anyhow::Result;
 pub use assistant_panel::AssistantPanel;
-use assistant_settings::OpenAIModel;
 use chrono::{DateTime, Local};
 use collections::HashMap;
 use fs::Fs;
@@ -68,7 +67,11 @@ struct SavedConversation {
     messages: Vec<SavedMessage>,
     message_metadata: HashMap<MessageId, MessageMetadata>,
     summary: String,
-    model: OpenAIModel,
+    model: String,
+    /// The name of the language model provider serving the model. Conversations saved before
+    /// providers were configurable don't have one, and used OpenAI.
+    #[serde(default)]
+    provider: Option<String>,
 }
 
 impl SavedConversation {
@@ -1,5 +1,5 @@
 use crate::{
-    assistant_settings::{AssistantDockPosition, AssistantSettings, OpenAIModel},
+    assistant_settings::{AssistantDockPosition, AssistantSettings},
     codegen::{self, Codegen, CodegenKind},
     prompts::generate_content_prompt,
     Assist, CycleMessageRole, InlineAssist, MessageId, MessageMetadata, MessageStatus,
@@ -10,7 +10,12 @@ use ai::prompts::repository_context::PromptCodeSnippet;
 use ai::{
     auth::ProviderCredential,
     completion::{CompletionProvider, CompletionRequest},
-    providers::open_ai::{OpenAICompletionProvider, OpenAIRequest, RequestMessage},
+    models::LanguageModel,
+    providers::{
+        anthropic::ANTHROPIC_API_URL,
+        open_ai::{OpenAIRequest, RequestMessage, OPENAI_API_URL},
+        LanguageModelSettings, ModelSelection, SelectedModel,
+    },
 };
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Local};
@@ -29,17 +34,18 @@ use fs::Fs;
 use futures::StreamExt;
 use gpui::{
     canvas, div, point, relative, rems, uniform_list, Action, AnyElement, AppContext,
-    AsyncAppContext, AsyncWindowContext, AvailableSpace, ClipboardItem, Context, EventEmitter,
-    FocusHandle, FocusableView, FontStyle, FontWeight, HighlightStyle, InteractiveElement,
-    IntoElement, Model, ModelContext, ParentElement, Pixels, PromptLevel, Render, SharedString,
-    StatefulInteractiveElement, Styled, Subscription, Task, TextStyle, UniformListScrollHandle,
-    View, ViewContext, VisualContext, WeakModel, WeakView, WhiteSpace, WindowContext,
+    AsyncAppContext, AsyncWindowContext, AvailableSpace, BackgroundExecutor, ClipboardItem,
+    Context, EventEmitter, FocusHandle, FocusableView, FontStyle, FontWeight, HighlightStyle,
+    InteractiveElement, IntoElement, Model, ModelContext, ParentElement, Pixels, PromptLevel,
+    Render, SharedString, StatefulInteractiveElement, Styled, Subscription, Task, TextStyle,
+    UniformListScrollHandle, View, ViewContext, VisualContext, WeakModel, WeakView, WhiteSpace,
+    WindowContext,
 };
 use language::{language_settings::SoftWrap, Buffer, LanguageRegistry, ToOffset as _};
 use project::Project;
 use search::{buffer_search::DivRegistrar, BufferSearchBar};
 use semantic_index::{SemanticIndex, SemanticIndexStatus};
-use settings::Settings;
+use settings::{Settings, SettingsStore};
 use std::{
     cell::Cell,
     cmp,
@@ -57,7 +63,7 @@ use ui::{
     utils::{DateTimeType, FormatDistance},
     ButtonLike, Tab, TabBar, Tooltip,
 };
-use util::{paths::CONVERSATIONS_DIR, post_inc, ResultExt, TryFutureExt};
+use util::{http::HttpClient, paths::CONVERSATIONS_DIR, post_inc, ResultExt, TryFutureExt};
 use uuid::Uuid;
 use workspace::{
     dock::{DockPosition, Panel, PanelEvent},
@@ -67,6 +73,7 @@ use workspace::{
 
 pub fn init(cx: &mut AppContext) {
     AssistantSettings::register(cx);
+    LanguageModelSettings::register(cx);
     cx.observe_new_views(
         |workspace: &mut Workspace, _cx: &mut ViewContext<Workspace>| {
             workspace
@@ -93,7 +100,9 @@ pub struct AssistantPanel {
     zoomed: bool,
     focus_handle: FocusHandle,
     toolbar: View<Toolbar>,
-    completion_provider: Arc<dyn CompletionProvider>,
+    /// The models configured in the language model settings, or why they can't be used.
+    models: Result<ConfiguredModels, SharedString>,
+    pending_models: Task<()>,
     api_key_editor: Option<View<Editor>>,
     languages: Arc<LanguageRegistry>,
     fs: Arc<dyn Fs>,
@@ -104,10 +113,66 @@ pub struct AssistantPanel {
     include_conversation_in_next_inline_assist: bool,
     inline_prompt_history: VecDeque<String>,
     _watch_saved_conversations: Task<Result<()>>,
-    semantic_index: Option<Model<SemanticIndex>>,
+    _settings_subscription: Subscription,
     retrieve_context_in_next_inline_assist: bool,
 }
 
+/// The models of the assistant panel and of the inline assistant, with the providers serving
+/// them.
+#[derive(Clone)]
+struct ConfiguredModels {
+    model: SelectedModel,
+    completion_provider: Arc<dyn CompletionProvider>,
+    inline_model: SelectedModel,
+    inline_completion_provider: Arc<dyn CompletionProvider>,
+}
+
+impl ConfiguredModels {
+    fn selected(cx: &AppContext) -> Result<(SelectedModel, SelectedModel)> {
+        let settings = LanguageModelSettings::get_global(cx);
+        // Keep the model picked with the setting that predates the providers, unless another
+        // one is picked for the feature.
+        let legacy_model = AssistantSettings::get_global(cx)
+            .default_open_ai_model
+            .as_deref();
+        let with_legacy_model =
+            |model: SelectedModel, selection: &ModelSelection| match legacy_model
+                .filter(|_| selection.model.is_none())
+            {
+                Some(name) => model.with_name(name).unwrap_or(model),
+                None => model,
+            };
+        Ok((
+            with_legacy_model(settings.assistant_panel_model()?, &settings.assistant_panel),
+            with_legacy_model(settings.inline_assist_model()?, &settings.inline_assist),
+        ))
+    }
+
+    async fn load(
+        model: SelectedModel,
+        inline_model: SelectedModel,
+        http_client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let completion_provider = model
+            .completion_provider(http_client.clone(), executor.clone())
+            .await;
+        let inline_completion_provider = if inline_model.is_same_provider(&model) {
+            completion_provider.clone()
+        } else {
+            inline_model
+                .completion_provider(http_client, executor)
+                .await
+        };
+        Self {
+            model,
+            completion_provider,
+            inline_model,
+            inline_completion_provider,
+        }
+    }
+}
+
 impl AssistantPanel {
     const INLINE_PROMPT_HISTORY_MAX_LEN: usize = 20;
 
@@ -121,10 +186,19 @@ impl AssistantPanel {
                 .await
                 .log_err()
                 .unwrap_or_default();
-            // Defaulting currently to GPT4, allow for this to be set via config.
-            let completion_provider =
-                OpenAICompletionProvider::new("gpt-4".into(), cx.background_executor().clone())
-                    .await;
+            let http_client =
+                workspace.update(&mut cx, |workspace, _| workspace.client().http_client())?;
+            // A configuration error is shown in the panel rather than failing to load it.
+            let models = match cx.update(ConfiguredModels::selected)? {
+                Ok((model, inline_model)) => Ok(ConfiguredModels::load(
+                    model,
+                    inline_model,
+                    http_client,
+                    cx.background_executor().clone(),
+                )
+                .await),
+                Err(error) => Err(error.to_string().into()),
+            };
 
             // TODO: deserialize state.
             let workspace_handle = workspace.clone();
@@ -157,7 +231,8 @@ impl AssistantPanel {
                         toolbar
                     });
 
-                    let semantic_index = SemanticIndex::global(cx);
+                    let _settings_subscription =
+                        cx.observe_global::<SettingsStore>(Self::update_models);
 
                     let focus_handle = cx.focus_handle();
                     cx.on_focus_in(&focus_handle, Self::focus_in).detach();
@@ -173,7 +248,8 @@ impl AssistantPanel {
                         zoomed: false,
                         focus_handle,
                         toolbar,
-                        completion_provider: Arc::new(completion_provider),
+                        models,
+                        pending_models: Task::ready(()),
                         api_key_editor: None,
                         languages: workspace.app_state().languages.clone(),
                         fs: workspace.app_state().fs.clone(),
@@ -186,7 +262,7 @@ impl AssistantPanel {
                         include_conversation_in_next_inline_assist: false,
                         inline_prompt_history: Default::default(),
                         _watch_saved_conversations,
-                        semantic_index,
+                        _settings_subscription,
                         retrieve_context_in_next_inline_assist: false,
                     }
                 })
@@ -213,6 +289,51 @@ impl AssistantPanel {
         cx.notify();
     }
 
+    /// Rebuilds the providers when the language model settings change. Open conversations keep
+    /// the provider they were started with.
+    fn update_models(&mut self, cx: &mut ViewContext<Self>) {
+        let (model, inline_model) = match ConfiguredModels::selected(cx) {
+            Ok(selected) => selected,
+            Err(error) => {
+                self.models = Err(error.to_string().into());
+                self.pending_models = Task::ready(());
+                self.api_key_editor = None;
+                cx.notify();
+                return;
+            }
+        };
+        if let Ok(models) = &self.models {
+            if models.model == model && models.inline_model == inline_model {
+                self.pending_models = Task::ready(());
+                return;
+            }
+        }
+
+        let Some(http_client) = self
+            .workspace
+            .update(cx, |workspace, _| workspace.client().http_client())
+            .log_err()
+        else {
+            return;
+        };
+        let executor = cx.background_executor().clone();
+        self.pending_models = cx.spawn(|this, mut cx| async move {
+            let models = ConfiguredModels::load(model, inline_model, http_client, executor).await;
+            let Some(load_credentials) = this
+                .update(&mut cx, |this, cx| {
+                    this.models = Ok(models);
+                    this.api_key_editor = None;
+                    cx.notify();
+                    this.load_credentials(cx)
+                })
+                .ok()
+            else {
+                return;
+            };
+            load_credentials.await;
+        });
+    }
+
     pub fn inline_assist(
         workspace: &mut Workspace,
         _: &InlineAssist,
@@ -231,7 +352,7 @@ impl AssistantPanel {
         };
         let project = workspace.project().clone();
 
-        if assistant.update(cx, |assistant, _| assistant.has_credentials()) {
+        if assistant.update(cx, |assistant, _| assistant.has_inline_credentials()) {
             assistant.update(cx, |assistant, cx| {
                 assistant.new_inline_assist(&active_editor, cx, &project)
             });
@@ -241,7 +362,7 @@ impl AssistantPanel {
                 assistant
                     .update(&mut cx, |assistant, cx| assistant.load_credentials(cx))?
                     .await;
-                if assistant.update(&mut cx, |assistant, _| assistant.has_credentials())? {
+                if assistant.update(&mut cx, |assistant, _| assistant.has_inline_credentials())? {
                     assistant.update(&mut cx, |assistant, cx| {
                         assistant.new_inline_assist(&active_editor, cx, &project)
                     })?;
@@ -263,6 +384,11 @@ impl AssistantPanel {
         cx: &mut ViewContext<Self>,
         project: &Model<Project>,
     ) {
+        let Ok(models) = &self.models else {
+            return;
+        };
+        let model = models.inline_model.clone();
+        let completion_provider = models.inline_completion_provider.clone();
         let selection = editor.read(cx).selections.newest_anchor().clone();
         if selection.start.excerpt_id != selection.end.excerpt_id {
             return;
@@ -292,13 +418,16 @@ impl AssistantPanel {
         };
 
         let inline_assist_id = post_inc(&mut self.next_inline_assist_id);
-        let provider = self.completion_provider.clone();
-
         let codegen = cx.new_model(|cx| {
-            Codegen::new(editor.read(cx).buffer().clone(), codegen_kind, provider, cx)
+            Codegen::new(
+                editor.read(cx).buffer().clone(),
+                codegen_kind,
+                completion_provider.clone(),
+                cx,
+            )
         });
 
-        if let Some(semantic_index) = self.semantic_index.clone() {
+        if let Some(semantic_index) = SemanticIndex::global(cx) {
             let project = project.clone();
             cx.spawn(|_, mut cx| async move {
                 let previously_indexed = semantic_index
@@ -327,10 +456,9 @@ impl AssistantPanel {
                 self.include_conversation_in_next_inline_assist,
                 self.inline_prompt_history.clone(),
                 codegen.clone(),
-                self.workspace.clone(),
                 cx,
                 self.retrieve_context_in_next_inline_assist,
-                self.semantic_index.clone(),
+                SemanticIndex::global(cx),
                 project.clone(),
             )
         });
@@ -371,6 +499,8 @@ impl AssistantPanel {
                 inline_assistant: Some((block_id, inline_assistant.clone())),
                 codegen: codegen.clone(),
                 project: project.downgrade(),
+                model,
+                completion_provider,
                 _subscriptions: vec![
                     cx.subscribe(&inline_assistant, Self::handle_inline_assistant_event),
                     cx.subscribe(editor, {
@@ -600,6 +730,8 @@ impl AssistantPanel {
         }
 
         let codegen = pending_assist.codegen.clone();
+        let inline_model = pending_assist.model.clone();
+        let completion_provider = pending_assist.completion_provider.clone();
         let snapshot = editor.read(cx).buffer().read(cx).snapshot(cx);
         let range = codegen.read(cx).range();
         let start = snapshot.point_to_buffer_offset(range.start);
@@ -649,7 +781,7 @@ impl AssistantPanel {
                 return;
             };
 
-            let search_results = if let Some(semantic_index) = self.semantic_index.clone() {
+            let search_results = if let Some(semantic_index) = SemanticIndex::global(cx) {
                 let search_results = semantic_index.update(cx, |this, cx| {
                     this.search_project(project, user_prompt.to_string(), 10, vec![], vec![], cx)
                 });
@@ -676,10 +808,21 @@ impl AssistantPanel {
             Task::ready(Ok(Vec::new()))
         };
 
-        let mut model = AssistantSettings::get_global(cx)
-            .default_open_ai_model
-            .clone();
-        let model_name = model.full_name();
+        // Keep using the model of the included conversation when it's served by the same provider.
+        let model = conversation
+            .as_ref()
+            .map(|conversation| conversation.read(cx).model.clone())
+            .filter(|model| model.is_same_provider(&inline_model))
+            .unwrap_or(inline_model);
+        let model_name = model.name.clone();
+        let language_model = base_language_model(&model, completion_provider.as_ref());
+        report_assistant_event(
+            self.workspace.clone(),
+            None,
+            AssistantKind::Inline,
+            &model_name,
+            cx,
+        );
 
         let prompt = cx.background_executor().spawn(async move {
             let snippets = snippets.await?;
@@ -691,7 +834,7 @@ impl AssistantPanel {
                 buffer,
                 range,
                 snippets,
-                model_name,
+                language_model.unwrap_or_else(|| model.language_model().into()),
                 project_name,
             )
         });
@@ -705,7 +848,6 @@ impl AssistantPanel {
                     .messages(cx)
                     .map(|message| message.to_open_ai_message(buffer)),
             );
-            model = conversation.model.clone();
         }
 
         cx.spawn(|_, mut cx| async move {
@@ -718,7 +860,7 @@ impl AssistantPanel {
             });
 
             let request = Box::new(OpenAIRequest {
-                model: model.full_name().into(),
+                model: model_name,
                 messages,
                 stream: true,
                 stop: vec!["|END|>".to_string()],
@@ -777,10 +919,12 @@ impl AssistantPanel {
         });
     }
 
-    fn new_conversation(&mut self, cx: &mut ViewContext<Self>) -> View<ConversationEditor> {
+    fn new_conversation(&mut self, cx: &mut ViewContext<Self>) -> Option<View<ConversationEditor>> {
+        let models = self.models.as_ref().ok()?;
         let editor = cx.new_view(|cx| {
             ConversationEditor::new(
-                self.completion_provider.clone(),
+                models.model.clone(),
+                models.completion_provider.clone(),
                 self.languages.clone(),
                 self.fs.clone(),
                 self.workspace.clone(),
@@ -788,7 +932,7 @@ impl AssistantPanel {
             )
         });
         self.add_conversation(editor.clone(), cx);
-        editor
+        Some(editor)
     }
 
     fn add_conversation(&mut self, editor: View<ConversationEditor>, cx: &mut ViewContext<Self>) {
@@ -846,7 +990,10 @@ impl AssistantPanel {
                     api_key: api_key.clone(),
                 };
 
-                let completion_provider = self.completion_provider.clone();
+                let Some((_, completion_provider)) = self.credentials_model() else {
+                    return;
+                };
+                let completion_provider = completion_provider.clone();
                 cx.spawn(|this, mut cx| async move {
                     cx.update(|cx| completion_provider.save_credentials(cx, credential))?
                         .await;
@@ -865,10 +1012,22 @@ impl AssistantPanel {
     }
 
     fn reset_credentials(&mut self, _: &ResetKey, cx: &mut ViewContext<Self>) {
-        let completion_provider = self.completion_provider.clone();
+        let Ok(models) = &self.models else {
+            return;
+        };
+        // The inline assistant may use another provider, whose key has to be reset as well.
+        let mut completion_providers = vec![models.completion_provider.clone()];
+        if !Arc::ptr_eq(
+            &models.completion_provider,
+            &models.inline_completion_provider,
+        ) {
+            completion_providers.push(models.inline_completion_provider.clone());
+        }
         cx.spawn(|this, mut cx| async move {
-            cx.update(|cx| completion_provider.delete_credentials(cx))?
-                .await;
+            for completion_provider in completion_providers {
+                cx.update(|cx| completion_provider.delete_credentials(cx))?
+                    .await;
+            }
             this.update(&mut cx, |this, cx| {
                 this.api_key_editor = Some(build_api_key_editor(cx));
                 this.focus_handle.focus(cx);
@@ -1090,12 +1249,22 @@ impl AssistantPanel {
         let fs = self.fs.clone();
         let workspace = self.workspace.clone();
         let languages = self.languages.clone();
+        let (model, completion_provider) = match &self.models {
+            Ok(models) => (models.model.clone(), models.completion_provider.clone()),
+            Err(error) => return Task::ready(Err(anyhow!("{error}"))),
+        };
         cx.spawn(|this, mut cx| async move {
             let saved_conversation = fs.load(&path).await?;
             let saved_conversation = serde_json::from_str(&saved_conversation)?;
-            let conversation =
-                Conversation::deserialize(saved_conversation, path.clone(), languages, &mut cx)
-                    .await?;
+            let conversation = Conversation::deserialize(
+                saved_conversation,
+                path.clone(),
+                model,
+                completion_provider,
+                languages,
+                &mut cx,
+            )
+            .await?;
             this.update(&mut cx, |this, cx| {
                 // If, by the time we've loaded the conversation, the user has already opened
                 // the same conversation, we don't want to open it again.
@@ -1118,23 +1287,71 @@ impl AssistantPanel {
             .position(|editor| editor.read(cx).conversation.read(cx).path.as_deref() == Some(path))
     }
 
-    fn has_credentials(&mut self) -> bool {
-        self.completion_provider.has_credentials()
+    fn has_inline_credentials(&mut self) -> bool {
+        self.models.as_ref().map_or(false, |models| {
+            models.inline_completion_provider.has_credentials()
+        })
+    }
+
+    /// The model whose provider the API key editor saves a key for: the one of the panel, or the
+    /// one of the inline assistant once the panel's has a key.
+    fn credentials_model(&self) -> Option<(&SelectedModel, &Arc<dyn CompletionProvider>)> {
+        let models = self.models.as_ref().ok()?;
+        if models.completion_provider.has_credentials()
+            && !models.inline_completion_provider.has_credentials()
+        {
+            Some((&models.inline_model, &models.inline_completion_provider))
+        } else {
+            Some((&models.model, &models.completion_provider))
+        }
     }
 
     fn load_credentials(&mut self, cx: &mut ViewContext<Self>) -> Task<()> {
-        let completion_provider = self.completion_provider.clone();
-        cx.spawn(|_, mut cx| async move {
-            if let Some(retrieve_credentials) = cx
-                .update(|cx| completion_provider.retrieve_credentials(cx))
-                .log_err()
-            {
-                retrieve_credentials.await;
+        let completion_providers = self
+            .models
+            .iter()
+            .flat_map(|models| {
+                [
+                    models.completion_provider.clone(),
+                    models.inline_completion_provider.clone(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(|this, mut cx| async move {
+            for completion_provider in completion_providers {
+                if let Some(retrieve_credentials) = cx
+                    .update(|cx| completion_provider.retrieve_credentials(cx))
+                    .log_err()
+                {
+                    retrieve_credentials.await;
+                }
             }
+
+            this.update(&mut cx, |this, cx| {
+                let has_credentials = this
+                    .credentials_model()
+                    .map_or(true, |(_, completion_provider)| {
+                        completion_provider.has_credentials()
+                    });
+                if this.api_key_editor.is_none() && !has_credentials {
+                    this.api_key_editor = Some(build_api_key_editor(cx));
+                    cx.notify();
+                }
+            })
+            .ok();
         })
     }
 }
 
+/// The provider's base model if it's `model`, which saves loading its tokenizer again.
+fn base_language_model(
+    model: &SelectedModel,
+    completion_provider: &dyn CompletionProvider,
+) -> Option<Arc<dyn LanguageModel>> {
+    let base_model = completion_provider.base_model();
+    (base_model.name() == model.name).then(|| base_model.into())
+}
+
 fn build_api_key_editor(cx: &mut ViewContext<AssistantPanel>) -> View<Editor> {
     cx.new_view(|cx| {
         let mut editor = Editor::single_line(cx);
@@ -1145,13 +1362,41 @@ fn build_api_key_editor(cx: &mut ViewContext<AssistantPanel>) -> View<Editor> {
 
 impl Render for AssistantPanel {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        if let Some(api_key_editor) = self.api_key_editor.clone() {
-            const INSTRUCTIONS: [&'static str; 5] = [
-                "To use the assistant panel or inline assistant, you need to add your OpenAI API key.",
-                " - You can create an API key at: platform.openai.com/api-keys",
-                " - Having a subscription for another service like GitHub Copilot won't work.",
-                " ",
-                "Paste your OpenAI API key and press Enter to use the assistant:"
+        if let Err(error) = &self.models {
+            v_flex()
+                .p_4()
+                .size_full()
+                .track_focus(&self.focus_handle)
+                .child(
+                    Label::new("The assistant's language models are misconfigured:")
+                        .size(LabelSize::Small),
+                )
+                .child(
+                    Label::new(error.clone())
+                        .size(LabelSize::Small)
+                        .color(Color::Error),
+                )
+                .child(
+                    Label::new("Fix the \"language_models\" settings to use the assistant.")
+                        .size(LabelSize::Small),
+                )
+        } else if let Some(((model, _), api_key_editor)) =
+            self.credentials_model().zip(self.api_key_editor.clone())
+        {
+            let api_key_url = match model.provider.api_url.as_str() {
+                OPENAI_API_URL => Some("platform.openai.com/api-keys"),
+                ANTHROPIC_API_URL => Some("console.anthropic.com/settings/keys"),
+                _ => None,
+            };
+            let instructions = [
+                Some(format!(
+                    "To use the assistant panel or inline assistant, you need to add your API key for the \"{}\" provider.",
+                    model.provider_name
+                )),
+                api_key_url.map(|url| format!(" - You can create an API key at: {url}")),
+                Some(" - Having a subscription for another service like GitHub Copilot won't work.".into()),
+                Some(" ".into()),
+                Some("Paste your API key and press Enter to use the assistant:".into()),
             ];
 
             v_flex()
@@ -1160,7 +1405,10 @@ impl Render for AssistantPanel {
                 .on_action(cx.listener(AssistantPanel::save_credentials))
                 .track_focus(&self.focus_handle)
                 .children(
-                    INSTRUCTIONS.map(|instruction| Label::new(instruction).size(LabelSize::Small)),
+                    instructions
+                        .into_iter()
+                        .flatten()
+                        .map(|instruction| Label::new(instruction).size(LabelSize::Small)),
                 )
                 .child(
                     h_flex()
@@ -1393,7 +1641,10 @@ struct Conversation {
     pending_summary: Task<Option<()>>,
     completion_count: usize,
     pending_completions: Vec<PendingCompletion>,
-    model: OpenAIModel,
+    model: SelectedModel,
+    /// The tokenizer of `model`, loaded when the tokens are first counted if the provider's base
+    /// model is another one.
+    language_model: Option<Arc<dyn LanguageModel>>,
     token_count: Option<usize>,
     max_token_count: usize,
     pending_token_count: Task<Option<()>>,
@@ -1408,6 +1659,7 @@ impl EventEmitter<ConversationEvent> for Conversation {}
 impl Conversation {
     fn new(
         language_registry: Arc<LanguageRegistry>,
+        model: SelectedModel,
         cx: &mut ModelContext<Self>,
         completion_provider: Arc<dyn CompletionProvider>,
     ) -> Self {
@@ -1426,9 +1678,6 @@ impl Conversation {
             buffer
         });
 
-        let settings = AssistantSettings::get_global(cx);
-        let model = settings.default_open_ai_model.clone();
-
         let mut this = Self {
             id: Some(Uuid::new_v4().to_string()),
             message_anchors: Default::default(),
@@ -1439,9 +1688,10 @@ impl Conversation {
             completion_count: Default::default(),
             pending_completions: Default::default(),
             token_count: None,
-            max_token_count: tiktoken_rs::model::get_context_size(&model.full_name()),
+            max_token_count: 0,
             pending_token_count: Task::ready(None),
-            model: model.clone(),
+            language_model: base_language_model(&model, completion_provider.as_ref()),
+            model,
             _subscriptions: vec![cx.subscribe(&buffer, Self::handle_buffer_event)],
             pending_save: Task::ready(Ok(())),
             path: None,
@@ -1485,13 +1735,18 @@ impl Conversation {
                 .as_ref()
                 .map(|summary| summary.text.clone())
                 .unwrap_or_default(),
-            model: self.model.clone(),
+            model: self.model.name.clone(),
+            provider: Some(self.model.provider_name.clone()),
         }
     }
 
+    /// Loads a saved conversation, which continues with the given provider. Its model is kept if
+    /// that provider has it, and replaced by `model` otherwise.
     async fn deserialize(
         saved_conversation: SavedConversation,
         path: PathBuf,
+        model: SelectedModel,
+        completion_provider: Arc<dyn CompletionProvider>,
         language_registry: Arc<LanguageRegistry>,
         cx: &mut AsyncAppContext,
     ) -> Result<Model<Self>> {
@@ -1499,15 +1754,12 @@ impl Conversation {
             Some(id) => Some(id),
             None => Some(Uuid::new_v4().to_string()),
         };
-        let model = saved_conversation.model;
-        let completion_provider: Arc<dyn CompletionProvider> = Arc::new(
-            OpenAICompletionProvider::new(
-                model.full_name().into(),
-                cx.background_executor().clone(),
-            )
-            .await,
-        );
-        cx.update(|cx| completion_provider.retrieve_credentials(cx))?;
+        let saved_provider = saved_conversation.provider.as_deref().unwrap_or("openai");
+        let model = if saved_provider == model.provider_name {
+            model.with_name(&saved_conversation.model).unwrap_or(model)
+        } else {
+            model
+        };
         let markdown = language_registry.language_for_name("Markdown");
         let mut message_anchors = Vec::new();
         let mut next_message_id = MessageId(0);
@@ -1546,8 +1798,9 @@ impl Conversation {
                 completion_count: Default::default(),
                 pending_completions: Default::default(),
                 token_count: None,
-                max_token_count: tiktoken_rs::model::get_context_size(&model.full_name()),
+                max_token_count: 0,
                 pending_token_count: Task::ready(None),
+                language_model: base_language_model(&model, completion_provider.as_ref()),
                 model,
                 _subscriptions: vec![cx.subscribe(&buffer, Self::handle_buffer_event)],
                 pending_save: Task::ready(Ok(())),
@@ -1578,41 +1831,37 @@ impl Conversation {
     fn count_remaining_tokens(&mut self, cx: &mut ModelContext<Self>) {
         let messages = self
             .messages(cx)
-            .into_iter()
-            .filter_map(|message| {
-                Some(tiktoken_rs::ChatCompletionRequestMessage {
-                    role: match message.role {
-                        Role::User => "user".into(),
-                        Role::Assistant => "assistant".into(),
-                        Role::System => "system".into(),
-                    },
-                    content: Some(
-                        self.buffer
-                            .read(cx)
-                            .text_for_range(message.offset_range)
-                            .collect(),
-                    ),
-                    name: None,
-                    function_call: None,
-                })
+            .map(|message| {
+                self.buffer
+                    .read(cx)
+                    .text_for_range(message.offset_range)
+                    .collect::<String>()
             })
             .collect::<Vec<_>>();
         let model = self.model.clone();
+        let language_model = self.language_model.clone();
         self.pending_token_count = cx.spawn(|this, mut cx| {
             async move {
                 cx.background_executor()
                     .timer(Duration::from_millis(200))
                     .await;
-                let token_count = cx
+                let (language_model, token_count, max_token_count) = cx
                     .background_executor()
                     .spawn(async move {
-                        tiktoken_rs::num_tokens_from_messages(&model.full_name(), &messages)
+                        let language_model =
+                            language_model.unwrap_or_else(|| model.language_model().into());
+                        let token_count = messages
+                            .iter()
+                            .map(|message| language_model.count_tokens(message))
+                            .sum::<Result<usize>>()?;
+                        let max_token_count = language_model.capacity()?;
+                        anyhow::Ok((language_model, token_count, max_token_count))
                     })
                     .await?;
 
                 this.update(&mut cx, |this, cx| {
-                    this.max_token_count =
-                        tiktoken_rs::model::get_context_size(&this.model.full_name());
+                    this.language_model = Some(language_model);
+                    this.max_token_count = max_token_count;
                     this.token_count = Some(token_count);
                     cx.notify()
                 })?;
@@ -1626,7 +1875,8 @@ impl Conversation {
         Some(self.max_token_count as isize - self.token_count? as isize)
     }
 
-    fn set_model(&mut self, model: OpenAIModel, cx: &mut ModelContext<Self>) {
+    fn set_model(&mut self, model: SelectedModel, cx: &mut ModelContext<Self>) {
+        self.language_model = base_language_model(&model, self.completion_provider.as_ref());
         self.model = model;
         self.count_remaining_tokens(cx);
         cx.notify();
@@ -1680,7 +1930,7 @@ impl Conversation {
             }
 
             let request: Box<dyn CompletionRequest> = Box::new(OpenAIRequest {
-                model: self.model.full_name().to_string(),
+                model: self.model.name.clone(),
                 messages: self
                     .messages(cx)
                     .filter(|message| matches!(message.status, MessageStatus::Done))
@@ -1963,7 +2213,7 @@ impl Conversation {
                         .into(),
                 }));
             let request: Box<dyn CompletionRequest> = Box::new(OpenAIRequest {
-                model: self.model.full_name().to_string(),
+                model: self.model.name.clone(),
                 messages: messages.collect(),
                 stream: true,
                 stop: vec![],
@@ -2157,6 +2407,7 @@ struct ConversationEditor {
 
 impl ConversationEditor {
     fn new(
+        model: SelectedModel,
         completion_provider: Arc<dyn CompletionProvider>,
         language_registry: Arc<LanguageRegistry>,
         fs: Arc<dyn Fs>,
@@ -2164,7 +2415,7 @@ impl ConversationEditor {
         cx: &mut ViewContext<Self>,
     ) -> Self {
         let conversation =
-            cx.new_model(|cx| Conversation::new(language_registry, cx, completion_provider));
+            cx.new_model(|cx| Conversation::new(language_registry, model, cx, completion_provider));
         Self::for_conversation(conversation, fs, workspace, cx)
     }
 
@@ -2202,10 +2453,12 @@ impl ConversationEditor {
     }
 
     fn assist(&mut self, _: &Assist, cx: &mut ViewContext<Self>) {
+        let conversation = self.conversation.read(cx);
         report_assistant_event(
             self.workspace.clone(),
-            self.conversation.read(cx).id.clone(),
+            conversation.id.clone(),
             AssistantKind::Panel,
+            &conversation.model.name,
             cx,
         );
 
@@ -2487,10 +2740,13 @@ impl ConversationEditor {
 
         if let Some(text) = text {
             panel.update(cx, |panel, cx| {
-                let conversation = panel
+                let Some(conversation) = panel
                     .active_editor()
                     .cloned()
-                    .unwrap_or_else(|| panel.new_conversation(cx));
+                    .or_else(|| panel.new_conversation(cx))
+                else {
+                    return;
+                };
                 conversation.update(cx, |conversation, cx| {
                     conversation
                         .editor
@@ -2571,7 +2827,7 @@ impl ConversationEditor {
     fn render_current_model(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         Button::new(
             "current_model",
-            self.conversation.read(cx).model.short_name(),
+            self.conversation.read(cx).model.name.clone(),
         )
         .style(ButtonStyle::Filled)
         .tooltip(move |cx| Tooltip::text("Change Model", cx))
@@ -2678,7 +2934,6 @@ enum InlineAssistantEvent {
 struct InlineAssistant {
     id: usize,
     prompt_editor: View<Editor>,
-    workspace: WeakView<Workspace>,
     confirmed: bool,
     include_conversation: bool,
     measurements: Rc<Cell<BlockMeasurements>>,
@@ -2785,7 +3040,6 @@ impl InlineAssistant {
         include_conversation: bool,
         prompt_history: VecDeque<String>,
         codegen: Model<Codegen>,
-        workspace: WeakView<Workspace>,
         cx: &mut ViewContext<Self>,
         retrieve_context: bool,
         semantic_index: Option<Model<SemanticIndex>>,
@@ -2814,7 +3068,6 @@ impl InlineAssistant {
         let assistant = Self {
             id,
             prompt_editor,
-            workspace,
             confirmed: false,
             include_conversation,
             measurements,
@@ -2919,8 +3172,6 @@ impl InlineAssistant {
         if self.confirmed {
             cx.emit(InlineAssistantEvent::Dismissed);
         } else {
-            report_assistant_event(self.workspace.clone(), None, AssistantKind::Inline, cx);
-
             let prompt = self.prompt_editor.read(cx).text(cx);
             self.prompt_editor
                 .update(cx, |editor, _cx| editor.set_read_only(true));
@@ -2947,7 +3198,7 @@ impl InlineAssistant {
             .collect::<Vec<&str>>()
             .join("/");
         let is_plural = project_name.chars().filter(|letter| *letter == '/').count() > 0;
-        let prompt_text = format!("Would you like to index the '{}' project{} for context retrieval? This requires sending code to the OpenAI API", project_name,
+        let prompt_text = format!("Would you like to index the '{}' project{} for context retrieval? This requires sending code to the provider of the embedding model", project_name,
             if is_plural {
                 "s"
             } else {""});
@@ -3027,7 +3278,7 @@ impl InlineAssistant {
             SemanticIndexStatus::NotAuthenticated {} => Some(
                 div()
                     .id("error")
-                    .tooltip(|cx| Tooltip::text("Not Authenticated. Please ensure you have a valid 'OPENAI_API_KEY' in your environment variables.", cx))
+                    .tooltip(|cx| Tooltip::text("Not Authenticated. Please ensure you have a valid API key for the provider of the embedding model.", cx))
                     .child(Icon::new(IconName::XCircle))
                     .into_any_element()
             ),
@@ -3174,6 +3425,10 @@ struct PendingInlineAssist {
     codegen: Model<Codegen>,
     _subscriptions: Vec<Subscription>,
     project: WeakModel<Project>,
+    /// The model of the inline assistant when the assist started, served by the provider of
+    /// `codegen`.
+    model: SelectedModel,
+    completion_provider: Arc<dyn CompletionProvider>,
 }
 
 fn merge_ranges(ranges: &mut Vec<Range<Anchor>>, buffer: &MultiBufferSnapshot) {
@@ -3206,6 +3461,54 @@ mod tests {
     use gpui::{AppContext, TestAppContext};
     use settings::SettingsStore;
 
+    #[gpui::test]
+    fn test_default_open_ai_model_fallback(cx: &mut AppContext) {
+        let settings_store = SettingsStore::test(cx);
+        cx.set_global(settings_store);
+        init(cx);
+
+        let selected_names = |cx: &AppContext| {
+            let (model, inline_model) = ConfiguredModels::selected(cx).unwrap();
+            (model.name, inline_model.name)
+        };
+        assert_eq!(
+            selected_names(cx),
+            ("gpt-4-1106-preview".into(), "gpt-4-1106-preview".into())
+        );
+
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store
+                .set_user_settings(
+                    r#"{ "assistant": { "default_open_ai_model": "gpt-4-0613" } }"#,
+                    cx,
+                )
+                .unwrap();
+        });
+        assert_eq!(
+            selected_names(cx),
+            ("gpt-4-0613".into(), "gpt-4-0613".into())
+        );
+
+        // A model picked for a feature takes precedence.
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store
+                .set_user_settings(
+                    r#"{
+                        "assistant": { "default_open_ai_model": "gpt-4-0613" },
+                        "language_models": {
+                            "assistant_panel": { "provider": "openai", "model": "gpt-3.5-turbo-0613" }
+                        }
+                    }"#,
+                    cx,
+                )
+                .unwrap();
+        });
+        assert_eq!(
+            selected_names(cx),
+            ("gpt-3.5-turbo-0613".into(), "gpt-4-0613".into())
+        );
+    }
+
     #[gpui::test]
     fn test_inserting_and_removing_messages(cx: &mut AppContext) {
         let settings_store = SettingsStore::test(cx);
@@ -3214,7 +3517,11 @@ mod tests {
         let registry = Arc::new(LanguageRegistry::test());
 
         let completion_provider = Arc::new(FakeCompletionProvider::new());
-        let conversation = cx.new_model(|cx| Conversation::new(registry, cx, completion_provider));
+        let model = LanguageModelSettings::get_global(cx)
+            .assistant_panel_model()
+            .unwrap();
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry, model, cx, completion_provider));
         let buffer = conversation.read(cx).buffer.clone();
 
         let message_1 = conversation.read(cx).message_anchors[0].clone();
@@ -3345,7 +3652,11 @@ mod tests {
         let registry = Arc::new(LanguageRegistry::test());
         let completion_provider = Arc::new(FakeCompletionProvider::new());
 
-        let conversation = cx.new_model(|cx| Conversation::new(registry, cx, completion_provider));
+        let model = LanguageModelSettings::get_global(cx)
+            .assistant_panel_model()
+            .unwrap();
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry, model, cx, completion_provider));
         let buffer = conversation.read(cx).buffer.clone();
 
         let message_1 = conversation.read(cx).message_anchors[0].clone();
@@ -3443,7 +3754,11 @@ mod tests {
         init(cx);
         let registry = Arc::new(LanguageRegistry::test());
         let completion_provider = Arc::new(FakeCompletionProvider::new());
-        let conversation = cx.new_model(|cx| Conversation::new(registry, cx, completion_provider));
+        let model = LanguageModelSettings::get_global(cx)
+            .assistant_panel_model()
+            .unwrap();
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry, model, cx, completion_provider));
         let buffer = conversation.read(cx).buffer.clone();
 
         let message_1 = conversation.read(cx).message_anchors[0].clone();
@@ -3526,9 +3841,21 @@ mod tests {
         cx.set_global(settings_store);
         cx.update(init);
         let registry = Arc::new(LanguageRegistry::test());
-        let completion_provider = Arc::new(FakeCompletionProvider::new());
-        let conversation =
-            cx.new_model(|cx| Conversation::new(registry.clone(), cx, completion_provider));
+        let completion_provider: Arc<dyn CompletionProvider> =
+            Arc::new(FakeCompletionProvider::new());
+        let model = cx.update(|cx| {
+            LanguageModelSettings::get_global(cx)
+                .assistant_panel_model()
+                .unwrap()
+        });
+        let conversation = cx.new_model(|cx| {
+            Conversation::new(
+                registry.clone(),
+                model.clone().cycle(),
+                cx,
+                completion_provider.clone(),
+            )
+        });
         let buffer = conversation.read_with(cx, |conversation, _| conversation.buffer.clone());
         let message_0 =
             conversation.read_with(cx, |conversation, _| conversation.message_anchors[0].id);
@@ -3562,14 +3889,24 @@ mod tests {
             ]
         );
 
+        let saved_conversation =
+            conversation.read_with(cx, |conversation, cx| conversation.serialize(cx));
+        assert_eq!(saved_conversation.provider.as_deref(), Some("openai"));
+        assert_eq!(saved_conversation.model, "gpt-4-0613");
         let deserialized_conversation = Conversation::deserialize(
-            conversation.read_with(cx, |conversation, cx| conversation.serialize(cx)),
+            saved_conversation,
             Default::default(),
+            model.clone(),
+            completion_provider.clone(),
             registry.clone(),
             &mut cx.to_async(),
         )
         .await
         .unwrap();
+        assert_eq!(
+            deserialized_conversation.read_with(cx, |conversation, _| conversation.model.clone()),
+            model.cycle()
+        );
         let deserialized_buffer =
             deserialized_conversation.read_with(cx, |conversation, _| conversation.buffer.clone());
         assert_eq!(
@@ -3586,6 +3923,64 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_deserializing_with_another_provider(cx: &mut TestAppContext) {
+        let settings_store = cx.update(SettingsStore::test);
+        cx.set_global(settings_store);
+        cx.update(init);
+        let registry = Arc::new(LanguageRegistry::test());
+        let completion_provider: Arc<dyn CompletionProvider> =
+            Arc::new(FakeCompletionProvider::new());
+        let model = cx.update(|cx| {
+            LanguageModelSettings::get_global(cx)
+                .assistant_panel_model()
+                .unwrap()
+        });
+        let saved_conversation = |model: &str, provider: Option<&str>| SavedConversation {
+            id: None,
+            zed: "conversation".into(),
+            version: SavedConversation::VERSION.into(),
+            text: String::new(),
+            messages: Vec::new(),
+            message_metadata: HashMap::default(),
+            summary: String::new(),
+            model: model.into(),
+            provider: provider.map(Into::into),
+        };
+
+        // Conversations saved before providers were configurable used OpenAI.
+        let conversation = Conversation::deserialize(
+            saved_conversation("gpt-3.5-turbo-0613", None),
+            Default::default(),
+            model.clone(),
+            completion_provider.clone(),
+            registry.clone(),
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            conversation.read_with(cx, |conversation, _| conversation.model.name.clone()),
+            "gpt-3.5-turbo-0613"
+        );
+
+        // The model of another provider is replaced by the one of the panel.
+        let conversation = Conversation::deserialize(
+            saved_conversation("claude-2.1", Some("anthropic")),
+            Default::default(),
+            model.clone(),
+            completion_provider.clone(),
+            registry.clone(),
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            conversation.read_with(cx, |conversation, _| conversation.model.clone()),
+            model
+        );
+    }
+
     fn messages(
         conversation: &Model<Conversation>,
         cx: &AppContext,
@@ -3602,6 +3997,7 @@ fn report_assistant_event(
     workspace: WeakView<Workspace>,
     conversation_id: Option<String>,
     assistant_kind: AssistantKind,
+    model: &str,
     cx: &AppContext,
 ) {
     let Some(workspace) = workspace.upgrade() else {
@@ -3611,9 +4007,5 @@ fn report_assistant_event(
     let client = workspace.read(cx).project().read(cx).client();
     let telemetry = client.telemetry();
 
-    let model = AssistantSettings::get_global(cx)
-        .default_open_ai_model
-        .clone();
-
-    telemetry.report_assistant_event(conversation_id, assistant_kind, model.full_name())
+    telemetry.report_assistant_event(conversation_id, assistant_kind, model)
 }
@@ -4,42 +4,6 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::Settings;
 
-#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
-pub enum OpenAIModel {
-    #[serde(rename = "gpt-3.5-turbo-0613")]
-    ThreePointFiveTurbo,
-    #[serde(rename = "gpt-4-0613")]
-    Four,
-    #[serde(rename = "gpt-4-1106-preview")]
-    FourTurbo,
-}
-
-impl OpenAIModel {
-    pub fn full_name(&self) -> &'static str {
-        match self {
-            OpenAIModel::ThreePointFiveTurbo => "gpt-3.5-turbo-0613",
-            OpenAIModel::Four => "gpt-4-0613",
-            OpenAIModel::FourTurbo => "gpt-4-1106-preview",
-        }
-    }
-
-    pub fn short_name(&self) -> &'static str {
-        match self {
-            OpenAIModel::ThreePointFiveTurbo => "gpt-3.5-turbo",
-            OpenAIModel::Four => "gpt-4",
-            OpenAIModel::FourTurbo => "gpt-4-turbo",
-        }
-    }
-
-    pub fn cycle(&self) -> Self {
-        match self {
-            OpenAIModel::ThreePointFiveTurbo => OpenAIModel::Four,
-            OpenAIModel::Four => OpenAIModel::FourTurbo,
-            OpenAIModel::FourTurbo => OpenAIModel::ThreePointFiveTurbo,
-        }
-    }
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum AssistantDockPosition {
@@ -54,7 +18,7 @@ pub struct AssistantSettings {
     pub dock: AssistantDockPosition,
     pub default_width: Pixels,
     pub default_height: Pixels,
-    pub default_open_ai_model: OpenAIModel,
+    pub default_open_ai_model: Option<String>,
 }
 
 /// Assistant panel settings
@@ -76,10 +40,12 @@ pub struct AssistantSettingsContent {
     ///
     /// Default: 320
     pub default_height: Option<f32>,
-    /// The default OpenAI model to use when starting new conversations.
+    /// The OpenAI model to use when starting new conversations. Deprecated in favor of the
+    /// "model" of "language_models.assistant_panel" and "language_models.inline_assist", and only
+    /// used when those don't set one.
     ///
-    /// Default: gpt-4-1106-preview
-    pub default_open_ai_model: Option<OpenAIModel>,
+    /// Default: none
+    pub default_open_ai_model: Option<String>,
 }
 
 impl Settings for AssistantSettings {
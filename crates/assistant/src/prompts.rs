@@ -4,7 +4,6 @@ use ai::prompts::file_context::FileContext;
 use ai::prompts::generate::GenerateInlineContent;
 use ai::prompts::preamble::EngineerPreamble;
 use ai::prompts::repository_context::{PromptCodeSnippet, RepositoryContext};
-use ai::providers::open_ai::OpenAILanguageModel;
 use language::{BufferSnapshot, OffsetRangeExt, ToOffset};
 use std::cmp::{self, Reverse};
 use std::ops::Range;
@@ -127,11 +126,10 @@ pub fn generate_content_prompt(
     buffer: BufferSnapshot,
     range: Range<usize>,
     search_results: Vec<PromptCodeSnippet>,
-    model: &str,
+    model: Arc<dyn LanguageModel>,
     project_name: Option<String>,
 ) -> anyhow::Result<String> {
     // Using new Prompt Templates
-    let openai_model: Arc<dyn LanguageModel> = Arc::new(OpenAILanguageModel::load(model));
     let lang_name = if let Some(language_name) = language_name {
         Some(language_name.to_string())
     } else {
@@ -139,7 +137,7 @@ pub fn generate_content_prompt(
     };
 
     let args = PromptArguments {
-        model: openai_model,
+        model,
         language_name: lang_name.clone(),
         project_name,
         snippets: search_results.clone(),
@@ -102,7 +102,7 @@ pub enum Event {
     Assistant {
         conversation_id: Option<String>,
         kind: AssistantKind,
-        model: &'static str,
+        model: String,
         milliseconds_since_first_event: i64,
     },
     Cpu {
@@ -337,12 +337,12 @@ impl Telemetry {
         self: &Arc<Self>,
         conversation_id: Option<String>,
         kind: AssistantKind,
-        model: &'static str,
+        model: &str,
     ) {
         let event = Event::Assistant {
             conversation_id,
             kind,
-            model,
+            model: model.to_string(),
             milliseconds_since_first_event: self.milliseconds_since_first_event(Utc::now()),
         };
 
@@ -5,7 +5,10 @@ use crate::{
 use call::{room, ActiveCall, ParticipantLocation, Room};
 use client::{User, RECEIVE_TIMEOUT};
 use collections::{HashMap, HashSet};
-use fs::{repository::GitFileStatus, FakeFs, Fs as _, RemoveOptions};
+use fs::{
+    repository::{BlameEntry, GitFileStatus},
+    FakeFs, Fs as _, RemoveOptions,
+};
 use futures::StreamExt as _;
 use gpui::{
     px, size, AppContext, BackgroundExecutor, Model, Modifiers, MouseButton, MouseDownEvent,
@@ -2700,6 +2703,267 @@ async fn test_git_diff_base_change(
     });
 }
 
+#[gpui::test]
+async fn test_git_stage_and_unstage_hunks(
+    executor: BackgroundExecutor,
+    cx_a: &mut TestAppContext,
+    cx_b: &mut TestAppContext,
+) {
+    let mut server = TestServer::start(executor.clone()).await;
+    let client_a = server.create_client(cx_a, "user_a").await;
+    let client_b = server.create_client(cx_b, "user_b").await;
+    server
+        .create_room(&mut [(&client_a, cx_a), (&client_b, cx_b)])
+        .await;
+    let active_call_a = cx_a.read(ActiveCall::global);
+
+    client_a
+        .fs()
+        .insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "a.txt": "one\nTWO\nthree\nFOUR\n",
+                "b.txt": "new\n",
+            }),
+        )
+        .await;
+    let committed_text = "one\ntwo\nthree\nfour\n".to_string();
+    client_a.fs().set_index_for_repo(
+        Path::new("/dir/.git"),
+        &[
+            (Path::new("a.txt"), committed_text.clone()),
+            (Path::new("b.txt"), "new\n".to_string()),
+        ],
+    );
+    client_a.fs().set_head_for_repo(
+        Path::new("/dir/.git"),
+        &[(Path::new("a.txt"), committed_text.clone())],
+    );
+
+    let (project_a, worktree_id) = client_a.build_local_project("/dir", cx_a).await;
+    let project_id = active_call_a
+        .update(cx_a, |call, cx| call.share_project(project_a.clone(), cx))
+        .await
+        .unwrap();
+    let project_b = client_b.build_remote_project(project_id, cx_b).await;
+
+    let buffer_a = project_a
+        .update(cx_a, |p, cx| p.open_buffer((worktree_id, "a.txt"), cx))
+        .await
+        .unwrap();
+    let buffer_b = project_b
+        .update(cx_b, |p, cx| p.open_buffer((worktree_id, "a.txt"), cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+
+    // The guest stages the hunk on the second line.
+    let range = buffer_b.read_with(cx_b, |buffer, _| {
+        buffer.anchor_before(Point::new(1, 0))..buffer.anchor_before(Point::new(1, 0))
+    });
+    project_b
+        .update(cx_b, |p, cx| p.stage_hunks(&buffer_b, vec![range], cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+    for (buffer, cx) in [(&buffer_a, &*cx_a), (&buffer_b, &*cx_b)] {
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.diff_base(), Some("one\nTWO\nthree\nfour\n"));
+        });
+    }
+
+    // The host unstages it again.
+    let range = buffer_a.read_with(cx_a, |buffer, _| {
+        buffer.anchor_before(Point::new(1, 0))..buffer.anchor_before(Point::new(1, 0))
+    });
+    project_a
+        .update(cx_a, |p, cx| p.unstage_hunks(&buffer_a, vec![range], cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+    for (buffer, cx) in [(&buffer_a, &*cx_a), (&buffer_b, &*cx_b)] {
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.diff_base(), Some(committed_text.as_str()));
+        });
+    }
+
+    // Unstaging a file that was never committed removes it from the index.
+    let buffer_a = project_a
+        .update(cx_a, |p, cx| p.open_buffer((worktree_id, "b.txt"), cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+    buffer_a.read_with(cx_a, |buffer, _| {
+        assert_eq!(buffer.diff_base(), Some("new\n"));
+    });
+    let range = buffer_a.read_with(cx_a, |buffer, _| {
+        buffer.anchor_before(Point::new(0, 0))..buffer.anchor_before(Point::new(0, 0))
+    });
+    project_a
+        .update(cx_a, |p, cx| p.unstage_hunks(&buffer_a, vec![range], cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+    buffer_a.read_with(cx_a, |buffer, _| assert_eq!(buffer.diff_base(), None));
+    let repo = client_a.fs().open_repo(Path::new("/dir/.git")).unwrap();
+    assert_eq!(repo.lock().load_index_text(Path::new("b.txt")), None);
+}
+
+#[gpui::test]
+async fn test_git_load_head_text(
+    executor: BackgroundExecutor,
+    cx_a: &mut TestAppContext,
+    cx_b: &mut TestAppContext,
+) {
+    let mut server = TestServer::start(executor.clone()).await;
+    let client_a = server.create_client(cx_a, "user_a").await;
+    let client_b = server.create_client(cx_b, "user_b").await;
+    server
+        .create_room(&mut [(&client_a, cx_a), (&client_b, cx_b)])
+        .await;
+    let active_call_a = cx_a.read(ActiveCall::global);
+
+    client_a
+        .fs()
+        .insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "a.txt": "one\nTWO\nthree\n",
+                "b.txt": "new\n",
+            }),
+        )
+        .await;
+    client_a.fs().set_head_for_repo(
+        Path::new("/dir/.git"),
+        &[(Path::new("a.txt"), "one\ntwo\nthree\n".to_string())],
+    );
+
+    let (project_a, worktree_id) = client_a.build_local_project("/dir", cx_a).await;
+    let project_id = active_call_a
+        .update(cx_a, |call, cx| call.share_project(project_a.clone(), cx))
+        .await
+        .unwrap();
+    let project_b = client_b.build_remote_project(project_id, cx_b).await;
+
+    // The guest loads the HEAD version of the file from the host.
+    let buffer_b = project_b
+        .update(cx_b, |p, cx| p.open_buffer((worktree_id, "a.txt"), cx))
+        .await
+        .unwrap();
+    let head_text = project_b
+        .update(cx_b, |p, cx| p.load_head_text(&buffer_b, cx))
+        .await
+        .unwrap();
+    assert_eq!(head_text.as_deref(), Some("one\ntwo\nthree\n"));
+
+    // Files that were never committed have no HEAD version.
+    let buffer_b = project_b
+        .update(cx_b, |p, cx| p.open_buffer((worktree_id, "b.txt"), cx))
+        .await
+        .unwrap();
+    let head_text = project_b
+        .update(cx_b, |p, cx| p.load_head_text(&buffer_b, cx))
+        .await
+        .unwrap();
+    assert_eq!(head_text, None);
+}
+
+#[gpui::test]
+async fn test_git_blame(
+    executor: BackgroundExecutor,
+    cx_a: &mut TestAppContext,
+    cx_b: &mut TestAppContext,
+) {
+    let mut server = TestServer::start(executor.clone()).await;
+    let client_a = server.create_client(cx_a, "user_a").await;
+    let client_b = server.create_client(cx_b, "user_b").await;
+    server
+        .create_room(&mut [(&client_a, cx_a), (&client_b, cx_b)])
+        .await;
+    let active_call_a = cx_a.read(ActiveCall::global);
+
+    client_a
+        .fs()
+        .insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "a.txt": "one\ntwo\nthree\n",
+            }),
+        )
+        .await;
+    let entry = |sha: Option<&str>, rows, author: &str, summary: &str| BlameEntry {
+        sha: sha.map(Into::into),
+        rows,
+        author: author.into(),
+        author_email: format!("{author}@example.com"),
+        unix_timestamp: 1_700_000_000,
+        summary: summary.into(),
+    };
+    client_a.fs().set_blame_for_repo(
+        Path::new("/dir/.git"),
+        vec![(
+            Path::new("a.txt"),
+            vec![
+                entry(Some("1111111"), 0..2, "alice", "Add one and two"),
+                entry(None, 2..3, "", ""),
+            ],
+        )],
+    );
+
+    let (project_a, worktree_id) = client_a.build_local_project("/dir", cx_a).await;
+    let project_id = active_call_a
+        .update(cx_a, |call, cx| call.share_project(project_a.clone(), cx))
+        .await
+        .unwrap();
+    let project_b = client_b.build_remote_project(project_id, cx_b).await;
+
+    let buffer_a = project_a
+        .update(cx_a, |p, cx| p.open_buffer((worktree_id, "a.txt"), cx))
+        .await
+        .unwrap();
+    let buffer_b = project_b
+        .update(cx_b, |p, cx| p.open_buffer((worktree_id, "a.txt"), cx))
+        .await
+        .unwrap();
+    executor.run_until_parked();
+
+    let blame_a = project_a
+        .update(cx_a, |p, cx| p.blame_buffer(&buffer_a, cx))
+        .await
+        .unwrap();
+    let blame_b = project_b
+        .update(cx_b, |p, cx| p.blame_buffer(&buffer_b, cx))
+        .await
+        .unwrap();
+    for (blame, buffer, cx) in [(blame_a, &buffer_a, &*cx_a), (blame_b, &buffer_b, &*cx_b)] {
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(
+                blame
+                    .iter()
+                    .map(|hunk| (
+                        hunk.range.to_point(buffer),
+                        hunk.sha.as_deref(),
+                        hunk.author.as_str(),
+                        hunk.summary.as_str()
+                    ))
+                    .collect::<Vec<_>>(),
+                [
+                    (
+                        Point::new(0, 0)..Point::new(2, 0),
+                        Some("1111111"),
+                        "alice",
+                        "Add one and two"
+                    ),
+                    (Point::new(2, 0)..Point::new(3, 0), None, "", ""),
+                ]
+            );
+        });
+    }
+}
+
 #[gpui::test]
 async fn test_git_branch_name(
     executor: BackgroundExecutor,
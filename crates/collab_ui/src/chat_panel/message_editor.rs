@@ -10,7 +10,7 @@ use gpui::{
 };
 use language::{
     language_settings::SoftWrap, Anchor, Buffer, BufferSnapshot, CodeLabel, Completion,
-    LanguageRegistry, LanguageServerId, ToOffset,
+    CompletionSource, LanguageRegistry, ToOffset,
 };
 use lazy_static::lazy_static;
 use parking_lot::RwLock;
@@ -261,7 +261,7 @@ impl MessageEditor {
                         runs: Vec::new(),
                     },
                     documentation: None,
-                    server_id: LanguageServerId(0), // TODO: Make this optional or something?
+                    source: CompletionSource::Custom,
                     lsp_completion: Default::default(), // TODO: Make this optional or something?
                 })
                 .collect())
@@ -91,6 +91,10 @@ impl_actions!(
 gpui::actions!(
     editor,
     [
+        AcceptBoth,
+        AcceptNeither,
+        AcceptOurs,
+        AcceptTheirs,
         AddSelectionAbove,
         AddSelectionBelow,
         Backspace,
@@ -128,10 +132,14 @@ gpui::actions!(
         Fold,
         FoldSelectedRanges,
         Format,
+        GoToConflict,
         GoToDefinition,
         GoToDefinitionSplit,
         GoToDiagnostic,
         GoToHunk,
+        GoToImplementation,
+        GoToImplementationSplit,
+        GoToPrevConflict,
         GoToPrevDiagnostic,
         GoToPrevHunk,
         GoToTypeDefinition,
@@ -175,6 +183,7 @@ gpui::actions!(
         RestartLanguageServer,
         RevealInFinder,
         ReverseLines,
+        RevertHunk,
         ScrollCursorBottom,
         ScrollCursorCenter,
         ScrollCursorTop,
@@ -197,17 +206,24 @@ gpui::actions!(
         SelectUp,
         ShowCharacterPalette,
         ShowCompletions,
+        ShowSignatureHelp,
+        ShowIncomingCalls,
+        ShowOutgoingCalls,
         ShuffleLines,
         SortLinesCaseInsensitive,
         SortLinesCaseSensitive,
         SplitSelectionIntoLines,
+        StageHunk,
         Tab,
         TabPrev,
+        ToggleCallHierarchyItem,
+        ToggleGitBlame,
         ToggleInlayHints,
         ToggleSoftWrap,
         Transpose,
         Undo,
         UndoSelection,
         UnfoldLines,
+        UnstageHunk,
     ]
 );
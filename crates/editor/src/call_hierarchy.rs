@@ -0,0 +1,476 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Editor, ShowIncomingCalls, ShowOutgoingCalls, ToggleCallHierarchyItem,
+};
+use anyhow::Result;
+use collections::{HashMap, HashSet};
+use gpui::{Context, ModelContext, Task, ViewContext, VisualContext};
+use language::{Bias, OffsetRangeExt, Point};
+use multi_buffer::{ExcerptId, ExcerptRange, MultiBuffer};
+use project::{CallHierarchyCall, CallHierarchyItem, Location, Project};
+use std::sync::Arc;
+use ui::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallHierarchyDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Tree of calls displayed by a multibuffer editor: every node's call sites are shown as excerpts,
+/// and the excerpts of a node's children are inserted right after the excerpts of their parent.
+pub(crate) struct CallHierarchy {
+    direction: CallHierarchyDirection,
+    nodes: HashMap<usize, CallHierarchyNode>,
+    next_node_id: usize,
+    pending_expansion: Option<Task<Result<()>>>,
+}
+
+struct CallHierarchyNode {
+    item: CallHierarchyItem,
+    parent: Option<usize>,
+    depth: usize,
+    excerpts: Vec<ExcerptId>,
+    block: BlockId,
+    expanded: bool,
+}
+
+impl CallHierarchy {
+    fn node_for_excerpt(&self, excerpt_id: ExcerptId) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|(_, node)| node.excerpts.contains(&excerpt_id))
+            .map(|(node_id, _)| *node_id)
+    }
+
+    fn descendants(&self, node_id: usize) -> Vec<usize> {
+        let mut descendants = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(parent_id) = stack.pop() {
+            for (child_id, child) in &self.nodes {
+                if child.parent == Some(parent_id) {
+                    descendants.push(*child_id);
+                    stack.push(*child_id);
+                }
+            }
+        }
+        descendants
+    }
+}
+
+pub fn show_incoming_calls(
+    editor: &mut Editor,
+    _: &ShowIncomingCalls,
+    cx: &mut ViewContext<Editor>,
+) {
+    show_call_hierarchy(editor, CallHierarchyDirection::Incoming, cx);
+}
+
+pub fn show_outgoing_calls(
+    editor: &mut Editor,
+    _: &ShowOutgoingCalls,
+    cx: &mut ViewContext<Editor>,
+) {
+    show_call_hierarchy(editor, CallHierarchyDirection::Outgoing, cx);
+}
+
+fn show_call_hierarchy(
+    editor: &mut Editor,
+    direction: CallHierarchyDirection,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(project) = editor.project.clone() else {
+        return;
+    };
+    let Some(workspace) = editor.workspace() else {
+        return;
+    };
+    let head = editor.selections.newest::<usize>(cx).head();
+    let Some((buffer, head)) = editor.buffer.read(cx).text_anchor_for_position(head, cx) else {
+        return;
+    };
+    let replica_id = editor.replica_id(cx);
+    let prepare_task = project.update(cx, |project, cx| {
+        project.prepare_call_hierarchy(&buffer, head, cx)
+    });
+    cx.spawn(|_, mut cx| async move {
+        let Some(item) = prepare_task.await?.into_iter().next() else {
+            return Ok(());
+        };
+        let calls = project
+            .update(&mut cx, |project, cx| {
+                calls_for_item(project, item.clone(), direction, cx)
+            })?
+            .await?;
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        workspace.update(&mut cx, |workspace, cx| {
+            let title = match direction {
+                CallHierarchyDirection::Incoming => format!("Incoming calls of `{}`", item.name),
+                CallHierarchyDirection::Outgoing => format!("Outgoing calls of `{}`", item.name),
+            };
+            let capability = project.read(cx).capability();
+            let multibuffer =
+                cx.new_model(|_| MultiBuffer::new(replica_id, capability).with_title(title));
+            let editor = cx.new_view(|cx| {
+                let mut editor = Editor::for_multibuffer(multibuffer, Some(project.clone()), cx);
+                editor.call_hierarchy = Some(CallHierarchy {
+                    direction,
+                    nodes: HashMap::default(),
+                    next_node_id: 0,
+                    pending_expansion: None,
+                });
+                editor.insert_call_hierarchy_nodes(None, calls, cx);
+                editor
+            });
+            workspace.add_item(Box::new(editor), cx);
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+pub fn toggle_call_hierarchy_item(
+    editor: &mut Editor,
+    _: &ToggleCallHierarchyItem,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(hierarchy) = editor.call_hierarchy.as_ref() else {
+        cx.propagate();
+        return;
+    };
+    let head = editor.selections.newest::<usize>(cx).head();
+    let Some((excerpt_id, _, _)) = editor.buffer.read(cx).excerpt_containing(head, cx) else {
+        return;
+    };
+    if let Some(node_id) = hierarchy.node_for_excerpt(excerpt_id) {
+        editor.toggle_call_hierarchy_node(node_id, cx);
+    }
+}
+
+fn calls_for_item(
+    project: &mut Project,
+    item: CallHierarchyItem,
+    direction: CallHierarchyDirection,
+    cx: &mut ModelContext<Project>,
+) -> Task<Result<Vec<CallHierarchyCall>>> {
+    match direction {
+        CallHierarchyDirection::Incoming => project.incoming_calls(item, cx),
+        CallHierarchyDirection::Outgoing => project.outgoing_calls(item, cx),
+    }
+}
+
+impl Editor {
+    fn toggle_call_hierarchy_node(&mut self, node_id: usize, cx: &mut ViewContext<Self>) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let Some(hierarchy) = self.call_hierarchy.as_mut() else {
+            return;
+        };
+        let Some(node) = hierarchy.nodes.get(&node_id) else {
+            return;
+        };
+        if node.expanded {
+            self.collapse_call_hierarchy_node(node_id, cx);
+            return;
+        }
+
+        let direction = hierarchy.direction;
+        let item = node.item.clone();
+        let calls_task = project.update(cx, |project, cx| {
+            calls_for_item(project, item, direction, cx)
+        });
+        hierarchy.pending_expansion = Some(cx.spawn(|editor, mut cx| async move {
+            let calls = calls_task.await?;
+            editor.update(&mut cx, |editor, cx| {
+                if let Some(node) = editor
+                    .call_hierarchy
+                    .as_mut()
+                    .and_then(|hierarchy| hierarchy.nodes.get_mut(&node_id))
+                {
+                    node.expanded = true;
+                    editor.insert_call_hierarchy_nodes(Some(node_id), calls, cx);
+                }
+            })
+        }));
+    }
+
+    fn insert_call_hierarchy_nodes(
+        &mut self,
+        parent: Option<usize>,
+        calls: Vec<CallHierarchyCall>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(hierarchy) = self.call_hierarchy.as_ref() else {
+            return;
+        };
+        let depth = parent.map_or(0, |parent| hierarchy.nodes[&parent].depth + 1);
+        let mut prev_excerpt_id = parent
+            .and_then(|parent| hierarchy.nodes[&parent].excerpts.last().copied())
+            .unwrap_or_else(ExcerptId::max);
+
+        let mut new_nodes = Vec::new();
+        self.buffer.update(cx, |multibuffer, cx| {
+            for call in calls {
+                // Servers may omit call sites for outgoing calls, fall back to the callee itself.
+                let call_sites = if call.call_sites.is_empty() {
+                    vec![Location {
+                        buffer: call.item.buffer.clone(),
+                        range: call.item.selection_range.clone(),
+                    }]
+                } else {
+                    call.call_sites
+                };
+
+                let mut excerpts = Vec::new();
+                for call_site in call_sites {
+                    let (context, primary) = {
+                        let buffer = call_site.buffer.read(cx);
+                        let range = call_site.range.to_point(buffer);
+                        let context_start = Point::new(range.start.row.saturating_sub(1), 0);
+                        let context_end =
+                            buffer.clip_point(Point::new(range.end.row + 1, u32::MAX), Bias::Left);
+                        (context_start..context_end, range)
+                    };
+                    let excerpt_ids = multibuffer.insert_excerpts_after(
+                        prev_excerpt_id,
+                        call_site.buffer,
+                        [ExcerptRange {
+                            context,
+                            primary: Some(primary),
+                        }],
+                        cx,
+                    );
+                    prev_excerpt_id = excerpt_ids.last().copied().unwrap_or(prev_excerpt_id);
+                    excerpts.extend(excerpt_ids);
+                }
+                new_nodes.push((call.item, excerpts));
+            }
+        });
+        // Nodes whose call sites couldn't be inserted have no excerpt to show their header above.
+        new_nodes.retain(|(_, excerpts)| !excerpts.is_empty());
+
+        let Some(hierarchy) = self.call_hierarchy.as_mut() else {
+            return;
+        };
+        let first_node_id = hierarchy.next_node_id;
+        hierarchy.next_node_id += new_nodes.len();
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let blocks = new_nodes
+            .iter()
+            .enumerate()
+            .map(|(ix, (item, excerpts))| BlockProperties {
+                position: snapshot.anchor_in_excerpt(excerpts[0], language::Anchor::MIN),
+                height: 1,
+                style: BlockStyle::Sticky,
+                render: render_call_hierarchy_item(first_node_id + ix, item, depth),
+                disposition: BlockDisposition::Above,
+            })
+            .collect::<Vec<_>>();
+        let block_ids = self.insert_blocks(blocks, None, cx);
+
+        let Some(hierarchy) = self.call_hierarchy.as_mut() else {
+            return;
+        };
+        for (node_id, ((item, excerpts), block)) in
+            (first_node_id..).zip(new_nodes.into_iter().zip(block_ids))
+        {
+            hierarchy.nodes.insert(
+                node_id,
+                CallHierarchyNode {
+                    item,
+                    parent,
+                    depth,
+                    excerpts,
+                    block,
+                    expanded: false,
+                },
+            );
+        }
+        cx.notify();
+    }
+
+    fn collapse_call_hierarchy_node(&mut self, node_id: usize, cx: &mut ViewContext<Self>) {
+        let Some(hierarchy) = self.call_hierarchy.as_mut() else {
+            return;
+        };
+        let mut excerpts_to_remove = Vec::new();
+        let mut blocks_to_remove = HashSet::default();
+        for descendant_id in hierarchy.descendants(node_id) {
+            if let Some(descendant) = hierarchy.nodes.remove(&descendant_id) {
+                excerpts_to_remove.extend(descendant.excerpts);
+                blocks_to_remove.insert(descendant.block);
+            }
+        }
+        if let Some(node) = hierarchy.nodes.get_mut(&node_id) {
+            node.expanded = false;
+        }
+
+        self.remove_blocks(blocks_to_remove, None, cx);
+        self.buffer.update(cx, |multibuffer, cx| {
+            multibuffer.remove_excerpts(excerpts_to_remove, cx)
+        });
+    }
+}
+
+fn render_call_hierarchy_item(
+    node_id: usize,
+    item: &CallHierarchyItem,
+    depth: usize,
+) -> RenderBlock {
+    let name: SharedString = item.name.clone().into();
+    let detail: Option<SharedString> = item.detail.clone().map(Into::into);
+    Arc::new(move |cx: &mut BlockContext| {
+        let expanded = cx
+            .view
+            .read(cx)
+            .call_hierarchy
+            .as_ref()
+            .and_then(|hierarchy| hierarchy.nodes.get(&node_id))
+            .map_or(false, |node| node.expanded);
+        let editor = cx.view.clone();
+        h_flex()
+            .id(cx.block_id)
+            .pl(cx.gutter_width + cx.em_width * (2 * depth) as f32)
+            .gap_2()
+            .cursor_pointer()
+            .on_click(move |_, cx| {
+                editor.update(cx, |editor, cx| {
+                    editor.toggle_call_hierarchy_node(node_id, cx)
+                })
+            })
+            .child(
+                Icon::new(if expanded {
+                    IconName::ChevronDown
+                } else {
+                    IconName::ChevronRight
+                })
+                .size(IconSize::Small)
+                .color(Color::Muted),
+            )
+            .child(Label::new(name.clone()))
+            .when_some(detail.clone(), |this, detail| {
+                this.child(Label::new(detail).color(Color::Muted))
+            })
+            .into_any_element()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_call_hierarchy_view(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                call_hierarchy_provider: Some(lsp::CallHierarchyServerCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn ˇa() {}
+
+            fn b() {
+                a();
+            }
+
+            fn main() {
+                b();
+            }
+        "});
+
+        fn item(name: &str, row: u32, uri: lsp::Url) -> lsp::CallHierarchyItem {
+            lsp::CallHierarchyItem {
+                name: name.to_string(),
+                kind: lsp::SymbolKind::FUNCTION,
+                tags: None,
+                detail: None,
+                uri,
+                range: lsp::Range::new(lsp::Position::new(row, 0), lsp::Position::new(row, 0)),
+                selection_range: lsp::Range::new(
+                    lsp::Position::new(row, 3),
+                    lsp::Position::new(row, 3 + name.len() as u32),
+                ),
+                data: None,
+            }
+        }
+        cx.handle_request::<lsp::request::CallHierarchyPrepare, _, _>(|url, _, _| async move {
+            Ok(Some(vec![item("a", 0, url)]))
+        });
+        cx.handle_request::<lsp::request::CallHierarchyIncomingCalls, _, _>(
+            |url, params, _| async move {
+                let call = |name: &str, row: u32, call_row: u32| lsp::CallHierarchyIncomingCall {
+                    from: item(name, row, url.clone()),
+                    from_ranges: vec![lsp::Range::new(
+                        lsp::Position::new(call_row, 4),
+                        lsp::Position::new(call_row, 5),
+                    )],
+                };
+                Ok(Some(match params.item.name.as_str() {
+                    "a" => vec![call("b", 2, 3)],
+                    "b" => vec![call("main", 6, 7)],
+                    _ => Vec::new(),
+                }))
+            },
+        );
+
+        cx.update_editor(|editor, cx| show_incoming_calls(editor, &ShowIncomingCalls, cx));
+        cx.run_until_parked();
+        let hierarchy_editor = cx
+            .update_workspace(|workspace, cx| workspace.active_item_as::<Editor>(cx))
+            .unwrap();
+        hierarchy_editor.update(&mut cx.cx.cx, |editor, cx| {
+            assert_eq!(
+                editor.text(cx),
+                indoc! {"
+                    fn b() {
+                        a();
+                    }"}
+            );
+            assert_eq!(editor.call_hierarchy.as_ref().unwrap().nodes.len(), 1);
+
+            // Expanding a caller inserts its own callers after it.
+            toggle_call_hierarchy_item(editor, &ToggleCallHierarchyItem, cx);
+        });
+        cx.run_until_parked();
+        hierarchy_editor.update(&mut cx.cx.cx, |editor, cx| {
+            assert_eq!(
+                editor.text(cx),
+                indoc! {"
+                    fn b() {
+                        a();
+                    }
+                    fn main() {
+                        b();
+                    }"}
+            );
+            assert_eq!(editor.call_hierarchy.as_ref().unwrap().nodes.len(), 2);
+
+            // Collapsing it removes them again.
+            toggle_call_hierarchy_item(editor, &ToggleCallHierarchyItem, cx);
+        });
+        cx.run_until_parked();
+        hierarchy_editor.update(&mut cx.cx.cx, |editor, cx| {
+            assert_eq!(
+                editor.text(cx),
+                indoc! {"
+                    fn b() {
+                        a();
+                    }"}
+            );
+            assert_eq!(editor.call_hierarchy.as_ref().unwrap().nodes.len(), 1);
+        });
+    }
+}
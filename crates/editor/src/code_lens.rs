@@ -0,0 +1,500 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Editor, EditorMode, ShowCompletions, ShowSignatureHelp,
+};
+use collections::{BTreeMap, HashMap, HashSet};
+use gpui::{Model, Task, ViewContext};
+use language::{
+    language_settings::language_settings, Bias, Buffer, OffsetRangeExt, Point, ToOffset, ToPoint,
+};
+use project::CodeLens;
+use std::{sync::Arc, time::Duration};
+use ui::prelude::*;
+use util::ResultExt;
+
+/// How long to wait after an edit before fetching the code lenses again.
+const CODE_LENS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Commands of the editor that servers expect clients to implement, as in VS Code.
+const TRIGGER_SUGGEST_COMMAND: &str = "editor.action.triggerSuggest";
+const TRIGGER_PARAMETER_HINTS_COMMAND: &str = "editor.action.triggerParameterHints";
+
+#[derive(Default)]
+pub(crate) struct CodeLensState {
+    buffers: HashMap<u64, BufferCodeLens>,
+    blocks: HashMap<u64, Vec<BlockId>>,
+    refresh_task: Option<Task<Option<()>>>,
+}
+
+struct BufferCodeLens {
+    version: clock::Global,
+    lenses: Vec<CodeLens>,
+    /// The indices of the lenses that were sent to the server to be resolved.
+    resolve_requested: HashSet<usize>,
+}
+
+impl Editor {
+    /// Fetches the code lenses of the buffers that changed since their lenses were last fetched,
+    /// or of all buffers when `invalidate` is set, and shows them above the lines they apply to.
+    pub(crate) fn refresh_code_lens(&mut self, invalidate: bool, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+
+        let mut buffers_to_fetch = Vec::new();
+        let mut buffers_to_update = Vec::new();
+        let mut buffers_to_clear = self
+            .code_lens
+            .buffers
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        for buffer in self.buffer.read(cx).all_buffers() {
+            let buffer_ref = buffer.read(cx);
+            let buffer_id = buffer_ref.remote_id();
+            if !code_lens_enabled(buffer_ref, cx) {
+                continue;
+            }
+            buffers_to_clear.remove(&buffer_id);
+            let is_up_to_date = !invalidate
+                && self
+                    .code_lens
+                    .buffers
+                    .get(&buffer_id)
+                    .map_or(false, |fetched| {
+                        !buffer_ref.version().changed_since(&fetched.version)
+                    });
+            if is_up_to_date {
+                buffers_to_update.push(buffer_id);
+            } else {
+                buffers_to_fetch.push(buffer);
+            }
+        }
+        for buffer_id in buffers_to_clear {
+            self.code_lens.buffers.remove(&buffer_id);
+            self.update_code_lens_blocks(buffer_id, cx);
+        }
+        // The excerpts of the up-to-date buffers may have changed, so their lenses are mapped
+        // into the multibuffer again.
+        for buffer_id in buffers_to_update {
+            self.update_code_lens_blocks(buffer_id, cx);
+        }
+        if buffers_to_fetch.is_empty() {
+            return;
+        }
+
+        self.code_lens.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(CODE_LENS_DEBOUNCE_TIMEOUT)
+                .await;
+            let requests = project
+                .update(&mut cx, |project, cx| {
+                    buffers_to_fetch
+                        .iter()
+                        .map(|buffer| {
+                            let buffer_id = buffer.read(cx).remote_id();
+                            let version = buffer.read(cx).version();
+                            (buffer_id, version, project.code_lens(buffer, cx))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+            for (buffer_id, version, request) in requests {
+                let Some(lenses) = request.await.log_err() else {
+                    continue;
+                };
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        editor.code_lens.buffers.insert(
+                            buffer_id,
+                            BufferCodeLens {
+                                version,
+                                lenses,
+                                resolve_requested: HashSet::default(),
+                            },
+                        );
+                        editor.update_code_lens_blocks(buffer_id, cx);
+                        editor.resolve_visible_code_lens(cx);
+                    })
+                    .ok()?;
+            }
+            Some(())
+        }));
+    }
+
+    fn update_code_lens_blocks(&mut self, buffer_id: u64, cx: &mut ViewContext<Self>) {
+        if let Some(blocks) = self.code_lens.blocks.remove(&buffer_id) {
+            self.remove_blocks(blocks.into_iter().collect(), None, cx);
+        }
+
+        let multibuffer = self.buffer.read(cx);
+        let (Some(fetched), Some(buffer)) = (
+            self.code_lens.buffers.get(&buffer_id),
+            multibuffer.buffer(buffer_id),
+        ) else {
+            cx.notify();
+            return;
+        };
+
+        let snapshot = multibuffer.snapshot(cx);
+        let buffer_snapshot = buffer.read(cx).snapshot();
+        let mut blocks = Vec::new();
+        for (excerpt_id, excerpt_range) in multibuffer.excerpts_for_buffer(&buffer, cx) {
+            let context = excerpt_range.context.to_point(&buffer_snapshot);
+            // All the lenses of a line are shown in a single block above it.
+            let mut lenses_by_row = BTreeMap::<u32, Vec<CodeLens>>::new();
+            // Lenses without a command are shown once they've been resolved.
+            for lens in &fetched.lenses {
+                let start = lens.range.start.to_point(&buffer_snapshot);
+                if lens.lsp_lens.command.is_some() && context.contains(&start) {
+                    lenses_by_row
+                        .entry(start.row)
+                        .or_default()
+                        .push(lens.clone());
+                }
+            }
+            for (row, lenses) in lenses_by_row {
+                // Align the lenses with the line's indentation.
+                let indent = buffer_snapshot.indent_size_for_line(row).len;
+                let position = buffer_snapshot.anchor_before(Point::new(row, indent));
+                blocks.push(BlockProperties {
+                    position: snapshot.anchor_in_excerpt(excerpt_id, position),
+                    height: 1,
+                    style: BlockStyle::Flex,
+                    render: render_code_lenses(buffer.clone(), lenses),
+                    disposition: BlockDisposition::Above,
+                });
+            }
+        }
+
+        if !blocks.is_empty() {
+            let block_ids = self.insert_blocks(blocks, None, cx);
+            self.code_lens.blocks.insert(buffer_id, block_ids);
+        }
+        cx.notify();
+    }
+
+    /// Resolves the lenses without a command in the visible part of the editor, if their server
+    /// resolves lenses.
+    pub(crate) fn resolve_visible_code_lens(&mut self, cx: &mut ViewContext<Self>) {
+        if self.code_lens.buffers.is_empty() {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+
+        let multibuffer = self.buffer.read(cx);
+        let snapshot = multibuffer.snapshot(cx);
+        let visible_start = self.scroll_manager.anchor().anchor.to_point(&snapshot);
+        let visible_end = snapshot.clip_point(
+            visible_start + Point::new(self.visible_line_count().unwrap_or(0.).ceil() as u32, 0),
+            Bias::Left,
+        );
+        let mut lenses_to_resolve = Vec::new();
+        for (buffer, visible_range, _) in
+            multibuffer.range_to_buffer_ranges(visible_start..visible_end, cx)
+        {
+            let buffer_snapshot = buffer.read(cx);
+            let Some(fetched) = self.code_lens.buffers.get_mut(&buffer_snapshot.remote_id()) else {
+                continue;
+            };
+            for (ix, lens) in fetched.lenses.iter().enumerate() {
+                let start = lens.range.start.to_offset(buffer_snapshot);
+                if lens.lsp_lens.command.is_none()
+                    && visible_range.start <= start
+                    && start <= visible_range.end
+                    && fetched.resolve_requested.insert(ix)
+                {
+                    lenses_to_resolve.push((buffer.clone(), ix, lens.clone()));
+                }
+            }
+        }
+
+        for (buffer, ix, lens) in lenses_to_resolve {
+            let buffer_id = buffer.read(cx).remote_id();
+            let unresolved_lens = lens.lsp_lens.clone();
+            let resolve_code_lens = project.update(cx, |project, cx| {
+                project.resolve_code_lens(buffer, lens, cx)
+            });
+            cx.spawn(|editor, mut cx| async move {
+                let resolved_lens = resolve_code_lens.await.log_err()?;
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        // The lenses may have been fetched again in the meantime.
+                        let lens = editor
+                            .code_lens
+                            .buffers
+                            .get_mut(&buffer_id)?
+                            .lenses
+                            .get_mut(ix)
+                            .filter(|lens| lens.lsp_lens == unresolved_lens)?;
+                        *lens = resolved_lens;
+                        editor.update_code_lens_blocks(buffer_id, cx);
+                        Some(())
+                    })
+                    .ok()?
+            })
+            .detach();
+        }
+    }
+
+    fn execute_code_lens(
+        &mut self,
+        buffer: Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let (Some(project), Some(workspace)) = (self.project.clone(), self.workspace()) else {
+            return;
+        };
+        let Some(command) = &lens.lsp_lens.command else {
+            return;
+        };
+        match command.command.as_str() {
+            TRIGGER_SUGGEST_COMMAND => {
+                self.show_completions(&ShowCompletions, cx);
+                return;
+            }
+            TRIGGER_PARAMETER_HINTS_COMMAND => {
+                self.show_signature_help(&ShowSignatureHelp, cx);
+                return;
+            }
+            _ if !lens.executable => return,
+            _ => {}
+        }
+        let title = lens
+            .lsp_lens
+            .command
+            .as_ref()
+            .map(|command| command.title.clone())
+            .unwrap_or_default();
+        let execute_code_lens = project.update(cx, |project, cx| {
+            project.execute_code_lens(buffer, lens, cx)
+        });
+        let workspace = workspace.downgrade();
+        cx.spawn(|editor, cx| async move {
+            let project_transaction = execute_code_lens.await?;
+            Self::open_project_transaction(&editor, workspace, project_transaction, title, cx).await
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+fn code_lens_enabled(buffer: &Buffer, cx: &gpui::AppContext) -> bool {
+    language_settings(buffer.language(), buffer.file(), cx).code_lens
+}
+
+/// Whether clicking the lens runs its command, either on the language server or in the editor.
+fn is_executable(lens: &CodeLens) -> bool {
+    lens.executable
+        || lens.lsp_lens.command.as_ref().map_or(false, |command| {
+            matches!(
+                command.command.as_str(),
+                TRIGGER_SUGGEST_COMMAND | TRIGGER_PARAMETER_HINTS_COMMAND
+            )
+        })
+}
+
+fn render_code_lenses(buffer: Model<Buffer>, lenses: Vec<CodeLens>) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let editor = cx.view.clone();
+        h_flex()
+            .id(cx.block_id)
+            .pl(cx.anchor_x)
+            .gap_1()
+            .children(lenses.iter().enumerate().map(|(ix, lens)| {
+                let title = lens
+                    .lsp_lens
+                    .command
+                    .as_ref()
+                    .map(|command| command.title.clone())
+                    .unwrap_or_default();
+                let editor = editor.clone();
+                let buffer = buffer.clone();
+                let lens = lens.clone();
+                h_flex()
+                    .gap_1()
+                    .when(ix > 0, |this| {
+                        this.child(Label::new("|").size(LabelSize::Small).color(Color::Muted))
+                    })
+                    .map(|this| {
+                        // Lenses whose command can't be run are only informative.
+                        if is_executable(&lens) {
+                            this.child(
+                                Button::new(("code-lens", ix), title)
+                                    .label_size(LabelSize::Small)
+                                    .color(Color::Muted)
+                                    .on_click(move |_, cx| {
+                                        editor.update(cx, |editor, cx| {
+                                            editor.execute_code_lens(
+                                                buffer.clone(),
+                                                lens.clone(),
+                                                cx,
+                                            )
+                                        })
+                                    }),
+                            )
+                        } else {
+                            this.child(Label::new(title).size(LabelSize::Small).color(Color::Muted))
+                        }
+                    })
+            }))
+            .into_any_element()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use smol::stream::StreamExt;
+
+    #[gpui::test]
+    async fn test_executing_code_lens(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_lens_provider: Some(lsp::CodeLensOptions {
+                    resolve_provider: None,
+                }),
+                execute_command_provider: Some(lsp::ExecuteCommandOptions {
+                    commands: vec!["run".to_string()],
+                    ..Default::default()
+                }),
+                completion_provider: Some(lsp::CompletionOptions::default()),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state("fn main() {ˇ}");
+
+        let mut commands =
+            cx.handle_request::<lsp::request::ExecuteCommand, _, _>(|_, params, _| async move {
+                assert_eq!(params.command, "run");
+                Ok(None)
+            });
+        let mut completions =
+            cx.handle_request::<lsp::request::Completion, _, _>(|_, _, _| async move {
+                Ok(Some(lsp::CompletionResponse::Array(Vec::new())))
+            });
+
+        let server_id = cx.lsp.server.server_id();
+        let execute = |command: &str, cx: &mut EditorLspTestContext| {
+            let lsp_lens = lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(0, 7)),
+                command: Some(lsp::Command {
+                    title: command.to_string(),
+                    command: command.to_string(),
+                    arguments: None,
+                }),
+                data: None,
+            };
+            cx.update_editor(|editor, cx| {
+                let buffer = editor.buffer().read(cx).as_singleton().unwrap();
+                let range = buffer.read(cx).anchor_after(Point::new(0, 3))
+                    ..buffer.read(cx).anchor_before(Point::new(0, 7));
+                let lens = CodeLens {
+                    server_id,
+                    range,
+                    lsp_lens,
+                    executable: command == "run",
+                };
+                editor.execute_code_lens(buffer, lens, cx);
+            });
+            cx.run_until_parked();
+        };
+
+        // Commands advertised by the server are executed by it.
+        execute("run", &mut cx);
+        commands.next().await.unwrap();
+
+        // Commands of the editor are executed by it.
+        execute("editor.action.triggerSuggest", &mut cx);
+        completions.next().await.unwrap();
+        assert!(commands.try_next().is_err());
+
+        // Other commands are ignored.
+        execute("rust-analyzer.runSingle", &mut cx);
+        assert!(commands.try_next().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_resolving_visible_code_lens(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| settings.defaults.code_lens = Some(true));
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_lens_provider: Some(lsp::CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(&format!("ˇfn a() {{}}{}fn b() {{}}", "\n".repeat(50)));
+
+        let lens = |row: u32| lsp::CodeLens {
+            range: lsp::Range::new(lsp::Position::new(row, 3), lsp::Position::new(row, 4)),
+            command: None,
+            data: Some(row.into()),
+        };
+        cx.handle_request::<lsp::request::CodeLensRequest, _, _>(move |_, _, _| async move {
+            Ok(Some(vec![lens(0), lens(50)]))
+        });
+        let mut resolves =
+            cx.handle_request::<lsp::request::CodeLensResolve, _, _>(|_, lens, _| async move {
+                let row = lens.data.as_ref().unwrap().as_u64().unwrap();
+                Ok(lsp::CodeLens {
+                    command: Some(lsp::Command {
+                        title: format!("{row} references"),
+                        command: "references".to_string(),
+                        arguments: None,
+                    }),
+                    ..lens
+                })
+            });
+
+        let titles = |cx: &mut EditorLspTestContext| {
+            cx.editor(|editor, _| {
+                editor
+                    .code_lens
+                    .buffers
+                    .values()
+                    .flat_map(|fetched| &fetched.lenses)
+                    .map(|lens| Some(lens.lsp_lens.command.as_ref()?.title.clone()))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // Only the lenses in view are resolved.
+        cx.update_editor(|editor, cx| {
+            editor.set_visible_line_count(10., cx);
+            editor.refresh_code_lens(true, cx);
+        });
+        cx.executor().advance_clock(CODE_LENS_DEBOUNCE_TIMEOUT);
+        resolves.next().await.unwrap();
+        cx.run_until_parked();
+        assert_eq!(titles(&mut cx), [Some("0 references".to_string()), None]);
+
+        cx.update_editor(|editor, cx| editor.set_scroll_position(gpui::Point::new(0., 45.), cx));
+        resolves.next().await.unwrap();
+        cx.run_until_parked();
+        assert_eq!(
+            titles(&mut cx),
+            [
+                Some("0 references".to_string()),
+                Some("50 references".to_string())
+            ]
+        );
+        assert!(resolves.try_next().is_err());
+    }
+}
@@ -66,6 +66,56 @@ pub trait ToDisplayPoint {
 
 type TextHighlights = TreeMap<Option<TypeId>, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>;
 type InlayHighlights = BTreeMap<TypeId, HashMap<InlayId, (HighlightStyle, InlayHighlight)>>;
+type SemanticHighlights = TreeMap<u64, Arc<SemanticTokenHighlights>>;
+type FoldingRanges = TreeMap<u64, Arc<Vec<Range<Anchor>>>>;
+
+/// The semantic token highlights of a buffer, sorted by their start.
+pub struct SemanticTokenHighlights {
+    ranges: Vec<(Range<Anchor>, HighlightStyle)>,
+    /// The furthest end among the ranges up to each index. Nested or overlapping tokens leave the
+    /// ends of the ranges unsorted, while these can be searched for the first range that may end
+    /// after a position.
+    max_ends: Vec<Anchor>,
+}
+
+impl SemanticTokenHighlights {
+    pub fn new(
+        mut ranges: Vec<(Range<Anchor>, HighlightStyle)>,
+        buffer: &MultiBufferSnapshot,
+    ) -> Self {
+        ranges.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start, buffer));
+        let mut max_ends = Vec::<Anchor>::with_capacity(ranges.len());
+        for (range, _) in &ranges {
+            let max_end = match max_ends.last() {
+                Some(max_end) if max_end.cmp(&range.end, buffer).is_gt() => *max_end,
+                _ => range.end,
+            };
+            max_ends.push(max_end);
+        }
+        Self { ranges, max_ends }
+    }
+
+    /// The highlights intersecting the given range, along with their index.
+    pub(crate) fn intersecting<'a>(
+        &'a self,
+        range: Range<Anchor>,
+        buffer: &'a MultiBufferSnapshot,
+    ) -> impl 'a + Iterator<Item = (usize, &'a (Range<Anchor>, HighlightStyle))> {
+        let start_ix = self
+            .max_ends
+            .partition_point(|max_end| max_end.cmp(&range.start, buffer).is_le());
+        self.ranges
+            .iter()
+            .enumerate()
+            .skip(start_ix)
+            .take_while(move |(_, (highlight_range, _))| {
+                highlight_range.start.cmp(&range.end, buffer).is_lt()
+            })
+            .filter(move |(_, (highlight_range, _))| {
+                highlight_range.end.cmp(&range.start, buffer).is_gt()
+            })
+    }
+}
 
 pub struct DisplayMap {
     buffer: Model<MultiBuffer>,
@@ -77,6 +127,8 @@ pub struct DisplayMap {
     block_map: BlockMap,
     text_highlights: TextHighlights,
     inlay_highlights: InlayHighlights,
+    semantic_highlights: SemanticHighlights,
+    folding_ranges: FoldingRanges,
     pub clip_at_line_ends: bool,
 }
 
@@ -109,6 +161,8 @@ impl DisplayMap {
             block_map,
             text_highlights: Default::default(),
             inlay_highlights: Default::default(),
+            semantic_highlights: Default::default(),
+            folding_ranges: Default::default(),
             clip_at_line_ends: false,
         }
     }
@@ -134,6 +188,8 @@ impl DisplayMap {
             block_snapshot,
             text_highlights: self.text_highlights.clone(),
             inlay_highlights: self.inlay_highlights.clone(),
+            semantic_highlights: self.semantic_highlights.clone(),
+            folding_ranges: self.folding_ranges.clone(),
             clip_at_line_ends: self.clip_at_line_ends,
         }
     }
@@ -264,6 +320,27 @@ impl DisplayMap {
         cleared
     }
 
+    /// Replaces the semantic token highlights of the given buffer, which are drawn below any
+    /// other text highlight.
+    pub fn set_semantic_highlights(&mut self, buffer_id: u64, highlights: SemanticTokenHighlights) {
+        self.semantic_highlights
+            .insert(buffer_id, Arc::new(highlights));
+    }
+
+    pub fn clear_semantic_highlights(&mut self, buffer_id: u64) -> bool {
+        self.semantic_highlights.remove(&buffer_id).is_some()
+    }
+
+    /// Replaces the ranges that the language server reported as foldable in the given buffer,
+    /// which are sorted by their start. Rows of that buffer are only foldable at these ranges.
+    pub fn set_folding_ranges(&mut self, buffer_id: u64, ranges: Vec<Range<Anchor>>) {
+        self.folding_ranges.insert(buffer_id, Arc::new(ranges));
+    }
+
+    pub fn clear_folding_ranges(&mut self, buffer_id: u64) -> bool {
+        self.folding_ranges.remove(&buffer_id).is_some()
+    }
+
     pub fn set_font(&self, font: Font, font_size: Pixels, cx: &mut ModelContext<Self>) -> bool {
         self.wrap_map
             .update(cx, |map, cx| map.set_font_with_size(font, font_size, cx))
@@ -329,6 +406,7 @@ impl DisplayMap {
 pub(crate) struct Highlights<'a> {
     pub text_highlights: Option<&'a TextHighlights>,
     pub inlay_highlights: Option<&'a InlayHighlights>,
+    pub semantic_highlights: Option<&'a SemanticHighlights>,
     pub inlay_highlight_style: Option<HighlightStyle>,
     pub suggestion_highlight_style: Option<HighlightStyle>,
 }
@@ -348,6 +426,8 @@ pub struct DisplaySnapshot {
     block_snapshot: block_map::BlockSnapshot,
     text_highlights: TextHighlights,
     inlay_highlights: InlayHighlights,
+    semantic_highlights: SemanticHighlights,
+    folding_ranges: FoldingRanges,
     clip_at_line_ends: bool,
 }
 
@@ -515,6 +595,7 @@ impl DisplaySnapshot {
             Highlights {
                 text_highlights: Some(&self.text_highlights),
                 inlay_highlights: Some(&self.inlay_highlights),
+                semantic_highlights: Some(&self.semantic_highlights),
                 inlay_highlight_style,
                 suggestion_highlight_style,
             },
@@ -844,52 +925,152 @@ impl DisplaySnapshot {
         }
     }
 
+    /// Returns the fold status of the given buffer rows, which the gutter displays on every frame.
+    /// Unlike [`Self::fold_for_line`], the brackets of the syntax tree are queried once for all the
+    /// rows.
+    pub fn fold_statuses_for_rows(&self, buffer_rows: Range<u32>) -> HashMap<u32, FoldStatus> {
+        let mut statuses = HashMap::default();
+        let buffer_rows =
+            buffer_rows.start..buffer_rows.end.min(self.buffer_snapshot.max_buffer_row());
+        if buffer_rows.is_empty() {
+            return statuses;
+        }
+
+        let lsp_ranges = self
+            .buffer_snapshot
+            .buffer_line_for_row(buffer_rows.start)
+            .and_then(|(buffer, _)| self.folding_ranges.get(&buffer.remote_id()));
+        let mut bracket_rows = HashSet::default();
+        if lsp_ranges.is_none() {
+            let last_row = buffer_rows.end - 1;
+            let range = Point::new(buffer_rows.start, 0)
+                ..Point::new(last_row, self.buffer_snapshot.line_len(last_row));
+            if let Some(brackets) = self.buffer_snapshot.bracket_ranges(range) {
+                for (open, close) in brackets {
+                    let open_row = self.buffer_snapshot.offset_to_point(open.start).row;
+                    let close_row = self.buffer_snapshot.offset_to_point(close.start).row;
+                    if close_row > open_row + 1 {
+                        bracket_rows.insert(open_row);
+                    }
+                }
+            }
+        }
+
+        for buffer_row in buffer_rows {
+            let status = if self.is_line_folded(buffer_row) {
+                Some(FoldStatus::Folded)
+            } else {
+                let is_foldable = match lsp_ranges {
+                    Some(ranges) => self.lsp_fold_range(ranges, buffer_row).is_some(),
+                    None => {
+                        bracket_rows.contains(&buffer_row)
+                            || self.indent_fold_range(buffer_row).is_some()
+                    }
+                };
+                is_foldable.then_some(FoldStatus::Foldable)
+            };
+            if let Some(status) = status {
+                statuses.insert(buffer_row, status);
+            }
+        }
+        statuses
+    }
+
     pub fn is_foldable(self: &Self, buffer_row: u32) -> bool {
-        let max_row = self.buffer_snapshot.max_buffer_row();
-        if buffer_row >= max_row {
-            return false;
+        self.fold_range_for_row(buffer_row).is_some()
+    }
+
+    pub fn foldable_range(self: &Self, buffer_row: u32) -> Option<Range<Point>> {
+        if self.is_line_folded(buffer_row) {
+            None
+        } else {
+            self.fold_range_for_row(buffer_row)
+        }
+    }
+
+    /// Returns the range that would be folded at the given row. The ranges reported by the
+    /// language server take precedence, then the bracket pairs of the syntax tree, and finally
+    /// the indentation of the subsequent lines.
+    fn fold_range_for_row(&self, buffer_row: u32) -> Option<Range<Point>> {
+        if buffer_row >= self.buffer_snapshot.max_buffer_row() {
+            return None;
+        }
+
+        let (buffer, _) = self.buffer_snapshot.buffer_line_for_row(buffer_row)?;
+        if let Some(ranges) = self.folding_ranges.get(&buffer.remote_id()) {
+            return self.lsp_fold_range(ranges, buffer_row);
         }
 
-        let (indent_size, is_blank) = self.line_indent_for_buffer_row(buffer_row);
+        self.bracket_fold_range(buffer_row)
+            .or_else(|| self.indent_fold_range(buffer_row))
+    }
+
+    fn lsp_fold_range(&self, ranges: &[Range<Anchor>], buffer_row: u32) -> Option<Range<Point>> {
+        let start_ix = ranges
+            .partition_point(|range| range.start.to_point(&self.buffer_snapshot).row < buffer_row);
+        ranges[start_ix..]
+            .iter()
+            .map(|range| {
+                range.start.to_point(&self.buffer_snapshot)
+                    ..range.end.to_point(&self.buffer_snapshot)
+            })
+            .take_while(|range| range.start.row == buffer_row)
+            .filter(|range| range.end.row > buffer_row)
+            .max_by_key(|range| range.end)
+    }
+
+    fn bracket_fold_range(&self, buffer_row: u32) -> Option<Range<Point>> {
+        let line_start = Point::new(buffer_row, 0);
+        let line_end = Point::new(buffer_row, self.buffer_snapshot.line_len(buffer_row));
+        let close_row = self
+            .buffer_snapshot
+            .bracket_ranges(line_start..line_end)?
+            .filter_map(|(open, close)| {
+                let open_row = self.buffer_snapshot.offset_to_point(open.start).row;
+                let close_row = self.buffer_snapshot.offset_to_point(close.start).row;
+                (open_row == buffer_row && close_row > buffer_row + 1).then_some(close_row)
+            })
+            .max()?;
+        let end_row = close_row - 1;
+        Some(line_end..Point::new(end_row, self.buffer_snapshot.line_len(end_row)))
+    }
+
+    fn indent_fold_range(&self, buffer_row: u32) -> Option<Range<Point>> {
+        let (start_indent, is_blank) = self.line_indent_for_buffer_row(buffer_row);
         if is_blank {
-            return false;
+            return None;
         }
 
-        for next_row in (buffer_row + 1)..=max_row {
-            let (next_indent_size, next_line_is_blank) = self.line_indent_for_buffer_row(next_row);
-            if next_indent_size > indent_size {
-                return true;
+        let max_point = self.buffer_snapshot.max_point();
+        let mut is_foldable = false;
+        for next_row in (buffer_row + 1)..=max_point.row {
+            let (next_indent, next_line_is_blank) = self.line_indent_for_buffer_row(next_row);
+            if next_indent > start_indent {
+                is_foldable = true;
+                break;
             } else if !next_line_is_blank {
                 break;
             }
         }
+        if !is_foldable {
+            return None;
+        }
 
-        false
-    }
-
-    pub fn foldable_range(self: &Self, buffer_row: u32) -> Option<Range<Point>> {
         let start = Point::new(buffer_row, self.buffer_snapshot.line_len(buffer_row));
-        if self.is_foldable(start.row) && !self.is_line_folded(start.row) {
-            let (start_indent, _) = self.line_indent_for_buffer_row(buffer_row);
-            let max_point = self.buffer_snapshot.max_point();
-            let mut end = None;
-
-            for row in (buffer_row + 1)..=max_point.row {
-                let (indent, is_blank) = self.line_indent_for_buffer_row(row);
-                if !is_blank && indent <= start_indent {
-                    let prev_row = row - 1;
-                    end = Some(Point::new(
-                        prev_row,
-                        self.buffer_snapshot.line_len(prev_row),
-                    ));
-                    break;
-                }
+        let mut end = None;
+        for row in (buffer_row + 1)..=max_point.row {
+            let (indent, is_blank) = self.line_indent_for_buffer_row(row);
+            if !is_blank && indent <= start_indent {
+                let prev_row = row - 1;
+                end = Some(Point::new(
+                    prev_row,
+                    self.buffer_snapshot.line_len(prev_row),
+                ));
+                break;
             }
-            let end = end.unwrap_or(max_point);
-            Some(start..end)
-        } else {
-            None
         }
+        let end = end.unwrap_or(max_point);
+        Some(start..end)
     }
 
     #[cfg(any(test, feature = "test-support"))]
@@ -1666,6 +1847,35 @@ pub mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_intersecting_semantic_token_highlights(cx: &mut gpui::AppContext) {
+        let buffer = MultiBuffer::build_simple("fn outer() { inner() }", cx);
+        let buffer = buffer.read(cx).snapshot(cx);
+        let anchor_range =
+            |range: Range<usize>| buffer.anchor_after(range.start)..buffer.anchor_before(range.end);
+
+        // A token containing the others is sorted before them, and ends after them.
+        let style = HighlightStyle::default();
+        let highlights = SemanticTokenHighlights::new(
+            vec![
+                (anchor_range(13..18), style),
+                (anchor_range(0..22), style),
+                (anchor_range(3..8), style),
+            ],
+            &buffer,
+        );
+        let intersecting = |range: Range<usize>| {
+            highlights
+                .intersecting(anchor_range(range), &buffer)
+                .map(|(_, (range, _))| range.to_offset(&buffer))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(intersecting(13..14), vec![0..22, 13..18]);
+        assert_eq!(intersecting(9..12), vec![0..22]);
+        assert_eq!(intersecting(19..20), vec![0..22]);
+        assert_eq!(intersecting(0..22), vec![0..22, 3..8, 13..18]);
+    }
+
     #[gpui::test]
     fn test_clip_point(cx: &mut gpui::AppContext) {
         init_test(cx, |_| {});
@@ -1791,6 +2001,121 @@ pub mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_foldable_ranges(cx: &mut gpui::TestAppContext) {
+        use unindent::Unindent as _;
+
+        let text = r#"
+            fn outer() {
+                call(
+                    1,
+                );
+                let y = a
+                    .b();
+            }"#
+        .unindent();
+
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig {
+                    name: "Test".into(),
+                    path_suffixes: vec![".test".to_string()],
+                    ..Default::default()
+                },
+                Some(tree_sitter_rust::language()),
+            )
+            .with_brackets_query(
+                r#"
+                ("(" @open ")" @close)
+                ("{" @open "}" @close)
+                "#,
+            )
+            .unwrap(),
+        );
+
+        cx.update(|cx| init_test(cx, |_| {}));
+
+        let buffer = cx.new_model(|cx| {
+            Buffer::new(0, cx.entity_id().as_u64(), text).with_language(language, cx)
+        });
+        cx.condition(&buffer, |buf, _| !buf.is_parsing()).await;
+        let buffer_id = buffer.read_with(cx, |buffer, _| buffer.remote_id());
+        let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+
+        let font_size = px(14.0);
+        let map = cx.new_model(|cx| {
+            DisplayMap::new(buffer.clone(), font("Helvetica"), font_size, None, 1, 1, cx)
+        });
+
+        // The statuses displayed in the gutter are the same as the ones of individual rows.
+        let assert_fold_statuses = |snapshot: &DisplaySnapshot| {
+            let statuses = (0..7)
+                .filter_map(|row| Some((row, snapshot.fold_for_line(row)?)))
+                .collect::<HashMap<_, _>>();
+            assert_eq!(snapshot.fold_statuses_for_rows(0..7), statuses);
+        };
+
+        // Without folding ranges from a language server, the brackets are used, and then the
+        // indentation.
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert_fold_statuses(&snapshot);
+        assert_eq!(
+            (0..7)
+                .map(|row| snapshot.foldable_range(row))
+                .collect::<Vec<_>>(),
+            vec![
+                Some(Point::new(0, 12)..Point::new(5, 13)),
+                Some(Point::new(1, 9)..Point::new(2, 10)),
+                None,
+                None,
+                Some(Point::new(4, 13)..Point::new(5, 13)),
+                None,
+                None,
+            ]
+        );
+
+        // The folding ranges of the language server replace the other ones.
+        map.update(cx, |map, cx| {
+            let buffer = buffer.read(cx).snapshot(cx);
+            map.set_folding_ranges(
+                buffer_id,
+                vec![
+                    buffer.anchor_after(Point::new(0, 12))..buffer.anchor_before(Point::new(3, 6)),
+                ],
+            );
+        });
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert_eq!(
+            (0..7)
+                .map(|row| snapshot.foldable_range(row))
+                .collect::<Vec<_>>(),
+            vec![
+                Some(Point::new(0, 12)..Point::new(3, 6)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+        assert_fold_statuses(&snapshot);
+
+        // Folded lines can't be folded again.
+        map.update(cx, |map, cx| {
+            map.fold(vec![Point::new(0, 12)..Point::new(3, 6)], cx);
+        });
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert_eq!(snapshot.foldable_range(0), None);
+        assert_eq!(snapshot.fold_for_line(0), Some(FoldStatus::Folded));
+        assert_fold_statuses(&snapshot);
+
+        map.update(cx, |map, _| map.clear_folding_ranges(buffer_id));
+        let snapshot = map.update(cx, |map, cx| map.snapshot(cx));
+        assert_eq!(snapshot.fold_for_line(4), Some(FoldStatus::Foldable));
+        assert_fold_statuses(&snapshot);
+    }
+
     #[gpui::test]
     fn test_max_point(cx: &mut gpui::AppContext) {
         init_test(cx, |_| {});
@@ -14,7 +14,7 @@ use std::{
 use sum_tree::{Bias, Cursor, SumTree, TreeMap};
 use text::{Patch, Rope};
 
-use super::Highlights;
+use super::{Highlights, SemanticTokenHighlights};
 
 pub struct InlayMap {
     snapshot: InlaySnapshot,
@@ -186,10 +186,18 @@ pub struct InlayBufferRows<'a> {
 struct HighlightEndpoint {
     offset: InlayOffset,
     is_start: bool,
-    tag: Option<TypeId>,
+    tag: HighlightTag,
     style: HighlightStyle,
 }
 
+/// Identifies an active highlight. Semantic tokens are ordered first, so that the styles of
+/// other text highlights are applied on top of them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum HighlightTag {
+    SemanticToken { buffer_id: u64, ix: usize },
+    Text(Option<TypeId>),
+}
+
 impl PartialOrd for HighlightEndpoint {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         Some(self.cmp(other))
@@ -215,7 +223,7 @@ pub struct InlayChunks<'a> {
     inlay_highlight_style: Option<HighlightStyle>,
     suggestion_highlight_style: Option<HighlightStyle>,
     highlight_endpoints: Peekable<vec::IntoIter<HighlightEndpoint>>,
-    active_highlights: BTreeMap<Option<TypeId>, HighlightStyle>,
+    active_highlights: BTreeMap<HighlightTag, HighlightStyle>,
     highlights: Highlights<'a>,
     snapshot: &'a InlaySnapshot,
 }
@@ -1037,6 +1045,15 @@ impl InlaySnapshot {
                 cursor.seek(&range.start, Bias::Right, &());
             }
         }
+        if let Some(semantic_highlights) = highlights.semantic_highlights {
+            if !semantic_highlights.is_empty() {
+                self.apply_semantic_highlights(
+                    &range,
+                    semantic_highlights,
+                    &mut highlight_endpoints,
+                );
+            }
+        }
         highlight_endpoints.sort();
         let buffer_range = self.to_buffer_offset(range.start)..self.to_buffer_offset(range.end);
         let buffer_chunks = self.buffer.chunks(buffer_range, language_aware);
@@ -1100,13 +1117,13 @@ impl InlaySnapshot {
                     highlight_endpoints.push(HighlightEndpoint {
                         offset: self.to_inlay_offset(range.start.to_offset(&self.buffer)),
                         is_start: true,
-                        tag: *tag,
+                        tag: HighlightTag::Text(*tag),
                         style,
                     });
                     highlight_endpoints.push(HighlightEndpoint {
                         offset: self.to_inlay_offset(range.end.to_offset(&self.buffer)),
                         is_start: false,
-                        tag: *tag,
+                        tag: HighlightTag::Text(*tag),
                         style,
                     });
                 }
@@ -1116,6 +1133,38 @@ impl InlaySnapshot {
         }
     }
 
+    fn apply_semantic_highlights(
+        &self,
+        range: &Range<InlayOffset>,
+        semantic_highlights: &TreeMap<u64, Arc<SemanticTokenHighlights>>,
+        highlight_endpoints: &mut Vec<HighlightEndpoint>,
+    ) {
+        let range_start = self.buffer.anchor_after(self.to_buffer_offset(range.start));
+        let range_end = self.buffer.anchor_before(self.to_buffer_offset(range.end));
+        for (buffer_id, highlights) in semantic_highlights.iter() {
+            for (ix, (highlight_range, style)) in
+                highlights.intersecting(range_start..range_end, &self.buffer)
+            {
+                let tag = HighlightTag::SemanticToken {
+                    buffer_id: *buffer_id,
+                    ix,
+                };
+                highlight_endpoints.push(HighlightEndpoint {
+                    offset: self.to_inlay_offset(highlight_range.start.to_offset(&self.buffer)),
+                    is_start: true,
+                    tag,
+                    style: *style,
+                });
+                highlight_endpoints.push(HighlightEndpoint {
+                    offset: self.to_inlay_offset(highlight_range.end.to_offset(&self.buffer)),
+                    is_start: false,
+                    tag,
+                    style: *style,
+                });
+            }
+        }
+    }
+
     #[cfg(test)]
     pub fn text(&self) -> String {
         self.chunks(Default::default()..self.len(), false, Highlights::default())
@@ -14,22 +14,34 @@
 //! If you're looking to improve Vim mode, you should check out Vim crate that wraps Editor and overrides it's behaviour.
 pub mod actions;
 mod blink_manager;
+mod call_hierarchy;
+mod code_lens;
 pub mod display_map;
 mod editor_settings;
 mod element;
+mod folding_ranges;
 mod inlay_hint_cache;
+mod linked_editing_ranges;
 
 mod git;
+mod git_blame;
 mod highlight_matching_bracket;
 mod hover_popover;
+mod hunk_staging;
 pub mod items;
 mod link_go_to_definition;
+mod merge_conflicts;
 mod mouse_context_menu;
 pub mod movement;
 mod persistence;
 mod rust_analyzer_ext;
 pub mod scroll;
+mod selection_ranges;
 mod selections_collection;
+mod semantic_tokens;
+mod signature_help;
+mod snippet_variables;
+mod user_snippets;
 
 #[cfg(test)]
 mod editor_tests;
@@ -42,6 +54,7 @@ use anyhow::{anyhow, Context as _, Result};
 use blink_manager::BlinkManager;
 use client::{Collaborator, ParticipantIndex};
 use clock::ReplicaId;
+use code_lens::CodeLensState;
 use collections::{BTreeMap, Bound, HashMap, HashSet, VecDeque};
 use convert_case::{Case, Casing};
 use copilot::Copilot;
@@ -50,9 +63,11 @@ use display_map::*;
 pub use editor_settings::EditorSettings;
 use element::LineWithInvisibles;
 pub use element::{Cursor, EditorElement, HighlightedRange, HighlightedRangeLine};
+use folding_ranges::FoldingRangesState;
 use futures::FutureExt;
 use fuzzy::{StringMatch, StringMatchCandidate};
 use git::diff_hunk_to_display;
+use git_blame::GitBlameState;
 use gpui::{
     div, impl_actions, point, prelude::*, px, relative, rems, size, uniform_list, Action,
     AnyElement, AppContext, AsyncWindowContext, BackgroundExecutor, Bounds, ClipboardItem, Context,
@@ -71,12 +86,15 @@ use language::{char_kind, CharKind};
 use language::{
     language_settings::{self, all_language_settings, InlayHintSettings},
     markdown, point_from_lsp, AutoindentMode, BracketPair, Buffer, Capability, CodeAction,
-    CodeLabel, Completion, CursorShape, Diagnostic, Documentation, IndentKind, IndentSize,
-    Language, LanguageServerName, OffsetRangeExt, Point, Selection, SelectionGoal, TransactionId,
+    CodeLabel, Completion, CompletionSource, CursorShape, Diagnostic, Documentation, IndentKind,
+    IndentSize, Language, LanguageServerName, OffsetRangeExt, Point, Selection, SelectionGoal,
+    TransactionId,
 };
 
 use link_go_to_definition::{GoToDefinitionLink, InlayHighlight, LinkGoToDefinitionState};
+use linked_editing_ranges::{is_linked_editing_input, LinkedEditingRangesState};
 use lsp::{DiagnosticSeverity, LanguageServerId};
+use merge_conflicts::MergeConflictsState;
 use mouse_context_menu::MouseContextMenu;
 use movement::TextLayoutDetails;
 use multi_buffer::ToOffsetUtf16;
@@ -86,13 +104,17 @@ pub use multi_buffer::{
 };
 use ordered_float::OrderedFloat;
 use parking_lot::RwLock;
+use persistence::{FoldsFingerprint, DB};
 use project::{FormatTrigger, Location, Project, ProjectPath, ProjectTransaction};
 use rand::prelude::*;
 use rpc::proto::*;
 use scroll::{Autoscroll, OngoingScroll, ScrollAnchor, ScrollManager, ScrollbarAutoHide};
+use selection_ranges::SelectionRangesState;
 use selections_collection::{resolve_multiple, MutableSelectionsCollection, SelectionsCollection};
+use semantic_tokens::SemanticTokensState;
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsStore};
+use signature_help::SignatureHelpState;
 use smallvec::SmallVec;
 use snippet::Snippet;
 use std::{
@@ -102,10 +124,10 @@ use std::{
     mem,
     num::NonZeroU32,
     ops::{ControlFlow, Deref, DerefMut, Range, RangeInclusive},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
     sync::Weak,
-    time::{Duration, Instant},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 pub use sum_tree::Bias;
 use sum_tree::TreeMap;
@@ -118,8 +140,12 @@ use ui::{
     h_flex, prelude::*, ButtonSize, ButtonStyle, IconButton, IconName, IconSize, ListItem, Popover,
     Tooltip,
 };
+use user_snippets::user_snippet_completions;
+pub use user_snippets::{handle_snippet_file_changes, UserSnippets};
 use util::{post_inc, RangeExt, ResultExt, TryFutureExt};
-use workspace::{searchable::SearchEvent, ItemNavHistory, Pane, SplitDirection, ViewId, Workspace};
+use workspace::{
+    searchable::SearchEvent, ItemNavHistory, Pane, SplitDirection, ViewId, Workspace, WorkspaceId,
+};
 
 const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
 const MAX_LINE_LEN: usize = 1024;
@@ -362,6 +388,7 @@ pub struct Editor {
     autoclose_regions: Vec<AutocloseRegion>,
     snippet_stack: InvalidationStack<SnippetState>,
     select_larger_syntax_node_stack: Vec<Box<[Selection<usize>]>>,
+    selection_ranges: SelectionRangesState,
     ime_transaction: Option<TransactionId>,
     active_diagnostics: Option<ActiveDiagnosticGroup>,
     soft_wrap_mode_override: Option<language_settings::SoftWrap>,
@@ -399,11 +426,19 @@ pub struct Editor {
     leader_peer_id: Option<PeerId>,
     remote_id: Option<ViewId>,
     hover_state: HoverState,
+    signature_help_state: SignatureHelpState,
     gutter_hovered: bool,
     link_go_to_definition_state: LinkGoToDefinitionState,
     copilot_state: CopilotState,
     inlay_hint_cache: InlayHintCache,
+    semantic_tokens: SemanticTokensState,
+    folding_ranges: FoldingRangesState,
+    code_lens: CodeLensState,
+    linked_editing_ranges: LinkedEditingRangesState,
+    git_blame: GitBlameState,
+    merge_conflicts: MergeConflictsState,
     next_inlay_id: usize,
+    call_hierarchy: Option<call_hierarchy::CallHierarchy>,
     _subscriptions: Vec<Subscription>,
     pixel_position_of_newest_cursor: Option<gpui::Point<Pixels>>,
     gutter_width: Pixels,
@@ -416,6 +451,7 @@ pub struct Editor {
 pub struct EditorSnapshot {
     pub mode: EditorMode,
     show_gutter: bool,
+    show_git_blame_gutter: bool,
     pub display_snapshot: DisplaySnapshot,
     pub placeholder_text: Option<Arc<str>>,
     is_focused: bool,
@@ -427,6 +463,8 @@ pub struct GutterDimensions {
     pub padding: Pixels,
     pub width: Pixels,
     pub margin: Pixels,
+    /// The width of the column showing the commit that last changed each line, if shown.
+    pub git_blame_entries_width: Option<Pixels>,
 }
 
 impl Default for GutterDimensions {
@@ -435,6 +473,7 @@ impl Default for GutterDimensions {
             padding: Pixels::ZERO,
             width: Pixels::ZERO,
             margin: Pixels::ZERO,
+            git_blame_entries_width: None,
         }
     }
 }
@@ -584,9 +623,22 @@ struct AutocloseRegion {
 #[derive(Debug)]
 struct SnippetState {
     ranges: Vec<Vec<Range<Anchor>>>,
+    /// The values offered for each tabstop, if it's a choice.
+    choices: Vec<Option<Vec<String>>>,
+    /// The transforms applied when leaving each tabstop.
+    transforms: Vec<Vec<SnippetTransform>>,
     active_index: usize,
 }
 
+#[derive(Debug)]
+struct SnippetTransform {
+    /// The range holding the value of the tabstop.
+    source: Range<Anchor>,
+    /// The range that the transformed value replaces.
+    target: Range<Anchor>,
+    transform: snippet::Transform,
+}
+
 #[doc(hidden)]
 pub struct RenameState {
     pub range: Range<Anchor>,
@@ -1263,6 +1315,7 @@ pub(crate) struct NavigationData {
 enum GotoDefinitionKind {
     Symbol,
     Type,
+    Implementation,
 }
 
 #[derive(Debug, Clone)]
@@ -1364,11 +1417,24 @@ impl Editor {
                         cx.emit(EditorEvent::TitleChanged);
                     }));
                 }
-                project_subscriptions.push(cx.subscribe(project, |editor, _, event, cx| {
-                    if let project::Event::RefreshInlayHints = event {
-                        editor.refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
-                    };
-                }));
+                project_subscriptions.push(cx.subscribe(
+                    project,
+                    |editor, _, event, cx| match event {
+                        project::Event::RefreshInlayHints => {
+                            editor
+                                .refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
+                        }
+                        project::Event::RefreshSemanticTokens => {
+                            editor.refresh_semantic_tokens(true, cx);
+                        }
+                        project::Event::LanguageServerAdded(_) => {
+                            editor.refresh_semantic_tokens(true, cx);
+                            editor.refresh_folding_ranges(true, cx);
+                            editor.refresh_code_lens(true, cx);
+                        }
+                        _ => {}
+                    },
+                ));
             }
         }
 
@@ -1397,6 +1463,7 @@ impl Editor {
             autoclose_regions: Default::default(),
             snippet_stack: Default::default(),
             select_larger_syntax_node_stack: Vec::new(),
+            selection_ranges: Default::default(),
             ime_transaction: Default::default(),
             active_diagnostics: None,
             soft_wrap_mode_override,
@@ -1434,9 +1501,17 @@ impl Editor {
             leader_peer_id: None,
             remote_id: None,
             hover_state: Default::default(),
+            signature_help_state: Default::default(),
             link_go_to_definition_state: Default::default(),
             copilot_state: Default::default(),
             inlay_hint_cache: InlayHintCache::new(inlay_hint_settings),
+            semantic_tokens: Default::default(),
+            folding_ranges: Default::default(),
+            code_lens: Default::default(),
+            linked_editing_ranges: Default::default(),
+            git_blame: Default::default(),
+            merge_conflicts: Default::default(),
+            call_hierarchy: None,
             gutter_hovered: false,
             pixel_position_of_newest_cursor: None,
             gutter_width: Default::default(),
@@ -1470,6 +1545,11 @@ impl Editor {
 
         this.end_selection(cx);
         this.scroll_manager.show_scrollbar(cx);
+        this.refresh_semantic_tokens(false, cx);
+        this.refresh_folding_ranges(false, cx);
+        this.refresh_code_lens(false, cx);
+        this.refresh_git_blame(false, cx);
+        this.refresh_merge_conflicts(false, cx);
 
         if mode == EditorMode::Full {
             let should_auto_hide_scrollbars = cx.should_auto_hide_scrollbars();
@@ -1589,6 +1669,7 @@ impl Editor {
         EditorSnapshot {
             mode: self.mode,
             show_gutter: self.show_gutter,
+            show_git_blame_gutter: self.git_blame.show_in_gutter,
             display_snapshot: self.display_map.update(cx, |map, cx| map.snapshot(cx)),
             scroll_anchor: self.scroll_manager.anchor(),
             ongoing_scroll: self.scroll_manager.ongoing_scroll(),
@@ -1745,6 +1826,7 @@ impl Editor {
         self.select_next_state = None;
         self.select_prev_state = None;
         self.select_larger_syntax_node_stack.clear();
+        self.selection_ranges = Default::default();
         self.invalidate_autoclose_regions(&self.selections.disjoint_anchors(), buffer);
         self.snippet_stack
             .invalidate(&self.selections.disjoint_anchors(), buffer);
@@ -1813,6 +1895,7 @@ impl Editor {
             }
 
             hide_hover(self, cx);
+            self.refresh_signature_help(cx);
 
             if old_cursor_position.to_display_point(&display_map).row()
                 != new_cursor_position.to_display_point(&display_map).row()
@@ -1821,6 +1904,7 @@ impl Editor {
             }
             self.refresh_code_actions(cx);
             self.refresh_document_highlights(cx);
+            self.refresh_linked_editing_ranges(cx);
             refresh_matching_bracket_highlights(self, cx);
             self.discard_copilot_suggestion(cx);
         }
@@ -2230,6 +2314,10 @@ impl Editor {
             return;
         }
 
+        if self.hide_signature_help(cx) {
+            return;
+        }
+
         if self.hide_context_menu(cx).is_some() {
             return;
         }
@@ -2263,6 +2351,12 @@ impl Editor {
             return;
         }
 
+        if is_linked_editing_input(&text)
+            && self.edit_linked_editing_ranges(cx, |this, cx| this.handle_input(&text, cx))
+        {
+            return;
+        }
+
         let selections = self.selections.all_adjusted(cx);
         let mut brace_inserted = false;
         let mut edits = Vec::new();
@@ -2450,6 +2544,7 @@ impl Editor {
                 this.trigger_completion_on_input(&text, cx);
                 this.refresh_copilot_suggestions(true, cx);
             }
+            this.trigger_signature_help_on_input(&text, cx);
         });
     }
 
@@ -2730,6 +2825,14 @@ impl Editor {
             return;
         }
 
+        if is_linked_editing_input(text)
+            && self.edit_linked_editing_ranges(cx, |this, cx| {
+                this.insert_with_autoindent_mode(text, autoindent_mode.clone(), cx)
+            })
+        {
+            return;
+        }
+
         let text: Arc<str> = text.into();
         self.transact(cx, |this, cx| {
             let old_selections = this.selections.all_adjusted(cx);
@@ -3119,12 +3222,15 @@ impl Editor {
 
         let query = Self::completion_query(&self.buffer.read(cx).read(cx), position.clone());
         let completions = provider.completions(&buffer, buffer_position, cx);
+        let snippet_completions = user_snippet_completions(buffer.read(cx), buffer_position, cx);
 
         let id = post_inc(&mut self.next_completion_id);
         let task = cx.spawn(|this, mut cx| {
             async move {
-                let completions = completions.await.log_err();
-                let (menu, pre_resolve_task) = if let Some(completions) = completions {
+                // Offer the user's snippets even if the language server failed to complete.
+                let mut completions = completions.await.log_err().unwrap_or_default();
+                completions.extend(snippet_completions);
+                let (menu, pre_resolve_task) = if !completions.is_empty() {
                     let mut menu = CompletionsMenu {
                         id,
                         initial_position: position,
@@ -3232,7 +3338,12 @@ impl Editor {
         let snippet;
         let text;
         if completion.is_snippet() {
-            snippet = Some(Snippet::parse(&completion.new_text).log_err()?);
+            snippet = Some(
+                Snippet::parse_with_variables(&completion.new_text, |name| {
+                    self.resolve_snippet_variable(name, cx)
+                })
+                .log_err()?,
+            );
             text = snippet.as_ref().unwrap().text.clone();
         } else {
             snippet = None;
@@ -3305,7 +3416,11 @@ impl Editor {
         self.transact(cx, |this, cx| {
             if let Some(mut snippet) = snippet {
                 snippet.text = text.to_string();
-                for tabstop in snippet.tabstops.iter_mut().flatten() {
+                for tabstop in snippet
+                    .tabstops
+                    .iter_mut()
+                    .flat_map(|tabstop| tabstop.ranges.iter_mut())
+                {
                     tabstop.start -= common_prefix_len as isize;
                     tabstop.end -= common_prefix_len as isize;
                 }
@@ -4015,7 +4130,7 @@ impl Editor {
         snippet: Snippet,
         cx: &mut ViewContext<Self>,
     ) -> Result<()> {
-        let tabstops = self.buffer.update(cx, |buffer, cx| {
+        let (tabstops, transforms) = self.buffer.update(cx, |buffer, cx| {
             let snippet_text: Arc<str> = snippet.text.clone().into();
             buffer.edit(
                 insertion_ranges
@@ -4027,33 +4142,71 @@ impl Editor {
             );
 
             let snapshot = &*buffer.read(cx);
-            let snippet = &snippet;
-            snippet
+            let mut delta = 0_isize;
+            let insertion_starts = insertion_ranges
+                .iter()
+                .map(|insertion_range| {
+                    let insertion_start = insertion_range.start as isize + delta;
+                    delta += snippet.text.len() as isize - insertion_range.len() as isize;
+                    insertion_start
+                })
+                .collect::<Vec<_>>();
+            let anchor_range = |insertion_start: isize, tabstop_range: &Range<isize>| {
+                let start =
+                    snapshot.anchor_before((insertion_start + tabstop_range.start) as usize);
+                let end = snapshot.anchor_after((insertion_start + tabstop_range.end) as usize);
+                start..end
+            };
+
+            let tabstops = snippet
                 .tabstops
                 .iter()
                 .map(|tabstop| {
                     let mut tabstop_ranges = tabstop
+                        .ranges
                         .iter()
                         .flat_map(|tabstop_range| {
-                            let mut delta = 0_isize;
-                            insertion_ranges.iter().map(move |insertion_range| {
-                                let insertion_start = insertion_range.start as isize + delta;
-                                delta +=
-                                    snippet.text.len() as isize - insertion_range.len() as isize;
-
-                                let start = snapshot.anchor_before(
-                                    (insertion_start + tabstop_range.start) as usize,
-                                );
-                                let end = snapshot
-                                    .anchor_after((insertion_start + tabstop_range.end) as usize);
-                                start..end
+                            insertion_starts.iter().map(move |insertion_start| {
+                                anchor_range(*insertion_start, tabstop_range)
                             })
                         })
                         .collect::<Vec<_>>();
                     tabstop_ranges.sort_unstable_by(|a, b| a.start.cmp(&b.start, snapshot));
                     tabstop_ranges
                 })
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>();
+
+            // Each transform is applied to the value of the first untransformed range of its
+            // tabstop, within the same insertion.
+            let transforms = snippet
+                .tabstops
+                .iter()
+                .map(|tabstop| {
+                    let source = tabstop
+                        .ranges
+                        .iter()
+                        .enumerate()
+                        .find(|(ix, _)| !tabstop.transforms.iter().any(|(jx, _)| jx == ix))
+                        .map(|(_, range)| range);
+                    tabstop
+                        .transforms
+                        .iter()
+                        .flat_map(|(ix, transform)| {
+                            let target = &tabstop.ranges[*ix];
+                            let source = source.unwrap_or(target);
+                            insertion_starts
+                                .iter()
+                                .map(move |insertion_start| SnippetTransform {
+                                    source: anchor_range(*insertion_start, source),
+                                    target: anchor_range(*insertion_start, target),
+                                    transform: transform.clone(),
+                                })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            (tabstops, transforms)
         });
 
         if let Some(tabstop) = tabstops.first() {
@@ -4063,7 +4216,14 @@ impl Editor {
             self.snippet_stack.push(SnippetState {
                 active_index: 0,
                 ranges: tabstops,
+                choices: snippet
+                    .tabstops
+                    .into_iter()
+                    .map(|tabstop| tabstop.choices)
+                    .collect(),
+                transforms,
             });
+            self.show_snippet_choices(cx);
         }
 
         Ok(())
@@ -4079,6 +4239,7 @@ impl Editor {
 
     pub fn move_to_snippet_tabstop(&mut self, bias: Bias, cx: &mut ViewContext<Self>) -> bool {
         if let Some(mut snippet) = self.snippet_stack.pop() {
+            let previous_index = snippet.active_index;
             match bias {
                 Bias::Left => {
                     if snippet.active_index > 0 {
@@ -4097,6 +4258,7 @@ impl Editor {
                     }
                 }
             }
+            self.apply_snippet_transforms(&snippet.transforms[previous_index], cx);
             if let Some(current_ranges) = snippet.ranges.get(snippet.active_index) {
                 self.change_selections(Some(Autoscroll::fit()), cx, |s| {
                     s.select_anchor_ranges(current_ranges.iter().cloned())
@@ -4104,6 +4266,7 @@ impl Editor {
                 // If snippet state is not at the last tabstop, push it back on the stack
                 if snippet.active_index + 1 < snippet.ranges.len() {
                     self.snippet_stack.push(snippet);
+                    self.show_snippet_choices(cx);
                 }
                 return true;
             }
@@ -4112,6 +4275,95 @@ impl Editor {
         false
     }
 
+    fn apply_snippet_transforms(
+        &mut self,
+        transforms: &[SnippetTransform],
+        cx: &mut ViewContext<Self>,
+    ) {
+        if transforms.is_empty() {
+            return;
+        }
+
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let mut edits = transforms
+            .iter()
+            .map(|transform| {
+                let value = snapshot
+                    .text_for_range(transform.source.clone())
+                    .collect::<String>();
+                (transform.target.clone(), transform.transform.apply(&value))
+            })
+            .collect::<Vec<_>>();
+        edits.sort_unstable_by(|(a, _), (b, _)| a.start.cmp(&b.start, &snapshot));
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+
+    /// Offers the choices of the active snippet tabstop, if any, in the completions menu.
+    fn show_snippet_choices(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(choices) = self
+            .snippet_stack
+            .last()
+            .and_then(|snippet| snippet.choices.get(snippet.active_index)?.clone())
+        else {
+            return;
+        };
+        let selection = self.selections.newest_anchor().clone();
+        let buffer = self.buffer.read(cx);
+        let Some((buffer, start)) = buffer.text_anchor_for_position(selection.start, cx) else {
+            return;
+        };
+        let Some((_, end)) = self
+            .buffer
+            .read(cx)
+            .text_anchor_for_position(selection.end, cx)
+        else {
+            return;
+        };
+
+        let completions = choices
+            .iter()
+            .map(|choice| Completion {
+                old_range: start..end,
+                new_text: choice.clone(),
+                label: CodeLabel::plain(choice.clone(), None),
+                source: CompletionSource::Custom,
+                documentation: Some(Documentation::Undocumented),
+                lsp_completion: Default::default(),
+            })
+            .collect::<Vec<_>>();
+        let match_candidates = choices
+            .iter()
+            .enumerate()
+            .map(|(id, choice)| StringMatchCandidate::new(id, choice.clone()))
+            .collect();
+        let matches = choices
+            .into_iter()
+            .enumerate()
+            .map(|(candidate_id, string)| StringMatch {
+                candidate_id,
+                score: 0.,
+                positions: Vec::new(),
+                string,
+            })
+            .collect();
+        let menu = CompletionsMenu {
+            id: post_inc(&mut self.next_completion_id),
+            initial_position: selection.start,
+            buffer,
+            completions: Arc::new(RwLock::new(completions.into())),
+            match_candidates,
+            matches,
+            selected_item: 0,
+            scroll_handle: UniformListScrollHandle::new(),
+        };
+        *self.context_menu.write() = Some(ContextMenu::Completions(menu));
+        self.discard_copilot_suggestion(cx);
+        cx.notify();
+    }
+
     pub fn clear(&mut self, cx: &mut ViewContext<Self>) {
         self.transact(cx, |this, cx| {
             this.select_all(&SelectAll, cx);
@@ -5900,6 +6152,17 @@ impl Editor {
         }
     }
 
+    /// Records a jump away from the given cursor position in the navigation history, even if the
+    /// newest cursor moved too few rows from it for the jump to have been recorded already.
+    pub fn push_jump_to_nav_history(&mut self, cursor_anchor: Anchor, cx: &mut ViewContext<Self>) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let old_row = cursor_anchor.to_point(&buffer).row;
+        let new_row = self.selections.newest_anchor().head().to_point(&buffer).row;
+        if (new_row as i64 - old_row as i64).abs() < MIN_NAVIGATION_HISTORY_ROW_DELTA {
+            self.push_to_nav_history(cursor_anchor, None, cx);
+        }
+    }
+
     pub fn select_to_end(&mut self, _: &SelectToEnd, cx: &mut ViewContext<Self>) {
         let buffer = self.buffer.read(cx).snapshot(cx);
         let mut selection = self.selections.first::<usize>(cx);
@@ -6723,19 +6986,36 @@ impl Editor {
         _: &SelectLargerSyntaxNode,
         cx: &mut ViewContext<Self>,
     ) {
+        if !self.fetch_selection_ranges(cx) {
+            self.expand_selections_to_larger_syntax_node(cx);
+        }
+    }
+
+    fn expand_selections_to_larger_syntax_node(&mut self, cx: &mut ViewContext<Self>) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
         let old_selections = self.selections.all::<usize>(cx).into_boxed_slice();
 
         let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
+        let selection_ranges = mem::take(&mut self.selection_ranges);
         let mut selected_larger_node = false;
         let new_selections = old_selections
             .iter()
             .map(|selection| {
                 let old_range = selection.start..selection.end;
+                // Prefer the ranges provided by the language server, falling back to the syntax
+                // tree when none of them contains the selection.
+                let lsp_ranges = selection_ranges.ranges(selection.id, &buffer);
                 let mut new_range = old_range.clone();
-                while let Some(containing_range) =
-                    buffer.range_for_syntax_ancestor(new_range.clone())
+                while let Some(containing_range) = lsp_ranges
+                    .iter()
+                    .find(|range| {
+                        range.start <= new_range.start
+                            && range.end >= new_range.end
+                            && range.len() > new_range.len()
+                    })
+                    .cloned()
+                    .or_else(|| buffer.range_for_syntax_ancestor(new_range.clone()))
                 {
                     new_range = containing_range;
                     if !display_map.intersects_fold(new_range.start)
@@ -6763,6 +7043,7 @@ impl Editor {
             });
         }
         self.select_larger_syntax_node_stack = stack;
+        self.selection_ranges = selection_ranges;
     }
 
     pub fn select_smaller_syntax_node(
@@ -6770,13 +7051,19 @@ impl Editor {
         _: &SelectSmallerSyntaxNode,
         cx: &mut ViewContext<Self>,
     ) {
+        if self.selection_ranges.cancel_pending_expansion() {
+            return;
+        }
+
         let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
+        let selection_ranges = mem::take(&mut self.selection_ranges);
         if let Some(selections) = stack.pop() {
             self.change_selections(Some(Autoscroll::fit()), cx, |s| {
                 s.select(selections.to_vec());
             });
         }
         self.select_larger_syntax_node_stack = stack;
+        self.selection_ranges = selection_ranges;
     }
 
     pub fn move_to_enclosing_bracket(
@@ -7057,6 +7344,10 @@ impl Editor {
         self.go_to_definition_of_kind(GotoDefinitionKind::Type, false, cx);
     }
 
+    pub fn go_to_implementation(&mut self, _: &GoToImplementation, cx: &mut ViewContext<Self>) {
+        self.go_to_definition_of_kind(GotoDefinitionKind::Implementation, false, cx);
+    }
+
     pub fn go_to_definition_split(&mut self, _: &GoToDefinitionSplit, cx: &mut ViewContext<Self>) {
         self.go_to_definition_of_kind(GotoDefinitionKind::Symbol, true, cx);
     }
@@ -7069,6 +7360,14 @@ impl Editor {
         self.go_to_definition_of_kind(GotoDefinitionKind::Type, true, cx);
     }
 
+    pub fn go_to_implementation_split(
+        &mut self,
+        _: &GoToImplementationSplit,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.go_to_definition_of_kind(GotoDefinitionKind::Implementation, true, cx);
+    }
+
     fn go_to_definition_of_kind(
         &mut self,
         kind: GotoDefinitionKind,
@@ -7090,6 +7389,7 @@ impl Editor {
         let definitions = project.update(cx, |project, cx| match kind {
             GotoDefinitionKind::Symbol => project.definition(&buffer, head, cx),
             GotoDefinitionKind::Type => project.type_definition(&buffer, head, cx),
+            GotoDefinitionKind::Implementation => project.implementation(&buffer, head, cx),
         });
 
         cx.spawn(|editor, mut cx| async move {
@@ -7823,7 +8123,10 @@ impl Editor {
         self.end_transaction_at(Instant::now(), cx)
     }
 
-    fn start_transaction_at(&mut self, now: Instant, cx: &mut ViewContext<Self>) {
+    /// Starts a transaction that lasts until `end_transaction_at` is called. Unlike `transact`,
+    /// the transaction can span several updates of the editor, such as the keystrokes typed by
+    /// vim's `:normal` command, which are grouped into one undo step.
+    pub fn start_transaction_at(&mut self, now: Instant, cx: &mut ViewContext<Self>) {
         self.end_selection(cx);
         if let Some(tx_id) = self
             .buffer
@@ -7834,7 +8137,9 @@ impl Editor {
         }
     }
 
-    fn end_transaction_at(
+    /// Ends a transaction started with `start_transaction_at`, returning its id if it edited
+    /// the buffer.
+    pub fn end_transaction_at(
         &mut self,
         now: Instant,
         cx: &mut ViewContext<Self>,
@@ -7965,6 +8270,7 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            self.serialize_folds(cx);
             cx.notify();
         }
     }
@@ -7984,10 +8290,90 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            self.serialize_folds(cx);
             cx.notify();
         }
     }
 
+    /// Returns the absolute path of the file edited by this editor, if it edits a single local
+    /// file and was added to a serialized workspace.
+    fn serializable_path(&self, cx: &AppContext) -> Option<(WorkspaceId, PathBuf)> {
+        if self.mode != EditorMode::Full {
+            return None;
+        }
+        let (_, workspace_id) = self.workspace.as_ref()?;
+        let buffer = self.buffer.read(cx).as_singleton()?;
+        let file = buffer.read(cx).file().and_then(|file| file.as_local())?;
+        Some((*workspace_id, file.abs_path(cx)))
+    }
+
+    /// Saves the folds of a clean buffer, whose offsets then refer to the text of the file. The
+    /// folds of a dirty buffer are saved along with it.
+    fn serialize_folds(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((workspace_id, path)) = self.serializable_path(cx) else {
+            return;
+        };
+        let Some(fingerprint) = self.folds_fingerprint(cx) else {
+            return;
+        };
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
+        let folds = display_map
+            .folds_in_range(0..buffer.len())
+            .map(|fold| {
+                let range = fold.range.to_offset(buffer);
+                (range.start, range.end)
+            })
+            .collect();
+
+        cx.foreground_executor()
+            .spawn(async move {
+                DB.save_folds(workspace_id, path, fingerprint, folds)
+                    .await
+                    .log_err()
+            })
+            .detach();
+    }
+
+    /// Identifies the saved text of the edited file, if the buffer is clean.
+    fn folds_fingerprint(&self, cx: &AppContext) -> Option<FoldsFingerprint> {
+        let buffer = self.buffer.read(cx).as_singleton()?;
+        let buffer = buffer.read(cx);
+        if buffer.is_dirty() {
+            return None;
+        }
+        let mtime = buffer.saved_mtime().duration_since(UNIX_EPOCH).ok()?;
+        Some((mtime.as_secs(), mtime.subsec_nanos(), buffer.len()))
+    }
+
+    pub(crate) fn read_folds_from_db(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((workspace_id, path)) = self.serializable_path(cx) else {
+            return;
+        };
+        let folds = cx
+            .background_executor()
+            .spawn(async move { DB.get_folds(workspace_id, path) });
+        cx.spawn(|editor, mut cx| async move {
+            let folds = folds.await.log_err()?;
+            editor
+                .update(&mut cx, |editor, cx| {
+                    // The file may have been changed outside of Zed since the folds were saved.
+                    let fingerprint = editor.folds_fingerprint(cx)?;
+                    let folds = folds
+                        .into_iter()
+                        .filter(|(start, end, saved_fingerprint)| {
+                            *saved_fingerprint == fingerprint && start < end
+                        })
+                        .map(|(start, end, _)| start..end)
+                        .collect::<Vec<_>>();
+                    editor.fold_ranges(folds, false, cx);
+                    Some(())
+                })
+                .ok()?
+        })
+        .detach();
+    }
+
     pub fn set_gutter_hovered(&mut self, hovered: bool, cx: &mut ViewContext<Self>) {
         if hovered != self.gutter_hovered {
             self.gutter_hovered = hovered;
@@ -8471,6 +8857,10 @@ impl Editor {
             } => {
                 self.refresh_active_diagnostics(cx);
                 self.refresh_code_actions(cx);
+                self.refresh_semantic_tokens(false, cx);
+                self.refresh_folding_ranges(false, cx);
+                self.refresh_code_lens(false, cx);
+                self.refresh_merge_conflicts(false, cx);
                 if self.has_active_copilot_suggestion(cx) {
                     self.update_visible_copilot_suggestion(cx);
                 }
@@ -8521,22 +8911,48 @@ impl Editor {
                     excerpts: excerpts.clone(),
                 });
                 self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+                self.refresh_semantic_tokens(false, cx);
+                self.refresh_folding_ranges(false, cx);
+                self.refresh_code_lens(false, cx);
+                self.refresh_git_blame(false, cx);
+                self.refresh_merge_conflicts(true, cx);
             }
             multi_buffer::Event::ExcerptsRemoved { ids } => {
                 self.refresh_inlay_hints(InlayHintRefreshReason::ExcerptsRemoved(ids.clone()), cx);
+                self.refresh_semantic_tokens(false, cx);
+                self.refresh_folding_ranges(false, cx);
+                self.refresh_code_lens(false, cx);
+                self.refresh_git_blame(false, cx);
+                self.refresh_merge_conflicts(true, cx);
                 cx.emit(EditorEvent::ExcerptsRemoved { ids: ids.clone() })
             }
             multi_buffer::Event::Reparsed => cx.emit(EditorEvent::Reparsed),
             multi_buffer::Event::DirtyChanged => cx.emit(EditorEvent::DirtyChanged),
-            multi_buffer::Event::Saved => cx.emit(EditorEvent::Saved),
-            multi_buffer::Event::FileHandleChanged | multi_buffer::Event::Reloaded => {
+            multi_buffer::Event::Saved => {
+                // The folds are stored as offsets, which are only meaningful for the saved text.
+                self.serialize_folds(cx);
+                self.refresh_git_blame(false, cx);
+                cx.emit(EditorEvent::Saved);
+            }
+            multi_buffer::Event::FileHandleChanged => cx.emit(EditorEvent::TitleChanged),
+            multi_buffer::Event::Reloaded => {
+                self.refresh_git_blame(false, cx);
                 cx.emit(EditorEvent::TitleChanged)
             }
-            multi_buffer::Event::DiffBaseChanged => cx.emit(EditorEvent::DiffBaseChanged),
+            multi_buffer::Event::DiffBaseChanged => {
+                // The diff base changes when the repository does, e.g. after a commit.
+                self.refresh_git_blame(true, cx);
+                cx.emit(EditorEvent::DiffBaseChanged)
+            }
             multi_buffer::Event::Closed => cx.emit(EditorEvent::Closed),
             multi_buffer::Event::DiagnosticsUpdated => {
                 self.refresh_active_diagnostics(cx);
             }
+            multi_buffer::Event::LanguageChanged => {
+                self.refresh_semantic_tokens(true, cx);
+                self.refresh_folding_ranges(true, cx);
+                self.refresh_code_lens(true, cx);
+            }
             _ => {}
         };
     }
@@ -8555,6 +8971,10 @@ impl Editor {
             )),
             cx,
         );
+        self.restyle_semantic_tokens(cx);
+        self.refresh_semantic_tokens(false, cx);
+        self.refresh_code_lens(false, cx);
+        self.refresh_git_blame(false, cx);
         cx.notify();
     }
 
@@ -8947,6 +9367,7 @@ impl Editor {
             .update(cx, |buffer, cx| buffer.remove_active_selections(cx));
         self.hide_context_menu(cx);
         hide_hover(self, cx);
+        self.hide_signature_help(cx);
         cx.emit(EditorEvent::Blurred);
         cx.notify();
     }
@@ -9166,14 +9587,19 @@ impl EditorSnapshot {
             let gutter_padding = (em_width * gutter_padding_factor).round();
             // Avoid flicker-like gutter resizes when the line number gains another digit and only resize the gutter on files with N*10^5 lines.
             let min_width_for_number_on_gutter = em_width * 4.0;
-            let gutter_width =
-                max_line_number_width.max(min_width_for_number_on_gutter) + gutter_padding * 2.0;
+            let git_blame_entries_width = self
+                .show_git_blame_gutter
+                .then(|| em_width * git_blame::GIT_BLAME_GUTTER_COLUMNS as f32);
+            let gutter_width = max_line_number_width.max(min_width_for_number_on_gutter)
+                + gutter_padding * 2.0
+                + git_blame_entries_width.unwrap_or(Pixels::ZERO);
             let gutter_margin = -descent;
 
             GutterDimensions {
                 padding: gutter_padding,
                 width: gutter_width,
                 margin: gutter_margin,
+                git_blame_entries_width,
             }
         } else {
             GutterDimensions::default()
@@ -4110,6 +4110,68 @@ async fn test_select_larger_smaller_syntax_node(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_select_larger_smaller_syntax_node_with_selection_ranges(
+    cx: &mut gpui::TestAppContext,
+) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            selection_range_provider: Some(lsp::SelectionRangeProviderCapability::Simple(true)),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    cx.set_state("fn main() { let x = foo(aˇ, b); }");
+    let mut requests =
+        cx.handle_request::<lsp::request::SelectionRangeRequest, _, _>(|_, params, _| async move {
+            assert_eq!(params.positions, vec![lsp::Position::new(0, 25)]);
+            let selection_range =
+                |start, end, parent: Option<lsp::SelectionRange>| lsp::SelectionRange {
+                    range: lsp::Range::new(
+                        lsp::Position::new(0, start),
+                        lsp::Position::new(0, end),
+                    ),
+                    parent: parent.map(Box::new),
+                };
+            let call = selection_range(20, 29, None);
+            let arguments = selection_range(24, 28, Some(call));
+            Ok(Some(vec![selection_range(24, 25, Some(arguments))]))
+        });
+
+    // Expansions requested while the selection ranges are being fetched are applied once they
+    // arrive. The ranges of the language server take precedence over the syntax tree.
+    cx.update_editor(|editor, cx| {
+        editor.select_larger_syntax_node(&SelectLargerSyntaxNode, cx);
+        editor.select_larger_syntax_node(&SelectLargerSyntaxNode, cx);
+    });
+    requests.next().await;
+    cx.run_until_parked();
+    cx.assert_editor_state("fn main() { let x = foo(«a, bˇ»); }");
+
+    cx.update_editor(|editor, cx| editor.select_larger_syntax_node(&SelectLargerSyntaxNode, cx));
+    cx.assert_editor_state("fn main() { let x = «foo(a, b)ˇ»; }");
+
+    // Past the ranges of the language server, the syntax tree is used.
+    cx.update_editor(|editor, cx| editor.select_larger_syntax_node(&SelectLargerSyntaxNode, cx));
+    cx.assert_editor_state("fn main() { «let x = foo(a, b);ˇ» }");
+
+    cx.update_editor(|editor, cx| {
+        editor.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx);
+        editor.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx);
+    });
+    cx.assert_editor_state("fn main() { let x = foo(«a, bˇ»); }");
+
+    cx.update_editor(|editor, cx| {
+        editor.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx);
+        editor.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx);
+    });
+    cx.assert_editor_state("fn main() { let x = foo(aˇ, b); }");
+}
+
 #[gpui::test]
 async fn test_autoindent_selections(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -5049,6 +5111,41 @@ async fn test_snippets(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_snippet_choices_and_transforms(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer = cx.update(|cx| MultiBuffer::build_simple("", cx));
+    let (editor, cx) = cx.add_window_view(|cx| build_editor(buffer, cx));
+
+    _ = editor.update(cx, |editor, cx| {
+        let snippet =
+            Snippet::parse("let ${1:name} = ${1/(.*)/${1:/upcase}/}: ${2|i32,u8|};$0").unwrap();
+        editor.insert_snippet(&[0..0], snippet, cx).unwrap();
+        assert_eq!(editor.text(cx), "let name = NAME: i32;");
+        assert_eq!(editor.selections.ranges::<usize>(cx), &[4..8, 11..15]);
+
+        // The transform is applied when leaving its tabstop.
+        editor.handle_input("count", cx);
+        assert_eq!(editor.text(cx), "let count = count: i32;");
+        assert!(editor.move_to_next_snippet_tabstop(cx));
+        assert_eq!(editor.text(cx), "let count = COUNT: i32;");
+        assert_eq!(editor.selections.ranges::<usize>(cx), &[19..22]);
+
+        // The choices of the tabstop are offered in the completions menu.
+        if let Some(ContextMenu::Completions(menu)) = editor.context_menu.read().as_ref() {
+            assert_eq!(
+                menu.matches.iter().map(|m| &m.string).collect::<Vec<_>>(),
+                &["i32", "u8"]
+            );
+        } else {
+            panic!("expected completion menu to be open");
+        }
+        editor.confirm_completion(&ConfirmCompletion { item_ix: Some(1) }, cx);
+        assert_eq!(editor.text(cx), "let count = COUNT: u8;");
+    });
+}
+
 #[gpui::test]
 async fn test_document_format_during_save(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -5558,6 +5655,120 @@ async fn test_strip_whitespace_and_format_via_lsp(cx: &mut gpui::TestAppContext)
     );
 }
 
+#[gpui::test]
+async fn test_user_snippet_completions(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            completion_provider: Some(lsp::CompletionOptions::default()),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+    cx.update(|cx| {
+        cx.set_global(UserSnippets::parse(&[(
+            PathBuf::from("/snippets/rust.json"),
+            r#"{
+                "Print": { "prefix": "pri", "body": "println!(\"$1\");$0" },
+                "Todo": { "prefix": "prtodo", "body": "TODO: $0", "zed_overrides": "comment" }
+            }"#
+            .to_string(),
+        )]))
+    });
+
+    cx.set_state("fn main() {\n    prˇ\n}");
+    cx.update_editor(|editor, cx| editor.show_completions(&ShowCompletions, cx));
+    handle_completion_request(&mut cx, "fn main() {\n    pr|<>\n}", vec!["process"]).await;
+    cx.condition(|editor, _| editor.context_menu_visible())
+        .await;
+
+    // Snippets scoped to comments aren't offered outside of them.
+    let snippet_ix = cx.update_editor(|editor, _| {
+        if let Some(ContextMenu::Completions(menu)) = editor.context_menu.read().as_ref() {
+            let mut labels = menu
+                .matches
+                .iter()
+                .map(|m| m.string.as_str())
+                .collect::<Vec<_>>();
+            let snippet_ix = labels.iter().position(|label| *label == "pri").unwrap();
+            labels.sort_unstable();
+            assert_eq!(labels, &["pri", "process"]);
+            snippet_ix
+        } else {
+            panic!("expected completion menu to be open");
+        }
+    });
+
+    cx.update_editor(|editor, cx| {
+        editor.confirm_completion(
+            &ConfirmCompletion {
+                item_ix: Some(snippet_ix),
+            },
+            cx,
+        )
+    });
+    cx.assert_editor_state("fn main() {\n    println!(\"ˇ\");\n}");
+}
+
+#[gpui::test]
+async fn test_user_snippet_completions_when_completions_fail(cx: &mut gpui::TestAppContext) {
+    struct FailingCompletionProvider;
+
+    impl CompletionProvider for FailingCompletionProvider {
+        fn completions(
+            &self,
+            _: &Model<Buffer>,
+            _: text::Anchor,
+            _: &mut ViewContext<Editor>,
+        ) -> Task<Result<Vec<Completion>>> {
+            Task::ready(Err(anyhow!("the language server crashed")))
+        }
+
+        fn resolve_completions(
+            &self,
+            _: Vec<usize>,
+            _: Arc<RwLock<Box<[Completion]>>>,
+            _: &mut ViewContext<Editor>,
+        ) -> Task<Result<bool>> {
+            Task::ready(Ok(false))
+        }
+
+        fn apply_additional_edits_for_completion(
+            &self,
+            _: Model<Buffer>,
+            _: Completion,
+            _: bool,
+            _: &mut ViewContext<Editor>,
+        ) -> Task<Result<Option<language::Transaction>>> {
+            Task::ready(Ok(None))
+        }
+    }
+
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(Default::default(), cx).await;
+    cx.update(|cx| {
+        cx.set_global(UserSnippets::parse(&[(
+            PathBuf::from("/snippets/rust.json"),
+            r#"{ "Print": { "prefix": "pri", "body": "println!(\"$1\");$0" } }"#.to_string(),
+        )]))
+    });
+    cx.update_editor(|editor, _| {
+        editor.set_completion_provider(Box::new(FailingCompletionProvider))
+    });
+
+    cx.set_state("fn main() {\n    prˇ\n}");
+    cx.update_editor(|editor, cx| editor.show_completions(&ShowCompletions, cx));
+    cx.condition(|editor, _| editor.context_menu_visible())
+        .await;
+    cx.update_editor(|editor, cx| {
+        editor.confirm_completion(&ConfirmCompletion { item_ix: Some(0) }, cx)
+    });
+    cx.assert_editor_state("fn main() {\n    println!(\"ˇ\");\n}");
+}
+
 #[gpui::test]
 async fn test_completion(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -7173,6 +7384,51 @@ async fn test_move_to_enclosing_bracket(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_go_to_implementation(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            implementation_provider: Some(lsp::ImplementationProviderCapability::Simple(true)),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    cx.set_state(indoc! {"
+        trait ˇA {}
+        struct B;
+        impl A for B {}
+    "});
+    let target_range = cx.lsp_range(indoc! {"
+        trait A {}
+        struct B;
+        «impl A for B {}»
+    "});
+    let mut requests = cx.handle_request::<lsp::request::GotoImplementation, _, _>(
+        move |url, params, _| async move {
+            assert_eq!(
+                params.text_document_position_params.position,
+                lsp::Position::new(0, 6)
+            );
+            Ok(Some(lsp::GotoImplementationResponse::Scalar(
+                lsp::Location::new(url, target_range),
+            )))
+        },
+    );
+
+    cx.update_editor(|editor, cx| editor.go_to_implementation(&GoToImplementation, cx));
+    requests.next().await;
+    cx.run_until_parked();
+    cx.assert_editor_state(indoc! {"
+        trait A {}
+        struct B;
+        «impl A for B {}ˇ»
+    "});
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext) {
     // flaky
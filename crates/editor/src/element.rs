@@ -5,6 +5,9 @@ use crate::{
     },
     editor_settings::ShowScrollbar,
     git::{diff_hunk_to_display, DisplayDiffHunk},
+    git_blame::{
+        gutter_blame_text, inline_blame_enabled, inline_blame_text, INLINE_BLAME_PADDING_EM_WIDTHS,
+    },
     hover_popover::{
         self, hover_at, HOVER_POPOVER_GAP, MIN_POPOVER_CHARACTER_WIDTH, MIN_POPOVER_LINE_HEIGHT,
     },
@@ -48,6 +51,7 @@ use std::{
     fmt::Write,
     iter,
     ops::Range,
+    ptr,
     sync::Arc,
 };
 use sum_tree::Bias;
@@ -257,10 +261,25 @@ impl EditorElement {
         register_action(view, cx, Editor::go_to_prev_diagnostic);
         register_action(view, cx, Editor::go_to_hunk);
         register_action(view, cx, Editor::go_to_prev_hunk);
+        register_action(view, cx, Editor::stage_hunk);
+        register_action(view, cx, Editor::unstage_hunk);
+        register_action(view, cx, Editor::revert_hunk);
+        register_action(view, cx, Editor::go_to_conflict);
+        register_action(view, cx, Editor::go_to_prev_conflict);
+        register_action(view, cx, Editor::accept_ours);
+        register_action(view, cx, Editor::accept_theirs);
+        register_action(view, cx, Editor::accept_both);
+        register_action(view, cx, Editor::accept_neither);
         register_action(view, cx, Editor::go_to_definition);
         register_action(view, cx, Editor::go_to_definition_split);
         register_action(view, cx, Editor::go_to_type_definition);
         register_action(view, cx, Editor::go_to_type_definition_split);
+        register_action(view, cx, Editor::go_to_implementation);
+        register_action(view, cx, Editor::go_to_implementation_split);
+        register_action(view, cx, Editor::show_signature_help);
+        register_action(view, cx, crate::call_hierarchy::show_incoming_calls);
+        register_action(view, cx, crate::call_hierarchy::show_outgoing_calls);
+        register_action(view, cx, crate::call_hierarchy::toggle_call_hierarchy_item);
         register_action(view, cx, Editor::fold);
         register_action(view, cx, Editor::fold_at);
         register_action(view, cx, Editor::unfold_lines);
@@ -271,6 +290,7 @@ impl EditorElement {
         register_action(view, cx, Editor::open_excerpts);
         register_action(view, cx, Editor::toggle_soft_wrap);
         register_action(view, cx, Editor::toggle_inlay_hints);
+        register_action(view, cx, Editor::toggle_git_blame);
         register_action(view, cx, hover_popover::hover);
         register_action(view, cx, Editor::reveal_in_finder);
         register_action(view, cx, Editor::copy_path);
@@ -435,6 +455,43 @@ impl EditorElement {
         cx.stop_propagation();
     }
 
+    /// Shows the actions for a diff hunk when clicking its indicator in the gutter, returning
+    /// whether one was clicked.
+    fn mouse_left_down_on_diff_hunk(
+        editor: &mut Editor,
+        event: &MouseDownEvent,
+        position_map: &PositionMap,
+        gutter_bounds: Bounds<Pixels>,
+        display_hunks: &[DisplayDiffHunk],
+        diff_hunks_x: Pixels,
+        stacking_order: &StackingOrder,
+        cx: &mut ViewContext<Editor>,
+    ) -> bool {
+        let line_height = position_map.line_height;
+        let hunk_width = 0.275 * line_height;
+        if cx.default_prevented()
+            || !gutter_bounds.contains(&event.position)
+            || (event.position.x - diff_hunks_x).abs() > hunk_width
+            || !cx.was_top_layer(&event.position, stacking_order)
+        {
+            return false;
+        }
+
+        let scroll_top = position_map.snapshot.scroll_position().y * line_height;
+        let row = ((event.position.y - gutter_bounds.origin.y + scroll_top) / line_height) as u32;
+        let Some(hunk) = display_hunks
+            .iter()
+            .find(|hunk| hunk.contains_display_row(row))
+        else {
+            return false;
+        };
+
+        let point = DisplayPoint::new(hunk.start_display_row(), 0);
+        mouse_context_menu::deploy_git_hunk_context_menu(editor, event.position, point, cx);
+        cx.stop_propagation();
+        true
+    }
+
     fn mouse_right_down(
         editor: &mut Editor,
         event: &MouseDownEvent,
@@ -674,6 +731,19 @@ impl EditorElement {
                 }
             }
 
+            for (rows, color) in &layout.merge_conflict_rows {
+                let origin = point(
+                    bounds.origin.x,
+                    bounds.origin.y + (layout.position_map.line_height * rows.start as f32)
+                        - scroll_top,
+                );
+                let size = size(
+                    bounds.size.width,
+                    layout.position_map.line_height * rows.len() as f32,
+                );
+                cx.paint_quad(fill(Bounds { origin, size }, *color));
+            }
+
             if let Some(highlighted_rows) = &layout.highlighted_rows {
                 let origin = point(
                     bounds.origin.x,
@@ -750,6 +820,18 @@ impl EditorElement {
             }
         }
 
+        for (ix, entry) in layout.git_blame_entries.iter().enumerate() {
+            if let Some(entry) = entry {
+                let entry_origin = bounds.origin
+                    + point(
+                        layout.position_map.em_width,
+                        ix as f32 * line_height - (scroll_top % line_height),
+                    );
+
+                entry.paint(entry_origin, line_height, cx).log_err();
+            }
+        }
+
         cx.with_z_index(1, |cx| {
             for (ix, fold_indicator) in layout.fold_indicators.drain(..).enumerate() {
                 if let Some(fold_indicator) = fold_indicator {
@@ -782,7 +864,7 @@ impl EditorElement {
                 );
                 let indicator_size = button.measure(available_space, cx);
 
-                let mut x = Pixels::ZERO;
+                let mut x = layout.git_blame_entries_width;
                 let mut y = indicator.row as f32 * line_height - scroll_top;
                 // Center indicator.
                 x += ((layout.gutter_padding + layout.gutter_margin) - indicator_size.width) / 2.;
@@ -807,7 +889,8 @@ impl EditorElement {
                     let end_y = start_y + line_height;
 
                     let width = 0.275 * line_height;
-                    let highlight_origin = bounds.origin + point(-width, start_y);
+                    let highlight_origin =
+                        bounds.origin + point(layout.git_blame_entries_width - width, start_y);
                     let highlight_size = size(width * 2., end_y - start_y);
                     let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
                     cx.paint_quad(quad(
@@ -840,7 +923,8 @@ impl EditorElement {
                     let end_y = start_y + line_height;
 
                     let width = 0.275 * line_height;
-                    let highlight_origin = bounds.origin + point(-width, start_y);
+                    let highlight_origin =
+                        bounds.origin + point(layout.git_blame_entries_width - width, start_y);
                     let highlight_size = size(width * 2., end_y - start_y);
                     let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
                     cx.paint_quad(quad(
@@ -880,7 +964,8 @@ impl EditorElement {
             let end_y = end_row_in_current_excerpt as f32 * line_height - scroll_top;
 
             let width = 0.275 * line_height;
-            let highlight_origin = bounds.origin + point(-width, start_y);
+            let highlight_origin =
+                bounds.origin + point(layout.git_blame_entries_width - width, start_y);
             let highlight_size = size(width * 2., end_y - start_y);
             let highlight_bounds = Bounds::new(highlight_origin, highlight_size);
             cx.paint_quad(quad(
@@ -1151,6 +1236,22 @@ impl EditorElement {
                     )
                 }
 
+                if let Some((row, blame)) = &layout.inline_blame {
+                    let line_layout =
+                        &layout.position_map.line_layouts[(row - start_row) as usize].line;
+                    let origin = point(
+                        content_origin.x
+                            + line_layout.width
+                            + INLINE_BLAME_PADDING_EM_WIDTHS * layout.position_map.em_width
+                            - layout.position_map.scroll_position.x,
+                        content_origin.y + *row as f32 * layout.position_map.line_height
+                            - layout.position_map.scroll_position.y,
+                    );
+                    blame
+                        .paint(origin, layout.position_map.line_height, cx)
+                        .log_err();
+                }
+
                 cx.with_z_index(0, |cx| {
                     for cursor in cursors {
                         cursor.paint(content_origin, cx);
@@ -1195,6 +1296,31 @@ impl EditorElement {
             cx.break_content_mask(|cx| context_menu.draw(list_origin, available_space, cx));
         }
 
+        if let Some((position, mut signature_help)) = layout.signature_help.take() {
+            let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
+            let popover_size = signature_help.measure(available_space, cx);
+
+            let cursor_row_layout =
+                &layout.position_map.line_layouts[(position.row() - start_row) as usize].line;
+            let x = cursor_row_layout.x_for_index(position.column() as usize)
+                - layout.position_map.scroll_position.x;
+            let y = position.row() as f32 * layout.position_map.line_height
+                - layout.position_map.scroll_position.y;
+            let mut popover_origin = content_origin + point(x, y - popover_size.height);
+
+            // Render the popover above the cursor, unless that would overflow the editor, in which
+            // case it is rendered below the cursor line.
+            if popover_origin.y < text_bounds.origin.y {
+                popover_origin.y = content_origin.y + y + layout.position_map.line_height;
+            }
+            if popover_origin.x + popover_size.width > cx.viewport_size().width {
+                popover_origin.x =
+                    (cx.viewport_size().width - popover_size.width).max(Pixels::ZERO);
+            }
+
+            cx.break_content_mask(|cx| signature_help.draw(popover_origin, available_space, cx));
+        }
+
         if let Some((position, mut hover_popovers)) = layout.hover_popovers.take() {
             let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
 
@@ -1643,6 +1769,102 @@ impl EditorElement {
             .collect()
     }
 
+    /// Shapes the commit that last changed each visible line, omitting the lines that were
+    /// changed by the same commit as the line above them.
+    fn layout_git_blame_entries(
+        &self,
+        rows: Range<u32>,
+        editor: &Editor,
+        snapshot: &EditorSnapshot,
+        cx: &ViewContext<Editor>,
+    ) -> Vec<Option<ShapedLine>> {
+        if !snapshot.show_git_blame_gutter {
+            return Vec::new();
+        }
+
+        let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+        let color = cx.theme().colors().editor_line_number;
+        let timezone = cx.local_timezone();
+        let mut previous_hunk = None;
+        let mut entries = Vec::with_capacity(rows.len());
+        for (ix, buffer_row) in snapshot
+            .buffer_rows(rows.start)
+            .take(rows.len())
+            .enumerate()
+        {
+            // Soft-wrapped rows continue the line above them.
+            if buffer_row.is_none() {
+                entries.push(None);
+                continue;
+            }
+
+            let row = DisplayPoint::new(rows.start + ix as u32, 0)
+                .to_point(snapshot)
+                .row;
+            let hunk = editor.blame_hunk_for_row(row, &snapshot.buffer_snapshot);
+            let is_new_hunk = match (hunk, previous_hunk) {
+                (Some(hunk), Some(previous_hunk)) => !ptr::eq(hunk, previous_hunk),
+                (hunk, _) => hunk.is_some(),
+            };
+            previous_hunk = hunk;
+            if !is_new_hunk {
+                entries.push(None);
+                continue;
+            }
+
+            let text = gutter_blame_text(hunk.unwrap(), timezone);
+            let run = TextRun {
+                len: text.len(),
+                font: self.style.text.font(),
+                color,
+                background_color: None,
+                underline: None,
+            };
+            entries.push(
+                cx.text_system()
+                    .shape_line(text.into(), font_size, &[run])
+                    .log_err(),
+            );
+        }
+        entries
+    }
+
+    /// Shapes the commit that last changed the line of the newest cursor, to be shown at the
+    /// end of that line.
+    fn layout_inline_blame(
+        &self,
+        newest_selection_head: DisplayPoint,
+        rows: Range<u32>,
+        editor: &Editor,
+        snapshot: &EditorSnapshot,
+        cx: &ViewContext<Editor>,
+    ) -> Option<(u32, ShapedLine)> {
+        if snapshot.mode != EditorMode::Full
+            || !snapshot.is_focused
+            || !inline_blame_enabled(cx)
+            || !rows.contains(&newest_selection_head.row())
+        {
+            return None;
+        }
+
+        let row = newest_selection_head.to_point(snapshot).row;
+        let hunk = editor.blame_hunk_for_row(row, &snapshot.buffer_snapshot)?;
+        let text = inline_blame_text(hunk, cx.local_timezone());
+        let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+        let run = TextRun {
+            len: text.len(),
+            font: self.style.text.font(),
+            color: cx.theme().colors().editor_line_number,
+            background_color: None,
+            underline: None,
+        };
+        let line = cx
+            .text_system()
+            .shape_line(text.into(), font_size, &[run])
+            .log_err()?;
+        Some((newest_selection_head.row(), line))
+    }
+
     fn calculate_relative_line_numbers(
         &self,
         snapshot: &EditorSnapshot,
@@ -1719,11 +1941,19 @@ impl EditorElement {
 
         let relative_rows = self.calculate_relative_line_numbers(&snapshot, &rows, relative_to);
 
-        for (ix, row) in snapshot
+        let buffer_rows = snapshot
             .buffer_rows(rows.start)
             .take((rows.end - rows.start) as usize)
-            .enumerate()
-        {
+            .collect::<Vec<_>>();
+        let fold_statuses_by_row = if is_singleton {
+            let first_row = buffer_rows.iter().flatten().min().copied().unwrap_or(0);
+            let last_row = buffer_rows.iter().flatten().max().copied().unwrap_or(0);
+            snapshot.fold_statuses_for_rows(first_row..last_row + 1)
+        } else {
+            Default::default()
+        };
+
+        for (ix, row) in buffer_rows.into_iter().enumerate() {
             let display_row = rows.start + ix as u32;
             let (active, color) = if active_rows.contains_key(&display_row) {
                 (true, cx.theme().colors().editor_active_line_number)
@@ -1751,13 +1981,9 @@ impl EditorElement {
                         .unwrap();
                     shaped_line_numbers.push(Some(shaped_line));
                     fold_statuses.push(
-                        is_singleton
-                            .then(|| {
-                                snapshot
-                                    .fold_for_line(buffer_row)
-                                    .map(|fold_status| (fold_status, buffer_row, active))
-                            })
-                            .flatten(),
+                        fold_statuses_by_row
+                            .get(&buffer_row)
+                            .map(|fold_status| (*fold_status, buffer_row, active)),
                     )
                 }
             } else {
@@ -1911,6 +2137,7 @@ impl EditorElement {
             let is_singleton = editor.is_singleton(cx);
 
             let highlighted_rows = editor.highlighted_rows();
+            let merge_conflict_rows = editor.merge_conflict_rows(start_row..end_row, &snapshot, cx);
             let highlighted_ranges = editor.background_highlights_in_range(
                 start_anchor..end_anchor,
                 &snapshot.display_snapshot,
@@ -2064,6 +2291,15 @@ impl EditorElement {
             );
 
             let display_hunks = self.layout_git_gutters(start_row..end_row, &snapshot);
+            let git_blame_entries =
+                self.layout_git_blame_entries(start_row..end_row, editor, &snapshot, cx);
+            let inline_blame = self.layout_inline_blame(
+                head_for_relative,
+                start_row..end_row,
+                editor,
+                &snapshot,
+                cx,
+            );
 
             let scrollbar_row_range = scroll_position.y..(scroll_position.y + height_in_lines);
 
@@ -2167,6 +2403,16 @@ impl EditorElement {
                     .max(MIN_POPOVER_LINE_HEIGHT * line_height), // Apply minimum height of 4 lines
             );
 
+            let signature_help = newest_selection_head
+                .filter(|head| (start_row..end_row).contains(&head.row()))
+                .and_then(|head| {
+                    let workspace = editor.workspace.as_ref().map(|(w, _)| w.clone());
+                    editor
+                        .signature_help_state
+                        .render(&style, max_size, workspace, cx)
+                        .map(|element| (head, element))
+                });
+
             let hover = if context_menu.is_some() {
                 None
             } else {
@@ -2251,11 +2497,17 @@ impl EditorElement {
                 is_singleton,
                 max_row,
                 gutter_margin: gutter_dimensions.margin,
+                git_blame_entries_width: gutter_dimensions
+                    .git_blame_entries_width
+                    .unwrap_or(Pixels::ZERO),
                 active_rows,
                 highlighted_rows,
+                merge_conflict_rows,
                 highlighted_ranges,
                 line_numbers,
                 display_hunks,
+                git_blame_entries,
+                inline_blame,
                 blocks,
                 selections,
                 context_menu,
@@ -2264,6 +2516,7 @@ impl EditorElement {
                 tab_invisible,
                 space_invisible,
                 hover_popovers: hover,
+                signature_help,
             }
         })
     }
@@ -2592,6 +2845,8 @@ impl EditorElement {
             let editor = self.editor.clone();
             let stacking_order = cx.stacking_order().clone();
             let interactive_bounds = interactive_bounds.clone();
+            let display_hunks = layout.display_hunks.clone();
+            let diff_hunks_x = gutter_bounds.origin.x + layout.git_blame_entries_width;
 
             move |event: &MouseDownEvent, phase, cx| {
                 if phase == DispatchPhase::Bubble
@@ -2599,6 +2854,18 @@ impl EditorElement {
                 {
                     match event.button {
                         MouseButton::Left => editor.update(cx, |editor, cx| {
+                            if Self::mouse_left_down_on_diff_hunk(
+                                editor,
+                                event,
+                                &position_map,
+                                gutter_bounds,
+                                &display_hunks,
+                                diff_hunks_x,
+                                &stacking_order,
+                                cx,
+                            ) {
+                                return;
+                            }
                             Self::mouse_left_down(
                                 editor,
                                 event,
@@ -3027,6 +3294,7 @@ pub struct LayoutState {
     gutter_size: Size<Pixels>,
     gutter_padding: Pixels,
     gutter_margin: Pixels,
+    git_blame_entries_width: Pixels,
     text_size: gpui::Size<Pixels>,
     mode: EditorMode,
     wrap_guides: SmallVec<[(Pixels, bool); 2]>,
@@ -3034,8 +3302,11 @@ pub struct LayoutState {
     visible_display_row_range: Range<u32>,
     active_rows: BTreeMap<u32, bool>,
     highlighted_rows: Option<Range<u32>>,
+    merge_conflict_rows: Vec<(Range<u32>, Hsla)>,
     line_numbers: Vec<Option<ShapedLine>>,
     display_hunks: Vec<DisplayDiffHunk>,
+    git_blame_entries: Vec<Option<ShapedLine>>,
+    inline_blame: Option<(u32, ShapedLine)>,
     blocks: Vec<BlockLayout>,
     highlighted_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     selections: Vec<(PlayerColor, Vec<SelectionLayout>)>,
@@ -3046,6 +3317,7 @@ pub struct LayoutState {
     context_menu: Option<(DisplayPoint, AnyElement)>,
     code_actions_indicator: Option<CodeActionsIndicator>,
     hover_popovers: Option<(DisplayPoint, Vec<AnyElement>)>,
+    signature_help: Option<(DisplayPoint, AnyElement)>,
     fold_indicators: Vec<Option<IconButton>>,
     tab_invisible: ShapedLine,
     space_invisible: ShapedLine,
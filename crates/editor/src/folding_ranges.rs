@@ -0,0 +1,147 @@
+use crate::{Editor, EditorMode};
+use collections::{HashMap, HashSet};
+use gpui::{Task, ViewContext};
+use language::Anchor;
+use std::{ops::Range, time::Duration};
+use util::ResultExt;
+
+/// How long to wait after an edit before fetching the folding ranges again.
+const FOLDING_RANGES_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Default)]
+pub(crate) struct FoldingRangesState {
+    buffers: HashMap<u64, BufferFoldingRanges>,
+    refresh_task: Option<Task<Option<()>>>,
+}
+
+struct BufferFoldingRanges {
+    version: clock::Global,
+    ranges: Vec<Range<Anchor>>,
+}
+
+impl Editor {
+    /// Fetches the folding ranges of the buffers that changed since their ranges were last
+    /// fetched, or of all buffers when `invalidate` is set. Buffers whose language server
+    /// doesn't report any folding range are folded based on their syntax tree and indentation.
+    pub(crate) fn refresh_folding_ranges(&mut self, invalidate: bool, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+
+        let mut buffers_to_fetch = Vec::new();
+        let mut buffers_to_update = Vec::new();
+        let mut buffers_to_clear = self
+            .folding_ranges
+            .buffers
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        for buffer in self.buffer.read(cx).all_buffers() {
+            let buffer_ref = buffer.read(cx);
+            let buffer_id = buffer_ref.remote_id();
+            buffers_to_clear.remove(&buffer_id);
+            let is_up_to_date = !invalidate
+                && self
+                    .folding_ranges
+                    .buffers
+                    .get(&buffer_id)
+                    .map_or(false, |fetched| {
+                        !buffer_ref.version().changed_since(&fetched.version)
+                    });
+            if is_up_to_date {
+                buffers_to_update.push(buffer_id);
+            } else {
+                buffers_to_fetch.push(buffer);
+            }
+        }
+        for buffer_id in buffers_to_clear {
+            self.folding_ranges.buffers.remove(&buffer_id);
+            self.display_map
+                .update(cx, |map, _| map.clear_folding_ranges(buffer_id));
+        }
+        // The excerpts of the up-to-date buffers may have changed, so their ranges are mapped
+        // into the multibuffer again.
+        for buffer_id in buffers_to_update {
+            self.update_folding_ranges(buffer_id, cx);
+        }
+        if buffers_to_fetch.is_empty() {
+            return;
+        }
+
+        self.folding_ranges.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(FOLDING_RANGES_DEBOUNCE_TIMEOUT)
+                .await;
+            let requests = project
+                .update(&mut cx, |project, cx| {
+                    buffers_to_fetch
+                        .iter()
+                        .map(|buffer| {
+                            let buffer_id = buffer.read(cx).remote_id();
+                            let version = buffer.read(cx).version();
+                            (buffer_id, version, project.folding_ranges(buffer, cx))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+            for (buffer_id, version, request) in requests {
+                let Some(ranges) = request.await.log_err() else {
+                    continue;
+                };
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        editor
+                            .folding_ranges
+                            .buffers
+                            .insert(buffer_id, BufferFoldingRanges { version, ranges });
+                        editor.update_folding_ranges(buffer_id, cx);
+                    })
+                    .ok()?;
+            }
+            Some(())
+        }));
+    }
+
+    fn update_folding_ranges(&mut self, buffer_id: u64, cx: &mut ViewContext<Self>) {
+        let multibuffer = self.buffer.read(cx);
+        let (Some(fetched), Some(buffer)) = (
+            self.folding_ranges.buffers.get(&buffer_id),
+            multibuffer.buffer(buffer_id),
+        ) else {
+            return;
+        };
+
+        if fetched.ranges.is_empty() {
+            self.display_map
+                .update(cx, |map, _| map.clear_folding_ranges(buffer_id));
+            cx.notify();
+            return;
+        }
+
+        let snapshot = multibuffer.snapshot(cx);
+        let buffer_snapshot = buffer.read(cx).text_snapshot();
+        let mut ranges = Vec::new();
+        for (excerpt_id, excerpt_range) in multibuffer.excerpts_for_buffer(&buffer, cx) {
+            let context = excerpt_range.context;
+            for range in &fetched.ranges {
+                // Folds spilling out of an excerpt would hide the excerpts after it.
+                if range.start.cmp(&context.start, &buffer_snapshot).is_lt()
+                    || range.end.cmp(&context.end, &buffer_snapshot).is_gt()
+                {
+                    continue;
+                }
+                let start = snapshot.anchor_in_excerpt(excerpt_id, range.start);
+                let end = snapshot.anchor_in_excerpt(excerpt_id, range.end);
+                ranges.push(start..end);
+            }
+        }
+        ranges.sort_by(|a, b| a.start.cmp(&b.start, &snapshot));
+
+        self.display_map
+            .update(cx, |map, _| map.set_folding_ranges(buffer_id, ranges));
+        cx.notify();
+    }
+}
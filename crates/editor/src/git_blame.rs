@@ -0,0 +1,317 @@
+use crate::{Editor, EditorMode, MultiBufferSnapshot, ToggleGitBlame};
+use collections::{HashMap, HashSet};
+use gpui::{AppContext, Task, ViewContext};
+use language::{Point, ToPoint};
+use project::{
+    project_settings::ProjectSettings,
+    repository::{format_commit_date, short_sha},
+    BlameHunk,
+};
+use settings::Settings;
+use std::time::Duration;
+use time::UtcOffset;
+use util::ResultExt;
+
+/// How long to wait after a buffer is saved or reloaded before blaming it again.
+const GIT_BLAME_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How many columns the blame of each line takes in the gutter.
+pub(crate) const GIT_BLAME_GUTTER_COLUMNS: usize = 32;
+
+/// How far the blame of the cursor's line is shown from the end of that line.
+pub(crate) const INLINE_BLAME_PADDING_EM_WIDTHS: f32 = 4.;
+
+#[derive(Default)]
+pub(crate) struct GitBlameState {
+    /// Whether the commit that last changed each line is shown in the gutter.
+    pub(crate) show_in_gutter: bool,
+    buffers: HashMap<u64, BufferBlame>,
+    refresh_task: Option<Task<Option<()>>>,
+}
+
+struct BufferBlame {
+    version: clock::Global,
+    /// The hunks of the buffer, sorted by their position, which are empty when the buffer
+    /// couldn't be blamed, so that it isn't blamed again until it changes.
+    hunks: Vec<BlameHunk>,
+}
+
+impl Editor {
+    pub fn toggle_git_blame(&mut self, _: &ToggleGitBlame, cx: &mut ViewContext<Self>) {
+        self.git_blame.show_in_gutter = !self.git_blame.show_in_gutter;
+        self.refresh_git_blame(false, cx);
+        cx.notify();
+    }
+
+    /// Blames the buffer if it changed since it was last blamed, or unconditionally when
+    /// `invalidate` is set. Only editors of a single buffer are blamed.
+    pub(crate) fn refresh_git_blame(&mut self, invalidate: bool, cx: &mut ViewContext<Self>) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        if self.mode != EditorMode::Full
+            || !self.buffer.read(cx).is_singleton()
+            || !(self.git_blame.show_in_gutter || inline_blame_enabled(cx))
+        {
+            self.git_blame.buffers.clear();
+            self.git_blame.refresh_task = None;
+            return;
+        }
+
+        let mut buffers_to_blame = Vec::new();
+        let mut buffers_to_clear = self
+            .git_blame
+            .buffers
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        for buffer in self.buffer.read(cx).all_buffers() {
+            let buffer_ref = buffer.read(cx);
+            let buffer_id = buffer_ref.remote_id();
+            buffers_to_clear.remove(&buffer_id);
+            let is_up_to_date = !invalidate
+                && self
+                    .git_blame
+                    .buffers
+                    .get(&buffer_id)
+                    .map_or(false, |blame| {
+                        !buffer_ref.version().changed_since(&blame.version)
+                    });
+            if !is_up_to_date {
+                buffers_to_blame.push(buffer);
+            }
+        }
+        for buffer_id in buffers_to_clear {
+            self.git_blame.buffers.remove(&buffer_id);
+        }
+        if buffers_to_blame.is_empty() {
+            return;
+        }
+
+        self.git_blame.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(GIT_BLAME_DEBOUNCE_TIMEOUT)
+                .await;
+            let requests = project
+                .update(&mut cx, |project, cx| {
+                    buffers_to_blame
+                        .iter()
+                        .map(|buffer| {
+                            let buffer_id = buffer.read(cx).remote_id();
+                            let version = buffer.read(cx).version();
+                            (buffer_id, version, project.blame_buffer(buffer, cx))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+            for (buffer_id, version, request) in requests {
+                // Buffers that failed to be blamed, such as files outside of a repository, are
+                // remembered without hunks.
+                let hunks = request.await.log_err().unwrap_or_default();
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        editor
+                            .git_blame
+                            .buffers
+                            .insert(buffer_id, BufferBlame { version, hunks });
+                        cx.notify();
+                    })
+                    .ok()?;
+            }
+            Some(())
+        }));
+    }
+
+    /// Returns the hunk of the commit that last changed the given row of the multibuffer.
+    pub(crate) fn blame_hunk_for_row(
+        &self,
+        row: u32,
+        snapshot: &MultiBufferSnapshot,
+    ) -> Option<&BlameHunk> {
+        let (buffer, range) = snapshot.buffer_line_for_row(row)?;
+        let blame = self.git_blame.buffers.get(&buffer.remote_id())?;
+        let position = Point::new(range.start.row, 0);
+        let ix = blame
+            .hunks
+            .partition_point(|hunk| hunk.range.end.to_point(buffer) <= position);
+        let hunk = blame.hunks.get(ix)?;
+        (hunk.range.start.to_point(buffer) <= position).then_some(hunk)
+    }
+}
+
+pub(crate) fn inline_blame_enabled(cx: &AppContext) -> bool {
+    ProjectSettings::get_global(cx)
+        .git
+        .inline_blame
+        .unwrap_or(false)
+}
+
+/// The text shown in the gutter for the lines of the given hunk, with its date in the given
+/// timezone.
+pub(crate) fn gutter_blame_text(hunk: &BlameHunk, timezone: UtcOffset) -> String {
+    if hunk.sha.is_none() {
+        return "Not committed yet".into();
+    }
+    let date = format_commit_date(hunk.unix_timestamp, timezone);
+    let max_author_len = GIT_BLAME_GUTTER_COLUMNS - date.len() - 4;
+    let author = if hunk.author.chars().count() > max_author_len {
+        let mut author = hunk
+            .author
+            .chars()
+            .take(max_author_len - 1)
+            .collect::<String>();
+        author.push('…');
+        author
+    } else {
+        hunk.author.clone()
+    };
+    format!("{date} {author}")
+}
+
+/// The text shown at the end of the cursor's line when it was last changed by the given hunk,
+/// with its date in the given timezone.
+pub(crate) fn inline_blame_text(hunk: &BlameHunk, timezone: UtcOffset) -> String {
+    match &hunk.sha {
+        Some(sha) => format!(
+            "{}, {} • {} {}",
+            hunk.author,
+            format_commit_date(hunk.unix_timestamp, timezone),
+            short_sha(sha),
+            hunk.summary
+        ),
+        None => "You, not committed yet".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, ExcerptRange, MultiBuffer};
+    use gpui::{Context, TestAppContext};
+    use language::Capability;
+    use project::{repository::BlameEntry, FakeFs, Project};
+    use serde_json::json;
+    use settings::SettingsStore;
+    use std::path::Path;
+
+    #[gpui::test]
+    async fn test_git_blame(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                ".git": {},
+                "a.txt": "one\ntwo\nthree\n",
+            }),
+        )
+        .await;
+        fs.insert_tree("/other", json!({ "b.txt": "four\n" })).await;
+        fs.set_blame_for_repo(
+            Path::new("/dir/.git"),
+            vec![(
+                Path::new("a.txt"),
+                vec![
+                    BlameEntry {
+                        sha: Some("1111111".into()),
+                        rows: 0..2,
+                        author: "alice".into(),
+                        author_email: "alice@example.com".into(),
+                        unix_timestamp: 1_700_000_000,
+                        summary: "Add one and two".into(),
+                    },
+                    BlameEntry {
+                        sha: None,
+                        rows: 2..3,
+                        author: String::new(),
+                        author_email: String::new(),
+                        unix_timestamp: 0,
+                        summary: String::new(),
+                    },
+                ],
+            )],
+        );
+
+        let project = Project::test(fs, ["/dir".as_ref(), "/other".as_ref()], cx).await;
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings::<ProjectSettings>(cx, |settings| {
+                    settings.git.inline_blame = Some(true);
+                });
+            });
+        });
+        let buffer_a = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/dir/a.txt", cx)
+            })
+            .await
+            .unwrap();
+        let buffer_b = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/other/b.txt", cx)
+            })
+            .await
+            .unwrap();
+        let editor_a =
+            cx.add_window(|cx| Editor::for_buffer(buffer_a.clone(), Some(project.clone()), cx));
+        let editor_b =
+            cx.add_window(|cx| Editor::for_buffer(buffer_b.clone(), Some(project.clone()), cx));
+        let multibuffer = cx.new_model(|cx| {
+            let mut multibuffer = MultiBuffer::new(0, Capability::ReadWrite);
+            multibuffer.push_excerpts(
+                buffer_a.clone(),
+                [ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(2, 0),
+                    primary: None,
+                }],
+                cx,
+            );
+            multibuffer
+        });
+        let multibuffer_editor =
+            cx.add_window(|cx| Editor::for_multibuffer(multibuffer, Some(project.clone()), cx));
+        cx.executor().advance_clock(GIT_BLAME_DEBOUNCE_TIMEOUT);
+        cx.executor().run_until_parked();
+
+        editor_a
+            .update(cx, |editor, cx| {
+                let snapshot = editor.buffer.read(cx).snapshot(cx);
+                let hunks = (0..3)
+                    .map(|row| {
+                        editor
+                            .blame_hunk_for_row(row, &snapshot)
+                            .map(|hunk| (hunk.sha.clone(), hunk.author.clone()))
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    hunks,
+                    [
+                        Some((Some("1111111".into()), "alice".into())),
+                        Some((Some("1111111".into()), "alice".into())),
+                        Some((None, String::new())),
+                    ]
+                );
+                let hunk = editor.blame_hunk_for_row(0, &snapshot).unwrap();
+                assert_eq!(gutter_blame_text(hunk, UtcOffset::UTC), "2023-11-14 alice");
+                assert_eq!(
+                    inline_blame_text(hunk, UtcOffset::UTC),
+                    "alice, 2023-11-14 • 1111111 Add one and two"
+                );
+            })
+            .unwrap();
+
+        // A file outside of a repository is remembered as not blamable until it changes.
+        editor_b
+            .update(cx, |editor, cx| {
+                let buffer_id = buffer_b.read(cx).remote_id();
+                let blame = editor.git_blame.buffers.get(&buffer_id).unwrap();
+                assert!(blame.hunks.is_empty());
+            })
+            .unwrap();
+
+        multibuffer_editor
+            .update(cx, |editor, _| assert!(editor.git_blame.buffers.is_empty()))
+            .unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{Editor, RevertHunk, StageHunk, UnstageHunk};
+use collections::HashMap;
+use git::diff::intersects_rows;
+use gpui::{Model, ViewContext};
+use language::{Buffer, OffsetRangeExt, Point};
+use std::{ops::Range, slice};
+use util::ResultExt;
+
+impl Editor {
+    pub fn stage_hunk(&mut self, _: &StageHunk, cx: &mut ViewContext<Self>) {
+        self.update_git_index(true, cx);
+    }
+
+    pub fn unstage_hunk(&mut self, _: &UnstageHunk, cx: &mut ViewContext<Self>) {
+        self.update_git_index(false, cx);
+    }
+
+    /// Replaces the hunks intersecting the selections with their contents in the git index.
+    pub fn revert_hunk(&mut self, _: &RevertHunk, cx: &mut ViewContext<Self>) {
+        let ranges_by_buffer = self.selected_buffer_ranges(cx);
+        self.transact(cx, |_, cx| {
+            for (buffer, ranges) in ranges_by_buffer {
+                buffer.update(cx, |buffer, cx| {
+                    let Some(diff_base) = buffer.diff_base() else {
+                        return;
+                    };
+                    let snapshot = buffer.snapshot();
+                    let mut hunks = ranges
+                        .iter()
+                        .flat_map(|range| {
+                            let range = range.to_point(&snapshot);
+                            let rows = range.start.row..range.end.row + 1;
+                            snapshot
+                                .git_diff_hunks_in_row_range(rows.clone())
+                                .filter(move |hunk| {
+                                    intersects_rows(&hunk.buffer_range, slice::from_ref(&rows))
+                                })
+                        })
+                        .collect::<Vec<_>>();
+                    hunks.sort_by_key(|hunk| hunk.buffer_range.start);
+                    hunks.dedup();
+
+                    let edits = hunks
+                        .into_iter()
+                        .map(|hunk| {
+                            let range = Point::new(hunk.buffer_range.start, 0)
+                                ..Point::new(hunk.buffer_range.end, 0);
+                            (range, diff_base[hunk.diff_base_byte_range].to_string())
+                        })
+                        .collect::<Vec<_>>();
+                    buffer.edit(edits, None, cx);
+                });
+            }
+        });
+    }
+
+    fn update_git_index(&mut self, stage: bool, cx: &mut ViewContext<Self>) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let ranges_by_buffer = self.selected_buffer_ranges(cx);
+        let tasks = project.update(cx, |project, cx| {
+            ranges_by_buffer
+                .into_iter()
+                .map(|(buffer, ranges)| {
+                    let ranges = ranges
+                        .into_iter()
+                        .map(|range| {
+                            let buffer = buffer.read(cx);
+                            buffer.anchor_before(range.start)..buffer.anchor_after(range.end)
+                        })
+                        .collect();
+                    if stage {
+                        project.stage_hunks(&buffer, ranges, cx)
+                    } else {
+                        project.unstage_hunks(&buffer, ranges, cx)
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+        cx.spawn(|_, _| async move {
+            for task in tasks {
+                task.await.log_err();
+            }
+        })
+        .detach();
+    }
+
+    fn selected_buffer_ranges(
+        &self,
+        cx: &mut ViewContext<Self>,
+    ) -> HashMap<Model<Buffer>, Vec<Range<usize>>> {
+        let mut ranges_by_buffer = HashMap::<_, Vec<_>>::default();
+        for selection in self.selections.all::<usize>(cx) {
+            for (buffer, range, _) in self
+                .buffer
+                .read(cx)
+                .range_to_buffer_ranges(selection.start..selection.end, cx)
+            {
+                ranges_by_buffer.entry(buffer).or_default().push(range);
+            }
+        }
+        ranges_by_buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        editor_tests::init_test, test::editor_test_context::EditorTestContext, RevertHunk,
+    };
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_revert_hunk(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+
+        let diff_base = indoc! {r#"
+            one
+            two
+            three
+            four
+        "#};
+        cx.set_diff_base(Some(diff_base));
+        cx.set_state(indoc! {r#"
+            ONE
+            two
+            thˇree
+            FOUR
+            five
+        "#});
+        cx.executor().run_until_parked();
+
+        cx.update_editor(|editor, cx| editor.revert_hunk(&RevertHunk, cx));
+        cx.assert_editor_state(indoc! {r#"
+            ONE
+            two
+            thˇree
+            FOUR
+            five
+        "#});
+
+        // The changed and the added line form a single hunk, which is reverted as a whole.
+        cx.set_selections_state(indoc! {r#"
+            «ONE
+            twoˇ»
+            three
+            FOUR
+            fˇive
+        "#});
+        cx.update_editor(|editor, cx| editor.revert_hunk(&RevertHunk, cx));
+        cx.assert_editor_state(indoc! {r#"
+            «one
+            twoˇ»
+            three
+            four
+            ˇ"#});
+
+        cx.update_editor(|editor, cx| editor.undo(&crate::Undo, cx));
+        cx.assert_editor_state(indoc! {r#"
+            «ONE
+            twoˇ»
+            three
+            FOUR
+            fˇive
+        "#});
+    }
+}
@@ -827,7 +827,11 @@ impl Item for Editor {
     fn added_to_workspace(&mut self, workspace: &mut Workspace, cx: &mut ViewContext<Self>) {
         let workspace_id = workspace.database_id();
         let item_id = cx.view().item_id().as_u64() as ItemId;
+        let is_first_workspace = self.workspace.is_none();
         self.workspace = Some((workspace.weak_handle(), workspace.database_id()));
+        if is_first_workspace {
+            self.read_folds_from_db(cx);
+        }
 
         fn serialize(
             buffer: Model<Buffer>,
@@ -1035,6 +1039,23 @@ impl SearchableItem for Editor {
     ) {
         let text = self.buffer.read(cx);
         let text = text.snapshot(cx);
+        if query.is_structural() {
+            // Structural replacements refer to the captures of the match, which are only known
+            // to the buffer that contains it.
+            let replacement = text
+                .buffer_for_excerpt(identifier.start.excerpt_id)
+                .and_then(|buffer| {
+                    let range = (identifier.start.text_anchor..identifier.end.text_anchor)
+                        .to_offset(buffer);
+                    query.replacement_for_range(buffer, range)
+                });
+            if let Some(replacement) = replacement {
+                self.transact(cx, |this, cx| {
+                    this.edit([(identifier.clone(), Arc::from(replacement))], cx);
+                });
+            }
+            return;
+        }
         let text = text.text_for_range(identifier.clone()).collect::<Vec<_>>();
         let text: Cow<_> = if text.len() == 1 {
             text.first().cloned().unwrap().into()
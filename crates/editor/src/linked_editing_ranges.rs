@@ -0,0 +1,261 @@
+use crate::{Anchor, Editor, EditorMode, ToOffset};
+use gpui::{Task, ViewContext};
+use language::{Selection, SelectionGoal};
+use project::LinkedEditingWordPattern;
+use std::{ops::Range, time::Duration};
+use util::ResultExt;
+
+/// How long to wait after the cursor moves before fetching the linked editing ranges around it.
+const LINKED_EDITING_RANGES_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub(crate) struct LinkedEditingRangesState {
+    /// The ranges that must be edited together with the one containing the newest cursor,
+    /// including that one.
+    ranges: Vec<Range<Anchor>>,
+    word_pattern: Option<LinkedEditingWordPattern>,
+    fetch_task: Option<Task<Option<()>>>,
+}
+
+impl Editor {
+    /// Fetches the linked editing ranges around the newest cursor, unless it is still inside the
+    /// ranges that were last fetched.
+    pub(crate) fn refresh_linked_editing_ranges(&mut self, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+
+        let head = self.selections.newest_anchor().head();
+        let multibuffer = self.buffer.read(cx);
+        let snapshot = multibuffer.snapshot(cx);
+        let head_offset = head.to_offset(&snapshot);
+        let is_inside_ranges = self.linked_editing_ranges.ranges.iter().any(|range| {
+            range.start.to_offset(&snapshot) <= head_offset
+                && head_offset <= range.end.to_offset(&snapshot)
+        });
+        if is_inside_ranges {
+            return;
+        }
+
+        self.linked_editing_ranges.ranges.clear();
+        self.linked_editing_ranges.word_pattern = None;
+        let Some((buffer, buffer_position)) = multibuffer.text_anchor_for_position(head, cx) else {
+            self.linked_editing_ranges.fetch_task = None;
+            return;
+        };
+        let excerpt_id = head.excerpt_id;
+        self.linked_editing_ranges.fetch_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(LINKED_EDITING_RANGES_DEBOUNCE_TIMEOUT)
+                .await;
+            let ranges = project
+                .update(&mut cx, |project, cx| {
+                    project.linked_editing_ranges(&buffer, buffer_position, cx)
+                })
+                .ok()?
+                .await
+                .log_err()?;
+            editor
+                .update(&mut cx, |editor, cx| {
+                    let snapshot = editor.buffer.read(cx).snapshot(cx);
+                    editor.linked_editing_ranges.word_pattern = ranges.word_pattern;
+                    editor.linked_editing_ranges.ranges = ranges
+                        .ranges
+                        .into_iter()
+                        .map(|range| {
+                            snapshot.anchor_in_excerpt(excerpt_id, range.start)
+                                ..snapshot.anchor_in_excerpt(excerpt_id, range.end)
+                        })
+                        .collect();
+                })
+                .ok()
+        }));
+    }
+
+    /// Performs `edit` with an extra selection in each range linked to the one containing the
+    /// only selection, so that the edit is mirrored into them.
+    ///
+    /// Returns `false` without performing the edit when there is nothing to mirror it into. The
+    /// ranges are dropped once their texts differ or are no longer words.
+    pub(crate) fn edit_linked_editing_ranges(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        edit: impl FnOnce(&mut Self, &mut ViewContext<Self>),
+    ) -> bool {
+        if self.linked_editing_ranges.ranges.len() < 2 {
+            return false;
+        }
+        let selections = self.selections.all::<usize>(cx);
+        let [selection] = selections.as_slice() else {
+            return false;
+        };
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let ranges = self
+            .linked_editing_ranges
+            .ranges
+            .iter()
+            .map(|range| range.start.to_offset(&snapshot)..range.end.to_offset(&snapshot))
+            .collect::<Vec<_>>();
+        let Some(range_ix) = ranges
+            .iter()
+            .position(|range| range.start <= selection.start && selection.end <= range.end)
+        else {
+            return false;
+        };
+        let range_text =
+            |range: &Range<usize>| snapshot.text_for_range(range.clone()).collect::<String>();
+        let text = range_text(&ranges[range_ix]);
+        let word_pattern = self.linked_editing_ranges.word_pattern.clone();
+        if word_pattern
+            .as_ref()
+            .map_or(false, |pattern| !pattern.matches(&text))
+            || ranges.iter().any(|range| range_text(range) != text)
+        {
+            self.linked_editing_ranges.ranges.clear();
+            self.linked_editing_ranges.word_pattern = None;
+            return false;
+        }
+
+        // The ranges grow with the mirrored edit, even at their boundaries.
+        let growing_ranges = ranges
+            .iter()
+            .map(|range| snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end))
+            .collect::<Vec<_>>();
+        let start_delta = selection.start - ranges[range_ix].start;
+        let end_delta = selection.end - ranges[range_ix].start;
+        let original_id = selection.id;
+        let original = selection.clone();
+        self.transact(cx, |this, cx| {
+            // The original selection is re-added last, so that it remains the newest one.
+            let mut selections = Vec::with_capacity(ranges.len());
+            for (ix, range) in ranges.iter().enumerate() {
+                if ix != range_ix {
+                    selections.push(Selection {
+                        id: this.selections.new_selection_id(),
+                        start: (range.start + start_delta).min(range.end),
+                        end: (range.start + end_delta).min(range.end),
+                        reversed: original.reversed,
+                        goal: SelectionGoal::None,
+                    });
+                }
+            }
+            let edited_id = this.selections.new_selection_id();
+            selections.push(Selection {
+                id: edited_id,
+                ..original
+            });
+            this.selections.change_with(cx, |s| s.select(selections));
+
+            edit(this, cx);
+
+            let selections = this
+                .selections
+                .all::<usize>(cx)
+                .into_iter()
+                .filter(|selection| selection.id == edited_id)
+                .map(|mut selection| {
+                    selection.id = original_id;
+                    selection
+                })
+                .collect::<Vec<_>>();
+            if !selections.is_empty() {
+                this.selections.change_with(cx, |s| s.select(selections));
+            }
+
+            // Moving the selections may have dropped the ranges, such as when typing at their
+            // ends, so they are restored without being fetched again.
+            let snapshot = this.buffer.read(cx).snapshot(cx);
+            this.linked_editing_ranges = LinkedEditingRangesState {
+                ranges: growing_ranges
+                    .iter()
+                    .map(|range| {
+                        snapshot.anchor_after(range.start.to_offset(&snapshot))
+                            ..snapshot.anchor_before(range.end.to_offset(&snapshot))
+                    })
+                    .collect(),
+                word_pattern,
+                fetch_task: None,
+            };
+        });
+        true
+    }
+}
+
+/// Whether the given input can be mirrored into linked editing ranges, which only holds for
+/// deletions and for text that can be part of a tag name.
+pub(crate) fn is_linked_editing_input(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+
+    #[gpui::test]
+    async fn test_typing_in_linked_editing_ranges(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                linked_editing_range_provider: Some(
+                    lsp::LinkedEditingRangeServerCapabilities::Simple(true),
+                ),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        // Only requests inside the opening tag's name are answered, so that the ranges must be
+        // kept up to date while typing.
+        cx.handle_request::<lsp::request::LinkedEditingRange, _, _>(|_, params, _| async move {
+            let position = params.text_document_position_params.position;
+            Ok((1..=4)
+                .contains(&position.character)
+                .then(|| lsp::LinkedEditingRanges {
+                    ranges: vec![
+                        lsp::Range::new(lsp::Position::new(0, 1), lsp::Position::new(0, 4)),
+                        lsp::Range::new(lsp::Position::new(0, 7), lsp::Position::new(0, 10)),
+                    ],
+                    word_pattern: Some("[a-z]+".to_string()),
+                }))
+        });
+        cx.set_state("<diˇv></div>");
+        cx.executor()
+            .advance_clock(LINKED_EDITING_RANGES_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+
+        cx.simulate_keystroke("x");
+        cx.assert_editor_state("<dixˇv></dixv>");
+
+        cx.set_selections_state("<ˇdixv></dixv>");
+        cx.simulate_keystroke("a");
+        cx.assert_editor_state("<aˇdixv></adixv>");
+
+        cx.set_selections_state("<adixvˇ></adixv>");
+        cx.simulate_keystroke("s");
+        cx.assert_editor_state("<adixvsˇ></adixvs>");
+
+        // Text typed after the tag name isn't mirrored.
+        cx.simulate_keystrokes(["space", "c", "l", "a", "s", "s"]);
+        cx.assert_editor_state("<adixvs classˇ></adixvs>");
+
+        // Once the names differ, they are no longer linked.
+        cx.set_state("<divˇ></div>");
+        cx.executor()
+            .advance_clock(LINKED_EDITING_RANGES_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| {
+            editor
+                .buffer()
+                .update(cx, |buffer, cx| buffer.edit([(9..10, "")], None, cx));
+        });
+        cx.simulate_keystroke("x");
+        cx.assert_editor_state("<divxˇ></di>");
+    }
+}
@@ -0,0 +1,661 @@
+use crate::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, DisplaySnapshot,
+        RenderBlock, ToDisplayPoint,
+    },
+    AcceptBoth, AcceptNeither, AcceptOurs, AcceptTheirs, Anchor, Autoscroll, Direction, Editor,
+    EditorMode, GoToConflict, GoToPrevConflict,
+};
+use collections::{HashMap, HashSet};
+use git::conflict::{parse_conflicts, Conflict};
+use gpui::{Hsla, Task, ViewContext, WindowContext};
+use language::{OffsetRangeExt, ToOffset, ToPoint};
+use std::{cmp::Ordering, mem, ops::Range, sync::Arc, time::Duration};
+use ui::prelude::*;
+
+/// How long to wait after an edit before searching for conflict markers again.
+const MERGE_CONFLICTS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(75);
+
+#[derive(Default)]
+pub(crate) struct MergeConflictsState {
+    /// Whether conflicts are shown when the editor has several buffers, which they aren't by
+    /// default.
+    show_in_multibuffer: bool,
+    buffers: HashMap<u64, BufferConflicts>,
+    /// The conflicts of all buffers mapped into the multibuffer, in order.
+    conflicts: Vec<Conflict<Anchor>>,
+    /// The blocks showing the actions of each conflict, along with their position.
+    blocks: Vec<(Anchor, BlockId)>,
+    excerpts_changed: bool,
+    refresh_task: Option<Task<Option<()>>>,
+}
+
+struct BufferConflicts {
+    version: clock::Global,
+    conflicts: Vec<Conflict<text::Anchor>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConflictResolution {
+    Ours,
+    Theirs,
+    Both,
+    Neither,
+}
+
+impl ConflictResolution {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ours => "Accept Ours",
+            Self::Theirs => "Accept Theirs",
+            Self::Both => "Accept Both",
+            Self::Neither => "Accept Neither",
+        }
+    }
+}
+
+impl Editor {
+    pub fn accept_ours(&mut self, _: &AcceptOurs, cx: &mut ViewContext<Self>) {
+        self.resolve_selected_conflicts(ConflictResolution::Ours, cx);
+    }
+
+    pub fn accept_theirs(&mut self, _: &AcceptTheirs, cx: &mut ViewContext<Self>) {
+        self.resolve_selected_conflicts(ConflictResolution::Theirs, cx);
+    }
+
+    pub fn accept_both(&mut self, _: &AcceptBoth, cx: &mut ViewContext<Self>) {
+        self.resolve_selected_conflicts(ConflictResolution::Both, cx);
+    }
+
+    pub fn accept_neither(&mut self, _: &AcceptNeither, cx: &mut ViewContext<Self>) {
+        self.resolve_selected_conflicts(ConflictResolution::Neither, cx);
+    }
+
+    pub fn go_to_conflict(&mut self, _: &GoToConflict, cx: &mut ViewContext<Self>) {
+        self.go_to_conflict_in_direction(Direction::Next, cx);
+    }
+
+    pub fn go_to_prev_conflict(&mut self, _: &GoToPrevConflict, cx: &mut ViewContext<Self>) {
+        self.go_to_conflict_in_direction(Direction::Prev, cx);
+    }
+
+    /// Shows the conflicts of every buffer in an editor of several buffers, such as one listing
+    /// the conflicted files.
+    pub fn set_show_merge_conflicts_in_multibuffer(
+        &mut self,
+        show: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.merge_conflicts.show_in_multibuffer = show;
+        self.refresh_merge_conflicts(true, cx);
+    }
+
+    /// Finds the conflict markers in the buffers that changed since they were last searched,
+    /// highlighting the conflicts and showing the actions resolving them above each one.
+    pub(crate) fn refresh_merge_conflicts(
+        &mut self,
+        excerpts_changed: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        if !self.buffer.read(cx).is_singleton() && !self.merge_conflicts.show_in_multibuffer {
+            self.merge_conflicts.refresh_task = None;
+            if !self.merge_conflicts.buffers.is_empty() {
+                self.merge_conflicts.buffers.clear();
+                self.update_merge_conflicts(cx);
+            }
+            return;
+        }
+
+        self.merge_conflicts.excerpts_changed |= excerpts_changed;
+        self.merge_conflicts.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT)
+                .await;
+            let (mut changed, snapshots) = editor
+                .update(&mut cx, |editor, cx| {
+                    let buffers = editor.buffer.read(cx).all_buffers();
+                    let buffer_ids = buffers
+                        .iter()
+                        .map(|buffer| buffer.read(cx).remote_id())
+                        .collect::<HashSet<_>>();
+                    let mut changed = false;
+                    editor.merge_conflicts.buffers.retain(|buffer_id, buffer| {
+                        let retain = buffer_ids.contains(buffer_id);
+                        changed |= !retain && !buffer.conflicts.is_empty();
+                        retain
+                    });
+                    let snapshots = buffers
+                        .iter()
+                        .map(|buffer| buffer.read(cx))
+                        .filter(|buffer| {
+                            editor
+                                .merge_conflicts
+                                .buffers
+                                .get(&buffer.remote_id())
+                                .map_or(true, |parsed| {
+                                    buffer.version().changed_since(&parsed.version)
+                                })
+                        })
+                        .map(|buffer| (buffer.remote_id(), buffer.text_snapshot()))
+                        .collect::<Vec<_>>();
+                    (changed, snapshots)
+                })
+                .ok()?;
+
+            let parsed = cx
+                .background_executor()
+                .spawn(async move {
+                    snapshots
+                        .into_iter()
+                        .map(|(buffer_id, snapshot)| {
+                            let conflicts = BufferConflicts {
+                                version: snapshot.version().clone(),
+                                conflicts: parse_conflicts(&snapshot),
+                            };
+                            (buffer_id, conflicts)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+            editor
+                .update(&mut cx, |editor, cx| {
+                    for (buffer_id, conflicts) in parsed {
+                        changed |= !conflicts.conflicts.is_empty();
+                        let previous = editor.merge_conflicts.buffers.insert(buffer_id, conflicts);
+                        changed |=
+                            previous.map_or(false, |previous| !previous.conflicts.is_empty());
+                    }
+                    let excerpts_changed = mem::take(&mut editor.merge_conflicts.excerpts_changed);
+                    let has_conflicts = editor
+                        .merge_conflicts
+                        .buffers
+                        .values()
+                        .any(|buffer| !buffer.conflicts.is_empty());
+                    if changed || (excerpts_changed && has_conflicts) {
+                        editor.update_merge_conflicts(cx);
+                    }
+                })
+                .ok()
+        }));
+    }
+
+    /// Maps the conflicts of every buffer into the multibuffer, keeping the blocks of the
+    /// conflicts that didn't move.
+    fn update_merge_conflicts(&mut self, cx: &mut ViewContext<Self>) {
+        let multibuffer = self.buffer.read(cx);
+        let snapshot = multibuffer.snapshot(cx);
+        let mut conflicts = Vec::new();
+        for (buffer_id, parsed) in &self.merge_conflicts.buffers {
+            if parsed.conflicts.is_empty() {
+                continue;
+            }
+            let Some(buffer) = multibuffer.buffer(*buffer_id) else {
+                continue;
+            };
+            let buffer_snapshot = buffer.read(cx).snapshot();
+            for (excerpt_id, excerpt_range) in multibuffer.excerpts_for_buffer(&buffer, cx) {
+                let context = excerpt_range.context.to_offset(&buffer_snapshot);
+                for conflict in &parsed.conflicts {
+                    let range = conflict.range.to_offset(&buffer_snapshot);
+                    if range.start >= context.end || range.end <= context.start {
+                        continue;
+                    }
+                    let to_anchors = |range: &Range<text::Anchor>| {
+                        snapshot.anchor_in_excerpt(excerpt_id, range.start)
+                            ..snapshot.anchor_in_excerpt(excerpt_id, range.end)
+                    };
+                    conflicts.push(Conflict {
+                        range: to_anchors(&conflict.range),
+                        ours: to_anchors(&conflict.ours),
+                        base: conflict.base.as_ref().map(to_anchors),
+                        theirs: to_anchors(&conflict.theirs),
+                    });
+                }
+            }
+        }
+        conflicts.sort_by(|a, b| a.range.start.cmp(&b.range.start, &snapshot));
+
+        // Both the old blocks and the conflicts are sorted by position.
+        let mut old_blocks = mem::take(&mut self.merge_conflicts.blocks)
+            .into_iter()
+            .peekable();
+        let mut blocks_to_remove = HashSet::default();
+        let mut kept_blocks = Vec::with_capacity(conflicts.len());
+        let mut blocks_to_insert = Vec::new();
+        for conflict in &conflicts {
+            let position = conflict.range.start;
+            let mut kept_block = None;
+            while let Some(&(old_position, block_id)) = old_blocks.peek() {
+                match old_position.cmp(&position, &snapshot) {
+                    Ordering::Less => {
+                        blocks_to_remove.insert(block_id);
+                        old_blocks.next();
+                    }
+                    Ordering::Equal => {
+                        kept_block = Some(block_id);
+                        old_blocks.next();
+                        break;
+                    }
+                    Ordering::Greater => break,
+                }
+            }
+            if kept_block.is_none() {
+                blocks_to_insert.push(BlockProperties {
+                    position,
+                    height: 1,
+                    style: BlockStyle::Flex,
+                    render: render_conflict_actions(position),
+                    disposition: BlockDisposition::Above,
+                });
+            }
+            kept_blocks.push(kept_block);
+        }
+        blocks_to_remove.extend(old_blocks.map(|(_, block_id)| block_id));
+
+        if !blocks_to_remove.is_empty() {
+            self.remove_blocks(blocks_to_remove, None, cx);
+        }
+        let mut inserted_blocks = if blocks_to_insert.is_empty() {
+            Vec::new()
+        } else {
+            self.insert_blocks(blocks_to_insert, None, cx)
+        }
+        .into_iter();
+        self.merge_conflicts.blocks = conflicts
+            .iter()
+            .zip(kept_blocks)
+            .filter_map(|(conflict, kept_block)| {
+                let block_id = kept_block.or_else(|| inserted_blocks.next())?;
+                Some((conflict.range.start, block_id))
+            })
+            .collect();
+        self.merge_conflicts.conflicts = conflicts;
+        cx.notify();
+    }
+
+    /// The display rows of the visible conflicts, along with the colors of their background:
+    /// each side of a conflict is highlighted differently from its markers.
+    pub(crate) fn merge_conflict_rows(
+        &self,
+        visible_rows: Range<u32>,
+        snapshot: &DisplaySnapshot,
+        cx: &WindowContext,
+    ) -> Vec<(Range<u32>, Hsla)> {
+        let status = cx.theme().status();
+        let mut rows = Vec::new();
+        for conflict in &self.merge_conflicts.conflicts {
+            let mut ranges = conflict
+                .marker_ranges()
+                .into_iter()
+                .map(|range| (range, status.conflict_background))
+                .collect::<Vec<_>>();
+            ranges.push((conflict.ours.clone(), status.created_background));
+            if let Some(base) = &conflict.base {
+                ranges.push((base.clone(), status.hidden_background));
+            }
+            ranges.push((conflict.theirs.clone(), status.info_background));
+
+            for (range, color) in ranges {
+                let range = display_rows_for_range(&range, snapshot);
+                if range.start < visible_rows.end && visible_rows.start < range.end {
+                    rows.push((range, color));
+                }
+            }
+        }
+        rows
+    }
+
+    fn resolve_selected_conflicts(
+        &mut self,
+        resolution: ConflictResolution,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let ranges = self
+            .selections
+            .all::<usize>(cx)
+            .into_iter()
+            .map(|selection| selection.start..selection.end)
+            .collect();
+        self.resolve_conflicts(ranges, resolution, cx);
+    }
+
+    fn resolve_conflict_at(
+        &mut self,
+        position: Anchor,
+        resolution: ConflictResolution,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let offset = position.to_offset(&self.buffer.read(cx).snapshot(cx));
+        self.resolve_conflicts(vec![offset..offset], resolution, cx);
+    }
+
+    /// Replaces the conflicts intersecting the given ranges with the sides picked by the
+    /// resolution.
+    fn resolve_conflicts(
+        &mut self,
+        ranges: Vec<Range<usize>>,
+        resolution: ConflictResolution,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let edits = self
+            .merge_conflicts
+            .conflicts
+            .iter()
+            .filter_map(|conflict| {
+                let range = conflict.range.to_offset(&snapshot);
+                let is_selected = ranges
+                    .iter()
+                    .any(|selected| selected.start < range.end && range.start <= selected.end);
+                if !is_selected {
+                    return None;
+                }
+                let ours = || snapshot.text_for_range(conflict.ours.clone());
+                let theirs = || snapshot.text_for_range(conflict.theirs.clone());
+                let text = match resolution {
+                    ConflictResolution::Ours => ours().collect::<String>(),
+                    ConflictResolution::Theirs => theirs().collect(),
+                    ConflictResolution::Both => ours().chain(theirs()).collect(),
+                    ConflictResolution::Neither => String::new(),
+                };
+                Some((range, text))
+            })
+            .collect::<Vec<_>>();
+        if edits.is_empty() {
+            return;
+        }
+
+        self.transact(cx, |editor, cx| {
+            editor
+                .buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+        });
+    }
+
+    fn go_to_conflict_in_direction(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let head = self.selections.newest::<usize>(cx).head();
+        let starts = self
+            .merge_conflicts
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.range.start.to_offset(&snapshot))
+            .collect::<Vec<_>>();
+        // Wrap around once the last conflict in that direction is reached.
+        let target = match direction {
+            Direction::Next => starts
+                .iter()
+                .find(|start| **start > head)
+                .or(starts.first()),
+            Direction::Prev => starts
+                .iter()
+                .rev()
+                .find(|start| **start < head)
+                .or(starts.last()),
+        };
+        if let Some(&offset) = target {
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([offset..offset]);
+            });
+        }
+    }
+}
+
+/// The display rows spanned by the given range of whole lines.
+fn display_rows_for_range(range: &Range<Anchor>, snapshot: &DisplaySnapshot) -> Range<u32> {
+    let start = range.start.to_point(&snapshot.buffer_snapshot);
+    let end = range.end.to_point(&snapshot.buffer_snapshot);
+    let start_row = start.to_display_point(snapshot).row();
+    if start == end {
+        start_row..start_row
+    } else if end.column == 0 {
+        start_row..end.to_display_point(snapshot).row()
+    } else {
+        // The range ends with the last line of the buffer.
+        start_row..end.to_display_point(snapshot).row() + 1
+    }
+}
+
+fn render_conflict_actions(position: Anchor) -> RenderBlock {
+    Arc::new(move |cx: &mut BlockContext| {
+        let editor = cx.view.clone();
+        h_flex()
+            .id(cx.block_id)
+            .pl(cx.anchor_x)
+            .gap_1()
+            .children(
+                [
+                    ConflictResolution::Ours,
+                    ConflictResolution::Theirs,
+                    ConflictResolution::Both,
+                    ConflictResolution::Neither,
+                ]
+                .into_iter()
+                .enumerate()
+                .map(|(ix, resolution)| {
+                    let editor = editor.clone();
+                    h_flex()
+                        .gap_1()
+                        .when(ix > 0, |this| {
+                            this.child(Label::new("|").size(LabelSize::Small).color(Color::Muted))
+                        })
+                        .child(
+                            Button::new(("resolve-conflict", ix), resolution.label())
+                                .label_size(LabelSize::Small)
+                                .color(Color::Muted)
+                                .on_click(move |_, cx| {
+                                    editor.update(cx, |editor, cx| {
+                                        editor.resolve_conflict_at(position, resolution, cx)
+                                    })
+                                }),
+                        )
+                }),
+            )
+            .into_any_element()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MERGE_CONFLICTS_DEBOUNCE_TIMEOUT;
+    use crate::{
+        editor_tests::init_test,
+        test::{build_editor, editor_test_context::EditorTestContext},
+        AcceptBoth, AcceptOurs, AcceptTheirs, ExcerptRange, GoToConflict, GoToPrevConflict,
+        MultiBuffer,
+    };
+    use gpui::{Context, TestAppContext};
+    use indoc::indoc;
+    use language::{Buffer, Capability, Point};
+
+    #[gpui::test]
+    async fn test_resolve_merge_conflicts(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+
+        cx.set_state(indoc! {r#"
+            ˇone
+            <<<<<<< HEAD
+            two
+            =======
+            TWO
+            >>>>>>> feature
+            three
+            <<<<<<< HEAD
+            four
+            ||||||| base
+            FOUR
+            =======
+            4
+            >>>>>>> feature
+            five
+        "#});
+        cx.executor()
+            .advance_clock(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| editor.go_to_conflict(&GoToConflict, cx));
+        cx.assert_editor_state(indoc! {r#"
+            one
+            ˇ<<<<<<< HEAD
+            two
+            =======
+            TWO
+            >>>>>>> feature
+            three
+            <<<<<<< HEAD
+            four
+            ||||||| base
+            FOUR
+            =======
+            4
+            >>>>>>> feature
+            five
+        "#});
+        cx.update_editor(|editor, cx| editor.go_to_prev_conflict(&GoToPrevConflict, cx));
+        cx.assert_editor_state(indoc! {r#"
+            one
+            <<<<<<< HEAD
+            two
+            =======
+            TWO
+            >>>>>>> feature
+            three
+            ˇ<<<<<<< HEAD
+            four
+            ||||||| base
+            FOUR
+            =======
+            4
+            >>>>>>> feature
+            five
+        "#});
+
+        cx.update_editor(|editor, cx| editor.accept_both(&AcceptBoth, cx));
+        assert_eq!(
+            cx.buffer_text(),
+            indoc! {r#"
+                one
+                <<<<<<< HEAD
+                two
+                =======
+                TWO
+                >>>>>>> feature
+                three
+                four
+                4
+                five
+            "#}
+        );
+
+        cx.executor()
+            .advance_clock(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.set_selections_state(indoc! {r#"
+            one
+            <<<<<<< HEAD
+            twˇo
+            =======
+            TWO
+            >>>>>>> feature
+            three
+            four
+            4
+            five
+        "#});
+        cx.update_editor(|editor, cx| editor.accept_theirs(&AcceptTheirs, cx));
+        assert_eq!(
+            cx.buffer_text(),
+            indoc! {r#"
+                one
+                TWO
+                three
+                four
+                4
+                five
+            "#}
+        );
+
+        // Outside of conflicts, the actions do nothing.
+        cx.executor()
+            .advance_clock(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        cx.set_selections_state(indoc! {r#"
+            one
+            TWO
+            ˇthree
+            four
+            4
+            five
+        "#});
+        cx.update_editor(|editor, cx| editor.accept_ours(&AcceptOurs, cx));
+        assert_eq!(
+            cx.buffer_text(),
+            indoc! {r#"
+                one
+                TWO
+                three
+                four
+                4
+                five
+            "#}
+        );
+    }
+
+    #[gpui::test]
+    async fn test_merge_conflicts_in_multibuffer(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let text = "one\n<<<<<<< HEAD\ntwo\n=======\nTWO\n>>>>>>> feature\nthree\n";
+        let buffer = cx.new_model(|cx| Buffer::new(0, cx.entity_id().as_u64(), text));
+        let multibuffer = cx.new_model(|cx| {
+            let mut multibuffer = MultiBuffer::new(0, Capability::ReadWrite);
+            multibuffer.push_excerpts(
+                buffer.clone(),
+                [ExcerptRange {
+                    context: Point::new(0, 0)..Point::new(7, 0),
+                    primary: None,
+                }],
+                cx,
+            );
+            multibuffer
+        });
+        let editor = cx.add_window(|cx| build_editor(multibuffer, cx));
+        cx.executor()
+            .advance_clock(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+
+        // Conflicts aren't shown in multibuffers by default.
+        editor
+            .update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |s| {
+                    s.select_ranges([Point::new(2, 0)..Point::new(2, 0)])
+                });
+                editor.accept_theirs(&AcceptTheirs, cx);
+                assert!(editor.merge_conflicts.blocks.is_empty());
+            })
+            .unwrap();
+        buffer.read_with(cx, |buffer, _| assert_eq!(buffer.text(), text));
+
+        editor
+            .update(cx, |editor, cx| {
+                editor.set_show_merge_conflicts_in_multibuffer(true, cx)
+            })
+            .unwrap();
+        cx.executor()
+            .advance_clock(MERGE_CONFLICTS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        editor
+            .update(cx, |editor, cx| {
+                assert_eq!(editor.merge_conflicts.blocks.len(), 1);
+                editor.accept_theirs(&AcceptTheirs, cx);
+            })
+            .unwrap();
+        buffer.read_with(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "one\nTWO\nthree\n")
+        });
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    DisplayPoint, Editor, EditorMode, FindAllReferences, GoToDefinition, GoToTypeDefinition,
-    Rename, RevealInFinder, SelectMode, ToggleCodeActions,
+    DisplayPoint, Editor, EditorMode, FindAllReferences, GoToDefinition, GoToImplementation,
+    GoToTypeDefinition, Rename, RevealInFinder, RevertHunk, SelectMode, StageHunk,
+    ToggleCodeActions, UnstageHunk,
 };
 use gpui::{DismissEvent, Pixels, Point, Subscription, View, ViewContext};
 
@@ -40,6 +41,7 @@ pub fn deploy_context_menu(
         menu.action("Rename Symbol", Box::new(Rename))
             .action("Go to Definition", Box::new(GoToDefinition))
             .action("Go to Type Definition", Box::new(GoToTypeDefinition))
+            .action("Go to Implementation", Box::new(GoToImplementation))
             .action("Find All References", Box::new(FindAllReferences))
             .action(
                 "Code Actions",
@@ -50,6 +52,44 @@ pub fn deploy_context_menu(
             .separator()
             .action("Reveal in Finder", Box::new(RevealInFinder))
     });
+    show_context_menu(editor, position, context_menu, cx);
+}
+
+/// Shows the actions for the git diff hunk starting at the given point, after clicking its
+/// indicator in the gutter.
+pub fn deploy_git_hunk_context_menu(
+    editor: &mut Editor,
+    position: Point<Pixels>,
+    point: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) {
+    if !editor.is_focused(cx) {
+        editor.focus(cx);
+    }
+
+    if editor.mode() != EditorMode::Full || editor.project.is_none() {
+        return;
+    }
+
+    // Move the cursor to the hunk so that the actions apply to it
+    editor.change_selections(None, cx, |s| {
+        s.select_display_ranges([point..point]);
+    });
+
+    let context_menu = ui::ContextMenu::build(cx, |menu, _cx| {
+        menu.action("Stage Hunk", Box::new(StageHunk))
+            .action("Unstage Hunk", Box::new(UnstageHunk))
+            .action("Revert Hunk", Box::new(RevertHunk))
+    });
+    show_context_menu(editor, position, context_menu, cx);
+}
+
+fn show_context_menu(
+    editor: &mut Editor,
+    position: Point<Pixels>,
+    context_menu: View<ui::ContextMenu>,
+    cx: &mut ViewContext<Editor>,
+) {
     let context_menu_focus = context_menu.focus_handle(cx);
     cx.focus(&context_menu_focus);
 
@@ -1,10 +1,16 @@
 use std::path::PathBuf;
 
+use anyhow::Result;
+
 use db::sqlez_macros::sql;
 use db::{define_connection, query};
 
 use workspace::{ItemId, WorkspaceDb, WorkspaceId};
 
+/// The modification time, in seconds and nanoseconds since the epoch, and length of the file
+/// whose saved text the folds' offsets refer to.
+pub type FoldsFingerprint = (u64, u32, usize);
+
 define_connection!(
     // Current schema shape using pseudo-rust syntax:
     // editors(
@@ -15,6 +21,16 @@ define_connection!(
     //   scroll_vertical_offset: f32,
     //   scroll_horizontal_offset: f32,
     // )
+    //
+    // editor_folds(
+    //   workspace_id: usize,
+    //   path: PathBuf,
+    //   start_offset: usize,
+    //   end_offset: usize,
+    //   mtime_seconds: u64,
+    //   mtime_nanos: u32,
+    //   len: usize,
+    // )
     pub static ref DB: EditorDb<WorkspaceDb> =
         &[sql! (
             CREATE TABLE editors(
@@ -31,6 +47,20 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN scroll_top_row INTEGER NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_horizontal_offset REAL NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_vertical_offset REAL NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            CREATE TABLE editor_folds(
+                workspace_id INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                mtime_seconds INTEGER NOT NULL,
+                mtime_nanos INTEGER NOT NULL,
+                len INTEGER NOT NULL,
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
         )];
 );
 
@@ -80,4 +110,50 @@ impl EditorDb {
             WHERE item_id = ?1 AND workspace_id = ?2
         }
     }
+
+    // Returns the folded offset ranges of the file, sorted by their start, along with the
+    // fingerprint of the file they were saved for
+    query! {
+        pub fn get_folds(workspace_id: WorkspaceId, path: PathBuf) -> Result<Vec<(usize, usize, FoldsFingerprint)>> {
+            SELECT start_offset, end_offset, mtime_seconds, mtime_nanos, len
+            FROM editor_folds
+            WHERE workspace_id = ? AND path = ?
+            ORDER BY start_offset
+        }
+    }
+
+    pub async fn save_folds(
+        &self,
+        workspace_id: WorkspaceId,
+        path: PathBuf,
+        fingerprint: FoldsFingerprint,
+        folds: Vec<(usize, usize)>,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_folds", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM editor_folds WHERE workspace_id = ? AND path = ?
+                ))?((workspace_id, path.as_path()))?;
+
+                let mut insert = conn.exec_bound(sql!(
+                    INSERT INTO editor_folds(workspace_id, path, start_offset, end_offset, mtime_seconds, mtime_nanos, len)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                ))?;
+                let (mtime_seconds, mtime_nanos, len) = fingerprint;
+                for (start, end) in folds {
+                    insert((
+                        workspace_id,
+                        path.as_path(),
+                        start,
+                        end,
+                        mtime_seconds,
+                        mtime_nanos,
+                        len,
+                    ))?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
 }
@@ -0,0 +1,126 @@
+use crate::{Anchor, Editor, ExcerptId, MultiBufferSnapshot, ToOffset};
+use collections::HashMap;
+use gpui::{Task, ViewContext};
+use std::{mem, ops::Range};
+use util::ResultExt;
+
+#[derive(Default)]
+pub(crate) struct SelectionRangesState {
+    /// The ranges that the language server would select around each selection, keyed by
+    /// selection id, from the innermost to the outermost one.
+    chains: HashMap<usize, Vec<Range<Anchor>>>,
+    /// How many times the selections should be expanded once the pending fetch completes.
+    pending_expansions: usize,
+    fetch_task: Option<Task<Option<()>>>,
+}
+
+impl SelectionRangesState {
+    pub(crate) fn ranges(
+        &self,
+        selection_id: usize,
+        buffer: &MultiBufferSnapshot,
+    ) -> Vec<Range<usize>> {
+        self.chains
+            .get(&selection_id)
+            .into_iter()
+            .flatten()
+            .map(|range| range.start.to_offset(buffer)..range.end.to_offset(buffer))
+            .collect()
+    }
+
+    /// Cancels one of the expansions waiting for the selection ranges to be fetched.
+    pub(crate) fn cancel_pending_expansion(&mut self) -> bool {
+        if self.pending_expansions > 0 {
+            self.pending_expansions -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Editor {
+    /// Fetches the selection ranges of the selections that weren't expanded yet, and expands
+    /// the selections once they are fetched. Returns `false` when there is nothing to fetch, in
+    /// which case the caller should expand the selections right away.
+    pub(crate) fn fetch_selection_ranges(&mut self, cx: &mut ViewContext<Self>) -> bool {
+        if self.selection_ranges.pending_expansions > 0 {
+            self.selection_ranges.pending_expansions += 1;
+            return true;
+        }
+        let Some(project) = self.project.clone() else {
+            return false;
+        };
+
+        let multibuffer = self.buffer.read(cx);
+        let mut positions_by_buffer = HashMap::default();
+        for selection in self.selections.all::<usize>(cx) {
+            if self.selection_ranges.chains.contains_key(&selection.id) {
+                continue;
+            }
+            let Some((buffer, offset, excerpt_id)) =
+                multibuffer.point_to_buffer_offset(selection.start, cx)
+            else {
+                continue;
+            };
+            positions_by_buffer
+                .entry(buffer.read(cx).remote_id())
+                .or_insert_with(|| (buffer, Vec::new()))
+                .1
+                .push((selection.id, excerpt_id, offset));
+        }
+        if positions_by_buffer.is_empty() {
+            return false;
+        }
+
+        self.selection_ranges.pending_expansions = 1;
+        self.selection_ranges.fetch_task = Some(cx.spawn(|editor, mut cx| async move {
+            let requests = project
+                .update(&mut cx, |project, cx| {
+                    positions_by_buffer
+                        .into_values()
+                        .map(|(buffer, selections)| {
+                            let positions = selections
+                                .iter()
+                                .map(|(_, _, offset)| *offset)
+                                .collect::<Vec<_>>();
+                            let request = project.selection_ranges(&buffer, positions, cx);
+                            (selections, request)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+
+            let mut chains = Vec::<(usize, ExcerptId, Vec<Range<language::Anchor>>)>::new();
+            for (selections, request) in requests {
+                // Selections without ranges are expanded using the syntax tree.
+                let mut buffer_chains = request.await.log_err().unwrap_or_default().into_iter();
+                for (selection_id, excerpt_id, _) in selections {
+                    let ranges = buffer_chains.next().unwrap_or_default();
+                    chains.push((selection_id, excerpt_id, ranges));
+                }
+            }
+
+            editor
+                .update(&mut cx, |editor, cx| {
+                    let snapshot = editor.buffer.read(cx).snapshot(cx);
+                    for (selection_id, excerpt_id, ranges) in chains {
+                        let ranges = ranges
+                            .into_iter()
+                            .map(|range| {
+                                snapshot.anchor_in_excerpt(excerpt_id, range.start)
+                                    ..snapshot.anchor_in_excerpt(excerpt_id, range.end)
+                            })
+                            .collect();
+                        editor.selection_ranges.chains.insert(selection_id, ranges);
+                    }
+                    let expansions = mem::take(&mut editor.selection_ranges.pending_expansions);
+                    for _ in 0..expansions {
+                        editor.expand_selections_to_larger_syntax_node(cx);
+                    }
+                })
+                .ok()
+        }));
+        true
+    }
+}
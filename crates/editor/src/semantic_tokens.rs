@@ -0,0 +1,262 @@
+use crate::{display_map::SemanticTokenHighlights, Editor, EditorMode};
+use collections::{HashMap, HashSet};
+use gpui::{HighlightStyle, Task, UnderlineStyle, ViewContext};
+use language::{language_settings::language_settings, Buffer, HighlightId};
+use project::SemanticToken;
+use std::{sync::Arc, time::Duration};
+use theme::{ActiveTheme, SyntaxTheme};
+use util::ResultExt;
+
+/// How long to wait after an edit before fetching the semantic tokens again.
+const SEMANTIC_TOKENS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(150);
+
+#[derive(Default)]
+pub(crate) struct SemanticTokensState {
+    buffers: HashMap<u64, BufferSemanticTokens>,
+    refresh_task: Option<Task<Option<()>>>,
+}
+
+struct BufferSemanticTokens {
+    version: clock::Global,
+    tokens: Vec<SemanticToken>,
+}
+
+impl Editor {
+    /// Fetches the semantic tokens of the buffers that changed since their tokens were last
+    /// fetched, or of all buffers when `invalidate` is set.
+    pub(crate) fn refresh_semantic_tokens(&mut self, invalidate: bool, cx: &mut ViewContext<Self>) {
+        if self.mode != EditorMode::Full {
+            return;
+        }
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+
+        let mut buffers_to_fetch = Vec::new();
+        let mut buffers_to_clear = self
+            .semantic_tokens
+            .buffers
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        for buffer in self.buffer.read(cx).all_buffers() {
+            let buffer_ref = buffer.read(cx);
+            let buffer_id = buffer_ref.remote_id();
+            if !semantic_tokens_enabled(buffer_ref, cx) {
+                continue;
+            }
+            buffers_to_clear.remove(&buffer_id);
+            let is_up_to_date = !invalidate
+                && self
+                    .semantic_tokens
+                    .buffers
+                    .get(&buffer_id)
+                    .map_or(false, |fetched| {
+                        !buffer_ref.version().changed_since(&fetched.version)
+                    });
+            if !is_up_to_date {
+                buffers_to_fetch.push(buffer);
+            }
+        }
+        for buffer_id in buffers_to_clear {
+            self.semantic_tokens.buffers.remove(&buffer_id);
+            self.display_map
+                .update(cx, |map, _| map.clear_semantic_highlights(buffer_id));
+            cx.notify();
+        }
+        if buffers_to_fetch.is_empty() {
+            return;
+        }
+
+        self.semantic_tokens.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+            cx.background_executor()
+                .timer(SEMANTIC_TOKENS_DEBOUNCE_TIMEOUT)
+                .await;
+            let requests = project
+                .update(&mut cx, |project, cx| {
+                    buffers_to_fetch
+                        .iter()
+                        .map(|buffer| {
+                            let buffer_id = buffer.read(cx).remote_id();
+                            let version = buffer.read(cx).version();
+                            (buffer_id, version, project.semantic_tokens(buffer, cx))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+            for (buffer_id, version, request) in requests {
+                let Some(tokens) = request.await.log_err() else {
+                    continue;
+                };
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        editor
+                            .semantic_tokens
+                            .buffers
+                            .insert(buffer_id, BufferSemanticTokens { version, tokens });
+                        editor.update_semantic_highlights(buffer_id, cx);
+                    })
+                    .ok()?;
+            }
+            Some(())
+        }));
+    }
+
+    /// Re-resolves the styles of the fetched semantic tokens, e.g. after the theme changed.
+    pub(crate) fn restyle_semantic_tokens(&mut self, cx: &mut ViewContext<Self>) {
+        let buffer_ids = self
+            .semantic_tokens
+            .buffers
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        for buffer_id in buffer_ids {
+            self.update_semantic_highlights(buffer_id, cx);
+        }
+    }
+
+    fn update_semantic_highlights(&mut self, buffer_id: u64, cx: &mut ViewContext<Self>) {
+        let multibuffer = self.buffer.read(cx);
+        let (Some(fetched), Some(buffer)) = (
+            self.semantic_tokens.buffers.get(&buffer_id),
+            multibuffer.buffer(buffer_id),
+        ) else {
+            return;
+        };
+
+        let theme = cx.theme().syntax().clone();
+        let snapshot = multibuffer.snapshot(cx);
+        let buffer_snapshot = buffer.read(cx).text_snapshot();
+        let mut highlights = Vec::new();
+        for (excerpt_id, excerpt_range) in multibuffer.excerpts_for_buffer(&buffer, cx) {
+            let context = excerpt_range.context;
+            for token in &fetched.tokens {
+                if token
+                    .range
+                    .end
+                    .cmp(&context.start, &buffer_snapshot)
+                    .is_le()
+                    || token
+                        .range
+                        .start
+                        .cmp(&context.end, &buffer_snapshot)
+                        .is_ge()
+                {
+                    continue;
+                }
+                if let Some(style) =
+                    semantic_token_style(&token.token_type, &token.token_modifiers, &theme)
+                {
+                    let start = snapshot.anchor_in_excerpt(excerpt_id, token.range.start);
+                    let end = snapshot.anchor_in_excerpt(excerpt_id, token.range.end);
+                    highlights.push((start..end, style));
+                }
+            }
+        }
+        let highlights = SemanticTokenHighlights::new(highlights, &snapshot);
+
+        self.display_map.update(cx, |map, _| {
+            map.set_semantic_highlights(buffer_id, highlights)
+        });
+        cx.notify();
+    }
+}
+
+fn semantic_tokens_enabled(buffer: &Buffer, cx: &gpui::AppContext) -> bool {
+    language_settings(buffer.language(), buffer.file(), cx).semantic_tokens
+}
+
+/// Resolves the style of a semantic token the same way as tree-sitter captures are resolved, by
+/// deriving a dot-separated capture name from the token's type and modifiers, e.g.
+/// `variable.mutable` or `function.method.builtin`.
+///
+/// Returns `None` when the theme has no style for the token, so that the syntax highlighting
+/// shows through.
+pub(crate) fn semantic_token_style(
+    token_type: &str,
+    token_modifiers: &[Arc<str>],
+    theme: &SyntaxTheme,
+) -> Option<HighlightStyle> {
+    let mut capture_name = match token_type {
+        "type" | "class" | "enum" | "interface" | "struct" | "typeParameter" | "typeAlias"
+        | "union" | "builtinType" => "type",
+        "enumMember" => "variant",
+        "parameter" => "variable.parameter",
+        "selfKeyword" | "selfTypeKeyword" => "variable.special",
+        "event" => "property",
+        "method" => "function.method",
+        "macro" => "function.macro",
+        "modifier" => "keyword",
+        "regexp" => "string.regex",
+        "decorator" => "attribute",
+        token_type => token_type,
+    }
+    .to_string();
+    for token_modifier in token_modifiers {
+        capture_name.push('.');
+        capture_name.push_str(match token_modifier.as_ref() {
+            "documentation" => "doc",
+            "defaultLibrary" => "builtin",
+            token_modifier => token_modifier,
+        });
+    }
+
+    let mut style = HighlightId::for_capture_name(&capture_name, theme).style(theme);
+    if token_modifiers
+        .iter()
+        .any(|modifier| modifier.as_ref() == "mutable")
+    {
+        style.get_or_insert_with(Default::default).underline = Some(UnderlineStyle {
+            thickness: 1.0.into(),
+            ..Default::default()
+        });
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::rgba;
+
+    #[test]
+    fn test_semantic_token_style() {
+        let theme = SyntaxTheme {
+            highlights: [
+                ("comment", rgba(0x100000ff)),
+                ("comment.doc", rgba(0x200000ff)),
+                ("function", rgba(0x300000ff)),
+                ("variable", rgba(0x400000ff)),
+                ("variable.special", rgba(0x500000ff)),
+            ]
+            .iter()
+            .map(|(name, color)| (name.to_string(), (*color).into()))
+            .collect(),
+        };
+
+        let color = |token_type: &str, token_modifiers: &[&str]| {
+            let token_modifiers = token_modifiers
+                .iter()
+                .map(|modifier| Arc::from(*modifier))
+                .collect::<Vec<_>>();
+            semantic_token_style(token_type, &token_modifiers, &theme).and_then(|style| style.color)
+        };
+        assert_eq!(color("comment", &[]), Some(rgba(0x100000ff).into()));
+        assert_eq!(
+            color("comment", &["documentation"]),
+            Some(rgba(0x200000ff).into())
+        );
+        assert_eq!(color("macro", &[]), Some(rgba(0x300000ff).into()));
+        assert_eq!(color("selfKeyword", &[]), Some(rgba(0x500000ff).into()));
+        assert_eq!(color("namespace", &[]), None);
+
+        let mutable_variable = semantic_token_style(
+            "variable",
+            &[Arc::from("declaration"), Arc::from("mutable")],
+            &theme,
+        )
+        .unwrap();
+        assert_eq!(mutable_variable.color, Some(rgba(0x400000ff).into()));
+        assert!(mutable_variable.underline.is_some());
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{hover_popover::parse_blocks, Editor, EditorSettings, EditorStyle, ShowSignatureHelp};
+use gpui::{
+    AnyElement, FontWeight, HighlightStyle, Size, StatefulInteractiveElement, StyledText, Task,
+    WeakView,
+};
+use language::ParsedMarkdown;
+use project::SignatureHelp;
+use settings::Settings;
+use std::time::Duration;
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::Workspace;
+
+const SIGNATURE_HELP_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(75);
+
+#[derive(Default)]
+pub struct SignatureHelpState {
+    popover: Option<SignatureHelpPopover>,
+    pending_request: Option<Task<Option<()>>>,
+}
+
+impl SignatureHelpState {
+    pub fn is_shown(&self) -> bool {
+        self.popover.is_some()
+    }
+
+    pub fn render(
+        &mut self,
+        style: &EditorStyle,
+        max_size: Size<Pixels>,
+        workspace: Option<WeakView<Workspace>>,
+        cx: &mut ViewContext<Editor>,
+    ) -> Option<AnyElement> {
+        self.popover
+            .as_mut()
+            .map(|popover| popover.render(style, max_size, workspace, cx))
+    }
+}
+
+pub struct SignatureHelpPopover {
+    signature_help: SignatureHelp,
+    parsed_documentation: Option<ParsedMarkdown>,
+}
+
+impl SignatureHelpPopover {
+    pub fn render(
+        &mut self,
+        style: &EditorStyle,
+        max_size: Size<Pixels>,
+        workspace: Option<WeakView<Workspace>>,
+        cx: &mut ViewContext<Editor>,
+    ) -> AnyElement {
+        let signature_count = self.signature_help.signatures.len();
+        let active_signature = self.signature_help.active_signature;
+        let signature = &self.signature_help.signatures[active_signature];
+        let active_parameter_highlight = signature
+            .active_parameter
+            .and_then(|ix| signature.parameters.get(ix)?.label_range.clone())
+            .map(|label_range| {
+                (
+                    label_range,
+                    HighlightStyle {
+                        font_weight: Some(FontWeight::BOLD),
+                        color: Some(cx.theme().colors().text_accent),
+                        ..Default::default()
+                    },
+                )
+            });
+        let label = StyledText::new(signature.label.clone())
+            .with_highlights(&style.text, active_parameter_highlight);
+        let documentation = self.parsed_documentation.as_ref().map(|documentation| {
+            crate::render_parsed_markdown(
+                "signature_help_documentation",
+                documentation,
+                style,
+                workspace,
+                cx,
+            )
+        });
+
+        div()
+            .id("signature_help_popover")
+            .elevation_2(cx)
+            .p_2()
+            .overflow_y_scroll()
+            .max_w(max_size.width)
+            .max_h(max_size.height)
+            // Prevent a mouse move on the popover from being propagated to the editor,
+            // because that would dismiss the hover popover.
+            .on_mouse_move(|_, cx| cx.stop_propagation())
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(label)
+                    .when(signature_count > 1, |this| {
+                        this.child(
+                            Label::new(format!("{}/{}", active_signature + 1, signature_count))
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                    }),
+            )
+            .when_some(documentation, |this, documentation| {
+                this.child(div().pt_1().child(documentation))
+            })
+            .into_any_element()
+    }
+}
+
+impl Editor {
+    pub fn show_signature_help(&mut self, _: &ShowSignatureHelp, cx: &mut ViewContext<Self>) {
+        self.request_signature_help(false, cx);
+    }
+
+    /// Hides the signature help popover, returning whether it was shown.
+    pub fn hide_signature_help(&mut self, cx: &mut ViewContext<Self>) -> bool {
+        self.signature_help_state.pending_request = None;
+        let did_hide = self.signature_help_state.popover.take().is_some();
+        if did_hide {
+            cx.notify();
+        }
+        did_hide
+    }
+
+    /// Shows signature help when the language server declared the inserted text as one of its
+    /// trigger characters, or updates the shown popover on one of its retrigger characters.
+    pub(crate) fn trigger_signature_help_on_input(
+        &mut self,
+        text: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if !EditorSettings::get_global(cx).show_signature_help_on_input {
+            return;
+        }
+
+        let head = self.selections.newest_anchor().head();
+        let Some((buffer, _)) = self.buffer.read(cx).text_anchor_for_position(head, cx) else {
+            return;
+        };
+        let triggers = buffer.read(cx).signature_help_triggers();
+        let is_trigger = triggers
+            .trigger_characters
+            .iter()
+            .any(|trigger| trigger == text);
+        let is_retrigger = self.signature_help_state.is_shown()
+            && triggers
+                .retrigger_characters
+                .iter()
+                .any(|trigger| trigger == text);
+        if is_trigger || is_retrigger {
+            self.request_signature_help(false, cx);
+        }
+    }
+
+    /// Re-queries the shown signature help once the cursor stops moving, so that the active
+    /// parameter follows it.
+    pub(crate) fn refresh_signature_help(&mut self, cx: &mut ViewContext<Self>) {
+        if self.signature_help_state.is_shown() {
+            self.request_signature_help(true, cx);
+        }
+    }
+
+    /// Replacing the pending request drops it, which cancels it on the language server.
+    fn request_signature_help(&mut self, debounce: bool, cx: &mut ViewContext<Self>) {
+        let Some(project) = self.project.clone() else {
+            return;
+        };
+        let head = self.selections.newest::<usize>(cx).head();
+        let Some((buffer, buffer_position)) =
+            self.buffer.read(cx).text_anchor_for_position(head, cx)
+        else {
+            return;
+        };
+
+        let language = buffer.read(cx).language().cloned();
+        let language_registry = project.read(cx).languages().clone();
+        self.signature_help_state.pending_request = Some(cx.spawn(|editor, mut cx| async move {
+            if debounce {
+                cx.background_executor()
+                    .timer(SIGNATURE_HELP_DEBOUNCE_TIMEOUT)
+                    .await;
+            }
+
+            let request = project
+                .update(&mut cx, |project, cx| {
+                    project.signature_help(&buffer, buffer_position, cx)
+                })
+                .ok()?;
+            let popover = match request.await.log_err().flatten() {
+                Some(signature_help) => {
+                    let documentation_blocks = signature_help
+                        .active_signature()
+                        .map(|signature| {
+                            let parameter_documentation = signature
+                                .active_parameter
+                                .and_then(|ix| signature.parameters.get(ix))
+                                .and_then(|parameter| parameter.documentation.clone());
+                            parameter_documentation
+                                .into_iter()
+                                .chain(signature.documentation.clone())
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+                    let parsed_documentation = if documentation_blocks.is_empty() {
+                        None
+                    } else {
+                        Some(
+                            parse_blocks(&documentation_blocks, &language_registry, language).await,
+                        )
+                    };
+                    Some(SignatureHelpPopover {
+                        signature_help,
+                        parsed_documentation,
+                    })
+                }
+                None => None,
+            };
+
+            editor
+                .update(&mut cx, |editor, cx| {
+                    editor.signature_help_state.popover = popover;
+                    cx.notify();
+                })
+                .log_err()
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use indoc::indoc;
+    use smol::stream::StreamExt;
+
+    #[gpui::test]
+    async fn test_signature_help_on_input(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                signature_help_provider: Some(lsp::SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string()]),
+                    retrigger_characters: Some(vec![",".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            fn sample(a: u8, b: u8) {}
+            fn main() { sampleˇ }
+        "});
+        let mut requests = cx.handle_request::<lsp::request::SignatureHelpRequest, _, _>(
+            |_, params, _| async move {
+                let active_parameter =
+                    if params.text_document_position_params.position.character < 21 {
+                        0
+                    } else {
+                        1
+                    };
+                Ok(Some(lsp::SignatureHelp {
+                    signatures: vec![lsp::SignatureInformation {
+                        label: "fn sample(a: u8, b: u8)".to_string(),
+                        documentation: None,
+                        parameters: Some(vec![
+                            lsp::ParameterInformation {
+                                label: lsp::ParameterLabel::Simple("a: u8".to_string()),
+                                documentation: None,
+                            },
+                            lsp::ParameterInformation {
+                                label: lsp::ParameterLabel::LabelOffsets([17, 22]),
+                                documentation: None,
+                            },
+                        ]),
+                        active_parameter: None,
+                    }],
+                    active_signature: Some(0),
+                    active_parameter: Some(active_parameter),
+                }))
+            },
+        );
+
+        let active_parameter_range = |cx: &mut EditorLspTestContext| {
+            cx.editor(|editor, _| {
+                editor
+                    .signature_help_state
+                    .popover
+                    .as_ref()
+                    .and_then(|popover| {
+                        let signature = popover.signature_help.active_signature().unwrap();
+                        signature.parameters[signature.active_parameter.unwrap()]
+                            .label_range
+                            .clone()
+                    })
+            })
+        };
+
+        // Typing a non-trigger character does not request signature help.
+        cx.simulate_keystroke("1");
+        cx.run_until_parked();
+        assert_eq!(active_parameter_range(&mut cx), None);
+        cx.simulate_keystroke("backspace");
+
+        // A trigger character shows the popover, with the first parameter active.
+        cx.simulate_keystroke("(");
+        requests.next().await;
+        cx.run_until_parked();
+        assert_eq!(active_parameter_range(&mut cx), Some(10..15));
+
+        // A retrigger character moves to the next parameter.
+        cx.simulate_keystroke("1");
+        cx.simulate_keystroke(",");
+        requests.next().await;
+        cx.run_until_parked();
+        assert_eq!(active_parameter_range(&mut cx), Some(17..22));
+
+        // Moving the cursor re-queries the shown signature help once it stops moving.
+        cx.update_editor(|editor, cx| editor.move_left(&crate::MoveLeft, cx));
+        cx.run_until_parked();
+        assert_eq!(active_parameter_range(&mut cx), Some(17..22));
+        cx.executor().advance_clock(SIGNATURE_HELP_DEBOUNCE_TIMEOUT);
+        requests.next().await;
+        cx.run_until_parked();
+        assert_eq!(active_parameter_range(&mut cx), Some(10..15));
+
+        cx.update_editor(|editor, cx| editor.cancel(&crate::Cancel, cx));
+        assert_eq!(active_parameter_range(&mut cx), None);
+    }
+
+    #[gpui::test]
+    async fn test_signature_help_parameter_missing_from_label(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                signature_help_provider: Some(lsp::SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            fn sample(a: u8, b: u8) {}
+            fn main() { sampleˇ }
+        "});
+        let mut requests =
+            cx.handle_request::<lsp::request::SignatureHelpRequest, _, _>(|_, _, _| async move {
+                Ok(Some(lsp::SignatureHelp {
+                    signatures: vec![lsp::SignatureInformation {
+                        label: "fn sample(a: u8, b: u8)".to_string(),
+                        documentation: None,
+                        parameters: Some(vec![
+                            lsp::ParameterInformation {
+                                label: lsp::ParameterLabel::Simple("c: u8".to_string()),
+                                documentation: None,
+                            },
+                            lsp::ParameterInformation {
+                                label: lsp::ParameterLabel::Simple("b: u8".to_string()),
+                                documentation: None,
+                            },
+                        ]),
+                        active_parameter: None,
+                    }],
+                    active_signature: Some(0),
+                    active_parameter: Some(1),
+                }))
+            });
+
+        cx.simulate_keystroke("(");
+        requests.next().await;
+        cx.run_until_parked();
+        cx.editor(|editor, _| {
+            let popover = editor.signature_help_state.popover.as_ref().unwrap();
+            let signature = popover.signature_help.active_signature().unwrap();
+            let label_ranges = signature
+                .parameters
+                .iter()
+                .map(|parameter| parameter.label_range.clone())
+                .collect::<Vec<_>>();
+            // The active parameter still points at the server's second parameter.
+            assert_eq!(label_ranges, [None, Some(17..22)]);
+            assert_eq!(signature.active_parameter, Some(1));
+        });
+    }
+}
@@ -0,0 +1,98 @@
+use crate::Editor;
+use gpui::AppContext;
+use language::{CharKind, Point};
+use std::path::Path;
+use time::OffsetDateTime;
+
+impl Editor {
+    /// Resolves a variable in a snippet, such as `$TM_FILENAME` or `$CURRENT_YEAR`, at the newest
+    /// selection. Returns `None` for variables that aren't known.
+    pub(crate) fn resolve_snippet_variable(&self, name: &str, cx: &AppContext) -> Option<String> {
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let selection = self.selections.newest::<Point>(cx);
+        let head = selection.head();
+        let file = snapshot.file_at(head);
+        let file_name = || {
+            file.map(|file| file.file_name(cx).to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let file_path = || {
+            file.map(|file| match file.as_local() {
+                Some(file) => file.abs_path(cx),
+                None => file.full_path(cx),
+            })
+            .unwrap_or_default()
+        };
+        let timezone = cx.local_timezone();
+        let now = || OffsetDateTime::now_utc().to_offset(timezone);
+        let abbreviate = |name: String| name.chars().take(3).collect::<String>();
+
+        let value = match name {
+            "TM_SELECTED_TEXT" => snapshot.text_for_range(selection.range()).collect(),
+            "TM_CURRENT_LINE" => snapshot
+                .text_for_range(
+                    Point::new(head.row, 0)..Point::new(head.row, snapshot.line_len(head.row)),
+                )
+                .collect(),
+            "TM_CURRENT_WORD" => match snapshot.surrounding_word(head) {
+                (range, Some(CharKind::Word)) => snapshot.text_for_range(range).collect(),
+                _ => String::new(),
+            },
+            "TM_LINE_INDEX" => head.row.to_string(),
+            "TM_LINE_NUMBER" => (head.row + 1).to_string(),
+            "TM_FILENAME" => file_name(),
+            "TM_FILENAME_BASE" => {
+                let file_name = file_name();
+                match Path::new(&file_name).file_stem() {
+                    Some(stem) => stem.to_string_lossy().into_owned(),
+                    None => file_name,
+                }
+            }
+            "TM_DIRECTORY" => file_path()
+                .parent()
+                .map(|directory| directory.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "TM_FILEPATH" => file_path().to_string_lossy().into_owned(),
+            "RELATIVE_FILEPATH" => file
+                .map(|file| file.path().to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "CLIPBOARD" => cx
+                .read_from_clipboard()
+                .map(|item| item.text().to_owned())
+                .unwrap_or_default(),
+            "CURRENT_YEAR" => now().year().to_string(),
+            "CURRENT_YEAR_SHORT" => format!("{:02}", now().year() % 100),
+            "CURRENT_MONTH" => format!("{:02}", u8::from(now().month())),
+            "CURRENT_MONTH_NAME" => now().month().to_string(),
+            "CURRENT_MONTH_NAME_SHORT" => abbreviate(now().month().to_string()),
+            "CURRENT_DATE" => format!("{:02}", now().day()),
+            "CURRENT_DAY_NAME" => now().weekday().to_string(),
+            "CURRENT_DAY_NAME_SHORT" => abbreviate(now().weekday().to_string()),
+            "CURRENT_HOUR" => format!("{:02}", now().hour()),
+            "CURRENT_MINUTE" => format!("{:02}", now().minute()),
+            "CURRENT_SECOND" => format!("{:02}", now().second()),
+            "CURRENT_SECONDS_UNIX" => now().unix_timestamp().to_string(),
+            "RANDOM" => format!("{:06}", rand::random::<u32>() % 1_000_000),
+            "RANDOM_HEX" => format!("{:06x}", rand::random::<u32>() & 0xff_ffff),
+            "LINE_COMMENT" | "BLOCK_COMMENT_START" | "BLOCK_COMMENT_END" => {
+                let scope = snapshot.language_scope_at(head);
+                let comment = match name {
+                    "LINE_COMMENT" => scope
+                        .as_ref()
+                        .and_then(|scope| scope.line_comment_prefixes()?.first().cloned()),
+                    "BLOCK_COMMENT_START" => scope
+                        .as_ref()
+                        .and_then(|scope| Some(scope.block_comment_delimiters()?.0.clone())),
+                    _ => scope
+                        .as_ref()
+                        .and_then(|scope| Some(scope.block_comment_delimiters()?.1.clone())),
+                };
+                comment
+                    .map(|comment| comment.trim().to_string())
+                    .unwrap_or_default()
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
@@ -0,0 +1,322 @@
+use collections::HashMap;
+use futures::{channel::mpsc, StreamExt};
+use gpui::AppContext;
+use language::{
+    Buffer, CharKind, CodeLabel, Completion, CompletionSource, Documentation, LanguageScope,
+    ToOffset,
+};
+use serde::Deserialize;
+use std::path::PathBuf;
+use util::ResultExt;
+
+/// The snippets in the user's snippets directory, which holds a file per language named after
+/// it, such as `rust.json` or `tsx.json`. The files use the format of VS Code snippet files,
+/// whose `scope` key is ignored since it only applies to VS Code's global snippet files.
+#[derive(Default)]
+pub struct UserSnippets {
+    /// The snippets of each language, keyed by its lowercased name.
+    snippets_by_language: HashMap<String, Vec<UserSnippet>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct UserSnippet {
+    prefixes: Vec<String>,
+    body: String,
+    description: Option<String>,
+    /// The overrides of the language that the snippet is offered in, such as `element` for
+    /// JSX. If empty, the snippet is offered anywhere in the language.
+    overrides: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SnippetDefinition {
+    prefix: StringOrList,
+    body: StringOrList,
+    #[serde(default)]
+    description: Option<String>,
+    /// The tree-sitter overrides of the language to offer the snippet in. This key is specific to
+    /// Zed, so that it doesn't clash with the keys of VS Code's snippet files.
+    #[serde(default)]
+    zed_overrides: Option<StringOrList>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrList {
+    String(String),
+    List(Vec<String>),
+}
+
+impl StringOrList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            StringOrList::String(string) => vec![string],
+            StringOrList::List(list) => list,
+        }
+    }
+}
+
+impl UserSnippets {
+    /// Parses the contents of the snippet files, skipping the files and the snippets that are
+    /// invalid.
+    pub fn parse(files: &[(PathBuf, String)]) -> Self {
+        let mut snippets_by_language = HashMap::<String, Vec<UserSnippet>>::default();
+        for (path, contents) in files {
+            let Some(language) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Some(definitions) =
+                settings::parse_json_with_comments::<HashMap<String, serde_json::Value>>(contents)
+                    .log_err()
+            else {
+                continue;
+            };
+
+            let mut definitions = definitions.into_iter().collect::<Vec<_>>();
+            definitions.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            // A snippet that can't be parsed doesn't prevent the others in its file from loading.
+            let snippets = definitions
+                .into_iter()
+                .filter_map(|(name, definition)| {
+                    match serde_json::from_value::<SnippetDefinition>(definition) {
+                        Ok(definition) => Some(definition),
+                        Err(error) => {
+                            log::error!("invalid snippet {name:?} in {path:?}: {error}");
+                            None
+                        }
+                    }
+                })
+                .map(|definition| UserSnippet {
+                    prefixes: definition.prefix.into_vec(),
+                    body: definition.body.into_vec().join("\n"),
+                    description: definition.description,
+                    overrides: definition
+                        .zed_overrides
+                        .map(StringOrList::into_vec)
+                        .unwrap_or_default(),
+                })
+                .collect::<Vec<_>>();
+            if !snippets.is_empty() {
+                snippets_by_language
+                    .entry(language.to_lowercase())
+                    .or_default()
+                    .extend(snippets);
+            }
+        }
+        Self {
+            snippets_by_language,
+        }
+    }
+
+    fn snippets_in_scope<'a>(
+        &'a self,
+        scope: &LanguageScope,
+    ) -> impl 'a + Iterator<Item = &'a UserSnippet> {
+        let override_name = scope.override_name().map(str::to_string);
+        self.snippets_by_language
+            .get(&scope.language_name().to_lowercase())
+            .into_iter()
+            .flatten()
+            .filter(move |snippet| {
+                snippet.overrides.is_empty()
+                    || override_name
+                        .as_ref()
+                        .map_or(false, |name| snippet.overrides.contains(name))
+            })
+    }
+}
+
+/// Reloads the user's snippets whenever the snippet files change.
+pub fn handle_snippet_file_changes(
+    mut snippet_files_rx: mpsc::UnboundedReceiver<Vec<(PathBuf, String)>>,
+    cx: &mut AppContext,
+) {
+    cx.set_global(UserSnippets::default());
+    cx.spawn(move |cx| async move {
+        while let Some(files) = snippet_files_rx.next().await {
+            let snippets = UserSnippets::parse(&files);
+            if cx.update(|cx| cx.set_global(snippets)).is_err() {
+                break; // App dropped
+            }
+        }
+    })
+    .detach();
+}
+
+/// Returns completions for the user's snippets in the language scope at `position`, replacing
+/// the word before it. Snippets are only offered once a word has been started.
+pub(crate) fn user_snippet_completions(
+    buffer: &Buffer,
+    position: text::Anchor,
+    cx: &AppContext,
+) -> Vec<Completion> {
+    let Some(user_snippets) = cx.try_global::<UserSnippets>() else {
+        return Vec::new();
+    };
+    let snapshot = buffer.snapshot();
+    let offset = position.to_offset(&snapshot);
+    let Some(scope) = snapshot.language_scope_at(offset) else {
+        return Vec::new();
+    };
+    let (word_range, kind) = snapshot.surrounding_word(offset);
+    if kind != Some(CharKind::Word) || word_range.start >= offset {
+        return Vec::new();
+    }
+
+    let old_range = snapshot.anchor_before(word_range.start)..snapshot.anchor_after(offset);
+    user_snippets
+        .snippets_in_scope(&scope)
+        .flat_map(|snippet| {
+            let old_range = old_range.clone();
+            snippet.prefixes.iter().map(move |prefix| {
+                let documentation = match &snippet.description {
+                    Some(description) => Documentation::SingleLine(description.clone()),
+                    None => Documentation::MultiLinePlainText(snippet.body.clone()),
+                };
+                Completion {
+                    old_range: old_range.clone(),
+                    new_text: snippet.body.clone(),
+                    label: CodeLabel::plain(prefix.clone(), None),
+                    source: CompletionSource::Custom,
+                    documentation: Some(documentation),
+                    lsp_completion: lsp::CompletionItem {
+                        label: prefix.clone(),
+                        kind: Some(lsp::CompletionItemKind::SNIPPET),
+                        insert_text: Some(snippet.body.clone()),
+                        insert_text_format: Some(lsp::InsertTextFormat::SNIPPET),
+                        ..Default::default()
+                    },
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use project::FakeFs;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_user_snippets() {
+        let files = [
+            (
+                Path::new("/snippets/Rust.json").to_path_buf(),
+                r##"{
+                    // Comments are allowed, as in VS Code.
+                    "Test function": {
+                        "prefix": ["test", "tst"],
+                        "body": ["#[test]", "fn ${1:name}() {", "    $0", "}"],
+                        "description": "A test function"
+                    },
+                    "Print": {
+                        "prefix": "pr",
+                        "body": "println!(\"$1\");",
+                        "zed_overrides": ["string", "comment"]
+                    },
+                    // VS Code's `scope` key only applies to global snippet files.
+                    "Vec": {
+                        "prefix": "vec",
+                        "body": "vec![$0]",
+                        "scope": "rust"
+                    },
+                    // Invalid snippets are skipped, without affecting the others.
+                    "Missing body": {
+                        "prefix": "mb"
+                    }
+                }"##
+                .to_string(),
+            ),
+            (
+                Path::new("/snippets/broken.json").to_path_buf(),
+                "{ \"x\": 1 }".to_string(),
+            ),
+        ];
+
+        let snippets = UserSnippets::parse(&files);
+        assert!(snippets.snippets_by_language.get("broken").is_none());
+        assert_eq!(
+            snippets.snippets_by_language["rust"],
+            &[
+                UserSnippet {
+                    prefixes: vec!["pr".into()],
+                    body: "println!(\"$1\");".into(),
+                    description: None,
+                    overrides: vec!["string".into(), "comment".into()],
+                },
+                UserSnippet {
+                    prefixes: vec!["test".into(), "tst".into()],
+                    body: "#[test]\nfn ${1:name}() {\n    $0\n}".into(),
+                    description: Some("A test function".into()),
+                    overrides: Vec::new(),
+                },
+                UserSnippet {
+                    prefixes: vec!["vec".into()],
+                    body: "vec![$0]".into(),
+                    description: None,
+                    overrides: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_reloading_user_snippets(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file(
+            "/snippets/rust.json",
+            r#"{ "Print": { "prefix": "pri", "body": "println!($1);" } }"#.to_string(),
+        )
+        .await;
+        cx.update(|cx| {
+            let snippet_files_rx = settings::watch_config_dir(
+                cx.background_executor(),
+                fs.clone(),
+                PathBuf::from("/snippets"),
+            );
+            handle_snippet_file_changes(snippet_files_rx, cx);
+        });
+        cx.run_until_parked();
+
+        let prefixes = |cx: &mut TestAppContext| {
+            cx.read(|cx| {
+                let mut prefixes = cx
+                    .global::<UserSnippets>()
+                    .snippets_by_language
+                    .iter()
+                    .flat_map(|(language, snippets)| {
+                        snippets.iter().flat_map(move |snippet| {
+                            snippet
+                                .prefixes
+                                .iter()
+                                .map(move |prefix| format!("{language}: {prefix}"))
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                prefixes.sort_unstable();
+                prefixes
+            })
+        };
+        assert_eq!(prefixes(cx), &["rust: pri"]);
+
+        // Editing a snippet file reloads the snippets.
+        fs.insert_file(
+            "/snippets/rust.json",
+            r#"{ "Print": { "prefix": ["pr", "print"], "body": "println!($1);" } }"#.to_string(),
+        )
+        .await;
+        cx.run_until_parked();
+        assert_eq!(prefixes(cx), &["rust: pr", "rust: print"]);
+
+        // So does adding one.
+        fs.insert_file(
+            "/snippets/python.json",
+            r#"{ "Print": { "prefix": "pr", "body": "print($1)" } }"#.to_string(),
+        )
+        .await;
+        cx.run_until_parked();
+        assert_eq!(prefixes(cx), &["python: pr", "rust: pr", "rust: print"]);
+    }
+}
@@ -6,6 +6,7 @@ use serde_derive::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
     ffi::OsStr,
+    ops::Range,
     os::unix::prelude::OsStrExt,
     path::{Component, Path, PathBuf},
     sync::Arc,
@@ -23,9 +24,60 @@ pub struct Branch {
     pub unix_timestamp: Option<i64>,
 }
 
+/// The commit that last changed a range of lines of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameEntry {
+    /// The hash of the commit, or `None` for lines that haven't been committed yet.
+    pub sha: Option<String>,
+    /// The rows of the blamed text that were last changed by the commit.
+    pub rows: Range<u32>,
+    pub author: String,
+    pub author_email: String,
+    /// Timestamp of the commit, normalized to Unix Epoch format.
+    pub unix_timestamp: i64,
+    pub summary: String,
+}
+
+/// A commit in the history of a repository.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub sha: String,
+    pub author: String,
+    pub author_email: String,
+    /// Timestamp of the commit, normalized to Unix Epoch format.
+    pub unix_timestamp: i64,
+    pub summary: String,
+}
+
+/// The abbreviated hash of a commit, as shown by `git log --oneline`.
+pub fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// The date of a commit in the given timezone, formatted as `YYYY-MM-DD`.
+pub fn format_commit_date(unix_timestamp: i64, timezone: time::UtcOffset) -> String {
+    let Some(timestamp) = time::OffsetDateTime::from_unix_timestamp(unix_timestamp).log_err()
+    else {
+        return String::new();
+    };
+    let date = timestamp.to_offset(timezone).date();
+    format!(
+        "{}-{:02}-{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    )
+}
+
 pub trait GitRepository: Send {
     fn reload_index(&self);
     fn load_index_text(&self, relative_file_path: &Path) -> Option<String>;
+    fn load_head_text(&self, relative_file_path: &Path) -> Option<String>;
+
+    /// Replaces the staged contents of the given file, or removes the file from the index
+    /// if `content` is `None`.
+    fn set_index_text(&self, path: &RepoPath, content: Option<&str>) -> Result<()>;
+
     fn branch_name(&self) -> Option<String>;
 
     /// Get the statuses of all of the files in the index that start with the given
@@ -46,9 +98,34 @@ pub trait GitRepository: Send {
     /// no need to consider the working directory file if the mtime matches.
     fn status(&self, path: &RepoPath, mtime: SystemTime) -> Option<GitFileStatus>;
 
+    /// Stages the contents of the given files in the working directory, removing the files
+    /// that no longer exist from the index.
+    fn stage_paths(&self, paths: &[RepoPath]) -> Result<()>;
+
+    /// Restores the given files in the index to their contents in the HEAD commit.
+    fn unstage_paths(&self, paths: &[RepoPath]) -> Result<()>;
+
+    /// Commits the contents of the index onto the current branch.
+    fn commit(&self, message: &str) -> Result<()>;
+
     fn branches(&self) -> Result<Vec<Branch>>;
     fn change_branch(&self, _: &str) -> Result<()>;
     fn create_branch(&self, _: &str) -> Result<()>;
+
+    /// Get the commits that last changed each line of the given file, whose current
+    /// text is `content`. Lines that differ from the HEAD commit are attributed to no
+    /// commit.
+    fn blame(&self, path: &RepoPath, content: &str) -> Result<Vec<BlameEntry>>;
+
+    /// List at most `limit` commits reachable from HEAD, newest first. When a path is given,
+    /// only the commits that changed the file or directory at that path are listed.
+    fn commit_history(&self, path: Option<&RepoPath>, limit: usize) -> Result<Vec<CommitSummary>>;
+
+    /// List the files that the given commit changed with respect to its first parent.
+    fn changed_paths(&self, sha: &str) -> Result<Vec<RepoPath>>;
+
+    /// Load the contents of the given file as of the given commit.
+    fn load_commit_text(&self, sha: &str, path: &RepoPath) -> Result<Option<String>>;
 }
 
 impl std::fmt::Debug for dyn GitRepository {
@@ -88,6 +165,50 @@ impl GitRepository for LibGitRepository {
         None
     }
 
+    fn load_head_text(&self, relative_file_path: &Path) -> Option<String> {
+        fn logic(repo: &LibGitRepository, relative_file_path: &Path) -> Result<Option<String>> {
+            check_path_to_repo_path_errors(relative_file_path)?;
+
+            let tree = repo.head()?.peel_to_tree()?;
+            let oid = match tree.get_path(relative_file_path) {
+                Ok(entry) => entry.id(),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            let content = repo.find_blob(oid)?.content().to_owned();
+            Ok(Some(String::from_utf8(content)?))
+        }
+
+        match logic(self, relative_file_path) {
+            Ok(value) => return value,
+            Err(err) => log::error!("Error loading head text: {:?}", err),
+        }
+        None
+    }
+
+    fn set_index_text(&self, path: &RepoPath, content: Option<&str>) -> Result<()> {
+        const STAGE_NORMAL: i32 = 0;
+        check_path_to_repo_path_errors(path)?;
+        let mut index = self.index()?;
+        match content {
+            Some(content) => {
+                let mut entry = index
+                    .get_path(path, STAGE_NORMAL)
+                    .ok_or_else(|| anyhow::anyhow!("{path:?} is not in the index"))?;
+                entry.id = self.blob(content.as_bytes())?;
+                entry.file_size = content.len() as u32;
+                // Clear the modification time so that the working directory file is compared
+                // against the new blob rather than assumed to match it.
+                entry.mtime = git2::IndexTime::new(0, 0);
+                index.add(&entry)?;
+            }
+            None => index.remove_path(path)?,
+        }
+        index.write()?;
+        Ok(())
+    }
+
     fn branch_name(&self) -> Option<String> {
         let head = self.head().log_err()?;
         let branch = String::from_utf8_lossy(head.shorthand_bytes());
@@ -155,6 +276,69 @@ impl GitRepository for LibGitRepository {
         status
     }
 
+    fn stage_paths(&self, paths: &[RepoPath]) -> Result<()> {
+        let workdir = self
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!("can't stage files in a bare repository"))?;
+        let mut index = self.index()?;
+        for path in paths {
+            check_path_to_repo_path_errors(path)?;
+            if workdir.join(path).exists() {
+                index.add_path(path)?;
+            } else {
+                index.remove_path(path)?;
+            }
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    fn unstage_paths(&self, paths: &[RepoPath]) -> Result<()> {
+        for path in paths {
+            check_path_to_repo_path_errors(path)?;
+        }
+        match self.head() {
+            Ok(head) => {
+                let head = head.peel_to_commit()?;
+                self.reset_default(
+                    Some(head.as_object()),
+                    paths.iter().map(|path| path.as_path()),
+                )?;
+            }
+            // Nothing has been committed yet, so the files are unstaged by removing them.
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => {
+                let mut index = self.index()?;
+                for path in paths {
+                    index.remove_path(path)?;
+                }
+                index.write()?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        let signature = self.signature()?;
+        let tree_id = self.index()?.write_tree()?;
+        let tree = self.find_tree(tree_id)?;
+        let parent = match self.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err.into()),
+        };
+        let parents = parent.iter().collect::<Vec<_>>();
+        self.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?;
+        Ok(())
+    }
+
     fn branches(&self) -> Result<Vec<Branch>> {
         let local_branches = self.branches(Some(BranchType::Local))?;
         let valid_branches = local_branches
@@ -195,6 +379,132 @@ impl GitRepository for LibGitRepository {
 
         Ok(())
     }
+
+    fn blame(&self, path: &RepoPath, content: &str) -> Result<Vec<BlameEntry>> {
+        check_path_to_repo_path_errors(path)?;
+        let blame = self.blame_file(path, None)?;
+        let blame = blame.blame_buffer(content.as_bytes())?;
+
+        let mut summaries = HashMap::default();
+        let mut entries = Vec::new();
+        for hunk in blame.iter() {
+            // Line numbers are 1-based.
+            let start_row = hunk.final_start_line().saturating_sub(1) as u32;
+            let rows = start_row..start_row + hunk.lines_in_hunk() as u32;
+            let oid = hunk.final_commit_id();
+            if oid.is_zero() {
+                entries.push(BlameEntry {
+                    sha: None,
+                    rows,
+                    author: String::new(),
+                    author_email: String::new(),
+                    unix_timestamp: 0,
+                    summary: String::new(),
+                });
+                continue;
+            }
+
+            let summary = match summaries.get(&oid) {
+                Some(summary) => summary.clone(),
+                None => {
+                    let commit = self.find_commit(oid)?;
+                    let summary = commit.summary().unwrap_or_default().to_string();
+                    summaries.insert(oid, summary.clone());
+                    summary
+                }
+            };
+            let signature = hunk.final_signature();
+            entries.push(BlameEntry {
+                sha: Some(oid.to_string()),
+                rows,
+                author: signature.name().unwrap_or_default().to_string(),
+                author_email: signature.email().unwrap_or_default().to_string(),
+                unix_timestamp: signature.when().seconds(),
+                summary,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn commit_history(&self, path: Option<&RepoPath>, limit: usize) -> Result<Vec<CommitSummary>> {
+        if let Some(path) = path {
+            check_path_to_repo_path_errors(path)?;
+        }
+        let mut revwalk = self.revwalk()?;
+        match revwalk.push_head() {
+            Ok(()) => {}
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        }
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        // The id of the blob or tree at the filtered path, which changes whenever its contents do.
+        let path_id = |tree: &git2::Tree| -> Option<git2::Oid> {
+            match path {
+                Some(path) => tree.get_path(path).ok().map(|entry| entry.id()),
+                None => Some(tree.id()),
+            }
+        };
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            if commits.len() >= limit {
+                break;
+            }
+            let commit = self.find_commit(oid?)?;
+            if path.is_some() {
+                let parent_id = match commit.parents().next() {
+                    Some(parent) => path_id(&parent.tree()?),
+                    None => None,
+                };
+                if path_id(&commit.tree()?) == parent_id {
+                    continue;
+                }
+            }
+
+            let author = commit.author();
+            commits.push(CommitSummary {
+                sha: commit.id().to_string(),
+                author: author.name().unwrap_or_default().to_string(),
+                author_email: author.email().unwrap_or_default().to_string(),
+                unix_timestamp: commit.time().seconds(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(commits)
+    }
+
+    fn changed_paths(&self, sha: &str) -> Result<Vec<RepoPath>> {
+        let commit = self.find_commit(git2::Oid::from_str(sha)?)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let diff = self.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| {
+                let file = if delta.status() == git2::Delta::Deleted {
+                    delta.old_file()
+                } else {
+                    delta.new_file()
+                };
+                Some(RepoPath(file.path()?.to_path_buf()))
+            })
+            .collect())
+    }
+
+    fn load_commit_text(&self, sha: &str, path: &RepoPath) -> Result<Option<String>> {
+        check_path_to_repo_path_errors(path)?;
+        let tree = self.find_commit(git2::Oid::from_str(sha)?)?.tree()?;
+        let oid = match tree.get_path(path) {
+            Ok(entry) => entry.id(),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let content = self.find_blob(oid)?.content().to_owned();
+        Ok(Some(String::from_utf8(content)?))
+    }
 }
 
 fn matches_index(repo: &LibGitRepository, path: &RepoPath, mtime: SystemTime) -> bool {
@@ -237,10 +547,23 @@ pub struct FakeGitRepository {
 #[derive(Debug, Clone, Default)]
 pub struct FakeGitRepositoryState {
     pub index_contents: HashMap<PathBuf, String>,
+    pub head_contents: HashMap<PathBuf, String>,
+    pub unstaged_statuses: HashMap<RepoPath, GitFileStatus>,
+    pub commit_messages: Vec<String>,
     pub worktree_statuses: HashMap<RepoPath, GitFileStatus>,
+    pub blames: HashMap<PathBuf, Vec<BlameEntry>>,
+    /// The commits reachable from HEAD, newest first.
+    pub commits: Vec<FakeCommit>,
     pub branch_name: Option<String>,
 }
 
+#[derive(Debug, Clone)]
+pub struct FakeCommit {
+    pub summary: CommitSummary,
+    /// The contents of the files that were changed by the commit.
+    pub changed_files: HashMap<PathBuf, String>,
+}
+
 impl FakeGitRepository {
     pub fn open(state: Arc<Mutex<FakeGitRepositoryState>>) -> Arc<Mutex<dyn GitRepository>> {
         Arc::new(Mutex::new(FakeGitRepository { state }))
@@ -255,6 +578,22 @@ impl GitRepository for FakeGitRepository {
         state.index_contents.get(path).cloned()
     }
 
+    fn load_head_text(&self, path: &Path) -> Option<String> {
+        let state = self.state.lock();
+        state.head_contents.get(path).cloned()
+    }
+
+    fn set_index_text(&self, path: &RepoPath, content: Option<&str>) -> Result<()> {
+        let mut state = self.state.lock();
+        match content {
+            Some(content) => state
+                .index_contents
+                .insert(path.0.clone(), content.to_owned()),
+            None => state.index_contents.remove(&path.0),
+        };
+        Ok(())
+    }
+
     fn branch_name(&self) -> Option<String> {
         let state = self.state.lock();
         state.branch_name.clone()
@@ -271,13 +610,45 @@ impl GitRepository for FakeGitRepository {
         map
     }
 
-    fn unstaged_status(&self, _path: &RepoPath, _mtime: SystemTime) -> Option<GitFileStatus> {
-        None
+    fn unstaged_status(&self, path: &RepoPath, _mtime: SystemTime) -> Option<GitFileStatus> {
+        let state = self.state.lock();
+        state.unstaged_statuses.get(path).cloned()
     }
 
     fn status(&self, path: &RepoPath, _mtime: SystemTime) -> Option<GitFileStatus> {
         let state = self.state.lock();
-        state.worktree_statuses.get(path).cloned()
+        state
+            .worktree_statuses
+            .get(path)
+            .or_else(|| state.unstaged_statuses.get(path))
+            .cloned()
+    }
+
+    fn stage_paths(&self, paths: &[RepoPath]) -> Result<()> {
+        let mut state = self.state.lock();
+        for path in paths {
+            if let Some(status) = state.unstaged_statuses.remove(path) {
+                state.worktree_statuses.insert(path.clone(), status);
+            }
+        }
+        Ok(())
+    }
+
+    fn unstage_paths(&self, paths: &[RepoPath]) -> Result<()> {
+        let mut state = self.state.lock();
+        for path in paths {
+            if let Some(status) = state.worktree_statuses.remove(path) {
+                state.unstaged_statuses.insert(path.clone(), status);
+            }
+        }
+        Ok(())
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        let mut state = self.state.lock();
+        state.worktree_statuses.clear();
+        state.commit_messages.push(message.to_owned());
+        Ok(())
     }
 
     fn branches(&self) -> Result<Vec<Branch>> {
@@ -295,6 +666,57 @@ impl GitRepository for FakeGitRepository {
         state.branch_name = Some(name.to_owned());
         Ok(())
     }
+
+    fn blame(&self, path: &RepoPath, _content: &str) -> Result<Vec<BlameEntry>> {
+        let state = self.state.lock();
+        Ok(state.blames.get(&path.0).cloned().unwrap_or_default())
+    }
+
+    fn commit_history(&self, path: Option<&RepoPath>, limit: usize) -> Result<Vec<CommitSummary>> {
+        let state = self.state.lock();
+        Ok(state
+            .commits
+            .iter()
+            .filter(|commit| {
+                path.map_or(true, |path| {
+                    commit
+                        .changed_files
+                        .keys()
+                        .any(|changed_path| changed_path.starts_with(path))
+                })
+            })
+            .take(limit)
+            .map(|commit| commit.summary.clone())
+            .collect())
+    }
+
+    fn changed_paths(&self, sha: &str) -> Result<Vec<RepoPath>> {
+        let state = self.state.lock();
+        let commit = state
+            .commits
+            .iter()
+            .find(|commit| commit.summary.sha == sha)
+            .ok_or_else(|| anyhow::anyhow!("unknown commit {sha}"))?;
+        let mut paths = commit
+            .changed_files
+            .keys()
+            .map(|path| RepoPath(path.clone()))
+            .collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn load_commit_text(&self, sha: &str, path: &RepoPath) -> Result<Option<String>> {
+        let state = self.state.lock();
+        let ix = state
+            .commits
+            .iter()
+            .position(|commit| commit.summary.sha == sha)
+            .ok_or_else(|| anyhow::anyhow!("unknown commit {sha}"))?;
+        Ok(state.commits[ix..]
+            .iter()
+            .find_map(|commit| commit.changed_files.get(&path.0).cloned()))
+    }
 }
 
 fn check_path_to_repo_path_errors(relative_file_path: &Path) -> Result<()> {
@@ -0,0 +1,261 @@
+use std::ops::Range;
+use text::{Anchor, BufferSnapshot, Point};
+
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR_MARKER: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+/// A conflict left in a file by a merge or a rebase, delimited by conflict markers:
+///
+/// ```text
+/// <<<<<<< HEAD
+/// our changes
+/// ||||||| base
+/// the common ancestor, with the diff3 conflict style
+/// =======
+/// their changes
+/// >>>>>>> branch
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict<T> {
+    /// The whole conflict, from the start of its first marker to the end of its last one.
+    pub range: Range<T>,
+    /// The lines between the first marker and the base or separator marker.
+    pub ours: Range<T>,
+    /// The lines between the base marker and the separator marker, if any.
+    pub base: Option<Range<T>>,
+    /// The lines between the separator marker and the last marker.
+    pub theirs: Range<T>,
+}
+
+impl<T: Clone> Conflict<T> {
+    /// The ranges of the lines holding the conflict markers.
+    pub fn marker_ranges(&self) -> Vec<Range<T>> {
+        let mut ranges = vec![self.range.start.clone()..self.ours.start.clone()];
+        match &self.base {
+            Some(base) => {
+                ranges.push(self.ours.end.clone()..base.start.clone());
+                ranges.push(base.end.clone()..self.theirs.start.clone());
+            }
+            None => ranges.push(self.ours.end.clone()..self.theirs.start.clone()),
+        }
+        ranges.push(self.theirs.end.clone()..self.range.end.clone());
+        ranges
+    }
+}
+
+/// Finds the conflicts delimited by conflict markers in the given buffer.
+pub fn parse_conflicts(buffer: &BufferSnapshot) -> Vec<Conflict<Anchor>> {
+    parse_conflict_offsets(buffer)
+        .into_iter()
+        .map(|conflict| {
+            let to_anchors = |range: Range<usize>| {
+                buffer.anchor_before(range.start)..buffer.anchor_after(range.end)
+            };
+            Conflict {
+                range: to_anchors(conflict.range),
+                ours: to_anchors(conflict.ours),
+                base: conflict.base.map(to_anchors),
+                theirs: to_anchors(conflict.theirs),
+            }
+        })
+        .collect()
+}
+
+fn parse_conflict_offsets(buffer: &BufferSnapshot) -> Vec<Conflict<usize>> {
+    #[derive(Clone, Copy)]
+    enum State {
+        Ours {
+            start: usize,
+            ours_start: usize,
+        },
+        Base {
+            start: usize,
+            ours: (usize, usize),
+            base_start: usize,
+        },
+        Theirs {
+            start: usize,
+            ours: (usize, usize),
+            base: Option<(usize, usize)>,
+            theirs_start: usize,
+        },
+    }
+
+    let mut conflicts = Vec::new();
+    let mut state = None;
+    let max_row = buffer.max_point().row;
+    for row in 0..=max_row {
+        let line_start = buffer.point_to_offset(Point::new(row, 0));
+        let line_end = line_start + buffer.line_len(row) as usize;
+        let next_line_start = if row < max_row {
+            line_end + 1
+        } else {
+            line_end
+        };
+
+        // A new conflict can start while another one is malformed, in which case the previous
+        // one is discarded.
+        if is_marker_line(buffer, line_start, line_end, OURS_MARKER, true) {
+            state = Some(State::Ours {
+                start: line_start,
+                ours_start: next_line_start,
+            });
+            continue;
+        }
+
+        state = match state {
+            Some(State::Ours { start, ours_start })
+                if is_marker_line(buffer, line_start, line_end, BASE_MARKER, true) =>
+            {
+                Some(State::Base {
+                    start,
+                    ours: (ours_start, line_start),
+                    base_start: next_line_start,
+                })
+            }
+            Some(State::Ours { start, ours_start })
+                if is_marker_line(buffer, line_start, line_end, SEPARATOR_MARKER, false) =>
+            {
+                Some(State::Theirs {
+                    start,
+                    ours: (ours_start, line_start),
+                    base: None,
+                    theirs_start: next_line_start,
+                })
+            }
+            Some(State::Base {
+                start,
+                ours,
+                base_start,
+            }) if is_marker_line(buffer, line_start, line_end, SEPARATOR_MARKER, false) => {
+                Some(State::Theirs {
+                    start,
+                    ours,
+                    base: Some((base_start, line_start)),
+                    theirs_start: next_line_start,
+                })
+            }
+            Some(State::Theirs {
+                start,
+                ours,
+                base,
+                theirs_start,
+            }) if is_marker_line(buffer, line_start, line_end, THEIRS_MARKER, true) => {
+                conflicts.push(Conflict {
+                    range: start..next_line_start,
+                    ours: ours.0..ours.1,
+                    base: base.map(|base| base.0..base.1),
+                    theirs: theirs_start..line_start,
+                });
+                None
+            }
+            state => state,
+        };
+    }
+    conflicts
+}
+
+/// Whether the line is a conflict marker, which may be followed by a label such as a branch name.
+fn is_marker_line(
+    buffer: &BufferSnapshot,
+    line_start: usize,
+    line_end: usize,
+    marker: &str,
+    allow_label: bool,
+) -> bool {
+    let marker_end = line_start + marker.len();
+    buffer.contains_str_at(line_start, marker)
+        && (marker_end == line_end || (allow_label && buffer.contains_str_at(marker_end, " ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text::Buffer;
+    use unindent::Unindent as _;
+
+    #[test]
+    fn test_parse_conflicts() {
+        let text = "
+            one
+            <<<<<<< HEAD
+            two
+            =======
+            TWO
+            >>>>>>> feature
+            three
+            <<<<<<< HEAD
+            ||||||| base
+            four
+            =======
+            FOUR
+            FIVE
+            >>>>>>> feature
+            six
+            ======= not a marker
+            >>>>>>> unmatched
+        "
+        .unindent();
+        let buffer = Buffer::new(0, 0, text.clone());
+        let conflicts = parse_conflict_offsets(&buffer.snapshot());
+        let texts = conflicts
+            .iter()
+            .map(|conflict| {
+                (
+                    &text[conflict.range.clone()],
+                    &text[conflict.ours.clone()],
+                    conflict.base.clone().map(|base| &text[base]),
+                    &text[conflict.theirs.clone()],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                (
+                    "<<<<<<< HEAD\ntwo\n=======\nTWO\n>>>>>>> feature\n",
+                    "two\n",
+                    None,
+                    "TWO\n"
+                ),
+                (
+                    "<<<<<<< HEAD\n||||||| base\nfour\n=======\nFOUR\nFIVE\n>>>>>>> feature\n",
+                    "",
+                    Some("four\n"),
+                    "FOUR\nFIVE\n"
+                ),
+            ]
+        );
+        assert_eq!(
+            conflicts[0]
+                .marker_ranges()
+                .into_iter()
+                .map(|range| &text[range])
+                .collect::<Vec<_>>(),
+            ["<<<<<<< HEAD\n", "=======\n", ">>>>>>> feature\n"]
+        );
+    }
+
+    #[test]
+    fn test_parse_unterminated_conflict() {
+        let text = "
+            <<<<<<< HEAD
+            one
+            <<<<<<< HEAD
+            two
+            =======
+            three
+            >>>>>>> feature"
+            .unindent();
+        let buffer = Buffer::new(0, 0, text.clone());
+        let conflicts = parse_conflict_offsets(&buffer.snapshot());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            &text[conflicts[0].range.clone()],
+            "<<<<<<< HEAD\ntwo\n=======\nthree\n>>>>>>> feature"
+        );
+        assert_eq!(&text[conflicts[0].theirs.clone()], "three\n");
+    }
+}
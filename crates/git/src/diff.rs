@@ -275,6 +275,134 @@ impl BufferDiff {
     }
 }
 
+/// A hunk of a line-based diff between two texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TextDiffHunk {
+    old_rows: Range<u32>,
+    new_rows: Range<u32>,
+}
+
+/// Returns `index_text` with the hunks in which `buffer_text` differs from it applied, if they
+/// intersect any of the given rows of `buffer_text`.
+pub fn stage_hunks(index_text: &str, buffer_text: &str, buffer_rows: &[Range<u32>]) -> String {
+    let hunks = diff_rows(index_text, buffer_text)
+        .into_iter()
+        .filter(|hunk| intersects_rows(&hunk.new_rows, buffer_rows))
+        .map(|hunk| (hunk.old_rows, hunk.new_rows));
+    splice_rows(index_text, buffer_text, hunks)
+}
+
+/// Returns `index_text` with the hunks in which it differs from `head_text` reverted, if they
+/// intersect any of the given rows of `buffer_text`, whose unstaged changes are skipped over.
+pub fn unstage_hunks(
+    head_text: &str,
+    index_text: &str,
+    buffer_text: &str,
+    buffer_rows: &[Range<u32>],
+) -> String {
+    let unstaged_hunks = diff_rows(index_text, buffer_text);
+    let index_rows = buffer_rows
+        .iter()
+        .map(|rows| {
+            let start = buffer_row_to_index_row(&unstaged_hunks, rows.start, false);
+            let end = buffer_row_to_index_row(&unstaged_hunks, rows.end, true);
+            start..end.max(start)
+        })
+        .collect::<Vec<_>>();
+    let hunks = diff_rows(head_text, index_text)
+        .into_iter()
+        .filter(|hunk| intersects_rows(&hunk.new_rows, &index_rows))
+        .map(|hunk| (hunk.new_rows, hunk.old_rows));
+    splice_rows(index_text, head_text, hunks)
+}
+
+fn diff_rows(old_text: &str, new_text: &str) -> Vec<TextDiffHunk> {
+    let Some(patch) = BufferDiff::diff(old_text, new_text) else {
+        return Vec::new();
+    };
+
+    // Line numbers are 1-based, except that an empty side of a hunk is given by the line
+    // preceding it.
+    fn rows(start: u32, lines: u32) -> Range<u32> {
+        if lines == 0 {
+            start..start
+        } else {
+            start - 1..start - 1 + lines
+        }
+    }
+
+    (0..patch.num_hunks())
+        .filter_map(|hunk_index| {
+            let (hunk, _) = patch.hunk(hunk_index).ok()?;
+            Some(TextDiffHunk {
+                old_rows: rows(hunk.old_start(), hunk.old_lines()),
+                new_rows: rows(hunk.new_start(), hunk.new_lines()),
+            })
+        })
+        .collect()
+}
+
+/// Whether a hunk spanning the given rows should be affected by any of the selected `rows`,
+/// where hunks of removed lines span no rows.
+pub fn intersects_rows(hunk_rows: &Range<u32>, rows: &[Range<u32>]) -> bool {
+    rows.iter().any(|rows| {
+        if hunk_rows.is_empty() {
+            rows.start <= hunk_rows.start && hunk_rows.start <= rows.end
+        } else {
+            hunk_rows.start < rows.end && rows.start < hunk_rows.end
+        }
+    })
+}
+
+fn buffer_row_to_index_row(unstaged_hunks: &[TextDiffHunk], buffer_row: u32, is_end: bool) -> u32 {
+    let mut divergence = 0i64;
+    for hunk in unstaged_hunks {
+        if buffer_row < hunk.new_rows.start {
+            break;
+        } else if buffer_row < hunk.new_rows.end {
+            return if is_end {
+                hunk.old_rows.end
+            } else {
+                hunk.old_rows.start
+            };
+        }
+        divergence = hunk.old_rows.end as i64 - hunk.new_rows.end as i64;
+    }
+    (buffer_row as i64 + divergence).max(0) as u32
+}
+
+/// Replaces the given rows of `target` with the corresponding rows of `source`.
+fn splice_rows(
+    target: &str,
+    source: &str,
+    hunks: impl Iterator<Item = (Range<u32>, Range<u32>)>,
+) -> String {
+    let target_offsets = row_offsets(target);
+    let source_offsets = row_offsets(source);
+    let offset =
+        |offsets: &[usize], len: usize, row: u32| offsets.get(row as usize).copied().unwrap_or(len);
+
+    let mut text = String::with_capacity(target.len());
+    let mut target_offset = 0;
+    for (target_rows, source_rows) in hunks {
+        let target_start = offset(&target_offsets, target.len(), target_rows.start);
+        let target_end = offset(&target_offsets, target.len(), target_rows.end);
+        let source_start = offset(&source_offsets, source.len(), source_rows.start);
+        let source_end = offset(&source_offsets, source.len(), source_rows.end);
+        text.push_str(&target[target_offset..target_start]);
+        text.push_str(&source[source_start..source_end]);
+        target_offset = target_end;
+    }
+    text.push_str(&target[target_offset..]);
+    text
+}
+
+fn row_offsets(text: &str) -> Vec<usize> {
+    iter::once(0)
+        .chain(text.match_indices('\n').map(|(ix, _)| ix + 1))
+        .collect()
+}
+
 /// Range (crossing new lines), old, new
 #[cfg(any(test, feature = "test-support"))]
 #[track_caller]
@@ -409,4 +537,56 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_stage_and_unstage_hunks() {
+        let head_text = "
+            one
+            two
+            three
+            four
+            five
+        "
+        .unindent();
+        let index_text = "
+            one
+            TWO
+            three
+            four
+            five
+        "
+        .unindent();
+        let buffer_text = "
+            zero
+            one
+            TWO
+            three
+            FOUR
+            five
+            six
+        "
+        .unindent();
+
+        // Only the hunk on the cursor's row is staged.
+        assert_eq!(
+            stage_hunks(&index_text, &buffer_text, &[4..5]),
+            "one\nTWO\nthree\nFOUR\nfive\n"
+        );
+        assert_eq!(
+            stage_hunks(&index_text, &buffer_text, &[0..1, 6..7]),
+            "zero\none\nTWO\nthree\nfour\nfive\nsix\n"
+        );
+        assert_eq!(stage_hunks(&index_text, &buffer_text, &[0..7]), buffer_text);
+        assert_eq!(stage_hunks(&index_text, &buffer_text, &[3..4]), index_text);
+
+        // Staged hunks are found by skipping over the buffer's unstaged changes.
+        assert_eq!(
+            unstage_hunks(&head_text, &index_text, &buffer_text, &[2..3]),
+            head_text
+        );
+        assert_eq!(
+            unstage_hunks(&head_text, &index_text, &buffer_text, &[4..5]),
+            index_text
+        );
+    }
 }
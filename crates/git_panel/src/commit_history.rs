@@ -0,0 +1,542 @@
+use anyhow::anyhow;
+use editor::{Editor, MultiBuffer};
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    actions, AnyElement, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model,
+    Render, Subscription, Task, View, ViewContext, VisualContext as _, WeakView,
+};
+use language::{Buffer, Capability};
+use picker::{Picker, PickerDelegate};
+use project::{
+    repository::{format_commit_date, short_sha, CommitSummary},
+    Project, Worktree, WorktreeId,
+};
+use std::{path::Path, sync::Arc};
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{notifications::DetachAndPromptErr, ModalView, Toast, Workspace};
+
+actions!(git, [ShowHistory, ShowFileHistory]);
+
+/// The maximum number of commits listed in the history.
+const COMMIT_LIMIT: usize = 1000;
+
+const HISTORY_UNSUPPORTED_TOAST_ID: usize = 5120;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(CommitHistory::register).detach();
+}
+
+/// A modal listing the commits of a repository or of a single file, from which a file can be
+/// opened as of any of those commits.
+pub struct CommitHistory {
+    picker: View<Picker<CommitHistoryDelegate>>,
+    _subscription: Subscription,
+}
+
+impl CommitHistory {
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(|workspace, _: &ShowHistory, cx| {
+            Self::toggle(workspace, false, cx);
+        });
+        workspace.register_action(|workspace, _: &ShowFileHistory, cx| {
+            Self::toggle(workspace, true, cx);
+        });
+    }
+
+    fn new(delegate: CommitHistoryDelegate, cx: &mut ViewContext<Self>) -> Self {
+        let picker = cx.new_view(|cx| Picker::new(delegate, cx));
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| cx.emit(DismissEvent));
+        Self {
+            picker,
+            _subscription,
+        }
+    }
+
+    fn toggle(workspace: &mut Workspace, file_history: bool, cx: &mut ViewContext<Workspace>) {
+        // The history is read from the local repository, which guests don't have access to.
+        if workspace.project().read(cx).is_remote() {
+            workspace.show_toast(
+                Toast::new(
+                    HISTORY_UNSUPPORTED_TOAST_ID,
+                    "Commit history is not supported in remote projects yet",
+                ),
+                cx,
+            );
+            return;
+        }
+        let Some((worktree, path)) = Self::history_path(workspace, file_history, cx) else {
+            return;
+        };
+        let worktree_id = worktree.read(cx).id();
+        let load_commits = worktree.update(cx, |worktree, cx| {
+            worktree
+                .as_local()
+                .map(|worktree| worktree.commit_history(&path, COMMIT_LIMIT, cx))
+        });
+        let Some(load_commits) = load_commits else {
+            return;
+        };
+
+        let project = workspace.project().clone();
+        cx.spawn(|workspace, mut cx| async move {
+            let commits = load_commits.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                let delegate = CommitHistoryDelegate {
+                    workspace: cx.view().downgrade(),
+                    project,
+                    worktree_id,
+                    path,
+                    file_history,
+                    commits,
+                    selected_commit: None,
+                    matches: Vec::new(),
+                    selected_index: 0,
+                };
+                workspace.toggle_modal(cx, |cx| CommitHistory::new(delegate, cx));
+            })
+        })
+        .detach_and_prompt_err("Failed to load commit history", cx, |_, _| None);
+    }
+
+    /// The path whose history is shown: the active file, or the working directory of the
+    /// repository containing it, falling back to the root of the first visible worktree.
+    fn history_path(
+        workspace: &Workspace,
+        file_history: bool,
+        cx: &AppContext,
+    ) -> Option<(Model<Worktree>, Arc<Path>)> {
+        let project = workspace.project().read(cx);
+        let active_path = workspace
+            .active_item(cx)
+            .and_then(|item| item.project_path(cx));
+        if file_history {
+            let active_path = active_path?;
+            let worktree = project.worktree_for_id(active_path.worktree_id, cx)?;
+            return Some((worktree, active_path.path));
+        }
+
+        let (worktree, path) = match active_path {
+            Some(active_path) => (
+                project.worktree_for_id(active_path.worktree_id, cx)?,
+                active_path.path,
+            ),
+            None => (
+                project.visible_worktrees(cx).next()?,
+                Arc::from(Path::new("")),
+            ),
+        };
+        let work_directory = worktree
+            .read(cx)
+            .as_local()?
+            .repository_and_work_directory_for_path(&path)
+            .map(|(work_directory, _)| Arc::from(work_directory.as_ref()));
+        Some((worktree, work_directory.unwrap_or(path)))
+    }
+}
+
+impl ModalView for CommitHistory {}
+impl EventEmitter<DismissEvent> for CommitHistory {}
+
+impl FocusableView for CommitHistory {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for CommitHistory {
+    fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+pub struct CommitHistoryDelegate {
+    workspace: WeakView<Workspace>,
+    project: Model<Project>,
+    worktree_id: WorktreeId,
+    /// The file whose history is listed, or the working directory of the repository.
+    path: Arc<Path>,
+    file_history: bool,
+    commits: Vec<CommitSummary>,
+    /// The commit chosen from the history of a repository, along with the files it changed,
+    /// which are listed in place of the commits.
+    selected_commit: Option<(CommitSummary, Vec<Arc<Path>>)>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl CommitHistoryDelegate {
+    fn candidates(&self) -> Vec<StringMatchCandidate> {
+        let strings = match &self.selected_commit {
+            Some((_, paths)) => paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>(),
+            None => self
+                .commits
+                .iter()
+                .map(|commit| format!("{} {}", commit.summary, commit.author))
+                .collect(),
+        };
+        strings
+            .into_iter()
+            .enumerate()
+            .map(|(id, string)| StringMatchCandidate {
+                id,
+                char_bag: string.chars().collect(),
+                string,
+            })
+            .collect()
+    }
+
+    fn show_changed_paths(&mut self, commit: CommitSummary, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(worktree) = self.project.read(cx).worktree_for_id(self.worktree_id, cx) else {
+            return;
+        };
+        let load_paths = worktree.update(cx, |worktree, cx| {
+            worktree
+                .as_local()
+                .map(|worktree| worktree.changed_paths(&self.path, commit.sha.clone(), cx))
+        });
+        let Some(load_paths) = load_paths else {
+            return;
+        };
+        cx.spawn(|picker, mut cx| async move {
+            let paths = load_paths.await?;
+            picker.update(&mut cx, |picker, cx| {
+                picker.delegate.selected_commit = Some((commit, paths));
+                picker.delegate.selected_index = 0;
+                picker.set_query("", cx);
+                picker.refresh(cx);
+            })
+        })
+        .detach_and_prompt_err("Failed to list changed files", cx, |_, _| None);
+    }
+
+    /// Opens the file at the given path as of the given commit in a read-only editor.
+    fn open_file_at_commit(
+        &self,
+        path: Arc<Path>,
+        commit: &CommitSummary,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) {
+        let Some(worktree) = self.project.read(cx).worktree_for_id(self.worktree_id, cx) else {
+            return;
+        };
+        let load_text = worktree.update(cx, |worktree, cx| {
+            worktree
+                .as_local()
+                .map(|worktree| worktree.load_commit_text(&path, commit.sha.clone(), cx))
+        });
+        let Some(load_text) = load_text else {
+            return;
+        };
+
+        let languages = self.project.read(cx).languages().clone();
+        let workspace = self.workspace.clone();
+        let short_sha = short_sha(&commit.sha).to_string();
+        cx.spawn(|_, mut cx| async move {
+            let text = load_text
+                .await?
+                .ok_or_else(|| anyhow!("{path:?} does not exist in commit {short_sha}"))?;
+            let language = languages.language_for_file(&path, None).await.log_err();
+            workspace.update(&mut cx, |workspace, cx| {
+                let buffer = cx.new_model(|cx| {
+                    let mut buffer = Buffer::new(0, cx.entity_id().as_u64(), text);
+                    buffer.set_language_registry(languages);
+                    buffer.set_language(language, cx);
+                    buffer.set_capability(Capability::ReadOnly, cx);
+                    buffer
+                });
+                let file_name = path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let buffer = cx.new_model(|cx| {
+                    MultiBuffer::singleton(buffer, cx)
+                        .with_title(format!("{file_name} @ {short_sha}"))
+                });
+                let editor = cx.new_view(|cx| Editor::for_multibuffer(buffer, None, cx));
+                workspace.add_item(Box::new(editor), cx);
+            })
+        })
+        .detach_and_prompt_err("Failed to open file", cx, |_, _| None);
+    }
+}
+
+impl PickerDelegate for CommitHistoryDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        "Search commits...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let candidates = self.candidates();
+        cx.spawn(move |picker, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                let mut matches = fuzzy::match_strings(
+                    &candidates,
+                    &query,
+                    true,
+                    COMMIT_LIMIT,
+                    &Default::default(),
+                    cx.background_executor().clone(),
+                )
+                .await;
+                // Keep the matching commits in chronological order.
+                matches.sort_unstable_by_key(|hit| hit.candidate_id);
+                matches
+            };
+            picker
+                .update(&mut cx, |picker, _| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    delegate.selected_index = delegate
+                        .selected_index
+                        .min(delegate.matches.len().saturating_sub(1));
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(hit) = self.matches.get(self.selected_index) else {
+            return;
+        };
+        if let Some((commit, paths)) = &self.selected_commit {
+            self.open_file_at_commit(paths[hit.candidate_id].clone(), commit, cx);
+            cx.emit(DismissEvent);
+        } else if self.file_history {
+            self.open_file_at_commit(self.path.clone(), &self.commits[hit.candidate_id], cx);
+            cx.emit(DismissEvent);
+        } else {
+            let commit = self.commits[hit.candidate_id].clone();
+            self.show_changed_paths(commit, cx);
+        }
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        cx.emit(DismissEvent);
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let hit = &self.matches[ix];
+        let item = ListItem::new(ix)
+            .inset(true)
+            .spacing(ListItemSpacing::Sparse)
+            .selected(selected);
+        if self.selected_commit.is_some() {
+            return Some(item.child(HighlightedLabel::new(
+                hit.string.clone(),
+                hit.positions.clone(),
+            )));
+        }
+
+        let commit = &self.commits[hit.candidate_id];
+        let summary_positions = hit
+            .positions
+            .iter()
+            .copied()
+            .filter(|position| *position < commit.summary.len())
+            .collect();
+        Some(
+            item.child(
+                v_flex()
+                    .child(HighlightedLabel::new(
+                        commit.summary.clone(),
+                        summary_positions,
+                    ))
+                    .child(
+                        Label::new(format!(
+                            "{} · {} · {}",
+                            short_sha(&commit.sha),
+                            commit.author,
+                            format_commit_date(commit.unix_timestamp, cx.local_timezone())
+                        ))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                    ),
+            ),
+        )
+    }
+
+    fn render_header(&self, _: &mut ViewContext<Picker<Self>>) -> Option<AnyElement> {
+        let title = match &self.selected_commit {
+            Some((commit, _)) => format!(
+                "Files changed in {} {}",
+                short_sha(&commit.sha),
+                commit.summary
+            ),
+            None if self.file_history => format!("History of {}", self.path.to_string_lossy()),
+            None => "Commits".to_string(),
+        };
+        Some(
+            h_flex()
+                .px_3()
+                .pt_2()
+                .child(Label::new(title).size(LabelSize::Small).color(Color::Muted))
+                .into_any_element(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_test;
+    use gpui::TestAppContext;
+    use project::{repository::FakeCommit, FakeFs};
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_commit_history(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "a2\n",
+                "b.txt": "b2\n",
+            }),
+        )
+        .await;
+        fs.set_commits_for_repo(
+            Path::new("/root/.git"),
+            vec![
+                fake_commit("2222222", "Update b", &[("b.txt", "b2\n")]),
+                fake_commit(
+                    "1111111",
+                    "Add a and b",
+                    &[("a.txt", "a1\n"), ("b.txt", "b1\n")],
+                ),
+            ],
+        );
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        // Without an active file, the history of the whole repository is listed.
+        workspace.update(cx, |workspace, cx| {
+            CommitHistory::toggle(workspace, false, cx)
+        });
+        cx.executor().run_until_parked();
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<CommitHistory>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        picker.update(cx, |picker, _| {
+            assert_eq!(
+                match_strings(&picker.delegate),
+                ["Update b Test", "Add a and b Test"]
+            );
+        });
+
+        // Choosing a commit lists the files it changed.
+        picker.update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.executor().run_until_parked();
+        picker.update(cx, |picker, _| {
+            assert_eq!(match_strings(&picker.delegate), ["a.txt", "b.txt"]);
+        });
+
+        // Choosing a file opens it as of that commit.
+        picker.update(cx, |picker, cx| {
+            picker.delegate.set_selected_index(1, cx);
+            picker.delegate.confirm(false, cx);
+        });
+        cx.executor().run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            assert!(workspace.active_modal::<CommitHistory>(cx).is_none());
+            let editor = workspace.active_item_as::<Editor>(cx).unwrap();
+            assert_eq!(editor.read(cx).text(cx), "b1\n");
+        });
+
+        // The history of a file only lists the commits that changed it.
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+        workspace.update(cx, |workspace, cx| {
+            let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project.clone()), cx));
+            workspace.add_item(Box::new(editor), cx);
+        });
+        cx.executor().run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            CommitHistory::toggle(workspace, true, cx)
+        });
+        cx.executor().run_until_parked();
+        let picker = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<CommitHistory>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        picker.update(cx, |picker, _| {
+            assert_eq!(match_strings(&picker.delegate), ["Add a and b Test"]);
+        });
+    }
+
+    fn fake_commit(sha: &str, summary: &str, changed_files: &[(&str, &str)]) -> FakeCommit {
+        FakeCommit {
+            summary: CommitSummary {
+                sha: sha.to_string(),
+                author: "Test".to_string(),
+                author_email: "test@example.com".to_string(),
+                unix_timestamp: 0,
+                summary: summary.to_string(),
+            },
+            changed_files: changed_files
+                .iter()
+                .map(|(path, text)| (Path::new(path).to_path_buf(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    fn match_strings(delegate: &CommitHistoryDelegate) -> Vec<&str> {
+        delegate
+            .matches
+            .iter()
+            .map(|hit| hit.string.as_str())
+            .collect()
+    }
+}
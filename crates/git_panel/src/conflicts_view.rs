@@ -0,0 +1,437 @@
+use anyhow::Result;
+use editor::{Editor, EditorEvent, MultiBuffer};
+use git::conflict::parse_conflicts;
+use gpui::{
+    actions, AnyElement, AnyView, AppContext, EventEmitter, FocusHandle, FocusableView, Model,
+    Render, SharedString, Subscription, Task, View, ViewContext, VisualContext as _, WindowContext,
+};
+use language::{Buffer, OffsetRangeExt};
+use project::{repository::GitFileStatus, Project, ProjectPath};
+use std::{
+    any::{Any, TypeId},
+    path::PathBuf,
+    time::Duration,
+};
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::{
+    item::{BreadcrumbText, Item, ItemEvent, ItemHandle},
+    ItemNavHistory, ToolbarItemLocation, Workspace,
+};
+
+actions!(git, [ShowConflicts]);
+
+/// How long to wait after the project changes before listing the conflicted files again.
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The number of lines shown around each conflict.
+const CONTEXT_LINE_COUNT: u32 = 1;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(ConflictsView::register).detach();
+}
+
+/// A workspace item showing the merge conflicts of every file that git reports as conflicted,
+/// in a multibuffer through which they can be navigated and resolved.
+pub struct ConflictsView {
+    project: Model<Project>,
+    excerpts: Model<MultiBuffer>,
+    editor: View<Editor>,
+    paths: Vec<ProjectPath>,
+    focus_handle: FocusHandle,
+    update_task: Task<Option<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<EditorEvent> for ConflictsView {}
+
+impl ConflictsView {
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(Self::deploy);
+    }
+
+    fn deploy(workspace: &mut Workspace, _: &ShowConflicts, cx: &mut ViewContext<Workspace>) {
+        if let Some(existing) = workspace.item_of_type::<ConflictsView>(cx) {
+            workspace.activate_item(&existing, cx);
+        } else {
+            let project = workspace.project().clone();
+            let conflicts_view = cx.new_view(|cx| ConflictsView::new(project, cx));
+            workspace.add_item(Box::new(conflicts_view), cx);
+        }
+    }
+
+    fn new(project: Model<Project>, cx: &mut ViewContext<Self>) -> Self {
+        let excerpts = cx.new_model(|cx| {
+            MultiBuffer::new(project.read(cx).replica_id(), project.read(cx).capability())
+        });
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_multibuffer(excerpts.clone(), Some(project.clone()), cx);
+            editor.set_vertical_scroll_margin(5, cx);
+            editor.set_show_merge_conflicts_in_multibuffer(true, cx);
+            editor
+        });
+        let focus_handle = cx.focus_handle();
+        let _subscriptions = vec![
+            // Files become conflicted or resolved as git updates the repository.
+            cx.subscribe(&project, |this, _, event, cx| match event {
+                project::Event::WorktreeAdded
+                | project::Event::WorktreeRemoved(_)
+                | project::Event::WorktreeUpdatedGitRepositories(_) => this.update_paths(cx),
+                _ => {}
+            }),
+            cx.subscribe(&editor, |_, _, event: &EditorEvent, cx| {
+                cx.emit(event.clone());
+            }),
+            cx.on_focus_in(&focus_handle, |this, cx| {
+                if this.focus_handle.is_focused(cx) && this.has_conflicts(cx) {
+                    this.editor.focus_handle(cx).focus(cx);
+                }
+            }),
+        ];
+
+        let mut this = Self {
+            project,
+            excerpts,
+            editor,
+            paths: Vec::new(),
+            focus_handle,
+            update_task: Task::ready(None),
+            _subscriptions,
+        };
+        this.update_paths(cx);
+        this
+    }
+
+    /// Lists the conflicted files of the project, and shows their conflicts again when they
+    /// changed.
+    fn update_paths(&mut self, cx: &mut ViewContext<Self>) {
+        let worktrees = self
+            .project
+            .read(cx)
+            .visible_worktrees(cx)
+            .collect::<Vec<_>>();
+        self.update_task = cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+            let requests = this
+                .update(&mut cx, |_, cx| {
+                    worktrees
+                        .iter()
+                        .filter_map(|worktree| {
+                            let worktree_id = worktree.read(cx).id();
+                            let request = worktree.update(cx, |worktree, cx| {
+                                worktree
+                                    .as_local()
+                                    .map(|worktree| worktree.git_statuses(cx))
+                            })?;
+                            Some((worktree_id, request))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+
+            let mut paths = Vec::new();
+            for (worktree_id, request) in requests {
+                paths.extend(
+                    request
+                        .await
+                        .into_iter()
+                        .filter(|entry| entry.status == GitFileStatus::Conflict)
+                        .map(|entry| ProjectPath {
+                            worktree_id,
+                            path: entry.path,
+                        }),
+                );
+            }
+            paths.sort();
+            paths.dedup();
+
+            let open_buffers = this
+                .update(&mut cx, |this, cx| {
+                    if this.paths == paths {
+                        return None;
+                    }
+                    this.paths = paths.clone();
+                    Some(this.project.update(cx, |project, cx| {
+                        paths
+                            .into_iter()
+                            .map(|path| project.open_buffer(path, cx))
+                            .collect::<Vec<_>>()
+                    }))
+                })
+                .ok()??;
+            let mut buffers = Vec::new();
+            for open_buffer in open_buffers {
+                buffers.extend(open_buffer.await.log_err());
+            }
+            this.update(&mut cx, |this, cx| this.set_buffers(buffers, cx))
+                .ok()
+        });
+    }
+
+    fn has_conflicts(&self, cx: &AppContext) -> bool {
+        !self.excerpts.read(cx).excerpt_ids().is_empty()
+    }
+
+    fn set_buffers(&mut self, buffers: Vec<Model<Buffer>>, cx: &mut ViewContext<Self>) {
+        self.excerpts.update(cx, |excerpts, cx| {
+            excerpts.clear(cx);
+            for buffer in buffers {
+                let snapshot = buffer.read(cx).snapshot();
+                let ranges = parse_conflicts(&snapshot)
+                    .into_iter()
+                    .map(|conflict| conflict.range.to_point(&snapshot))
+                    .collect::<Vec<_>>();
+                if !ranges.is_empty() {
+                    excerpts.push_excerpts_with_context_lines(
+                        buffer,
+                        ranges,
+                        CONTEXT_LINE_COUNT,
+                        cx,
+                    );
+                }
+            }
+        });
+
+        if !self.has_conflicts(cx) {
+            if self.editor.focus_handle(cx).is_focused(cx) {
+                cx.focus(&self.focus_handle);
+            }
+        } else if self.focus_handle.is_focused(cx) {
+            let focus_handle = self.editor.focus_handle(cx);
+            cx.focus(&focus_handle);
+        }
+        cx.notify();
+    }
+}
+
+impl Render for ConflictsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let child = if !self.has_conflicts(cx) {
+            div()
+                .size_full()
+                .flex()
+                .items_center()
+                .justify_center()
+                .child(Label::new("No merge conflicts").color(Color::Muted))
+                .into_any_element()
+        } else {
+            div()
+                .size_full()
+                .child(self.editor.clone())
+                .into_any_element()
+        };
+
+        div()
+            .key_context("ConflictsView")
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .child(child)
+    }
+}
+
+impl FocusableView for ConflictsView {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Item for ConflictsView {
+    type Event = EditorEvent;
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn deactivated(&mut self, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, cx| editor.deactivated(cx));
+    }
+
+    fn navigate(&mut self, data: Box<dyn Any>, cx: &mut ViewContext<Self>) -> bool {
+        self.editor
+            .update(cx, |editor, cx| editor.navigate(data, cx))
+    }
+
+    fn tab_tooltip_text(&self, _: &AppContext) -> Option<SharedString> {
+        Some("Merge Conflicts".into())
+    }
+
+    fn tab_content(&self, _detail: Option<usize>, selected: bool, _: &WindowContext) -> AnyElement {
+        let label_color = if selected {
+            Color::Default
+        } else {
+            Color::Muted
+        };
+        if self.paths.is_empty() {
+            Label::new("No conflicts")
+                .color(label_color)
+                .into_any_element()
+        } else {
+            h_flex()
+                .gap_1()
+                .child(Icon::new(IconName::ExclamationTriangle).color(Color::Conflict))
+                .child(Label::new(format!("Conflicts ({})", self.paths.len())).color(label_color))
+                .into_any_element()
+        }
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("git conflicts view")
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::Item),
+    ) {
+        self.editor.for_each_project_item(cx, f)
+    }
+
+    fn is_singleton(&self, _: &AppContext) -> bool {
+        false
+    }
+
+    fn set_nav_history(&mut self, nav_history: ItemNavHistory, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, _| {
+            editor.set_nav_history(Some(nav_history));
+        });
+    }
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: workspace::WorkspaceId,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<View<Self>>
+    where
+        Self: Sized,
+    {
+        Some(cx.new_view(|cx| ConflictsView::new(self.project.clone(), cx)))
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.excerpts.read(cx).is_dirty(cx)
+    }
+
+    fn has_conflict(&self, cx: &AppContext) -> bool {
+        self.excerpts.read(cx).has_conflict(cx)
+    }
+
+    fn can_save(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn save(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.save(project, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        _: Model<Project>,
+        _: PathBuf,
+        _: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        unreachable!()
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.reload(project, cx)
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a View<Self>,
+        _: &'a AppContext,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+
+    fn breadcrumb_location(&self) -> ToolbarItemLocation {
+        ToolbarItemLocation::PrimaryLeft
+    }
+
+    fn breadcrumbs(&self, theme: &theme::Theme, cx: &AppContext) -> Option<Vec<BreadcrumbText>> {
+        self.editor.breadcrumbs(theme, cx)
+    }
+
+    fn added_to_workspace(&mut self, workspace: &mut Workspace, cx: &mut ViewContext<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.added_to_workspace(workspace, cx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_test;
+    use gpui::TestAppContext;
+    use language::Point;
+    use project::FakeFs;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[gpui::test]
+    async fn test_conflicts_view(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "one\n<<<<<<< HEAD\ntwo\n=======\nTWO\n>>>>>>> feature\nthree\nfour\nfive\n",
+                "b.txt": "one\ntwo\n",
+            }),
+        )
+        .await;
+        fs.set_status_for_repo_via_git_operation(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), GitFileStatus::Conflict)],
+        );
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+
+        let (conflicts_view, cx) = cx.add_window_view(|cx| ConflictsView::new(project.clone(), cx));
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        conflicts_view.update(cx, |conflicts_view, cx| {
+            assert_eq!(conflicts_view.paths.len(), 1);
+            assert_eq!(
+                conflicts_view.editor.read(cx).text(cx),
+                "one\n<<<<<<< HEAD\ntwo\n=======\nTWO\n>>>>>>> feature\nthree\nfour"
+            );
+        });
+
+        // The conflicts can be resolved from the view.
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        conflicts_view.update(cx, |conflicts_view, cx| {
+            conflicts_view.editor.update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |s| {
+                    s.select_ranges([Point::new(2, 0)..Point::new(2, 0)])
+                });
+                editor.accept_theirs(&editor::actions::AcceptTheirs, cx);
+                assert_eq!(editor.text(cx), "one\nTWO\nthree\nfour");
+            });
+        });
+
+        // Once git no longer reports the file as conflicted, it is removed from the view.
+        fs.set_status_for_repo_via_git_operation(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), GitFileStatus::Modified)],
+        );
+        cx.executor().run_until_parked();
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        conflicts_view.update(cx, |conflicts_view, cx| {
+            assert!(conflicts_view.paths.is_empty());
+            assert!(!conflicts_view.has_conflicts(cx));
+        });
+    }
+}
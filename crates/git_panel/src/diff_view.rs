@@ -0,0 +1,1224 @@
+use anyhow::Result;
+use collections::HashSet;
+use editor::{
+    display_map::{
+        BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle, RenderBlock,
+    },
+    Editor, EditorEvent, ExcerptId, ExcerptRange, MultiBuffer,
+};
+use git::diff::{BufferDiff, DiffHunk};
+use gpui::{
+    actions, AnyElement, AnyView, AppContext, EventEmitter, FocusHandle, FocusableView,
+    HighlightStyle, Hsla, Model, Render, SharedString, StyledText, Subscription, Task, View,
+    ViewContext, VisualContext as _, WindowContext,
+};
+use language::{Buffer, BufferSnapshot, Point, Selection, ToPoint as _};
+use project::Project;
+use std::{
+    any::{Any, TypeId},
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use theme::SyntaxTheme;
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::{
+    item::{BreadcrumbText, Item, ItemEvent, ItemHandle},
+    ItemNavHistory, ToolbarItemLocation, Workspace,
+};
+
+actions!(git, [DiffWithHead, DiffWithIndex, ToggleSideBySide]);
+
+/// How long to wait after the buffer changes before diffing it again.
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The number of unchanged lines shown around each hunk.
+const CONTEXT_LINE_COUNT: u32 = 3;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(DiffView::register).detach();
+}
+
+/// The version of a file that a [`DiffView`] compares the file's buffer against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffBase {
+    Head,
+    Index,
+}
+
+impl DiffBase {
+    fn label(self) -> &'static str {
+        match self {
+            DiffBase::Head => "HEAD",
+            DiffBase::Index => "Index",
+        }
+    }
+}
+
+/// A workspace item showing the changes made to a buffer since its HEAD or index version, in
+/// excerpts of the buffer around its hunks.
+///
+/// Inline, the removed lines are rendered as blocks above the lines that replaced them.
+/// Side by side, the matching excerpts of the base version are shown in a read-only editor to
+/// the left of the buffer's, with blank blocks padding both editors so that each hunk starts on
+/// the same line.
+pub struct DiffView {
+    project: Model<Project>,
+    buffer: Model<Buffer>,
+    diff_base: DiffBase,
+    base_buffer: Model<Buffer>,
+    base_text_outdated: bool,
+    excerpts: Model<MultiBuffer>,
+    base_excerpts: Model<MultiBuffer>,
+    editor: View<Editor>,
+    base_editor: View<Editor>,
+    hunks: Vec<DiffHunkRows>,
+    hunk_excerpts: Vec<HunkExcerpt>,
+    blocks: HashSet<BlockId>,
+    base_blocks: HashSet<BlockId>,
+    side_by_side: bool,
+    focus_handle: FocusHandle,
+    update_task: Task<Option<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+/// The rows spanned by a hunk in the buffer and in the base text, both of which are empty for
+/// hunks that only add or only remove lines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DiffHunkRows {
+    buffer_rows: Range<u32>,
+    base_rows: Range<u32>,
+}
+
+/// The excerpts of the buffer and of the base text showing a group of nearby hunks, which
+/// include the same number of unchanged lines on both sides.
+#[derive(Clone, Debug)]
+struct HunkExcerpt {
+    /// The indices of the hunks shown in the excerpts.
+    hunks: Range<usize>,
+    rows: DiffHunkRows,
+    excerpt_id: ExcerptId,
+    base_excerpt_id: ExcerptId,
+}
+
+impl DiffHunkRows {
+    fn new(hunk: DiffHunk<u32>, base_snapshot: &BufferSnapshot) -> Self {
+        let base_range = hunk.diff_base_byte_range;
+        let start = base_snapshot.offset_to_point(base_range.start);
+        let end = base_snapshot.offset_to_point(base_range.end);
+        let end_row = if base_range.is_empty() || end.column == 0 {
+            end.row
+        } else {
+            end.row + 1
+        };
+        Self {
+            buffer_rows: hunk.buffer_range,
+            base_rows: start.row..end_row,
+        }
+    }
+}
+
+impl EventEmitter<EditorEvent> for DiffView {}
+
+impl DiffView {
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(|workspace, _: &DiffWithHead, cx| {
+            Self::deploy(workspace, DiffBase::Head, cx);
+        });
+        workspace.register_action(|workspace, _: &DiffWithIndex, cx| {
+            Self::deploy(workspace, DiffBase::Index, cx);
+        });
+    }
+
+    fn deploy(workspace: &mut Workspace, diff_base: DiffBase, cx: &mut ViewContext<Workspace>) {
+        let Some(editor) = workspace.active_item_as::<Editor>(cx) else {
+            return;
+        };
+        let Some(buffer) = editor.read(cx).buffer().read(cx).as_singleton() else {
+            return;
+        };
+        Self::open(workspace, buffer, diff_base, cx);
+    }
+
+    /// Activates the diff of the given buffer against the given base, opening it if needed.
+    pub fn open(
+        workspace: &mut Workspace,
+        buffer: Model<Buffer>,
+        diff_base: DiffBase,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let existing = workspace.items_of_type::<DiffView>(cx).find(|diff_view| {
+            let diff_view = diff_view.read(cx);
+            diff_view.buffer == buffer && diff_view.diff_base == diff_base
+        });
+        if let Some(existing) = existing {
+            workspace.activate_item(&existing, cx);
+        } else {
+            let project = workspace.project().clone();
+            let diff_view = cx.new_view(|cx| DiffView::new(project, buffer, diff_base, cx));
+            workspace.add_item(Box::new(diff_view), cx);
+        }
+    }
+
+    fn new(
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        diff_base: DiffBase,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let base_buffer = cx.new_model(|cx| {
+            let mut base_buffer = Buffer::new(0, cx.entity_id().as_u64(), "");
+            base_buffer.set_language(buffer.read(cx).language().cloned(), cx);
+            base_buffer
+        });
+        let excerpts = cx.new_model(|cx| {
+            MultiBuffer::new(project.read(cx).replica_id(), project.read(cx).capability())
+        });
+        let base_excerpts = cx.new_model(|_| MultiBuffer::new(0, language::Capability::ReadOnly));
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_multibuffer(excerpts.clone(), Some(project.clone()), cx);
+            editor.set_soft_wrap_mode(language::language_settings::SoftWrap::None, cx);
+            editor
+        });
+        let base_editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_multibuffer(base_excerpts.clone(), None, cx);
+            editor.set_read_only(true);
+            editor.set_soft_wrap_mode(language::language_settings::SoftWrap::None, cx);
+            editor
+        });
+
+        let focus_handle = cx.focus_handle();
+        let subscriptions = vec![
+            cx.subscribe(&buffer, |this, buffer, event, cx| match event {
+                language::Event::Edited | language::Event::Reloaded => this.update_diff(false, cx),
+                language::Event::DiffBaseChanged => this.update_diff(true, cx),
+                language::Event::LanguageChanged => {
+                    let language = buffer.read(cx).language().cloned();
+                    this.base_buffer
+                        .update(cx, |base_buffer, cx| base_buffer.set_language(language, cx));
+                }
+                _ => {}
+            }),
+            // Committing or checking out another branch changes the HEAD version of the file
+            // without changing its index version.
+            cx.subscribe(&project, |this, _, event, cx| {
+                if let project::Event::WorktreeUpdatedGitRepositories(_) = event {
+                    if this.diff_base == DiffBase::Head {
+                        this.update_diff(true, cx);
+                    }
+                }
+            }),
+            cx.subscribe(&editor, |this, editor, event: &EditorEvent, cx| {
+                if let EditorEvent::ScrollPositionChanged { local: true, .. } = event {
+                    this.sync_scroll_position(&editor, &this.base_editor.clone(), cx);
+                }
+                cx.emit(event.clone());
+            }),
+            cx.subscribe(
+                &base_editor,
+                |this, base_editor, event: &EditorEvent, cx| {
+                    if let EditorEvent::ScrollPositionChanged { local: true, .. } = event {
+                        this.sync_scroll_position(&base_editor, &this.editor.clone(), cx);
+                    }
+                },
+            ),
+            cx.on_focus_in(&focus_handle, |this, cx| {
+                if this.focus_handle.is_focused(cx) {
+                    this.editor.focus_handle(cx).focus(cx);
+                }
+            }),
+        ];
+
+        let mut this = Self {
+            project,
+            buffer,
+            diff_base,
+            base_buffer,
+            base_text_outdated: true,
+            excerpts,
+            base_excerpts,
+            editor,
+            base_editor,
+            hunks: Vec::new(),
+            hunk_excerpts: Vec::new(),
+            blocks: HashSet::default(),
+            base_blocks: HashSet::default(),
+            side_by_side: false,
+            focus_handle,
+            update_task: Task::ready(None),
+            _subscriptions: subscriptions,
+        };
+        this.update_diff(true, cx);
+        this
+    }
+
+    fn toggle_side_by_side(&mut self, _: &ToggleSideBySide, cx: &mut ViewContext<Self>) {
+        self.side_by_side = !self.side_by_side;
+        self.refresh_blocks(cx);
+        if self.side_by_side {
+            self.sync_scroll_position(&self.editor.clone(), &self.base_editor.clone(), cx);
+        }
+        cx.notify();
+    }
+
+    fn set_diff_base(&mut self, diff_base: DiffBase, cx: &mut ViewContext<Self>) {
+        if self.diff_base != diff_base {
+            self.diff_base = diff_base;
+            self.update_diff(true, cx);
+            cx.emit(EditorEvent::TitleChanged);
+            cx.notify();
+        }
+    }
+
+    fn load_base_text(&self, cx: &mut ViewContext<Self>) -> Task<Result<Option<String>>> {
+        match self.diff_base {
+            DiffBase::Head => self
+                .project
+                .update(cx, |project, cx| project.load_head_text(&self.buffer, cx)),
+            DiffBase::Index => Task::ready(Ok(self
+                .buffer
+                .read(cx)
+                .diff_base()
+                .map(|diff_base| diff_base.to_string()))),
+        }
+    }
+
+    fn update_diff(&mut self, reload_base_text: bool, cx: &mut ViewContext<Self>) {
+        self.base_text_outdated |= reload_base_text;
+        self.update_task = cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+
+            let load_base_text = this
+                .update(&mut cx, |this, cx| {
+                    this.base_text_outdated.then(|| this.load_base_text(cx))
+                })
+                .ok()?;
+            if let Some(load_base_text) = load_base_text {
+                let base_text = load_base_text.await.log_err()?.unwrap_or_default();
+                this.update(&mut cx, |this, cx| {
+                    this.base_text_outdated = false;
+                    this.base_buffer
+                        .update(cx, |base_buffer, cx| base_buffer.set_text(base_text, cx));
+                })
+                .ok()?;
+            }
+
+            let (snapshot, base_snapshot) = this
+                .update(&mut cx, |this, cx| {
+                    (
+                        this.buffer.read(cx).snapshot(),
+                        this.base_buffer.read(cx).snapshot(),
+                    )
+                })
+                .ok()?;
+            let hunks = cx
+                .background_executor()
+                .spawn(async move {
+                    let mut diff = BufferDiff::new();
+                    diff.update(&base_snapshot.text(), &snapshot).await;
+                    diff.hunks_in_row_range(0..u32::MAX, &snapshot)
+                        .map(|hunk| DiffHunkRows::new(hunk, &base_snapshot))
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+            this.update(&mut cx, |this, cx| this.set_hunks(hunks, cx))
+                .ok()
+        });
+    }
+
+    /// Shows the given hunks, replacing the excerpts only when the lines they span changed.
+    fn set_hunks(&mut self, hunks: Vec<DiffHunkRows>, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot();
+        let base_snapshot = self.base_buffer.read(cx).snapshot();
+        let excerpt_rows = excerpt_rows(
+            &hunks,
+            snapshot.max_point().row + 1,
+            base_snapshot.max_point().row + 1,
+        );
+        self.hunks = hunks;
+
+        let excerpts_changed = excerpt_rows.len() != self.hunk_excerpts.len()
+            || excerpt_rows
+                .iter()
+                .zip(&self.hunk_excerpts)
+                .any(|((_, rows), excerpt)| *rows != excerpt.rows);
+        if excerpts_changed {
+            self.set_excerpts(excerpt_rows, cx);
+        } else {
+            for ((hunks, _), excerpt) in excerpt_rows.into_iter().zip(&mut self.hunk_excerpts) {
+                excerpt.hunks = hunks;
+            }
+        }
+        self.refresh_blocks(cx);
+        cx.notify();
+    }
+
+    /// Replaces the excerpts of both editors, keeping the cursors on the same lines of the
+    /// buffer.
+    fn set_excerpts(
+        &mut self,
+        excerpt_rows: Vec<(Range<usize>, DiffHunkRows)>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let snapshot = self.buffer.read(cx).snapshot();
+        let base_snapshot = self.base_buffer.read(cx).snapshot();
+        let selections = self
+            .editor
+            .read(cx)
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .map(|selection| Selection {
+                id: selection.id,
+                start: selection.start.text_anchor.to_point(&snapshot),
+                end: selection.end.text_anchor.to_point(&snapshot),
+                reversed: selection.reversed,
+                goal: selection.goal,
+            })
+            .collect::<Vec<_>>();
+
+        let buffer = self.buffer.clone();
+        let excerpt_ids = self.excerpts.update(cx, |excerpts, cx| {
+            excerpts.clear(cx);
+            excerpts.push_excerpts(
+                buffer,
+                excerpt_rows.iter().map(|(_, rows)| ExcerptRange {
+                    context: rows_to_excerpt_range(&rows.buffer_rows, &snapshot),
+                    primary: None,
+                }),
+                cx,
+            )
+        });
+        let base_buffer = self.base_buffer.clone();
+        let base_excerpt_ids = self.base_excerpts.update(cx, |base_excerpts, cx| {
+            base_excerpts.clear(cx);
+            base_excerpts.push_excerpts(
+                base_buffer,
+                excerpt_rows.iter().map(|(_, rows)| ExcerptRange {
+                    context: rows_to_excerpt_range(&rows.base_rows, &base_snapshot),
+                    primary: None,
+                }),
+                cx,
+            )
+        });
+        self.hunk_excerpts = excerpt_rows
+            .into_iter()
+            .zip(excerpt_ids)
+            .zip(base_excerpt_ids)
+            .map(
+                |(((hunks, rows), excerpt_id), base_excerpt_id)| HunkExcerpt {
+                    hunks,
+                    rows,
+                    excerpt_id,
+                    base_excerpt_id,
+                },
+            )
+            .collect();
+
+        if self.hunk_excerpts.is_empty() {
+            return;
+        }
+        let excerpts_snapshot = self.excerpts.read(cx).snapshot(cx);
+        let anchor = |point: Point| {
+            let excerpt = self
+                .hunk_excerpts
+                .iter()
+                .rev()
+                .find(|excerpt| excerpt.rows.buffer_rows.start <= point.row)
+                .unwrap_or(&self.hunk_excerpts[0]);
+            let range = rows_to_excerpt_range(&excerpt.rows.buffer_rows, &snapshot);
+            let point = point.max(range.start).min(range.end);
+            excerpts_snapshot.anchor_in_excerpt(excerpt.excerpt_id, snapshot.anchor_before(point))
+        };
+        let selections = selections
+            .into_iter()
+            .map(|selection| Selection {
+                id: selection.id,
+                start: anchor(selection.start),
+                end: anchor(selection.end),
+                reversed: selection.reversed,
+                goal: selection.goal,
+            })
+            .collect();
+        self.editor.update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_anchors(selections))
+        });
+    }
+
+    /// Replaces the blocks and highlights of both editors with those of the current hunks.
+    fn refresh_blocks(&mut self, cx: &mut ViewContext<Self>) {
+        let snapshot = self.buffer.read(cx).snapshot();
+        let base_snapshot = self.base_buffer.read(cx).snapshot();
+        let syntax_theme = cx.theme().syntax().clone();
+        let mut added_background = cx.theme().status().created_background;
+        added_background.fade_out(0.7);
+        let mut deleted_background = cx.theme().status().deleted_background;
+        deleted_background.fade_out(0.7);
+
+        let mut blocks = Vec::new();
+        let mut base_blocks = Vec::new();
+        let mut added_ranges = Vec::new();
+        let mut deleted_ranges = Vec::new();
+        for excerpt in &self.hunk_excerpts {
+            for hunk in &self.hunks[excerpt.hunks.clone()] {
+                let added_row_count = hunk.buffer_rows.len() as u32;
+                let deleted_row_count = hunk.base_rows.len() as u32;
+                if added_row_count > 0 {
+                    added_ranges.push((
+                        excerpt.excerpt_id,
+                        row_range_to_points(&hunk.buffer_rows, &snapshot),
+                    ));
+                }
+
+                if self.side_by_side {
+                    if deleted_row_count > 0 {
+                        deleted_ranges.push((
+                            excerpt.base_excerpt_id,
+                            row_range_to_points(&hunk.base_rows, &base_snapshot),
+                        ));
+                    }
+                    if deleted_row_count > added_row_count {
+                        blocks.extend(
+                            spacer_blocks(
+                                hunk.buffer_rows.end,
+                                deleted_row_count - added_row_count,
+                                &snapshot,
+                            )
+                            .map(|block| (excerpt.excerpt_id, block)),
+                        );
+                    } else if added_row_count > deleted_row_count {
+                        base_blocks.extend(
+                            spacer_blocks(
+                                hunk.base_rows.end,
+                                added_row_count - deleted_row_count,
+                                &base_snapshot,
+                            )
+                            .map(|block| (excerpt.base_excerpt_id, block)),
+                        );
+                    }
+                } else if deleted_row_count > 0 {
+                    let lines = highlighted_lines(&hunk.base_rows, &base_snapshot, &syntax_theme);
+                    let (position, disposition) = block_position(hunk.buffer_rows.start, &snapshot);
+                    // Block heights can't exceed `u8::MAX`, so longer deletions are split into
+                    // several blocks, which are shown in the order they're inserted.
+                    blocks.extend(lines.chunks(u8::MAX as usize).map(|lines| {
+                        (
+                            excerpt.excerpt_id,
+                            BlockProperties {
+                                position,
+                                height: lines.len() as u8,
+                                style: BlockStyle::Fixed,
+                                render: deleted_lines_renderer(lines.to_vec(), deleted_background),
+                                disposition,
+                            },
+                        )
+                    }));
+                }
+            }
+        }
+
+        self.blocks = replace_blocks(
+            &self.editor,
+            &snapshot,
+            blocks,
+            std::mem::take(&mut self.blocks),
+            added_ranges,
+            added_background,
+            cx,
+        );
+        self.base_blocks = replace_blocks(
+            &self.base_editor,
+            &base_snapshot,
+            base_blocks,
+            std::mem::take(&mut self.base_blocks),
+            deleted_ranges,
+            deleted_background,
+            cx,
+        );
+    }
+
+    fn sync_scroll_position(
+        &mut self,
+        source: &View<Editor>,
+        target: &View<Editor>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if !self.side_by_side {
+            return;
+        }
+        let position = source.update(cx, |editor, cx| editor.scroll_position(cx));
+        target.update(cx, |editor, cx| {
+            if editor.scroll_position(cx) != position {
+                editor.set_scroll_position(position, cx);
+            }
+        });
+    }
+
+    fn title(&self, cx: &AppContext) -> String {
+        let file_name = self
+            .buffer
+            .read(cx)
+            .file()
+            .map(|file| file.file_name(cx).to_string_lossy().into_owned())
+            .unwrap_or_else(|| "untitled".to_string());
+        format!("{file_name} ({})", self.diff_base.label())
+    }
+}
+
+/// Groups the hunks whose surrounding lines overlap, returning the indices of the hunks in each
+/// group along with the rows that the group and its surrounding lines span in the buffer and in
+/// the base text, which have the given numbers of rows.
+fn excerpt_rows(
+    hunks: &[DiffHunkRows],
+    row_count: u32,
+    base_row_count: u32,
+) -> Vec<(Range<usize>, DiffHunkRows)> {
+    let mut groups = Vec::<Range<usize>>::new();
+    for (ix, hunk) in hunks.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group)
+                if hunk.buffer_rows.start
+                    <= hunks[group.end - 1].buffer_rows.end + 2 * CONTEXT_LINE_COUNT =>
+            {
+                group.end = ix + 1;
+            }
+            _ => groups.push(ix..ix + 1),
+        }
+    }
+
+    // The lines around the hunks are unchanged, so there are as many of them on both sides.
+    groups
+        .into_iter()
+        .map(|group| {
+            let first = &hunks[group.start];
+            let last = &hunks[group.end - 1];
+            let before = CONTEXT_LINE_COUNT
+                .min(first.buffer_rows.start)
+                .min(first.base_rows.start);
+            let after = CONTEXT_LINE_COUNT
+                .min(row_count.saturating_sub(last.buffer_rows.end))
+                .min(base_row_count.saturating_sub(last.base_rows.end));
+            let rows = DiffHunkRows {
+                buffer_rows: first.buffer_rows.start - before..last.buffer_rows.end + after,
+                base_rows: first.base_rows.start - before..last.base_rows.end + after,
+            };
+            (group, rows)
+        })
+        .collect()
+}
+
+/// The range of an excerpt spanning the given rows, excluding the newline after the last one.
+fn rows_to_excerpt_range(rows: &Range<u32>, snapshot: &BufferSnapshot) -> Range<Point> {
+    let max_row = snapshot.max_point().row;
+    let start_row = rows.start.min(max_row);
+    let end_row = rows.end.saturating_sub(1).max(start_row).min(max_row);
+    Point::new(start_row, 0)..Point::new(end_row, snapshot.line_len(end_row))
+}
+
+/// Inserts the given blocks and highlights, which are positioned in the buffer of the given
+/// excerpts, into an editor in place of the blocks it had before, returning the ids of the new
+/// blocks.
+fn replace_blocks(
+    editor: &View<Editor>,
+    buffer_snapshot: &BufferSnapshot,
+    blocks: Vec<(ExcerptId, BlockProperties<Point>)>,
+    old_blocks: HashSet<BlockId>,
+    highlighted_ranges: Vec<(ExcerptId, Range<Point>)>,
+    highlight_color: Hsla,
+    cx: &mut ViewContext<DiffView>,
+) -> HashSet<BlockId> {
+    editor.update(cx, |editor, cx| {
+        let snapshot = editor.buffer().read(cx).snapshot(cx);
+        let anchor = |excerpt_id, point: Point, bias_left: bool| {
+            let text_anchor = if bias_left {
+                buffer_snapshot.anchor_before(point)
+            } else {
+                buffer_snapshot.anchor_after(point)
+            };
+            snapshot.anchor_in_excerpt(excerpt_id, text_anchor)
+        };
+        editor.remove_blocks(old_blocks, None, cx);
+        let block_ids = editor.insert_blocks(
+            blocks
+                .into_iter()
+                .map(|(excerpt_id, block)| BlockProperties {
+                    position: anchor(excerpt_id, block.position, true),
+                    height: block.height,
+                    style: block.style,
+                    render: block.render,
+                    disposition: block.disposition,
+                })
+                .collect::<Vec<_>>(),
+            None,
+            cx,
+        );
+        editor.highlight_text::<DiffView>(
+            highlighted_ranges
+                .into_iter()
+                .map(|(excerpt_id, range)| {
+                    anchor(excerpt_id, range.start, true)..anchor(excerpt_id, range.end, false)
+                })
+                .collect(),
+            HighlightStyle {
+                background_color: Some(highlight_color),
+                ..Default::default()
+            },
+            cx,
+        );
+        block_ids.into_iter().collect()
+    })
+}
+
+fn row_range_to_points(rows: &Range<u32>, snapshot: &BufferSnapshot) -> Range<Point> {
+    let max_point = snapshot.max_point();
+    Point::new(rows.start, 0).min(max_point)..Point::new(rows.end, 0).min(max_point)
+}
+
+/// Where to place a block so that it appears above the given row, which may be past the end of
+/// the buffer.
+fn block_position(row: u32, snapshot: &BufferSnapshot) -> (Point, BlockDisposition) {
+    let max_point = snapshot.max_point();
+    if row > max_point.row {
+        (max_point, BlockDisposition::Below)
+    } else {
+        (Point::new(row, 0), BlockDisposition::Above)
+    }
+}
+
+/// Blank blocks that are `height` lines tall in total, placed above the given row.
+fn spacer_blocks(
+    row: u32,
+    height: u32,
+    snapshot: &BufferSnapshot,
+) -> impl Iterator<Item = BlockProperties<Point>> {
+    let (position, disposition) = block_position(row, snapshot);
+    // Block heights can't exceed `u8::MAX`, so taller spacers are split into several blocks.
+    (0..height)
+        .step_by(u8::MAX as usize)
+        .map(move |start| BlockProperties {
+            position,
+            height: (height - start).min(u8::MAX as u32) as u8,
+            style: BlockStyle::Fixed,
+            render: Arc::new(|cx: &mut BlockContext| div().id(cx.block_id).into_any_element()),
+            disposition,
+        })
+}
+
+/// A line of text along with the syntax highlights of its byte ranges.
+type HighlightedLine = (SharedString, Vec<(Range<usize>, HighlightStyle)>);
+
+/// The given rows of a buffer, highlighted according to the buffer's language.
+fn highlighted_lines(
+    rows: &Range<u32>,
+    snapshot: &BufferSnapshot,
+    syntax_theme: &SyntaxTheme,
+) -> Vec<HighlightedLine> {
+    let max_row = snapshot.max_point().row;
+    (rows.start..rows.end.min(max_row + 1))
+        .map(|row| {
+            let mut text = String::new();
+            let mut highlights = Vec::new();
+            let range = Point::new(row, 0)..Point::new(row, snapshot.line_len(row));
+            for chunk in snapshot.chunks(range, true) {
+                let start = text.len();
+                text.push_str(chunk.text);
+                if let Some(style) = chunk
+                    .syntax_highlight_id
+                    .and_then(|highlight_id| highlight_id.style(syntax_theme))
+                {
+                    highlights.push((start..text.len(), style));
+                }
+            }
+            (text.into(), highlights)
+        })
+        .collect()
+}
+
+fn deleted_lines_renderer(lines: Vec<HighlightedLine>, background: Hsla) -> RenderBlock {
+    let lines: Arc<[HighlightedLine]> = lines.into();
+    Arc::new(move |cx: &mut BlockContext| {
+        let text_style = cx.editor_style.text.clone();
+        let line_height = cx.line_height;
+        v_flex()
+            .id(cx.block_id)
+            .w(cx.max_width + cx.gutter_width)
+            .pl(cx.gutter_width)
+            .bg(background)
+            .children(lines.iter().map(|(text, highlights)| {
+                div().h(line_height).child(
+                    StyledText::new(text.clone())
+                        .with_highlights(&text_style, highlights.iter().cloned()),
+                )
+            }))
+            .into_any_element()
+    })
+}
+
+impl Render for DiffView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let other_base = match self.diff_base {
+            DiffBase::Head => DiffBase::Index,
+            DiffBase::Index => DiffBase::Head,
+        };
+
+        v_flex()
+            .key_context("DiffView")
+            .track_focus(&self.focus_handle)
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .on_action(cx.listener(Self::toggle_side_by_side))
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_2()
+                    .justify_between()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(
+                        Label::new(if self.hunks.is_empty() {
+                            format!("No changes since {}", self.diff_base.label())
+                        } else {
+                            format!("Changes since {}", self.diff_base.label())
+                        })
+                        .color(Color::Muted),
+                    )
+                    .child(
+                        h_flex()
+                            .gap_1()
+                            .child(
+                                Button::new(
+                                    "switch-diff-base",
+                                    format!("Compare with {}", other_base.label()),
+                                )
+                                .on_click(cx.listener(
+                                    move |this, _, cx| this.set_diff_base(other_base, cx),
+                                )),
+                            )
+                            .child(
+                                Button::new(
+                                    "toggle-side-by-side",
+                                    if self.side_by_side {
+                                        "Inline"
+                                    } else {
+                                        "Side by Side"
+                                    },
+                                )
+                                .on_click(cx.listener(
+                                    |this, _, cx| this.toggle_side_by_side(&ToggleSideBySide, cx),
+                                )),
+                            ),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .flex_1()
+                    .w_full()
+                    .when(self.side_by_side, |this| {
+                        this.child(
+                            div()
+                                .flex_1()
+                                .h_full()
+                                .border_r_1()
+                                .border_color(cx.theme().colors().border)
+                                .child(self.base_editor.clone()),
+                        )
+                    })
+                    .child(div().flex_1().h_full().child(self.editor.clone())),
+            )
+    }
+}
+
+impl FocusableView for DiffView {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Item for DiffView {
+    type Event = EditorEvent;
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn deactivated(&mut self, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, cx| editor.deactivated(cx));
+    }
+
+    fn navigate(&mut self, data: Box<dyn Any>, cx: &mut ViewContext<Self>) -> bool {
+        self.editor
+            .update(cx, |editor, cx| editor.navigate(data, cx))
+    }
+
+    fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
+        Some(format!("Diff of {}", self.title(cx)).into())
+    }
+
+    fn tab_content(
+        &self,
+        _detail: Option<usize>,
+        selected: bool,
+        cx: &WindowContext,
+    ) -> AnyElement {
+        h_flex()
+            .gap_1()
+            .child(Icon::new(IconName::Split).color(Color::Muted))
+            .child(Label::new(self.title(cx)).color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            }))
+            .into_any_element()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("git diff view")
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::Item),
+    ) {
+        self.editor.for_each_project_item(cx, f)
+    }
+
+    fn set_nav_history(&mut self, nav_history: ItemNavHistory, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, _| {
+            editor.set_nav_history(Some(nav_history));
+        });
+    }
+
+    fn clone_on_split(
+        &self,
+        _workspace_id: workspace::WorkspaceId,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<View<Self>>
+    where
+        Self: Sized,
+    {
+        Some(cx.new_view(|cx| {
+            DiffView::new(
+                self.project.clone(),
+                self.buffer.clone(),
+                self.diff_base,
+                cx,
+            )
+        }))
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.buffer.read(cx).is_dirty()
+    }
+
+    fn has_conflict(&self, cx: &AppContext) -> bool {
+        self.buffer.read(cx).has_conflict()
+    }
+
+    fn can_save(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn save(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.save(project, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        _: Model<Project>,
+        _: PathBuf,
+        _: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        unreachable!()
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.reload(project, cx)
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a View<Self>,
+        _: &'a AppContext,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+
+    fn breadcrumb_location(&self) -> ToolbarItemLocation {
+        ToolbarItemLocation::PrimaryLeft
+    }
+
+    fn breadcrumbs(&self, theme: &theme::Theme, cx: &AppContext) -> Option<Vec<BreadcrumbText>> {
+        self.editor.breadcrumbs(theme, cx)
+    }
+
+    fn added_to_workspace(&mut self, workspace: &mut Workspace, cx: &mut ViewContext<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.added_to_workspace(workspace, cx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_test;
+    use gpui::TestAppContext;
+    use project::FakeFs;
+    use serde_json::json;
+    use std::path::Path;
+
+    #[gpui::test]
+    async fn test_diff_view_against_index(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "one\nTWO\nthree\nfour\n",
+            }),
+        )
+        .await;
+        fs.set_index_for_repo(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), "one\ntwo\nthree\n".into())],
+        );
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+
+        let (diff_view, cx) = cx.add_window_view(|cx| {
+            DiffView::new(project.clone(), buffer.clone(), DiffBase::Index, cx)
+        });
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, cx| {
+            assert_eq!(
+                diff_view.base_buffer.read(cx).text(),
+                "one\ntwo\nthree\n".to_string()
+            );
+            assert_eq!(
+                diff_view.hunks,
+                [
+                    DiffHunkRows {
+                        buffer_rows: 1..2,
+                        base_rows: 1..2,
+                    },
+                    DiffHunkRows {
+                        buffer_rows: 3..4,
+                        base_rows: 3..3,
+                    },
+                ]
+            );
+            // Both hunks are close enough to be shown in the same excerpt, along with the
+            // unchanged lines around them.
+            assert_eq!(diff_view.hunk_excerpts.len(), 1);
+            assert_eq!(
+                diff_view.editor.read(cx).text(cx),
+                "one\nTWO\nthree\nfour\n"
+            );
+            assert_eq!(diff_view.base_editor.read(cx).text(cx), "one\ntwo\nthree\n");
+
+            // Inline, the removed line is shown above the line replacing it.
+            assert_eq!(diff_view.blocks.len(), 1);
+            assert!(diff_view.base_blocks.is_empty());
+
+            // Side by side, the base editor is padded where the buffer added a line.
+            diff_view.toggle_side_by_side(&ToggleSideBySide, cx);
+            assert!(diff_view.blocks.is_empty());
+            assert_eq!(diff_view.base_blocks.len(), 1);
+        });
+
+        buffer.update(cx, |buffer, cx| buffer.edit([(4..7, "two")], None, cx));
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, _| {
+            assert_eq!(
+                diff_view.hunks,
+                [DiffHunkRows {
+                    buffer_rows: 3..4,
+                    base_rows: 3..3,
+                }]
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_diff_view_excerpts(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let text = (1..=20).map(|row| format!("{row}\n")).collect::<String>();
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": text.replace("2\n", "TWO\n").replace("18\n", "EIGHTEEN\n"),
+            }),
+        )
+        .await;
+        fs.set_index_for_repo(Path::new("/root/.git"), &[(Path::new("a.txt"), text)]);
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+
+        let (diff_view, cx) = cx.add_window_view(|cx| {
+            DiffView::new(project.clone(), buffer.clone(), DiffBase::Index, cx)
+        });
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, cx| {
+            // Hunks far apart are shown in separate excerpts, with at most three unchanged lines
+            // around them.
+            assert_eq!(diff_view.hunk_excerpts.len(), 2);
+            assert_eq!(
+                diff_view.editor.read(cx).text(cx),
+                "1\nTWO\n3\n4\n5\n15\n16\n17\nEIGHTEEN\n19\n20\n"
+            );
+            assert_eq!(
+                diff_view.base_editor.read(cx).text(cx),
+                "1\n2\n3\n4\n5\n15\n16\n17\n18\n19\n20\n"
+            );
+        });
+
+        // Editing lines within the excerpts keeps them.
+        buffer.update(cx, |buffer, cx| buffer.edit([(2..5, "two")], None, cx));
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, cx| {
+            assert_eq!(diff_view.hunk_excerpts.len(), 1);
+            assert_eq!(
+                diff_view.editor.read(cx).text(cx),
+                "15\n16\n17\nEIGHTEEN\n19\n20\n"
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_diff_view_long_deletion(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let deleted_lines = (0..300).map(|row| format!("{row}\n")).collect::<String>();
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "one\ntwo\n",
+            }),
+        )
+        .await;
+        fs.set_index_for_repo(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), format!("one\n{deleted_lines}two\n"))],
+        );
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+
+        let (diff_view, cx) = cx.add_window_view(|cx| {
+            DiffView::new(project.clone(), buffer.clone(), DiffBase::Index, cx)
+        });
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, _| {
+            // The deleted lines don't fit in a single block, so they're split into two.
+            assert_eq!(diff_view.blocks.len(), 2);
+            assert!(diff_view.base_blocks.is_empty());
+        });
+    }
+
+    #[gpui::test]
+    async fn test_diff_view_against_head(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "one\nTWO\nthree\n",
+            }),
+        )
+        .await;
+        fs.set_head_for_repo(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), "one\ntwo\nthree\n".into())],
+        );
+        fs.set_index_for_repo(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), "one\nTWO\nthree\n".into())],
+        );
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        cx.executor().run_until_parked();
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.txt", cx)
+            })
+            .await
+            .unwrap();
+
+        let (diff_view, cx) = cx.add_window_view(|cx| {
+            DiffView::new(project.clone(), buffer.clone(), DiffBase::Head, cx)
+        });
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, cx| {
+            assert_eq!(
+                diff_view.base_buffer.read(cx).text(),
+                "one\ntwo\nthree\n".to_string()
+            );
+            assert_eq!(
+                diff_view.hunks,
+                [DiffHunkRows {
+                    buffer_rows: 1..2,
+                    base_rows: 1..2,
+                }]
+            );
+        });
+
+        // Committing the change updates HEAD without changing the index.
+        fs.set_head_for_repo(
+            Path::new("/root/.git"),
+            &[(Path::new("a.txt"), "one\nTWO\nthree\n".into())],
+        );
+        cx.executor().run_until_parked();
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.executor().run_until_parked();
+        diff_view.update(cx, |diff_view, cx| {
+            assert_eq!(
+                diff_view.base_buffer.read(cx).text(),
+                "one\nTWO\nthree\n".to_string()
+            );
+            assert!(diff_view.hunks.is_empty());
+            assert!(diff_view.hunk_excerpts.is_empty());
+        });
+    }
+}
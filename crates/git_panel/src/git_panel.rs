@@ -0,0 +1,691 @@
+mod commit_history;
+mod conflicts_view;
+mod diff_view;
+mod git_panel_settings;
+
+use anyhow::{anyhow, Result};
+use collections::{HashMap, HashSet};
+pub use commit_history::{CommitHistory, ShowFileHistory, ShowHistory};
+pub use conflicts_view::{ConflictsView, ShowConflicts};
+use db::kvp::KEY_VALUE_STORE;
+pub use diff_view::{DiffBase, DiffView, DiffWithHead, DiffWithIndex, ToggleSideBySide};
+use editor::Editor;
+use git_panel_settings::{GitPanelDockPosition, GitPanelSettings};
+use gpui::{
+    actions, Action, AppContext, AsyncWindowContext, EventEmitter, FocusHandle, FocusableView,
+    Model, Pixels, Render, Task, View, ViewContext, VisualContext as _, WeakView, WindowContext,
+};
+use project::{repository::GitFileStatus, Fs, GitStatusEntry, Project, ProjectPath, WorktreeId};
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use std::{path::Path, sync::Arc, time::Duration};
+use ui::{prelude::*, KeyBinding, ListHeader, ListItem, Tooltip};
+use util::ResultExt;
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    notifications::DetachAndPromptErr,
+    Workspace,
+};
+
+const GIT_PANEL_KEY: &'static str = "GitPanel";
+
+/// How long to wait after the project changes before listing the changed files again.
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(50);
+
+actions!(git_panel, [ToggleFocus, StageAll, UnstageAll, Commit]);
+
+pub fn init_settings(cx: &mut AppContext) {
+    GitPanelSettings::register(cx);
+}
+
+pub fn init(cx: &mut AppContext) {
+    init_settings(cx);
+    commit_history::init(cx);
+    conflicts_view::init(cx);
+    diff_view::init(cx);
+}
+
+pub struct GitPanel {
+    project: Model<Project>,
+    workspace: WeakView<Workspace>,
+    fs: Arc<dyn Fs>,
+    focus_handle: FocusHandle,
+    commit_editor: View<Editor>,
+    /// The files with staged or unstaged changes in each local worktree.
+    statuses: Vec<(WorktreeId, GitStatusEntry)>,
+    width: Option<Pixels>,
+    pending_serialization: Task<Option<()>>,
+    update_statuses_task: Task<Option<()>>,
+    is_committing: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedGitPanel {
+    width: Option<Pixels>,
+}
+
+impl GitPanel {
+    fn new(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) -> View<Self> {
+        let project = workspace.project().clone();
+        cx.new_view(|cx: &mut ViewContext<Self>| {
+            cx.subscribe(&project, |this, _, event, cx| match event {
+                project::Event::WorktreeAdded
+                | project::Event::WorktreeRemoved(_)
+                | project::Event::WorktreeUpdatedEntries(..)
+                | project::Event::WorktreeUpdatedGitRepositories(_) => this.update_statuses(cx),
+                _ => {}
+            })
+            .detach();
+
+            let commit_editor = cx.new_view(|cx| {
+                let mut editor = Editor::auto_height(6, cx);
+                editor.set_placeholder_text("Commit message", cx);
+                editor
+            });
+            cx.observe(&commit_editor, |_, _, cx| cx.notify()).detach();
+
+            let mut this = Self {
+                project,
+                workspace: workspace.weak_handle(),
+                fs: workspace.app_state().fs.clone(),
+                focus_handle: cx.focus_handle(),
+                commit_editor,
+                statuses: Vec::new(),
+                width: None,
+                pending_serialization: Task::ready(None),
+                update_statuses_task: Task::ready(None),
+                is_committing: false,
+            };
+            this.update_statuses(cx);
+            this
+        })
+    }
+
+    pub async fn load(
+        workspace: WeakView<Workspace>,
+        mut cx: AsyncWindowContext,
+    ) -> Result<View<Self>> {
+        let serialized_panel = cx
+            .background_executor()
+            .spawn(async move { KEY_VALUE_STORE.read_kvp(GIT_PANEL_KEY) })
+            .await
+            .map_err(|e| anyhow!("Failed to load git panel: {}", e))
+            .log_err()
+            .flatten()
+            .map(|panel| serde_json::from_str::<SerializedGitPanel>(&panel))
+            .transpose()
+            .log_err()
+            .flatten();
+
+        workspace.update(&mut cx, |workspace, cx| {
+            let panel = GitPanel::new(workspace, cx);
+            if let Some(serialized_panel) = serialized_panel {
+                panel.update(cx, |panel, cx| {
+                    panel.width = serialized_panel.width;
+                    cx.notify();
+                });
+            }
+            panel
+        })
+    }
+
+    fn serialize(&mut self, cx: &mut ViewContext<Self>) {
+        let width = self.width;
+        self.pending_serialization = cx.background_executor().spawn(
+            async move {
+                KEY_VALUE_STORE
+                    .write_kvp(
+                        GIT_PANEL_KEY.into(),
+                        serde_json::to_string(&SerializedGitPanel { width })?,
+                    )
+                    .await?;
+                anyhow::Ok(())
+            }
+            .log_err(),
+        );
+    }
+
+    fn update_statuses(&mut self, cx: &mut ViewContext<Self>) {
+        let worktrees = self
+            .project
+            .read(cx)
+            .visible_worktrees(cx)
+            .collect::<Vec<_>>();
+        self.update_statuses_task = cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+            let requests = this
+                .update(&mut cx, |_, cx| {
+                    worktrees
+                        .iter()
+                        .filter_map(|worktree| {
+                            let worktree_id = worktree.read(cx).id();
+                            let request = worktree.update(cx, |worktree, cx| {
+                                worktree
+                                    .as_local()
+                                    .map(|worktree| worktree.git_statuses(cx))
+                            })?;
+                            Some((worktree_id, request))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .ok()?;
+
+            let mut statuses = Vec::new();
+            for (worktree_id, request) in requests {
+                statuses.extend(request.await.into_iter().map(|entry| (worktree_id, entry)));
+            }
+            this.update(&mut cx, |this, cx| {
+                this.statuses = statuses;
+                cx.notify();
+            })
+            .ok()
+        });
+    }
+
+    fn has_staged_changes(&self) -> bool {
+        self.statuses.iter().any(|(_, entry)| entry.is_staged)
+    }
+
+    fn stage_all(&mut self, _: &StageAll, cx: &mut ViewContext<Self>) {
+        self.stage_entries(false, true, cx);
+    }
+
+    fn unstage_all(&mut self, _: &UnstageAll, cx: &mut ViewContext<Self>) {
+        self.stage_entries(true, false, cx);
+    }
+
+    /// Stages or unstages all of the files listed as staged when `is_staged` is set, or as
+    /// unstaged otherwise.
+    fn stage_entries(&mut self, is_staged: bool, stage: bool, cx: &mut ViewContext<Self>) {
+        let paths = self
+            .statuses
+            .iter()
+            .filter(|(_, entry)| entry.is_staged == is_staged)
+            .map(|(worktree_id, entry)| (*worktree_id, entry.path.clone()))
+            .collect();
+        self.stage_paths(paths, stage, cx);
+    }
+
+    fn stage_paths(
+        &mut self,
+        paths: Vec<(WorktreeId, Arc<Path>)>,
+        stage: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let mut paths_by_worktree = HashMap::<_, Vec<_>>::default();
+        for (worktree_id, path) in paths {
+            paths_by_worktree.entry(worktree_id).or_default().push(path);
+        }
+
+        let requests = paths_by_worktree
+            .into_iter()
+            .filter_map(|(worktree_id, paths)| {
+                let worktree = self.project.read(cx).worktree_for_id(worktree_id, cx)?;
+                worktree.update(cx, |worktree, cx| {
+                    worktree
+                        .as_local()
+                        .map(|worktree| worktree.stage_paths(paths, stage, cx))
+                })
+            })
+            .collect::<Vec<_>>();
+        cx.spawn(|this, mut cx| async move {
+            for request in requests {
+                request.await?;
+            }
+            this.update(&mut cx, |this, cx| this.update_statuses(cx))
+        })
+        .detach_and_prompt_err(
+            if stage {
+                "Failed to stage changes"
+            } else {
+                "Failed to unstage changes"
+            },
+            cx,
+            |_, _| None,
+        );
+    }
+
+    fn commit(&mut self, _: &Commit, cx: &mut ViewContext<Self>) {
+        let message = self.commit_editor.read(cx).text(cx);
+        if self.is_committing || message.trim().is_empty() || !self.has_staged_changes() {
+            return;
+        }
+
+        // Each repository with staged changes gets a commit with the same message.
+        let repositories = self
+            .statuses
+            .iter()
+            .filter(|(_, entry)| entry.is_staged)
+            .map(|(worktree_id, entry)| (*worktree_id, entry.work_directory.clone()))
+            .collect::<HashSet<_>>();
+        let requests = repositories
+            .into_iter()
+            .filter_map(|(worktree_id, work_directory)| {
+                let worktree = self.project.read(cx).worktree_for_id(worktree_id, cx)?;
+                worktree.update(cx, |worktree, cx| {
+                    worktree
+                        .as_local()
+                        .map(|worktree| worktree.commit(&work_directory, message.clone(), cx))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        self.is_committing = true;
+        cx.notify();
+        cx.spawn(|this, mut cx| async move {
+            let mut result = Ok(());
+            for request in requests {
+                result = result.and(request.await);
+            }
+            this.update(&mut cx, |this, cx| {
+                this.is_committing = false;
+                if result.is_ok() {
+                    this.commit_editor.update(cx, |editor, cx| editor.clear(cx));
+                }
+                this.update_statuses(cx);
+            })?;
+            result
+        })
+        .detach_and_prompt_err("Failed to commit", cx, |_, _| None);
+    }
+
+    fn open_entry(&mut self, worktree_id: WorktreeId, path: Arc<Path>, cx: &mut ViewContext<Self>) {
+        self.workspace
+            .update(cx, |workspace, cx| {
+                workspace
+                    .open_path(ProjectPath { worktree_id, path }, None, true, cx)
+                    .detach_and_log_err(cx);
+            })
+            .log_err();
+    }
+
+    /// Opens the diff of a staged file against HEAD, or of an unstaged file against the index.
+    fn open_diff(
+        &mut self,
+        worktree_id: WorktreeId,
+        path: Arc<Path>,
+        is_staged: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let diff_base = if is_staged {
+            DiffBase::Head
+        } else {
+            DiffBase::Index
+        };
+        let open_buffer = self.project.update(cx, |project, cx| {
+            project.open_buffer(ProjectPath { worktree_id, path }, cx)
+        });
+        let workspace = self.workspace.clone();
+        cx.spawn(|_, mut cx| async move {
+            let buffer = open_buffer.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                DiffView::open(workspace, buffer, diff_base, cx)
+            })
+        })
+        .detach_and_prompt_err("Failed to open diff", cx, |_, _| None);
+    }
+
+    fn render_section_header(
+        &self,
+        label: &'static str,
+        count: usize,
+        is_staged: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let (icon, tooltip) = if is_staged {
+            (IconName::Dash, "Unstage All")
+        } else {
+            (IconName::Plus, "Stage All")
+        };
+        ListHeader::new(format!("{label} ({count})"))
+            .inset(true)
+            .end_slot(
+                IconButton::new(label, icon)
+                    .icon_color(Color::Muted)
+                    .disabled(count == 0)
+                    .tooltip(move |cx| Tooltip::text(tooltip, cx))
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.stage_entries(is_staged, !is_staged, cx);
+                    })),
+            )
+    }
+
+    fn render_entry(
+        &self,
+        ix: usize,
+        worktree_id: WorktreeId,
+        entry: &GitStatusEntry,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let color = match entry.status {
+            GitFileStatus::Added => Color::Created,
+            GitFileStatus::Modified => Color::Modified,
+            GitFileStatus::Conflict => Color::Conflict,
+        };
+        let file_name = entry
+            .path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let directory = entry
+            .path
+            .parent()
+            .map(|directory| directory.to_string_lossy().into_owned())
+            .filter(|directory| !directory.is_empty());
+        let is_staged = entry.is_staged;
+        let (icon, tooltip) = if is_staged {
+            (IconName::Dash, "Unstage File")
+        } else {
+            (IconName::Plus, "Stage File")
+        };
+        let path = entry.path.clone();
+
+        ListItem::new(ix)
+            .inset(true)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Label::new(file_name).color(color))
+                    .children(directory.map(|directory| {
+                        Label::new(directory)
+                            .color(Color::Muted)
+                            .size(LabelSize::Small)
+                    })),
+            )
+            .end_slot(
+                h_flex()
+                    .child(
+                        IconButton::new(("diff", ix), IconName::Split)
+                            .icon_color(Color::Muted)
+                            .tooltip(|cx| Tooltip::text("Open Diff", cx))
+                            .on_click(cx.listener({
+                                let path = path.clone();
+                                move |this, _, cx| {
+                                    this.open_diff(worktree_id, path.clone(), is_staged, cx);
+                                }
+                            })),
+                    )
+                    .child(
+                        IconButton::new(("stage", ix), icon)
+                            .icon_color(Color::Muted)
+                            .tooltip(move |cx| Tooltip::text(tooltip, cx))
+                            .on_click(cx.listener({
+                                let path = path.clone();
+                                move |this, _, cx| {
+                                    this.stage_paths(
+                                        vec![(worktree_id, path.clone())],
+                                        !is_staged,
+                                        cx,
+                                    );
+                                }
+                            })),
+                    ),
+            )
+            .on_click(cx.listener(move |this, _, cx| {
+                this.open_entry(worktree_id, path.clone(), cx);
+            }))
+    }
+}
+
+impl Render for GitPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let panel = v_flex()
+            .id("git-panel")
+            .size_full()
+            .key_context("GitPanel")
+            .track_focus(&self.focus_handle);
+        if !self.project.read(cx).is_local() {
+            return panel
+                .p_4()
+                .child(Label::new("Git is only available in local projects.").color(Color::Muted));
+        }
+
+        let (staged, unstaged): (Vec<_>, Vec<_>) = self
+            .statuses
+            .iter()
+            .enumerate()
+            .partition(|(_, (_, entry))| entry.is_staged);
+        let conflict_count = self
+            .statuses
+            .iter()
+            .filter(|(_, entry)| entry.status == GitFileStatus::Conflict)
+            .map(|(worktree_id, entry)| (worktree_id, &entry.path))
+            .collect::<HashSet<_>>()
+            .len();
+        let can_commit = !self.is_committing
+            && !staged.is_empty()
+            && !self.commit_editor.read(cx).text(cx).trim().is_empty();
+
+        panel
+            .on_action(cx.listener(Self::stage_all))
+            .on_action(cx.listener(Self::unstage_all))
+            .on_action(cx.listener(Self::commit))
+            .child(
+                v_flex()
+                    .id("git-panel-entries")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .when(conflict_count > 0, |this| {
+                        this.child(
+                            ListHeader::new(format!("Merge Conflicts ({conflict_count})"))
+                                .inset(true)
+                                .end_slot(
+                                    Button::new("show-conflicts", "Resolve")
+                                        .key_binding(KeyBinding::for_action(&ShowConflicts, cx))
+                                        .on_click(|_, cx| {
+                                            cx.dispatch_action(ShowConflicts.boxed_clone())
+                                        }),
+                                ),
+                        )
+                    })
+                    .child(self.render_section_header("Staged Changes", staged.len(), true, cx))
+                    .children(
+                        staged
+                            .iter()
+                            .map(|(ix, (worktree_id, entry))| {
+                                self.render_entry(*ix, *worktree_id, entry, cx)
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .child(self.render_section_header("Changes", unstaged.len(), false, cx))
+                    .children(
+                        unstaged
+                            .iter()
+                            .map(|(ix, (worktree_id, entry))| {
+                                self.render_entry(*ix, *worktree_id, entry, cx)
+                            })
+                            .collect::<Vec<_>>(),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .p_2()
+                    .gap_2()
+                    .border_t_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(
+                        div()
+                            .p_1()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(cx.theme().colors().border)
+                            .child(self.commit_editor.clone()),
+                    )
+                    .child(
+                        Button::new("commit", "Commit")
+                            .style(ButtonStyle::Filled)
+                            .full_width()
+                            .disabled(!can_commit)
+                            .key_binding(KeyBinding::for_action(&Commit, cx))
+                            .on_click(cx.listener(|this, _, cx| this.commit(&Commit, cx))),
+                    ),
+            )
+    }
+}
+
+impl EventEmitter<PanelEvent> for GitPanel {}
+
+impl Panel for GitPanel {
+    fn position(&self, cx: &WindowContext) -> DockPosition {
+        match GitPanelSettings::get_global(cx).dock {
+            GitPanelDockPosition::Left => DockPosition::Left,
+            GitPanelDockPosition::Right => DockPosition::Right,
+        }
+    }
+
+    fn position_is_valid(&self, position: DockPosition) -> bool {
+        matches!(position, DockPosition::Left | DockPosition::Right)
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        settings::update_settings_file::<GitPanelSettings>(self.fs.clone(), cx, move |settings| {
+            let dock = match position {
+                DockPosition::Left | DockPosition::Bottom => GitPanelDockPosition::Left,
+                DockPosition::Right => GitPanelDockPosition::Right,
+            };
+            settings.dock = Some(dock);
+        });
+    }
+
+    fn size(&self, cx: &WindowContext) -> Pixels {
+        self.width
+            .unwrap_or_else(|| GitPanelSettings::get_global(cx).default_width)
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.width = size;
+        self.serialize(cx);
+        cx.notify();
+    }
+
+    fn icon(&self, cx: &WindowContext) -> Option<ui::IconName> {
+        Some(ui::IconName::FileGit).filter(|_| GitPanelSettings::get_global(cx).button)
+    }
+
+    fn icon_tooltip(&self, _cx: &WindowContext) -> Option<&'static str> {
+        Some("Git Panel")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleFocus)
+    }
+
+    fn persistent_name() -> &'static str {
+        "Git Panel"
+    }
+}
+
+impl FocusableView for GitPanel {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{repository::RepoPath, FakeFs};
+    use serde_json::json;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_stage_unstage_and_commit(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                ".git": {},
+                "a.txt": "a",
+                "b.txt": "b",
+            }),
+        )
+        .await;
+        fs.with_git_state(Path::new("/root/.git"), true, |state| {
+            state
+                .unstaged_statuses
+                .insert(RepoPath::from(Path::new("a.txt")), GitFileStatus::Modified);
+            state
+                .unstaged_statuses
+                .insert(RepoPath::from(Path::new("b.txt")), GitFileStatus::Added);
+        });
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        let worktree_id = project.read_with(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let panel = workspace.update(cx, |workspace, cx| GitPanel::new(workspace, cx));
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.run_until_parked();
+
+        let entries = |cx: &mut VisualTestContext| {
+            panel.update(cx, |panel, _| {
+                panel
+                    .statuses
+                    .iter()
+                    .map(|(_, entry)| (entry.path.to_string_lossy().into_owned(), entry.is_staged))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(
+            entries(cx),
+            [("a.txt".into(), false), ("b.txt".into(), false)]
+        );
+
+        panel.update(cx, |panel, cx| panel.stage_all(&StageAll, cx));
+        cx.run_until_parked();
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            entries(cx),
+            [("a.txt".into(), true), ("b.txt".into(), true)]
+        );
+
+        panel.update(cx, |panel, cx| {
+            panel.stage_paths(vec![(worktree_id, Path::new("b.txt").into())], false, cx)
+        });
+        cx.run_until_parked();
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(
+            entries(cx),
+            [("a.txt".into(), true), ("b.txt".into(), false)]
+        );
+
+        // Only the staged file is committed, after which the message is cleared.
+        panel.update(cx, |panel, cx| {
+            panel
+                .commit_editor
+                .update(cx, |editor, cx| editor.set_text("Update a", cx));
+            panel.commit(&Commit, cx);
+        });
+        cx.run_until_parked();
+        cx.executor().advance_clock(UPDATE_DEBOUNCE);
+        cx.run_until_parked();
+        assert_eq!(entries(cx), [("b.txt".into(), false)]);
+        panel.update(cx, |panel, cx| {
+            assert_eq!(panel.commit_editor.read(cx).text(cx), "");
+        });
+        let mut commit_messages = Vec::new();
+        fs.with_git_state(Path::new("/root/.git"), false, |state| {
+            commit_messages = state.commit_messages.clone();
+        });
+        assert_eq!(commit_messages, ["Update a"]);
+    }
+
+    pub(crate) fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            editor::init_settings(cx);
+            workspace::init_settings(cx);
+            client::init_settings(cx);
+            Project::init_settings(cx);
+            init_settings(cx);
+        });
+    }
+}
@@ -22,6 +22,7 @@ use gpui::{AppContext, EventEmitter, HighlightStyle, ModelContext, Task, TaskLab
 use lazy_static::lazy_static;
 use lsp::LanguageServerId;
 use parking_lot::Mutex;
+use postage::{prelude::Stream as _, watch};
 use similar::{ChangeTag, TextDiff};
 use smallvec::SmallVec;
 use smol::future::yield_now;
@@ -98,6 +99,7 @@ pub struct Buffer {
     sync_parse_timeout: Duration,
     syntax_map: Mutex<SyntaxMap>,
     parsing_in_background: bool,
+    parse_status: (watch::Sender<bool>, watch::Receiver<bool>),
     parse_count: usize,
     diagnostics: SmallVec<[(LanguageServerId, DiagnosticSet); 2]>,
     remote_selections: TreeMap<ReplicaId, SelectionSet>,
@@ -108,6 +110,8 @@ pub struct Buffer {
     git_diff_update_count: usize,
     completion_triggers: Vec<String>,
     completion_triggers_timestamp: clock::Lamport,
+    signature_help_triggers: SignatureHelpTriggers,
+    signature_help_triggers_timestamp: clock::Lamport,
     deferred_ops: OperationQueue<Operation>,
     capability: Capability,
 }
@@ -249,7 +253,7 @@ pub enum Documentation {
     MultiLineMarkdown(ParsedMarkdown),
 }
 
-/// A completion provided by a language server
+/// A completion provided by a language server, or by the editor itself.
 #[derive(Clone, Debug)]
 pub struct Completion {
     /// The range of the buffer that will be replaced.
@@ -258,14 +262,25 @@ pub struct Completion {
     pub new_text: String,
     /// A label for this completion that is shown in the menu.
     pub label: CodeLabel,
-    /// The id of the language server that produced this completion.
-    pub server_id: LanguageServerId,
+    /// Where this completion comes from.
+    pub source: CompletionSource,
     /// The documentation for this completion.
     pub documentation: Option<Documentation>,
     /// The raw completion provided by the language server.
     pub lsp_completion: lsp::CompletionItem,
 }
 
+/// Where a [`Completion`] comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionSource {
+    /// The language server with the given id, which can resolve the completion and provide
+    /// additional edits for it.
+    LanguageServer(LanguageServerId),
+    /// The editor itself, such as a user snippet or a choice of a snippet's tabstop. These
+    /// completions are only ever shown locally.
+    Custom,
+}
+
 /// A code action provided by a language server.
 #[derive(Clone, Debug)]
 pub struct CodeAction {
@@ -313,6 +328,25 @@ pub enum Operation {
         /// The buffer's lamport timestamp.
         lamport_timestamp: clock::Lamport,
     },
+
+    /// An update to the characters that should trigger signature help
+    /// for this buffer.
+    UpdateSignatureHelpTriggers {
+        /// The characters that trigger or re-trigger signature help.
+        triggers: SignatureHelpTriggers,
+        /// The buffer's lamport timestamp.
+        lamport_timestamp: clock::Lamport,
+    },
+}
+
+/// The characters that trigger signature help for a buffer, usually
+/// provided by the LSP server's signature help capabilities.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignatureHelpTriggers {
+    /// The characters that show signature help.
+    pub trigger_characters: Vec<String>,
+    /// The characters that update signature help while it is already shown.
+    pub retrigger_characters: Vec<String>,
 }
 
 /// An event that occurs in a buffer.
@@ -616,6 +650,13 @@ impl Buffer {
             },
         ));
 
+        operations.push(proto::serialize_operation(
+            &Operation::UpdateSignatureHelpTriggers {
+                triggers: self.signature_help_triggers.clone(),
+                lamport_timestamp: self.signature_help_triggers_timestamp,
+            },
+        ));
+
         let text_operations = self.text.operations().clone();
         cx.background_executor().spawn(async move {
             let since = since.unwrap_or_default();
@@ -673,6 +714,7 @@ impl Buffer {
             capability,
             syntax_map: Mutex::new(SyntaxMap::new()),
             parsing_in_background: false,
+            parse_status: watch::channel_with(false),
             parse_count: 0,
             sync_parse_timeout: Duration::from_millis(1),
             autoindent_requests: Default::default(),
@@ -687,6 +729,8 @@ impl Buffer {
             git_diff_update_count: 0,
             completion_triggers: Default::default(),
             completion_triggers_timestamp: Default::default(),
+            signature_help_triggers: Default::default(),
+            signature_help_triggers_timestamp: Default::default(),
             deferred_ops: OperationQueue::new(),
         }
     }
@@ -979,6 +1023,21 @@ impl Buffer {
         self.parsing_in_background
     }
 
+    /// Waits until the buffer is no longer being parsed in the background.
+    pub fn parsing_idle(&self) -> impl Future<Output = ()> {
+        let mut parse_status = self.parse_status.1.clone();
+        async move {
+            let mut parsing = *parse_status.borrow();
+            while parsing {
+                if let Some(value) = parse_status.recv().await {
+                    parsing = value;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
     /// Indicates whether the buffer contains any regions that may be
     /// written in a language that hasn't been loaded yet.
     pub fn contains_unknown_injections(&self) -> bool {
@@ -1051,6 +1110,7 @@ impl Buffer {
             }
             Err(parse_task) => {
                 self.parsing_in_background = true;
+                *self.parse_status.0.borrow_mut() = true;
                 cx.spawn(move |this, mut cx| async move {
                     let new_syntax_map = parse_task.await;
                     this.update(&mut cx, move |this, cx| {
@@ -1068,6 +1128,7 @@ impl Buffer {
                             || this.version.changed_since(&parsed_version);
                         this.did_finish_parsing(new_syntax_map, cx);
                         this.parsing_in_background = false;
+                        *this.parse_status.0.borrow_mut() = false;
                         if parse_again {
                             this.reparse(cx);
                         }
@@ -1800,6 +1861,45 @@ impl Buffer {
         Some(edit_id)
     }
 
+    /// Re-indents every line intersecting the given ranges according to the
+    /// buffer's language, as if those lines had just been typed.
+    pub fn autoindent_ranges<I, T>(&mut self, ranges: I, cx: &mut ModelContext<Self>)
+    where
+        I: IntoIterator<Item = Range<T>>,
+        T: ToOffset,
+    {
+        if self.language.is_none() {
+            return;
+        }
+
+        let before_edit = self.snapshot();
+        let entries = ranges
+            .into_iter()
+            .map(|range| {
+                let start = range.start.to_offset(self);
+                AutoindentRequestEntry {
+                    first_line_is_new: true,
+                    original_indent_column: None,
+                    indent_size: before_edit.language_indent_size_at(start, cx),
+                    range: self.anchor_before(start)..self.anchor_after(range.end.to_offset(self)),
+                }
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return;
+        }
+
+        self.autoindent_requests.push(Arc::new(AutoindentRequest {
+            before_edit,
+            entries,
+            is_block_mode: false,
+        }));
+        // A request made during a reparse is computed once the reparse finishes.
+        if !self.parsing_in_background {
+            self.request_autoindent(cx);
+        }
+    }
+
     fn did_edit(
         &mut self,
         old_version: &clock::Global,
@@ -1881,6 +1981,7 @@ impl Buffer {
                 .iter()
                 .all(|s| self.can_resolve(&s.start) && self.can_resolve(&s.end)),
             Operation::UpdateCompletionTriggers { .. } => true,
+            Operation::UpdateSignatureHelpTriggers { .. } => true,
         }
     }
 
@@ -1933,6 +2034,13 @@ impl Buffer {
                 self.completion_triggers = triggers;
                 self.text.lamport_clock.observe(lamport_timestamp);
             }
+            Operation::UpdateSignatureHelpTriggers {
+                triggers,
+                lamport_timestamp,
+            } => {
+                self.signature_help_triggers = triggers;
+                self.text.lamport_clock.observe(lamport_timestamp);
+            }
         }
     }
 
@@ -2077,6 +2185,29 @@ impl Buffer {
     pub fn completion_triggers(&self) -> &[String] {
         &self.completion_triggers
     }
+
+    /// Override current signature help triggers with the ones provided by the language server.
+    pub fn set_signature_help_triggers(
+        &mut self,
+        triggers: SignatureHelpTriggers,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.signature_help_triggers = triggers.clone();
+        self.signature_help_triggers_timestamp = self.text.lamport_clock.tick();
+        self.send_operation(
+            Operation::UpdateSignatureHelpTriggers {
+                triggers,
+                lamport_timestamp: self.signature_help_triggers_timestamp,
+            },
+            cx,
+        );
+        cx.notify();
+    }
+
+    /// Returns the characters which show or update signature help for this buffer.
+    pub fn signature_help_triggers(&self) -> &SignatureHelpTriggers {
+        &self.signature_help_triggers
+    }
 }
 
 #[doc(hidden)]
@@ -2875,6 +3006,49 @@ impl BufferSnapshot {
         })
     }
 
+    /// Returns the ranges that should be edited together with the one containing `position`,
+    /// such as the names of an opening and a closing tag, including the range containing it.
+    /// Ranges are only linked while they have the same text.
+    pub fn linked_editing_ranges<T: ToOffset>(&self, position: T) -> Vec<Range<usize>> {
+        let position = position.to_offset(self);
+        let mut matches = self
+            .syntax
+            .matches(position..position, &self.text, |grammar| {
+                grammar.linked_editing_config.as_ref().map(|c| &c.query)
+            });
+        let configs = matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.linked_editing_config.as_ref().unwrap())
+            .collect::<Vec<_>>();
+
+        while let Some(mat) = matches.peek() {
+            let mut open = None;
+            let mut close = None;
+            let config = &configs[mat.grammar_index];
+            for capture in mat.captures {
+                if capture.index == config.open_capture_ix {
+                    open = Some(capture.node.byte_range());
+                } else if capture.index == config.close_capture_ix {
+                    close = Some(capture.node.byte_range());
+                }
+            }
+            matches.advance();
+
+            let Some((open, close)) = open.zip(close) else {
+                continue;
+            };
+            let contains_position = |range: &Range<usize>| range.to_inclusive().contains(&position);
+            if (contains_position(&open) || contains_position(&close))
+                && self.text_for_range(open.clone()).collect::<String>()
+                    == self.text_for_range(close.clone()).collect::<String>()
+            {
+                return vec![open, close];
+            }
+        }
+        Vec::new()
+    }
+
     /// Returns selections for remote peers intersecting the given range.
     #[allow(clippy::type_complexity)]
     pub fn remote_selections_in_range(
@@ -3311,6 +3485,9 @@ impl operation_queue::Operation for Operation {
             }
             | Operation::UpdateCompletionTriggers {
                 lamport_timestamp, ..
+            }
+            | Operation::UpdateSignatureHelpTriggers {
+                lamport_timestamp, ..
             } => *lamport_timestamp,
         }
     }
@@ -3398,6 +3575,14 @@ impl Completion {
     pub fn is_snippet(&self) -> bool {
         self.lsp_completion.insert_text_format == Some(lsp::InsertTextFormat::SNIPPET)
     }
+
+    /// The id of the language server that produced this completion, if any.
+    pub fn server_id(&self) -> Option<LanguageServerId> {
+        match self.source {
+            CompletionSource::LanguageServer(server_id) => Some(server_id),
+            CompletionSource::Custom => None,
+        }
+    }
 }
 
 pub(crate) fn contiguous_ranges(
@@ -1280,6 +1280,31 @@ fn test_autoindent_with_edit_at_end_of_buffer(cx: &mut AppContext) {
     });
 }
 
+#[gpui::test]
+fn test_autoindent_ranges(cx: &mut AppContext) {
+    init_settings(cx, |_| {});
+
+    cx.new_model(|cx| {
+        let text = "fn a() {\n    b();\n        if c {\nd();\n  }\ne();\n}";
+        let mut buffer =
+            Buffer::new(0, cx.entity_id().as_u64(), text).with_language(Arc::new(rust_lang()), cx);
+
+        // Only the lines intersecting the ranges are re-indented.
+        buffer.autoindent_ranges([Point::new(2, 0)..Point::new(4, 0)], cx);
+        assert_eq!(
+            buffer.text(),
+            "fn a() {\n    b();\n    if c {\n        d();\n    }\ne();\n}"
+        );
+
+        buffer.autoindent_ranges([0..buffer.len()], cx);
+        assert_eq!(
+            buffer.text(),
+            "fn a() {\n    b();\n    if c {\n        d();\n    }\n    e();\n}"
+        );
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_autoindent_multi_line_insertion(cx: &mut AppContext) {
     init_settings(cx, |_| {});
@@ -1906,6 +1931,34 @@ fn test_language_scope_at_with_combined_injections(cx: &mut AppContext) {
     });
 }
 
+#[gpui::test]
+fn test_linked_editing_ranges(cx: &mut AppContext) {
+    init_settings(cx, |_| {});
+
+    cx.new_model(|cx| {
+        let text = "<div><span>a</span></div>";
+        let buffer =
+            Buffer::new(0, cx.entity_id().as_u64(), text).with_language(Arc::new(html_lang()), cx);
+        let snapshot = buffer.snapshot();
+
+        assert_eq!(snapshot.linked_editing_ranges(1), vec![1..4, 21..24]);
+        assert_eq!(snapshot.linked_editing_ranges(23), vec![1..4, 21..24]);
+        assert_eq!(snapshot.linked_editing_ranges(10), vec![6..10, 14..18]);
+        assert_eq!(snapshot.linked_editing_ranges(11), vec![]);
+        assert_eq!(snapshot.linked_editing_ranges(0), vec![]);
+
+        buffer
+    });
+
+    // Tags whose names differ are not linked.
+    cx.new_model(|cx| {
+        let buffer = Buffer::new(0, cx.entity_id().as_u64(), "<div></dov>")
+            .with_language(Arc::new(html_lang()), cx);
+        assert_eq!(buffer.snapshot().linked_editing_ranges(2), vec![]);
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_serialization(cx: &mut gpui::AppContext) {
     let mut now = Instant::now();
@@ -2342,6 +2395,14 @@ fn html_lang() -> Language {
         "#,
     )
     .unwrap()
+    .with_linked_editing_query(
+        "
+        (_
+          (start_tag (tag_name) @open)
+          (end_tag (tag_name) @close))
+        ",
+    )
+    .unwrap()
 }
 
 fn erb_lang() -> Language {
@@ -450,6 +450,7 @@ pub struct LanguageQueries {
     pub embedding: Option<Cow<'static, str>>,
     pub injections: Option<Cow<'static, str>>,
     pub overrides: Option<Cow<'static, str>>,
+    pub linked_editing: Option<Cow<'static, str>>,
 }
 
 /// Represents a language for the given range. Some languages (e.g. HTML)
@@ -625,6 +626,7 @@ pub struct Grammar {
     pub embedding_config: Option<EmbeddingConfig>,
     pub(crate) injection_config: Option<InjectionConfig>,
     pub(crate) override_config: Option<OverrideConfig>,
+    pub(crate) linked_editing_config: Option<LinkedEditingConfig>,
     pub(crate) highlight_map: Mutex<HighlightMap>,
 }
 
@@ -678,6 +680,12 @@ struct BracketConfig {
     close_capture_ix: u32,
 }
 
+struct LinkedEditingConfig {
+    query: Query,
+    open_capture_ix: u32,
+    close_capture_ix: u32,
+}
+
 #[derive(Clone)]
 pub enum LanguageServerBinaryStatus {
     CheckingForUpdate,
@@ -828,6 +836,26 @@ impl LanguageRegistry {
         result
     }
 
+    /// Returns the path suffixes of the languages that have a grammar, whose files can be parsed
+    /// into syntax trees.
+    pub fn grammar_path_suffixes(&self) -> HashSet<String> {
+        let state = self.state.read();
+        state
+            .available_languages
+            .iter()
+            .filter(|language| !language.loaded)
+            .map(|language| &language.config)
+            .chain(
+                state
+                    .languages
+                    .iter()
+                    .filter(|language| language.grammar.is_some())
+                    .map(|language| &language.config),
+            )
+            .flat_map(|config| config.path_suffixes.iter().cloned())
+            .collect()
+    }
+
     pub fn add(&self, language: Arc<Language>) {
         self.state.write().add(language);
     }
@@ -1287,6 +1315,7 @@ impl Language {
                     indents_config: None,
                     injection_config: None,
                     override_config: None,
+                    linked_editing_config: None,
                     error_query: Query::new(&ts_language, "(ERROR) @error").unwrap(),
                     ts_language,
                     highlight_map: Default::default(),
@@ -1343,6 +1372,11 @@ impl Language {
                 .with_override_query(query.as_ref())
                 .context("Error loading override query")?;
         }
+        if let Some(query) = queries.linked_editing {
+            self = self
+                .with_linked_editing_query(query.as_ref())
+                .context("Error loading linked editing query")?;
+        }
         Ok(self)
     }
 
@@ -1433,6 +1467,28 @@ impl Language {
         Ok(self)
     }
 
+    pub fn with_linked_editing_query(mut self, source: &str) -> Result<Self> {
+        let grammar = self.grammar_mut();
+        let query = Query::new(&grammar.ts_language, source)?;
+        let mut open_capture_ix = None;
+        let mut close_capture_ix = None;
+        get_capture_indices(
+            &query,
+            &mut [
+                ("open", &mut open_capture_ix),
+                ("close", &mut close_capture_ix),
+            ],
+        );
+        if let Some((open_capture_ix, close_capture_ix)) = open_capture_ix.zip(close_capture_ix) {
+            grammar.linked_editing_config = Some(LinkedEditingConfig {
+                query,
+                open_capture_ix,
+                close_capture_ix,
+            });
+        }
+        Ok(self)
+    }
+
     pub fn with_indents_query(mut self, source: &str) -> Result<Self> {
         let grammar = self.grammar_mut();
         let query = Query::new(&grammar.ts_language, source)?;
@@ -1708,6 +1764,20 @@ impl Language {
 }
 
 impl LanguageScope {
+    /// Returns the name of the language that this scope belongs to.
+    pub fn language_name(&self) -> Arc<str> {
+        self.language.name()
+    }
+
+    /// Returns the name of the override that this scope is in, such as `string`, or `None`
+    /// for the language's default scope.
+    pub fn override_name(&self) -> Option<&str> {
+        let id = self.override_id?;
+        let grammar = self.language.grammar.as_ref()?;
+        let override_config = grammar.override_config.as_ref()?;
+        override_config.values.get(&id).map(|e| e.0.as_str())
+    }
+
     pub fn collapsed_placeholder(&self) -> &str {
         self.language.config.collapsed_placeholder.as_ref()
     }
@@ -91,6 +91,12 @@ pub struct LanguageSettings {
     pub extend_comment_on_newline: bool,
     /// Inlay hint related settings.
     pub inlay_hints: InlayHintSettings,
+    /// Whether to highlight the semantic tokens reported by language servers
+    /// on top of the syntax highlighting.
+    pub semantic_tokens: bool,
+    /// Whether to show the code lenses reported by language servers above the
+    /// lines they apply to.
+    pub code_lens: bool,
     /// Whether to automatically close brackets.
     pub use_autoclose: bool,
 }
@@ -210,6 +216,18 @@ pub struct LanguageSettingsContent {
     /// Inlay hint related settings.
     #[serde(default)]
     pub inlay_hints: Option<InlayHintSettings>,
+    /// Whether to highlight the semantic tokens reported by language servers
+    /// on top of the syntax highlighting.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub semantic_tokens: Option<bool>,
+    /// Whether to show the code lenses reported by language servers above the
+    /// lines they apply to.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub code_lens: Option<bool>,
     /// Whether to automatically type closing characters for you. For example,
     /// when you type (, Zed will automatically add a closing ) at the correct position.
     ///
@@ -548,6 +566,8 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
     merge(&mut settings.hard_tabs, src.hard_tabs);
     merge(&mut settings.soft_wrap, src.soft_wrap);
     merge(&mut settings.use_autoclose, src.use_autoclose);
+    merge(&mut settings.semantic_tokens, src.semantic_tokens);
+    merge(&mut settings.code_lens, src.code_lens);
     merge(&mut settings.show_wrap_guides, src.show_wrap_guides);
     merge(&mut settings.wrap_guides, src.wrap_guides.clone());
 
@@ -1,8 +1,8 @@
 //! Handles conversions of `language` items to and from the [`rpc`] protocol.
 
 use crate::{
-    diagnostic_set::DiagnosticEntry, CodeAction, CodeLabel, Completion, CursorShape, Diagnostic,
-    Language,
+    diagnostic_set::DiagnosticEntry, CodeAction, CodeLabel, Completion, CompletionSource,
+    CursorShape, Diagnostic, Language, SignatureHelpTriggers,
 };
 use anyhow::{anyhow, Result};
 use clock::ReplicaId;
@@ -99,6 +99,18 @@ pub fn serialize_operation(operation: &crate::Operation) -> proto::Operation {
                     triggers: triggers.clone(),
                 },
             ),
+
+            crate::Operation::UpdateSignatureHelpTriggers {
+                triggers,
+                lamport_timestamp,
+            } => proto::operation::Variant::UpdateSignatureHelpTriggers(
+                proto::operation::UpdateSignatureHelpTriggers {
+                    replica_id: lamport_timestamp.replica_id as u32,
+                    lamport_timestamp: lamport_timestamp.value,
+                    trigger_characters: triggers.trigger_characters.clone(),
+                    retrigger_characters: triggers.retrigger_characters.clone(),
+                },
+            ),
         }),
     }
 }
@@ -325,6 +337,18 @@ pub fn deserialize_operation(message: proto::Operation) -> Result<crate::Operati
                     },
                 }
             }
+            proto::operation::Variant::UpdateSignatureHelpTriggers(message) => {
+                crate::Operation::UpdateSignatureHelpTriggers {
+                    triggers: SignatureHelpTriggers {
+                        trigger_characters: message.trigger_characters,
+                        retrigger_characters: message.retrigger_characters,
+                    },
+                    lamport_timestamp: clock::Lamport {
+                        replica_id: message.replica_id as ReplicaId,
+                        value: message.lamport_timestamp,
+                    },
+                }
+            }
         },
     )
 }
@@ -459,6 +483,10 @@ pub fn lamport_timestamp_for_operation(operation: &proto::Operation) -> Option<c
             replica_id = op.replica_id;
             value = op.lamport_timestamp;
         }
+        proto::operation::Variant::UpdateSignatureHelpTriggers(op) => {
+            replica_id = op.replica_id;
+            value = op.lamport_timestamp;
+        }
     }
 
     Some(clock::Lamport {
@@ -467,15 +495,16 @@ pub fn lamport_timestamp_for_operation(operation: &proto::Operation) -> Option<c
     })
 }
 
-/// Serializes a [`Completion`] to be sent over RPC.
-pub fn serialize_completion(completion: &Completion) -> proto::Completion {
-    proto::Completion {
+/// Serializes a [`Completion`] to be sent over RPC. Returns `None` for completions that don't
+/// come from a language server, which are never sent to collaborators.
+pub fn serialize_completion(completion: &Completion) -> Option<proto::Completion> {
+    Some(proto::Completion {
         old_start: Some(serialize_anchor(&completion.old_range.start)),
         old_end: Some(serialize_anchor(&completion.old_range.end)),
         new_text: completion.new_text.clone(),
-        server_id: completion.server_id.0 as u64,
+        server_id: completion.server_id()?.0 as u64,
         lsp_completion: serde_json::to_vec(&completion.lsp_completion).unwrap(),
-    }
+    })
 }
 
 /// Deserializes a [`Completion`] from the RPC representation.
@@ -508,7 +537,7 @@ pub async fn deserialize_completion(
             )
         }),
         documentation: None,
-        server_id: LanguageServerId(completion.server_id as usize),
+        source: CompletionSource::LanguageServer(LanguageServerId(completion.server_id as usize)),
         lsp_completion,
     })
 }
@@ -15,7 +15,9 @@ use std::{
 };
 use sum_tree::{Bias, SeekTarget, SumTree};
 use text::{Anchor, BufferSnapshot, OffsetRangeExt, Point, Rope, ToOffset, ToPoint};
-use tree_sitter::{Node, Query, QueryCapture, QueryCaptures, QueryCursor, QueryMatches, Tree};
+use tree_sitter::{
+    Node, Query, QueryCapture, QueryCaptures, QueryCursor, QueryMatch, QueryMatches, Tree,
+};
 
 use super::PARSER;
 
@@ -1465,6 +1467,22 @@ impl<'a> SyntaxLayer<'a> {
             .root_node_with_offset(self.offset.0, self.offset.1)
     }
 
+    /// Runs the given query against this layer within a byte range of its text, calling
+    /// `callback` with each match.
+    pub fn query_matches(
+        &self,
+        query: &Query,
+        range: Range<usize>,
+        text: &Rope,
+        mut callback: impl FnMut(&QueryMatch<'_, 'a>),
+    ) {
+        let mut query_cursor = QueryCursorHandle::new();
+        query_cursor.set_byte_range(range);
+        for mat in query_cursor.matches(query, self.node(), TextProvider(text)) {
+            callback(&mat);
+        }
+    }
+
     pub(crate) fn override_id(&self, offset: usize, text: &text::BufferSnapshot) -> Option<u32> {
         let text = TextProvider(text.as_rope());
         let config = self.language.grammar.as_ref()?.override_config.as_ref()?;
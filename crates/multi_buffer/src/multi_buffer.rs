@@ -596,6 +596,29 @@ impl MultiBuffer {
         tail(self, buffer_edits, autoindent_mode, edited_excerpt_ids, cx);
     }
 
+    /// Re-indents every line intersecting the given ranges according to the
+    /// language of the buffer containing it.
+    pub fn autoindent_ranges<I, T>(&mut self, ranges: I, cx: &mut ModelContext<Self>)
+    where
+        I: IntoIterator<Item = Range<T>>,
+        T: ToOffset,
+    {
+        let mut ranges_by_buffer = HashMap::<u64, (Model<Buffer>, Vec<Range<usize>>)>::default();
+        for range in ranges {
+            for (buffer, range, _) in self.range_to_buffer_ranges(range, cx) {
+                ranges_by_buffer
+                    .entry(buffer.read(cx).remote_id())
+                    .or_insert_with(|| (buffer, Vec::new()))
+                    .1
+                    .push(range);
+            }
+        }
+
+        for (buffer, ranges) in ranges_by_buffer.into_values() {
+            buffer.update(cx, |buffer, cx| buffer.autoindent_ranges(ranges, cx));
+        }
+    }
+
     pub fn start_transaction(&mut self, cx: &mut ModelContext<Self>) -> Option<TransactionId> {
         self.start_transaction_at(Instant::now(), cx)
     }
@@ -645,6 +668,57 @@ impl MultiBuffer {
         }
     }
 
+    /// The id of the last transaction that can be undone.
+    pub fn last_transaction_id(&self, cx: &AppContext) -> Option<TransactionId> {
+        if let Some(buffer) = self.as_singleton() {
+            return buffer
+                .read(cx)
+                .peek_undo_stack()
+                .map(|history_entry| history_entry.transaction_id());
+        }
+        self.history
+            .undo_stack
+            .last()
+            .map(|transaction| transaction.id)
+    }
+
+    /// The ranges of each buffer edited by the given transaction, in that buffer's coordinates.
+    pub fn edited_ranges_for_transaction<D>(
+        &self,
+        transaction_id: TransactionId,
+        cx: &AppContext,
+    ) -> Vec<(Model<Buffer>, Range<D>)>
+    where
+        D: TextDimension,
+    {
+        if let Some(buffer) = self.as_singleton() {
+            return buffer
+                .read(cx)
+                .edited_ranges_for_transaction_id(transaction_id)
+                .map(|range| (buffer.clone(), range))
+                .collect();
+        }
+
+        let Some(transaction) = self.history.transaction(transaction_id) else {
+            return Vec::new();
+        };
+        let buffers = self.buffers.borrow();
+        let mut ranges = Vec::new();
+        for (buffer_id, buffer_transaction_id) in &transaction.buffer_transactions {
+            let Some(state) = buffers.get(buffer_id) else {
+                continue;
+            };
+            ranges.extend(
+                state
+                    .buffer
+                    .read(cx)
+                    .edited_ranges_for_transaction_id(*buffer_transaction_id)
+                    .map(|range| (state.buffer.clone(), range)),
+            );
+        }
+        ranges
+    }
+
     pub fn merge_transactions(
         &mut self,
         transaction: TransactionId,
@@ -3461,6 +3535,17 @@ impl History {
         }
     }
 
+    fn transaction(&self, transaction_id: TransactionId) -> Option<&Transaction> {
+        self.undo_stack
+            .iter()
+            .find(|transaction| transaction.id == transaction_id)
+            .or_else(|| {
+                self.redo_stack
+                    .iter()
+                    .find(|transaction| transaction.id == transaction_id)
+            })
+    }
+
     fn transaction_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Transaction> {
         self.undo_stack
             .iter_mut()
@@ -1,7 +1,9 @@
 use crate::{
-    DocumentHighlight, Hover, HoverBlock, HoverBlockKind, InlayHint, InlayHintLabel,
-    InlayHintLabelPart, InlayHintLabelPartTooltip, InlayHintTooltip, Location, LocationLink,
-    MarkupContent, Project, ProjectTransaction, ResolveState,
+    CallHierarchyCall, CallHierarchyItem, CodeLens, DocumentHighlight, Hover, HoverBlock,
+    HoverBlockKind, InlayHint, InlayHintLabel, InlayHintLabelPart, InlayHintLabelPartTooltip,
+    InlayHintTooltip, LinkedEditingRanges, LinkedEditingWordPattern, Location, LocationLink,
+    MarkupContent, ParameterInformation, Project, ProjectTransaction, ResolveState, SignatureHelp,
+    SignatureInformation, TypeHierarchyItem,
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -13,15 +15,16 @@ use language::{
     point_from_lsp, point_to_lsp, prepare_completion_documentation,
     proto::{deserialize_anchor, deserialize_version, serialize_anchor, serialize_version},
     range_from_lsp, range_to_lsp, Anchor, Bias, Buffer, BufferSnapshot, CachedLspAdapter, CharKind,
-    CodeAction, Completion, OffsetRangeExt, PointUtf16, ToOffset, ToPointUtf16, Transaction,
-    Unclipped,
+    CodeAction, Completion, CompletionSource, OffsetRangeExt, Point, PointUtf16, ToOffset,
+    ToPointUtf16, Transaction, Unclipped,
 };
 use lsp::{
     CompletionListItemDefaultsEditRange, DocumentHighlightKind, LanguageServer, LanguageServerId,
     OneOf, ServerCapabilities,
 };
-use std::{cmp::Reverse, ops::Range, path::Path, sync::Arc};
+use std::{cmp::Reverse, mem, ops::Range, path::Path, sync::Arc};
 use text::LineEnding;
+use util::ResultExt;
 
 pub fn lsp_formatting_options(tab_size: u32) -> lsp::FormattingOptions {
     lsp::FormattingOptions {
@@ -105,6 +108,10 @@ pub(crate) struct GetTypeDefinition {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetImplementation {
+    pub position: PointUtf16,
+}
+
 pub(crate) struct GetReferences {
     pub position: PointUtf16,
 }
@@ -113,10 +120,48 @@ pub(crate) struct GetDocumentHighlights {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetFoldingRanges;
+
+pub(crate) struct GetSelectionRanges {
+    pub positions: Vec<PointUtf16>,
+}
+
+pub(crate) struct GetLinkedEditingRanges {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct PrepareCallHierarchy {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetIncomingCalls {
+    pub item: CallHierarchyItem,
+}
+
+pub(crate) struct GetOutgoingCalls {
+    pub item: CallHierarchyItem,
+}
+
+pub(crate) struct PrepareTypeHierarchy {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetSupertypes {
+    pub item: TypeHierarchyItem,
+}
+
+pub(crate) struct GetSubtypes {
+    pub item: TypeHierarchyItem,
+}
+
 pub(crate) struct GetHover {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetSignatureHelp {
+    pub position: PointUtf16,
+}
+
 pub(crate) struct GetCompletions {
     pub position: PointUtf16,
 }
@@ -125,6 +170,8 @@ pub(crate) struct GetCodeActions {
     pub range: Range<Anchor>,
 }
 
+pub(crate) struct GetCodeLens;
+
 pub(crate) struct OnTypeFormatting {
     pub position: PointUtf16,
     pub trigger: String,
@@ -592,6 +639,107 @@ impl LspCommand for GetTypeDefinition {
     }
 }
 
+#[async_trait(?Send)]
+impl LspCommand for GetImplementation {
+    type Response = Vec<LocationLink>;
+    type LspRequest = lsp::request::GotoImplementation;
+    type ProtoRequest = proto::GetImplementation;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.implementation_provider {
+            None => false,
+            Some(lsp::ImplementationProviderCapability::Simple(false)) => false,
+            _ => true,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::GotoImplementationParams {
+        lsp::GotoImplementationParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::GotoImplementationResponse>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_lsp(message, project, buffer, server_id, cx).await
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetImplementation {
+        proto::GetImplementation {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetImplementation,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<LocationLink>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetImplementationResponse {
+        let links = location_links_to_proto(response, project, peer_id, cx);
+        proto::GetImplementationResponse { links }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetImplementationResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_proto(message.links, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetImplementation) -> u64 {
+        message.buffer_id
+    }
+}
+
 fn language_server_for_buffer(
     project: &Model<Project>,
     buffer: &Model<Buffer>,
@@ -946,14 +1094,252 @@ impl LspCommand for GetReferences {
     }
 }
 
+fn anchor_range_from_lsp(buffer: &Buffer, range: lsp::Range) -> Range<Anchor> {
+    let start = buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+    let end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+    buffer.anchor_after(start)..buffer.anchor_before(end)
+}
+
+fn location_to_proto(
+    location: Location,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> proto::Location {
+    let buffer_id = project.create_buffer_for_peer(&location.buffer, peer_id, cx);
+    proto::Location {
+        start: Some(serialize_anchor(&location.range.start)),
+        end: Some(serialize_anchor(&location.range.end)),
+        buffer_id,
+    }
+}
+
+async fn location_from_proto(
+    location: proto::Location,
+    project: &Model<Project>,
+    cx: &mut AsyncAppContext,
+) -> Result<Location> {
+    let buffer = project
+        .update(cx, |this, cx| {
+            this.wait_for_remote_buffer(location.buffer_id, cx)
+        })?
+        .await?;
+    let start = location
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location start"))?;
+    let end = location
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location end"))?;
+    buffer
+        .update(cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+        .await?;
+    Ok(Location {
+        buffer,
+        range: start..end,
+    })
+}
+
+fn supports_call_hierarchy(capabilities: &ServerCapabilities) -> bool {
+    !matches!(
+        capabilities.call_hierarchy_provider,
+        None | Some(lsp::CallHierarchyServerCapability::Simple(false))
+    )
+}
+
+async fn call_hierarchy_item_from_lsp(
+    lsp_item: lsp::CallHierarchyItem,
+    project: &Model<Project>,
+    lsp_adapter: &Arc<CachedLspAdapter>,
+    language_server: &Arc<LanguageServer>,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyItem> {
+    let buffer_handle = project
+        .update(cx, |this, cx| {
+            this.open_local_buffer_via_lsp(
+                lsp_item.uri,
+                language_server.server_id(),
+                lsp_adapter.name.clone(),
+                cx,
+            )
+        })?
+        .await?;
+    buffer_handle
+        .clone()
+        .update(cx, |buffer, _| CallHierarchyItem {
+            name: lsp_item.name,
+            kind: lsp_item.kind,
+            detail: lsp_item.detail,
+            buffer: buffer_handle,
+            range: anchor_range_from_lsp(buffer, lsp_item.range),
+            selection_range: anchor_range_from_lsp(buffer, lsp_item.selection_range),
+            lsp_data: lsp_item.data,
+        })
+}
+
+fn call_hierarchy_item_to_lsp(
+    item: &CallHierarchyItem,
+    path: &Path,
+    buffer: &Buffer,
+) -> lsp::CallHierarchyItem {
+    lsp::CallHierarchyItem {
+        name: item.name.clone(),
+        kind: item.kind,
+        tags: None,
+        detail: item.detail.clone(),
+        uri: lsp::Url::from_file_path(path).unwrap(),
+        range: range_to_lsp(item.range.to_point_utf16(buffer)),
+        selection_range: range_to_lsp(item.selection_range.to_point_utf16(buffer)),
+        data: item.lsp_data.clone(),
+    }
+}
+
+fn serialize_call_hierarchy_item(
+    item: &CallHierarchyItem,
+    buffer_id: u64,
+) -> proto::CallHierarchyItem {
+    proto::CallHierarchyItem {
+        name: item.name.clone(),
+        kind: unsafe { mem::transmute(item.kind) },
+        detail: item.detail.clone(),
+        location: Some(proto::Location {
+            start: Some(serialize_anchor(&item.range.start)),
+            end: Some(serialize_anchor(&item.range.end)),
+            buffer_id,
+        }),
+        selection_start: Some(serialize_anchor(&item.selection_range.start)),
+        selection_end: Some(serialize_anchor(&item.selection_range.end)),
+        lsp_data: item.lsp_data.as_ref().map(|data| data.to_string()),
+    }
+}
+
+fn deserialize_call_hierarchy_item(
+    item: proto::CallHierarchyItem,
+    buffer: Model<Buffer>,
+    range: Range<Anchor>,
+) -> Result<CallHierarchyItem> {
+    let selection_start = item
+        .selection_start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing selection start"))?;
+    let selection_end = item
+        .selection_end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing selection end"))?;
+    let lsp_data = item
+        .lsp_data
+        .map(|data| serde_json::from_str(&data))
+        .transpose()
+        .context("deserializing call hierarchy item data")?;
+    Ok(CallHierarchyItem {
+        name: item.name,
+        kind: unsafe { mem::transmute(item.kind) },
+        detail: item.detail,
+        buffer,
+        range,
+        selection_range: selection_start..selection_end,
+        lsp_data,
+    })
+}
+
+async fn call_hierarchy_item_from_proto(
+    item: proto::CallHierarchyItem,
+    project: &Model<Project>,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyItem> {
+    let location = item
+        .location
+        .clone()
+        .ok_or_else(|| anyhow!("missing call hierarchy item location"))?;
+    let location = location_from_proto(location, project, cx).await?;
+    let item = deserialize_call_hierarchy_item(item, location.buffer, location.range)?;
+    item.buffer
+        .update(cx, |buffer, _| {
+            buffer.wait_for_anchors([item.selection_range.start, item.selection_range.end])
+        })?
+        .await?;
+    Ok(item)
+}
+
+async fn call_hierarchy_calls_from_proto(
+    calls: Vec<proto::CallHierarchyCall>,
+    project: Model<Project>,
+    mut cx: AsyncAppContext,
+) -> Result<Vec<CallHierarchyCall>> {
+    let mut result = Vec::new();
+    for call in calls {
+        let item = call
+            .item
+            .ok_or_else(|| anyhow!("missing call hierarchy item"))?;
+        let item = call_hierarchy_item_from_proto(item, &project, &mut cx).await?;
+        let mut call_sites = Vec::new();
+        for call_site in call.call_sites {
+            call_sites.push(location_from_proto(call_site, &project, &mut cx).await?);
+        }
+        result.push(CallHierarchyCall { item, call_sites });
+    }
+    Ok(result)
+}
+
+fn call_hierarchy_calls_to_proto(
+    calls: Vec<CallHierarchyCall>,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> Vec<proto::CallHierarchyCall> {
+    calls
+        .into_iter()
+        .map(|call| {
+            let buffer_id = project.create_buffer_for_peer(&call.item.buffer, peer_id, cx);
+            proto::CallHierarchyCall {
+                item: Some(serialize_call_hierarchy_item(&call.item, buffer_id)),
+                call_sites: call
+                    .call_sites
+                    .into_iter()
+                    .map(|location| location_to_proto(location, project, peer_id, cx))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Reconstructs the queried item on the host, whose anchors all point into the request's buffer.
+async fn call_hierarchy_item_from_request(
+    item: Option<proto::CallHierarchyItem>,
+    version: Vec<proto::VectorClockEntry>,
+    buffer: Model<Buffer>,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyItem> {
+    let item = item.ok_or_else(|| anyhow!("missing call hierarchy item"))?;
+    let location = item
+        .location
+        .clone()
+        .ok_or_else(|| anyhow!("missing call hierarchy item location"))?;
+    let start = location
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location start"))?;
+    let end = location
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location end"))?;
+    buffer
+        .update(cx, |buffer, _| {
+            buffer.wait_for_version(deserialize_version(&version))
+        })?
+        .await?;
+    deserialize_call_hierarchy_item(item, buffer, start..end)
+}
+
 #[async_trait(?Send)]
-impl LspCommand for GetDocumentHighlights {
-    type Response = Vec<DocumentHighlight>;
-    type LspRequest = lsp::request::DocumentHighlightRequest;
-    type ProtoRequest = proto::GetDocumentHighlights;
+impl LspCommand for PrepareCallHierarchy {
+    type Response = Vec<CallHierarchyItem>;
+    type LspRequest = lsp::request::CallHierarchyPrepare;
+    type ProtoRequest = proto::PrepareCallHierarchy;
 
     fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
-        capabilities.document_highlight_provider.is_some()
+        supports_call_hierarchy(capabilities)
     }
 
     fn to_lsp(
@@ -962,8 +1348,8 @@ impl LspCommand for GetDocumentHighlights {
         _: &Buffer,
         _: &Arc<LanguageServer>,
         _: &AppContext,
-    ) -> lsp::DocumentHighlightParams {
-        lsp::DocumentHighlightParams {
+    ) -> lsp::CallHierarchyPrepareParams {
+        lsp::CallHierarchyPrepareParams {
             text_document_position_params: lsp::TextDocumentPositionParams {
                 text_document: lsp::TextDocumentIdentifier {
                     uri: lsp::Url::from_file_path(path).unwrap(),
@@ -971,41 +1357,37 @@ impl LspCommand for GetDocumentHighlights {
                 position: point_to_lsp(self.position),
             },
             work_done_progress_params: Default::default(),
-            partial_result_params: Default::default(),
         }
     }
 
     async fn response_from_lsp(
         self,
-        lsp_highlights: Option<Vec<lsp::DocumentHighlight>>,
-        _: Model<Project>,
+        lsp_items: Option<Vec<lsp::CallHierarchyItem>>,
+        project: Model<Project>,
         buffer: Model<Buffer>,
-        _: LanguageServerId,
+        server_id: LanguageServerId,
         mut cx: AsyncAppContext,
-    ) -> Result<Vec<DocumentHighlight>> {
-        buffer.update(&mut cx, |buffer, _| {
-            let mut lsp_highlights = lsp_highlights.unwrap_or_default();
-            lsp_highlights.sort_unstable_by_key(|h| (h.range.start, Reverse(h.range.end)));
-            lsp_highlights
-                .into_iter()
-                .map(|lsp_highlight| {
-                    let start = buffer
-                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.start), Bias::Left);
-                    let end = buffer
-                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.end), Bias::Left);
-                    DocumentHighlight {
-                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
-                        kind: lsp_highlight
-                            .kind
-                            .unwrap_or(lsp::DocumentHighlightKind::READ),
-                    }
-                })
-                .collect()
-        })
+    ) -> Result<Vec<CallHierarchyItem>> {
+        let (lsp_adapter, language_server) =
+            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+        let mut items = Vec::new();
+        for lsp_item in lsp_items.unwrap_or_default() {
+            items.push(
+                call_hierarchy_item_from_lsp(
+                    lsp_item,
+                    &project,
+                    &lsp_adapter,
+                    &language_server,
+                    &mut cx,
+                )
+                .await?,
+            );
+        }
+        Ok(items)
     }
 
-    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDocumentHighlights {
-        proto::GetDocumentHighlights {
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::PrepareCallHierarchy {
+        proto::PrepareCallHierarchy {
             project_id,
             buffer_id: buffer.remote_id(),
             position: Some(language::proto::serialize_anchor(
@@ -1016,7 +1398,7 @@ impl LspCommand for GetDocumentHighlights {
     }
 
     async fn from_proto(
-        message: proto::GetDocumentHighlights,
+        message: proto::PrepareCallHierarchy,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
@@ -1036,63 +1418,1307 @@ impl LspCommand for GetDocumentHighlights {
     }
 
     fn response_to_proto(
-        response: Vec<DocumentHighlight>,
-        _: &mut Project,
-        _: PeerId,
+        response: Vec<CallHierarchyItem>,
+        project: &mut Project,
+        peer_id: PeerId,
         _: &clock::Global,
-        _: &mut AppContext,
-    ) -> proto::GetDocumentHighlightsResponse {
-        let highlights = response
+        cx: &mut AppContext,
+    ) -> proto::PrepareCallHierarchyResponse {
+        let items = response
             .into_iter()
-            .map(|highlight| proto::DocumentHighlight {
-                start: Some(serialize_anchor(&highlight.range.start)),
-                end: Some(serialize_anchor(&highlight.range.end)),
-                kind: match highlight.kind {
-                    DocumentHighlightKind::TEXT => proto::document_highlight::Kind::Text.into(),
-                    DocumentHighlightKind::WRITE => proto::document_highlight::Kind::Write.into(),
-                    DocumentHighlightKind::READ => proto::document_highlight::Kind::Read.into(),
-                    _ => proto::document_highlight::Kind::Text.into(),
-                },
+            .map(|item| {
+                let buffer_id = project.create_buffer_for_peer(&item.buffer, peer_id, cx);
+                serialize_call_hierarchy_item(&item, buffer_id)
             })
             .collect();
-        proto::GetDocumentHighlightsResponse { highlights }
+        proto::PrepareCallHierarchyResponse { items }
     }
 
     async fn response_from_proto(
         self,
-        message: proto::GetDocumentHighlightsResponse,
-        _: Model<Project>,
-        buffer: Model<Buffer>,
+        message: proto::PrepareCallHierarchyResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
         mut cx: AsyncAppContext,
-    ) -> Result<Vec<DocumentHighlight>> {
-        let mut highlights = Vec::new();
-        for highlight in message.highlights {
-            let start = highlight
-                .start
-                .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target start"))?;
-            let end = highlight
-                .end
-                .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target end"))?;
-            buffer
-                .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
-                .await?;
-            let kind = match proto::document_highlight::Kind::from_i32(highlight.kind) {
-                Some(proto::document_highlight::Kind::Text) => DocumentHighlightKind::TEXT,
-                Some(proto::document_highlight::Kind::Read) => DocumentHighlightKind::READ,
-                Some(proto::document_highlight::Kind::Write) => DocumentHighlightKind::WRITE,
-                None => DocumentHighlightKind::TEXT,
-            };
-            highlights.push(DocumentHighlight {
+    ) -> Result<Vec<CallHierarchyItem>> {
+        let mut items = Vec::new();
+        for item in message.items {
+            items.push(call_hierarchy_item_from_proto(item, &project, &mut cx).await?);
+        }
+        Ok(items)
+    }
+
+    fn buffer_id_from_proto(message: &proto::PrepareCallHierarchy) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetIncomingCalls {
+    type Response = Vec<CallHierarchyCall>;
+    type LspRequest = lsp::request::CallHierarchyIncomingCalls;
+    type ProtoRequest = proto::GetIncomingCalls;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_call_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        buffer: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CallHierarchyIncomingCallsParams {
+        lsp::CallHierarchyIncomingCallsParams {
+            item: call_hierarchy_item_to_lsp(&self.item, path, buffer),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_calls: Option<Vec<lsp::CallHierarchyIncomingCall>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let (lsp_adapter, language_server) =
+            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+        let mut calls = Vec::new();
+        for lsp_call in lsp_calls.unwrap_or_default() {
+            let item = call_hierarchy_item_from_lsp(
+                lsp_call.from,
+                &project,
+                &lsp_adapter,
+                &language_server,
+                &mut cx,
+            )
+            .await?;
+            let call_sites = item.buffer.update(&mut cx, |caller_buffer, _| {
+                lsp_call
+                    .from_ranges
+                    .into_iter()
+                    .map(|range| Location {
+                        buffer: item.buffer.clone(),
+                        range: anchor_range_from_lsp(caller_buffer, range),
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+            calls.push(CallHierarchyCall { item, call_sites });
+        }
+        Ok(calls)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetIncomingCalls {
+        proto::GetIncomingCalls {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            item: Some(serialize_call_hierarchy_item(
+                &self.item,
+                buffer.remote_id(),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetIncomingCalls,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let item = call_hierarchy_item_from_request(message.item, message.version, buffer, &mut cx)
+            .await?;
+        Ok(Self { item })
+    }
+
+    fn response_to_proto(
+        response: Vec<CallHierarchyCall>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetIncomingCallsResponse {
+        proto::GetIncomingCallsResponse {
+            calls: call_hierarchy_calls_to_proto(response, project, peer_id, cx),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetIncomingCallsResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        call_hierarchy_calls_from_proto(message.calls, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetIncomingCalls) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetOutgoingCalls {
+    type Response = Vec<CallHierarchyCall>;
+    type LspRequest = lsp::request::CallHierarchyOutgoingCalls;
+    type ProtoRequest = proto::GetOutgoingCalls;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_call_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        buffer: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CallHierarchyOutgoingCallsParams {
+        lsp::CallHierarchyOutgoingCallsParams {
+            item: call_hierarchy_item_to_lsp(&self.item, path, buffer),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_calls: Option<Vec<lsp::CallHierarchyOutgoingCall>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let (lsp_adapter, language_server) =
+            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+        let mut calls = Vec::new();
+        for lsp_call in lsp_calls.unwrap_or_default() {
+            let item = call_hierarchy_item_from_lsp(
+                lsp_call.to,
+                &project,
+                &lsp_adapter,
+                &language_server,
+                &mut cx,
+            )
+            .await?;
+            let call_sites = buffer.update(&mut cx, |caller_buffer, _| {
+                lsp_call
+                    .from_ranges
+                    .into_iter()
+                    .map(|range| Location {
+                        buffer: buffer.clone(),
+                        range: anchor_range_from_lsp(caller_buffer, range),
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+            calls.push(CallHierarchyCall { item, call_sites });
+        }
+        Ok(calls)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetOutgoingCalls {
+        proto::GetOutgoingCalls {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            item: Some(serialize_call_hierarchy_item(
+                &self.item,
+                buffer.remote_id(),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetOutgoingCalls,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let item = call_hierarchy_item_from_request(message.item, message.version, buffer, &mut cx)
+            .await?;
+        Ok(Self { item })
+    }
+
+    fn response_to_proto(
+        response: Vec<CallHierarchyCall>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetOutgoingCallsResponse {
+        proto::GetOutgoingCallsResponse {
+            calls: call_hierarchy_calls_to_proto(response, project, peer_id, cx),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetOutgoingCallsResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        call_hierarchy_calls_from_proto(message.calls, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetOutgoingCalls) -> u64 {
+        message.buffer_id
+    }
+}
+
+fn supports_type_hierarchy(capabilities: &ServerCapabilities) -> bool {
+    !matches!(
+        capabilities.type_hierarchy_provider,
+        None | Some(lsp::TypeHierarchyServerCapability::Simple(false))
+    )
+}
+
+async fn type_hierarchy_items_from_lsp(
+    lsp_items: Option<Vec<lsp::TypeHierarchyItem>>,
+    project: Model<Project>,
+    buffer: Model<Buffer>,
+    server_id: LanguageServerId,
+    mut cx: AsyncAppContext,
+) -> Result<Vec<TypeHierarchyItem>> {
+    let (lsp_adapter, language_server) =
+        language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+    let mut items = Vec::new();
+    for lsp_item in lsp_items.unwrap_or_default() {
+        let buffer_handle = project
+            .update(&mut cx, |this, cx| {
+                this.open_local_buffer_via_lsp(
+                    lsp_item.uri,
+                    language_server.server_id(),
+                    lsp_adapter.name.clone(),
+                    cx,
+                )
+            })?
+            .await?;
+        items.push(
+            buffer_handle
+                .clone()
+                .update(&mut cx, |buffer, _| TypeHierarchyItem {
+                    name: lsp_item.name,
+                    kind: lsp_item.kind,
+                    detail: lsp_item.detail,
+                    buffer: buffer_handle,
+                    range: anchor_range_from_lsp(buffer, lsp_item.range),
+                    selection_range: anchor_range_from_lsp(buffer, lsp_item.selection_range),
+                    lsp_data: lsp_item.data,
+                })?,
+        );
+    }
+    Ok(items)
+}
+
+fn type_hierarchy_item_to_lsp(
+    item: &TypeHierarchyItem,
+    path: &Path,
+    buffer: &Buffer,
+) -> lsp::TypeHierarchyItem {
+    lsp::TypeHierarchyItem {
+        name: item.name.clone(),
+        kind: item.kind,
+        tags: None,
+        detail: item.detail.clone(),
+        uri: lsp::Url::from_file_path(path).unwrap(),
+        range: range_to_lsp(item.range.to_point_utf16(buffer)),
+        selection_range: range_to_lsp(item.selection_range.to_point_utf16(buffer)),
+        data: item.lsp_data.clone(),
+    }
+}
+
+fn serialize_type_hierarchy_item(
+    item: &TypeHierarchyItem,
+    buffer_id: u64,
+) -> proto::TypeHierarchyItem {
+    proto::TypeHierarchyItem {
+        name: item.name.clone(),
+        kind: unsafe { mem::transmute(item.kind) },
+        detail: item.detail.clone(),
+        location: Some(proto::Location {
+            start: Some(serialize_anchor(&item.range.start)),
+            end: Some(serialize_anchor(&item.range.end)),
+            buffer_id,
+        }),
+        selection_start: Some(serialize_anchor(&item.selection_range.start)),
+        selection_end: Some(serialize_anchor(&item.selection_range.end)),
+        lsp_data: item.lsp_data.as_ref().map(|data| data.to_string()),
+    }
+}
+
+fn deserialize_type_hierarchy_item(
+    item: proto::TypeHierarchyItem,
+    buffer: Model<Buffer>,
+    range: Range<Anchor>,
+) -> Result<TypeHierarchyItem> {
+    let selection_start = item
+        .selection_start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing selection start"))?;
+    let selection_end = item
+        .selection_end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing selection end"))?;
+    let lsp_data = item
+        .lsp_data
+        .map(|data| serde_json::from_str(&data))
+        .transpose()
+        .context("deserializing type hierarchy item data")?;
+    Ok(TypeHierarchyItem {
+        name: item.name,
+        kind: unsafe { mem::transmute(item.kind) },
+        detail: item.detail,
+        buffer,
+        range,
+        selection_range: selection_start..selection_end,
+        lsp_data,
+    })
+}
+
+async fn type_hierarchy_items_from_proto(
+    items: Vec<proto::TypeHierarchyItem>,
+    project: Model<Project>,
+    mut cx: AsyncAppContext,
+) -> Result<Vec<TypeHierarchyItem>> {
+    let mut result = Vec::new();
+    for item in items {
+        let location = item
+            .location
+            .clone()
+            .ok_or_else(|| anyhow!("missing type hierarchy item location"))?;
+        let location = location_from_proto(location, &project, &mut cx).await?;
+        let item = deserialize_type_hierarchy_item(item, location.buffer, location.range)?;
+        item.buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_anchors([item.selection_range.start, item.selection_range.end])
+            })?
+            .await?;
+        result.push(item);
+    }
+    Ok(result)
+}
+
+fn type_hierarchy_items_to_proto(
+    items: Vec<TypeHierarchyItem>,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> Vec<proto::TypeHierarchyItem> {
+    items
+        .into_iter()
+        .map(|item| {
+            let buffer_id = project.create_buffer_for_peer(&item.buffer, peer_id, cx);
+            serialize_type_hierarchy_item(&item, buffer_id)
+        })
+        .collect()
+}
+
+/// Reconstructs the queried item on the host, whose anchors all point into the request's buffer.
+async fn type_hierarchy_item_from_request(
+    item: Option<proto::TypeHierarchyItem>,
+    version: Vec<proto::VectorClockEntry>,
+    buffer: Model<Buffer>,
+    cx: &mut AsyncAppContext,
+) -> Result<TypeHierarchyItem> {
+    let item = item.ok_or_else(|| anyhow!("missing type hierarchy item"))?;
+    let location = item
+        .location
+        .clone()
+        .ok_or_else(|| anyhow!("missing type hierarchy item location"))?;
+    let start = location
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location start"))?;
+    let end = location
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location end"))?;
+    buffer
+        .update(cx, |buffer, _| {
+            buffer.wait_for_version(deserialize_version(&version))
+        })?
+        .await?;
+    deserialize_type_hierarchy_item(item, buffer, start..end)
+}
+
+#[async_trait(?Send)]
+impl LspCommand for PrepareTypeHierarchy {
+    type Response = Vec<TypeHierarchyItem>;
+    type LspRequest = lsp::request::TypeHierarchyPrepare;
+    type ProtoRequest = proto::PrepareTypeHierarchy;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_type_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::TypeHierarchyPrepareParams {
+        lsp::TypeHierarchyPrepareParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_items: Option<Vec<lsp::TypeHierarchyItem>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_lsp(lsp_items, project, buffer, server_id, cx).await
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::PrepareTypeHierarchy {
+        proto::PrepareTypeHierarchy {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::PrepareTypeHierarchy,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<TypeHierarchyItem>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::PrepareTypeHierarchyResponse {
+        proto::PrepareTypeHierarchyResponse {
+            items: type_hierarchy_items_to_proto(response, project, peer_id, cx),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::PrepareTypeHierarchyResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_proto(message.items, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::PrepareTypeHierarchy) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetSupertypes {
+    type Response = Vec<TypeHierarchyItem>;
+    type LspRequest = lsp::request::TypeHierarchySupertypes;
+    type ProtoRequest = proto::GetSupertypes;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_type_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        buffer: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::TypeHierarchySupertypesParams {
+        lsp::TypeHierarchySupertypesParams {
+            item: type_hierarchy_item_to_lsp(&self.item, path, buffer),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_items: Option<Vec<lsp::TypeHierarchyItem>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_lsp(lsp_items, project, buffer, server_id, cx).await
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetSupertypes {
+        proto::GetSupertypes {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            item: Some(serialize_type_hierarchy_item(
+                &self.item,
+                buffer.remote_id(),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetSupertypes,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let item = type_hierarchy_item_from_request(message.item, message.version, buffer, &mut cx)
+            .await?;
+        Ok(Self { item })
+    }
+
+    fn response_to_proto(
+        response: Vec<TypeHierarchyItem>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetSupertypesResponse {
+        proto::GetSupertypesResponse {
+            items: type_hierarchy_items_to_proto(response, project, peer_id, cx),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetSupertypesResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_proto(message.items, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetSupertypes) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetSubtypes {
+    type Response = Vec<TypeHierarchyItem>;
+    type LspRequest = lsp::request::TypeHierarchySubtypes;
+    type ProtoRequest = proto::GetSubtypes;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_type_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        buffer: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::TypeHierarchySubtypesParams {
+        lsp::TypeHierarchySubtypesParams {
+            item: type_hierarchy_item_to_lsp(&self.item, path, buffer),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_items: Option<Vec<lsp::TypeHierarchyItem>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_lsp(lsp_items, project, buffer, server_id, cx).await
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetSubtypes {
+        proto::GetSubtypes {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            item: Some(serialize_type_hierarchy_item(
+                &self.item,
+                buffer.remote_id(),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetSubtypes,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let item = type_hierarchy_item_from_request(message.item, message.version, buffer, &mut cx)
+            .await?;
+        Ok(Self { item })
+    }
+
+    fn response_to_proto(
+        response: Vec<TypeHierarchyItem>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetSubtypesResponse {
+        proto::GetSubtypesResponse {
+            items: type_hierarchy_items_to_proto(response, project, peer_id, cx),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetSubtypesResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        type_hierarchy_items_from_proto(message.items, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetSubtypes) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetDocumentHighlights {
+    type Response = Vec<DocumentHighlight>;
+    type LspRequest = lsp::request::DocumentHighlightRequest;
+    type ProtoRequest = proto::GetDocumentHighlights;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.document_highlight_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::DocumentHighlightParams {
+        lsp::DocumentHighlightParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_highlights: Option<Vec<lsp::DocumentHighlight>>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentHighlight>> {
+        buffer.update(&mut cx, |buffer, _| {
+            let mut lsp_highlights = lsp_highlights.unwrap_or_default();
+            lsp_highlights.sort_unstable_by_key(|h| (h.range.start, Reverse(h.range.end)));
+            lsp_highlights
+                .into_iter()
+                .map(|lsp_highlight| {
+                    let start = buffer
+                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.start), Bias::Left);
+                    let end = buffer
+                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.end), Bias::Left);
+                    DocumentHighlight {
+                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
+                        kind: lsp_highlight
+                            .kind
+                            .unwrap_or(lsp::DocumentHighlightKind::READ),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDocumentHighlights {
+        proto::GetDocumentHighlights {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetDocumentHighlights,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<DocumentHighlight>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetDocumentHighlightsResponse {
+        let highlights = response
+            .into_iter()
+            .map(|highlight| proto::DocumentHighlight {
+                start: Some(serialize_anchor(&highlight.range.start)),
+                end: Some(serialize_anchor(&highlight.range.end)),
+                kind: match highlight.kind {
+                    DocumentHighlightKind::TEXT => proto::document_highlight::Kind::Text.into(),
+                    DocumentHighlightKind::WRITE => proto::document_highlight::Kind::Write.into(),
+                    DocumentHighlightKind::READ => proto::document_highlight::Kind::Read.into(),
+                    _ => proto::document_highlight::Kind::Text.into(),
+                },
+            })
+            .collect();
+        proto::GetDocumentHighlightsResponse { highlights }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetDocumentHighlightsResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentHighlight>> {
+        let mut highlights = Vec::new();
+        for highlight in message.highlights {
+            let start = highlight
+                .start
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target start"))?;
+            let end = highlight
+                .end
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target end"))?;
+            buffer
+                .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+                .await?;
+            let kind = match proto::document_highlight::Kind::from_i32(highlight.kind) {
+                Some(proto::document_highlight::Kind::Text) => DocumentHighlightKind::TEXT,
+                Some(proto::document_highlight::Kind::Read) => DocumentHighlightKind::READ,
+                Some(proto::document_highlight::Kind::Write) => DocumentHighlightKind::WRITE,
+                None => DocumentHighlightKind::TEXT,
+            };
+            highlights.push(DocumentHighlight {
                 range: start..end,
                 kind,
             });
         }
-        Ok(highlights)
+        Ok(highlights)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetDocumentHighlights) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetFoldingRanges {
+    type Response = Vec<Range<Anchor>>;
+    type LspRequest = lsp::request::FoldingRangeRequest;
+    type ProtoRequest = proto::GetFoldingRanges;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        !matches!(
+            capabilities.folding_range_provider,
+            None | Some(lsp::FoldingRangeProviderCapability::Simple(false))
+        )
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::FoldingRangeParams {
+        lsp::FoldingRangeParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_ranges: Option<Vec<lsp::FoldingRange>>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Range<Anchor>>> {
+        buffer.update(&mut cx, |buffer, _| {
+            // Folds always span whole lines, so the ranges are extended to the end of their
+            // first and last lines, regardless of the characters reported by the server.
+            let max_row = buffer.max_point().row;
+            let mut lsp_ranges = lsp_ranges.unwrap_or_default();
+            lsp_ranges.retain(|range| range.start_line < range.end_line);
+            lsp_ranges.sort_unstable_by_key(|range| (range.start_line, Reverse(range.end_line)));
+            lsp_ranges
+                .into_iter()
+                .filter(|range| range.start_line < max_row)
+                .map(|range| {
+                    let start_row = range.start_line;
+                    let end_row = range.end_line.min(max_row);
+                    let start = Point::new(start_row, buffer.line_len(start_row));
+                    let end = Point::new(end_row, buffer.line_len(end_row));
+                    buffer.anchor_after(start)..buffer.anchor_before(end)
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetFoldingRanges {
+        proto::GetFoldingRanges {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetFoldingRanges,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self)
+    }
+
+    fn response_to_proto(
+        response: Vec<Range<Anchor>>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetFoldingRangesResponse {
+        let ranges = response
+            .into_iter()
+            .map(|range| proto::FoldingRange {
+                start: Some(serialize_anchor(&range.start)),
+                end: Some(serialize_anchor(&range.end)),
+            })
+            .collect();
+        proto::GetFoldingRangesResponse { ranges }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetFoldingRangesResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Range<Anchor>>> {
+        let mut ranges = Vec::new();
+        for range in message.ranges {
+            let start = range
+                .start
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing folding range start"))?;
+            let end = range
+                .end
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing folding range end"))?;
+            ranges.push(start..end);
+        }
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_anchors(ranges.iter().flat_map(|range| [range.start, range.end]))
+            })?
+            .await?;
+        Ok(ranges)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetFoldingRanges) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetSelectionRanges {
+    type Response = Vec<Vec<Range<Anchor>>>;
+    type LspRequest = lsp::request::SelectionRangeRequest;
+    type ProtoRequest = proto::GetSelectionRanges;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        !matches!(
+            capabilities.selection_range_provider,
+            None | Some(lsp::SelectionRangeProviderCapability::Simple(false))
+        )
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::SelectionRangeParams {
+        lsp::SelectionRangeParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            positions: self
+                .positions
+                .iter()
+                .map(|position| point_to_lsp(*position))
+                .collect(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_ranges: Option<Vec<lsp::SelectionRange>>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Vec<Range<Anchor>>>> {
+        buffer.update(&mut cx, |buffer, _| {
+            lsp_ranges
+                .unwrap_or_default()
+                .into_iter()
+                .map(|lsp_range| {
+                    // Each selection range links to the range containing it, all the way up to
+                    // the whole document.
+                    let mut ranges = Vec::new();
+                    let mut lsp_range = Some(&lsp_range);
+                    while let Some(selection_range) = lsp_range {
+                        let start = buffer.clip_point_utf16(
+                            point_from_lsp(selection_range.range.start),
+                            Bias::Left,
+                        );
+                        let end = buffer.clip_point_utf16(
+                            point_from_lsp(selection_range.range.end),
+                            Bias::Left,
+                        );
+                        ranges.push(buffer.anchor_after(start)..buffer.anchor_before(end));
+                        lsp_range = selection_range.parent.as_deref();
+                    }
+                    ranges
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetSelectionRanges {
+        proto::GetSelectionRanges {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            positions: self
+                .positions
+                .iter()
+                .map(|position| serialize_anchor(&buffer.anchor_before(*position)))
+                .collect(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetSelectionRanges,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let positions = message
+            .positions
+            .into_iter()
+            .map(|position| deserialize_anchor(position).ok_or_else(|| anyhow!("invalid position")))
+            .collect::<Result<Vec<_>>>()?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            positions: buffer.update(&mut cx, |buffer, _| {
+                positions
+                    .iter()
+                    .map(|position| position.to_point_utf16(buffer))
+                    .collect()
+            })?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<Vec<Range<Anchor>>>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetSelectionRangesResponse {
+        let chains = response
+            .into_iter()
+            .map(|ranges| proto::SelectionRangeChain {
+                ranges: ranges
+                    .into_iter()
+                    .map(|range| proto::SelectionRange {
+                        start: Some(serialize_anchor(&range.start)),
+                        end: Some(serialize_anchor(&range.end)),
+                    })
+                    .collect(),
+            })
+            .collect();
+        proto::GetSelectionRangesResponse { chains }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetSelectionRangesResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Vec<Range<Anchor>>>> {
+        let mut chains = Vec::new();
+        for chain in message.chains {
+            let mut ranges = Vec::new();
+            for range in chain.ranges {
+                let start = range
+                    .start
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing selection range start"))?;
+                let end = range
+                    .end
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing selection range end"))?;
+                ranges.push(start..end);
+            }
+            chains.push(ranges);
+        }
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_anchors(
+                    chains
+                        .iter()
+                        .flatten()
+                        .flat_map(|range| [range.start, range.end]),
+                )
+            })?
+            .await?;
+        Ok(chains)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetSelectionRanges) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetLinkedEditingRanges {
+    type Response = LinkedEditingRanges;
+    type LspRequest = lsp::request::LinkedEditingRange;
+    type ProtoRequest = proto::GetLinkedEditingRanges;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        !matches!(
+            capabilities.linked_editing_range_provider,
+            None | Some(lsp::LinkedEditingRangeServerCapabilities::Simple(false))
+        )
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::LinkedEditingRangeParams {
+        lsp::LinkedEditingRangeParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_ranges: Option<lsp::LinkedEditingRanges>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<LinkedEditingRanges> {
+        let Some(lsp_ranges) = lsp_ranges else {
+            return Ok(LinkedEditingRanges::default());
+        };
+        let word_pattern = lsp_ranges
+            .word_pattern
+            .and_then(|pattern| LinkedEditingWordPattern::new(&pattern).log_err());
+        let ranges = buffer.update(&mut cx, |buffer, _| {
+            lsp_ranges
+                .ranges
+                .into_iter()
+                .map(|lsp_range| {
+                    let range = range_from_lsp(lsp_range);
+                    let start = buffer.clip_point_utf16(range.start, Bias::Left);
+                    let end = buffer.clip_point_utf16(range.end, Bias::Left);
+                    // Text typed next to a range, such as an attribute after a tag name, doesn't
+                    // become part of it.
+                    buffer.anchor_after(start)..buffer.anchor_before(end)
+                })
+                .collect()
+        })?;
+        Ok(LinkedEditingRanges {
+            ranges,
+            word_pattern,
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetLinkedEditingRanges {
+        proto::GetLinkedEditingRanges {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(serialize_anchor(&buffer.anchor_before(self.position))),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetLinkedEditingRanges,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: LinkedEditingRanges,
+        _: &mut Project,
+        _: PeerId,
+        buffer_version: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetLinkedEditingRangesResponse {
+        proto::GetLinkedEditingRangesResponse {
+            word_pattern: response
+                .word_pattern
+                .map(|pattern| pattern.as_str().to_string()),
+            ranges: response
+                .ranges
+                .into_iter()
+                .map(|range| proto::LinkedEditingRange {
+                    start: Some(serialize_anchor(&range.start)),
+                    end: Some(serialize_anchor(&range.end)),
+                })
+                .collect(),
+            version: serialize_version(buffer_version),
+        }
     }
 
-    fn buffer_id_from_proto(message: &proto::GetDocumentHighlights) -> u64 {
+    async fn response_from_proto(
+        self,
+        message: proto::GetLinkedEditingRangesResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<LinkedEditingRanges> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        let word_pattern = message
+            .word_pattern
+            .and_then(|pattern| LinkedEditingWordPattern::new(&pattern).log_err());
+        let ranges = message
+            .ranges
+            .into_iter()
+            .map(|range| {
+                let start = range
+                    .start
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing linked editing range start"))?;
+                let end = range
+                    .end
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing linked editing range end"))?;
+                Ok(start..end)
+            })
+            .collect::<Result<_>>()?;
+        Ok(LinkedEditingRanges {
+            ranges,
+            word_pattern,
+        })
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetLinkedEditingRanges) -> u64 {
         message.buffer_id
     }
 }
@@ -1243,15 +2869,7 @@ impl LspCommand for GetHover {
             let contents = response
                 .contents
                 .into_iter()
-                .map(|block| proto::HoverBlock {
-                    text: block.text,
-                    is_markdown: block.kind == HoverBlockKind::Markdown,
-                    language: if let HoverBlockKind::Code { language } = block.kind {
-                        Some(language)
-                    } else {
-                        None
-                    },
-                })
+                .map(hover_block_to_proto)
                 .collect();
 
             proto::GetHoverResponse {
@@ -1278,16 +2896,7 @@ impl LspCommand for GetHover {
         let contents: Vec<_> = message
             .contents
             .into_iter()
-            .map(|block| HoverBlock {
-                text: block.text,
-                kind: if let Some(language) = block.language {
-                    HoverBlockKind::Code { language }
-                } else if block.is_markdown {
-                    HoverBlockKind::Markdown
-                } else {
-                    HoverBlockKind::PlainText
-                },
-            })
+            .map(hover_block_from_proto)
             .collect();
         if contents.is_empty() {
             return Ok(None);
@@ -1313,6 +2922,288 @@ impl LspCommand for GetHover {
     }
 }
 
+fn hover_block_to_proto(block: HoverBlock) -> proto::HoverBlock {
+    proto::HoverBlock {
+        text: block.text,
+        is_markdown: block.kind == HoverBlockKind::Markdown,
+        language: if let HoverBlockKind::Code { language } = block.kind {
+            Some(language)
+        } else {
+            None
+        },
+    }
+}
+
+fn hover_block_from_proto(block: proto::HoverBlock) -> HoverBlock {
+    HoverBlock {
+        text: block.text,
+        kind: if let Some(language) = block.language {
+            HoverBlockKind::Code { language }
+        } else if block.is_markdown {
+            HoverBlockKind::Markdown
+        } else {
+            HoverBlockKind::PlainText
+        },
+    }
+}
+
+fn hover_block_from_documentation(documentation: lsp::Documentation) -> Option<HoverBlock> {
+    let block = match documentation {
+        lsp::Documentation::String(text) => HoverBlock {
+            text,
+            kind: HoverBlockKind::PlainText,
+        },
+        lsp::Documentation::MarkupContent(content) => HoverBlock {
+            text: content.value,
+            kind: if content.kind == lsp::MarkupKind::Markdown {
+                HoverBlockKind::Markdown
+            } else {
+                HoverBlockKind::PlainText
+            },
+        },
+    };
+    if block.text.is_empty() {
+        None
+    } else {
+        Some(block)
+    }
+}
+
+fn parameter_label_range(
+    signature_label: &str,
+    parameter_label: &lsp::ParameterLabel,
+    search_start: usize,
+) -> Option<Range<usize>> {
+    match parameter_label {
+        lsp::ParameterLabel::Simple(label) => {
+            let start = search_start + signature_label.get(search_start..)?.find(label.as_str())?;
+            Some(start..start + label.len())
+        }
+        lsp::ParameterLabel::LabelOffsets([start, end]) => {
+            let offset_for_utf16 = |utf16_offset: u32| {
+                let mut utf16_len = 0;
+                for (ix, ch) in signature_label.char_indices() {
+                    if utf16_len >= utf16_offset as usize {
+                        return ix;
+                    }
+                    utf16_len += ch.len_utf16();
+                }
+                signature_label.len()
+            };
+            Some(offset_for_utf16(*start)..offset_for_utf16(*end))
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetSignatureHelp {
+    type Response = Option<SignatureHelp>;
+    type LspRequest = lsp::request::SignatureHelpRequest;
+    type ProtoRequest = proto::GetSignatureHelp;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.signature_help_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::SignatureHelpParams {
+        lsp::SignatureHelpParams {
+            context: None,
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::SignatureHelp>,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: LanguageServerId,
+        _: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        let Some(help) = message else {
+            return Ok(None);
+        };
+        if help.signatures.is_empty() {
+            return Ok(None);
+        }
+
+        let signatures = help
+            .signatures
+            .into_iter()
+            .map(|signature| {
+                let mut search_start = 0;
+                let parameters = signature
+                    .parameters
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|parameter| {
+                        // Keep parameters missing from the label, as the active parameter is
+                        // an index into all of them.
+                        let label_range =
+                            parameter_label_range(&signature.label, &parameter.label, search_start);
+                        if let Some(label_range) = &label_range {
+                            search_start = label_range.end;
+                        }
+                        ParameterInformation {
+                            label_range,
+                            documentation: parameter
+                                .documentation
+                                .and_then(hover_block_from_documentation),
+                        }
+                    })
+                    .collect();
+                SignatureInformation {
+                    label: signature.label,
+                    documentation: signature
+                        .documentation
+                        .and_then(hover_block_from_documentation),
+                    parameters,
+                    // A signature's own active parameter takes precedence over the response's one.
+                    active_parameter: signature
+                        .active_parameter
+                        .or(help.active_parameter)
+                        .map(|ix| ix as usize),
+                }
+            })
+            .collect::<Vec<_>>();
+        let active_signature = help
+            .active_signature
+            .map_or(0, |ix| ix as usize)
+            .min(signatures.len() - 1);
+
+        Ok(Some(SignatureHelp {
+            signatures,
+            active_signature,
+        }))
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetSignatureHelp {
+        proto::GetSignatureHelp {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetSignatureHelp,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Self::Response,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetSignatureHelpResponse {
+        let Some(help) = response else {
+            return proto::GetSignatureHelpResponse::default();
+        };
+        proto::GetSignatureHelpResponse {
+            signatures: help
+                .signatures
+                .into_iter()
+                .map(|signature| proto::SignatureInformation {
+                    label: signature.label,
+                    documentation: signature.documentation.map(hover_block_to_proto),
+                    parameters: signature
+                        .parameters
+                        .into_iter()
+                        .map(|parameter| proto::ParameterInformation {
+                            label_start: parameter
+                                .label_range
+                                .as_ref()
+                                .map(|range| range.start as u32),
+                            label_end: parameter.label_range.map(|range| range.end as u32),
+                            documentation: parameter.documentation.map(hover_block_to_proto),
+                        })
+                        .collect(),
+                    active_parameter: signature.active_parameter.map(|ix| ix as u32),
+                })
+                .collect(),
+            active_signature: help.active_signature as u32,
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetSignatureHelpResponse,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        if message.signatures.is_empty() {
+            return Ok(None);
+        }
+
+        let signatures = message
+            .signatures
+            .into_iter()
+            .map(|signature| {
+                let parameters = signature
+                    .parameters
+                    .into_iter()
+                    .map(|parameter| ParameterInformation {
+                        label_range: parameter
+                            .label_start
+                            .zip(parameter.label_end)
+                            .map(|(start, end)| start as usize..end as usize)
+                            .filter(|range| signature.label.get(range.clone()).is_some()),
+                        documentation: parameter.documentation.map(hover_block_from_proto),
+                    })
+                    .collect();
+                SignatureInformation {
+                    label: signature.label,
+                    documentation: signature.documentation.map(hover_block_from_proto),
+                    parameters,
+                    active_parameter: signature.active_parameter.map(|ix| ix as usize),
+                }
+            })
+            .collect::<Vec<_>>();
+        let active_signature = (message.active_signature as usize).min(signatures.len() - 1);
+
+        Ok(Some(SignatureHelp {
+            signatures,
+            active_signature,
+        }))
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetSignatureHelp) -> u64 {
+        message.buffer_id
+    }
+}
+
 #[async_trait(?Send)]
 impl LspCommand for GetCompletions {
     type Response = Vec<Completion>;
@@ -1478,7 +3369,7 @@ impl LspCommand for GetCompletions {
                                 )
                             }),
                             documentation,
-                            server_id,
+                            source: CompletionSource::LanguageServer(server_id),
                             lsp_completion,
                         }
                     })
@@ -1530,7 +3421,7 @@ impl LspCommand for GetCompletions {
         proto::GetCompletionsResponse {
             completions: completions
                 .iter()
-                .map(language::proto::serialize_completion)
+                .filter_map(language::proto::serialize_completion)
                 .collect(),
             version: serialize_version(buffer_version),
         }
@@ -1700,6 +3591,165 @@ impl LspCommand for GetCodeActions {
     }
 }
 
+#[async_trait(?Send)]
+impl LspCommand for GetCodeLens {
+    type Response = Vec<CodeLens>;
+    type LspRequest = lsp::request::CodeLensRequest;
+    type ProtoRequest = proto::GetCodeLens;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.code_lens_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CodeLensParams {
+        lsp::CodeLensParams {
+            text_document: lsp::TextDocumentIdentifier::new(
+                lsp::Url::from_file_path(path).unwrap(),
+            ),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_lenses: Option<Vec<lsp::CodeLens>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        let language_server = project
+            .update(&mut cx, |project, _| {
+                project.language_server_for_id(server_id)
+            })?
+            .ok_or_else(|| anyhow!("no language server found for buffer"))?;
+
+        // Lenses without a command are resolved once they're shown.
+        buffer.update(&mut cx, |buffer, _| {
+            lsp_lenses
+                .unwrap_or_default()
+                .into_iter()
+                .map(|lsp_lens| {
+                    let range = range_from_lsp(lsp_lens.range);
+                    let start = buffer.clip_point_utf16(range.start, Bias::Left);
+                    let end = buffer.clip_point_utf16(range.end, Bias::Left);
+                    CodeLens {
+                        server_id,
+                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
+                        executable: lsp_lens.command.as_ref().map_or(false, |command| {
+                            is_advertised_command(&language_server, command)
+                        }),
+                        lsp_lens,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetCodeLens {
+        proto::GetCodeLens {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetCodeLens,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self)
+    }
+
+    fn response_to_proto(
+        lenses: Vec<CodeLens>,
+        _: &mut Project,
+        _: PeerId,
+        buffer_version: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetCodeLensResponse {
+        proto::GetCodeLensResponse {
+            lenses: lenses.iter().map(serialize_code_lens).collect(),
+            version: serialize_version(buffer_version),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetCodeLensResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        message
+            .lenses
+            .into_iter()
+            .map(deserialize_code_lens)
+            .collect()
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetCodeLens) -> u64 {
+        message.buffer_id
+    }
+}
+
+/// Whether the language server can execute the command, rather than expecting the client to.
+pub(crate) fn is_advertised_command(server: &LanguageServer, command: &lsp::Command) -> bool {
+    server
+        .capabilities()
+        .execute_command_provider
+        .as_ref()
+        .map_or(false, |provider| {
+            provider.commands.contains(&command.command)
+        })
+}
+
+pub(crate) fn serialize_code_lens(lens: &CodeLens) -> proto::CodeLens {
+    proto::CodeLens {
+        server_id: lens.server_id.0 as u64,
+        start: Some(serialize_anchor(&lens.range.start)),
+        end: Some(serialize_anchor(&lens.range.end)),
+        lsp_lens: serde_json::to_vec(&lens.lsp_lens).unwrap(),
+        executable: lens.executable,
+    }
+}
+
+pub(crate) fn deserialize_code_lens(lens: proto::CodeLens) -> Result<CodeLens> {
+    let start = lens
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid code lens start"))?;
+    let end = lens
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid code lens end"))?;
+    Ok(CodeLens {
+        server_id: LanguageServerId(lens.server_id as usize),
+        range: start..end,
+        lsp_lens: serde_json::from_slice(&lens.lsp_lens)?,
+        executable: lens.executable,
+    })
+}
+
 #[async_trait(?Send)]
 impl LspCommand for OnTypeFormatting {
     type Response = Option<Transaction>;
@@ -3,7 +3,9 @@ pub mod lsp_command;
 pub mod lsp_ext_command;
 mod prettier_support;
 pub mod project_settings;
+mod pull_diagnostics;
 pub mod search;
+mod semantic_tokens;
 pub mod terminals;
 pub mod worktree;
 
@@ -42,8 +44,9 @@ use language::{
     range_from_lsp, range_to_lsp, Bias, Buffer, BufferSnapshot, CachedLspAdapter, Capability,
     CodeAction, CodeLabel, Completion, Diagnostic, DiagnosticEntry, DiagnosticSet, Diff,
     Documentation, Event as BufferEvent, File as _, Language, LanguageRegistry, LanguageServerName,
-    LocalFile, LspAdapterDelegate, OffsetRangeExt, Operation, Patch, PendingLanguageServer,
-    PointUtf16, TextBufferSnapshot, ToOffset, ToPointUtf16, Transaction, Unclipped,
+    LocalFile, LspAdapterDelegate, OffsetRangeExt, Operation, Patch, PendingLanguageServer, Point,
+    PointUtf16, SignatureHelpTriggers, TextBufferSnapshot, ToOffset, ToPointUtf16, Transaction,
+    Unclipped,
 };
 use log::error;
 use lsp::{
@@ -56,8 +59,11 @@ use parking_lot::{Mutex, RwLock};
 use postage::watch;
 use prettier_support::{DefaultPrettier, PrettierInstance};
 use project_settings::{LspSettings, ProjectSettings};
+use pull_diagnostics::{ProgressNotification, PullDiagnostics};
 use rand::prelude::*;
+use regex::Regex;
 use search::SearchQuery;
+use semantic_tokens::BufferSemanticTokens;
 use serde::Serialize;
 use settings::{Settings, SettingsStore};
 use sha2::{Digest, Sha256};
@@ -90,6 +96,7 @@ use util::{
 pub use fs::*;
 #[cfg(any(test, feature = "test-support"))]
 pub use prettier::FORMAT_SUFFIX as TEST_PRETTIER_FORMAT_SUFFIX;
+pub use semantic_tokens::SemanticToken;
 pub use worktree::*;
 
 const MAX_SERVER_REINSTALL_ATTEMPT_COUNT: u64 = 4;
@@ -138,6 +145,8 @@ pub struct Project {
     /// Used for re-issuing buffer requests when peers temporarily disconnect
     incomplete_remote_buffers: HashMap<u64, Option<Model<Buffer>>>,
     buffer_snapshots: HashMap<u64, HashMap<LanguageServerId, Vec<LspBufferSnapshot>>>, // buffer_id -> server_id -> vec of snapshots
+    semantic_tokens: HashMap<u64, BufferSemanticTokens>,
+    pull_diagnostics: PullDiagnostics,
     buffers_being_formatted: HashSet<u64>,
     buffers_needing_diff: HashSet<WeakModel<Buffer>>,
     git_diff_debouncer: DelayedDebounced,
@@ -267,6 +276,7 @@ pub enum Event {
     WorktreeAdded,
     WorktreeRemoved(WorktreeId),
     WorktreeUpdatedEntries(WorktreeId, UpdatedEntriesSet),
+    WorktreeUpdatedGitRepositories(WorktreeId),
     DiskBasedDiagnosticsStarted {
         language_server_id: LanguageServerId,
     },
@@ -288,6 +298,7 @@ pub enum Event {
     CollaboratorJoined(proto::PeerId),
     CollaboratorLeft(proto::PeerId),
     RefreshInlayHints,
+    RefreshSemanticTokens,
     RevealInProjectPanel(ProjectEntryId),
 }
 
@@ -401,6 +412,63 @@ pub struct LocationLink {
     pub target: Location,
 }
 
+#[derive(Clone, Debug)]
+pub struct CodeLens {
+    pub server_id: LanguageServerId,
+    pub range: Range<language::Anchor>,
+    /// The lens as reported by the language server. Lenses without a command are shown once
+    /// they've been resolved with [`Project::resolve_code_lens`].
+    pub lsp_lens: lsp::CodeLens,
+    /// Whether the language server advertises the lens's command, and can therefore execute it.
+    pub executable: bool,
+}
+
+/// A range of a buffer's lines that were last changed by the same commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameHunk {
+    pub range: Range<language::Anchor>,
+    /// The hash of the commit, or `None` for lines that haven't been committed yet.
+    pub sha: Option<String>,
+    pub author: String,
+    pub author_email: String,
+    /// Timestamp of the commit, normalized to Unix Epoch format.
+    pub unix_timestamp: i64,
+    pub summary: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallHierarchyItem {
+    pub name: String,
+    pub kind: lsp::SymbolKind,
+    pub detail: Option<String>,
+    pub buffer: Model<Buffer>,
+    pub range: Range<language::Anchor>,
+    pub selection_range: Range<language::Anchor>,
+    /// Opaque data from the language server, preserved between the prepare and the calls requests.
+    pub lsp_data: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallHierarchyCall {
+    /// The caller for incoming calls, or the callee for outgoing calls.
+    pub item: CallHierarchyItem,
+    /// Ranges of the calls: in the caller's buffer for incoming calls,
+    /// and in the queried item's buffer for outgoing calls.
+    pub call_sites: Vec<Location>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TypeHierarchyItem {
+    pub name: String,
+    pub kind: lsp::SymbolKind,
+    pub detail: Option<String>,
+    pub buffer: Model<Buffer>,
+    pub range: Range<language::Anchor>,
+    pub selection_range: Range<language::Anchor>,
+    /// Opaque data from the language server, preserved between the prepare and the supertypes/subtypes requests.
+    pub lsp_data: Option<serde_json::Value>,
+}
+
 #[derive(Debug)]
 pub struct DocumentHighlight {
     pub range: Range<language::Anchor>,
@@ -445,6 +513,62 @@ impl Hover {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct SignatureHelp {
+    pub signatures: Vec<SignatureInformation>,
+    pub active_signature: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SignatureInformation {
+    pub label: String,
+    pub documentation: Option<HoverBlock>,
+    pub parameters: Vec<ParameterInformation>,
+    pub active_parameter: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParameterInformation {
+    /// The byte range of the parameter within its signature's label, if the label contains it.
+    pub label_range: Option<Range<usize>>,
+    pub documentation: Option<HoverBlock>,
+}
+
+impl SignatureHelp {
+    pub fn active_signature(&self) -> Option<&SignatureInformation> {
+        self.signatures.get(self.active_signature)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LinkedEditingRanges {
+    pub ranges: Vec<Range<Anchor>>,
+    /// The pattern that the text of the ranges must match for edits to be mirrored into them.
+    pub word_pattern: Option<LinkedEditingWordPattern>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkedEditingWordPattern(Regex);
+
+impl LinkedEditingWordPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self(Regex::new(pattern)?))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Whether the whole of `text` is a word, which an empty text is considered to be.
+    pub fn matches(&self, text: &str) -> bool {
+        text.is_empty()
+            || self
+                .0
+                .find(text)
+                .map_or(false, |word| word.start() == 0 && word.end() == text.len())
+    }
+}
+
 #[derive(Default)]
 pub struct ProjectTransaction(pub HashMap<Model<Buffer>, language::Transaction>);
 
@@ -592,22 +716,41 @@ impl Project {
         client.add_model_request_handler(Self::handle_apply_additional_edits_for_completion);
         client.add_model_request_handler(Self::handle_resolve_completion_documentation);
         client.add_model_request_handler(Self::handle_apply_code_action);
+        client.add_model_request_handler(Self::handle_execute_code_lens);
+        client.add_model_request_handler(Self::handle_resolve_code_lens);
+        client.add_model_request_handler(Self::handle_blame_buffer);
+        client.add_model_request_handler(Self::handle_update_git_index);
+        client.add_model_request_handler(Self::handle_load_head_text);
         client.add_model_request_handler(Self::handle_on_type_formatting);
         client.add_model_request_handler(Self::handle_inlay_hints);
         client.add_model_request_handler(Self::handle_resolve_inlay_hint);
         client.add_model_request_handler(Self::handle_refresh_inlay_hints);
+        client.add_model_request_handler(Self::handle_get_semantic_tokens);
+        client.add_model_request_handler(Self::handle_refresh_semantic_tokens);
         client.add_model_request_handler(Self::handle_reload_buffers);
         client.add_model_request_handler(Self::handle_synchronize_buffers);
         client.add_model_request_handler(Self::handle_format_buffers);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCodeActions>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCompletions>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetHover>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSignatureHelp>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetDefinition>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetTypeDefinition>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetImplementation>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetDocumentHighlights>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetFoldingRanges>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSelectionRanges>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetCodeLens>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetLinkedEditingRanges>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetReferences>);
         client.add_model_request_handler(Self::handle_lsp_command::<PrepareRename>);
         client.add_model_request_handler(Self::handle_lsp_command::<PerformRename>);
+        client.add_model_request_handler(Self::handle_lsp_command::<PrepareCallHierarchy>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetIncomingCalls>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetOutgoingCalls>);
+        client.add_model_request_handler(Self::handle_lsp_command::<PrepareTypeHierarchy>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSupertypes>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSubtypes>);
         client.add_model_request_handler(Self::handle_search_project);
         client.add_model_request_handler(Self::handle_get_project_symbols);
         client.add_model_request_handler(Self::handle_open_buffer_for_symbol);
@@ -645,6 +788,8 @@ impl Project {
                 local_buffer_ids_by_path: Default::default(),
                 local_buffer_ids_by_entry_id: Default::default(),
                 buffer_snapshots: Default::default(),
+                semantic_tokens: Default::default(),
+                pull_diagnostics: Default::default(),
                 join_project_response_message_id: 0,
                 client_state: ProjectClientState::Local,
                 opened_buffer: watch::channel(),
@@ -776,6 +921,8 @@ impl Project {
                 buffers_needing_diff: Default::default(),
                 git_diff_debouncer: DelayedDebounced::new(),
                 buffer_snapshots: Default::default(),
+                semantic_tokens: Default::default(),
+                pull_diagnostics: Default::default(),
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
@@ -2062,6 +2209,8 @@ impl Project {
         self.register_buffer_with_language_servers(buffer, cx);
         self.register_buffer_with_copilot(buffer, cx);
         cx.observe_release(buffer, |this, buffer, cx| {
+            this.semantic_tokens.remove(&buffer.remote_id());
+            this.cancel_document_diagnostics_pull(buffer.remote_id());
             if let Some(file) = File::from_dyn(buffer.file()) {
                 if file.is_local() {
                     let uri = lsp::Url::from_file_path(file.abs_path(cx)).unwrap();
@@ -2153,6 +2302,10 @@ impl Project {
                                 .unwrap_or_default(),
                             cx,
                         );
+                        buffer.set_signature_help_triggers(
+                            signature_help_triggers(server.capabilities()),
+                            cx,
+                        );
                     });
 
                     let snapshot = LspBufferSnapshot {
@@ -2166,6 +2319,8 @@ impl Project {
                 }
             }
         }
+
+        self.pull_document_diagnostics(buffer_handle, cx);
     }
 
     fn unregister_buffer_from_language_servers(
@@ -2190,6 +2345,8 @@ impl Project {
             }
 
             self.buffer_snapshots.remove(&buffer.remote_id());
+            self.semantic_tokens.remove(&buffer.remote_id());
+            self.cancel_document_diagnostics_pull(buffer.remote_id());
             let file_url = lsp::Url::from_file_path(old_path).unwrap();
             for (_, language_server) in self.language_servers_for_buffer(buffer, cx) {
                 language_server
@@ -2333,6 +2490,9 @@ impl Project {
         ) {
             self.request_buffer_diff_recalculation(&buffer, cx);
         }
+        if matches!(event, BufferEvent::Edited { .. }) {
+            self.pull_document_diagnostics(&buffer, cx);
+        }
 
         match event {
             BufferEvent::Operation(operation) => {
@@ -2463,6 +2623,7 @@ impl Project {
 
                 let language_server_ids = self.language_server_ids_for_buffer(buffer.read(cx), cx);
                 for language_server_id in language_server_ids {
+                    self.refresh_workspace_diagnostics(language_server_id);
                     if let Some(LanguageServerState::Running {
                         adapter,
                         simulate_disk_based_diagnostics_completion,
@@ -2749,6 +2910,35 @@ impl Project {
         None
     }
 
+    /// Assigns the language of the buffer's file to the buffer once it's loaded, unlike
+    /// `detect_language_for_buffer`, which only assigns languages that are already loaded.
+    fn wait_for_buffer_language(
+        &mut self,
+        buffer_handle: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<()> {
+        let buffer = buffer_handle.read(cx);
+        let Some(file) = buffer.file() else {
+            return Task::ready(());
+        };
+        let language = self
+            .languages
+            .language_for_file(&file.full_path(cx), Some(buffer.as_rope()));
+        let buffer_handle = buffer_handle.clone();
+        cx.spawn(move |this, mut cx| async move {
+            let Some(language) = language.await.ok() else {
+                return;
+            };
+            this.update(&mut cx, |this, cx| {
+                let buffer_language = buffer_handle.read(cx).language();
+                if buffer_language.is_none() || buffer_language == Some(&*language::PLAIN_TEXT) {
+                    this.set_language_for_buffer(&buffer_handle, language, cx);
+                }
+            })
+            .ok();
+        })
+    }
+
     pub fn set_language_for_buffer(
         &mut self,
         buffer: &Model<Buffer>,
@@ -3020,7 +3210,7 @@ impl Project {
                     if let Some(this) = this.upgrade() {
                         adapter.process_diagnostics(&mut params);
                         this.update(&mut cx, |this, cx| {
-                            this.update_diagnostics(
+                            this.update_published_diagnostics(
                                 server_id,
                                 params,
                                 &adapter.disk_based_diagnostic_sources,
@@ -3146,15 +3336,61 @@ impl Project {
             })
             .detach();
 
+        language_server
+            .on_request::<lsp::request::SemanticTokensRefresh, _, _>({
+                let this = this.clone();
+                move |(), mut cx| {
+                    let this = this.clone();
+                    async move {
+                        this.update(&mut cx, |project, cx| {
+                            cx.emit(Event::RefreshSemanticTokens);
+                            project.remote_id().map(|project_id| {
+                                project
+                                    .client
+                                    .send(proto::RefreshSemanticTokens { project_id })
+                            })
+                        })?
+                        .transpose()?;
+                        Ok(())
+                    }
+                }
+            })
+            .detach();
+
         let disk_based_diagnostics_progress_token =
             adapter.disk_based_diagnostics_progress_token.clone();
 
         language_server
-            .on_notification::<lsp::notification::Progress, _>(move |params, mut cx| {
+            .on_request::<lsp::request::WorkspaceDiagnosticRefresh, _, _>({
+                let this = this.clone();
+                move |(), mut cx| {
+                    let this = this.clone();
+                    async move {
+                        this.update(&mut cx, |project, cx| {
+                            project.refresh_pulled_diagnostics(server_id, cx);
+                        })?;
+                        Ok(())
+                    }
+                }
+            })
+            .detach();
+
+        // Progress notifications also carry the partial results of workspace diagnostics pulls.
+        language_server
+            .on_notification::<ProgressNotification, _>(move |params, mut cx| {
                 if let Some(this) = this.upgrade() {
                     this.update(&mut cx, |this, cx| {
+                        if this.on_workspace_diagnostics_partial_result(server_id, &params, cx) {
+                            return;
+                        }
+                        let Some(value) = serde_json::from_value(params.value).log_err() else {
+                            return;
+                        };
                         this.on_lsp_progress(
-                            params,
+                            lsp::ProgressParams {
+                                token: params.token,
+                                value,
+                            },
                             server_id,
                             disk_based_diagnostics_progress_token.clone(),
                             cx,
@@ -3241,6 +3477,7 @@ impl Project {
         }
 
         // Tell the language server about every open buffer in the worktree that matches the language.
+        let mut opened_buffers = Vec::new();
         for buffer in self.opened_buffers.values() {
             if let Some(buffer_handle) = buffer.upgrade() {
                 let buffer = buffer_handle.read(cx);
@@ -3304,11 +3541,21 @@ impl Project {
                             .and_then(|provider| provider.trigger_characters.clone())
                             .unwrap_or_default(),
                         cx,
-                    )
+                    );
+                    buffer.set_signature_help_triggers(
+                        signature_help_triggers(language_server.capabilities()),
+                        cx,
+                    );
                 });
+                opened_buffers.push(buffer_handle);
             }
         }
 
+        for buffer in opened_buffers {
+            self.pull_document_diagnostics(&buffer, cx);
+        }
+        self.start_workspace_diagnostics_pull(adapter, language_server, cx);
+
         cx.notify();
         Ok(())
     }
@@ -3353,6 +3600,7 @@ impl Project {
             }
 
             self.language_server_statuses.remove(&server_id);
+            self.stop_pulling_diagnostics(server_id);
             cx.notify();
 
             let server_state = self.language_servers.remove(&server_id);
@@ -4482,6 +4730,21 @@ impl Project {
         )
     }
 
+    pub fn implementation<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<LocationLink>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetImplementation { position },
+            cx,
+        )
+    }
+
     pub fn references<T: ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -4497,6 +4760,88 @@ impl Project {
         )
     }
 
+    pub fn prepare_call_hierarchy<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyItem>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            PrepareCallHierarchy { position },
+            cx,
+        )
+    }
+
+    pub fn incoming_calls(
+        &self,
+        item: CallHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyCall>>> {
+        self.request_lsp(
+            item.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetIncomingCalls { item },
+            cx,
+        )
+    }
+
+    pub fn outgoing_calls(
+        &self,
+        item: CallHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyCall>>> {
+        self.request_lsp(
+            item.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetOutgoingCalls { item },
+            cx,
+        )
+    }
+
+    pub fn prepare_type_hierarchy<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<TypeHierarchyItem>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            PrepareTypeHierarchy { position },
+            cx,
+        )
+    }
+
+    pub fn supertypes(
+        &self,
+        item: TypeHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<TypeHierarchyItem>>> {
+        self.request_lsp(
+            item.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSupertypes { item },
+            cx,
+        )
+    }
+
+    pub fn subtypes(
+        &self,
+        item: TypeHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<TypeHierarchyItem>>> {
+        self.request_lsp(
+            item.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSubtypes { item },
+            cx,
+        )
+    }
+
     pub fn document_highlights<T: ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -4512,6 +4857,289 @@ impl Project {
         )
     }
 
+    /// Returns the ranges that the primary language server reports as foldable in the given
+    /// buffer, extended to span whole lines. The result is empty when no server supports folding.
+    pub fn folding_ranges(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<Range<Anchor>>>> {
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetFoldingRanges,
+            cx,
+        )
+    }
+
+    /// Returns, for each of the given positions, the ranges that the primary language server
+    /// would select around it, from the innermost to the outermost one. The result is empty
+    /// when no server supports selection ranges.
+    pub fn selection_ranges<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        positions: Vec<T>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<Vec<Range<Anchor>>>>> {
+        let positions = positions
+            .into_iter()
+            .map(|position| position.to_point_utf16(buffer.read(cx)))
+            .collect();
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSelectionRanges { positions },
+            cx,
+        )
+    }
+
+    /// Returns the ranges that should be edited together with the one at `position`, such as
+    /// the names of an opening and a closing tag. When the language server doesn't report any,
+    /// the ranges are computed from the language's linked editing query.
+    pub fn linked_editing_ranges<T: ToOffset + ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<LinkedEditingRanges>> {
+        let snapshot = buffer.read(cx).snapshot();
+        let syntax_ranges = snapshot
+            .linked_editing_ranges(position.to_offset(&snapshot))
+            .into_iter()
+            .map(|range| snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end))
+            .collect::<Vec<_>>();
+        let position = position.to_point_utf16(&snapshot);
+        let request = self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetLinkedEditingRanges { position },
+            cx,
+        );
+        cx.background_executor().spawn(async move {
+            let ranges = request.await?;
+            Ok(if ranges.ranges.is_empty() {
+                LinkedEditingRanges {
+                    ranges: syntax_ranges,
+                    word_pattern: None,
+                }
+            } else {
+                ranges
+            })
+        })
+    }
+
+    /// Computes the commits that last changed each line of the given buffer, attributing the
+    /// lines with unsaved or uncommitted changes to no commit.
+    pub fn blame_buffer(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<BlameHunk>>> {
+        if self.is_local() {
+            let snapshot = buffer.read(cx).snapshot();
+            let Some(file) = File::from_dyn(snapshot.file()) else {
+                return Task::ready(Err(anyhow!("buffer has no file")));
+            };
+            let path = file.path.clone();
+            let blame = file.worktree.update(cx, |worktree, cx| {
+                worktree
+                    .as_local()
+                    .map(|worktree| worktree.blame(&path, snapshot.text(), cx))
+            });
+            let Some(blame) = blame else {
+                return Task::ready(Err(anyhow!("buffer is not in a local worktree")));
+            };
+            cx.background_executor().spawn(async move {
+                let max_point = snapshot.max_point();
+                Ok(blame
+                    .await?
+                    .into_iter()
+                    .map(|entry| {
+                        let start = Point::new(entry.rows.start, 0).min(max_point);
+                        let end = Point::new(entry.rows.end, 0).min(max_point);
+                        BlameHunk {
+                            range: snapshot.anchor_before(start)..snapshot.anchor_before(end),
+                            sha: entry.sha,
+                            author: entry.author,
+                            author_email: entry.author_email,
+                            unix_timestamp: entry.unix_timestamp,
+                            summary: entry.summary,
+                        }
+                    })
+                    .collect())
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::BlameBuffer {
+                project_id,
+                buffer_id: buffer.read(cx).remote_id(),
+                version: serialize_version(&buffer.read(cx).version()),
+            };
+            let buffer = buffer.clone();
+            cx.spawn(move |_, mut cx| async move {
+                let response = client.request(request).await?;
+                buffer
+                    .update(&mut cx, |buffer, _| {
+                        buffer.wait_for_version(deserialize_version(&response.version))
+                    })?
+                    .await?;
+                response
+                    .hunks
+                    .into_iter()
+                    .map(deserialize_blame_hunk)
+                    .collect()
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
+    /// Loads the contents of the given buffer's file in the HEAD commit of its repository.
+    pub fn load_head_text(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Option<String>>> {
+        if self.is_local() {
+            let Some(file) = File::from_dyn(buffer.read(cx).file()) else {
+                return Task::ready(Err(anyhow!("buffer has no file")));
+            };
+            let path = file.path.clone();
+            let head_text = file.worktree.update(cx, |worktree, cx| {
+                worktree
+                    .as_local()
+                    .map(|worktree| worktree.load_head_text(&path, cx))
+            });
+            head_text
+                .unwrap_or_else(|| Task::ready(Err(anyhow!("buffer is not in a local worktree"))))
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::LoadHeadText {
+                project_id,
+                buffer_id: buffer.read(cx).remote_id(),
+            };
+            cx.background_executor().spawn(async move {
+                let response = client.request(request).await?;
+                Ok(response.text)
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
+    /// Stages the hunks of the given buffer that intersect any of the given ranges, writing
+    /// their unsaved contents into the git index.
+    pub fn stage_hunks(
+        &self,
+        buffer: &Model<Buffer>,
+        ranges: Vec<Range<language::Anchor>>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        self.update_git_index(buffer, ranges, true, cx)
+    }
+
+    /// Unstages the hunks of the given buffer that intersect any of the given ranges, restoring
+    /// their contents in the git index to those of the HEAD commit.
+    pub fn unstage_hunks(
+        &self,
+        buffer: &Model<Buffer>,
+        ranges: Vec<Range<language::Anchor>>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        self.update_git_index(buffer, ranges, false, cx)
+    }
+
+    fn update_git_index(
+        &self,
+        buffer: &Model<Buffer>,
+        ranges: Vec<Range<language::Anchor>>,
+        stage: bool,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<()>> {
+        if self.is_local() {
+            let snapshot = buffer.read(cx).snapshot();
+            let Some(file) = File::from_dyn(snapshot.file()) else {
+                return Task::ready(Err(anyhow!("buffer has no file")));
+            };
+            let path = file.path.clone();
+            let rows = ranges
+                .iter()
+                .map(|range| {
+                    let range = range.to_point(&snapshot);
+                    range.start.row..range.end.row + 1
+                })
+                .collect::<Vec<_>>();
+            let buffer_text = snapshot.text();
+            let update = file.worktree.update(cx, |worktree, cx| {
+                worktree.as_local().map(|worktree| {
+                    worktree.update_index_text(
+                        &path,
+                        move |head_text, index_text| {
+                            let index_text = index_text?;
+                            if stage {
+                                Some(git::diff::stage_hunks(index_text, &buffer_text, &rows))
+                            } else {
+                                // A file that isn't in the HEAD commit is staged as a single
+                                // hunk, so unstaging it removes the file from the index.
+                                Some(git::diff::unstage_hunks(
+                                    head_text?,
+                                    index_text,
+                                    &buffer_text,
+                                    &rows,
+                                ))
+                            }
+                        },
+                        cx,
+                    )
+                })
+            });
+            let Some(update) = update else {
+                return Task::ready(Err(anyhow!("buffer is not in a local worktree")));
+            };
+            let remote_id = self.remote_id();
+            let client = self.client.clone();
+            let buffer = buffer.clone();
+            cx.spawn(move |_, mut cx| async move {
+                let diff_base = update.await?;
+                let buffer_id = buffer.update(&mut cx, |buffer, cx| {
+                    buffer.set_diff_base(diff_base.clone(), cx);
+                    buffer.remote_id()
+                })?;
+                if let Some(project_id) = remote_id {
+                    client
+                        .send(proto::UpdateDiffBase {
+                            project_id,
+                            buffer_id,
+                            diff_base,
+                        })
+                        .log_err();
+                }
+                Ok(())
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::UpdateGitIndex {
+                project_id,
+                buffer_id: buffer.read(cx).remote_id(),
+                version: serialize_version(&buffer.read(cx).version()),
+                ranges: ranges
+                    .iter()
+                    .map(|range| proto::AnchorRange {
+                        start: Some(serialize_anchor(&range.start)),
+                        end: Some(serialize_anchor(&range.end)),
+                    })
+                    .collect(),
+                stage,
+            };
+            cx.background_executor().spawn(async move {
+                client.request(request).await?;
+                Ok(())
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
     pub fn symbols(&self, query: &str, cx: &mut ModelContext<Self>) -> Task<Result<Vec<Symbol>>> {
         if self.is_local() {
             let mut requests = Vec::new();
@@ -4746,6 +5374,21 @@ impl Project {
         )
     }
 
+    pub fn signature_help<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Option<SignatureHelp>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSignatureHelp { position },
+            cx,
+        )
+    }
+
     pub fn completions<T: ToOffset + ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -4825,8 +5468,10 @@ impl Project {
                         continue;
                     }
 
+                    let Some(server_id) = completion.server_id() else {
+                        continue;
+                    };
                     did_resolve = true;
-                    let server_id = completion.server_id;
                     let completion = completion.lsp_completion.clone();
                     drop(completions_guard);
 
@@ -4849,7 +5494,9 @@ impl Project {
                         continue;
                     }
 
-                    let server_id = completion.server_id;
+                    let Some(server_id) = completion.server_id() else {
+                        continue;
+                    };
                     let completion = completion.lsp_completion.clone();
                     drop(completions_guard);
 
@@ -4973,9 +5620,12 @@ impl Project {
     ) -> Task<Result<Option<Transaction>>> {
         let buffer = buffer_handle.read(cx);
         let buffer_id = buffer.remote_id();
+        // Only language servers provide additional edits.
+        let Some(server_id) = completion.server_id() else {
+            return Task::ready(Ok(None));
+        };
 
         if self.is_local() {
-            let server_id = completion.server_id;
             let lang_server = match self.language_server_for_buffer(buffer, server_id, cx) {
                 Some((_, server)) => server.clone(),
                 _ => return Task::ready(Ok(Default::default())),
@@ -5049,7 +5699,7 @@ impl Project {
                     .request(proto::ApplyCompletionAdditionalEdits {
                         project_id,
                         buffer_id,
-                        completion: Some(language::proto::serialize_completion(&completion)),
+                        completion: language::proto::serialize_completion(&completion),
                     })
                     .await?;
 
@@ -5149,29 +5799,7 @@ impl Project {
                 }
 
                 if let Some(command) = action.lsp_action.command {
-                    this.update(&mut cx, |this, _| {
-                        this.last_workspace_edits_by_language_server
-                            .remove(&lang_server.server_id());
-                    })?;
-
-                    let result = lang_server
-                        .request::<lsp::request::ExecuteCommand>(lsp::ExecuteCommandParams {
-                            command: command.command,
-                            arguments: command.arguments.unwrap_or_default(),
-                            ..Default::default()
-                        })
-                        .await;
-
-                    if let Err(err) = result {
-                        // TODO: LSP ERROR
-                        return Err(err);
-                    }
-
-                    return Ok(this.update(&mut cx, |this, _| {
-                        this.last_workspace_edits_by_language_server
-                            .remove(&lang_server.server_id())
-                            .unwrap_or_default()
-                    })?);
+                    return Self::execute_lsp_command(this, lang_server, command, &mut cx).await;
                 }
 
                 Ok(ProjectTransaction::default())
@@ -5199,6 +5827,156 @@ impl Project {
         }
     }
 
+    pub fn code_lens(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CodeLens>>> {
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetCodeLens,
+            cx,
+        )
+    }
+
+    pub fn execute_code_lens(
+        &self,
+        buffer_handle: Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        if self.is_local() {
+            let Some(command) = lens.lsp_lens.command else {
+                return Task::ready(Err(anyhow!("code lens has no command")));
+            };
+            let lang_server = if let Some((_, server)) =
+                self.language_server_for_buffer(buffer_handle.read(cx), lens.server_id, cx)
+            {
+                server.clone()
+            } else {
+                return Task::ready(Ok(Default::default()));
+            };
+            // Lenses may carry commands that the client is expected to implement.
+            if !is_advertised_command(&lang_server, &command) {
+                return Task::ready(Err(anyhow!(
+                    "language server does not support command {:?}",
+                    command.command
+                )));
+            }
+            cx.spawn(move |this, mut cx| async move {
+                Self::execute_lsp_command(this, lang_server, command, &mut cx).await
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::ExecuteCodeLens {
+                project_id,
+                buffer_id: buffer_handle.read(cx).remote_id(),
+                lens: Some(serialize_code_lens(&lens)),
+            };
+            cx.spawn(move |this, mut cx| async move {
+                let response = client
+                    .request(request)
+                    .await?
+                    .transaction
+                    .ok_or_else(|| anyhow!("missing transaction"))?;
+                this.update(&mut cx, |this, cx| {
+                    this.deserialize_project_transaction(response, true, cx)
+                })?
+                .await
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
+    /// Resolves the command of a lens that the language server reported without one. Returns the
+    /// lens unchanged if it already has a command or the server doesn't resolve lenses.
+    pub fn resolve_code_lens(
+        &self,
+        buffer_handle: Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<CodeLens>> {
+        if lens.lsp_lens.command.is_some() {
+            return Task::ready(Ok(lens));
+        }
+
+        if self.is_local() {
+            let lang_server = if let Some((_, server)) =
+                self.language_server_for_buffer(buffer_handle.read(cx), lens.server_id, cx)
+            {
+                server.clone()
+            } else {
+                return Task::ready(Ok(lens));
+            };
+            let resolves_lenses = lang_server
+                .capabilities()
+                .code_lens_provider
+                .as_ref()
+                .and_then(|options| options.resolve_provider)
+                .unwrap_or(false);
+            if !resolves_lenses {
+                return Task::ready(Ok(lens));
+            }
+
+            cx.spawn(move |_, _| async move {
+                let lsp_lens = lang_server
+                    .request::<lsp::request::CodeLensResolve>(lens.lsp_lens)
+                    .await
+                    .context("code lens resolve LSP request")?;
+                Ok(CodeLens {
+                    executable: lsp_lens.command.as_ref().map_or(false, |command| {
+                        is_advertised_command(&lang_server, command)
+                    }),
+                    lsp_lens,
+                    ..lens
+                })
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::ResolveCodeLens {
+                project_id,
+                buffer_id: buffer_handle.read(cx).remote_id(),
+                lens: Some(serialize_code_lens(&lens)),
+            };
+            cx.spawn(move |_, _| async move {
+                let response = client.request(request).await?;
+                deserialize_code_lens(response.lens.ok_or_else(|| anyhow!("missing code lens"))?)
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
+    /// Executes a command on the language server, returning the edits that the server applied
+    /// to the workspace while executing it.
+    async fn execute_lsp_command(
+        this: WeakModel<Self>,
+        language_server: Arc<LanguageServer>,
+        command: lsp::Command,
+        cx: &mut AsyncAppContext,
+    ) -> Result<ProjectTransaction> {
+        this.update(cx, |this, _| {
+            this.last_workspace_edits_by_language_server
+                .remove(&language_server.server_id());
+        })?;
+
+        language_server
+            .request::<lsp::request::ExecuteCommand>(lsp::ExecuteCommandParams {
+                command: command.command,
+                arguments: command.arguments.unwrap_or_default(),
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(this.update(cx, |this, _| {
+            this.last_workspace_edits_by_language_server
+                .remove(&language_server.server_id())
+                .unwrap_or_default()
+        })?)
+    }
+
     fn apply_on_type_formatting(
         &self,
         buffer: Model<Buffer>,
@@ -5695,6 +6473,11 @@ impl Project {
         query: SearchQuery,
         cx: &mut ModelContext<Self>,
     ) -> Receiver<(Model<Buffer>, Vec<Range<Anchor>>)> {
+        let query = if query.is_structural() {
+            query.with_grammar_path_suffixes(self.languages.grammar_path_suffixes())
+        } else {
+            query
+        };
         // Local search is split into several phases.
         // TL;DR is that we do 2 passes; initial pass to pick files which contain at least one match
         // and the second phase that finds positions of all the matches found in the candidate files.
@@ -5785,7 +6568,8 @@ impl Project {
             ))
             .detach();
 
-        let (buffers, buffers_rx) = Self::sort_candidates_and_open_buffers(matching_paths_rx, cx);
+        let (buffers, buffers_rx) =
+            Self::sort_candidates_and_open_buffers(matching_paths_rx, query.is_structural(), cx);
         let background = cx.background_executor().clone();
         let (result_tx, result_rx) = smol::channel::bounded(1024);
         cx.background_executor()
@@ -6184,6 +6968,7 @@ impl Project {
 
     fn sort_candidates_and_open_buffers(
         mut matching_paths_rx: Receiver<SearchMatchCandidate>,
+        wait_for_parsing: bool,
         cx: &mut ModelContext<Self>,
     ) -> (
         futures::channel::oneshot::Receiver<Vec<SearchMatchCandidate>>,
@@ -6234,6 +7019,15 @@ impl Project {
                             .log_err(),
                     };
                     if let Some(buffer) = buffer {
+                        if wait_for_parsing {
+                            this.update(&mut cx, |this, cx| {
+                                this.wait_for_buffer_language(&buffer, cx)
+                            })?
+                            .await;
+                            buffer
+                                .update(&mut cx, |buffer, _| buffer.parsing_idle())?
+                                .await;
+                        }
                         let snapshot = buffer.update(&mut cx, |buffer, _| buffer.snapshot())?;
                         buffers_tx
                             .send((Some((buffer, snapshot)), index))
@@ -6423,7 +7217,10 @@ impl Project {
                     ));
                 }
                 worktree::Event::UpdatedGitRepositories(updated_repos) => {
-                    this.update_local_worktree_buffers_git_repos(worktree, updated_repos, cx)
+                    this.update_local_worktree_buffers_git_repos(worktree, updated_repos, cx);
+                    cx.emit(Event::WorktreeUpdatedGitRepositories(
+                        worktree.read(cx).id(),
+                    ));
                 }
             })
             .detach();
@@ -7792,6 +8589,150 @@ impl Project {
         })
     }
 
+    async fn handle_execute_code_lens(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::ExecuteCodeLens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::ExecuteCodeLensResponse> {
+        let sender_id = envelope.original_sender_id()?;
+        let lens = deserialize_code_lens(
+            envelope
+                .payload
+                .lens
+                .ok_or_else(|| anyhow!("invalid code lens"))?,
+        )?;
+        let execute_code_lens = this.update(&mut cx, |this, cx| {
+            let buffer = this
+                .opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))?;
+            Ok::<_, anyhow::Error>(this.execute_code_lens(buffer, lens, cx))
+        })??;
+
+        let project_transaction = execute_code_lens.await?;
+        let project_transaction = this.update(&mut cx, |this, cx| {
+            this.serialize_project_transaction_for_peer(project_transaction, sender_id, cx)
+        })?;
+        Ok(proto::ExecuteCodeLensResponse {
+            transaction: Some(project_transaction),
+        })
+    }
+
+    async fn handle_resolve_code_lens(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::ResolveCodeLens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::ResolveCodeLensResponse> {
+        let lens = deserialize_code_lens(
+            envelope
+                .payload
+                .lens
+                .ok_or_else(|| anyhow!("invalid code lens"))?,
+        )?;
+        let resolve_code_lens = this.update(&mut cx, |this, cx| {
+            let buffer = this
+                .opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))?;
+            Ok::<_, anyhow::Error>(this.resolve_code_lens(buffer, lens, cx))
+        })??;
+
+        Ok(proto::ResolveCodeLensResponse {
+            lens: Some(serialize_code_lens(&resolve_code_lens.await?)),
+        })
+    }
+
+    async fn handle_blame_buffer(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::BlameBuffer>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::BlameBufferResponse> {
+        let buffer = this.update(&mut cx, |this, _| {
+            this.opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))
+        })??;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&envelope.payload.version))
+            })?
+            .await?;
+
+        let (version, blame) = this.update(&mut cx, |this, cx| {
+            (buffer.read(cx).version(), this.blame_buffer(&buffer, cx))
+        })?;
+        let hunks = blame.await?;
+        Ok(proto::BlameBufferResponse {
+            hunks: hunks.iter().map(serialize_blame_hunk).collect(),
+            version: serialize_version(&version),
+        })
+    }
+
+    async fn handle_update_git_index(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::UpdateGitIndex>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::Ack> {
+        let buffer = this.update(&mut cx, |this, _| {
+            this.opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))
+        })??;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&envelope.payload.version))
+            })?
+            .await?;
+
+        let ranges = envelope
+            .payload
+            .ranges
+            .into_iter()
+            .map(|range| {
+                let start = range
+                    .start
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("invalid start"))?;
+                let end = range
+                    .end
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("invalid end"))?;
+                Ok(start..end)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        this.update(&mut cx, |this, cx| {
+            this.update_git_index(&buffer, ranges, envelope.payload.stage, cx)
+        })?
+        .await?;
+        Ok(proto::Ack {})
+    }
+
+    async fn handle_load_head_text(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::LoadHeadText>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::LoadHeadTextResponse> {
+        let buffer = this.update(&mut cx, |this, _| {
+            this.opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))
+        })??;
+        let text = this
+            .update(&mut cx, |this, cx| this.load_head_text(&buffer, cx))?
+            .await?;
+        Ok(proto::LoadHeadTextResponse { text })
+    }
+
     async fn handle_on_type_formatting(
         this: Model<Self>,
         envelope: TypedEnvelope<proto::OnTypeFormatting>,
@@ -8912,6 +9853,37 @@ fn serialize_symbol(symbol: &Symbol) -> proto::Symbol {
     }
 }
 
+fn serialize_blame_hunk(hunk: &BlameHunk) -> proto::BlameHunk {
+    proto::BlameHunk {
+        start: Some(serialize_anchor(&hunk.range.start)),
+        end: Some(serialize_anchor(&hunk.range.end)),
+        sha: hunk.sha.clone(),
+        author: hunk.author.clone(),
+        author_email: hunk.author_email.clone(),
+        unix_timestamp: hunk.unix_timestamp,
+        summary: hunk.summary.clone(),
+    }
+}
+
+fn deserialize_blame_hunk(hunk: proto::BlameHunk) -> Result<BlameHunk> {
+    let start = hunk
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid start"))?;
+    let end = hunk
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid end"))?;
+    Ok(BlameHunk {
+        range: start..end,
+        sha: hunk.sha,
+        author: hunk.author,
+        author_email: hunk.author_email,
+        unix_timestamp: hunk.unix_timestamp,
+        summary: hunk.summary,
+    })
+}
+
 fn relativize_path(base: &Path, path: &Path) -> PathBuf {
     let mut path_components = path.components();
     let mut base_components = base.components();
@@ -8997,3 +9969,14 @@ fn include_text(server: &lsp::LanguageServer) -> bool {
         })
         .unwrap_or(false)
 }
+
+fn signature_help_triggers(capabilities: &lsp::ServerCapabilities) -> SignatureHelpTriggers {
+    capabilities
+        .signature_help_provider
+        .as_ref()
+        .map(|provider| SignatureHelpTriggers {
+            trigger_characters: provider.trigger_characters.clone().unwrap_or_default(),
+            retrigger_characters: provider.retrigger_characters.clone().unwrap_or_default(),
+        })
+        .unwrap_or_default()
+}
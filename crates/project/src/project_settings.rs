@@ -43,6 +43,11 @@ pub struct GitSettings {
     /// Default: tracked_files
     pub git_gutter: Option<GitGutterSetting>,
     pub gutter_debounce: Option<u64>,
+    /// Whether or not to show the author and commit that last changed the
+    /// cursor's line at the end of it.
+    ///
+    /// Default: false
+    pub inline_blame: Option<bool>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -1,4 +1,7 @@
-use crate::{Event, *};
+use crate::{
+    pull_diagnostics::{ProgressNotification, ProgressNotificationParams},
+    Event, *,
+};
 use fs::FakeFs;
 use futures::{future, StreamExt};
 use gpui::AppContext;
@@ -2344,24 +2347,21 @@ async fn test_definition(cx: &mut gpui::TestAppContext) {
 }
 
 #[gpui::test]
-async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
+async fn test_call_hierarchy(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let mut language = Language::new(
         LanguageConfig {
-            name: "TypeScript".into(),
-            path_suffixes: vec!["ts".to_string()],
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
             ..Default::default()
         },
-        Some(tree_sitter_typescript::language_typescript()),
+        Some(tree_sitter_rust::language()),
     );
-    let mut fake_language_servers = language
+    let mut fake_servers = language
         .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
             capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![":".to_string()]),
-                    ..Default::default()
-                }),
+                call_hierarchy_provider: Some(lsp::CallHierarchyServerCapability::Simple(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -2372,7 +2372,8 @@ async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
     fs.insert_tree(
         "/dir",
         json!({
-            "a.ts": "",
+            "a.rs": "fn a() {}",
+            "b.rs": "fn b() { a(); a(); }",
         }),
     )
     .await;
@@ -2380,85 +2381,112 @@ async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
     let project = Project::test(fs, ["/dir".as_ref()], cx).await;
     project.update(cx, |project, _| project.languages.add(Arc::new(language)));
     let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
-
-    let text = "let a = b.fqn";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len(), cx)
+    let fake_server = fake_servers.next().await.unwrap();
+    let a_item = lsp::CallHierarchyItem {
+        name: "a".to_string(),
+        kind: lsp::SymbolKind::FUNCTION,
+        tags: None,
+        detail: None,
+        uri: lsp::Url::from_file_path("/dir/a.rs").unwrap(),
+        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 9)),
+        selection_range: lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(0, 4)),
+        data: Some(json!({ "id": 1 })),
+    };
+    fake_server.handle_request::<lsp::request::CallHierarchyPrepare, _, _>({
+        let a_item = a_item.clone();
+        move |params, _| {
+            let a_item = a_item.clone();
+            async move {
+                assert_eq!(
+                    params.text_document_position_params.position,
+                    lsp::Position::new(0, 3)
+                );
+                Ok(Some(vec![a_item]))
+            }
+        }
     });
-
-    fake_server
-        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "fullyQualifiedName?".into(),
-                    insert_text: Some("fullyQualifiedName".into()),
-                    ..Default::default()
+    fake_server.handle_request::<lsp::request::CallHierarchyIncomingCalls, _, _>(
+        move |params, _| async move {
+            assert_eq!(params.item.name, "a");
+            assert_eq!(params.item.data, Some(json!({ "id": 1 })));
+            Ok(Some(vec![lsp::CallHierarchyIncomingCall {
+                from: lsp::CallHierarchyItem {
+                    name: "b".to_string(),
+                    kind: lsp::SymbolKind::FUNCTION,
+                    tags: None,
+                    detail: None,
+                    uri: lsp::Url::from_file_path("/dir/b.rs").unwrap(),
+                    range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 20)),
+                    selection_range: lsp::Range::new(
+                        lsp::Position::new(0, 3),
+                        lsp::Position::new(0, 4),
+                    ),
+                    data: None,
                 },
-            ])))
-        })
-        .next()
-        .await;
-    let completions = completions.await.unwrap();
-    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "fullyQualifiedName");
-    assert_eq!(
-        completions[0].old_range.to_offset(&snapshot),
-        text.len() - 3..text.len()
+                from_ranges: vec![
+                    lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 10)),
+                    lsp::Range::new(lsp::Position::new(0, 14), lsp::Position::new(0, 15)),
+                ],
+            }]))
+        },
     );
 
-    let text = "let a = \"atoms/cmp\"";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len() - 1, cx)
+    let items = project
+        .update(cx, |project, cx| {
+            project.prepare_call_hierarchy(&buffer, 3, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    let item = items[0].clone();
+    cx.update(|cx| {
+        assert_eq!(item.name, "a");
+        assert_eq!(item.selection_range.to_offset(item.buffer.read(cx)), 3..4);
     });
 
-    fake_server
-        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "component".into(),
-                    ..Default::default()
-                },
-            ])))
-        })
-        .next()
-        .await;
-    let completions = completions.await.unwrap();
-    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "component");
-    assert_eq!(
-        completions[0].old_range.to_offset(&snapshot),
-        text.len() - 4..text.len() - 1
-    );
+    let calls = project
+        .update(cx, |project, cx| project.incoming_calls(item, cx))
+        .await
+        .unwrap();
+    assert_eq!(calls.len(), 1);
+    cx.update(|cx| {
+        let caller_buffer = calls[0].item.buffer.read(cx);
+        assert_eq!(calls[0].item.name, "b");
+        assert_eq!(
+            caller_buffer.file().unwrap().path().as_ref(),
+            Path::new("b.rs")
+        );
+        assert_eq!(
+            calls[0]
+                .call_sites
+                .iter()
+                .map(|location| location.range.to_offset(location.buffer.read(cx)))
+                .collect::<Vec<_>>(),
+            [9..10, 14..15]
+        );
+    });
 }
 
 #[gpui::test]
-async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
+async fn test_type_hierarchy(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let mut language = Language::new(
         LanguageConfig {
-            name: "TypeScript".into(),
-            path_suffixes: vec!["ts".to_string()],
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
             ..Default::default()
         },
-        Some(tree_sitter_typescript::language_typescript()),
+        Some(tree_sitter_rust::language()),
     );
-    let mut fake_language_servers = language
+    let mut fake_servers = language
         .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
             capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![":".to_string()]),
-                    ..Default::default()
-                }),
+                type_hierarchy_provider: Some(lsp::TypeHierarchyServerCapability::Simple(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -2469,7 +2497,8 @@ async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
     fs.insert_tree(
         "/dir",
         json!({
-            "a.ts": "",
+            "a.rs": "trait A {}",
+            "b.rs": "struct B; impl A for B {}",
         }),
     )
     .await;
@@ -2477,54 +2506,166 @@ async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
     let project = Project::test(fs, ["/dir".as_ref()], cx).await;
     project.update(cx, |project, _| project.languages.add(Arc::new(language)));
     let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::TypeHierarchyPrepare, _, _>(
+        |params, _| async move {
+            assert_eq!(
+                params.text_document_position_params.position,
+                lsp::Position::new(0, 6)
+            );
+            Ok(Some(vec![lsp::TypeHierarchyItem {
+                name: "A".to_string(),
+                kind: lsp::SymbolKind::INTERFACE,
+                tags: None,
+                detail: None,
+                uri: lsp::Url::from_file_path("/dir/a.rs").unwrap(),
+                range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 10)),
+                selection_range: lsp::Range::new(
+                    lsp::Position::new(0, 6),
+                    lsp::Position::new(0, 7),
+                ),
+                data: Some(json!({ "id": 1 })),
+            }]))
+        },
+    );
+    fake_server.handle_request::<lsp::request::TypeHierarchySubtypes, _, _>(
+        |params, _| async move {
+            assert_eq!(params.item.name, "A");
+            assert_eq!(params.item.data, Some(json!({ "id": 1 })));
+            Ok(Some(vec![lsp::TypeHierarchyItem {
+                name: "B".to_string(),
+                kind: lsp::SymbolKind::STRUCT,
+                tags: None,
+                detail: None,
+                uri: lsp::Url::from_file_path("/dir/b.rs").unwrap(),
+                range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 9)),
+                selection_range: lsp::Range::new(
+                    lsp::Position::new(0, 7),
+                    lsp::Position::new(0, 8),
+                ),
+                data: None,
+            }]))
+        },
+    );
 
-    let text = "let a = b.fqn";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len(), cx)
+    let items = project
+        .update(cx, |project, cx| {
+            project.prepare_type_hierarchy(&buffer, 6, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    let item = items[0].clone();
+    cx.update(|cx| {
+        assert_eq!(item.name, "A");
+        assert_eq!(item.selection_range.to_offset(item.buffer.read(cx)), 6..7);
     });
 
-    fake_server
-        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "fullyQualifiedName?".into(),
-                    insert_text: Some("fully\rQualified\r\nName".into()),
-                    ..Default::default()
-                },
-            ])))
-        })
-        .next()
+    let subtypes = project
+        .update(cx, |project, cx| project.subtypes(item, cx))
+        .await
+        .unwrap();
+    assert_eq!(subtypes.len(), 1);
+    cx.update(|cx| {
+        let subtype_buffer = subtypes[0].buffer.read(cx);
+        assert_eq!(subtypes[0].name, "B");
+        assert_eq!(
+            subtype_buffer.file().unwrap().path().as_ref(),
+            Path::new("b.rs")
+        );
+        assert_eq!(subtypes[0].selection_range.to_offset(subtype_buffer), 7..8);
+    });
+}
+
+#[gpui::test]
+async fn test_type_hierarchy_without_capability(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter::default()))
         .await;
-    let completions = completions.await.unwrap();
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "fully\nQualified\nName");
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "a.rs": "trait A {}" }))
+        .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::TypeHierarchyPrepare, _, _>(|_, _| async move {
+        panic!("type hierarchy requested from a server that doesn't support it")
+    });
+
+    let items = project
+        .update(cx, |project, cx| {
+            project.prepare_type_hierarchy(&buffer, 6, cx)
+        })
+        .await
+        .unwrap();
+    assert!(items.is_empty());
 }
 
-#[gpui::test(iterations = 10)]
-async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
+#[gpui::test]
+async fn test_semantic_tokens(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let mut language = Language::new(
         LanguageConfig {
-            name: "TypeScript".into(),
-            path_suffixes: vec!["ts".to_string()],
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
             ..Default::default()
         },
-        None,
+        Some(tree_sitter_rust::language()),
     );
-    let mut fake_language_servers = language.set_fake_lsp_adapter(Default::default()).await;
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                semantic_tokens_provider: Some(
+                    lsp::SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        lsp::SemanticTokensOptions {
+                            legend: lsp::SemanticTokensLegend {
+                                token_types: vec![
+                                    lsp::SemanticTokenType::FUNCTION,
+                                    lsp::SemanticTokenType::VARIABLE,
+                                ],
+                                token_modifiers: vec![
+                                    lsp::SemanticTokenModifier::DECLARATION,
+                                    lsp::SemanticTokenModifier::new("mutable"),
+                                ],
+                            },
+                            full: Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                            ..Default::default()
+                        },
+                    ),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         "/dir",
         json!({
-            "a.ts": "a",
+            "a.rs": "fn main() { let mut x = 1; }",
         }),
     )
     .await;
@@ -2532,50 +2673,1033 @@ async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
     let project = Project::test(fs, ["/dir".as_ref()], cx).await;
     project.update(cx, |project, _| project.languages.add(Arc::new(language)));
     let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::SemanticTokensFullRequest, _, _>(
+        |_, _| async move {
+            Ok(Some(lsp::SemanticTokensResult::Tokens(
+                lsp::SemanticTokens {
+                    result_id: Some("1".to_string()),
+                    data: vec![
+                        lsp::SemanticToken {
+                            delta_line: 0,
+                            delta_start: 3,
+                            length: 4,
+                            token_type: 0,
+                            token_modifiers_bitset: 0b01,
+                        },
+                        lsp::SemanticToken {
+                            delta_line: 0,
+                            delta_start: 17,
+                            length: 1,
+                            token_type: 1,
+                            token_modifiers_bitset: 0b11,
+                        },
+                    ],
+                },
+            )))
+        },
+    );
+    fake_server.handle_request::<lsp::request::SemanticTokensFullDeltaRequest, _, _>(
+        |params, _| async move {
+            assert_eq!(params.previous_result_id, "1");
+            Ok(Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(
+                lsp::SemanticTokensDelta {
+                    result_id: Some("2".to_string()),
+                    edits: vec![lsp::SemanticTokensEdit {
+                        start: 5,
+                        delete_count: 5,
+                        data: Some(vec![lsp::SemanticToken {
+                            delta_line: 0,
+                            delta_start: 17,
+                            length: 1,
+                            token_type: 1,
+                            token_modifiers_bitset: 0b01,
+                        }]),
+                    }],
+                },
+            )))
+        },
+    );
 
-    // Language server returns code actions that contain commands, and not edits.
-    let actions = project.update(cx, |project, cx| project.code_actions(&buffer, 0..0, cx));
-    fake_server
-        .handle_request::<lsp::request::CodeActionRequest, _, _>(|_, _| async move {
-            Ok(Some(vec![
-                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
-                    title: "The code action".into(),
-                    command: Some(lsp::Command {
-                        title: "The command".into(),
-                        command: "_the/command".into(),
-                        arguments: Some(vec![json!("the-argument")]),
-                    }),
-                    ..Default::default()
-                }),
-                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
-                    title: "two".into(),
-                    ..Default::default()
-                }),
-            ]))
+    let summarize = |tokens: Vec<SemanticToken>, cx: &mut gpui::TestAppContext| {
+        cx.update(|cx| {
+            let buffer = buffer.read(cx);
+            tokens
+                .into_iter()
+                .map(|token| {
+                    (
+                        token.range.to_offset(buffer),
+                        token.token_type.to_string(),
+                        token
+                            .token_modifiers
+                            .iter()
+                            .map(|modifier| modifier.to_string())
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>()
         })
-        .next()
-        .await;
-
-    let action = actions.await.unwrap()[0].clone();
-    let apply = project.update(cx, |project, cx| {
-        project.apply_code_action(buffer.clone(), action, true, cx)
-    });
+    };
 
-    // Resolving the code action does not populate its edits. In absence of
-    // edits, we must execute the given command.
-    fake_server.handle_request::<lsp::request::CodeActionResolveRequest, _, _>(
-        |action, _| async move { Ok(action) },
+    let tokens = project
+        .update(cx, |project, cx| project.semantic_tokens(&buffer, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        summarize(tokens, cx),
+        vec![
+            (
+                3..7,
+                "function".to_string(),
+                vec!["declaration".to_string()]
+            ),
+            (
+                20..21,
+                "variable".to_string(),
+                vec!["declaration".to_string(), "mutable".to_string()]
+            ),
+        ]
     );
 
-    // While executing the command, the language server sends the editor
-    // a `workspaceEdit` request.
-    fake_server
-        .handle_request::<lsp::request::ExecuteCommand, _, _>({
+    // The second request only asks for the changes since the first one.
+    let tokens = project
+        .update(cx, |project, cx| project.semantic_tokens(&buffer, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        summarize(tokens, cx),
+        vec![
+            (
+                3..7,
+                "function".to_string(),
+                vec!["declaration".to_string()]
+            ),
+            (
+                20..21,
+                "variable".to_string(),
+                vec!["declaration".to_string()]
+            ),
+        ]
+    );
+
+    // The tokens are forgotten along with the buffer.
+    drop(summarize);
+    cx.update(|_| drop(buffer));
+    cx.executor().run_until_parked();
+    project.update(cx, |project, _| assert!(project.semantic_tokens.is_empty()));
+}
+
+#[gpui::test]
+async fn test_folding_ranges(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                folding_range_provider: Some(lsp::FoldingRangeProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let text = "
+        use a::b;
+        use c::d;
+
+        fn main() {
+            let x = 1;
+        }
+    "
+    .unindent();
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.rs": text,
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::FoldingRangeRequest, _, _>(|params, _| async move {
+        assert_eq!(
+            params.text_document.uri,
+            lsp::Url::from_file_path("/dir/a.rs").unwrap(),
+        );
+        Ok(Some(vec![
+            lsp::FoldingRange {
+                start_line: 3,
+                start_character: Some(11),
+                end_line: 4,
+                ..Default::default()
+            },
+            lsp::FoldingRange {
+                start_line: 0,
+                end_line: 1,
+                kind: Some(lsp::FoldingRangeKind::Imports),
+                ..Default::default()
+            },
+            // Ranges within a single line can't be folded.
+            lsp::FoldingRange {
+                start_line: 4,
+                start_character: Some(4),
+                end_line: 4,
+                end_character: Some(13),
+                ..Default::default()
+            },
+        ]))
+    });
+
+    let ranges = project
+        .update(cx, |project, cx| project.folding_ranges(&buffer, cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            ranges
+                .into_iter()
+                .map(|range| range.to_point(buffer))
+                .collect::<Vec<_>>(),
+            vec![
+                Point::new(0, 9)..Point::new(1, 9),
+                Point::new(3, 11)..Point::new(4, 14),
+            ]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_pulled_document_diagnostics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                diagnostic_provider: Some(lsp::DiagnosticServerCapabilities::Options(
+                    lsp::DiagnosticOptions {
+                        inter_file_dependencies: true,
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.rs": "let a = b;",
+            "b.rs": "fn b() {}",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    let mut requests = fake_server.handle_request::<lsp::request::DocumentDiagnosticRequest, _, _>(
+        |params, _| async move {
+            let unchanged = |result_id: &str| {
+                lsp::DocumentDiagnosticReport::Unchanged(
+                    lsp::RelatedUnchangedDocumentDiagnosticReport {
+                        related_documents: None,
+                        unchanged_document_diagnostic_report:
+                            lsp::UnchangedDocumentDiagnosticReport {
+                                result_id: result_id.into(),
+                            },
+                    },
+                )
+            };
+            let report = match (
+                params.text_document.uri.path(),
+                params.previous_result_id.as_deref(),
+            ) {
+                ("/dir/a.rs", None) => {
+                    lsp::DocumentDiagnosticReport::Full(lsp::RelatedFullDocumentDiagnosticReport {
+                        related_documents: Some(
+                            [(
+                                lsp::Url::from_file_path("/dir/b.rs").unwrap(),
+                                lsp::DocumentDiagnosticReportKind::Full(
+                                    lsp::FullDocumentDiagnosticReport {
+                                        result_id: Some("b1".into()),
+                                        items: vec![lsp::Diagnostic {
+                                            range: lsp::Range::new(
+                                                lsp::Position::new(0, 3),
+                                                lsp::Position::new(0, 4),
+                                            ),
+                                            severity: Some(lsp::DiagnosticSeverity::WARNING),
+                                            message: "unused function 'b'".into(),
+                                            ..Default::default()
+                                        }],
+                                    },
+                                ),
+                            )]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        full_document_diagnostic_report: lsp::FullDocumentDiagnosticReport {
+                            result_id: Some("a1".into()),
+                            items: vec![lsp::Diagnostic {
+                                range: lsp::Range::new(
+                                    lsp::Position::new(0, 8),
+                                    lsp::Position::new(0, 9),
+                                ),
+                                severity: Some(lsp::DiagnosticSeverity::ERROR),
+                                message: "undefined variable 'b'".into(),
+                                ..Default::default()
+                            }],
+                        },
+                    })
+                }
+                ("/dir/a.rs", Some("a1")) => unchanged("a1"),
+                ("/dir/b.rs", Some("b1")) => unchanged("b1"),
+                (path, result_id) => panic!("unexpected request for {path} ({result_id:?})"),
+            };
+            Ok(lsp::DocumentDiagnosticReportResult::Report(report))
+        },
+    );
+
+    // The diagnostics are pulled once the server starts.
+    cx.executor().advance_clock(Duration::from_secs(1));
+    requests.next().await.unwrap();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| (entry.range, entry.diagnostic.message))
+                .collect::<Vec<_>>(),
+            [(
+                Point::new(0, 8)..Point::new(0, 9),
+                "undefined variable 'b'".to_string()
+            )]
+        );
+    });
+
+    // The diagnostics of related documents are reported as well.
+    let related_buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/b.rs", cx))
+        .await
+        .unwrap();
+    related_buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| entry.diagnostic.message)
+                .collect::<Vec<_>>(),
+            ["unused function 'b'"]
+        );
+    });
+
+    // The diagnostics published by the server are displayed along with the pulled ones.
+    fake_server.notify::<lsp::notification::PublishDiagnostics>(lsp::PublishDiagnosticsParams {
+        uri: lsp::Url::from_file_path("/dir/b.rs").unwrap(),
+        version: None,
+        diagnostics: vec![lsp::Diagnostic {
+            range: lsp::Range::new(lsp::Position::new(0, 7), lsp::Position::new(0, 9)),
+            severity: Some(lsp::DiagnosticSeverity::HINT),
+            message: "empty function body".into(),
+            ..Default::default()
+        }],
+    });
+    cx.executor().run_until_parked();
+    related_buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| entry.diagnostic.message)
+                .collect::<Vec<_>>(),
+            ["unused function 'b'", "empty function body"]
+        );
+    });
+
+    // After an edit, the server reports that the diagnostics are unchanged.
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    assert_eq!(requests.try_next().ok().flatten(), Some(()));
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| entry.range)
+                .collect::<Vec<_>>(),
+            [Point::new(1, 8)..Point::new(1, 9)]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_pulled_workspace_diagnostics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                diagnostic_provider: Some(lsp::DiagnosticServerCapabilities::Options(
+                    lsp::DiagnosticOptions {
+                        workspace_diagnostics: true,
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.rs": "let a = b;",
+            "b.rs": "let c = d;",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::DocumentDiagnosticRequest, _, _>(
+        |params, _| async move {
+            Ok(lsp::DocumentDiagnosticReportResult::Report(
+                lsp::DocumentDiagnosticReport::Unchanged(
+                    lsp::RelatedUnchangedDocumentDiagnosticReport {
+                        related_documents: None,
+                        unchanged_document_diagnostic_report:
+                            lsp::UnchangedDocumentDiagnosticReport {
+                                result_id: params.previous_result_id.unwrap_or_default(),
+                            },
+                    },
+                ),
+            ))
+        },
+    );
+    let report = |path: &str, result_id: &str, message: &str| {
+        lsp::WorkspaceDocumentDiagnosticReport::Full(lsp::WorkspaceFullDocumentDiagnosticReport {
+            uri: lsp::Url::from_file_path(path).unwrap(),
+            version: None,
+            full_document_diagnostic_report: lsp::FullDocumentDiagnosticReport {
+                result_id: Some(result_id.into()),
+                items: vec![lsp::Diagnostic {
+                    range: lsp::Range::new(lsp::Position::new(0, 8), lsp::Position::new(0, 9)),
+                    severity: Some(lsp::DiagnosticSeverity::ERROR),
+                    message: message.into(),
+                    ..Default::default()
+                }],
+            },
+        })
+    };
+    let server = fake_server.server.clone();
+    let previous_result_ids = Arc::new(Mutex::new(Vec::new()));
+    let mut requests = fake_server
+        .handle_request::<lsp::request::WorkspaceDiagnosticRequest, _, _>({
+            let previous_result_ids = previous_result_ids.clone();
+            move |params, _| {
+                previous_result_ids.lock().push(
+                    params
+                        .previous_result_ids
+                        .into_iter()
+                        .map(|id| (id.uri.path().to_string(), id.value))
+                        .collect::<HashMap<_, _>>(),
+                );
+                // The diagnostics of `b.rs` are streamed as a partial result.
+                server
+                    .notify::<ProgressNotification>(ProgressNotificationParams {
+                        token: params.partial_result_params.partial_result_token.unwrap(),
+                        value: serde_json::to_value(lsp::WorkspaceDiagnosticReportPartialResult {
+                            items: vec![report("/dir/b.rs", "b1", "undefined variable 'd'")],
+                        })
+                        .unwrap(),
+                    })
+                    .unwrap();
+                let items = vec![report("/dir/a.rs", "a2", "undefined variable 'b'")];
+                async move {
+                    Ok(lsp::WorkspaceDiagnosticReportResult::Report(
+                        lsp::WorkspaceDiagnosticReport { items },
+                    ))
+                }
+            }
+        });
+
+    // The workspace diagnostics are pulled once the server starts.
+    requests.next().await.unwrap();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| entry.diagnostic.message)
+                .collect::<Vec<_>>(),
+            ["undefined variable 'b'"]
+        );
+    });
+    let other_buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/b.rs", cx))
+        .await
+        .unwrap();
+    other_buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            buffer
+                .snapshot()
+                .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+                .map(|entry| entry.diagnostic.message)
+                .collect::<Vec<_>>(),
+            ["undefined variable 'd'"]
+        );
+    });
+
+    // Saving a buffer pulls the workspace diagnostics again, passing the ids of the previous
+    // reports.
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    requests.next().await.unwrap();
+    cx.executor().run_until_parked();
+    assert_eq!(
+        previous_result_ids.lock().last().unwrap(),
+        &[
+            ("/dir/a.rs".to_string(), "a2".to_string()),
+            ("/dir/b.rs".to_string(), "b1".to_string()),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>()
+    );
+}
+
+#[gpui::test]
+async fn test_code_lens(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                code_lens_provider: Some(lsp::CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
+                execute_command_provider: Some(lsp::ExecuteCommandOptions {
+                    commands: vec!["run".to_string()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let text = "
+        #[test]
+        fn test_a() {}
+
+        fn b() {}
+    "
+    .unindent();
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "a.rs": text })).await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::CodeLensRequest, _, _>(|params, _| async move {
+        assert_eq!(
+            params.text_document.uri,
+            lsp::Url::from_file_path("/dir/a.rs").unwrap(),
+        );
+        Ok(Some(vec![
+            lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(1, 3), lsp::Position::new(1, 9)),
+                command: Some(lsp::Command {
+                    title: "Run test".into(),
+                    command: "run".into(),
+                    arguments: Some(vec![json!("test_a")]),
+                }),
+                data: None,
+            },
+            lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(3, 3), lsp::Position::new(3, 4)),
+                command: None,
+                data: Some(json!({ "references": "b" })),
+            },
+            lsp::CodeLens {
+                range: lsp::Range::new(lsp::Position::new(3, 3), lsp::Position::new(3, 4)),
+                command: None,
+                data: None,
+            },
+        ]))
+    });
+    fake_server.handle_request::<lsp::request::CodeLensResolve, _, _>(|lens, _| async move {
+        let Some(data) = lens.data.clone() else {
+            return Err(anyhow::anyhow!("missing data"));
+        };
+        assert_eq!(data, json!({ "references": "b" }));
+        Ok(lsp::CodeLens {
+            command: Some(lsp::Command {
+                title: "2 references".into(),
+                command: "references".into(),
+                arguments: None,
+            }),
+            ..lens
+        })
+    });
+
+    // Lenses are only resolved on demand.
+    let lenses = project
+        .update(cx, |project, cx| project.code_lens(&buffer, cx))
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(
+            lenses
+                .iter()
+                .map(|lens| (
+                    lens.range.to_point(buffer),
+                    lens.lsp_lens
+                        .command
+                        .as_ref()
+                        .map(|command| command.title.as_str()),
+                    lens.executable
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (Point::new(1, 3)..Point::new(1, 9), Some("Run test"), true),
+                (Point::new(3, 3)..Point::new(3, 4), None, false),
+                (Point::new(3, 3)..Point::new(3, 4), None, false),
+            ]
+        );
+    });
+
+    let resolved_lens = project
+        .update(cx, |project, cx| {
+            project.resolve_code_lens(buffer.clone(), lenses[1].clone(), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        resolved_lens.lsp_lens.command.as_ref().unwrap().title,
+        "2 references"
+    );
+    // The server didn't advertise the resolved lens's command.
+    assert!(!resolved_lens.executable);
+    let result = project
+        .update(cx, |project, cx| {
+            project.resolve_code_lens(buffer.clone(), lenses[2].clone(), cx)
+        })
+        .await;
+    assert!(result.is_err());
+
+    let mut commands =
+        fake_server.handle_request::<lsp::request::ExecuteCommand, _, _>(|params, _| async move {
+            assert_eq!(params.command, "run");
+            assert_eq!(params.arguments, [json!("test_a")]);
+            Ok(None)
+        });
+    let transaction = project
+        .update(cx, |project, cx| {
+            project.execute_code_lens(buffer.clone(), lenses[0].clone(), cx)
+        })
+        .await
+        .unwrap();
+    commands.next().await.unwrap();
+    assert!(transaction.0.is_empty());
+
+    // Commands that the server didn't advertise aren't sent to it.
+    let result = project
+        .update(cx, |project, cx| {
+            project.execute_code_lens(buffer.clone(), resolved_lens, cx)
+        })
+        .await;
+    assert!(result.is_err());
+    cx.executor().run_until_parked();
+    assert!(commands.try_next().is_err());
+}
+
+#[gpui::test]
+async fn test_linked_editing_ranges(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                linked_editing_range_provider: Some(
+                    lsp::LinkedEditingRangeServerCapabilities::Simple(true),
+                ),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({ "a.rs": "fn foo() { foo(); }" }))
+        .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::LinkedEditingRange, _, _>(|params, _| async move {
+        let position = params.text_document_position_params.position;
+        if position.character < 3 || position.character > 6 {
+            return Ok(None);
+        }
+        Ok(Some(lsp::LinkedEditingRanges {
+            ranges: vec![
+                lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(0, 6)),
+                lsp::Range::new(lsp::Position::new(0, 11), lsp::Position::new(0, 14)),
+            ],
+            word_pattern: Some("[a-z]+".to_string()),
+        }))
+    });
+
+    let ranges = project
+        .update(cx, |project, cx| {
+            project.linked_editing_ranges(&buffer, Point::new(0, 4), cx)
+        })
+        .await
+        .unwrap();
+    let word_pattern = ranges.word_pattern.unwrap();
+    assert!(word_pattern.matches("foo"));
+    assert!(!word_pattern.matches("foo bar"));
+    // The ranges don't grow when text is inserted at their boundaries.
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(3..3, "x"), (6..6, "x"), (14..14, "x")], None, cx);
+        assert_eq!(
+            ranges
+                .ranges
+                .iter()
+                .map(|range| range.to_offset(buffer))
+                .collect::<Vec<_>>(),
+            [4..7, 13..16]
+        );
+    });
+
+    // Without a language server response, the language's syntax is used instead, which doesn't
+    // link any ranges in Rust.
+    let ranges = project
+        .update(cx, |project, cx| {
+            project.linked_editing_ranges(&buffer, Point::new(0, 0), cx)
+        })
+        .await
+        .unwrap();
+    assert!(ranges.ranges.is_empty());
+}
+
+#[gpui::test]
+async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "TypeScript".into(),
+            path_suffixes: vec!["ts".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_typescript::language_typescript()),
+    );
+    let mut fake_language_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![":".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.ts": "",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_language_servers.next().await.unwrap();
+
+    let text = "let a = b.fqn";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len(), cx)
+    });
+
+    fake_server
+        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "fullyQualifiedName?".into(),
+                    insert_text: Some("fullyQualifiedName".into()),
+                    ..Default::default()
+                },
+            ])))
+        })
+        .next()
+        .await;
+    let completions = completions.await.unwrap();
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "fullyQualifiedName");
+    assert_eq!(
+        completions[0].old_range.to_offset(&snapshot),
+        text.len() - 3..text.len()
+    );
+
+    let text = "let a = \"atoms/cmp\"";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len() - 1, cx)
+    });
+
+    fake_server
+        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "component".into(),
+                    ..Default::default()
+                },
+            ])))
+        })
+        .next()
+        .await;
+    let completions = completions.await.unwrap();
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "component");
+    assert_eq!(
+        completions[0].old_range.to_offset(&snapshot),
+        text.len() - 4..text.len() - 1
+    );
+}
+
+#[gpui::test]
+async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "TypeScript".into(),
+            path_suffixes: vec!["ts".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_typescript::language_typescript()),
+    );
+    let mut fake_language_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![":".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.ts": "",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_language_servers.next().await.unwrap();
+
+    let text = "let a = b.fqn";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len(), cx)
+    });
+
+    fake_server
+        .handle_request::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "fullyQualifiedName?".into(),
+                    insert_text: Some("fully\rQualified\r\nName".into()),
+                    ..Default::default()
+                },
+            ])))
+        })
+        .next()
+        .await;
+    let completions = completions.await.unwrap();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "fully\nQualified\nName");
+}
+
+#[gpui::test(iterations = 10)]
+async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "TypeScript".into(),
+            path_suffixes: vec!["ts".to_string()],
+            ..Default::default()
+        },
+        None,
+    );
+    let mut fake_language_servers = language.set_fake_lsp_adapter(Default::default()).await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.ts": "a",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/a.ts", cx))
+        .await
+        .unwrap();
+
+    let fake_server = fake_language_servers.next().await.unwrap();
+
+    // Language server returns code actions that contain commands, and not edits.
+    let actions = project.update(cx, |project, cx| project.code_actions(&buffer, 0..0, cx));
+    fake_server
+        .handle_request::<lsp::request::CodeActionRequest, _, _>(|_, _| async move {
+            Ok(Some(vec![
+                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                    title: "The code action".into(),
+                    command: Some(lsp::Command {
+                        title: "The command".into(),
+                        command: "_the/command".into(),
+                        arguments: Some(vec![json!("the-argument")]),
+                    }),
+                    ..Default::default()
+                }),
+                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                    title: "two".into(),
+                    ..Default::default()
+                }),
+            ]))
+        })
+        .next()
+        .await;
+
+    let action = actions.await.unwrap()[0].clone();
+    let apply = project.update(cx, |project, cx| {
+        project.apply_code_action(buffer.clone(), action, true, cx)
+    });
+
+    // Resolving the code action does not populate its edits. In absence of
+    // edits, we must execute the given command.
+    fake_server.handle_request::<lsp::request::CodeActionResolveRequest, _, _>(
+        |action, _| async move { Ok(action) },
+    );
+
+    // While executing the command, the language server sends the editor
+    // a `workspaceEdit` request.
+    fake_server
+        .handle_request::<lsp::request::ExecuteCommand, _, _>({
             let fake = fake_server.clone();
             move |params, _| {
                 assert_eq!(params.command, "_the/command");
@@ -3837,6 +4961,179 @@ async fn test_search(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_search_multiline_regex(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "fn one() {\n    1\n}\n",
+            "two.rs": "fn two() { 1 }\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::regex(r"\{\s+1\s+\}", false, true, false, Vec::new(), Vec::new()).unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            ("one.rs".to_string(), vec![9..18]),
+            ("two.rs".to_string(), vec![9..14])
+        ])
+    );
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::regex(r"^\s+1$", false, true, false, Vec::new(), Vec::new()).unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([("one.rs".to_string(), vec![11..16])])
+    );
+}
+
+#[gpui::test]
+async fn test_search_structural(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "fn one() { foo(1); bar(2); }",
+            "two.rs": "fn two() { foo(foo(3)); }",
+            "three.txt": "foo(4)",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| {
+        project.languages.add(Arc::new(Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                path_suffixes: vec!["rs".to_string()],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        )));
+    });
+
+    let query = SearchQuery::structural(
+        r#"(call_expression
+            function: (identifier) @name (#eq? @name "foo")
+            arguments: (arguments) @args) @match"#,
+        false,
+        Vec::new(),
+        Vec::new(),
+    )
+    .unwrap()
+    .with_replacement("baz$args".to_string());
+
+    // Nested matches are covered by the outermost one.
+    assert_eq!(
+        search(&project, query.clone(), cx).await.unwrap(),
+        HashMap::from_iter([
+            ("one.rs".to_string(), vec![11..17]),
+            ("two.rs".to_string(), vec![11..22])
+        ])
+    );
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/two.rs", cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, _| buffer.parsing_idle()).await;
+    buffer.update(cx, |buffer, _| {
+        let snapshot = buffer.snapshot();
+        assert_eq!(
+            query.replacement_for_range(&snapshot, 11..22).as_deref(),
+            Some("baz(foo(3))")
+        );
+        assert_eq!(
+            query.replacement_for_range(&snapshot, 15..21).as_deref(),
+            Some("baz(3)")
+        );
+        assert_eq!(query.replacement_for_range(&snapshot, 12..18), None);
+    });
+
+    // Only the files of languages with a grammar that contain the strings compared to captures
+    // are candidates.
+    let languages = project.read_with(cx, |project, _| project.languages().clone());
+    let candidate_query = query
+        .clone()
+        .with_grammar_path_suffixes(languages.grammar_path_suffixes());
+    assert!(candidate_query.file_matches(Some("one.rs".as_ref())));
+    assert!(!candidate_query.file_matches(Some("three.txt".as_ref())));
+    assert!(query.detect("foo(4)".as_bytes()).unwrap());
+    assert!(!query.detect("bar(4)".as_bytes()).unwrap());
+
+    // Patterns that don't compile for the grammar of any searched file are reported once the
+    // search is done.
+    assert_eq!(query.structural_error(), None);
+    let unknown_node_query =
+        SearchQuery::structural("(no_such_node) @match", false, Vec::new(), Vec::new()).unwrap();
+    assert_eq!(unknown_node_query.structural_error(), None);
+    assert!(search(&project, unknown_node_query.clone(), cx)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(unknown_node_query.structural_error().is_some());
+
+    assert!(SearchQuery::structural("(call_expression", false, Vec::new(), Vec::new()).is_err());
+}
+
+#[gpui::test]
+async fn test_search_structural_with_unloaded_language(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "one.rs": "fn one() { foo(1); }",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    // Registered languages are only loaded once a file needs them, so the search has to wait
+    // for the language of each file to be loaded and assigned before parsing it.
+    let languages = project.update(cx, |project, _| project.languages().clone());
+    languages.register(
+        "/some/path",
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".into()],
+            ..Default::default()
+        },
+        tree_sitter_rust::language(),
+        vec![],
+        |_| Default::default(),
+    );
+
+    let query = SearchQuery::structural(
+        r#"(call_expression function: (identifier) @name (#eq? @name "foo")) @match"#,
+        false,
+        Vec::new(),
+        Vec::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        search(&project, query.clone(), cx).await.unwrap(),
+        HashMap::from_iter([("one.rs".to_string(), vec![11..17])])
+    );
+    assert_eq!(query.structural_error(), None);
+}
+
 #[gpui::test]
 async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -0,0 +1,449 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use collections::HashMap;
+use futures::{channel::mpsc, StreamExt};
+use gpui::{Model, ModelContext, Task};
+use language::{Buffer, CachedLspAdapter};
+use lsp::{LanguageServer, LanguageServerId};
+use serde::{Deserialize, Serialize};
+use util::ResultExt;
+
+use crate::{File, LanguageServerState, Project};
+
+/// How long to wait after an edit before pulling the diagnostics of a document.
+const PULL_DIAGNOSTICS_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(125);
+
+/// The state of the diagnostics pulled from the language servers, as opposed to the ones the
+/// servers publish on their own.
+#[derive(Default)]
+pub(crate) struct PullDiagnostics {
+    /// The id of the last report of each document, which lets servers report that the
+    /// diagnostics of a document are unchanged.
+    result_ids: HashMap<LanguageServerId, HashMap<lsp::Url, String>>,
+    /// The diagnostics last pulled and published for each document. Servers that support
+    /// pulling diagnostics may still publish some, and both sets are displayed together.
+    documents: HashMap<LanguageServerId, HashMap<lsp::Url, DocumentDiagnostics>>,
+    document_tasks: HashMap<u64, Task<()>>,
+    workspace_pulls: HashMap<LanguageServerId, WorkspaceDiagnosticsPull>,
+}
+
+#[derive(Default)]
+struct DocumentDiagnostics {
+    pulled: Vec<lsp::Diagnostic>,
+    published: Vec<lsp::Diagnostic>,
+}
+
+struct WorkspaceDiagnosticsPull {
+    trigger: mpsc::UnboundedSender<()>,
+    partial_result_token: String,
+    _task: Task<()>,
+}
+
+/// The `$/progress` notification, which carries either the progress of some work or a partial
+/// result of a request, depending on the token.
+pub(crate) enum ProgressNotification {}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ProgressNotificationParams {
+    pub token: lsp::ProgressToken,
+    pub value: serde_json::Value,
+}
+
+impl lsp::notification::Notification for ProgressNotification {
+    type Params = ProgressNotificationParams;
+    const METHOD: &'static str = "$/progress";
+}
+
+impl Project {
+    /// Pulls the diagnostics of the given buffer from each of its language servers that supports
+    /// `textDocument/diagnostic`, after a short delay to coalesce consecutive edits.
+    pub(crate) fn pull_document_diagnostics(
+        &mut self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if !self.is_local() {
+            return;
+        }
+        let buffer = buffer.read(cx);
+        let buffer_id = buffer.remote_id();
+        let Some(file) = File::from_dyn(buffer.file()).and_then(|file| file.as_local()) else {
+            return;
+        };
+        let uri = lsp::Url::from_file_path(file.abs_path(cx)).unwrap();
+        let servers = self
+            .language_servers_for_buffer(buffer, cx)
+            .filter(|(_, server)| diagnostic_options(server.capabilities()).is_some())
+            .map(|(adapter, server)| (adapter.clone(), server.clone()))
+            .collect::<Vec<_>>();
+        if servers.is_empty() {
+            return;
+        }
+
+        let task = cx.spawn(move |this, mut cx| async move {
+            cx.background_executor()
+                .timer(PULL_DIAGNOSTICS_DEBOUNCE_TIMEOUT)
+                .await;
+            for (adapter, server) in servers {
+                let server_id = server.server_id();
+                let Some(options) = diagnostic_options(server.capabilities()) else {
+                    continue;
+                };
+                let Ok((previous_result_id, version)) = this.update(&mut cx, |this, _| {
+                    let previous_result_id = this
+                        .pull_diagnostics
+                        .result_ids
+                        .get(&server_id)
+                        .and_then(|result_ids| result_ids.get(&uri))
+                        .cloned();
+                    // The version that the server computes the diagnostics for.
+                    let version = this
+                        .buffer_snapshots
+                        .get(&buffer_id)
+                        .and_then(|snapshots| snapshots.get(&server_id))
+                        .and_then(|snapshots| snapshots.last())
+                        .map(|snapshot| snapshot.version);
+                    (previous_result_id, version)
+                }) else {
+                    return;
+                };
+
+                let report = server
+                    .request::<lsp::request::DocumentDiagnosticRequest>(
+                        lsp::DocumentDiagnosticParams {
+                            text_document: lsp::TextDocumentIdentifier::new(uri.clone()),
+                            identifier: options.identifier.clone(),
+                            previous_result_id,
+                            work_done_progress_params: Default::default(),
+                            partial_result_params: Default::default(),
+                        },
+                    )
+                    .await;
+                let Some(report) = report.log_err() else {
+                    continue;
+                };
+
+                let updated = this.update(&mut cx, |this, cx| {
+                    let related_documents = match report {
+                        lsp::DocumentDiagnosticReportResult::Report(
+                            lsp::DocumentDiagnosticReport::Full(report),
+                        ) => {
+                            this.apply_diagnostic_report(
+                                &adapter,
+                                server_id,
+                                uri.clone(),
+                                version,
+                                lsp::DocumentDiagnosticReportKind::Full(
+                                    report.full_document_diagnostic_report,
+                                ),
+                                cx,
+                            );
+                            report.related_documents
+                        }
+                        lsp::DocumentDiagnosticReportResult::Report(
+                            lsp::DocumentDiagnosticReport::Unchanged(report),
+                        ) => {
+                            this.apply_diagnostic_report(
+                                &adapter,
+                                server_id,
+                                uri.clone(),
+                                version,
+                                lsp::DocumentDiagnosticReportKind::Unchanged(
+                                    report.unchanged_document_diagnostic_report,
+                                ),
+                                cx,
+                            );
+                            report.related_documents
+                        }
+                        lsp::DocumentDiagnosticReportResult::Partial(report) => {
+                            report.related_documents
+                        }
+                    };
+                    for (uri, report) in related_documents.into_iter().flatten() {
+                        this.apply_diagnostic_report(&adapter, server_id, uri, None, report, cx);
+                    }
+                });
+                if updated.is_err() {
+                    return;
+                }
+            }
+        });
+        self.pull_diagnostics.document_tasks.insert(buffer_id, task);
+    }
+
+    pub(crate) fn cancel_document_diagnostics_pull(&mut self, buffer_id: u64) {
+        self.pull_diagnostics.document_tasks.remove(&buffer_id);
+    }
+
+    /// Starts pulling the diagnostics of the whole workspace from the given server, if it
+    /// supports `workspace/diagnostic`. The diagnostics are pulled again every time
+    /// [`Self::refresh_workspace_diagnostics`] is called.
+    pub(crate) fn start_workspace_diagnostics_pull(
+        &mut self,
+        adapter: Arc<CachedLspAdapter>,
+        server: Arc<LanguageServer>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let Some(options) = diagnostic_options(server.capabilities()) else {
+            return;
+        };
+        if !options.workspace_diagnostics {
+            return;
+        }
+
+        let server_id = server.server_id();
+        let partial_result_token = format!("workspace-diagnostics-{}", server_id.0);
+        let (trigger, mut triggers) = mpsc::unbounded();
+        trigger.unbounded_send(()).ok();
+        let task = cx.spawn({
+            let partial_result_token = partial_result_token.clone();
+            move |this, mut cx| async move {
+                while triggers.next().await.is_some() {
+                    // Coalesce the triggers received while the previous pull was running.
+                    while let Ok(Some(())) = triggers.try_next() {}
+
+                    let Ok(previous_result_ids) = this.update(&mut cx, |this, _| {
+                        this.pull_diagnostics
+                            .result_ids
+                            .get(&server_id)
+                            .into_iter()
+                            .flatten()
+                            .map(|(uri, result_id)| lsp::PreviousResultId {
+                                uri: uri.clone(),
+                                value: result_id.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                    }) else {
+                        return;
+                    };
+
+                    // Servers may hold this request open until the diagnostics change, streaming
+                    // them through partial results in the meantime.
+                    let report = server
+                        .request::<lsp::request::WorkspaceDiagnosticRequest>(
+                            lsp::WorkspaceDiagnosticParams {
+                                identifier: options.identifier.clone(),
+                                previous_result_ids,
+                                work_done_progress_params: Default::default(),
+                                partial_result_params: lsp::PartialResultParams {
+                                    partial_result_token: Some(lsp::NumberOrString::String(
+                                        partial_result_token.clone(),
+                                    )),
+                                },
+                            },
+                        )
+                        .await;
+                    let Some(report) = report.log_err() else {
+                        continue;
+                    };
+                    let items = match report {
+                        lsp::WorkspaceDiagnosticReportResult::Report(report) => report.items,
+                        lsp::WorkspaceDiagnosticReportResult::Partial(report) => report.items,
+                    };
+                    let updated = this.update(&mut cx, |this, cx| {
+                        this.apply_workspace_diagnostic_reports(&adapter, server_id, items, cx)
+                    });
+                    if updated.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        self.pull_diagnostics.workspace_pulls.insert(
+            server_id,
+            WorkspaceDiagnosticsPull {
+                trigger,
+                partial_result_token,
+                _task: task,
+            },
+        );
+    }
+
+    /// Pulls the diagnostics of the workspace again from the given server, once the pending
+    /// pull completes.
+    pub(crate) fn refresh_workspace_diagnostics(&mut self, server_id: LanguageServerId) {
+        if let Some(pull) = self.pull_diagnostics.workspace_pulls.get(&server_id) {
+            pull.trigger.unbounded_send(()).ok();
+        }
+    }
+
+    /// Pulls the diagnostics of all the documents and of the workspace again, as requested by
+    /// a `workspace/diagnostic/refresh` request.
+    pub(crate) fn refresh_pulled_diagnostics(
+        &mut self,
+        server_id: LanguageServerId,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let buffers = self
+            .opened_buffers
+            .values()
+            .filter_map(|buffer| buffer.upgrade())
+            .filter(|buffer| {
+                self.language_servers_for_buffer(buffer.read(cx), cx)
+                    .any(|(_, server)| server.server_id() == server_id)
+            })
+            .collect::<Vec<_>>();
+        for buffer in buffers {
+            self.pull_document_diagnostics(&buffer, cx);
+        }
+        self.refresh_workspace_diagnostics(server_id);
+    }
+
+    pub(crate) fn stop_pulling_diagnostics(&mut self, server_id: LanguageServerId) {
+        self.pull_diagnostics.result_ids.remove(&server_id);
+        self.pull_diagnostics.documents.remove(&server_id);
+        self.pull_diagnostics.workspace_pulls.remove(&server_id);
+    }
+
+    /// Handles a `$/progress` notification carrying a partial result of a workspace diagnostics
+    /// pull. Returns `false` if the notification reports the progress of some work instead.
+    pub(crate) fn on_workspace_diagnostics_partial_result(
+        &mut self,
+        server_id: LanguageServerId,
+        params: &ProgressNotificationParams,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        let Some(pull) = self.pull_diagnostics.workspace_pulls.get(&server_id) else {
+            return false;
+        };
+        if params.token != lsp::NumberOrString::String(pull.partial_result_token.clone()) {
+            return false;
+        }
+
+        let Some(LanguageServerState::Running { adapter, .. }) =
+            self.language_servers.get(&server_id)
+        else {
+            return true;
+        };
+        let adapter = adapter.clone();
+        if let Some(report) = serde_json::from_value::<lsp::WorkspaceDiagnosticReportPartialResult>(
+            params.value.clone(),
+        )
+        .log_err()
+        {
+            self.apply_workspace_diagnostic_reports(&adapter, server_id, report.items, cx);
+        }
+        true
+    }
+
+    fn apply_workspace_diagnostic_reports(
+        &mut self,
+        adapter: &CachedLspAdapter,
+        server_id: LanguageServerId,
+        reports: Vec<lsp::WorkspaceDocumentDiagnosticReport>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        for report in reports {
+            let (uri, version, report) = match report {
+                lsp::WorkspaceDocumentDiagnosticReport::Full(report) => (
+                    report.uri,
+                    report.version,
+                    lsp::DocumentDiagnosticReportKind::Full(report.full_document_diagnostic_report),
+                ),
+                lsp::WorkspaceDocumentDiagnosticReport::Unchanged(report) => (
+                    report.uri,
+                    report.version,
+                    lsp::DocumentDiagnosticReportKind::Unchanged(
+                        report.unchanged_document_diagnostic_report,
+                    ),
+                ),
+            };
+            let version = version.and_then(|version| i32::try_from(version).ok());
+            self.apply_diagnostic_report(adapter, server_id, uri, version, report, cx);
+        }
+    }
+
+    /// Updates the diagnostics published by a server, along with the ones pulled from it if it
+    /// supports pulling diagnostics, so that neither set replaces the other.
+    pub(crate) fn update_published_diagnostics(
+        &mut self,
+        server_id: LanguageServerId,
+        mut params: lsp::PublishDiagnosticsParams,
+        disk_based_sources: &[String],
+        cx: &mut ModelContext<Self>,
+    ) -> Result<()> {
+        let supports_pull = self
+            .language_server_for_id(server_id)
+            .map_or(false, |server| {
+                diagnostic_options(server.capabilities()).is_some()
+            });
+        if supports_pull {
+            let document = self
+                .pull_diagnostics
+                .documents
+                .entry(server_id)
+                .or_default()
+                .entry(params.uri.clone())
+                .or_default();
+            document.published = params.diagnostics;
+            params.diagnostics = document.all();
+        }
+        self.update_diagnostics(server_id, params, disk_based_sources, cx)
+    }
+
+    fn apply_diagnostic_report(
+        &mut self,
+        adapter: &CachedLspAdapter,
+        server_id: LanguageServerId,
+        uri: lsp::Url,
+        version: Option<i32>,
+        report: lsp::DocumentDiagnosticReportKind,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let result_ids = self
+            .pull_diagnostics
+            .result_ids
+            .entry(server_id)
+            .or_default();
+        match report {
+            lsp::DocumentDiagnosticReportKind::Full(report) => {
+                match report.result_id {
+                    Some(result_id) => result_ids.insert(uri.clone(), result_id),
+                    None => result_ids.remove(&uri),
+                };
+                let mut params = lsp::PublishDiagnosticsParams {
+                    uri,
+                    diagnostics: report.items,
+                    version,
+                };
+                adapter.process_diagnostics(&mut params);
+                let document = self
+                    .pull_diagnostics
+                    .documents
+                    .entry(server_id)
+                    .or_default()
+                    .entry(params.uri.clone())
+                    .or_default();
+                document.pulled = params.diagnostics;
+                params.diagnostics = document.all();
+                self.update_diagnostics(
+                    server_id,
+                    params,
+                    &adapter.disk_based_diagnostic_sources,
+                    cx,
+                )
+                .log_err();
+            }
+            lsp::DocumentDiagnosticReportKind::Unchanged(report) => {
+                result_ids.insert(uri, report.result_id);
+            }
+        }
+    }
+}
+
+impl DocumentDiagnostics {
+    fn all(&self) -> Vec<lsp::Diagnostic> {
+        self.pulled.iter().chain(&self.published).cloned().collect()
+    }
+}
+
+fn diagnostic_options(capabilities: &lsp::ServerCapabilities) -> Option<&lsp::DiagnosticOptions> {
+    match capabilities.diagnostic_provider.as_ref()? {
+        lsp::DiagnosticServerCapabilities::Options(options) => Some(options),
+        lsp::DiagnosticServerCapabilities::RegistrationOptions(options) => {
+            Some(&options.diagnostic_options)
+        }
+    }
+}
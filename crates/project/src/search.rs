@@ -1,18 +1,28 @@
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use anyhow::{Context, Result};
 use client::proto;
+use collections::{HashMap, HashSet};
 use itertools::Itertools;
-use language::{char_kind, BufferSnapshot};
+use language::{char_kind, BufferSnapshot, Grammar};
+use parking_lot::Mutex;
 use regex::{Regex, RegexBuilder};
+use regex_syntax::hir::{Class, Hir, HirKind};
 use smol::future::yield_now;
 use std::{
     borrow::Cow,
+    cmp::Reverse,
+    fmt,
     io::{BufRead, BufReader, Read},
     ops::Range,
     path::Path,
     sync::Arc,
 };
-use util::paths::PathMatcher;
+use tree_sitter::{Query, QueryMatch};
+use util::paths::{PathExt, PathMatcher};
+
+/// The name of the capture that marks the range matched by a structural query. Without it, a
+/// match spans all of its captures.
+const STRUCTURAL_MATCH_CAPTURE: &str = "match";
 
 #[derive(Clone, Debug)]
 pub struct SearchInputs {
@@ -52,6 +62,59 @@ pub enum SearchQuery {
         include_ignored: bool,
         inner: SearchInputs,
     },
+
+    /// A tree-sitter query, matched against the syntax trees of buffers. Its captures can be
+    /// referred to as `$name` in the replacement.
+    Structural {
+        queries: StructuralQueries,
+        /// Searches for the strings that a file has to contain to match, if there are any.
+        literals: Option<Arc<AhoCorasick>>,
+        /// The path suffixes of the languages that have a grammar, once known. Other files can't
+        /// be parsed, so they can't match.
+        path_suffixes: Option<Arc<HashSet<String>>>,
+        replacement: Option<String>,
+        include_ignored: bool,
+        inner: SearchInputs,
+    },
+}
+
+/// The compiled queries of a structural search, or the errors compiling them, by grammar. The
+/// same pattern yields a different query for each grammar, and is invalid for grammars lacking
+/// the node kinds that it refers to.
+#[derive(Clone, Default)]
+pub struct StructuralQueries(Arc<Mutex<HashMap<usize, Result<Arc<Query>, String>>>>);
+
+impl fmt::Debug for StructuralQueries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StructuralQueries").finish_non_exhaustive()
+    }
+}
+
+impl StructuralQueries {
+    fn for_grammar(&self, source: &str, grammar: &Grammar) -> Result<Arc<Query>, String> {
+        self.0
+            .lock()
+            .entry(grammar.id())
+            .or_insert_with(|| {
+                Query::new(&grammar.ts_language, source)
+                    .map(Arc::new)
+                    .map_err(|error| error.to_string())
+            })
+            .clone()
+    }
+
+    /// The error compiling the query for the first grammar, if it was compiled for some grammars
+    /// and none of them accepted it.
+    fn error(&self) -> Option<String> {
+        let queries = self.0.lock();
+        if queries.values().any(|query| query.is_ok()) {
+            return None;
+        }
+        queries
+            .iter()
+            .min_by_key(|(grammar_id, _)| **grammar_id)
+            .and_then(|(_, query)| query.clone().err())
+    }
 }
 
 impl SearchQuery {
@@ -100,7 +163,7 @@ impl SearchQuery {
             query = word_query
         }
 
-        let multiline = query.contains('\n') || query.contains("\\n");
+        let multiline = regex_syntax::parse(&query).map_or(false, |hir| can_match_newline(&hir));
         let regex = RegexBuilder::new(&query)
             .case_insensitive(!case_sensitive)
             .multi_line(multiline)
@@ -121,8 +184,47 @@ impl SearchQuery {
         })
     }
 
+    /// Creates a structural query from a tree-sitter query pattern, such as
+    /// `(call_expression function: (identifier) @name (#eq? @name "foo")) @match`.
+    ///
+    /// A match covers the node captured as `@match`, or all of the captures of the pattern if it
+    /// has no such capture, so patterns without captures don't produce matches.
+    pub fn structural(
+        query: impl ToString,
+        include_ignored: bool,
+        files_to_include: Vec<PathMatcher>,
+        files_to_exclude: Vec<PathMatcher>,
+    ) -> Result<Self> {
+        let query = query.to_string();
+        validate_structural_pattern(&query)?;
+        let literals = match structural_literals(&query) {
+            Some(literals) => Some(Arc::new(AhoCorasick::new(&literals)?)),
+            None => None,
+        };
+        let inner = SearchInputs {
+            query: query.into(),
+            files_to_exclude,
+            files_to_include,
+        };
+        Ok(Self::Structural {
+            queries: StructuralQueries::default(),
+            literals,
+            path_suffixes: None,
+            replacement: None,
+            include_ignored,
+            inner,
+        })
+    }
+
     pub fn from_proto(message: proto::SearchProject) -> Result<Self> {
-        if message.regex {
+        if message.structural {
+            Self::structural(
+                message.query,
+                message.include_ignored,
+                deserialize_path_matches(&message.files_to_include)?,
+                deserialize_path_matches(&message.files_to_exclude)?,
+            )
+        } else if message.regex {
             Self::regex(
                 message.query,
                 message.whole_word,
@@ -142,6 +244,19 @@ impl SearchQuery {
             )
         }
     }
+    /// Restricts a structural query to the files of languages with a grammar, given their path
+    /// suffixes. Other queries are returned unchanged.
+    pub fn with_grammar_path_suffixes(mut self, new_path_suffixes: HashSet<String>) -> Self {
+        if let Self::Structural {
+            ref mut path_suffixes,
+            ..
+        } = self
+        {
+            *path_suffixes = Some(Arc::new(new_path_suffixes));
+        }
+        self
+    }
+
     pub fn with_replacement(mut self, new_replacement: String) -> Self {
         match self {
             Self::Text {
@@ -151,6 +266,10 @@ impl SearchQuery {
             | Self::Regex {
                 ref mut replacement,
                 ..
+            }
+            | Self::Structural {
+                ref mut replacement,
+                ..
             } => {
                 *replacement = Some(new_replacement);
                 self
@@ -162,6 +281,7 @@ impl SearchQuery {
             project_id,
             query: self.as_str().to_string(),
             regex: self.is_regex(),
+            structural: self.is_structural(),
             whole_word: self.whole_word(),
             case_sensitive: self.case_sensitive(),
             include_ignored: self.include_ignored(),
@@ -213,14 +333,35 @@ impl SearchQuery {
                     Ok(false)
                 }
             }
+            // Files can only be matched structurally once they're parsed, but they can't match
+            // without the strings that the pattern compares captures to.
+            Self::Structural { literals, .. } => match literals {
+                Some(literals) => match literals.stream_find_iter(stream).next() {
+                    Some(Ok(_)) => Ok(true),
+                    Some(Err(err)) => Err(err.into()),
+                    None => Ok(false),
+                },
+                None => Ok(true),
+            },
+        }
+    }
+
+    /// The error compiling a structural query, if none of the grammars of the files searched with
+    /// it accepted it. Structural queries are compiled for the grammar of each file as it's
+    /// searched, so this is only known once the search is done.
+    pub fn structural_error(&self) -> Option<String> {
+        match self {
+            Self::Structural { queries, .. } => queries.error(),
+            _ => None,
         }
     }
+
     /// Returns the replacement text for this `SearchQuery`.
     pub fn replacement(&self) -> Option<&str> {
         match self {
-            SearchQuery::Text { replacement, .. } | SearchQuery::Regex { replacement, .. } => {
-                replacement.as_deref()
-            }
+            SearchQuery::Text { replacement, .. }
+            | SearchQuery::Regex { replacement, .. }
+            | SearchQuery::Structural { replacement, .. } => replacement.as_deref(),
         }
     }
     /// Replaces search hits if replacement is set. `text` is assumed to be a string that matches this `SearchQuery` exactly, without any leftovers on either side.
@@ -236,8 +377,51 @@ impl SearchQuery {
                     None
                 }
             }
+            // The captures of a structural match can't be recovered from its text alone.
+            SearchQuery::Structural { .. } => None,
         }
     }
+
+    /// Replaces a search hit in `buffer` if replacement is set. Unlike [`Self::replacement_for`],
+    /// this substitutes the captures of structural matches.
+    pub fn replacement_for_range(
+        &self,
+        buffer: &BufferSnapshot,
+        range: Range<usize>,
+    ) -> Option<String> {
+        let Self::Structural {
+            queries,
+            replacement,
+            ..
+        } = self
+        else {
+            let text = buffer.text_for_range(range).collect::<String>();
+            return self
+                .replacement_for(&text)
+                .map(|replacement| replacement.into_owned());
+        };
+
+        let replacement = replacement.as_ref()?;
+        let mut result = None;
+        for layer in buffer.syntax_layers() {
+            let Some(query) = layer
+                .language
+                .grammar()
+                .and_then(|grammar| queries.for_grammar(self.as_str(), grammar).ok())
+            else {
+                continue;
+            };
+            layer.query_matches(&query, range.clone(), buffer.as_rope(), |mat| {
+                if result.is_none() && structural_match_range(&query, mat) == Some(range.clone()) {
+                    result = Some(substitute_captures(replacement, &query, mat, buffer));
+                }
+            });
+            if result.is_some() {
+                break;
+            }
+        }
+        result
+    }
     pub async fn search(
         &self,
         buffer: &BufferSnapshot,
@@ -322,6 +506,42 @@ impl SearchQuery {
                     }
                 }
             }
+
+            Self::Structural { queries, .. } => {
+                let range = range_offset..range_offset + rope.len();
+                for layer in buffer.syntax_layers() {
+                    let Some(query) = layer
+                        .language
+                        .grammar()
+                        .and_then(|grammar| queries.for_grammar(self.as_str(), grammar).ok())
+                    else {
+                        continue;
+                    };
+                    layer.query_matches(&query, range.clone(), buffer.as_rope(), |mat| {
+                        if let Some(match_range) = structural_match_range(&query, mat) {
+                            if match_range.start >= range.start && match_range.end <= range.end {
+                                matches.push(
+                                    match_range.start - range_offset
+                                        ..match_range.end - range_offset,
+                                );
+                            }
+                        }
+                    });
+                }
+
+                // Keep the outermost of overlapping matches, from nested nodes or from several
+                // syntax layers, so that they can all be replaced.
+                matches.sort_unstable_by_key(|range| (range.start, Reverse(range.end)));
+                let mut last_end = 0;
+                matches.retain(|range| {
+                    if range.start < last_end {
+                        false
+                    } else {
+                        last_end = range.end.max(range.start + 1);
+                        true
+                    }
+                });
+            }
         }
 
         matches
@@ -335,6 +555,7 @@ impl SearchQuery {
         match self {
             Self::Text { whole_word, .. } => *whole_word,
             Self::Regex { whole_word, .. } => *whole_word,
+            Self::Structural { .. } => false,
         }
     }
 
@@ -342,6 +563,7 @@ impl SearchQuery {
         match self {
             Self::Text { case_sensitive, .. } => *case_sensitive,
             Self::Regex { case_sensitive, .. } => *case_sensitive,
+            Self::Structural { .. } => true,
         }
     }
 
@@ -353,6 +575,9 @@ impl SearchQuery {
             Self::Regex {
                 include_ignored, ..
             } => *include_ignored,
+            Self::Structural {
+                include_ignored, ..
+            } => *include_ignored,
         }
     }
 
@@ -360,6 +585,10 @@ impl SearchQuery {
         matches!(self, Self::Regex { .. })
     }
 
+    pub fn is_structural(&self) -> bool {
+        matches!(self, Self::Structural { .. })
+    }
+
     pub fn files_to_include(&self) -> &[PathMatcher] {
         self.as_inner().files_to_include()
     }
@@ -369,6 +598,24 @@ impl SearchQuery {
     }
 
     pub fn file_matches(&self, file_path: Option<&Path>) -> bool {
+        if let (
+            Self::Structural {
+                path_suffixes: Some(path_suffixes),
+                ..
+            },
+            Some(file_path),
+        ) = (self, file_path)
+        {
+            let file_name = file_path.file_name().and_then(|name| name.to_str());
+            if ![file_path.extension_or_hidden_file_name(), file_name]
+                .into_iter()
+                .flatten()
+                .any(|suffix| path_suffixes.contains(suffix))
+            {
+                return false;
+            }
+        }
+
         match file_path {
             Some(file_path) => {
                 let mut path = file_path.to_path_buf();
@@ -396,9 +643,235 @@ impl SearchQuery {
     }
     pub fn as_inner(&self) -> &SearchInputs {
         match self {
-            Self::Regex { inner, .. } | Self::Text { inner, .. } => inner,
+            Self::Regex { inner, .. }
+            | Self::Text { inner, .. }
+            | Self::Structural { inner, .. } => inner,
+        }
+    }
+}
+
+/// Whether a regular expression can match a line break, in which case it has to be matched
+/// against whole texts rather than line by line.
+fn can_match_newline(hir: &Hir) -> bool {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => false,
+        HirKind::Literal(literal) => literal.0.contains(&b'\n'),
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .any(|range| range.start() <= '\n' && '\n' <= range.end()),
+        HirKind::Class(Class::Bytes(class)) => class
+            .ranges()
+            .iter()
+            .any(|range| range.start() <= b'\n' && b'\n' <= range.end()),
+        HirKind::Repetition(repetition) => can_match_newline(&repetition.sub),
+        HirKind::Capture(capture) => can_match_newline(&capture.sub),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => hirs.iter().any(can_match_newline),
+    }
+}
+
+/// Checks that a structural pattern is well-formed, since it can only be compiled once the
+/// grammars that it's matched against are known.
+fn validate_structural_pattern(pattern: &str) -> Result<()> {
+    let mut depth = 0_usize;
+    let mut chars = pattern.chars();
+    let mut has_node = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    if c == '"' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '(' | '[' => {
+                depth += 1;
+                has_node = true;
+            }
+            ')' | ']' => {
+                depth = depth
+                    .checked_sub(1)
+                    .context("unbalanced parentheses in structural query")?;
+            }
+            _ => {}
         }
     }
+    anyhow::ensure!(depth == 0, "unbalanced parentheses in structural query");
+    anyhow::ensure!(has_node, "structural query has no pattern");
+    Ok(())
+}
+
+/// Returns the strings that a file has to contain one of to match a structural pattern, which
+/// are those that `#eq?` predicates compare captures to. Returns `None` if some top-level
+/// pattern has no such predicate, or if the pattern has alternations or quantifiers, which can
+/// make the nodes that the predicates apply to optional.
+fn structural_literals(pattern: &str) -> Option<Vec<String>> {
+    enum Token {
+        Open,
+        Close,
+        Atom(String),
+        String(String),
+    }
+
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '[' | ']' => return None,
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '"' => {
+                let mut string = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(c) => string.push(c),
+                            None => {}
+                        },
+                        c => string.push(c),
+                    }
+                }
+                tokens.push(Token::String(string));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '"' | ';') {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Atom(atom));
+            }
+        }
+    }
+
+    let mut literals = Vec::new();
+    let mut depth = 0_usize;
+    let mut pattern_has_literal = false;
+    for (ix, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open => {
+                if depth == 0 {
+                    pattern_has_literal = false;
+                }
+                depth += 1;
+                if let [Token::Atom(predicate), Token::Atom(capture), Token::String(literal), ..] =
+                    &tokens[ix + 1..]
+                {
+                    if predicate == "#eq?" && capture.starts_with('@') {
+                        literals.push(literal.clone());
+                        pattern_has_literal = true;
+                    }
+                }
+            }
+            Token::Close => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && !pattern_has_literal {
+                    return None;
+                }
+            }
+            Token::Atom(atom) => {
+                if matches!(atom.as_str(), "*" | "+" | "?")
+                    || (depth == 0 && !atom.starts_with('@'))
+                {
+                    return None;
+                }
+            }
+            // A string on its own matches an anonymous node with that text.
+            Token::String(string) => {
+                if depth == 0 {
+                    literals.push(string.clone());
+                }
+            }
+        }
+    }
+    Some(literals).filter(|literals| !literals.is_empty())
+}
+
+/// Returns the range of a structural match: its `@match` capture, or the range spanning all of
+/// its captures if there's no such capture.
+fn structural_match_range(query: &Query, mat: &QueryMatch) -> Option<Range<usize>> {
+    let match_capture = query
+        .capture_index_for_name(STRUCTURAL_MATCH_CAPTURE)
+        .and_then(|index| mat.captures.iter().find(|capture| capture.index == index));
+    if let Some(capture) = match_capture {
+        return Some(capture.node.byte_range());
+    }
+
+    mat.captures
+        .iter()
+        .map(|capture| capture.node.byte_range())
+        .reduce(|range, capture_range| {
+            range.start.min(capture_range.start)..range.end.max(capture_range.end)
+        })
+}
+
+/// Substitutes the text of the captures of a structural match for their `$name` references in a
+/// replacement. Names containing other characters than letters, digits and underscores, such as
+/// `@function.name`, are referred to as `${function.name}`. `$$` is a literal `$`.
+fn substitute_captures(
+    replacement: &str,
+    query: &Query,
+    mat: &QueryMatch,
+    buffer: &BufferSnapshot,
+) -> String {
+    let capture_text = |name: &str| {
+        let index = query.capture_index_for_name(name)?;
+        let capture = mat.captures.iter().find(|capture| capture.index == index)?;
+        Some(
+            buffer
+                .text_for_range(capture.node.byte_range())
+                .collect::<String>(),
+        )
+    };
+
+    let mut result = String::new();
+    let mut rest = replacement;
+    while let Some(dollar_ix) = rest.find('$') {
+        result.push_str(&rest[..dollar_ix]);
+        rest = &rest[dollar_ix + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (name, reference_len) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], end)
+        };
+        match capture_text(name).filter(|_| !name.is_empty()) {
+            Some(text) => {
+                result.push_str(&text);
+                rest = &rest[reference_len..];
+            }
+            None => result.push('$'),
+        }
+    }
+    result.push_str(rest);
+    result
 }
 
 fn deserialize_path_matches(glob_set: &str) -> anyhow::Result<Vec<PathMatcher>> {
@@ -438,6 +911,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn regex_queries_spanning_lines() {
+        for (pattern, multiline) in [
+            ("a.b", false),
+            ("^fn main$", false),
+            ("[a-z]+", false),
+            ("a\\nb", true),
+            ("a\nb", true),
+            ("fn\\s+main", true),
+            ("[^;]*;", true),
+            ("(?s)a.b", true),
+        ] {
+            let query =
+                SearchQuery::regex(pattern, false, false, false, Vec::new(), Vec::new()).unwrap();
+            let SearchQuery::Regex {
+                multiline: is_multiline,
+                ..
+            } = query
+            else {
+                unreachable!()
+            };
+            assert_eq!(is_multiline, multiline, "multiline for pattern {pattern:?}");
+        }
+    }
+
+    #[test]
+    fn structural_query_validation() {
+        for valid_pattern in [
+            "(identifier) @match",
+            "[(identifier) (field_identifier)] @match",
+            r#"((identifier) @name (#eq? @name "(")) ; a comment with )"#,
+        ] {
+            assert!(
+                SearchQuery::structural(valid_pattern, false, Vec::new(), Vec::new()).is_ok(),
+                "pattern {valid_pattern} should be accepted"
+            );
+        }
+        for invalid_pattern in ["", "identifier", "(call_expression", "(identifier))"] {
+            assert!(
+                SearchQuery::structural(invalid_pattern, false, Vec::new(), Vec::new()).is_err(),
+                "pattern {invalid_pattern} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn structural_query_literals() {
+        for (pattern, literals) in [
+            (
+                r#"(call_expression function: (identifier) @name (#eq? @name "foo")) @match"#,
+                Some(vec!["foo"]),
+            ),
+            (
+                r#"((identifier) @a (#eq? @a "a\"b")) ((string) @b (#eq? @b "c"))"#,
+                Some(vec!["a\"b", "c"]),
+            ),
+            (r#""return" @keyword"#, Some(vec!["return"])),
+            ("(identifier) @match", None),
+            (r#"((identifier) @a (#not-eq? @a "foo"))"#, None),
+            (r#"((identifier) @a (#eq? @a @b))"#, None),
+            // Other patterns can match without the string.
+            (r#"((identifier) @a (#eq? @a "foo")) (string) @b"#, None),
+            (r#"[((identifier) @a (#eq? @a "foo")) (string)]"#, None),
+            (r#"(block ((identifier) @a (#eq? @a "foo"))?)"#, None),
+        ] {
+            assert_eq!(
+                structural_literals(pattern),
+                literals.map(|literals| literals.into_iter().map(String::from).collect()),
+                "literals of pattern {pattern}"
+            );
+        }
+    }
+
     #[test]
     fn path_matcher_creation_for_globs() {
         for invalid_glob in ["dir/[].txt", "dir/[a-z.txt", "dir/{file"] {
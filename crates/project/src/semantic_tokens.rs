@@ -0,0 +1,384 @@
+use std::{ops::Range, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use client::{proto, Client, TypedEnvelope};
+use collections::HashMap;
+use gpui::{AsyncAppContext, Model, ModelContext, Task};
+use language::{
+    proto::{deserialize_anchor, deserialize_version, serialize_anchor, serialize_version},
+    Bias, Buffer, BufferSnapshot, LocalFile, PointUtf16, Unclipped,
+};
+use lsp::LanguageServerId;
+use text::Anchor;
+
+use crate::{File, Project};
+
+/// A token of a language server's semantic highlighting, with its type and modifiers resolved
+/// against the legend of that server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: Range<Anchor>,
+    pub token_type: Arc<str>,
+    pub token_modifiers: Vec<Arc<str>>,
+}
+
+/// The tokens last reported for a buffer, which `textDocument/semanticTokens/full/delta`
+/// responses are applied to.
+pub(crate) struct BufferSemanticTokens {
+    server_id: LanguageServerId,
+    result_id: Option<String>,
+    data: Vec<lsp::SemanticToken>,
+}
+
+impl Project {
+    pub fn semantic_tokens(
+        &mut self,
+        buffer_handle: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<SemanticToken>>> {
+        let buffer = buffer_handle.read(cx);
+        let buffer_id = buffer.remote_id();
+        if self.is_local() {
+            let Some(file) = File::from_dyn(buffer.file()).and_then(|file| file.as_local()) else {
+                return Task::ready(Ok(Vec::new()));
+            };
+            let Some((_, server)) = self.primary_language_server_for_buffer(buffer, cx) else {
+                return Task::ready(Ok(Vec::new()));
+            };
+            let Some(options) = semantic_tokens_options(server.capabilities()) else {
+                return Task::ready(Ok(Vec::new()));
+            };
+            let supports_delta = match options.full {
+                Some(lsp::SemanticTokensFullOptions::Delta { delta }) => delta.unwrap_or(false),
+                Some(lsp::SemanticTokensFullOptions::Bool(true)) => false,
+                Some(lsp::SemanticTokensFullOptions::Bool(false)) | None => {
+                    return Task::ready(Ok(Vec::new()));
+                }
+            };
+
+            let server = server.clone();
+            let server_id = server.server_id();
+            let legend = options.legend.clone();
+            let previous = self
+                .semantic_tokens
+                .get(&buffer_id)
+                .filter(|previous| supports_delta && previous.server_id == server_id)
+                .and_then(|previous| Some((previous.result_id.clone()?, previous.data.clone())));
+            let text_document = lsp::TextDocumentIdentifier::new(
+                lsp::Url::from_file_path(file.abs_path(cx)).unwrap(),
+            );
+            let snapshot = buffer.snapshot();
+            cx.spawn(move |this, mut cx| async move {
+                let (result_id, data) = match previous {
+                    Some((previous_result_id, mut data)) => {
+                        let response = server
+                            .request::<lsp::request::SemanticTokensFullDeltaRequest>(
+                                lsp::SemanticTokensDeltaParams {
+                                    text_document,
+                                    previous_result_id,
+                                    work_done_progress_params: Default::default(),
+                                    partial_result_params: Default::default(),
+                                },
+                            )
+                            .await
+                            .context("semantic tokens delta LSP request")?;
+                        match response {
+                            Some(lsp::SemanticTokensFullDeltaResult::Tokens(tokens)) => {
+                                (tokens.result_id, tokens.data)
+                            }
+                            Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(delta)) => {
+                                apply_semantic_tokens_edits(&mut data, delta.edits)?;
+                                (delta.result_id, data)
+                            }
+                            Some(lsp::SemanticTokensFullDeltaResult::PartialTokensDelta {
+                                edits,
+                            }) => {
+                                apply_semantic_tokens_edits(&mut data, edits)?;
+                                (None, data)
+                            }
+                            None => (None, Vec::new()),
+                        }
+                    }
+                    None => {
+                        let response = server
+                            .request::<lsp::request::SemanticTokensFullRequest>(
+                                lsp::SemanticTokensParams {
+                                    text_document,
+                                    work_done_progress_params: Default::default(),
+                                    partial_result_params: Default::default(),
+                                },
+                            )
+                            .await
+                            .context("semantic tokens LSP request")?;
+                        match response {
+                            Some(lsp::SemanticTokensResult::Tokens(tokens)) => {
+                                (tokens.result_id, tokens.data)
+                            }
+                            Some(lsp::SemanticTokensResult::Partial(partial)) => {
+                                (None, partial.data)
+                            }
+                            None => (None, Vec::new()),
+                        }
+                    }
+                };
+
+                let tokens = semantic_tokens_from_lsp(&data, &legend, &snapshot);
+                this.update(&mut cx, |this, _| {
+                    this.semantic_tokens.insert(
+                        buffer_id,
+                        BufferSemanticTokens {
+                            server_id,
+                            result_id,
+                            data,
+                        },
+                    );
+                })?;
+                Ok(tokens)
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::GetSemanticTokens {
+                project_id,
+                buffer_id,
+                version: serialize_version(&buffer.version()),
+            };
+            let buffer_handle = buffer_handle.clone();
+            cx.spawn(move |_, mut cx| async move {
+                let response = client
+                    .request(request)
+                    .await
+                    .context("semantic tokens proto request")?;
+                buffer_handle
+                    .update(&mut cx, |buffer, _| {
+                        buffer.wait_for_version(deserialize_version(&response.version))
+                    })?
+                    .await?;
+                semantic_tokens_from_proto(response)
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
+    pub(crate) async fn handle_get_semantic_tokens(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::GetSemanticTokens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::GetSemanticTokensResponse> {
+        let buffer = this.update(&mut cx, |this, _| {
+            this.opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))
+        })??;
+        let buffer_version = deserialize_version(&envelope.payload.version);
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(buffer_version.clone())
+            })?
+            .await
+            .with_context(|| {
+                format!(
+                    "waiting for version {:?} for buffer {}",
+                    buffer_version,
+                    buffer.entity_id()
+                )
+            })?;
+
+        let tokens = this
+            .update(&mut cx, |this, cx| this.semantic_tokens(&buffer, cx))?
+            .await
+            .context("semantic tokens fetch")?;
+        let version = buffer.update(&mut cx, |buffer, _| buffer.version())?;
+        Ok(semantic_tokens_to_proto(tokens, &version))
+    }
+
+    pub(crate) async fn handle_refresh_semantic_tokens(
+        this: Model<Self>,
+        _: TypedEnvelope<proto::RefreshSemanticTokens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::Ack> {
+        this.update(&mut cx, |_, cx| {
+            cx.emit(crate::Event::RefreshSemanticTokens);
+        })?;
+        Ok(proto::Ack {})
+    }
+}
+
+fn semantic_tokens_options(
+    capabilities: &lsp::ServerCapabilities,
+) -> Option<&lsp::SemanticTokensOptions> {
+    match capabilities.semantic_tokens_provider.as_ref()? {
+        lsp::SemanticTokensServerCapabilities::SemanticTokensOptions(options) => Some(options),
+        lsp::SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options) => {
+            Some(&options.semantic_tokens_options)
+        }
+    }
+}
+
+/// Applies the edits of a delta response to the tokens of the previous response.
+///
+/// The edits refer to the integer-encoded token array, in which every token takes five integers,
+/// and are all relative to the previous response, so they are applied from the last one.
+fn apply_semantic_tokens_edits(
+    data: &mut Vec<lsp::SemanticToken>,
+    mut edits: Vec<lsp::SemanticTokensEdit>,
+) -> Result<()> {
+    edits.sort_by_key(|edit| edit.start);
+    for edit in edits.into_iter().rev() {
+        if edit.start % 5 != 0 || edit.delete_count % 5 != 0 {
+            return Err(anyhow!("semantic tokens edit does not align with tokens"));
+        }
+        let start = edit.start as usize / 5;
+        let end = start + edit.delete_count as usize / 5;
+        if end > data.len() {
+            return Err(anyhow!("semantic tokens edit is out of bounds"));
+        }
+        data.splice(start..end, edit.data.unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn semantic_tokens_from_lsp(
+    data: &[lsp::SemanticToken],
+    legend: &lsp::SemanticTokensLegend,
+    snapshot: &BufferSnapshot,
+) -> Vec<SemanticToken> {
+    let token_types = legend
+        .token_types
+        .iter()
+        .map(|token_type| Arc::<str>::from(token_type.as_str()))
+        .collect::<Vec<_>>();
+    let token_modifiers = legend
+        .token_modifiers
+        .iter()
+        .map(|token_modifier| Arc::<str>::from(token_modifier.as_str()))
+        .collect::<Vec<_>>();
+
+    let mut tokens = Vec::with_capacity(data.len());
+    let mut line = 0;
+    let mut start = 0;
+    for token in data {
+        if token.delta_line > 0 {
+            line += token.delta_line;
+            start = token.delta_start;
+        } else {
+            start += token.delta_start;
+        }
+
+        let Some(token_type) = token_types.get(token.token_type as usize) else {
+            continue;
+        };
+        let token_modifiers = token_modifiers
+            .iter()
+            .take(u32::BITS as usize)
+            .enumerate()
+            .filter(|(ix, _)| token.token_modifiers_bitset & (1 << ix) != 0)
+            .map(|(_, token_modifier)| token_modifier.clone())
+            .collect();
+        let range_start =
+            snapshot.clip_point_utf16(Unclipped(PointUtf16::new(line, start)), Bias::Left);
+        let range_end = snapshot.clip_point_utf16(
+            Unclipped(PointUtf16::new(line, start + token.length)),
+            Bias::Left,
+        );
+        if range_start == range_end {
+            continue;
+        }
+
+        tokens.push(SemanticToken {
+            range: snapshot.anchor_after(range_start)..snapshot.anchor_before(range_end),
+            token_type: token_type.clone(),
+            token_modifiers,
+        });
+    }
+    tokens
+}
+
+fn semantic_tokens_to_proto(
+    tokens: Vec<SemanticToken>,
+    version: &clock::Global,
+) -> proto::GetSemanticTokensResponse {
+    let mut token_types = HashMap::<Arc<str>, u32>::default();
+    let mut token_modifiers = HashMap::<Arc<str>, u32>::default();
+    let mut response = proto::GetSemanticTokensResponse {
+        version: serialize_version(version),
+        ..Default::default()
+    };
+
+    for token in tokens {
+        let token_type = *token_types
+            .entry(token.token_type.clone())
+            .or_insert_with(|| {
+                response.token_types.push(token.token_type.to_string());
+                response.token_types.len() as u32 - 1
+            });
+        let mut token_modifiers_bitset = 0;
+        for token_modifier in &token.token_modifiers {
+            let ix = *token_modifiers
+                .entry(token_modifier.clone())
+                .or_insert_with(|| {
+                    response.token_modifiers.push(token_modifier.to_string());
+                    response.token_modifiers.len() as u32 - 1
+                });
+            if ix < u32::BITS {
+                token_modifiers_bitset |= 1 << ix;
+            }
+        }
+        response.tokens.push(proto::SemanticToken {
+            start: Some(serialize_anchor(&token.range.start)),
+            end: Some(serialize_anchor(&token.range.end)),
+            token_type,
+            token_modifiers: token_modifiers_bitset,
+        });
+    }
+    response
+}
+
+fn semantic_tokens_from_proto(
+    response: proto::GetSemanticTokensResponse,
+) -> Result<Vec<SemanticToken>> {
+    let token_types = response
+        .token_types
+        .into_iter()
+        .map(Arc::<str>::from)
+        .collect::<Vec<_>>();
+    let token_modifiers = response
+        .token_modifiers
+        .into_iter()
+        .map(Arc::<str>::from)
+        .collect::<Vec<_>>();
+
+    response
+        .tokens
+        .into_iter()
+        .map(|token| {
+            let start = token
+                .start
+                .and_then(deserialize_anchor)
+                .context("invalid semantic token start")?;
+            let end = token
+                .end
+                .and_then(deserialize_anchor)
+                .context("invalid semantic token end")?;
+            let token_type = token_types
+                .get(token.token_type as usize)
+                .context("invalid semantic token type")?
+                .clone();
+            let token_modifiers = token_modifiers
+                .iter()
+                .take(u32::BITS as usize)
+                .enumerate()
+                .filter(|(ix, _)| token.token_modifiers & (1 << ix) != 0)
+                .map(|(_, token_modifier)| token_modifier.clone())
+                .collect();
+            Ok(SemanticToken {
+                range: start..end,
+                token_type,
+                token_modifiers,
+            })
+        })
+        .collect()
+}
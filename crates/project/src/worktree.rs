@@ -8,7 +8,7 @@ use client::{proto, Client};
 use clock::ReplicaId;
 use collections::{HashMap, HashSet, VecDeque};
 use fs::{
-    repository::{GitFileStatus, GitRepository, RepoPath},
+    repository::{BlameEntry, CommitSummary, GitFileStatus, GitRepository, RepoPath},
     Fs,
 };
 use futures::{
@@ -136,6 +136,19 @@ pub struct Snapshot {
     completed_scan_id: usize,
 }
 
+/// A file whose contents in the index differ from the HEAD commit, or whose contents in the
+/// working directory differ from the index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitStatusEntry {
+    pub path: Arc<Path>,
+    /// The work directory of the repository containing the file.
+    pub work_directory: Arc<Path>,
+    pub status: GitFileStatus,
+    /// Whether the status is of the file's changes in the index, rather than in the working
+    /// directory.
+    pub is_staged: bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RepositoryEntry {
     pub(crate) work_directory: WorkDirectoryEntry,
@@ -1033,6 +1046,266 @@ impl LocalWorktree {
         })
     }
 
+    /// Computes the commits that last changed each line of the file at the given path, whose
+    /// current text is `content`.
+    pub fn blame(
+        &self,
+        path: &Path,
+        content: String,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Vec<BlameEntry>>> {
+        let Some(repo) = self.snapshot.repository_for_path(path) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let repo_path = match repo.work_directory.relativize(&self.snapshot, path) {
+            Ok(repo_path) => repo_path,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let Some(git_repo) = self.snapshot.get_local_repo(&repo) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let git_repo = git_repo.repo_ptr.clone();
+        cx.background_executor()
+            .spawn(async move { git_repo.lock().blame(&repo_path, &content) })
+    }
+
+    /// Loads the contents of the file at the given path in the HEAD commit, if it was committed.
+    pub fn load_head_text(
+        &self,
+        path: &Path,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Option<String>>> {
+        let Some(repo) = self.snapshot.repository_for_path(path) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let repo_path = match repo.work_directory.relativize(&self.snapshot, path) {
+            Ok(repo_path) => repo_path,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let Some(git_repo) = self.snapshot.get_local_repo(&repo) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let git_repo = git_repo.repo_ptr.clone();
+        cx.background_executor()
+            .spawn(async move { Ok(git_repo.lock().load_head_text(&repo_path)) })
+    }
+
+    /// Replaces the staged contents of the file at the given path with the text computed by
+    /// `update` from the file's contents in the HEAD commit and in the index, returning the new
+    /// staged contents.
+    pub fn update_index_text(
+        &self,
+        path: &Path,
+        update: impl 'static + Send + FnOnce(Option<&str>, Option<&str>) -> Option<String>,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Option<String>>> {
+        let Some(repo) = self.snapshot.repository_for_path(path) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let repo_path = match repo.work_directory.relativize(&self.snapshot, path) {
+            Ok(repo_path) => repo_path,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let Some(git_repo) = self.snapshot.get_local_repo(&repo) else {
+            return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+        };
+        let git_repo = git_repo.repo_ptr.clone();
+        cx.background_executor().spawn(async move {
+            let git_repo = git_repo.lock();
+            let head_text = git_repo.load_head_text(&repo_path);
+            let index_text = git_repo.load_index_text(&repo_path);
+            let new_index_text = update(head_text.as_deref(), index_text.as_deref());
+            if new_index_text != index_text {
+                git_repo.set_index_text(&repo_path, new_index_text.as_deref())?;
+            }
+            Ok(new_index_text)
+        })
+    }
+
+    /// Lists at most `limit` commits of the repository containing the given path, newest first.
+    /// Unless the path is the repository's working directory, only the commits that changed
+    /// the file or directory at that path are listed.
+    pub fn commit_history(
+        &self,
+        path: &Path,
+        limit: usize,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Vec<CommitSummary>>> {
+        let (_, repo_path, git_repo) = match self.git_repo_for_path(path) {
+            Ok(repo) => repo,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        cx.background_executor().spawn(async move {
+            let path_filter = (!repo_path.as_os_str().is_empty()).then_some(&repo_path);
+            git_repo.lock().commit_history(path_filter, limit)
+        })
+    }
+
+    /// Lists the paths of the files changed by the given commit of the repository containing
+    /// the given path.
+    pub fn changed_paths(
+        &self,
+        path: &Path,
+        sha: String,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Vec<Arc<Path>>>> {
+        let (work_directory, _, git_repo) = match self.git_repo_for_path(path) {
+            Ok(repo) => repo,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        cx.background_executor().spawn(async move {
+            let paths = git_repo.lock().changed_paths(&sha)?;
+            Ok(paths
+                .into_iter()
+                .map(|repo_path| work_directory.0.join(repo_path.0).into())
+                .collect())
+        })
+    }
+
+    /// Loads the contents of the file at the given path as of the given commit.
+    pub fn load_commit_text(
+        &self,
+        path: &Path,
+        sha: String,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<Option<String>>> {
+        let (_, repo_path, git_repo) = match self.git_repo_for_path(path) {
+            Ok(repo) => repo,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        cx.background_executor()
+            .spawn(async move { git_repo.lock().load_commit_text(&sha, &repo_path) })
+    }
+
+    fn git_repo_for_path(
+        &self,
+        path: &Path,
+    ) -> Result<(
+        RepositoryWorkDirectory,
+        RepoPath,
+        Arc<Mutex<dyn GitRepository>>,
+    )> {
+        let (work_directory, repo) = self
+            .snapshot
+            .repository_and_work_directory_for_path(path)
+            .ok_or_else(|| anyhow!("{path:?} is not in a git repository"))?;
+        let repo_path = repo.work_directory.relativize(&self.snapshot, path)?;
+        let git_repo = self
+            .snapshot
+            .get_local_repo(&repo)
+            .ok_or_else(|| anyhow!("{path:?} is not in a git repository"))?;
+        Ok((work_directory, repo_path, git_repo.repo_ptr.clone()))
+    }
+
+    /// Lists the files of every repository in the worktree that have staged or unstaged
+    /// changes, with files that have both being listed twice.
+    pub fn git_statuses(&self, cx: &mut ModelContext<Worktree>) -> Task<Vec<GitStatusEntry>> {
+        let snapshot = self.snapshot();
+        cx.background_executor().spawn(async move {
+            let mut statuses = Vec::new();
+            for (work_directory, repo) in snapshot.repositories() {
+                let Some(local_repo) = snapshot.get_local_repo(repo) else {
+                    continue;
+                };
+                let git_repo = local_repo.repo_ptr.lock();
+                for (repo_path, status) in git_repo.staged_statuses(Path::new("")).iter() {
+                    statuses.push(GitStatusEntry {
+                        path: work_directory.join(repo_path).into(),
+                        work_directory: work_directory.clone(),
+                        status: *status,
+                        is_staged: true,
+                    });
+                }
+
+                for entry in snapshot.descendent_entries(false, false, work_directory) {
+                    if entry.git_status.is_none() {
+                        continue;
+                    }
+                    // Skip the files of nested repositories.
+                    let Some((entry_work_directory, _)) =
+                        snapshot.repository_and_work_directory_for_path(&entry.path)
+                    else {
+                        continue;
+                    };
+                    if entry_work_directory.0 != *work_directory {
+                        continue;
+                    }
+                    let Ok(repo_path) = entry.path.strip_prefix(work_directory) else {
+                        continue;
+                    };
+                    let repo_path = RepoPath::from(repo_path);
+                    if let Some(status) = git_repo.unstaged_status(&repo_path, entry.mtime) {
+                        statuses.push(GitStatusEntry {
+                            path: entry.path.clone(),
+                            work_directory: work_directory.clone(),
+                            status,
+                            is_staged: false,
+                        });
+                    }
+                }
+            }
+            statuses
+        })
+    }
+
+    /// Stages or unstages the files at the given paths in their repositories.
+    pub fn stage_paths(
+        &self,
+        paths: Vec<Arc<Path>>,
+        stage: bool,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<()>> {
+        let mut repo_paths_by_repo = HashMap::default();
+        for path in &paths {
+            let Some((work_directory, local_repo)) = self.snapshot.local_repo_for_path(path) else {
+                return Task::ready(Err(anyhow!("{path:?} is not in a git repository")));
+            };
+            let repo_path = match path.strip_prefix(&work_directory.0) {
+                Ok(repo_path) => RepoPath::from(repo_path),
+                Err(error) => return Task::ready(Err(error.into())),
+            };
+            repo_paths_by_repo
+                .entry(work_directory.0.clone())
+                .or_insert_with(|| (local_repo.repo_ptr.clone(), Vec::new()))
+                .1
+                .push(repo_path);
+        }
+
+        cx.background_executor().spawn(async move {
+            for (git_repo, repo_paths) in repo_paths_by_repo.into_values() {
+                let git_repo = git_repo.lock();
+                if stage {
+                    git_repo.stage_paths(&repo_paths)?;
+                } else {
+                    git_repo.unstage_paths(&repo_paths)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Commits the staged changes of the repository with the given work directory.
+    pub fn commit(
+        &self,
+        work_directory: &Path,
+        message: String,
+        cx: &mut ModelContext<Worktree>,
+    ) -> Task<Result<()>> {
+        let Some(git_repo) = self
+            .snapshot
+            .repository_entries
+            .get(&RepositoryWorkDirectory(work_directory.into()))
+            .and_then(|repo| self.snapshot.get_local_repo(repo))
+            .map(|local_repo| local_repo.repo_ptr.clone())
+        else {
+            return Task::ready(Err(anyhow!(
+                "{work_directory:?} is not the work directory of a git repository"
+            )));
+        };
+        cx.background_executor()
+            .spawn(async move { git_repo.lock().commit(&message) })
+    }
+
     pub fn save_buffer(
         &self,
         buffer_handle: Model<Buffer>,
@@ -1,11 +1,14 @@
 use crate::{
     project_settings::ProjectSettings,
     worktree::{Event, Snapshot, WorktreeModelHandle},
-    Entry, EntryKind, PathChange, Project, Worktree,
+    Entry, EntryKind, GitStatusEntry, PathChange, Project, Worktree,
 };
 use anyhow::Result;
 use client::Client;
-use fs::{repository::GitFileStatus, FakeFs, Fs, RealFs, RemoveOptions};
+use fs::{
+    repository::{CommitSummary, GitFileStatus},
+    FakeFs, Fs, RealFs, RemoveOptions,
+};
 use git::GITIGNORE;
 use gpui::{ModelContext, Task, TestAppContext};
 use parking_lot::Mutex;
@@ -2211,6 +2214,271 @@ async fn test_git_status(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_git_stage_and_commit(cx: &mut TestAppContext) {
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let root = temp_tree(json!({
+        "project": {
+            "a.txt": "a",
+            "b.txt": "b",
+        },
+    }));
+    let work_dir = root.path().join("project");
+    let repo = git_init(work_dir.as_path());
+    git_add("a.txt", &repo);
+    git_commit("Initial commit", &repo);
+    std::fs::write(work_dir.join("a.txt"), "aa").unwrap();
+
+    let tree = Worktree::local(
+        build_client(cx),
+        root.path(),
+        true,
+        Arc::new(RealFs),
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+    cx.executor().run_until_parked();
+
+    let statuses = |cx: &mut TestAppContext| {
+        tree.update(cx, |tree, cx| tree.as_local().unwrap().git_statuses(cx))
+    };
+    let entry = |path: &str, status, is_staged| GitStatusEntry {
+        path: Path::new("project").join(path).into(),
+        work_directory: Path::new("project").into(),
+        status,
+        is_staged,
+    };
+
+    assert_eq!(
+        statuses(cx).await,
+        [
+            entry("a.txt", GitFileStatus::Modified, false),
+            entry("b.txt", GitFileStatus::Added, false),
+        ]
+    );
+
+    let paths = ["a.txt", "b.txt"].map(|path| Arc::from(Path::new("project").join(path)));
+    tree.update(cx, |tree, cx| {
+        tree.as_local()
+            .unwrap()
+            .stage_paths(paths.to_vec(), true, cx)
+    })
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.executor().run_until_parked();
+    assert_eq!(
+        statuses(cx).await,
+        [
+            entry("a.txt", GitFileStatus::Modified, true),
+            entry("b.txt", GitFileStatus::Added, true),
+        ]
+    );
+
+    tree.update(cx, |tree, cx| {
+        tree.as_local()
+            .unwrap()
+            .stage_paths(vec![paths[1].clone()], false, cx)
+    })
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.executor().run_until_parked();
+    assert_eq!(
+        statuses(cx).await,
+        [
+            entry("a.txt", GitFileStatus::Modified, true),
+            entry("b.txt", GitFileStatus::Added, false),
+        ]
+    );
+
+    tree.update(cx, |tree, cx| {
+        tree.as_local()
+            .unwrap()
+            .commit(Path::new("project"), "Update a".into(), cx)
+    })
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.executor().run_until_parked();
+    assert_eq!(
+        statuses(cx).await,
+        [entry("b.txt", GitFileStatus::Added, false)]
+    );
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.message(), Some("Update a"));
+    assert_eq!(head.parent_count(), 1);
+}
+
+#[gpui::test]
+async fn test_git_blame(cx: &mut TestAppContext) {
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let root = temp_tree(json!({
+        "project": {
+            "a.txt": "one\ntwo\n",
+        },
+    }));
+    let work_dir = root.path().join("project");
+    let repo = git_init(work_dir.as_path());
+    git_add("a.txt", &repo);
+    git_commit("Add a", &repo);
+    std::fs::write(work_dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+    git_add("a.txt", &repo);
+    git_commit("Update a", &repo);
+
+    let tree = Worktree::local(
+        build_client(cx),
+        root.path(),
+        true,
+        Arc::new(RealFs),
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+    cx.executor().run_until_parked();
+
+    // Lines that differ from HEAD aren't attributed to any commit.
+    let entries = tree
+        .update(cx, |tree, cx| {
+            tree.as_local().unwrap().blame(
+                Path::new("project/a.txt"),
+                "one\nTWO\nthree\nfour\n".to_string(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| (
+                entry.rows.clone(),
+                entry.sha.as_ref().map(|_| entry.summary.as_str())
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (0..1, Some("Add a")),
+            (1..2, None),
+            (2..3, Some("Update a")),
+            (3..4, None),
+        ]
+    );
+    assert_eq!(entries[0].author, "test");
+    assert_eq!(entries[0].author_email, "test@zed.dev");
+
+    let error = tree
+        .update(cx, |tree, cx| {
+            tree.as_local()
+                .unwrap()
+                .blame(Path::new("a.txt"), String::new(), cx)
+        })
+        .await;
+    assert!(error.is_err());
+}
+
+#[gpui::test]
+async fn test_git_commit_history(cx: &mut TestAppContext) {
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let root = temp_tree(json!({
+        "project": {
+            "a.txt": "a",
+            "b.txt": "b",
+        },
+    }));
+    let work_dir = root.path().join("project");
+    let repo = git_init(work_dir.as_path());
+    git_add("a.txt", &repo);
+    git_commit("Add a", &repo);
+    git_add("b.txt", &repo);
+    git_commit("Add b", &repo);
+    std::fs::write(work_dir.join("a.txt"), "aa").unwrap();
+    git_add("a.txt", &repo);
+    git_commit("Update a", &repo);
+
+    let tree = Worktree::local(
+        build_client(cx),
+        root.path(),
+        true,
+        Arc::new(RealFs),
+        Default::default(),
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    tree.flush_fs_events(cx).await;
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+    cx.executor().run_until_parked();
+
+    let history = |path: &str, cx: &mut TestAppContext| {
+        tree.update(cx, |tree, cx| {
+            tree.as_local()
+                .unwrap()
+                .commit_history(Path::new(path), 10, cx)
+        })
+    };
+    let summaries = |commits: Vec<CommitSummary>| {
+        commits
+            .into_iter()
+            .map(|commit| commit.summary)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        summaries(history("project", cx).await.unwrap()),
+        ["Update a", "Add b", "Add a"]
+    );
+    let file_history = history("project/a.txt", cx).await.unwrap();
+    assert_eq!(summaries(file_history.clone()), ["Update a", "Add a"]);
+    assert_eq!(file_history[0].author, "test");
+
+    let changed_paths = tree
+        .update(cx, |tree, cx| {
+            tree.as_local().unwrap().changed_paths(
+                Path::new("project"),
+                file_history[0].sha.clone(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    assert_eq!(changed_paths, [Arc::from(Path::new("project/a.txt"))]);
+
+    let load_text = |path: &str, sha: &str, cx: &mut TestAppContext| {
+        tree.update(cx, |tree, cx| {
+            tree.as_local()
+                .unwrap()
+                .load_commit_text(Path::new(path), sha.to_string(), cx)
+        })
+    };
+    assert_eq!(
+        load_text("project/a.txt", &file_history[1].sha, cx)
+            .await
+            .unwrap(),
+        Some("a".to_string())
+    );
+    assert_eq!(
+        load_text("project/b.txt", &file_history[1].sha, cx)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
 #[gpui::test]
 async fn test_propagate_git_statuses(cx: &mut TestAppContext) {
     init_test(cx);
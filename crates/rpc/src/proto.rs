@@ -182,6 +182,27 @@ messages!(
     (GetDocumentHighlightsResponse, Background),
     (GetHover, Background),
     (GetHoverResponse, Background),
+    (GetSignatureHelp, Background),
+    (GetSignatureHelpResponse, Background),
+    (GetSemanticTokens, Background),
+    (GetSemanticTokensResponse, Background),
+    (GetFoldingRanges, Background),
+    (GetFoldingRangesResponse, Background),
+    (GetSelectionRanges, Background),
+    (GetSelectionRangesResponse, Background),
+    (GetCodeLens, Background),
+    (GetCodeLensResponse, Background),
+    (ExecuteCodeLens, Background),
+    (ExecuteCodeLensResponse, Background),
+    (ResolveCodeLens, Background),
+    (ResolveCodeLensResponse, Background),
+    (GetLinkedEditingRanges, Background),
+    (GetLinkedEditingRangesResponse, Background),
+    (BlameBuffer, Background),
+    (BlameBufferResponse, Background),
+    (UpdateGitIndex, Background),
+    (LoadHeadText, Background),
+    (LoadHeadTextResponse, Background),
     (GetNotifications, Foreground),
     (GetNotificationsResponse, Foreground),
     (GetPrivateUserInfo, Foreground),
@@ -192,6 +213,8 @@ messages!(
     (GetReferencesResponse, Background),
     (GetTypeDefinition, Background),
     (GetTypeDefinitionResponse, Background),
+    (GetImplementation, Background),
+    (GetImplementationResponse, Background),
     (GetUsers, Foreground),
     (Hello, Foreground),
     (IncomingCall, Foreground),
@@ -227,6 +250,7 @@ messages!(
     (PrepareRenameResponse, Background),
     (ProjectEntryResponse, Foreground),
     (RefreshInlayHints, Foreground),
+    (RefreshSemanticTokens, Foreground),
     (RejoinChannelBuffers, Foreground),
     (RejoinChannelBuffersResponse, Foreground),
     (RejoinRoom, Foreground),
@@ -283,7 +307,19 @@ messages!(
     (UsersResponse, Foreground),
     (LspExtExpandMacro, Background),
     (LspExtExpandMacroResponse, Background),
+    (PrepareCallHierarchy, Background),
+    (PrepareCallHierarchyResponse, Background),
+    (GetIncomingCalls, Background),
+    (GetIncomingCallsResponse, Background),
+    (GetOutgoingCalls, Background),
+    (GetOutgoingCallsResponse, Background),
     (SetRoomParticipantRole, Foreground),
+    (PrepareTypeHierarchy, Background),
+    (PrepareTypeHierarchyResponse, Background),
+    (GetSupertypes, Background),
+    (GetSupertypesResponse, Background),
+    (GetSubtypes, Background),
+    (GetSubtypesResponse, Background),
 );
 
 request_messages!(
@@ -313,11 +349,23 @@ request_messages!(
     (GetDefinition, GetDefinitionResponse),
     (GetDocumentHighlights, GetDocumentHighlightsResponse),
     (GetHover, GetHoverResponse),
+    (GetSignatureHelp, GetSignatureHelpResponse),
+    (GetSemanticTokens, GetSemanticTokensResponse),
+    (GetFoldingRanges, GetFoldingRangesResponse),
+    (GetSelectionRanges, GetSelectionRangesResponse),
+    (GetCodeLens, GetCodeLensResponse),
+    (ExecuteCodeLens, ExecuteCodeLensResponse),
+    (ResolveCodeLens, ResolveCodeLensResponse),
+    (GetLinkedEditingRanges, GetLinkedEditingRangesResponse),
+    (BlameBuffer, BlameBufferResponse),
+    (UpdateGitIndex, Ack),
+    (LoadHeadText, LoadHeadTextResponse),
     (GetNotifications, GetNotificationsResponse),
     (GetPrivateUserInfo, GetPrivateUserInfoResponse),
     (GetProjectSymbols, GetProjectSymbolsResponse),
     (GetReferences, GetReferencesResponse),
     (GetTypeDefinition, GetTypeDefinitionResponse),
+    (GetImplementation, GetImplementationResponse),
     (GetUsers, UsersResponse),
     (IncomingCall, Ack),
     (InlayHints, InlayHintsResponse),
@@ -339,6 +387,7 @@ request_messages!(
     (Ping, Ack),
     (PrepareRename, PrepareRenameResponse),
     (RefreshInlayHints, Ack),
+    (RefreshSemanticTokens, Ack),
     (RejoinChannelBuffers, RejoinChannelBuffersResponse),
     (RejoinRoom, RejoinRoomResponse),
     (ReloadBuffers, ReloadBuffersResponse),
@@ -369,6 +418,12 @@ request_messages!(
     (UpdateWorktree, Ack),
     (LspExtExpandMacro, LspExtExpandMacroResponse),
     (SetRoomParticipantRole, Ack),
+    (PrepareCallHierarchy, PrepareCallHierarchyResponse),
+    (GetIncomingCalls, GetIncomingCallsResponse),
+    (GetOutgoingCalls, GetOutgoingCallsResponse),
+    (PrepareTypeHierarchy, PrepareTypeHierarchyResponse),
+    (GetSupertypes, GetSupertypesResponse),
+    (GetSubtypes, GetSubtypesResponse),
 );
 
 entity_messages!(
@@ -389,9 +444,21 @@ entity_messages!(
     GetDefinition,
     GetDocumentHighlights,
     GetHover,
+    GetSignatureHelp,
+    GetSemanticTokens,
+    GetFoldingRanges,
+    GetSelectionRanges,
+    GetCodeLens,
+    ExecuteCodeLens,
+    ResolveCodeLens,
+    GetLinkedEditingRanges,
+    BlameBuffer,
+    UpdateGitIndex,
+    LoadHeadText,
     GetProjectSymbols,
     GetReferences,
     GetTypeDefinition,
+    GetImplementation,
     InlayHints,
     JoinProject,
     LeaveProject,
@@ -402,6 +469,7 @@ entity_messages!(
     PerformRename,
     PrepareRename,
     RefreshInlayHints,
+    RefreshSemanticTokens,
     ReloadBuffers,
     RemoveProjectCollaborator,
     RenameProjectEntry,
@@ -422,6 +490,12 @@ entity_messages!(
     UpdateWorktree,
     UpdateWorktreeSettings,
     LspExtExpandMacro,
+    PrepareCallHierarchy,
+    GetIncomingCalls,
+    GetOutgoingCalls,
+    PrepareTypeHierarchy,
+    GetSupertypes,
+    GetSubtypes,
 );
 
 entity_messages!(
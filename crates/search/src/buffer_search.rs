@@ -1,7 +1,7 @@
 mod registrar;
 
 use crate::{
-    history::SearchHistory,
+    history::{add_to_search_history, SearchHistory, SearchHistoryCursor, SearchHistoryEntry},
     mode::{next_mode, SearchMode},
     search_bar::render_nav_button,
     ActivateRegexMode, ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery,
@@ -66,7 +66,7 @@ pub struct BufferSearchBar {
     default_options: SearchOptions,
     query_contains_error: bool,
     dismissed: bool,
-    search_history: SearchHistory,
+    search_history_cursor: SearchHistoryCursor,
     current_mode: SearchMode,
     replace_enabled: bool,
 }
@@ -501,7 +501,7 @@ impl BufferSearchBar {
             pending_search: None,
             query_contains_error: false,
             dismissed: true,
-            search_history: SearchHistory::default(),
+            search_history_cursor: SearchHistoryCursor::default(),
             current_mode: SearchMode::default(),
             active_search: None,
             replace_enabled: false,
@@ -858,7 +858,20 @@ impl BufferSearchBar {
                 }
                 .into();
                 self.active_search = Some(query.clone());
-                let query_text = query.as_str().to_string();
+                let mut history_entry = SearchHistoryEntry::from_query(&query);
+                if !self.replace_enabled {
+                    history_entry.replacement.clear();
+                }
+                // The buffer search has no file filters, so keep those of a project search
+                // that it's running again.
+                if let Some(current_entry) = cx
+                    .try_global::<SearchHistory>()
+                    .and_then(|history| history.current_entry(&self.search_history_cursor))
+                    .filter(|current_entry| current_entry.query == history_entry.query)
+                {
+                    history_entry.files_to_include = current_entry.files_to_include.clone();
+                    history_entry.files_to_exclude = current_entry.files_to_exclude.clone();
+                }
 
                 let matches = active_searchable_item.find_matches(query, cx);
 
@@ -874,7 +887,11 @@ impl BufferSearchBar {
                                 .insert(active_searchable_item.downgrade(), matches);
 
                             this.update_match_index(cx);
-                            this.search_history.add(query_text);
+                            add_to_search_history(
+                                history_entry,
+                                &mut this.search_history_cursor,
+                                cx,
+                            );
                             if !this.dismissed {
                                 let matches = this
                                     .searchable_items_with_matches
@@ -918,28 +935,55 @@ impl BufferSearchBar {
     }
 
     fn next_history_query(&mut self, _: &NextHistoryQuery, cx: &mut ViewContext<Self>) {
-        if let Some(new_query) = self.search_history.next().map(str::to_string) {
-            let _ = self.search(&new_query, Some(self.search_options), cx);
+        if let Some(new_entry) = cx
+            .default_global::<SearchHistory>()
+            .next(&mut self.search_history_cursor)
+            .cloned()
+        {
+            self.show_history_entry(&new_entry, cx);
         } else {
-            self.search_history.reset_selection();
+            self.search_history_cursor.reset_selection();
             let _ = self.search("", Some(self.search_options), cx);
         }
     }
 
     fn previous_history_query(&mut self, _: &PreviousHistoryQuery, cx: &mut ViewContext<Self>) {
         if self.query(cx).is_empty() {
-            if let Some(new_query) = self.search_history.current().map(str::to_string) {
-                let _ = self.search(&new_query, Some(self.search_options), cx);
+            if let Some(new_entry) = cx
+                .default_global::<SearchHistory>()
+                .current_entry(&self.search_history_cursor)
+                .cloned()
+            {
+                self.show_history_entry(&new_entry, cx);
                 return;
             }
         }
 
-        if let Some(new_query) = self.search_history.previous().map(str::to_string) {
-            let _ = self.search(&new_query, Some(self.search_options), cx);
+        if let Some(new_entry) = cx
+            .default_global::<SearchHistory>()
+            .previous(&mut self.search_history_cursor)
+            .cloned()
+        {
+            self.show_history_entry(&new_entry, cx);
         }
     }
+
+    /// Searches for an entry navigated to in the history with the current search options,
+    /// restoring its mode and replacement. Searches from the project search that the buffer
+    /// search can't run are run as text searches.
+    fn show_history_entry(&mut self, entry: &SearchHistoryEntry, cx: &mut ViewContext<Self>) {
+        self.current_mode = if entry.mode == SearchMode::Regex {
+            SearchMode::Regex
+        } else {
+            SearchMode::Text
+        };
+        let replacement = Some(entry.replacement.as_str()).filter(|text| !text.is_empty());
+        self.set_replacement(replacement, cx);
+        let _ = self.search(&entry.query, Some(self.search_options), cx);
+        cx.notify();
+    }
     fn cycle_mode(&mut self, _: &CycleMode, cx: &mut ViewContext<Self>) {
-        self.activate_search_mode(next_mode(&self.current_mode, false), cx);
+        self.activate_search_mode(next_mode(&self.current_mode, false, false), cx);
     }
     fn toggle_replace(&mut self, _: &ToggleReplace, cx: &mut ViewContext<Self>) {
         if let Some(_) = &self.active_searchable_item {
@@ -1720,6 +1764,58 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_search_history_entries_from_project_search(cx: &mut TestAppContext) {
+        let (_editor, search_bar, cx) = init_test(cx);
+
+        // The project search adds its searches to the same history.
+        cx.update(|cx| {
+            add_to_search_history(
+                SearchHistoryEntry {
+                    query: "expression".into(),
+                    mode: SearchMode::Regex,
+                    files_to_include: "*.rs".into(),
+                    replacement: "expr".into(),
+                    ..Default::default()
+                },
+                &mut SearchHistoryCursor::default(),
+                cx,
+            )
+        });
+        search_bar
+            .update(cx, |search_bar, cx| search_bar.search("regex", None, cx))
+            .await
+            .unwrap();
+
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.previous_history_query(&PreviousHistoryQuery, cx);
+        });
+        cx.run_until_parked();
+        search_bar.update(cx, |search_bar, cx| {
+            assert_eq!(search_bar.query(cx), "expression");
+            assert_eq!(search_bar.current_mode, SearchMode::Regex);
+            assert!(search_bar.replace_enabled);
+            assert_eq!(search_bar.replacement(cx), "expr");
+            // Running the search again keeps the filters of the project search.
+            assert_eq!(
+                cx.global::<SearchHistory>()
+                    .current_entry(&search_bar.search_history_cursor)
+                    .map(|entry| entry.files_to_include.as_str()),
+                Some("*.rs")
+            );
+        });
+
+        search_bar.update(cx, |search_bar, cx| {
+            search_bar.next_history_query(&NextHistoryQuery, cx);
+        });
+        cx.run_until_parked();
+        search_bar.update(cx, |search_bar, cx| {
+            assert_eq!(search_bar.query(cx), "regex");
+            assert_eq!(search_bar.current_mode, SearchMode::Text);
+            assert!(!search_bar.replace_enabled);
+        });
+    }
+
     #[gpui::test]
     async fn test_replace_simple(cx: &mut TestAppContext) {
         let (editor, search_bar, cx) = init_test(cx);
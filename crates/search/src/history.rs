@@ -1,61 +1,154 @@
-use smallvec::SmallVec;
+use crate::{mode::SearchMode, persistence::DB, SearchOptions};
+use gpui::{AppContext, Task};
+use project::search::{SearchInputs, SearchQuery};
+use std::{collections::VecDeque, time::Duration};
+use util::{paths::PathMatcher, ResultExt, TryFutureExt};
+
 const SEARCH_HISTORY_LIMIT: usize = 20;
+/// Searches are added to the history as they're typed, so the history is saved once typing stops.
+const SEARCH_HISTORY_SAVE_DEBOUNCE: Duration = Duration::from_millis(1000);
 
-#[derive(Default, Debug, Clone)]
+/// A search run from the buffer or project search bar, with everything needed to run it again.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchHistoryEntry {
+    pub query: String,
+    pub mode: SearchMode,
+    pub options: SearchOptions,
+    /// The comma-separated globs of the files to search in, as typed in the project search.
+    pub files_to_include: String,
+    pub files_to_exclude: String,
+    pub replacement: String,
+}
+
+impl SearchHistoryEntry {
+    pub fn from_query(query: &SearchQuery) -> Self {
+        let mode = if query.is_structural() {
+            SearchMode::Structural
+        } else if query.is_regex() {
+            SearchMode::Regex
+        } else {
+            SearchMode::Text
+        };
+        Self {
+            query: query.as_str().to_string(),
+            mode,
+            options: SearchOptions::from_query(query),
+            files_to_include: join_path_matchers(query.files_to_include()),
+            files_to_exclude: join_path_matchers(query.files_to_exclude()),
+            replacement: query.replacement().unwrap_or_default().to_string(),
+        }
+    }
+
+    pub fn from_semantic_inputs(inputs: &SearchInputs) -> Self {
+        Self {
+            query: inputs.as_str().to_string(),
+            mode: SearchMode::Semantic,
+            files_to_include: join_path_matchers(inputs.files_to_include()),
+            files_to_exclude: join_path_matchers(inputs.files_to_exclude()),
+            ..Default::default()
+        }
+    }
+}
+
+fn join_path_matchers(matchers: &[PathMatcher]) -> String {
+    matchers
+        .iter()
+        .map(|matcher| matcher.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The searches run from all search bars, most recent last. Each search bar navigates through
+/// it with its own [`SearchHistoryCursor`].
+#[derive(Default, Debug)]
 pub struct SearchHistory {
-    history: SmallVec<[String; SEARCH_HISTORY_LIMIT]>,
+    history: VecDeque<SearchHistoryEntry>,
+    /// How many entries were dropped from the front of the history to respect its limit. The
+    /// cursors index entries from the first one ever added, so they stay valid when it happens.
+    dropped_count: usize,
+    /// Whether the history is saved to the database whenever it changes. Only the history
+    /// loaded by [`load_search_history`] is, so that tests don't share one.
+    persistent: bool,
+    save_task: Option<Task<Option<()>>>,
+}
+
+/// The entry of the [`SearchHistory`] selected by a search bar.
+#[derive(Default, Debug, Clone)]
+pub struct SearchHistoryCursor {
     selected: Option<usize>,
 }
 
+impl SearchHistoryCursor {
+    pub fn reset_selection(&mut self) {
+        self.selected = None;
+    }
+}
+
 impl SearchHistory {
-    pub fn add(&mut self, search_string: String) {
-        if let Some(i) = self.selected {
-            if search_string == self.history[i] {
+    pub fn entries(&self) -> impl Iterator<Item = &SearchHistoryEntry> {
+        self.history.iter()
+    }
+
+    pub fn add(&mut self, cursor: &mut SearchHistoryCursor, entry: SearchHistoryEntry) {
+        if let Some(i) = self.selected_index(cursor) {
+            if entry.query == self.history[i].query {
+                self.history[i] = entry;
                 return;
             }
         }
 
-        if let Some(previously_searched) = self.history.last_mut() {
-            if search_string.find(previously_searched.as_str()).is_some() {
-                *previously_searched = search_string;
-                self.selected = Some(self.history.len() - 1);
+        let last_index = self.history.len().checked_sub(1);
+        if let Some(previously_searched) = self.history.back_mut() {
+            // Searches that are typed into a bar are added as they're typed, so only keep the
+            // complete query.
+            if previously_searched.query == entry.query
+                || (self.selected_index(cursor) == last_index
+                    && entry.query.contains(previously_searched.query.as_str()))
+            {
+                *previously_searched = entry;
+                cursor.selected = last_index.map(|ix| ix + self.dropped_count);
                 return;
             }
         }
 
-        self.history.push(search_string);
+        self.history.push_back(entry);
         if self.history.len() > SEARCH_HISTORY_LIMIT {
-            self.history.remove(0);
+            self.history.pop_front();
+            self.dropped_count += 1;
         }
-        self.selected = Some(self.history.len() - 1);
+        cursor.selected = Some(self.dropped_count + self.history.len() - 1);
     }
 
-    pub fn next(&mut self) -> Option<&str> {
+    pub fn next(&mut self, cursor: &mut SearchHistoryCursor) -> Option<&SearchHistoryEntry> {
         let history_size = self.history.len();
         if history_size == 0 {
             return None;
         }
 
-        let selected = self.selected?;
+        let selected = self.selected_index(cursor)?;
         if selected == history_size - 1 {
             return None;
         }
         let next_index = selected + 1;
-        self.selected = Some(next_index);
+        cursor.selected = Some(self.dropped_count + next_index);
         Some(&self.history[next_index])
     }
 
-    pub fn current(&self) -> Option<&str> {
-        Some(&self.history[self.selected?])
+    pub fn current(&self, cursor: &SearchHistoryCursor) -> Option<&str> {
+        Some(&self.current_entry(cursor)?.query)
     }
 
-    pub fn previous(&mut self) -> Option<&str> {
+    pub fn current_entry(&self, cursor: &SearchHistoryCursor) -> Option<&SearchHistoryEntry> {
+        Some(&self.history[self.selected_index(cursor)?])
+    }
+
+    pub fn previous(&mut self, cursor: &mut SearchHistoryCursor) -> Option<&SearchHistoryEntry> {
         let history_size = self.history.len();
         if history_size == 0 {
             return None;
         }
 
-        let prev_index = match self.selected {
+        let prev_index = match self.selected_index(cursor) {
             Some(selected_index) => {
                 if selected_index == 0 {
                     return None;
@@ -66,15 +159,65 @@ impl SearchHistory {
             None => history_size - 1,
         };
 
-        self.selected = Some(prev_index);
+        cursor.selected = Some(self.dropped_count + prev_index);
         Some(&self.history[prev_index])
     }
 
-    pub fn reset_selection(&mut self) {
-        self.selected = None;
+    fn selected_index(&self, cursor: &SearchHistoryCursor) -> Option<usize> {
+        cursor
+            .selected?
+            .checked_sub(self.dropped_count)
+            .filter(|index| *index < self.history.len())
     }
 }
 
+/// Adds a search to the history shared by the search bars, saving it shortly after if the
+/// history is persistent.
+pub(crate) fn add_to_search_history(
+    entry: SearchHistoryEntry,
+    cursor: &mut SearchHistoryCursor,
+    cx: &mut AppContext,
+) {
+    let history = cx.default_global::<SearchHistory>();
+    history.add(cursor, entry);
+    if !history.persistent {
+        return;
+    }
+
+    // Replacing the pending save cancels it.
+    let save_task = cx.spawn(|cx| {
+        async move {
+            cx.background_executor()
+                .timer(SEARCH_HISTORY_SAVE_DEBOUNCE)
+                .await;
+            let entries =
+                cx.update(|cx| cx.global::<SearchHistory>().entries().cloned().collect())?;
+            DB.save_search_history(entries).await
+        }
+        .log_err()
+    });
+    cx.global_mut::<SearchHistory>().save_task = Some(save_task);
+}
+
+/// Loads the search history saved by previous sessions, and saves it from now on.
+pub fn load_search_history(cx: &mut AppContext) {
+    let history = DB
+        .search_history()
+        .log_err()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .take(SEARCH_HISTORY_LIMIT)
+        .rev()
+        .collect();
+    cx.set_global(SearchHistory {
+        history,
+        dropped_count: 0,
+        persistent: true,
+        save_task: None,
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,40 +225,41 @@ mod tests {
     #[test]
     fn test_add() {
         let mut search_history = SearchHistory::default();
+        let mut cursor = SearchHistoryCursor::default();
         assert_eq!(
-            search_history.current(),
+            search_history.current(&cursor),
             None,
             "No current selection should be set for the default search history"
         );
 
-        search_history.add("rust".to_string());
+        search_history.add(&mut cursor, entry("rust"));
         assert_eq!(
-            search_history.current(),
+            search_history.current(&cursor),
             Some("rust"),
             "Newly added item should be selected"
         );
 
         // check if duplicates are not added
-        search_history.add("rust".to_string());
+        search_history.add(&mut cursor, entry("rust"));
         assert_eq!(
             search_history.history.len(),
             1,
             "Should not add a duplicate"
         );
-        assert_eq!(search_history.current(), Some("rust"));
+        assert_eq!(search_history.current(&cursor), Some("rust"));
 
         // check if new string containing the previous string replaces it
-        search_history.add("rustlang".to_string());
+        search_history.add(&mut cursor, entry("rustlang"));
         assert_eq!(
             search_history.history.len(),
             1,
             "Should replace previous item if it's a substring"
         );
-        assert_eq!(search_history.current(), Some("rustlang"));
+        assert_eq!(search_history.current(&cursor), Some("rustlang"));
 
         // push enough items to test SEARCH_HISTORY_LIMIT
         for i in 0..SEARCH_HISTORY_LIMIT * 2 {
-            search_history.add(format!("item{i}"));
+            search_history.add(&mut cursor, entry(&format!("item{i}")));
         }
         assert!(search_history.history.len() <= SEARCH_HISTORY_LIMIT);
     }
@@ -123,62 +267,121 @@ mod tests {
     #[test]
     fn test_next_and_previous() {
         let mut search_history = SearchHistory::default();
+        let mut cursor = SearchHistoryCursor::default();
         assert_eq!(
-            search_history.next(),
+            query(search_history.next(&mut cursor)),
             None,
             "Default search history should not have a next item"
         );
 
-        search_history.add("Rust".to_string());
-        assert_eq!(search_history.next(), None);
-        search_history.add("JavaScript".to_string());
-        assert_eq!(search_history.next(), None);
-        search_history.add("TypeScript".to_string());
-        assert_eq!(search_history.next(), None);
+        search_history.add(&mut cursor, entry("Rust"));
+        assert_eq!(query(search_history.next(&mut cursor)), None);
+        search_history.add(&mut cursor, entry("JavaScript"));
+        assert_eq!(query(search_history.next(&mut cursor)), None);
+        search_history.add(&mut cursor, entry("TypeScript"));
+        assert_eq!(query(search_history.next(&mut cursor)), None);
 
-        assert_eq!(search_history.current(), Some("TypeScript"));
+        assert_eq!(search_history.current(&cursor), Some("TypeScript"));
 
-        assert_eq!(search_history.previous(), Some("JavaScript"));
-        assert_eq!(search_history.current(), Some("JavaScript"));
+        assert_eq!(
+            query(search_history.previous(&mut cursor)),
+            Some("JavaScript")
+        );
+        assert_eq!(search_history.current(&cursor), Some("JavaScript"));
 
-        assert_eq!(search_history.previous(), Some("Rust"));
-        assert_eq!(search_history.current(), Some("Rust"));
+        assert_eq!(query(search_history.previous(&mut cursor)), Some("Rust"));
+        assert_eq!(search_history.current(&cursor), Some("Rust"));
 
-        assert_eq!(search_history.previous(), None);
-        assert_eq!(search_history.current(), Some("Rust"));
+        assert_eq!(query(search_history.previous(&mut cursor)), None);
+        assert_eq!(search_history.current(&cursor), Some("Rust"));
 
-        assert_eq!(search_history.next(), Some("JavaScript"));
-        assert_eq!(search_history.current(), Some("JavaScript"));
+        assert_eq!(query(search_history.next(&mut cursor)), Some("JavaScript"));
+        assert_eq!(search_history.current(&cursor), Some("JavaScript"));
 
-        assert_eq!(search_history.next(), Some("TypeScript"));
-        assert_eq!(search_history.current(), Some("TypeScript"));
+        assert_eq!(query(search_history.next(&mut cursor)), Some("TypeScript"));
+        assert_eq!(search_history.current(&cursor), Some("TypeScript"));
 
-        assert_eq!(search_history.next(), None);
-        assert_eq!(search_history.current(), Some("TypeScript"));
+        assert_eq!(query(search_history.next(&mut cursor)), None);
+        assert_eq!(search_history.current(&cursor), Some("TypeScript"));
     }
 
     #[test]
     fn test_reset_selection() {
         let mut search_history = SearchHistory::default();
-        search_history.add("Rust".to_string());
-        search_history.add("JavaScript".to_string());
-        search_history.add("TypeScript".to_string());
+        let mut cursor = SearchHistoryCursor::default();
+        search_history.add(&mut cursor, entry("Rust"));
+        search_history.add(&mut cursor, entry("JavaScript"));
+        search_history.add(&mut cursor, entry("TypeScript"));
 
-        assert_eq!(search_history.current(), Some("TypeScript"));
-        search_history.reset_selection();
-        assert_eq!(search_history.current(), None);
+        assert_eq!(search_history.current(&cursor), Some("TypeScript"));
+        cursor.reset_selection();
+        assert_eq!(search_history.current(&cursor), None);
         assert_eq!(
-            search_history.previous(),
+            query(search_history.previous(&mut cursor)),
             Some("TypeScript"),
             "Should start from the end after reset on previous item query"
         );
 
-        search_history.previous();
-        assert_eq!(search_history.current(), Some("JavaScript"));
-        search_history.previous();
-        assert_eq!(search_history.current(), Some("Rust"));
+        search_history.previous(&mut cursor);
+        assert_eq!(search_history.current(&cursor), Some("JavaScript"));
+        search_history.previous(&mut cursor);
+        assert_eq!(search_history.current(&cursor), Some("Rust"));
+
+        cursor.reset_selection();
+        assert_eq!(search_history.current(&cursor), None);
+    }
+
+    #[test]
+    fn test_shared_between_cursors() {
+        let mut search_history = SearchHistory::default();
+        let mut buffer_cursor = SearchHistoryCursor::default();
+        let mut project_cursor = SearchHistoryCursor::default();
 
-        search_history.reset_selection();
-        assert_eq!(search_history.current(), None);
+        search_history.add(&mut buffer_cursor, entry("Rust"));
+        search_history.add(
+            &mut project_cursor,
+            SearchHistoryEntry {
+                files_to_include: "src/*.rs".into(),
+                ..entry("JavaScript")
+            },
+        );
+        assert_eq!(search_history.current(&buffer_cursor), Some("Rust"));
+        assert_eq!(search_history.current(&project_cursor), Some("JavaScript"));
+        assert_eq!(
+            query(search_history.previous(&mut project_cursor)),
+            Some("Rust")
+        );
+
+        // A search that extends the last one only replaces it for the bar that ran it.
+        search_history.add(&mut buffer_cursor, entry("JavaScripts"));
+        assert_eq!(search_history.history.len(), 3);
+        search_history.add(&mut buffer_cursor, entry("JavaScriptsX"));
+        assert_eq!(search_history.history.len(), 3);
+        assert_eq!(search_history.history[1].files_to_include, "src/*.rs");
+
+        // The cursors keep pointing at their entries when older ones are dropped.
+        let mut other_cursor = SearchHistoryCursor::default();
+        for i in 0..SEARCH_HISTORY_LIMIT - 3 {
+            search_history.add(&mut other_cursor, entry(&format!("{i}")));
+        }
+        assert_eq!(search_history.current(&project_cursor), Some("Rust"));
+        search_history.add(&mut other_cursor, entry("Go"));
+        assert_eq!(search_history.current(&project_cursor), None);
+        assert_eq!(search_history.current(&buffer_cursor), Some("JavaScriptsX"));
+        assert_eq!(
+            query(search_history.previous(&mut project_cursor)),
+            Some("Go")
+        );
+    }
+
+    fn query(entry: Option<&SearchHistoryEntry>) -> Option<&str> {
+        entry.map(|entry| entry.query.as_str())
+    }
+
+    fn entry(query: &str) -> SearchHistoryEntry {
+        SearchHistoryEntry {
+            query: query.to_string(),
+            ..Default::default()
+        }
     }
 }
@@ -1,6 +1,6 @@
 use gpui::{Action, SharedString};
 
-use crate::{ActivateRegexMode, ActivateSemanticMode, ActivateTextMode};
+use crate::{ActivateRegexMode, ActivateSemanticMode, ActivateStructuralMode, ActivateTextMode};
 
 // TODO: Update the default search mode to get from config
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -9,6 +9,7 @@ pub enum SearchMode {
     Text,
     Semantic,
     Regex,
+    Structural,
 }
 
 impl SearchMode {
@@ -17,6 +18,7 @@ impl SearchMode {
             SearchMode::Text => "Text",
             SearchMode::Semantic => "Semantic",
             SearchMode::Regex => "Regex",
+            SearchMode::Structural => "Structural",
         }
     }
     pub(crate) fn tooltip(&self) -> SharedString {
@@ -27,20 +29,33 @@ impl SearchMode {
             SearchMode::Text => ActivateTextMode.boxed_clone(),
             SearchMode::Semantic => ActivateSemanticMode.boxed_clone(),
             SearchMode::Regex => ActivateRegexMode.boxed_clone(),
+            SearchMode::Structural => ActivateStructuralMode.boxed_clone(),
         }
     }
 }
 
-pub(crate) fn next_mode(mode: &SearchMode, semantic_enabled: bool) -> SearchMode {
+pub(crate) fn next_mode(
+    mode: &SearchMode,
+    structural_enabled: bool,
+    semantic_enabled: bool,
+) -> SearchMode {
+    let after_structural = || {
+        if semantic_enabled {
+            SearchMode::Semantic
+        } else {
+            SearchMode::Text
+        }
+    };
     match mode {
         SearchMode::Text => SearchMode::Regex,
         SearchMode::Regex => {
-            if semantic_enabled {
-                SearchMode::Semantic
+            if structural_enabled {
+                SearchMode::Structural
             } else {
-                SearchMode::Text
+                after_structural()
             }
         }
+        SearchMode::Structural => after_structural(),
         SearchMode::Semantic => SearchMode::Text,
     }
 }
@@ -0,0 +1,197 @@
+use anyhow::{anyhow, Result};
+use db::sqlez::{
+    bindable::{Bind, Column, StaticColumnCount},
+    statement::Statement,
+};
+use db::sqlez_macros::sql;
+use db::{define_connection, query};
+
+use crate::{history::SearchHistoryEntry, SearchMode, SearchOptions};
+
+define_connection!(
+    // Current schema shape using pseudo-rust syntax:
+    // search_history(
+    //   position: usize,
+    //   query: String,
+    //   mode: String,
+    //   options: u32,
+    //   files_to_include: String,
+    //   files_to_exclude: String,
+    //   replacement: String,
+    // )
+    //
+    // saved_searches(
+    //   name: String,
+    //   ..the columns of search_history, but position
+    // )
+    pub static ref DB: SearchDb<()> =
+        &[sql! (
+            CREATE TABLE search_history(
+                position INTEGER PRIMARY KEY,
+                query TEXT NOT NULL,
+                mode TEXT NOT NULL,
+                options INTEGER NOT NULL,
+                files_to_include TEXT NOT NULL,
+                files_to_exclude TEXT NOT NULL,
+                replacement TEXT NOT NULL
+            ) STRICT;
+
+            CREATE TABLE saved_searches(
+                name TEXT PRIMARY KEY,
+                query TEXT NOT NULL,
+                mode TEXT NOT NULL,
+                options INTEGER NOT NULL,
+                files_to_include TEXT NOT NULL,
+                files_to_exclude TEXT NOT NULL,
+                replacement TEXT NOT NULL
+            ) STRICT;
+        )];
+);
+
+impl SearchDb {
+    query! {
+        pub fn search_history() -> Result<Vec<SearchHistoryEntry>> {
+            SELECT query, mode, options, files_to_include, files_to_exclude, replacement
+            FROM search_history
+            ORDER BY position
+        }
+    }
+
+    /// Replaces the saved search history with `entries`, most recent last.
+    pub async fn save_search_history(&self, entries: Vec<SearchHistoryEntry>) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_search_history", || {
+                conn.exec(sql!(DELETE FROM search_history))?()?;
+                let mut insert = conn.exec_bound(sql!(
+                    INSERT INTO search_history(position, query, mode, options, files_to_include, files_to_exclude, replacement)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                ))?;
+                for (position, entry) in entries.into_iter().enumerate() {
+                    insert((position, entry))?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    query! {
+        pub fn saved_searches() -> Result<Vec<(String, SearchHistoryEntry)>> {
+            SELECT name, query, mode, options, files_to_include, files_to_exclude, replacement
+            FROM saved_searches
+            ORDER BY name
+        }
+    }
+
+    query! {
+        pub async fn save_search(name: String, entry: SearchHistoryEntry) -> Result<()> {
+            INSERT OR REPLACE INTO saved_searches
+                (name, query, mode, options, files_to_include, files_to_exclude, replacement)
+            VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        }
+    }
+
+    query! {
+        pub async fn delete_saved_search(name: String) -> Result<()> {
+            DELETE FROM saved_searches
+            WHERE name = ?
+        }
+    }
+}
+
+impl StaticColumnCount for SearchHistoryEntry {
+    fn column_count() -> usize {
+        6
+    }
+}
+
+impl Bind for SearchHistoryEntry {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let next_index = statement.bind(&self.query, start_index)?;
+        let next_index = statement.bind(&self.mode.label(), next_index)?;
+        let next_index = statement.bind(&(self.options.bits() as u32), next_index)?;
+        let next_index = statement.bind(&self.files_to_include, next_index)?;
+        let next_index = statement.bind(&self.files_to_exclude, next_index)?;
+        statement.bind(&self.replacement, next_index)
+    }
+}
+
+impl Column for SearchHistoryEntry {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let (query, next_index) = String::column(statement, start_index)?;
+        let (mode, next_index) = String::column(statement, next_index)?;
+        let (options, next_index) = u32::column(statement, next_index)?;
+        let (files_to_include, next_index) = String::column(statement, next_index)?;
+        let (files_to_exclude, next_index) = String::column(statement, next_index)?;
+        let (replacement, next_index) = String::column(statement, next_index)?;
+        let mode = match mode.as_str() {
+            "Text" => SearchMode::Text,
+            "Semantic" => SearchMode::Semantic,
+            "Regex" => SearchMode::Regex,
+            "Structural" => SearchMode::Structural,
+            _ => return Err(anyhow!("unknown search mode {mode:?}")),
+        };
+        Ok((
+            SearchHistoryEntry {
+                query,
+                mode,
+                options: SearchOptions::from_bits_truncate(options as u8),
+                files_to_include,
+                files_to_exclude,
+                replacement,
+            },
+            next_index,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::open_test_db;
+
+    #[gpui::test]
+    async fn test_saved_searches() {
+        let db = SearchDb(open_test_db("test_saved_searches").await);
+
+        let entry = SearchHistoryEntry {
+            query: "fn (\\w+)".into(),
+            mode: SearchMode::Regex,
+            options: SearchOptions::CASE_SENSITIVE | SearchOptions::INCLUDE_IGNORED,
+            files_to_include: "src/*.rs".into(),
+            files_to_exclude: "target".into(),
+            replacement: "fn $1".into(),
+        };
+        let other_entry = SearchHistoryEntry {
+            query: "TODO".into(),
+            ..Default::default()
+        };
+
+        db.save_search_history(vec![entry.clone(), other_entry.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            db.search_history().unwrap(),
+            vec![entry.clone(), other_entry.clone()]
+        );
+
+        db.save_search("functions".into(), entry.clone())
+            .await
+            .unwrap();
+        db.save_search("todos".into(), entry.clone()).await.unwrap();
+        db.save_search("todos".into(), other_entry.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.saved_searches().unwrap(),
+            vec![
+                ("functions".to_string(), entry),
+                ("todos".to_string(), other_entry)
+            ]
+        );
+
+        db.delete_saved_search("functions".into()).await.unwrap();
+        assert_eq!(db.saved_searches().unwrap().len(), 1);
+    }
+}
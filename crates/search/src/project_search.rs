@@ -1,8 +1,10 @@
 use crate::{
-    history::SearchHistory, mode::SearchMode, ActivateRegexMode, ActivateSemanticMode,
-    ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery, ReplaceAll, ReplaceNext,
-    SearchOptions, SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive, ToggleIncludeIgnored,
-    ToggleReplace, ToggleWholeWord,
+    history::{add_to_search_history, SearchHistory, SearchHistoryCursor, SearchHistoryEntry},
+    mode::SearchMode,
+    ActivateRegexMode, ActivateSemanticMode, ActivateStructuralMode, ActivateTextMode, CycleMode,
+    NextHistoryQuery, PreviousHistoryQuery, ReplaceAll, ReplaceNext, SearchOptions,
+    SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive, ToggleIncludeIgnored, ToggleReplace,
+    ToggleWholeWord,
 };
 use anyhow::{Context as _, Result};
 use collections::HashMap;
@@ -79,6 +81,12 @@ pub fn init(cx: &mut AppContext) {
         register_workspace_action(workspace, move |search_bar, _: &ActivateTextMode, cx| {
             search_bar.activate_search_mode(SearchMode::Text, cx)
         });
+        register_workspace_action(
+            workspace,
+            move |search_bar, _: &ActivateStructuralMode, cx| {
+                search_bar.activate_search_mode(SearchMode::Structural, cx)
+            },
+        );
         register_workspace_action(
             workspace,
             move |search_bar, _: &ActivateSemanticMode, cx| {
@@ -134,7 +142,7 @@ struct ProjectSearch {
     match_ranges: Vec<Range<Anchor>>,
     active_query: Option<SearchQuery>,
     search_id: usize,
-    search_history: SearchHistory,
+    search_history_cursor: SearchHistoryCursor,
     no_results: Option<bool>,
 }
 
@@ -196,7 +204,7 @@ impl ProjectSearch {
             match_ranges: Default::default(),
             active_query: None,
             search_id: 0,
-            search_history: SearchHistory::default(),
+            search_history_cursor: SearchHistoryCursor::default(),
             no_results: None,
         }
     }
@@ -211,7 +219,7 @@ impl ProjectSearch {
             match_ranges: self.match_ranges.clone(),
             active_query: self.active_query.clone(),
             search_id: self.search_id,
-            search_history: self.search_history.clone(),
+            search_history_cursor: self.search_history_cursor.clone(),
             no_results: self.no_results.clone(),
         })
     }
@@ -221,7 +229,11 @@ impl ProjectSearch {
             .project
             .update(cx, |project, cx| project.search(query.clone(), cx));
         self.search_id += 1;
-        self.search_history.add(query.as_str().to_string());
+        add_to_search_history(
+            SearchHistoryEntry::from_query(&query),
+            &mut self.search_history_cursor,
+            cx,
+        );
         self.active_query = Some(query);
         self.match_ranges.clear();
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
@@ -277,7 +289,11 @@ impl ProjectSearch {
         });
         self.search_id += 1;
         self.match_ranges.clear();
-        self.search_history.add(inputs.as_str().to_string());
+        add_to_search_history(
+            SearchHistoryEntry::from_semantic_inputs(inputs),
+            &mut self.search_history_cursor,
+            cx,
+        );
         self.no_results = None;
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
             let results = search?.await.log_err()?;
@@ -473,12 +489,9 @@ impl Item for ProjectSearchView {
     }
 
     fn tab_content(&self, _: Option<usize>, selected: bool, cx: &WindowContext<'_>) -> AnyElement {
-        let last_query: Option<SharedString> = self
-            .model
-            .read(cx)
-            .search_history
-            .current()
-            .as_ref()
+        let last_query: Option<SharedString> = cx
+            .try_global::<SearchHistory>()
+            .and_then(|history| history.current(&self.model.read(cx).search_history_cursor))
             .map(|query| {
                 let query = query.replace('\n', "");
                 let query_text = util::truncate_and_trailoff(&query, MAX_TAB_TITLE_LEN);
@@ -778,7 +791,7 @@ impl ProjectSearchView {
                     anyhow::Ok(())
                 }).detach_and_log_err(cx);
             }
-            SearchMode::Regex | SearchMode::Text => {
+            SearchMode::Regex | SearchMode::Text | SearchMode::Structural => {
                 self.semantic_state = None;
                 self.active_match_index = None;
                 self.search(cx);
@@ -1118,7 +1131,10 @@ impl ProjectSearchView {
             }
 
             _ => {
-                if let Some(query) = self.build_search_query(cx) {
+                if let Some(mut query) = self.build_search_query(cx) {
+                    if self.replace_enabled {
+                        query = query.with_replacement(self.replacement(cx));
+                    }
                     self.model.update(cx, |model, cx| model.search(query, cx));
                 }
             }
@@ -1194,6 +1210,29 @@ impl ProjectSearchView {
                     }
                 }
             }
+            SearchMode::Structural => match SearchQuery::structural(
+                text,
+                self.search_options.contains(SearchOptions::INCLUDE_IGNORED),
+                included_files,
+                excluded_files,
+            ) {
+                Ok(query) => {
+                    let should_unmark_error = self.panels_with_errors.remove(&InputPanel::Query);
+                    if should_unmark_error {
+                        cx.notify();
+                    }
+
+                    Some(query)
+                }
+                Err(_e) => {
+                    let should_mark_error = self.panels_with_errors.insert(InputPanel::Query);
+                    if should_mark_error {
+                        cx.notify();
+                    }
+
+                    None
+                }
+            },
             _ => match SearchQuery::text(
                 text,
                 self.search_options.contains(SearchOptions::WHOLE_WORD),
@@ -1269,6 +1308,92 @@ impl ProjectSearchView {
             .update(cx, |query_editor, cx| query_editor.set_text(query, cx));
     }
 
+    /// The search that's currently typed in, which might not have been run yet.
+    pub(crate) fn history_entry(&self, cx: &AppContext) -> SearchHistoryEntry {
+        let text_if = |enabled: bool, editor: &View<Editor>| {
+            if enabled {
+                editor.read(cx).text(cx)
+            } else {
+                String::new()
+            }
+        };
+        SearchHistoryEntry {
+            query: self.query_editor.read(cx).text(cx),
+            mode: self.current_mode,
+            options: self.search_options,
+            files_to_include: text_if(self.filters_enabled, &self.included_files_editor),
+            files_to_exclude: text_if(self.filters_enabled, &self.excluded_files_editor),
+            replacement: text_if(self.replace_enabled, &self.replacement_editor),
+        }
+    }
+
+    /// Fills in the inputs of a search from the history or a saved one, and runs it.
+    pub(crate) fn run_history_entry(
+        &mut self,
+        entry: SearchHistoryEntry,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.fill_history_entry(&entry, cx);
+        self.search_options = entry.options;
+        if self.current_mode == entry.mode {
+            self.search(cx);
+        } else {
+            // Switching modes runs the search.
+            self.activate_search_mode(entry.mode, cx);
+        }
+        cx.notify();
+    }
+
+    /// Fills in the inputs of a search navigated to in the history, switching to its mode.
+    fn show_history_entry(&mut self, entry: &SearchHistoryEntry, cx: &mut ViewContext<Self>) {
+        self.fill_history_entry(entry, cx);
+        self.activate_search_mode(entry.mode, cx);
+        cx.notify();
+    }
+
+    fn fill_history_entry(&mut self, entry: &SearchHistoryEntry, cx: &mut ViewContext<Self>) {
+        self.set_query(&entry.query, cx);
+        self.included_files_editor.update(cx, |editor, cx| {
+            editor.set_text(entry.files_to_include.as_str(), cx)
+        });
+        self.excluded_files_editor.update(cx, |editor, cx| {
+            editor.set_text(entry.files_to_exclude.as_str(), cx)
+        });
+        self.replacement_editor.update(cx, |editor, cx| {
+            editor.set_text(entry.replacement.as_str(), cx)
+        });
+        self.filters_enabled =
+            !entry.files_to_include.is_empty() || !entry.files_to_exclude.is_empty();
+        self.replace_enabled = !entry.replacement.is_empty();
+    }
+
+    /// Runs a search from the history or a saved one in the project search of the active pane,
+    /// or in a new one.
+    pub(crate) fn open_history_entry(
+        workspace: &mut Workspace,
+        entry: SearchHistoryEntry,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let existing = workspace
+            .active_pane()
+            .read(cx)
+            .items()
+            .find_map(|item| item.downcast::<ProjectSearchView>());
+        let search = if let Some(existing) = existing {
+            workspace.activate_item(&existing, cx);
+            existing
+        } else {
+            let model = cx.new_model(|cx| ProjectSearch::new(workspace.project().clone(), cx));
+            let view = cx.new_view(|cx| ProjectSearchView::new(model, cx, None));
+            workspace.add_item(Box::new(view.clone()), cx);
+            view
+        };
+        search.update(cx, |search, cx| {
+            search.run_history_entry(entry, cx);
+            search.focus_results_editor(cx);
+        });
+    }
+
     fn focus_results_editor(&mut self, cx: &mut ViewContext<Self>) {
         self.query_editor.update(cx, |query_editor, cx| {
             let cursor = query_editor.selections.newest_anchor().head();
@@ -1280,6 +1405,18 @@ impl ProjectSearchView {
     }
 
     fn model_changed(&mut self, cx: &mut ViewContext<Self>) {
+        // Structural queries are compiled for the grammars of the searched files, so patterns
+        // that none of them accept are only reported once the search is done.
+        let model = self.model.read(cx);
+        if model.pending_search.is_none()
+            && model
+                .active_query
+                .as_ref()
+                .map_or(false, |query| query.structural_error().is_some())
+        {
+            self.panels_with_errors.insert(InputPanel::Query);
+        }
+
         let match_ranges = self.model.read(cx).match_ranges.clone();
         if match_ranges.is_empty() {
             self.active_match_index = None;
@@ -1333,6 +1470,7 @@ impl ProjectSearchView {
     fn landing_text_minor(&self) -> SharedString {
         match self.current_mode {
             SearchMode::Text | SearchMode::Regex => "Include/exclude specific paths with the filter option. Matching exact word and/or casing is available too.".into(),
+            SearchMode::Structural => "\nMatch syntax nodes with a tree-sitter query. ex. '(call_expression function: (identifier) @name (#eq? @name \"foo\")) @match'. Captures can be used as $name in the replacement.".into(),
             SearchMode::Semantic => "\nSimply explain the code you are looking to find. ex. 'prompt user for permissions to index their project'".into()
         }
     }
@@ -1365,7 +1503,7 @@ impl ProjectSearchBar {
         if let Some(view) = self.active_project_search.as_ref() {
             view.update(cx, |this, cx| {
                 let new_mode =
-                    crate::mode::next_mode(&this.current_mode, SemanticIndex::enabled(cx));
+                    crate::mode::next_mode(&this.current_mode, true, SemanticIndex::enabled(cx));
                 this.activate_search_mode(new_mode, cx);
                 let editor_handle = this.query_editor.focus_handle(cx);
                 cx.focus(&editor_handle);
@@ -1518,15 +1656,21 @@ impl ProjectSearchBar {
     fn next_history_query(&mut self, _: &NextHistoryQuery, cx: &mut ViewContext<Self>) {
         if let Some(search_view) = self.active_project_search.as_ref() {
             search_view.update(cx, |search_view, cx| {
-                let new_query = search_view.model.update(cx, |model, _| {
-                    if let Some(new_query) = model.search_history.next().map(str::to_string) {
-                        new_query
-                    } else {
-                        model.search_history.reset_selection();
-                        String::new()
+                let new_entry = search_view.model.update(cx, |model, cx| {
+                    let new_entry = cx
+                        .default_global::<SearchHistory>()
+                        .next(&mut model.search_history_cursor)
+                        .cloned();
+                    if new_entry.is_none() {
+                        model.search_history_cursor.reset_selection();
                     }
+                    new_entry
                 });
-                search_view.set_query(&new_query, cx);
+                if let Some(new_entry) = new_entry {
+                    search_view.show_history_entry(&new_entry, cx);
+                } else {
+                    search_view.set_query("", cx);
+                }
             });
         }
     }
@@ -1535,22 +1679,24 @@ impl ProjectSearchBar {
         if let Some(search_view) = self.active_project_search.as_ref() {
             search_view.update(cx, |search_view, cx| {
                 if search_view.query_editor.read(cx).text(cx).is_empty() {
-                    if let Some(new_query) = search_view
-                        .model
-                        .read(cx)
-                        .search_history
-                        .current()
-                        .map(str::to_string)
+                    if let Some(new_entry) = cx
+                        .try_global::<SearchHistory>()
+                        .and_then(|history| {
+                            history.current_entry(&search_view.model.read(cx).search_history_cursor)
+                        })
+                        .cloned()
                     {
-                        search_view.set_query(&new_query, cx);
+                        search_view.show_history_entry(&new_entry, cx);
                         return;
                     }
                 }
 
-                if let Some(new_query) = search_view.model.update(cx, |model, _| {
-                    model.search_history.previous().map(str::to_string)
+                if let Some(new_entry) = search_view.model.update(cx, |model, cx| {
+                    cx.default_global::<SearchHistory>()
+                        .previous(&mut model.search_history_cursor)
+                        .cloned()
                 }) {
-                    search_view.set_query(&new_query, cx);
+                    search_view.show_history_entry(&new_entry, cx);
                 }
             });
         }
@@ -1768,6 +1914,23 @@ impl Render for ProjectSearchBar {
                                         cx,
                                     )
                                 })
+                                .middle(),
+                        )
+                        .child(
+                            ToggleButton::new("project-search-structural-button", "Structural")
+                                .style(ButtonStyle::Filled)
+                                .size(ButtonSize::Large)
+                                .selected(search.current_mode == SearchMode::Structural)
+                                .on_click(cx.listener(|this, _, cx| {
+                                    this.activate_search_mode(SearchMode::Structural, cx)
+                                }))
+                                .tooltip(|cx| {
+                                    Tooltip::for_action(
+                                        "Toggle structural search",
+                                        &ActivateStructuralMode,
+                                        cx,
+                                    )
+                                })
                                 .map(|this| {
                                     if semantic_is_available {
                                         this.middle()
@@ -1895,6 +2058,9 @@ impl Render for ProjectSearchBar {
             .on_action(cx.listener(|this, _: &ActivateRegexMode, cx| {
                 this.activate_search_mode(SearchMode::Regex, cx)
             }))
+            .on_action(cx.listener(|this, _: &ActivateStructuralMode, cx| {
+                this.activate_search_mode(SearchMode::Structural, cx)
+            }))
             .on_action(cx.listener(|this, _: &ActivateSemanticMode, cx| {
                 this.activate_search_mode(SearchMode::Semantic, cx)
             }))
@@ -2092,6 +2258,7 @@ fn register_workspace_action_for_present_search<A: Action>(
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::BufferSearchBar;
     use editor::DisplayPoint;
     use gpui::{Action, TestAppContext};
     use project::FakeFs;
@@ -3226,6 +3393,129 @@ pub mod tests {
             .unwrap();
     }
 
+    #[gpui::test]
+    async fn test_search_history_shared_with_buffer_search(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.background_executor.clone());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                "one.rs": "const ONE: usize = 1;",
+                "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let window = cx.add_window(|cx| Workspace::test_new(project, cx));
+        let workspace = window.root(cx).unwrap();
+        let search_bar = window.build_view(cx, |_| ProjectSearchBar::new());
+        window
+            .update(cx, {
+                let search_bar = search_bar.clone();
+                move |workspace, cx| {
+                    workspace.panes()[0].update(cx, move |pane, cx| {
+                        pane.toolbar()
+                            .update(cx, |toolbar, cx| toolbar.add_item(search_bar, cx))
+                    });
+
+                    ProjectSearchView::new_search(workspace, &workspace::NewSearch, cx)
+                }
+            })
+            .unwrap();
+        let search_view = cx.read(|cx| {
+            workspace
+                .read(cx)
+                .active_pane()
+                .read(cx)
+                .active_item()
+                .and_then(|item| item.downcast::<ProjectSearchView>())
+                .expect("Search view expected to appear after new search event trigger")
+        });
+        let buffer_search_bar = window.build_view(cx, |cx| {
+            let editor = cx.new_view(|cx| {
+                let mut editor = Editor::multi_line(cx);
+                editor.set_text("const TWO: usize = one::ONE + one::ONE;", cx);
+                editor
+            });
+            let mut search_bar = BufferSearchBar::new(cx);
+            search_bar.set_active_pane_item(Some(&editor), cx);
+            search_bar.show(cx);
+            search_bar
+        });
+
+        // Search in the buffer, then in the project with filters and a replacement.
+        window
+            .update(cx, |_, cx| {
+                buffer_search_bar.update(cx, |search_bar, cx| {
+                    let _ = search_bar.search("TWO", None, cx);
+                })
+            })
+            .unwrap();
+        cx.background_executor.run_until_parked();
+        window
+            .update(cx, |_, cx| {
+                search_view.update(cx, |search_view, cx| {
+                    search_view.run_history_entry(
+                        SearchHistoryEntry {
+                            query: "ONE".into(),
+                            mode: SearchMode::Regex,
+                            options: SearchOptions::CASE_SENSITIVE,
+                            files_to_include: "*.rs".into(),
+                            files_to_exclude: String::new(),
+                            replacement: "1".into(),
+                        },
+                        cx,
+                    )
+                })
+            })
+            .unwrap();
+        cx.background_executor.run_until_parked();
+
+        // The project search goes back to the buffer search, without its filters and replacement.
+        window
+            .update(cx, |_, cx| {
+                search_bar.update(cx, |search_bar, cx| {
+                    search_bar.previous_history_query(&PreviousHistoryQuery, cx);
+                })
+            })
+            .unwrap();
+        cx.background_executor.run_until_parked();
+        window
+            .update(cx, |_, cx| {
+                search_view.update(cx, |search_view, cx| {
+                    assert_eq!(search_view.query_editor.read(cx).text(cx), "TWO");
+                    assert_eq!(search_view.current_mode, SearchMode::Text);
+                    assert!(!search_view.filters_enabled);
+                    assert_eq!(search_view.included_files_editor.read(cx).text(cx), "");
+                    assert!(!search_view.replace_enabled);
+                });
+            })
+            .unwrap();
+
+        // And forward to its own search, restoring them.
+        window
+            .update(cx, |_, cx| {
+                search_bar.update(cx, |search_bar, cx| {
+                    search_bar.next_history_query(&NextHistoryQuery, cx);
+                })
+            })
+            .unwrap();
+        cx.background_executor.run_until_parked();
+        window
+            .update(cx, |_, cx| {
+                search_view.update(cx, |search_view, cx| {
+                    assert_eq!(search_view.query_editor.read(cx).text(cx), "ONE");
+                    assert_eq!(search_view.current_mode, SearchMode::Regex);
+                    assert!(search_view.filters_enabled);
+                    assert_eq!(search_view.included_files_editor.read(cx).text(cx), "*.rs");
+                    assert!(search_view.replace_enabled);
+                    assert_eq!(search_view.replacement(cx), "1");
+                });
+            })
+            .unwrap();
+    }
+
     #[gpui::test]
     async fn test_deploy_search_with_multiple_panes(cx: &mut TestAppContext) {
         init_test(cx);
@@ -0,0 +1,374 @@
+use crate::{history::SearchHistoryEntry, persistence::DB, OpenSavedSearches, ProjectSearchView};
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Subscription, Task, View,
+    ViewContext, WeakView,
+};
+use picker::{Picker, PickerDelegate};
+use std::sync::Arc;
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(SavedSearches::register).detach();
+}
+
+/// A picker of the searches saved by name, which runs the selected one in the project search.
+/// The search typed in the active project search can be saved from it too.
+pub struct SavedSearches {
+    picker: View<Picker<SavedSearchesDelegate>>,
+    _subscription: Subscription,
+}
+
+impl ModalView for SavedSearches {}
+
+impl SavedSearches {
+    fn new(delegate: SavedSearchesDelegate, cx: &mut ViewContext<Self>) -> Self {
+        let picker = cx.new_view(|cx| Picker::new(delegate, cx));
+        let _subscription = cx.subscribe(&picker, |_, _, _, cx| cx.emit(DismissEvent));
+        cx.spawn(|this, mut cx| async move {
+            let saved_searches = cx
+                .background_executor()
+                .spawn(async move { DB.saved_searches().log_err().unwrap_or_default() })
+                .await;
+            this.update(&mut cx, move |this, cx| {
+                this.picker.update(cx, move |picker, cx| {
+                    picker.delegate.saved_searches = saved_searches;
+                    picker.update_matches(picker.query(cx), cx)
+                })
+            })
+            .ok()
+        })
+        .detach();
+        Self {
+            picker,
+            _subscription,
+        }
+    }
+
+    fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+        workspace.register_action(|workspace, _: &OpenSavedSearches, cx| {
+            let current_search = workspace
+                .active_item(cx)
+                .and_then(|item| item.downcast::<ProjectSearchView>())
+                .map(|search| search.read(cx).history_entry(cx))
+                .filter(|entry| !entry.query.is_empty());
+            let weak_workspace = cx.view().downgrade();
+            workspace.toggle_modal(cx, |cx| {
+                SavedSearches::new(
+                    SavedSearchesDelegate::new(weak_workspace, current_search),
+                    cx,
+                )
+            });
+        });
+    }
+}
+
+impl EventEmitter<DismissEvent> for SavedSearches {}
+
+impl FocusableView for SavedSearches {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for SavedSearches {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .w(rems(34.))
+            .child(self.picker.clone())
+            .on_mouse_down_out(cx.listener(|this, _, cx| {
+                this.picker.update(cx, |this, cx| {
+                    this.cancel(&Default::default(), cx);
+                })
+            }))
+    }
+}
+
+pub struct SavedSearchesDelegate {
+    workspace: WeakView<Workspace>,
+    saved_searches: Vec<(String, SearchHistoryEntry)>,
+    /// The search typed in the active project search, which can be saved under the query.
+    current_search: Option<SearchHistoryEntry>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+    query: String,
+}
+
+impl SavedSearchesDelegate {
+    fn new(workspace: WeakView<Workspace>, current_search: Option<SearchHistoryEntry>) -> Self {
+        Self {
+            workspace,
+            saved_searches: Vec::new(),
+            current_search,
+            matches: Vec::new(),
+            selected_index: 0,
+            query: String::new(),
+        }
+    }
+
+    /// The name that the current search can be saved under, which is offered after the matches.
+    fn name_to_save(&self) -> Option<&str> {
+        let name = self.query.trim();
+        let is_saved = self.saved_searches.iter().any(|(saved, _)| saved == name);
+        (self.current_search.is_some() && !name.is_empty() && !is_saved).then_some(name)
+    }
+}
+
+impl EventEmitter<DismissEvent> for SavedSearchesDelegate {}
+
+impl PickerDelegate for SavedSearchesDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        "Run a saved search, or name the current one to save it...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len() + self.name_to_save().is_some() as usize
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let candidates = self
+            .saved_searches
+            .iter()
+            .enumerate()
+            .map(|(id, (name, _))| StringMatchCandidate::new(id, name.clone()))
+            .collect::<Vec<_>>();
+        let executor = cx.background_executor().clone();
+        cx.spawn(move |picker, mut cx| async move {
+            let matches = if query.trim().is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                fuzzy::match_strings(
+                    candidates.as_slice(),
+                    query.trim(),
+                    false,
+                    100,
+                    &Default::default(),
+                    executor,
+                )
+                .await
+            };
+            picker
+                .update(&mut cx, |picker, cx| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    delegate.query = query;
+                    delegate.selected_index = delegate
+                        .selected_index
+                        .min(delegate.match_count().saturating_sub(1));
+                    cx.notify();
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, secondary: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(selected_match) = self.matches.get(self.selected_index) else {
+            if let Some((name, entry)) = self.name_to_save().zip(self.current_search.as_ref()) {
+                cx.background_executor()
+                    .spawn(DB.save_search(name.to_string(), entry.clone()))
+                    .detach_and_log_err(cx);
+                cx.emit(DismissEvent);
+            }
+            return;
+        };
+
+        let candidate_id = selected_match.candidate_id;
+        if secondary {
+            let (name, _) = self.saved_searches.remove(candidate_id);
+            // Keep the matches pointing at the right searches until they're updated.
+            self.matches
+                .retain(|string_match| string_match.candidate_id != candidate_id);
+            for string_match in &mut self.matches {
+                if string_match.candidate_id > candidate_id {
+                    string_match.candidate_id -= 1;
+                }
+            }
+            cx.background_executor()
+                .spawn(DB.delete_saved_search(name))
+                .detach_and_log_err(cx);
+            let query = self.query.clone();
+            self.update_matches(query, cx).detach();
+            cx.notify();
+        } else {
+            let (_, entry) = self.saved_searches[candidate_id].clone();
+            self.workspace
+                .update(cx, |workspace, cx| {
+                    ProjectSearchView::open_history_entry(workspace, entry, cx)
+                })
+                .log_err();
+            cx.emit(DismissEvent);
+        }
+    }
+
+    fn dismissed(&mut self, _: &mut ViewContext<Picker<Self>>) {}
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let item = ListItem::new(ix)
+            .inset(true)
+            .spacing(ListItemSpacing::Sparse)
+            .selected(selected);
+        let Some(selected_match) = self.matches.get(ix) else {
+            let name = self.name_to_save()?;
+            return Some(item.child(Label::new(format!("Save current search as \"{name}\""))));
+        };
+
+        let (name, entry) = &self.saved_searches[selected_match.candidate_id];
+        Some(
+            item.child(
+                h_flex()
+                    .gap_2()
+                    .child(HighlightedLabel::new(
+                        name.clone(),
+                        selected_match.positions.clone(),
+                    ))
+                    .child(
+                        Label::new(format!("{}: {}", entry.mode.label(), entry.query))
+                            .color(Color::Muted),
+                    ),
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{project_search::tests::init_test, SearchMode};
+    use gpui::{TestAppContext, VisualTestContext};
+    use menu::{Confirm, SecondaryConfirm};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_saved_searches(cx: &mut TestAppContext) {
+        init_test(cx);
+        cx.update(init);
+
+        let fs = FakeFs::new(cx.background_executor.clone());
+        fs.insert_tree(
+            "/dir",
+            json!({
+                "one.rs": "const ONE: usize = 1;",
+            }),
+        )
+        .await;
+        let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+
+        cx.dispatch_action(workspace::NewSearch);
+        let search_view = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_item(cx)
+                .and_then(|item| item.downcast::<ProjectSearchView>())
+                .unwrap()
+        });
+        search_view.update(cx, |search_view, cx| {
+            search_view.run_history_entry(
+                SearchHistoryEntry {
+                    query: "ONE".into(),
+                    mode: SearchMode::Regex,
+                    files_to_include: "*.rs".into(),
+                    ..Default::default()
+                },
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        let saved_entry = search_view.update(cx, |search_view, cx| search_view.history_entry(cx));
+
+        // The search typed in the project search is saved under the query.
+        let picker = open_saved_searches(&workspace, cx);
+        cx.simulate_input("constants");
+        cx.run_until_parked();
+        picker.update(cx, |picker, _| {
+            assert!(picker.delegate.matches.is_empty());
+            assert_eq!(picker.delegate.name_to_save(), Some("constants"));
+        });
+        cx.dispatch_action(Confirm);
+        cx.run_until_parked();
+        assert_eq!(
+            DB.saved_searches().unwrap(),
+            vec![("constants".to_string(), saved_entry.clone())]
+        );
+
+        // Saved searches are matched fuzzily, and run in the project search.
+        search_view.update(cx, |search_view, cx| {
+            search_view.run_history_entry(
+                SearchHistoryEntry {
+                    query: "usize".into(),
+                    ..Default::default()
+                },
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        let picker = open_saved_searches(&workspace, cx);
+        cx.simulate_input("cnst");
+        cx.run_until_parked();
+        picker.update(cx, |picker, _| {
+            let names = picker
+                .delegate
+                .matches
+                .iter()
+                .map(|string_match| string_match.string.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["constants"]);
+            assert_eq!(picker.delegate.match_count(), 2);
+            assert_eq!(picker.delegate.selected_index(), 0);
+        });
+        cx.dispatch_action(Confirm);
+        cx.run_until_parked();
+        search_view.update(cx, |search_view, cx| {
+            assert_eq!(search_view.history_entry(cx), saved_entry);
+        });
+
+        // They can be deleted too.
+        let picker = open_saved_searches(&workspace, cx);
+        cx.run_until_parked();
+        picker.update(cx, |picker, _| assert_eq!(picker.delegate.matches.len(), 1));
+        cx.dispatch_action(SecondaryConfirm);
+        cx.run_until_parked();
+        picker.update(cx, |picker, _| assert_eq!(picker.delegate.match_count(), 0));
+        assert_eq!(DB.saved_searches().unwrap(), Vec::new());
+    }
+
+    fn open_saved_searches(
+        workspace: &View<Workspace>,
+        cx: &mut VisualTestContext,
+    ) -> View<Picker<SavedSearchesDelegate>> {
+        cx.dispatch_action(OpenSavedSearches);
+        workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<SavedSearches>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        })
+    }
+}
@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 pub use buffer_search::BufferSearchBar;
 use gpui::{actions, Action, AppContext, IntoElement};
+pub use history::load_search_history;
 pub use mode::SearchMode;
 use project::search::SearchQuery;
 pub use project_search::ProjectSearchView;
@@ -10,13 +11,16 @@ use ui::{ButtonStyle, IconButton};
 pub mod buffer_search;
 mod history;
 mod mode;
+mod persistence;
 pub mod project_search;
+mod saved_searches;
 pub(crate) mod search_bar;
 
 pub fn init(cx: &mut AppContext) {
     menu::init();
     buffer_search::init(cx);
     project_search::init(cx);
+    saved_searches::init(cx);
 }
 
 actions!(
@@ -35,8 +39,10 @@ actions!(
         ActivateTextMode,
         ActivateSemanticMode,
         ActivateRegexMode,
+        ActivateStructuralMode,
         ReplaceAll,
         ReplaceNext,
+        OpenSavedSearches,
     ]
 );
 
@@ -8,7 +8,7 @@ mod semantic_index_tests;
 
 use crate::semantic_index_settings::SemanticIndexSettings;
 use ai::embedding::{Embedding, EmbeddingProvider};
-use ai::providers::open_ai::OpenAIEmbeddingProvider;
+use ai::providers::{LanguageModelSettings, SelectedModel};
 use anyhow::{anyhow, Context as _, Result};
 use collections::{BTreeMap, HashMap, HashSet};
 use db::VectorDatabase;
@@ -25,7 +25,7 @@ use parking_lot::Mutex;
 use parsing::{CodeContextRetriever, Span, SpanDigest, PARSEABLE_ENTIRE_FILE_TYPES};
 use postage::watch;
 use project::{Fs, PathChange, Project, ProjectEntryId, Worktree, WorktreeId};
-use settings::Settings;
+use settings::{Settings, SettingsStore};
 use smol::channel;
 use std::{
     cmp::Reverse,
@@ -38,12 +38,16 @@ use std::{
     time::{Duration, Instant, SystemTime},
 };
 use util::paths::PathMatcher;
-use util::{channel::RELEASE_CHANNEL_NAME, http::HttpClient, paths::EMBEDDINGS_DIR, ResultExt};
+use util::{
+    channel::RELEASE_CHANNEL_NAME, http::HttpClient, paths::EMBEDDINGS_DIR, ResultExt, TryFutureExt,
+};
 use workspace::Workspace;
 
 const SEMANTIC_INDEX_VERSION: usize = 11;
 const BACKGROUND_INDEXING_DELAY: Duration = Duration::from_secs(5 * 60);
 const EMBEDDING_QUEUE_FLUSH_TIMEOUT: Duration = Duration::from_millis(250);
+/// The model whose embeddings are stored in the database that predates configurable models.
+const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-ada-002";
 
 lazy_static! {
     static ref OPENAI_API_KEY: Option<String> = env::var("OPENAI_API_KEY").ok();
@@ -56,10 +60,7 @@ pub fn init(
     cx: &mut AppContext,
 ) {
     SemanticIndexSettings::register(cx);
-
-    let db_file_path = EMBEDDINGS_DIR
-        .join(Path::new(RELEASE_CHANNEL_NAME.as_str()))
-        .join("embeddings_db");
+    LanguageModelSettings::register(cx);
 
     cx.observe_new_views(
         |workspace: &mut Workspace, cx: &mut ViewContext<Workspace>| {
@@ -89,23 +90,87 @@ pub fn init(
     )
     .detach();
 
-    cx.spawn(move |cx| async move {
-        let embedding_provider =
-            OpenAIEmbeddingProvider::new(http_client, cx.background_executor().clone()).await;
-        let semantic_index = SemanticIndex::new(
-            fs,
-            db_file_path,
-            Arc::new(embedding_provider),
-            language_registry,
-            cx.clone(),
-        )
-        .await?;
+    let mut loader = SemanticIndexLoader {
+        fs,
+        http_client,
+        language_registry,
+        embedding_model: None,
+        pending_index: None,
+    };
+    loader.update(cx);
+    cx.observe_global::<SettingsStore>(move |cx| loader.update(cx))
+        .detach();
+}
+
+/// Loads the global semantic index for the embedding model of the language model settings, and
+/// loads another one whenever the model changes.
+struct SemanticIndexLoader {
+    fs: Arc<dyn Fs>,
+    http_client: Arc<dyn HttpClient>,
+    language_registry: Arc<LanguageRegistry>,
+    embedding_model: Option<Result<SelectedModel, String>>,
+    pending_index: Option<Task<Option<()>>>,
+}
 
-        cx.update(|cx| cx.set_global(semantic_index.clone()))?;
+impl SemanticIndexLoader {
+    fn update(&mut self, cx: &mut AppContext) {
+        let embedding_model = LanguageModelSettings::get_global(cx)
+            .embedding_model()
+            .map_err(|error| error.to_string());
+        if self.embedding_model.as_ref() == Some(&embedding_model) {
+            return;
+        }
+        self.embedding_model = Some(embedding_model.clone());
+
+        let embedding_model = match embedding_model {
+            Ok(embedding_model) => embedding_model,
+            Err(error) => {
+                log::error!("semantic index is unavailable: {error}");
+                self.pending_index = None;
+                if cx.has_global::<Model<SemanticIndex>>() {
+                    cx.remove_global::<Model<SemanticIndex>>();
+                }
+                return;
+            }
+        };
 
-        anyhow::Ok(())
-    })
-    .detach();
+        // Embeddings of different models can't be compared, so each model gets its own database.
+        let db_file_name = if embedding_model.name == DEFAULT_EMBEDDING_MODEL {
+            "embeddings_db".to_string()
+        } else {
+            format!(
+                "embeddings_db-{}",
+                embedding_model.name.replace(['/', ':'], "-")
+            )
+        };
+        let db_file_path = EMBEDDINGS_DIR
+            .join(Path::new(RELEASE_CHANNEL_NAME.as_str()))
+            .join(db_file_name);
+
+        let fs = self.fs.clone();
+        let http_client = self.http_client.clone();
+        let language_registry = self.language_registry.clone();
+        self.pending_index = Some(cx.spawn(move |cx| {
+            async move {
+                let embedding_provider = embedding_model
+                    .embedding_provider(http_client, cx.background_executor().clone())
+                    .await?;
+                let semantic_index = SemanticIndex::new(
+                    fs,
+                    db_file_path,
+                    embedding_provider,
+                    language_registry,
+                    cx.clone(),
+                )
+                .await?;
+
+                cx.update(|cx| cx.set_global(semantic_index.clone()))?;
+
+                anyhow::Ok(())
+            }
+            .log_err()
+        }));
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
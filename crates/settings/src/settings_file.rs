@@ -62,6 +62,50 @@ pub fn watch_config_file(
     rx
 }
 
+/// Watches a directory of config files, sending the paths and contents of all the JSON files in
+/// it, sorted by path, whenever any of them change.
+pub fn watch_config_dir(
+    executor: &BackgroundExecutor,
+    fs: Arc<dyn Fs>,
+    path: PathBuf,
+) -> mpsc::UnboundedReceiver<Vec<(PathBuf, String)>> {
+    let (tx, rx) = mpsc::unbounded();
+    executor
+        .spawn(async move {
+            let events = fs.watch(&path, Duration::from_millis(100)).await;
+            futures::pin_mut!(events);
+
+            loop {
+                let mut files = Vec::new();
+                if let Ok(mut entries) = fs.read_dir(&path).await {
+                    while let Some(entry) = entries.next().await {
+                        let Ok(entry) = entry else {
+                            continue;
+                        };
+                        if entry
+                            .extension()
+                            .map_or(false, |extension| extension == "json")
+                        {
+                            if let Ok(contents) = fs.load(&entry).await {
+                                files.push((entry, contents));
+                            }
+                        }
+                    }
+                }
+                files.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+                if tx.unbounded_send(files).is_err() {
+                    break;
+                }
+
+                if events.next().await.is_none() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    rx
+}
+
 pub fn handle_settings_file_changes(
     mut user_settings_file_rx: mpsc::UnboundedReceiver<String>,
     cx: &mut AppContext,
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Context, Result};
+use regex::{Captures, Regex, RegexBuilder};
 use smallvec::SmallVec;
 use std::{collections::BTreeMap, ops::Range};
 
@@ -8,25 +9,89 @@ pub struct Snippet {
     pub tabstops: Vec<TabStop>,
 }
 
-type TabStop = SmallVec<[Range<isize>; 2]>;
+#[derive(Clone, Debug, Default)]
+pub struct TabStop {
+    pub ranges: SmallVec<[Range<isize>; 2]>,
+    /// The values offered by a choice such as `${1|one,two|}`, the first of which is inserted.
+    pub choices: Option<Vec<String>>,
+    /// Transforms applied when leaving the tabstop, keyed by the index of the range they
+    /// replace.
+    pub transforms: Vec<(usize, Transform)>,
+}
+
+/// A regex substitution such as `${1/(.*)/${1:/upcase}/g}`.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    regex: Regex,
+    format: Vec<FormatItem>,
+    global: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum FormatItem {
+    Text(String),
+    Group(usize),
+    Case(usize, Case),
+    Conditional {
+        group: usize,
+        if_text: String,
+        else_text: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Case {
+    Upcase,
+    Downcase,
+    Capitalize,
+    CamelCase,
+    PascalCase,
+}
 
 impl Snippet {
     pub fn parse(source: &str) -> Result<Self> {
-        let mut text = String::with_capacity(source.len());
-        let mut tabstops = BTreeMap::new();
-        parse_snippet(source, false, &mut text, &mut tabstops)
+        Self::parse_with_variables(source, |_| None)
+    }
+
+    /// Parses a snippet, resolving variables such as `$TM_FILENAME` with `resolve_variable`.
+    /// Variables it returns `None` for are unknown, and become placeholders holding their name.
+    pub fn parse_with_variables(
+        source: &str,
+        resolve_variable: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut parser = Parser {
+            text: String::with_capacity(source.len()),
+            tabstops: BTreeMap::new(),
+            unknown_variables: Vec::new(),
+            resolve_variable: &resolve_variable,
+        };
+        parser
+            .parse_snippet(source, false)
             .context("failed to parse snippet")?;
+        let Parser {
+            text,
+            mut tabstops,
+            unknown_variables,
+            ..
+        } = parser;
 
         let len = text.len() as isize;
         let final_tabstop = tabstops.remove(&0);
         let mut tabstops = tabstops.into_values().collect::<Vec<_>>();
+        tabstops.extend(unknown_variables.into_iter().map(|range| TabStop {
+            ranges: [range].into_iter().collect(),
+            ..Default::default()
+        }));
 
         if let Some(final_tabstop) = final_tabstop {
             tabstops.push(final_tabstop);
         } else {
-            let end_tabstop = [len..len].into_iter().collect();
-            if !tabstops.last().map_or(false, |t| *t == end_tabstop) {
-                tabstops.push(end_tabstop);
+            let end_tabstop = [len..len].into_iter().collect::<SmallVec<_>>();
+            if !tabstops.last().map_or(false, |t| t.ranges == end_tabstop) {
+                tabstops.push(TabStop {
+                    ranges: end_tabstop,
+                    ..Default::default()
+                });
             }
         }
 
@@ -34,75 +99,420 @@ impl Snippet {
     }
 }
 
-fn parse_snippet<'a>(
-    mut source: &'a str,
-    nested: bool,
-    text: &mut String,
-    tabstops: &mut BTreeMap<usize, TabStop>,
-) -> Result<&'a str> {
-    loop {
-        match source.chars().next() {
-            None => return Ok(""),
-            Some('$') => {
-                source = parse_tabstop(&source[1..], text, tabstops)?;
+impl Transform {
+    /// Replaces the first match of the regex in `text`, or every match if the transform has
+    /// the `g` option, with the transform's format.
+    pub fn apply(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last_match_end = 0;
+        for captures in self.regex.captures_iter(text) {
+            let Some(matched) = captures.get(0) else {
+                continue;
+            };
+            result.push_str(&text[last_match_end..matched.start()]);
+            for item in &self.format {
+                item.append_to(&captures, &mut result);
             }
-            Some('\\') => {
-                source = &source[1..];
-                if let Some(c) = source.chars().next() {
-                    text.push(c);
-                    source = &source[c.len_utf8()..];
-                }
+            last_match_end = matched.end();
+            if !self.global {
+                break;
             }
-            Some('}') => {
-                if nested {
-                    return Ok(source);
+        }
+        result.push_str(&text[last_match_end..]);
+        result
+    }
+}
+
+impl FormatItem {
+    fn append_to(&self, captures: &Captures, result: &mut String) {
+        let group = |ix: usize| captures.get(ix).map_or("", |group| group.as_str());
+        match self {
+            FormatItem::Text(text) => result.push_str(text),
+            FormatItem::Group(ix) => result.push_str(group(*ix)),
+            FormatItem::Case(ix, case) => result.push_str(&case.convert(group(*ix))),
+            FormatItem::Conditional {
+                group: ix,
+                if_text,
+                else_text,
+            } => {
+                if group(*ix).is_empty() {
+                    result.push_str(else_text);
                 } else {
-                    text.push('}');
+                    result.push_str(if_text);
+                }
+            }
+        }
+    }
+}
+
+impl Case {
+    fn convert(self, text: &str) -> String {
+        match self {
+            Case::Upcase => text.to_uppercase(),
+            Case::Downcase => text.to_lowercase(),
+            Case::Capitalize => capitalize(text),
+            Case::CamelCase | Case::PascalCase => {
+                let mut words = text.split(|c: char| !c.is_alphanumeric());
+                let mut result = String::with_capacity(text.len());
+                if self == Case::CamelCase {
+                    if let Some(first_word) = words.find(|word| !word.is_empty()) {
+                        let mut chars = first_word.chars();
+                        result.extend(chars.next().into_iter().flat_map(char::to_lowercase));
+                        result.push_str(chars.as_str());
+                    }
+                }
+                for word in words {
+                    result.push_str(&capitalize(word));
+                }
+                result
+            }
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .into_iter()
+        .flat_map(char::to_uppercase)
+        .chain(chars)
+        .collect()
+}
+
+struct Parser<'a> {
+    text: String,
+    tabstops: BTreeMap<usize, TabStop>,
+    unknown_variables: Vec<Range<isize>>,
+    resolve_variable: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_snippet<'b>(&mut self, mut source: &'b str, nested: bool) -> Result<&'b str> {
+        loop {
+            match source.chars().next() {
+                None => return Ok(""),
+                Some('$') => {
+                    source = self.parse_tabstop_or_variable(&source[1..])?;
+                }
+                Some('\\') => {
                     source = &source[1..];
+                    if let Some(c) = source.chars().next() {
+                        self.text.push(c);
+                        source = &source[c.len_utf8()..];
+                    }
+                }
+                Some('}') => {
+                    if nested {
+                        return Ok(source);
+                    } else {
+                        self.text.push('}');
+                        source = &source[1..];
+                    }
+                }
+                Some(_) => {
+                    let chunk_end = source.find(['}', '$', '\\']).unwrap_or(source.len());
+                    let (chunk, rest) = source.split_at(chunk_end);
+                    self.text.push_str(chunk);
+                    source = rest;
                 }
             }
-            Some(_) => {
-                let chunk_end = source.find(['}', '$', '\\']).unwrap_or(source.len());
-                let (chunk, rest) = source.split_at(chunk_end);
-                text.push_str(chunk);
+        }
+    }
+
+    fn parse_tabstop_or_variable<'b>(&mut self, source: &'b str) -> Result<&'b str> {
+        let name_start = source.strip_prefix('{').unwrap_or(source);
+        if name_start.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic()) {
+            self.parse_variable(source)
+        } else {
+            self.parse_tabstop(source)
+        }
+    }
+
+    fn parse_tabstop<'b>(&mut self, mut source: &'b str) -> Result<&'b str> {
+        let tabstop_start = self.text.len();
+        let tabstop_index;
+        let mut choices = None;
+        let mut transform = None;
+        if source.starts_with('{') {
+            let (index, rest) = parse_int(&source[1..])?;
+            tabstop_index = index;
+            source = rest;
+
+            if source.starts_with(':') {
+                source = self.parse_snippet(&source[1..], true)?;
+            } else if source.starts_with('|') {
+                let (parsed_choices, rest) = parse_choices(&source[1..])?;
+                self.text
+                    .push_str(parsed_choices.first().map_or("", String::as_str));
+                choices = Some(parsed_choices);
                 source = rest;
+            } else if source.starts_with('/') {
+                let (parsed_transform, rest) = parse_transform(&source[1..])?;
+                // Transformed occurrences start out showing the transformed placeholder.
+                let placeholder = self.tabstops.get(&tabstop_index).and_then(|tabstop| {
+                    let range = tabstop.ranges.iter().enumerate().find_map(|(ix, range)| {
+                        let transformed = tabstop.transforms.iter().any(|(jx, _)| *jx == ix);
+                        (!transformed).then_some(range)
+                    })?;
+                    Some(self.text[range.start as usize..range.end as usize].to_string())
+                });
+                self.text
+                    .push_str(&parsed_transform.apply(placeholder.as_deref().unwrap_or("")));
+                transform = Some(parsed_transform);
+                source = rest;
+            }
+
+            if source.starts_with('}') {
+                source = &source[1..];
+            } else {
+                return Err(anyhow!("expected a closing brace"));
             }
+        } else {
+            let (index, rest) = parse_int(source)?;
+            tabstop_index = index;
+            source = rest;
+        }
+
+        let tabstop = self.tabstops.entry(tabstop_index).or_default();
+        if let Some(transform) = transform {
+            tabstop.transforms.push((tabstop.ranges.len(), transform));
+        }
+        if choices.is_some() {
+            tabstop.choices = choices;
         }
+        tabstop
+            .ranges
+            .push(tabstop_start as isize..self.text.len() as isize);
+        Ok(source)
     }
-}
 
-fn parse_tabstop<'a>(
-    mut source: &'a str,
-    text: &mut String,
-    tabstops: &mut BTreeMap<usize, TabStop>,
-) -> Result<&'a str> {
-    let tabstop_start = text.len();
-    let tabstop_index;
-    if source.starts_with('{') {
-        let (index, rest) = parse_int(&source[1..])?;
-        tabstop_index = index;
+    fn parse_variable<'b>(&mut self, mut source: &'b str) -> Result<&'b str> {
+        let braced = source.starts_with('{');
+        if braced {
+            source = &source[1..];
+        }
+        let name_len = source
+            .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+            .unwrap_or(source.len());
+        let (name, rest) = source.split_at(name_len);
         source = rest;
 
-        if source.starts_with(':') {
-            source = parse_snippet(&source[1..], true, text, tabstops)?;
+        let value = (self.resolve_variable)(name);
+        let start = self.text.len();
+        let mut has_default = false;
+        if braced {
+            if source.starts_with(':') {
+                if value.as_ref().map_or(false, |value| !value.is_empty()) {
+                    // Parse the unused default into a scratch parser, to find where it ends.
+                    let mut scratch = Parser {
+                        text: String::new(),
+                        tabstops: BTreeMap::new(),
+                        unknown_variables: Vec::new(),
+                        resolve_variable: self.resolve_variable,
+                    };
+                    source = scratch.parse_snippet(&source[1..], true)?;
+                } else {
+                    source = self.parse_snippet(&source[1..], true)?;
+                    has_default = true;
+                }
+            } else if source.starts_with('/') {
+                let (transform, rest) = parse_transform(&source[1..])?;
+                source = rest;
+                if let Some(value) = value.as_ref().filter(|value| !value.is_empty()) {
+                    self.text.push_str(&transform.apply(value));
+                    has_default = true;
+                }
+            }
+
+            if source.starts_with('}') {
+                source = &source[1..];
+            } else {
+                return Err(anyhow!("expected a closing brace"));
+            }
         }
 
-        if source.starts_with('}') {
-            source = &source[1..];
-        } else {
-            return Err(anyhow!("expected a closing brace"));
+        if !has_default {
+            match value {
+                Some(value) => self.text.push_str(&value),
+                None => {
+                    self.text.push_str(name);
+                    self.unknown_variables
+                        .push(start as isize..self.text.len() as isize);
+                }
+            }
         }
+        Ok(source)
+    }
+}
+
+fn parse_choices(mut source: &str) -> Result<(Vec<String>, &str)> {
+    let mut choices = vec![String::new()];
+    loop {
+        let mut chars = source.chars();
+        match chars.next() {
+            None => return Err(anyhow!("expected the end of a choice")),
+            Some('|') => {
+                return Ok((choices, chars.as_str()));
+            }
+            Some(',') => choices.push(String::new()),
+            Some('\\') => {
+                if let Some(c) = chars.next() {
+                    choices.last_mut().unwrap().push(c);
+                }
+            }
+            Some(c) => choices.last_mut().unwrap().push(c),
+        }
+        source = chars.as_str();
+    }
+}
+
+fn parse_transform(source: &str) -> Result<(Transform, &str)> {
+    let (pattern, source) = parse_until(source, '/', true)?;
+    let (format, source) = parse_format(source)?;
+    let options_len = source.find('}').unwrap_or(source.len());
+    let (options, source) = source.split_at(options_len);
+
+    let mut regex = RegexBuilder::new(&pattern);
+    let mut global = false;
+    for option in options.chars() {
+        match option {
+            'g' => global = true,
+            'i' => {
+                regex.case_insensitive(true);
+            }
+            'm' => {
+                regex.multi_line(true);
+            }
+            's' => {
+                regex.dot_matches_new_line(true);
+            }
+            _ => {}
+        }
+    }
+    let transform = Transform {
+        regex: regex.build()?,
+        format,
+        global,
+    };
+    Ok((transform, source))
+}
+
+fn parse_format(mut source: &str) -> Result<(Vec<FormatItem>, &str)> {
+    let mut items = Vec::new();
+    let mut text = String::new();
+    loop {
+        let mut chars = source.chars();
+        match chars.next() {
+            None => return Err(anyhow!("expected the end of a transform")),
+            Some('/') => break,
+            Some('\\') => {
+                if let Some(c) = chars.next() {
+                    text.push(c);
+                }
+            }
+            Some('$') if source[1..].starts_with(|c: char| c == '{' || c.is_ascii_digit()) => {
+                if !text.is_empty() {
+                    items.push(FormatItem::Text(std::mem::take(&mut text)));
+                }
+                let (item, rest) = parse_format_item(&source[1..])?;
+                items.push(item);
+                source = rest;
+                continue;
+            }
+            Some(c) => text.push(c),
+        }
+        source = chars.as_str();
+    }
+    if !text.is_empty() {
+        items.push(FormatItem::Text(text));
+    }
+    Ok((items, &source[1..]))
+}
+
+fn parse_format_item(source: &str) -> Result<(FormatItem, &str)> {
+    let Some(source) = source.strip_prefix('{') else {
+        let (group, rest) = parse_int(source)?;
+        return Ok((FormatItem::Group(group), rest));
+    };
+
+    let (group, source) = parse_int(source)?;
+    if let Some(rest) = source.strip_prefix('}') {
+        Ok((FormatItem::Group(group), rest))
+    } else if let Some(rest) = source.strip_prefix(":/") {
+        let (case, rest) = parse_until(rest, '}', false)?;
+        let case = match case.as_str() {
+            "upcase" => Case::Upcase,
+            "downcase" => Case::Downcase,
+            "capitalize" => Case::Capitalize,
+            "camelcase" => Case::CamelCase,
+            "pascalcase" => Case::PascalCase,
+            _ => return Err(anyhow!("unknown case modifier {case:?}")),
+        };
+        Ok((FormatItem::Case(group, case), rest))
+    } else if let Some(rest) = source.strip_prefix(":+") {
+        let (if_text, rest) = parse_until(rest, '}', false)?;
+        let else_text = String::new();
+        Ok((
+            FormatItem::Conditional {
+                group,
+                if_text,
+                else_text,
+            },
+            rest,
+        ))
+    } else if let Some(rest) = source.strip_prefix(":?") {
+        let (if_text, rest) = parse_until(rest, ':', false)?;
+        let (else_text, rest) = parse_until(rest, '}', false)?;
+        Ok((
+            FormatItem::Conditional {
+                group,
+                if_text,
+                else_text,
+            },
+            rest,
+        ))
+    } else if let Some(rest) = source
+        .strip_prefix(":-")
+        .or_else(|| source.strip_prefix(':'))
+    {
+        let if_text = String::new();
+        let (else_text, rest) = parse_until(rest, '}', false)?;
+        Ok((
+            FormatItem::Conditional {
+                group,
+                if_text,
+                else_text,
+            },
+            rest,
+        ))
     } else {
-        let (index, rest) = parse_int(source)?;
-        tabstop_index = index;
-        source = rest;
+        Err(anyhow!("expected a closing brace"))
     }
+}
 
-    tabstops
-        .entry(tabstop_index)
-        .or_default()
-        .push(tabstop_start as isize..text.len() as isize);
-    Ok(source)
+/// Reads text up to an unescaped `terminator`, returning it and the text after the terminator.
+/// When `keep_escapes` is set, escapes other than that of the terminator are left in place, as
+/// regexes need them.
+fn parse_until(mut source: &str, terminator: char, keep_escapes: bool) -> Result<(String, &str)> {
+    let mut text = String::new();
+    loop {
+        let mut chars = source.chars();
+        match chars.next() {
+            None => return Err(anyhow!("expected {terminator:?}")),
+            Some(c) if c == terminator => return Ok((text, chars.as_str())),
+            Some('\\') => {
+                if let Some(c) = chars.next() {
+                    if keep_escapes && c != terminator {
+                        text.push('\\');
+                    }
+                    text.push(c);
+                }
+            }
+            Some(c) => text.push(c),
+        }
+        source = chars.as_str();
+    }
 }
 
 fn parse_int(source: &str) -> Result<(usize, &str)> {
@@ -199,7 +609,90 @@ mod tests {
         assert_eq!(tabstops(&snippet), &[vec![3..3]]);
     }
 
+    #[test]
+    fn test_snippet_with_choices() {
+        let snippet = Snippet::parse("let x = ${1|one,two,t\\,hree|};$0").unwrap();
+        assert_eq!(snippet.text, "let x = one;");
+        assert_eq!(tabstops(&snippet), &[vec![8..11], vec![12..12]]);
+        assert_eq!(
+            snippet.tabstops[0].choices.as_deref(),
+            Some(&["one".to_string(), "two".to_string(), "t,hree".to_string()][..])
+        );
+        assert_eq!(snippet.tabstops[1].choices, None);
+    }
+
+    #[test]
+    fn test_snippet_with_variables() {
+        let resolve = |name: &str| match name {
+            "TM_FILENAME" => Some("main.rs".to_string()),
+            "CURRENT_YEAR" => Some("2024".to_string()),
+            "TM_SELECTED_TEXT" => Some(String::new()),
+            _ => None,
+        };
+
+        let snippet = Snippet::parse_with_variables(
+            "// $TM_FILENAME, ${CURRENT_YEAR}\n${TM_SELECTED_TEXT:${1:body}}",
+            resolve,
+        )
+        .unwrap();
+        assert_eq!(snippet.text, "// main.rs, 2024\nbody");
+        assert_eq!(tabstops(&snippet), &[vec![17..21], vec![21..21]]);
+
+        // Known variables override their default, even if it contains tabstops.
+        let snippet = Snippet::parse_with_variables("${TM_FILENAME:${1:file}}$2", resolve).unwrap();
+        assert_eq!(snippet.text, "main.rs");
+        assert_eq!(tabstops(&snippet), &[vec![7..7]]);
+
+        // Unknown variables become placeholders holding their name.
+        let snippet = Snippet::parse_with_variables("$1 $UNKNOWN ${OTHER:x}", resolve).unwrap();
+        assert_eq!(snippet.text, " UNKNOWN x");
+        assert_eq!(tabstops(&snippet), &[vec![0..0], vec![1..8], vec![10..10]]);
+
+        // Variables can be transformed.
+        let snippet =
+            Snippet::parse_with_variables("${TM_FILENAME/(.*)\\.rs$/${1:/upcase}/}", resolve)
+                .unwrap();
+        assert_eq!(snippet.text, "MAIN");
+    }
+
+    #[test]
+    fn test_snippet_with_transforms() {
+        let snippet = Snippet::parse("${1:foo} ${1/(.*)/${1:/upcase}/}").unwrap();
+        assert_eq!(snippet.text, "foo FOO");
+        assert_eq!(tabstops(&snippet), &[vec![0..3, 4..7], vec![7..7]]);
+
+        let transforms = &snippet.tabstops[0].transforms;
+        assert_eq!(transforms.len(), 1);
+        assert_eq!(transforms[0].0, 1);
+        assert_eq!(transforms[0].1.apply("bar"), "BAR");
+    }
+
+    #[test]
+    fn test_transform_formats() {
+        let transform = |source: &str| {
+            let snippet = Snippet::parse(source).unwrap();
+            snippet.tabstops[0].transforms[0].1.clone()
+        };
+
+        let case = transform("${1/(.*)/${1:/capitalize} ${1:/pascalcase} ${1:/camelcase}/}");
+        assert_eq!(case.apply("foo_bar-baz"), "Foo_bar-baz FooBarBaz fooBarBaz");
+
+        let conditional = transform("${1/(a)?b/${1:?yes:no}-${1:+A}${1:-B}/}");
+        assert_eq!(conditional.apply("ab"), "yes-A");
+        assert_eq!(conditional.apply("b"), "no-B");
+
+        // Only the first match is replaced, unless the transform is global.
+        let first = transform("${1/o/0/}");
+        assert_eq!(first.apply("foo"), "f0o");
+        let global = transform("${1/O/0/gi}");
+        assert_eq!(global.apply("foo"), "f00");
+
+        // Escaped slashes are part of the regex and the format.
+        let slashes = transform("${1/\\//\\\\/g}");
+        assert_eq!(slashes.apply("a/b/c"), "a\\b\\c");
+    }
+
     fn tabstops(snippet: &Snippet) -> Vec<Vec<Range<isize>>> {
-        snippet.tabstops.iter().map(|t| t.to_vec()).collect()
+        snippet.tabstops.iter().map(|t| t.ranges.to_vec()).collect()
     }
 }
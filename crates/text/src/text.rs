@@ -313,6 +313,19 @@ impl History {
         }
     }
 
+    fn transaction(&self, transaction_id: TransactionId) -> Option<&Transaction> {
+        let entry = self
+            .undo_stack
+            .iter()
+            .rfind(|entry| entry.transaction.id == transaction_id)
+            .or_else(|| {
+                self.redo_stack
+                    .iter()
+                    .rfind(|entry| entry.transaction.id == transaction_id)
+            })?;
+        Some(&entry.transaction)
+    }
+
     fn transaction_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Transaction> {
         let entry = self
             .undo_stack
@@ -1308,6 +1321,20 @@ impl Buffer {
         })
     }
 
+    /// The ranges edited by the transaction with the given id, if it's still in the history.
+    pub fn edited_ranges_for_transaction_id<D>(
+        &self,
+        transaction_id: TransactionId,
+    ) -> impl '_ + Iterator<Item = Range<D>>
+    where
+        D: TextDimension,
+    {
+        self.history
+            .transaction(transaction_id)
+            .into_iter()
+            .flat_map(|transaction| self.edited_ranges_for_transaction(transaction))
+    }
+
     pub fn subscribe(&mut self) -> Subscription {
         self.subscriptions.subscribe()
     }
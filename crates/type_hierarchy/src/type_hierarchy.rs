@@ -0,0 +1,296 @@
+use editor::{scroll::Autoscroll, Editor, EditorMode};
+use fuzzy::{StringMatch, StringMatchCandidate};
+use gpui::{
+    actions, rems, AnyElement, AppContext, DismissEvent, Model, Task, View, ViewContext, WeakView,
+    WindowContext,
+};
+use language::ToOffset;
+use picker::{Picker, PickerDelegate};
+use project::{Project, TypeHierarchyItem};
+use std::sync::Arc;
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::Workspace;
+
+actions!(type_hierarchy, [ShowSupertypes, ShowSubtypes]);
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(register).detach();
+}
+
+fn register(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    if editor.mode() == EditorMode::Full {
+        let handle = cx.view().downgrade();
+        editor.register_action({
+            let handle = handle.clone();
+            move |_: &ShowSupertypes, cx| {
+                if let Some(editor) = handle.upgrade() {
+                    show_type_hierarchy(editor, TypeHierarchyDirection::Supertypes, cx);
+                }
+            }
+        });
+        editor.register_action(move |_: &ShowSubtypes, cx| {
+            if let Some(editor) = handle.upgrade() {
+                show_type_hierarchy(editor, TypeHierarchyDirection::Subtypes, cx);
+            }
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeHierarchyDirection {
+    Supertypes,
+    Subtypes,
+}
+
+impl TypeHierarchyDirection {
+    fn title(&self, item: &TypeHierarchyItem) -> SharedString {
+        match self {
+            TypeHierarchyDirection::Supertypes => format!("Supertypes of `{}`", item.name),
+            TypeHierarchyDirection::Subtypes => format!("Subtypes of `{}`", item.name),
+        }
+        .into()
+    }
+}
+
+fn types_for_item(
+    project: &Model<Project>,
+    item: TypeHierarchyItem,
+    direction: TypeHierarchyDirection,
+    cx: &mut WindowContext,
+) -> Task<anyhow::Result<Vec<TypeHierarchyItem>>> {
+    project.update(cx, |project, cx| match direction {
+        TypeHierarchyDirection::Supertypes => project.supertypes(item, cx),
+        TypeHierarchyDirection::Subtypes => project.subtypes(item, cx),
+    })
+}
+
+fn show_type_hierarchy(
+    editor: View<Editor>,
+    direction: TypeHierarchyDirection,
+    cx: &mut WindowContext,
+) {
+    let Some(workspace) = editor.read(cx).workspace() else {
+        return;
+    };
+    let project = workspace.read(cx).project().clone();
+    let head = editor.read(cx).selections.newest::<usize>(cx).head();
+    let Some((buffer, head)) = editor
+        .read(cx)
+        .buffer()
+        .read(cx)
+        .text_anchor_for_position(head, cx)
+    else {
+        return;
+    };
+    let prepare_task = project.update(cx, |project, cx| {
+        project.prepare_type_hierarchy(&buffer, head, cx)
+    });
+    let weak_workspace = workspace.downgrade();
+    cx.spawn(|mut cx| async move {
+        let Some(item) = prepare_task.await?.into_iter().next() else {
+            return Ok(());
+        };
+        let items = cx
+            .update(|cx| types_for_item(&project, item.clone(), direction, cx))?
+            .await?;
+        workspace.update(&mut cx, |workspace, cx| {
+            workspace.toggle_modal(cx, move |cx| {
+                let delegate =
+                    TypeHierarchyDelegate::new(weak_workspace, project, direction, item, items);
+                Picker::new(delegate, cx).width(rems(34.))
+            });
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+pub struct TypeHierarchyDelegate {
+    workspace: WeakView<Workspace>,
+    project: Model<Project>,
+    direction: TypeHierarchyDirection,
+    title: SharedString,
+    items: Vec<TypeHierarchyItem>,
+    matches: Vec<StringMatch>,
+    selected_match_index: usize,
+    pending_drill_down: Option<Task<()>>,
+}
+
+impl TypeHierarchyDelegate {
+    fn new(
+        workspace: WeakView<Workspace>,
+        project: Model<Project>,
+        direction: TypeHierarchyDirection,
+        root: TypeHierarchyItem,
+        items: Vec<TypeHierarchyItem>,
+    ) -> Self {
+        Self {
+            workspace,
+            project,
+            direction,
+            title: direction.title(&root),
+            items,
+            matches: Vec::new(),
+            selected_match_index: 0,
+            pending_drill_down: None,
+        }
+    }
+
+    fn selected_item(&self) -> Option<TypeHierarchyItem> {
+        self.matches
+            .get(self.selected_match_index)
+            .map(|mat| self.items[mat.candidate_id].clone())
+    }
+
+    /// Replaces the listed types with the next level of the hierarchy below the selected type.
+    fn drill_down(&mut self, item: TypeHierarchyItem, cx: &mut ViewContext<Picker<Self>>) {
+        let direction = self.direction;
+        let title = direction.title(&item);
+        let items_task = types_for_item(&self.project, item, direction, cx);
+        self.pending_drill_down = Some(cx.spawn(|picker, mut cx| async move {
+            let Some(items) = items_task.await.log_err() else {
+                return;
+            };
+            picker
+                .update(&mut cx, |picker, cx| {
+                    picker.delegate.items = items;
+                    picker.delegate.title = title;
+                    picker.set_query("", cx);
+                    picker.refresh(cx);
+                })
+                .log_err();
+        }));
+    }
+}
+
+impl PickerDelegate for TypeHierarchyDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        match self.direction {
+            TypeHierarchyDirection::Supertypes => "Search supertypes...".into(),
+            TypeHierarchyDirection::Subtypes => "Search subtypes...".into(),
+        }
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_match_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_match_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let candidates = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(id, item)| StringMatchCandidate::new(id, item.name.clone()))
+            .collect::<Vec<_>>();
+        self.matches = if query.is_empty() {
+            candidates
+                .into_iter()
+                .map(|candidate| StringMatch {
+                    candidate_id: candidate.id,
+                    score: 0.,
+                    positions: Vec::new(),
+                    string: candidate.string,
+                })
+                .collect()
+        } else {
+            cx.background_executor().block(fuzzy::match_strings(
+                &candidates,
+                &query,
+                false,
+                100,
+                &Default::default(),
+                cx.background_executor().clone(),
+            ))
+        };
+        self.selected_match_index = 0;
+        Task::ready(())
+    }
+
+    fn confirm(&mut self, secondary: bool, cx: &mut ViewContext<Picker<Self>>) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        if secondary {
+            self.drill_down(item, cx);
+            return;
+        }
+
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let position = item.selection_range.start.to_offset(item.buffer.read(cx));
+                let editor = workspace.open_project_item::<Editor>(item.buffer, cx);
+                editor.update(cx, |editor, cx| {
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([position..position])
+                    });
+                });
+            })
+            .log_err();
+        cx.emit(DismissEvent);
+    }
+
+    fn dismissed(&mut self, _: &mut ViewContext<Picker<Self>>) {}
+
+    fn render_footer(&self, _: &mut ViewContext<Picker<Self>>) -> Option<AnyElement> {
+        Some(
+            h_flex()
+                .px_3()
+                .py_1()
+                .child(
+                    Label::new(self.title.clone())
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .into_any_element(),
+        )
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        cx: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let string_match = &self.matches[ix];
+        let item = &self.items[string_match.candidate_id];
+        let path = item
+            .buffer
+            .read(cx)
+            .file()
+            .map(|file| file.path().to_string_lossy().to_string());
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(
+                    v_flex()
+                        .child(
+                            h_flex()
+                                .gap_2()
+                                .child(HighlightedLabel::new(
+                                    item.name.clone(),
+                                    string_match.positions.clone(),
+                                ))
+                                .when_some(item.detail.clone(), |this, detail| {
+                                    this.child(Label::new(detail).color(Color::Muted))
+                                }),
+                        )
+                        .when_some(path, |this, path| {
+                            this.child(Label::new(path).size(LabelSize::Small).color(Color::Muted))
+                        }),
+                ),
+        )
+    }
+}
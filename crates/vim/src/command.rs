@@ -1,17 +1,23 @@
+use std::{ops::Range, time::Instant};
+
+use anyhow::{anyhow, bail, Result};
 use command_palette::CommandInterceptResult;
-use editor::actions::{SortLinesCaseInsensitive, SortLinesCaseSensitive};
-use gpui::{impl_actions, Action, AppContext, ViewContext};
+use editor::{scroll::Autoscroll, Anchor, Editor, MultiBufferSnapshot, ToPoint};
+use gpui::{
+    impl_actions, Action, AppContext, AsyncWindowContext, KeyDownEvent, Keystroke, Modifiers,
+    PlatformInput, ViewContext, WindowContext,
+};
+use language::Point;
+use regex::{Regex, RegexBuilder};
 use serde_derive::Deserialize;
-use workspace::{SaveIntent, Workspace};
+use workspace::{SaveIntent, Toast, Workspace};
 
 use crate::{
-    motion::{EndOfDocument, Motion},
-    normal::{
-        move_cursor,
-        search::{FindCommand, ReplaceCommand},
-        JoinLines,
-    },
+    mark::MarkPosition,
+    motion::Motion,
+    normal::{move_cursor, search::FindCommand},
     state::Mode,
+    utils::copy_selections_content,
     Vim,
 };
 
@@ -20,7 +26,15 @@ pub struct GoToLine {
     pub line: u32,
 }
 
-impl_actions!(vim, [GoToLine]);
+/// Runs an ex command, such as `'<,'>s/a/b/g`, as typed after `:`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExCommand {
+    pub command: String,
+}
+
+impl_actions!(vim, [GoToLine, ExCommand]);
+
+const EX_COMMAND_ERROR_TOAST_ID: usize = 7316;
 
 pub fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
     workspace.register_action(|_: &mut Workspace, action: &GoToLine, cx| {
@@ -29,18 +43,15 @@ pub fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
             move_cursor(vim, Motion::StartOfDocument, Some(action.line as usize), cx);
         });
     });
+    workspace.register_action(ex_command);
 }
 
 pub fn command_interceptor(mut query: &str, _: &AppContext) -> Option<CommandInterceptResult> {
-    // Note: this is a very poor simulation of vim's command palette.
-    // In the future we should adjust it to handle parsing range syntax,
-    // and then calling the appropriate commands with/without ranges.
+    // Commands that act on files, panes and the quickfix list are matched by name here, while
+    // commands that edit the buffer are parsed along with their ranges by `parse_command`.
     //
-    // We also need to support passing arguments to commands like :w
+    // We still need to support passing arguments to commands like :w
     // (ideally with filename autocompletion).
-    //
-    // For now, you can only do a replace on the % range, and you can
-    // only use a specific line number range to "go to line"
     while query.starts_with(":") {
         query = &query[1..];
     }
@@ -224,40 +235,44 @@ pub fn command_interceptor(mut query: &str, _: &AppContext) -> Option<CommandInt
             ("lNext", editor::actions::GoToPrevDiagnostic.boxed_clone())
         }
 
-        // modify the buffer (should accept [range])
-        "j" | "jo" | "joi" | "join" => ("join", JoinLines.boxed_clone()),
-        "d" | "de" | "del" | "dele" | "delet" | "delete" | "dl" | "dell" | "delel" | "deletl"
-        | "deletel" | "dp" | "dep" | "delp" | "delep" | "deletp" | "deletep" => {
-            ("delete", editor::actions::DeleteLine.boxed_clone())
-        }
-        "sor" | "sor " | "sort" | "sort " => ("sort", SortLinesCaseSensitive.boxed_clone()),
-        "sor i" | "sort i" => ("sort i", SortLinesCaseInsensitive.boxed_clone()),
-
-        // goto (other ranges handled under _ => )
-        "$" => ("$", EndOfDocument.boxed_clone()),
-
         _ => {
-            if query.starts_with("/") || query.starts_with("?") {
+            let command = match parse_command(query) {
+                Ok(Some(command)) => command,
+                Ok(None) => return None,
+                Err(error) => {
+                    // Don't shadow the palette's own matches for queries like "move line up".
+                    if looks_like_prose(query) {
+                        return None;
+                    }
+                    let string = format!(":{query}  {error}");
+                    let positions = generate_positions(&string, query);
+                    return Some(CommandInterceptResult {
+                        action: ExCommand {
+                            command: query.to_string(),
+                        }
+                        .boxed_clone(),
+                        string,
+                        positions,
+                    });
+                }
+            };
+            if let Some((pattern, backwards)) = command.as_search() {
                 (
                     query,
                     FindCommand {
-                        query: query[1..].to_string(),
-                        backwards: query.starts_with("?"),
+                        query: pattern.to_string(),
+                        backwards,
                     }
                     .boxed_clone(),
                 )
-            } else if query.starts_with("%") {
+            } else {
                 (
                     query,
-                    ReplaceCommand {
-                        query: query.to_string(),
+                    ExCommand {
+                        command: query.to_string(),
                     }
                     .boxed_clone(),
                 )
-            } else if let Ok(line) = query.parse::<u32>() {
-                (query, GoToLine { line }.boxed_clone())
-            } else {
-                return None;
             }
         }
     };
@@ -294,14 +309,1211 @@ fn generate_positions(string: &str, query: &str) -> Vec<usize> {
     positions
 }
 
+/// Whether a query that isn't a valid ex command reads like a search for an action in the
+/// palette, such as "move line up", rather than a mistyped command.
+fn looks_like_prose(query: &str) -> bool {
+    let mut words = query.split_whitespace();
+    let Some(first) = words.next() else {
+        return false;
+    };
+    first.chars().all(|c| c.is_ascii_alphabetic())
+        && words
+            .next()
+            .map_or(true, |word| word.starts_with(|c: char| c.is_alphabetic()))
+}
+
+/// A line address in the range of an ex command, such as `.`, `$-1`, `'a` or `/pattern/`.
+#[derive(Debug, Clone, PartialEq)]
+struct Address {
+    base: AddressBase,
+    offset: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AddressBase {
+    /// A 1-based line number, where 0 is the position before the first line.
+    Line(u32),
+    CurrentLine,
+    LastLine,
+    Mark(char),
+    /// The next line matching the pattern, or the previous one when searching backwards.
+    Search {
+        pattern: String,
+        backwards: bool,
+    },
+}
+
+impl Address {
+    fn new(base: AddressBase) -> Self {
+        Self { base, offset: 0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CommandRange {
+    start: Address,
+    end: Option<Address>,
+    /// Whether the addresses were separated with `;`, which resolves the end relative to the
+    /// start rather than to the cursor.
+    end_relative_to_start: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedCommand {
+    range: Option<CommandRange>,
+    kind: CommandKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CommandKind {
+    /// A range on its own, which moves the cursor to its last line.
+    GoToLine,
+    Substitute {
+        pattern: String,
+        replacement: String,
+        replace_all: bool,
+        ignore_case: bool,
+        report_not_found: bool,
+    },
+    /// Runs a command on every line that matches the pattern, or with `invert`, that doesn't.
+    Global {
+        pattern: String,
+        invert: bool,
+        command: Box<ParsedCommand>,
+    },
+    /// Types the keystrokes in normal mode on every line.
+    Normal {
+        keystrokes: String,
+    },
+    Delete {
+        register: Option<char>,
+    },
+    Join,
+    Move {
+        destination: Address,
+    },
+    Copy {
+        destination: Address,
+    },
+    Sort {
+        reverse: bool,
+        ignore_case: bool,
+        numeric: bool,
+        unique: bool,
+    },
+    Shift {
+        right: bool,
+        times: usize,
+    },
+}
+
+impl ParsedCommand {
+    /// The pattern and direction of a command like `:/pattern`, which searches like `/` does.
+    fn as_search(&self) -> Option<(&str, bool)> {
+        let range = self.range.as_ref()?;
+        match (&range.start.base, &range.end, &self.kind) {
+            (AddressBase::Search { pattern, backwards }, None, CommandKind::GoToLine)
+                if range.start.offset == 0 =>
+            {
+                Some((pattern, *backwards))
+            }
+            _ => None,
+        }
+    }
+
+    fn types_keystrokes(&self) -> bool {
+        match &self.kind {
+            CommandKind::Normal { .. } => true,
+            CommandKind::Global { command, .. } => command.types_keystrokes(),
+            _ => false,
+        }
+    }
+}
+
+/// Parses an ex command. Commands that aren't handled here, like `:w`, are `Ok(None)`, so that
+/// the command palette can look them up instead.
+fn parse_command(input: &str) -> Result<Option<ParsedCommand>> {
+    let mut parser = CommandParser { input, offset: 0 };
+    let command = parser.command(false)?;
+    parser.skip_whitespace();
+    if command.is_some() && !parser.rest().is_empty() {
+        bail!("E488: Trailing characters: {}", parser.rest());
+    }
+    Ok(command)
+}
+
+struct CommandParser<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> CommandParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.offset += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start_matches(predicate).len();
+        self.offset += len;
+        &rest[..len]
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        (!digits.is_empty()).then(|| digits.parse().unwrap_or(u32::MAX))
+    }
+
+    /// Reads up to the next unescaped `delimiter`. `\` followed by the delimiter stands for the
+    /// delimiter itself, while other escapes are kept for the pattern. As in vim, the closing
+    /// delimiter can be left out at the end of the command.
+    fn delimited(&mut self, delimiter: char) -> String {
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            if c == delimiter {
+                break;
+            } else if c == '\\' {
+                match self.next() {
+                    Some(c) if c == delimiter => text.push(c),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => text.push('\\'),
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        text
+    }
+
+    fn pattern_delimiter(&mut self) -> Result<char> {
+        match self.next() {
+            None => Err(anyhow!("E471: Argument required")),
+            Some(c)
+                if c.is_alphanumeric() || c.is_whitespace() || matches!(c, '\\' | '"' | '|') =>
+            {
+                Err(anyhow!(
+                    "E146: Regular expressions can't be delimited by letters"
+                ))
+            }
+            Some(c) => Ok(c),
+        }
+    }
+
+    fn command(&mut self, in_global: bool) -> Result<Option<ParsedCommand>> {
+        self.skip_whitespace();
+        while self.eat(':') {
+            self.skip_whitespace();
+        }
+        let range = self.range()?;
+        self.skip_whitespace();
+        let kind = match self.peek() {
+            None if range.is_none() => return Ok(None),
+            None => CommandKind::GoToLine,
+            Some(c @ ('>' | '<')) => {
+                let times = self.take_while(|next| next == c).len();
+                CommandKind::Shift {
+                    right: c == '>',
+                    times,
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.take_while(|c| c.is_ascii_alphabetic());
+                let bang = self.eat('!');
+                match self.command_kind(name, bang, in_global)? {
+                    Some(kind) => kind,
+                    None if range.is_none() => return Ok(None),
+                    None => bail!("E492: Not an editor command: {}", self.input.trim()),
+                }
+            }
+            Some(_) if range.is_none() => return Ok(None),
+            Some(_) => bail!("E492: Not an editor command: {}", self.input.trim()),
+        };
+        Ok(Some(ParsedCommand { range, kind }))
+    }
+
+    fn command_kind(
+        &mut self,
+        name: &str,
+        bang: bool,
+        in_global: bool,
+    ) -> Result<Option<CommandKind>> {
+        let abbreviates =
+            |command: &str, min_len: usize| name.len() >= min_len && command.starts_with(name);
+        let no_bang = || {
+            if bang {
+                Err(anyhow!("E477: No ! allowed"))
+            } else {
+                Ok(())
+            }
+        };
+
+        let kind = if abbreviates("substitute", 1) {
+            no_bang()?;
+            self.substitute(!in_global)?
+        } else if abbreviates("global", 1) || abbreviates("vglobal", 1) {
+            if in_global {
+                bail!("E147: Cannot do :global recursive");
+            }
+            self.global(bang || name.starts_with('v'))?
+        } else if abbreviates("normal", 4) {
+            self.skip_whitespace();
+            let keystrokes = self.take_while(|_| true);
+            if keystrokes.is_empty() {
+                bail!("E471: Argument required");
+            }
+            CommandKind::Normal {
+                keystrokes: keystrokes.to_string(),
+            }
+        } else if abbreviates("delete", 1) {
+            no_bang()?;
+            self.skip_whitespace();
+            let register = self
+                .peek()
+                .filter(|c| c.is_ascii_alphabetic() || matches!(c, '"' | '-' | '_' | '+' | '*'));
+            if register.is_some() {
+                self.next();
+            }
+            CommandKind::Delete { register }
+        } else if abbreviates("join", 1) {
+            no_bang()?;
+            CommandKind::Join
+        } else if abbreviates("move", 1) {
+            no_bang()?;
+            CommandKind::Move {
+                destination: self.destination()?,
+            }
+        } else if name == "t" || abbreviates("copy", 2) {
+            no_bang()?;
+            CommandKind::Copy {
+                destination: self.destination()?,
+            }
+        } else if abbreviates("sort", 3) {
+            let (mut ignore_case, mut numeric, mut unique) = (false, false, false);
+            loop {
+                self.skip_whitespace();
+                match self.peek() {
+                    Some('i') => ignore_case = true,
+                    Some('n') => numeric = true,
+                    Some('u') => unique = true,
+                    _ => break,
+                }
+                self.next();
+            }
+            CommandKind::Sort {
+                reverse: bang,
+                ignore_case,
+                numeric,
+                unique,
+            }
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(kind))
+    }
+
+    fn substitute(&mut self, report_not_found: bool) -> Result<CommandKind> {
+        let delimiter = self.pattern_delimiter()?;
+        let pattern = self.delimited(delimiter);
+        let replacement = self.delimited(delimiter);
+        let (mut replace_all, mut ignore_case, mut report_not_found) =
+            (false, false, report_not_found);
+        while let Some(flag) = self.peek() {
+            match flag {
+                'g' => replace_all = true,
+                'i' => ignore_case = true,
+                'I' => ignore_case = false,
+                'e' => report_not_found = false,
+                _ => break,
+            }
+            self.next();
+        }
+        Ok(CommandKind::Substitute {
+            pattern,
+            replacement,
+            replace_all,
+            ignore_case,
+            report_not_found,
+        })
+    }
+
+    fn global(&mut self, invert: bool) -> Result<CommandKind> {
+        let delimiter = self.pattern_delimiter()?;
+        let pattern = self.delimited(delimiter);
+        // Without a command, `:g` prints the lines, which leaves the cursor on the last one.
+        let command = if self.rest().trim().is_empty() {
+            ParsedCommand {
+                range: None,
+                kind: CommandKind::GoToLine,
+            }
+        } else {
+            self.command(true)?
+                .ok_or_else(|| anyhow!("E492: Not an editor command: {}", self.rest().trim()))?
+        };
+        Ok(CommandKind::Global {
+            pattern,
+            invert,
+            command: Box::new(command),
+        })
+    }
+
+    fn destination(&mut self) -> Result<Address> {
+        self.address()?
+            .ok_or_else(|| anyhow!("E14: Invalid address"))
+    }
+
+    fn range(&mut self) -> Result<Option<CommandRange>> {
+        self.skip_whitespace();
+        if self.eat('%') {
+            return Ok(Some(CommandRange {
+                start: Address::new(AddressBase::Line(1)),
+                end: Some(Address::new(AddressBase::LastLine)),
+                end_relative_to_start: false,
+            }));
+        }
+
+        let start = self.address()?;
+        let end_relative_to_start = match self.peek() {
+            Some(',') => false,
+            Some(';') => true,
+            _ => {
+                return Ok(start.map(|start| CommandRange {
+                    start,
+                    end: None,
+                    end_relative_to_start: false,
+                }))
+            }
+        };
+        self.next();
+        // A missing address on either side of the separator is the current line.
+        let start = start.unwrap_or(Address::new(AddressBase::CurrentLine));
+        let end = self
+            .address()?
+            .unwrap_or(Address::new(AddressBase::CurrentLine));
+        Ok(Some(CommandRange {
+            start,
+            end: Some(end),
+            end_relative_to_start,
+        }))
+    }
+
+    fn address(&mut self) -> Result<Option<Address>> {
+        self.skip_whitespace();
+        let base = match self.peek() {
+            Some(c) if c.is_ascii_digit() => self.number().map(AddressBase::Line),
+            Some('.') => {
+                self.next();
+                Some(AddressBase::CurrentLine)
+            }
+            Some('$') => {
+                self.next();
+                Some(AddressBase::LastLine)
+            }
+            Some('\'') => {
+                self.next();
+                match self.next() {
+                    Some('\'') => Some(AddressBase::Mark('`')),
+                    Some(mark) => Some(AddressBase::Mark(mark)),
+                    None => bail!("E20: Mark not set"),
+                }
+            }
+            Some(delimiter @ ('/' | '?')) => {
+                self.next();
+                Some(AddressBase::Search {
+                    pattern: self.delimited(delimiter),
+                    backwards: delimiter == '?',
+                })
+            }
+            _ => None,
+        };
+
+        let mut offset = None;
+        loop {
+            self.skip_whitespace();
+            let sign = match self.peek() {
+                Some('+') => 1,
+                Some('-') => -1,
+                _ => break,
+            };
+            self.next();
+            let amount = self.number().unwrap_or(1) as i64;
+            offset = Some(offset.unwrap_or(0) + sign * amount);
+        }
+
+        Ok(match (base, offset) {
+            (Some(base), offset) => Some(Address {
+                base,
+                offset: offset.unwrap_or(0),
+            }),
+            // An offset on its own, like `+2`, is relative to the current line.
+            (None, Some(offset)) => Some(Address {
+                base: AddressBase::CurrentLine,
+                offset,
+            }),
+            (None, None) => None,
+        })
+    }
+}
+
+fn ex_command(workspace: &mut Workspace, action: &ExCommand, cx: &mut ViewContext<Workspace>) {
+    let command = match parse_command(&action.command) {
+        Ok(Some(command)) => command,
+        Ok(None) => {
+            let error = anyhow!("E492: Not an editor command: {}", action.command.trim());
+            show_error(workspace, error, cx);
+            return;
+        }
+        Err(error) => {
+            show_error(workspace, error, cx);
+            return;
+        }
+    };
+
+    let command = Vim::update(cx, |vim, cx| vim.prepare_command(command, cx));
+    if command.types_keystrokes() {
+        cx.spawn(|workspace, mut cx| async move {
+            if let Err(error) = run_keystrokes_command(command, &mut cx).await {
+                workspace
+                    .update(&mut cx, |workspace, cx| show_error(workspace, error, cx))
+                    .ok();
+            }
+        })
+        .detach();
+    } else {
+        let result = Vim::update(cx, |vim, cx| {
+            vim.command_transaction(cx, |vim, cx| vim.run_command(&command, cx))
+        });
+        if let Err(error) = result {
+            show_error(workspace, error, cx);
+        }
+    }
+}
+
+fn show_error(workspace: &mut Workspace, error: anyhow::Error, cx: &mut ViewContext<Workspace>) {
+    workspace.show_toast(Toast::new(EX_COMMAND_ERROR_TOAST_ID, error.to_string()), cx);
+}
+
+/// Runs a `:normal` command, or a `:g` command that runs one, by typing its keystrokes on each
+/// line. Keystrokes like `@a` replay actions asynchronously, so each one is given the chance to
+/// finish before the next one is typed.
+async fn run_keystrokes_command(command: ParsedCommand, cx: &mut AsyncWindowContext) -> Result<()> {
+    let editor = cx
+        .update(|cx| Vim::read(cx).active_editor.clone())?
+        .and_then(|editor| editor.upgrade())
+        .ok_or_else(|| anyhow!("No active editor"))?;
+    editor.update(cx, |editor, cx| {
+        editor.start_transaction_at(Instant::now(), cx)
+    })?;
+    let result = type_keystrokes_on_lines(command, cx).await;
+    editor.update(cx, |editor, cx| {
+        editor.end_transaction_at(Instant::now(), cx)
+    })?;
+    result
+}
+
+async fn type_keystrokes_on_lines(
+    command: ParsedCommand,
+    cx: &mut AsyncWindowContext,
+) -> Result<()> {
+    let (lines, filter, normal) = match command.kind {
+        CommandKind::Global {
+            pattern,
+            invert,
+            command: normal,
+        } => {
+            let (regex, lines) = cx.update(|cx| {
+                Vim::update(cx, |vim, cx| {
+                    vim.matching_lines(&pattern, invert, command.range.as_ref(), cx)
+                })
+            })??;
+            (lines, Some((regex, invert)), *normal)
+        }
+        _ => {
+            let cursor = cx.update(|cx| Vim::update(cx, |vim, cx| vim.newest_cursor(cx)))?;
+            (cursor.into_iter().collect(), None, command)
+        }
+    };
+    let CommandKind::Normal { keystrokes } = &normal.kind else {
+        return Ok(());
+    };
+    let keystrokes = keystrokes.chars().map(char_keystroke).collect::<Vec<_>>();
+
+    for line in lines {
+        let rows = cx.update(|cx| {
+            Vim::update(cx, |vim, cx| {
+                if !vim.move_to_line(line, filter.as_ref(), cx) {
+                    return Ok(Vec::new());
+                }
+                vim.line_anchors(normal.range.as_ref(), cx)
+            })
+        })??;
+        for row in rows {
+            cx.update(|cx| Vim::update(cx, |vim, cx| vim.move_to_line(row, None, cx)))?;
+            for keystroke in &keystrokes {
+                let replay_finished = cx.update(|cx| {
+                    type_keystroke(keystroke, cx);
+                    Vim::update(cx, |vim, _| vim.replay_finished())
+                })?;
+                replay_finished.await.ok();
+            }
+            // Like vim, end an incomplete command as though escape was typed.
+            let incomplete = cx.update(|cx| {
+                let vim = Vim::read(cx);
+                vim.state().mode != Mode::Normal || vim.active_operator().is_some()
+            })?;
+            if incomplete {
+                cx.update(|cx| type_keystroke(&Keystroke::parse("escape").unwrap(), cx))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Types the keystroke through the keymap, like the user would, inserting its text into the
+/// active editor if no binding handles it.
+fn type_keystroke(keystroke: &Keystroke, cx: &mut WindowContext) {
+    let handled = cx.dispatch_event(PlatformInput::KeyDown(KeyDownEvent {
+        keystroke: keystroke.clone(),
+        is_held: false,
+    }));
+    if handled {
+        return;
+    }
+    if let Some(text) = keystroke.ime_key.as_deref() {
+        Vim::update(cx, |vim, cx| {
+            vim.update_active_editor(cx, |editor, cx| editor.handle_input(text, cx))
+        });
+    }
+}
+
+fn char_keystroke(c: char) -> Keystroke {
+    let (key, shift) = match c {
+        ' ' => ("space".to_string(), false),
+        c if c.is_uppercase() => (c.to_lowercase().to_string(), true),
+        c => (c.to_string(), false),
+    };
+    Keystroke {
+        modifiers: Modifiers {
+            shift,
+            ..Default::default()
+        },
+        key,
+        ime_key: Some(c.to_string()),
+    }
+}
+
+impl Vim {
+    /// Leaves visual mode before running a command, which then applies to the selected lines as
+    /// though it was given the `'<,'>` range.
+    fn prepare_command(
+        &mut self,
+        mut command: ParsedCommand,
+        cx: &mut WindowContext,
+    ) -> ParsedCommand {
+        if self.state().mode.is_visual() {
+            if command.range.is_none() {
+                command.range = Some(CommandRange {
+                    start: Address::new(AddressBase::Mark('<')),
+                    end: Some(Address::new(AddressBase::Mark('>'))),
+                    end_relative_to_start: false,
+                });
+            }
+            self.switch_mode(Mode::Normal, false, cx);
+        }
+        command
+    }
+
+    /// Groups the edits of a command into one undo step, even for commands like `:g` that edit
+    /// the buffer once per line.
+    fn command_transaction<T>(
+        &mut self,
+        cx: &mut WindowContext,
+        f: impl FnOnce(&mut Self, &mut WindowContext) -> T,
+    ) -> T {
+        let editor = self
+            .active_editor
+            .clone()
+            .and_then(|editor| editor.upgrade());
+        if let Some(editor) = &editor {
+            editor.update(cx, |editor, cx| {
+                editor.start_transaction_at(Instant::now(), cx)
+            });
+        }
+        let result = f(self, cx);
+        if let Some(editor) = editor {
+            editor.update(cx, |editor, cx| {
+                editor.end_transaction_at(Instant::now(), cx)
+            });
+        }
+        result
+    }
+
+    fn run_command(&mut self, command: &ParsedCommand, cx: &mut WindowContext) -> Result<()> {
+        let range = command.range.as_ref();
+        match &command.kind {
+            CommandKind::GoToLine => {
+                let rows = self.resolve_range(range, false, cx)?;
+                if let Some(origin) = self.newest_cursor(cx) {
+                    self.record_jump(origin, cx);
+                }
+                move_cursor(self, Motion::StartOfDocument, Some(rows.end as usize), cx);
+            }
+            CommandKind::Substitute {
+                pattern,
+                replacement,
+                replace_all,
+                ignore_case,
+                report_not_found,
+            } => {
+                let regex = self.command_regex(pattern, *ignore_case)?;
+                let replacement = vim_replacement(replacement);
+                let rows = self.resolve_range(range, false, cx)?;
+                let substituted = self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let limit = if *replace_all { 0 } else { 1 };
+                    let edits = rows
+                        .filter_map(|row| {
+                            let line = line_text(&snapshot, row);
+                            if !regex.is_match(&line) {
+                                return None;
+                            }
+                            let new_line = regex.replacen(&line, limit, replacement.as_str());
+                            let range = Point::new(row, 0)..Point::new(row, line.len() as u32);
+                            Some((range, new_line.into_owned()))
+                        })
+                        .collect::<Vec<_>>();
+                    let last_line = snapshot.anchor_before(edits.last()?.0.start);
+                    editor.edit(edits, cx);
+                    move_to_first_non_blank(editor, last_line, cx);
+                    Some(())
+                });
+                if substituted.flatten().is_none() && *report_not_found {
+                    bail!("E486: Pattern not found: {pattern}");
+                }
+            }
+            CommandKind::Global {
+                pattern,
+                invert,
+                command,
+            } => {
+                let (regex, lines) = self.matching_lines(pattern, *invert, range, cx)?;
+                let filter = (regex, *invert);
+                for line in lines {
+                    if self.move_to_line(line, Some(&filter), cx) {
+                        self.run_command(command, cx)?;
+                    }
+                }
+            }
+            CommandKind::Normal { .. } => {
+                unreachable!("`:normal` is run by typing its keystrokes")
+            }
+            CommandKind::Delete { register } => {
+                let rows = self.resolve_range(range, false, cx)?;
+                let content = self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let mut content = None;
+                    editor.transact(cx, |editor, cx| {
+                        let lines = line_selection(&snapshot, rows);
+                        editor.change_selections(None, cx, |s| s.select_ranges([lines]));
+                        content = Some(copy_selections_content(editor, true, cx));
+                        editor.insert("", cx);
+                        let cursor = editor.selections.newest::<Point>(cx).head();
+                        move_to_first_non_blank(editor, Point::new(cursor.row, 0), cx);
+                    });
+                    content
+                });
+                if let Some(content) = content.flatten() {
+                    self.workspace_state.selected_register = *register;
+                    self.write_register(content, false, cx);
+                }
+            }
+            CommandKind::Join => {
+                let rows = self.resolve_range(range, false, cx)?;
+                self.update_active_editor(cx, |editor, cx| {
+                    let last_row = rows.end.saturating_sub(1).max(rows.start);
+                    let selection = Point::new(rows.start, 0)..Point::new(last_row, 0);
+                    editor.change_selections(None, cx, |s| s.select_ranges([selection]));
+                    editor.join_lines(&Default::default(), cx);
+                });
+            }
+            CommandKind::Move { destination } => {
+                let rows = self.resolve_range(range, false, cx)?;
+                let destination = self.resolve_destination(destination, cx)?;
+                if rows.start < destination && destination < rows.end {
+                    bail!("E134: Cannot move a range of lines into itself");
+                }
+                self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let (span, new_rows, last_row) = if destination <= rows.start {
+                        let new_rows = rows.clone().chain(destination..rows.start);
+                        let last_row = destination + rows.len() as u32 - 1;
+                        (
+                            destination..rows.end,
+                            new_rows.collect::<Vec<_>>(),
+                            last_row,
+                        )
+                    } else {
+                        let new_rows = (rows.end..destination).chain(rows.clone());
+                        (rows.start..destination, new_rows.collect(), destination - 1)
+                    };
+                    let text = new_rows
+                        .into_iter()
+                        .map(|row| line_text(&snapshot, row))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let span = Point::new(span.start, 0)
+                        ..Point::new(span.end - 1, snapshot.line_len(span.end - 1));
+                    editor.transact(cx, |editor, cx| {
+                        editor.edit([(span, text)], cx);
+                        move_to_first_non_blank(editor, Point::new(last_row, 0), cx);
+                    });
+                });
+            }
+            CommandKind::Copy { destination } => {
+                let rows = self.resolve_range(range, false, cx)?;
+                let destination = self.resolve_destination(destination, cx)?;
+                self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let copied = lines_text(&snapshot, rows.clone());
+                    let edit = if destination == 0 {
+                        (Point::zero(), copied + "\n")
+                    } else {
+                        let row = destination - 1;
+                        (
+                            Point::new(row, snapshot.line_len(row)),
+                            "\n".to_string() + &copied,
+                        )
+                    };
+                    let last_row = destination + rows.len() as u32 - 1;
+                    editor.transact(cx, |editor, cx| {
+                        editor.edit([(edit.0..edit.0, edit.1)], cx);
+                        move_to_first_non_blank(editor, Point::new(last_row, 0), cx);
+                    });
+                });
+            }
+            CommandKind::Sort {
+                reverse,
+                ignore_case,
+                numeric,
+                unique,
+            } => {
+                let rows = self.resolve_range(range, true, cx)?;
+                self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let mut lines = rows
+                        .clone()
+                        .map(|row| line_text(&snapshot, row))
+                        .collect::<Vec<_>>();
+                    if *numeric {
+                        // Lines without a number come first, in their original order.
+                        lines.sort_by_key(|line| first_number(line));
+                    } else if *ignore_case {
+                        lines.sort_by_key(|line| line.to_lowercase());
+                    } else {
+                        lines.sort();
+                    }
+                    if *reverse {
+                        lines.reverse();
+                    }
+                    if *unique {
+                        lines.dedup_by(|a, b| {
+                            if *ignore_case {
+                                a.to_lowercase() == b.to_lowercase()
+                            } else {
+                                a == b
+                            }
+                        });
+                    }
+                    let last_row = rows.end - 1;
+                    let span = Point::new(rows.start, 0)
+                        ..Point::new(last_row, snapshot.line_len(last_row));
+                    editor.transact(cx, |editor, cx| {
+                        editor.edit([(span, lines.join("\n"))], cx);
+                        let start = Point::new(rows.start, 0);
+                        editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                            s.select_ranges([start..start])
+                        });
+                    });
+                });
+            }
+            CommandKind::Shift { right, times } => {
+                let rows = self.resolve_range(range, false, cx)?;
+                self.update_active_editor(cx, |editor, cx| {
+                    let snapshot = editor.buffer().read(cx).snapshot(cx);
+                    let last_row = rows.end - 1;
+                    let selection = Point::new(rows.start, 0)
+                        ..Point::new(last_row, snapshot.line_len(last_row));
+                    editor.transact(cx, |editor, cx| {
+                        editor.change_selections(None, cx, |s| s.select_ranges([selection]));
+                        for _ in 0..*times {
+                            if *right {
+                                editor.indent(&Default::default(), cx);
+                            } else {
+                                editor.outdent(&Default::default(), cx);
+                            }
+                        }
+                        move_to_first_non_blank(editor, Point::new(last_row, 0), cx);
+                    });
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The buffer and the row of the cursor, which addresses like `.` are relative to.
+    fn command_context(&self, cx: &mut WindowContext) -> Result<(MultiBufferSnapshot, u32)> {
+        self.update_active_editor(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let row = editor.selections.newest::<Point>(cx).head().row;
+            (snapshot, row)
+        })
+        .ok_or_else(|| anyhow!("No active editor"))
+    }
+
+    /// Resolves an address to a 1-based line number, where 0 is the position before the first
+    /// line.
+    fn resolve_address(
+        &mut self,
+        address: &Address,
+        snapshot: &MultiBufferSnapshot,
+        current_row: u32,
+        cx: &mut WindowContext,
+    ) -> Result<u32> {
+        let last_line = snapshot.max_point().row + 1;
+        let line = match &address.base {
+            AddressBase::Line(line) => *line,
+            AddressBase::CurrentLine => current_row + 1,
+            AddressBase::LastLine => last_line,
+            AddressBase::Mark(mark) => match self.mark_position(*mark, cx) {
+                Some(MarkPosition::Editor(anchor)) => anchor.to_point(snapshot).row + 1,
+                _ => bail!("E20: Mark not set"),
+            },
+            AddressBase::Search { pattern, backwards } => {
+                let regex = self.command_regex(pattern, false)?;
+                let row = search_row(snapshot, current_row, &regex, *backwards)
+                    .ok_or_else(|| anyhow!("E486: Pattern not found: {pattern}"))?;
+                row + 1
+            }
+        };
+        let line = line as i64 + address.offset;
+        if line < 0 || line > last_line as i64 {
+            bail!("E16: Invalid range");
+        }
+        Ok(line as u32)
+    }
+
+    /// Resolves a range to the rows it spans. Without a range, most commands apply to the
+    /// cursor's line, while others like `:sort` apply to the whole buffer.
+    fn resolve_range(
+        &mut self,
+        range: Option<&CommandRange>,
+        whole_buffer_by_default: bool,
+        cx: &mut WindowContext,
+    ) -> Result<Range<u32>> {
+        let (snapshot, cursor_row) = self.command_context(cx)?;
+        let Some(range) = range else {
+            return Ok(if whole_buffer_by_default {
+                0..snapshot.max_point().row + 1
+            } else {
+                cursor_row..cursor_row + 1
+            });
+        };
+
+        let start = self.resolve_address(&range.start, &snapshot, cursor_row, cx)?;
+        let end = match &range.end {
+            Some(end) => {
+                let relative_row = if range.end_relative_to_start {
+                    start.saturating_sub(1)
+                } else {
+                    cursor_row
+                };
+                self.resolve_address(end, &snapshot, relative_row, cx)?
+            }
+            None => start,
+        };
+        // Vim asks whether to swap a backwards range, which is done here without asking.
+        let (start, end) = if start > end {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        Ok(start.saturating_sub(1)..end.max(1))
+    }
+
+    /// Resolves the line that `:m` and `:t` put lines after, where 0 puts them first.
+    fn resolve_destination(&mut self, address: &Address, cx: &mut WindowContext) -> Result<u32> {
+        let (snapshot, cursor_row) = self.command_context(cx)?;
+        self.resolve_address(address, &snapshot, cursor_row, cx)
+    }
+
+    /// Converts the pattern of a command, reusing the last one when it's empty as in `:s//x/`.
+    fn command_regex(&mut self, pattern: &str, ignore_case: bool) -> Result<Regex> {
+        let pattern = if pattern.is_empty() {
+            self.workspace_state
+                .last_command_pattern
+                .clone()
+                .ok_or_else(|| anyhow!("E35: No previous regular expression"))?
+        } else {
+            pattern.to_string()
+        };
+        let regex = vim_regex(&pattern, ignore_case)?;
+        self.workspace_state.last_command_pattern = Some(pattern);
+        Ok(regex)
+    }
+
+    /// The starts of the lines in the range that match the pattern of a `:g` command, or with
+    /// `invert`, that don't.
+    fn matching_lines(
+        &mut self,
+        pattern: &str,
+        invert: bool,
+        range: Option<&CommandRange>,
+        cx: &mut WindowContext,
+    ) -> Result<(Regex, Vec<Anchor>)> {
+        let regex = self.command_regex(pattern, false)?;
+        let rows = self.resolve_range(range, true, cx)?;
+        let (snapshot, _) = self.command_context(cx)?;
+        let lines = rows
+            .filter(|row| regex.is_match(&line_text(&snapshot, *row)) != invert)
+            .map(|row| snapshot.anchor_after(Point::new(row, 0)))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            if invert {
+                bail!("Pattern found in every line: {pattern}");
+            } else {
+                bail!("E486: Pattern not found: {pattern}");
+            }
+        }
+        Ok((regex, lines))
+    }
+
+    /// The starts of the lines in the range, which `:normal` types its keystrokes on.
+    fn line_anchors(
+        &mut self,
+        range: Option<&CommandRange>,
+        cx: &mut WindowContext,
+    ) -> Result<Vec<Anchor>> {
+        let rows = self.resolve_range(range, false, cx)?;
+        let (snapshot, _) = self.command_context(cx)?;
+        Ok(rows
+            .map(|row| snapshot.anchor_after(Point::new(row, 0)))
+            .collect())
+    }
+
+    /// Moves the cursor to the start of a line. When a `:g` pattern is given, lines that an
+    /// earlier run of the command changed so that they no longer match are skipped, returning
+    /// false.
+    fn move_to_line(
+        &self,
+        line: Anchor,
+        filter: Option<&(Regex, bool)>,
+        cx: &mut WindowContext,
+    ) -> bool {
+        self.update_active_editor(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let row = line.to_point(&snapshot).row;
+            if let Some((regex, invert)) = filter {
+                if regex.is_match(&line_text(&snapshot, row)) == *invert {
+                    return false;
+                }
+            }
+            let start = Point::new(row, 0);
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([start..start])
+            });
+            true
+        })
+        .unwrap_or(false)
+    }
+}
+
+fn move_to_first_non_blank(editor: &mut Editor, line: impl ToPoint, cx: &mut ViewContext<Editor>) {
+    let snapshot = editor.buffer().read(cx).snapshot(cx);
+    let row = line.to_point(&snapshot).row;
+    let point = Point::new(row, snapshot.indent_size_for_line(row).len);
+    editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+        s.select_ranges([point..point])
+    });
+}
+
+fn line_text(snapshot: &MultiBufferSnapshot, row: u32) -> String {
+    snapshot
+        .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+        .collect()
+}
+
+fn lines_text(snapshot: &MultiBufferSnapshot, rows: Range<u32>) -> String {
+    rows.map(|row| line_text(snapshot, row))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The whole lines of the rows, along with the line break that separates them from the rest of
+/// the buffer, which precedes them when they include the last line.
+fn line_selection(snapshot: &MultiBufferSnapshot, rows: Range<u32>) -> Range<Point> {
+    let max_point = snapshot.max_point();
+    if rows.end > max_point.row {
+        let start = match rows.start.checked_sub(1) {
+            Some(row) => Point::new(row, snapshot.line_len(row)),
+            None => Point::zero(),
+        };
+        start..max_point
+    } else {
+        Point::new(rows.start, 0)..Point::new(rows.end, 0)
+    }
+}
+
+/// The next row after `from_row` that matches, or the previous one when searching backwards,
+/// wrapping around the end of the buffer.
+fn search_row(
+    snapshot: &MultiBufferSnapshot,
+    from_row: u32,
+    regex: &Regex,
+    backwards: bool,
+) -> Option<u32> {
+    let row_count = snapshot.max_point().row + 1;
+    (1..=row_count)
+        .map(|distance| {
+            if backwards {
+                (from_row + row_count - distance) % row_count
+            } else {
+                (from_row + distance) % row_count
+            }
+        })
+        .find(|row| regex.is_match(&line_text(snapshot, *row)))
+}
+
+fn first_number(line: &str) -> Option<i64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let negative = line[..start].ends_with('-');
+    let digits = &line[start..];
+    let digits = &digits[..digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len())];
+    let number = digits.parse::<i64>().unwrap_or(i64::MAX);
+    Some(if negative { -number } else { number })
+}
+
+/// Converts a vim pattern into a `regex` one. Only the differences that come up in everyday use
+/// are handled: in vim, `(`, `)`, `|`, `+`, `?` and `{` are literal unless they're escaped,
+/// `\<` and `\>` match at word boundaries, and `\c` and `\C` override the case sensitivity.
+fn vim_regex(pattern: &str, mut ignore_case: bool) -> Result<Regex> {
+    let mut converted = String::new();
+    let mut in_repetition = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('(' | ')' | '|' | '+' | '?')) => converted.push(c),
+                Some('{') => {
+                    in_repetition = true;
+                    converted.push('{');
+                }
+                Some('}') if in_repetition => {
+                    in_repetition = false;
+                    converted.push('}');
+                }
+                Some('<' | '>') => converted.push_str("\\b"),
+                Some('c') => ignore_case = true,
+                Some('C') => ignore_case = false,
+                Some(c) => {
+                    converted.push('\\');
+                    converted.push(c);
+                }
+                None => converted.push_str("\\\\"),
+            },
+            '}' if in_repetition => {
+                in_repetition = false;
+                converted.push('}');
+            }
+            '(' | ')' | '|' | '+' | '?' | '{' | '}' => {
+                converted.push('\\');
+                converted.push(c);
+            }
+            c => converted.push(c),
+        }
+    }
+    RegexBuilder::new(&converted)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|_| anyhow!("E383: Invalid search string: {pattern}"))
+}
+
+/// Converts a vim replacement into a `regex` one, where `&` and `\0` to `\9` insert the match
+/// and its groups, and `\r` breaks the line.
+fn vim_replacement(replacement: &str) -> String {
+    let mut converted = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(group @ '0'..='9') => {
+                    converted.push_str("${");
+                    converted.push(group);
+                    converted.push('}');
+                }
+                Some('r' | 'n') => converted.push('\n'),
+                Some('t') => converted.push('\t'),
+                Some('$') => converted.push_str("$$"),
+                Some(c) => converted.push(c),
+                None => converted.push('\\'),
+            },
+            '&' => converted.push_str("${0}"),
+            '$' => converted.push_str("$$"),
+            c => converted.push(c),
+        }
+    }
+    converted
+}
+
 #[cfg(test)]
 mod test {
     use std::path::Path;
 
-    use crate::test::{NeovimBackedTestContext, VimTestContext};
+    use crate::{
+        state::Mode,
+        test::{NeovimBackedTestContext, VimTestContext},
+    };
     use gpui::TestAppContext;
     use indoc::indoc;
 
+    use super::command_interceptor;
+
+    fn simulate_ex_command(cx: &mut VimTestContext, command: &str) {
+        cx.simulate_keystrokes([":"]);
+        for c in command.chars() {
+            cx.simulate_keystroke(&c.to_string());
+        }
+        cx.simulate_keystrokes(["enter"]);
+    }
+
     #[gpui::test]
     async fn test_command_basics(cx: &mut TestAppContext) {
         let mut cx = NeovimBackedTestContext::new(cx).await;
@@ -437,4 +1649,237 @@ mod test {
         cx.simulate_keystrokes([":", "q", "a", "enter"]);
         cx.workspace(|workspace, cx| assert_eq!(workspace.items(cx).count(), 0));
     }
+
+    #[gpui::test]
+    async fn test_command_ranges(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇa
+                b
+                c
+                d
+                e"},
+            Mode::Normal,
+        );
+
+        simulate_ex_command(&mut cx, "2,3d");
+        cx.assert_state(
+            indoc! {"
+                a
+                ˇd
+                e"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, ".,$d");
+        cx.assert_state("ˇa", Mode::Normal);
+
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three
+                four"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["j", "m", "a", "k"]);
+        simulate_ex_command(&mut cx, "'a,/four/s/o/0/g");
+        cx.assert_state(
+            indoc! {"
+                one
+                tw0
+                three
+                ˇf0ur"},
+            Mode::Normal,
+        );
+
+        // Commands run from visual mode apply to the selected lines.
+        cx.set_state(
+            indoc! {"
+                a
+                ˇb
+                c
+                d"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["shift-v", "j"]);
+        simulate_ex_command(&mut cx, "s/$/!");
+        cx.assert_state(
+            indoc! {"
+                a
+                b!
+                ˇc!
+                d"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_command_global(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇa1
+                b
+                a2
+                c"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "g/a/d");
+        cx.assert_state(
+            indoc! {"
+                b
+                ˇc"},
+            Mode::Normal,
+        );
+
+        cx.set_state(
+            indoc! {"
+                ˇa1
+                b
+                a2
+                c"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "v/a/s/$/!");
+        cx.assert_state(
+            indoc! {"
+                a1
+                b!
+                a2
+                ˇc!"},
+            Mode::Normal,
+        );
+
+        // Undo reverts the whole command.
+        cx.simulate_keystrokes(["u"]);
+        cx.assert_state(
+            indoc! {"
+                ˇa1
+                b
+                a2
+                c"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_command_normal(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "%norm x");
+        cx.assert_state(
+            indoc! {"
+                ne
+                wo
+                ˇhree"},
+            Mode::Normal,
+        );
+
+        // Incomplete commands end as though escape was typed.
+        simulate_ex_command(&mut cx, "g/e/normal A!");
+        cx.assert_state(
+            indoc! {"
+                ne!
+                wo
+                hreeˇ!"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_command_move_copy_sort(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇa
+                b
+                c"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "m$");
+        cx.assert_state(
+            indoc! {"
+                b
+                c
+                ˇa"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "1,2t0");
+        cx.assert_state(
+            indoc! {"
+                b
+                ˇc
+                b
+                c
+                a"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "sort u");
+        cx.assert_state(
+            indoc! {"
+                ˇa
+                b
+                c"},
+            Mode::Normal,
+        );
+
+        cx.set_state(
+            indoc! {"
+                ˇx10
+                y2
+                z1"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "sort! n");
+        cx.assert_state(
+            indoc! {"
+                ˇx10
+                y2
+                z1"},
+            Mode::Normal,
+        );
+        simulate_ex_command(&mut cx, "sort n");
+        cx.assert_state(
+            indoc! {"
+                ˇz1
+                y2
+                x10"},
+            Mode::Normal,
+        );
+
+        simulate_ex_command(&mut cx, "2,3>");
+        cx.assert_state(
+            indoc! {"
+                z1
+                    y2
+                    ˇx10"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_command_interceptor_errors(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.update(|cx| {
+            let result = command_interceptor("3foo", cx).unwrap();
+            assert_eq!(result.string, ":3foo  E492: Not an editor command: 3foo");
+            let result = command_interceptor("s/a/b/x", cx).unwrap();
+            assert_eq!(result.string, ":s/a/b/x  E488: Trailing characters: x");
+            let result = command_interceptor("g/a/g/b/d", cx).unwrap();
+            assert_eq!(
+                result.string,
+                ":g/a/g/b/d  E147: Cannot do :global recursive"
+            );
+            // Searches for actions aren't mistaken for commands.
+            assert!(command_interceptor("move line up", cx).is_none());
+            assert!(command_interceptor("sort lines", cx).is_none());
+        });
+    }
 }
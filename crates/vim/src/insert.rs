@@ -14,6 +14,9 @@ fn normal_before(_: &mut Workspace, action: &NormalBefore, cx: &mut ViewContext<
     let should_repeat = Vim::update(cx, |vim, cx| {
         let count = vim.take_count(cx).unwrap_or(1);
         vim.stop_recording_immediately(action.boxed_clone());
+        if let Some(cursor) = vim.newest_cursor(cx) {
+            vim.set_mark('^', cursor, cx);
+        }
         if count <= 1 || vim.workspace_state.replaying {
             vim.update_active_editor(cx, |editor, cx| {
                 editor.cancel(&Default::default(), cx);
@@ -0,0 +1,532 @@
+use std::{ops::Range, path::PathBuf, sync::Arc};
+
+use collections::HashMap;
+
+use anyhow::Context as _;
+use editor::{scroll::Autoscroll, Anchor, Bias, Editor, ToOffset};
+use gpui::{AppContext, Model, View, WindowContext};
+use language::{Buffer, Point, ToPoint as _};
+
+use crate::{
+    motion::{self, Motion},
+    persistence::DB,
+    state::{BufferMarks, GlobalMark},
+    Vim,
+};
+
+/// Where the cursor goes when jumping to a mark.
+pub(crate) enum MarkPosition {
+    /// A position in one of the buffers of the active editor.
+    Editor(Anchor),
+    /// A position in a file that has to be opened first.
+    File { abs_path: PathBuf, point: Point },
+}
+
+pub(crate) fn create_mark(text: Arc<str>, cx: &mut WindowContext) {
+    let mark = match text.chars().next().unwrap() {
+        '\'' => '`',
+        mark => mark,
+    };
+    Vim::update(cx, |vim, cx| {
+        if mark.is_ascii_alphabetic() || matches!(mark, '`' | '[' | ']' | '<' | '>') {
+            if let Some(cursor) = vim.newest_cursor(cx) {
+                vim.set_mark(mark, cursor, cx);
+            }
+        }
+        vim.clear_operator(cx);
+    });
+}
+
+pub(crate) fn jump(text: Arc<str>, line: bool, cx: &mut WindowContext) {
+    let mark = match text.chars().next().unwrap() {
+        '\'' => '`',
+        mark => mark,
+    };
+    let position = Vim::update(cx, |vim, cx| {
+        vim.pop_operator(cx);
+        vim.mark_position(mark, cx)
+    });
+    match position {
+        Some(MarkPosition::Editor(anchor)) => motion::motion(Motion::Jump { anchor, line }, cx),
+        Some(MarkPosition::File { abs_path, point }) => Vim::update(cx, |vim, cx| {
+            vim.clear_operator(cx);
+            if let Some(cursor) = vim.newest_cursor(cx) {
+                vim.set_mark('`', cursor, cx);
+            }
+            vim.open_mark(abs_path, point, line, cx);
+        }),
+        None => Vim::update(cx, |vim, cx| vim.clear_operator(cx)),
+    }
+}
+
+impl Vim {
+    pub(crate) fn newest_cursor(&self, cx: &mut WindowContext) -> Option<Anchor> {
+        self.update_active_editor(cx, |editor, _| editor.selections.newest_anchor().head())
+    }
+
+    /// Sets the mark to the given position in the active editor. Uppercase marks are global, and
+    /// are remembered across sessions when they're in a file on disk.
+    pub(crate) fn set_mark(&mut self, mark: char, position: Anchor, cx: &mut WindowContext) {
+        let Some((buffer, anchor)) = self
+            .update_active_editor(cx, |editor, cx| buffer_anchor(editor, position, cx))
+            .flatten()
+        else {
+            return;
+        };
+
+        if mark.is_ascii_uppercase() {
+            let (abs_path, point) = {
+                let buffer = buffer.read(cx);
+                let abs_path = buffer
+                    .file()
+                    .and_then(|file| file.as_local())
+                    .map(|file| file.abs_path(cx));
+                (abs_path, anchor.to_point(buffer))
+            };
+            if let Some(abs_path) = abs_path.clone() {
+                cx.background_executor()
+                    .spawn(DB.save_global_mark(mark.to_string(), abs_path, point.row, point.column))
+                    .detach_and_log_err(cx);
+            }
+            self.workspace_state.global_marks.insert(
+                mark,
+                GlobalMark {
+                    abs_path,
+                    anchor: Some((buffer.downgrade(), anchor)),
+                    point,
+                },
+            );
+        } else {
+            self.buffer_marks(&buffer, cx).insert(mark, anchor);
+        }
+    }
+
+    /// The marks of the given buffer, forgetting those of the buffers that were dropped.
+    fn buffer_marks(
+        &mut self,
+        buffer: &Model<Buffer>,
+        cx: &AppContext,
+    ) -> &mut HashMap<char, language::Anchor> {
+        let marks = &mut self.workspace_state.marks;
+        marks.retain(|_, buffer_marks| buffer_marks.buffer.upgrade().is_some());
+        &mut marks
+            .entry(buffer.read(cx).remote_id())
+            .or_insert_with(|| BufferMarks {
+                buffer: buffer.downgrade(),
+                marks: HashMap::default(),
+            })
+            .marks
+    }
+
+    /// Sets two marks to the first and the last character of the given range.
+    pub(crate) fn set_range_marks(
+        &mut self,
+        start_mark: char,
+        end_mark: char,
+        range: Range<Anchor>,
+        cx: &mut WindowContext,
+    ) {
+        let Some(end) = self.update_active_editor(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let start = range.start.to_offset(&snapshot);
+            let end = range.end.to_offset(&snapshot);
+            let last_char_len = snapshot
+                .reversed_chars_at(end)
+                .next()
+                .map_or(0, |c| c.len_utf8());
+            snapshot.anchor_before(end.saturating_sub(last_char_len).max(start))
+        }) else {
+            return;
+        };
+        self.set_mark(start_mark, range.start, cx);
+        self.set_mark(end_mark, end, cx);
+    }
+
+    /// Sets the `<` and `>` marks to the start and the end of the visual selection.
+    pub(crate) fn set_visual_marks(&mut self, cx: &mut WindowContext) {
+        let selection = self.update_active_editor(cx, |editor, _| {
+            let selections = editor.selections.disjoint_anchors();
+            Some(selections.first()?.start..selections.last()?.end)
+        });
+        if let Some(range) = selection.flatten() {
+            self.set_range_marks('<', '>', range, cx);
+        }
+    }
+
+    /// Sets the `[` and `]` marks of each buffer edited by the last transaction of the editor
+    /// to the first and the last character of the changed text, and the `.` mark to the start of
+    /// the last change.
+    pub(crate) fn set_change_marks(&mut self, editor: &View<Editor>, cx: &mut WindowContext) {
+        let multi_buffer = editor.read(cx).buffer().read(cx);
+        let Some(transaction_id) = multi_buffer.last_transaction_id(cx) else {
+            return;
+        };
+        let edited_ranges = multi_buffer.edited_ranges_for_transaction::<usize>(transaction_id, cx);
+        let mut changes = HashMap::<u64, (Model<Buffer>, Range<usize>, usize)>::default();
+        for (buffer, range) in edited_ranges {
+            changes
+                .entry(buffer.read(cx).remote_id())
+                .and_modify(|(_, changed_range, last_change)| {
+                    changed_range.start = changed_range.start.min(range.start);
+                    changed_range.end = changed_range.end.max(range.end);
+                    *last_change = range.start;
+                })
+                .or_insert_with(|| (buffer, range.clone(), range.start));
+        }
+
+        for (buffer, range, last_change) in changes.into_values() {
+            let (start, end, last_change) = {
+                let snapshot = buffer.read(cx).snapshot();
+                let last_char_len = snapshot
+                    .reversed_chars_at(range.end)
+                    .next()
+                    .map_or(0, |c| c.len_utf8());
+                let end = range.end.saturating_sub(last_char_len).max(range.start);
+                (
+                    snapshot.anchor_before(range.start),
+                    snapshot.anchor_before(end),
+                    snapshot.anchor_before(last_change),
+                )
+            };
+            let marks = self.buffer_marks(&buffer, cx);
+            marks.insert('[', start);
+            marks.insert(']', end);
+            marks.insert('.', last_change);
+        }
+    }
+
+    /// Remembers the global marks saved in previous sessions, unless they were set since.
+    pub(crate) fn load_global_marks(&mut self, marks: Vec<(String, PathBuf, u32, u32)>) {
+        for (mark, abs_path, row, column) in marks {
+            let Some(mark) = mark.chars().next() else {
+                continue;
+            };
+            self.workspace_state
+                .global_marks
+                .entry(mark)
+                .or_insert_with(|| GlobalMark {
+                    abs_path: Some(abs_path),
+                    anchor: None,
+                    point: Point::new(row, column),
+                });
+        }
+    }
+
+    /// Remembers the position of the cursor before a jump, both in the `` ` `` mark and in the
+    /// navigation history, so that `Ctrl-O` goes back to it.
+    pub(crate) fn record_jump(&mut self, origin: Anchor, cx: &mut WindowContext) {
+        self.set_mark('`', origin, cx);
+        self.update_active_editor(cx, |editor, cx| editor.push_jump_to_nav_history(origin, cx));
+    }
+
+    pub(crate) fn mark_position(
+        &mut self,
+        mark: char,
+        cx: &mut WindowContext,
+    ) -> Option<MarkPosition> {
+        if !mark.is_ascii_uppercase() {
+            return self
+                .update_active_editor(cx, |editor, cx| {
+                    let cursor = editor.selections.newest_anchor().head();
+                    let (buffer, _) = buffer_anchor(editor, cursor, cx)?;
+                    let buffer_marks = self
+                        .workspace_state
+                        .marks
+                        .get(&buffer.read(cx).remote_id())?;
+                    editor_anchor(editor, &buffer, *buffer_marks.marks.get(&mark)?, cx)
+                })
+                .flatten()
+                .map(MarkPosition::Editor);
+        }
+
+        let global_mark = self.workspace_state.global_marks.get(&mark)?.clone();
+
+        let open_buffer = global_mark
+            .anchor
+            .and_then(|(buffer, anchor)| Some((buffer.upgrade()?, anchor)));
+        let point = match open_buffer {
+            Some((buffer, anchor)) => {
+                let anchor_in_editor = self
+                    .update_active_editor(cx, |editor, cx| {
+                        editor_anchor(editor, &buffer, anchor, cx)
+                    })
+                    .flatten();
+                if let Some(anchor_in_editor) = anchor_in_editor {
+                    return Some(MarkPosition::Editor(anchor_in_editor));
+                }
+                anchor.to_point(buffer.read(cx))
+            }
+            None => global_mark.point,
+        };
+        Some(MarkPosition::File {
+            abs_path: global_mark.abs_path?,
+            point,
+        })
+    }
+
+    /// Opens the file of a global mark, which records the jump in the navigation history as the
+    /// active editor is deactivated.
+    fn open_mark(&self, abs_path: PathBuf, point: Point, line: bool, cx: &mut WindowContext) {
+        let Some(workspace) = self
+            .update_active_editor(cx, |editor, _| editor.workspace())
+            .flatten()
+        else {
+            return;
+        };
+        let open = workspace.update(cx, |workspace, cx| {
+            workspace.open_abs_path(abs_path, true, cx)
+        });
+        cx.spawn(|mut cx| async move {
+            let editor = open
+                .await?
+                .downcast::<Editor>()
+                .context("mark is not in an editor")?;
+            editor.update(&mut cx, |editor, cx| {
+                let snapshot = editor.buffer().read(cx).snapshot(cx);
+                let mut point = snapshot.clip_point(point, Bias::Left);
+                if line {
+                    point.column = snapshot.indent_size_for_line(point.row).len;
+                }
+                editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                    s.select_ranges([point..point])
+                });
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+/// The buffer at the given position of the editor, along with that position in the buffer.
+fn buffer_anchor(
+    editor: &Editor,
+    position: Anchor,
+    cx: &AppContext,
+) -> Option<(Model<Buffer>, language::Anchor)> {
+    let multibuffer = editor.buffer().read(cx);
+    let snapshot = multibuffer.snapshot(cx);
+    // The anchors at the very start and end of a multibuffer don't belong to any buffer.
+    let position = snapshot.anchor_before(position.to_offset(&snapshot));
+    let buffer = multibuffer.buffer(position.buffer_id?)?;
+    Some((buffer, position.text_anchor))
+}
+
+/// The position in the editor of a position in one of its buffers, which is clipped to the
+/// nearest excerpt if it's not part of any.
+fn editor_anchor(
+    editor: &Editor,
+    buffer: &Model<Buffer>,
+    anchor: language::Anchor,
+    cx: &AppContext,
+) -> Option<Anchor> {
+    let multibuffer = editor.buffer().read(cx);
+    let buffer_snapshot = buffer.read(cx);
+    let excerpts = multibuffer.excerpts_for_buffer(buffer, cx);
+    let (excerpt_id, _) = excerpts
+        .iter()
+        .find(|(_, range)| {
+            range.context.start.cmp(&anchor, buffer_snapshot).is_le()
+                && range.context.end.cmp(&anchor, buffer_snapshot).is_ge()
+        })
+        .or(excerpts.first())?;
+    Some(
+        multibuffer
+            .snapshot(cx)
+            .anchor_in_excerpt(*excerpt_id, anchor),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    #[gpui::test]
+    async fn test_local_marks(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                one
+                    twˇo
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["m", "a", "shift-g"]);
+        cx.simulate_keystrokes(["`", "a"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                    twˇo
+                three"},
+            Mode::Normal,
+        );
+
+        // Marks move with the text they're in.
+        cx.simulate_keystrokes(["g", "g", "shift-o", "z", "e", "r", "o", "escape"]);
+        cx.simulate_keystrokes(["'", "a"]);
+        cx.assert_state(
+            indoc! {"
+                zero
+                one
+                    ˇtwo
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["g", "g", "d", "'", "a"]);
+        cx.assert_state(
+            indoc! {"
+            ˇthree"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_special_marks(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three"},
+            Mode::Normal,
+        );
+
+        // `` jumps back to where the last jump started.
+        cx.simulate_keystrokes(["l", "shift-g", "`", "`"]);
+        cx.assert_state(
+            indoc! {"
+                oˇne
+                two
+                three"},
+            Mode::Normal,
+        );
+
+        // `. jumps to the last change.
+        cx.simulate_keystrokes(["j", "x", "g", "g", "`", "."]);
+        cx.assert_state(
+            indoc! {"
+                one
+                tˇo
+                three"},
+            Mode::Normal,
+        );
+
+        // `< and `> jump to the start and the end of the last visual selection.
+        cx.simulate_keystrokes(["g", "g", "v", "j", "escape", "g", "g"]);
+        cx.simulate_keystrokes(["`", ">"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇto
+                three"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["`", "<"]);
+        cx.assert_state(
+            indoc! {"
+                ˇone
+                to
+                three"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_change_marks(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                one
+                twˇo
+                three"},
+            Mode::Normal,
+        );
+
+        // `[ and `] jump to the first and the last character of the changed text.
+        cx.simulate_keystrokes(["shift-a", "space", "a", "n", "d", "escape", "g", "g"]);
+        cx.simulate_keystrokes(["`", "["]);
+        cx.assert_state(
+            indoc! {"
+                one
+                twoˇ and
+                three"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["`", "]"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                two anˇd
+                three"},
+            Mode::Normal,
+        );
+
+        // Edits made outside of the editor don't move the marks.
+        cx.update_buffer(|buffer, cx| buffer.edit([(0..0, "zero ")], None, cx));
+        cx.simulate_keystrokes(["g", "g", "`", "["]);
+        cx.assert_state(
+            indoc! {"
+                zero one
+                twoˇ and
+                three"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_global_marks(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                one
+                twˇo
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["m", "shift-a", "shift-g"]);
+        cx.simulate_keystrokes(["'", "shift-a"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇtwo
+                three"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_jump_list(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                oˇne
+                two
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["shift-g", "ctrl-o"]);
+        cx.run_until_parked();
+        cx.assert_state(
+            indoc! {"
+                oˇne
+                two
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["ctrl-i"]);
+        cx.run_until_parked();
+        cx.assert_state(
+            indoc! {"
+                one
+                two
+                ˇthree"},
+            Mode::Normal,
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use editor::{
     display_map::{DisplaySnapshot, FoldPoint, ToDisplayPoint},
     movement::{self, find_boundary, find_preceding_boundary, FindRange, TextLayoutDetails},
-    Bias, DisplayPoint, ToOffset,
+    Anchor, Bias, DisplayPoint, ToOffset,
 };
 use gpui::{actions, impl_actions, px, ViewContext, WindowContext};
 use language::{char_kind, CharKind, Point, Selection, SelectionGoal};
@@ -41,6 +41,7 @@ pub enum Motion {
     StartOfLineDownward,
     EndOfLineDownward,
     GoToColumn,
+    Jump { anchor: Anchor, line: bool },
 }
 
 #[derive(Clone, Deserialize, PartialEq)]
@@ -242,14 +243,26 @@ pub(crate) fn motion(motion: Motion, cx: &mut WindowContext) {
 
     let count = Vim::update(cx, |vim, cx| vim.take_count(cx));
     let operator = Vim::read(cx).active_operator();
+    let jump_origin = if operator.is_none() && motion.is_jump() {
+        Vim::update(cx, |vim, cx| {
+            vim.update_active_editor(cx, |editor, _| editor.selections.newest_anchor().head())
+        })
+    } else {
+        None
+    };
     match Vim::read(cx).state().mode {
         Mode::Normal => normal_motion(motion, operator, count, cx),
         Mode::Visual | Mode::VisualLine | Mode::VisualBlock => visual_motion(motion, count, cx),
-        Mode::Insert => {
-            // Shouldn't execute a motion in insert mode. Ignoring
+        Mode::Insert | Mode::Replace => {
+            // Shouldn't execute a motion in insert or replace mode. Ignoring
         }
     }
-    Vim::update(cx, |vim, cx| vim.clear_operator(cx));
+    Vim::update(cx, |vim, cx| {
+        if let Some(jump_origin) = jump_origin {
+            vim.record_jump(jump_origin, cx);
+        }
+        vim.clear_operator(cx)
+    });
 }
 
 fn repeat_motion(backwards: bool, cx: &mut WindowContext) {
@@ -310,13 +323,14 @@ impl Motion {
             | PreviousWordStart { .. }
             | FirstNonWhitespace { .. }
             | FindBackward { .. } => false,
+            Jump { line, .. } => *line,
         }
     }
 
     pub fn infallible(&self) -> bool {
         use Motion::*;
         match self {
-            StartOfDocument | EndOfDocument | CurrentLine => true,
+            StartOfDocument | EndOfDocument | CurrentLine | Jump { .. } => true,
             Down { .. }
             | Up { .. }
             | EndOfLine { .. }
@@ -365,10 +379,26 @@ impl Motion {
             | NextWordStart { .. }
             | PreviousWordStart { .. }
             | FirstNonWhitespace { .. }
-            | FindBackward { .. } => false,
+            | FindBackward { .. }
+            | Jump { .. } => false,
         }
     }
 
+    /// Whether the motion jumps away from the cursor, in which case the cursor position is
+    /// remembered by the jump list and the `` ` `` mark.
+    pub fn is_jump(&self) -> bool {
+        use Motion::*;
+        matches!(
+            self,
+            StartOfParagraph
+                | EndOfParagraph
+                | StartOfDocument
+                | EndOfDocument
+                | Matching
+                | Jump { .. }
+        )
+    }
+
     pub fn move_point(
         &self,
         map: &DisplaySnapshot,
@@ -446,6 +476,14 @@ impl Motion {
             StartOfLineDownward => (next_line_start(map, point, times - 1), SelectionGoal::None),
             EndOfLineDownward => (next_line_end(map, point, times), SelectionGoal::None),
             GoToColumn => (go_to_column(map, point, times), SelectionGoal::None),
+            Jump { anchor, line } => {
+                let point = anchor.to_display_point(map);
+                if *line {
+                    (first_non_whitespace(map, false, point), SelectionGoal::None)
+                } else {
+                    (point, SelectionGoal::None)
+                }
+            }
         };
 
         (new_point != point || infallible).then_some((new_point, goal))
@@ -1,7 +1,9 @@
 mod case;
 mod change;
 mod delete;
+mod format;
 mod increment;
+mod indent;
 mod paste;
 pub(crate) mod repeat;
 mod scroll;
@@ -26,9 +28,11 @@ use log::error;
 use workspace::Workspace;
 
 use self::{
-    case::change_case,
+    case::{change_case, change_case_motion, change_case_object, CaseTarget},
     change::{change_motion, change_object},
     delete::{delete_motion, delete_object},
+    format::{format_motion, format_object},
+    indent::{indent_motion, indent_object, IndentDirection},
     yank::{yank_motion, yank_object},
 };
 
@@ -145,6 +149,24 @@ pub fn normal_motion(
             Some(Operator::Change) => change_motion(vim, motion, times, cx),
             Some(Operator::Delete) => delete_motion(vim, motion, times, cx),
             Some(Operator::Yank) => yank_motion(vim, motion, times, cx),
+            Some(Operator::Indent) => indent_motion(vim, motion, times, IndentDirection::In, cx),
+            Some(Operator::Outdent) => indent_motion(vim, motion, times, IndentDirection::Out, cx),
+            Some(Operator::AutoIndent) => {
+                indent_motion(vim, motion, times, IndentDirection::Auto, cx)
+            }
+            Some(Operator::Format { keep_cursor }) => {
+                format_motion(vim, motion, times, keep_cursor, cx)
+            }
+            Some(Operator::Lowercase) => {
+                change_case_motion(vim, motion, times, CaseTarget::Lowercase, cx)
+            }
+            Some(Operator::Uppercase) => {
+                change_case_motion(vim, motion, times, CaseTarget::Uppercase, cx)
+            }
+            Some(Operator::OppositeCase) => {
+                change_case_motion(vim, motion, times, CaseTarget::OppositeCase, cx)
+            }
+            Some(Operator::Rot13) => change_case_motion(vim, motion, times, CaseTarget::Rot13, cx),
             Some(operator) => {
                 // Can't do anything for text objects, Ignoring
                 error!("Unexpected normal mode motion operator: {:?}", operator)
@@ -160,6 +182,30 @@ pub fn normal_object(object: Object, cx: &mut WindowContext) {
                 Some(Operator::Change) => change_object(vim, object, around, cx),
                 Some(Operator::Delete) => delete_object(vim, object, around, cx),
                 Some(Operator::Yank) => yank_object(vim, object, around, cx),
+                Some(Operator::Indent) => {
+                    indent_object(vim, object, around, IndentDirection::In, cx)
+                }
+                Some(Operator::Outdent) => {
+                    indent_object(vim, object, around, IndentDirection::Out, cx)
+                }
+                Some(Operator::AutoIndent) => {
+                    indent_object(vim, object, around, IndentDirection::Auto, cx)
+                }
+                Some(Operator::Format { keep_cursor }) => {
+                    format_object(vim, object, around, keep_cursor, cx)
+                }
+                Some(Operator::Lowercase) => {
+                    change_case_object(vim, object, around, CaseTarget::Lowercase, cx)
+                }
+                Some(Operator::Uppercase) => {
+                    change_case_object(vim, object, around, CaseTarget::Uppercase, cx)
+                }
+                Some(Operator::OppositeCase) => {
+                    change_case_object(vim, object, around, CaseTarget::OppositeCase, cx)
+                }
+                Some(Operator::Rot13) => {
+                    change_case_object(vim, object, around, CaseTarget::Rot13, cx)
+                }
                 _ => {
                     // Can't do anything for namespace operators. Ignoring
                 }
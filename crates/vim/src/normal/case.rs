@@ -1,9 +1,137 @@
-use editor::scroll::Autoscroll;
-use gpui::ViewContext;
-use language::{Bias, Point};
+use collections::HashMap;
+use editor::{display_map::ToDisplayPoint, scroll::Autoscroll, DisplayPoint, Editor};
+use gpui::{ViewContext, WindowContext};
+use language::{Bias, Point, SelectionGoal};
 use workspace::Workspace;
 
-use crate::{normal::ChangeCase, state::Mode, Vim};
+use crate::{motion::Motion, normal::ChangeCase, object::Object, state::Mode, Vim};
+
+#[derive(Clone, Copy)]
+pub enum CaseTarget {
+    Lowercase,
+    Uppercase,
+    OppositeCase,
+    Rot13,
+}
+
+impl CaseTarget {
+    fn convert(self, text: &str) -> String {
+        match self {
+            CaseTarget::Lowercase => text.to_lowercase(),
+            CaseTarget::Uppercase => text.to_uppercase(),
+            CaseTarget::OppositeCase => text
+                .chars()
+                .flat_map(|c| {
+                    if c.is_lowercase() {
+                        c.to_uppercase().collect::<Vec<char>>()
+                    } else {
+                        c.to_lowercase().collect::<Vec<char>>()
+                    }
+                })
+                .collect(),
+            CaseTarget::Rot13 => text
+                .chars()
+                .map(|c| match c {
+                    'a'..='m' | 'A'..='M' => (c as u8 + 13) as char,
+                    'n'..='z' | 'N'..='Z' => (c as u8 - 13) as char,
+                    _ => c,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Changes the case of the text a motion moves over. The cursor moves to the start of the text,
+/// keeping its column when the motion is linewise.
+pub fn change_case_motion(
+    vim: &mut Vim,
+    motion: Motion,
+    times: Option<usize>,
+    target: CaseTarget,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        let text_layout_details = editor.text_layout_details(cx);
+        editor.transact(cx, |editor, cx| {
+            let mut cursors: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let original = selection.head();
+                    motion.expand_selection(map, selection, times, false, &text_layout_details);
+                    let cursor = if motion.linewise() {
+                        map.clip_point(
+                            DisplayPoint::new(selection.start.row(), original.column()),
+                            Bias::Left,
+                        )
+                    } else {
+                        selection.start
+                    };
+                    cursors.insert(
+                        selection.id,
+                        map.buffer_snapshot.anchor_before(cursor.to_point(map)),
+                    );
+                });
+            });
+            convert_selections(editor, target, cx);
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let cursor = cursors.remove(&selection.id).unwrap().to_display_point(map);
+                    selection.collapse_to(map.clip_point(cursor, Bias::Left), SelectionGoal::None);
+                });
+            });
+        });
+    });
+}
+
+/// Changes the case of a text object, moving the cursor to its start.
+pub fn change_case_object(
+    vim: &mut Vim,
+    object: Object,
+    around: bool,
+    target: CaseTarget,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        editor.transact(cx, |editor, cx| {
+            let mut cursors: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    object.expand_selection(map, selection, around);
+                    let cursor = map
+                        .buffer_snapshot
+                        .anchor_before(selection.start.to_point(map));
+                    cursors.insert(selection.id, cursor);
+                });
+            });
+            convert_selections(editor, target, cx);
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let cursor = cursors.remove(&selection.id).unwrap().to_display_point(map);
+                    selection.collapse_to(map.clip_point(cursor, Bias::Left), SelectionGoal::None);
+                });
+            });
+        });
+    });
+}
+
+fn convert_selections(editor: &mut Editor, target: CaseTarget, cx: &mut ViewContext<Editor>) {
+    let snapshot = editor.buffer().read(cx).snapshot(cx);
+    let edits = editor
+        .selections
+        .all::<usize>(cx)
+        .into_iter()
+        .filter_map(|selection| {
+            let text = snapshot
+                .text_for_range(selection.range())
+                .collect::<String>();
+            let converted = target.convert(&text);
+            (converted != text).then(|| (selection.range(), converted))
+        })
+        .collect::<Vec<_>>();
+    editor.edit(edits, cx);
+}
 
 pub fn change_case(_: &mut Workspace, _: &ChangeCase, cx: &mut ViewContext<Workspace>) {
     Vim::update(cx, |vim, cx| {
@@ -32,7 +160,7 @@ pub fn change_case(_: &mut Workspace, _: &ChangeCase, cx: &mut ViewContext<Works
                             cursor_positions.push(selection.start..selection.start);
                         }
                     }
-                    Mode::Insert | Mode::Normal => {
+                    Mode::Insert | Mode::Replace | Mode::Normal => {
                         let start = selection.start;
                         let mut end = start;
                         for _ in 0..count {
@@ -51,17 +179,11 @@ pub fn change_case(_: &mut Workspace, _: &ChangeCase, cx: &mut ViewContext<Works
                 for range in ranges.into_iter().rev() {
                     let snapshot = editor.buffer().read(cx).snapshot(cx);
                     editor.buffer().update(cx, |buffer, cx| {
-                        let text = snapshot
-                            .text_for_range(range.start..range.end)
-                            .flat_map(|s| s.chars())
-                            .flat_map(|c| {
-                                if c.is_lowercase() {
-                                    c.to_uppercase().collect::<Vec<char>>()
-                                } else {
-                                    c.to_lowercase().collect::<Vec<char>>()
-                                }
-                            })
-                            .collect::<String>();
+                        let text = CaseTarget::OppositeCase.convert(
+                            &snapshot
+                                .text_for_range(range.start..range.end)
+                                .collect::<String>(),
+                        );
 
                         buffer.edit([(range, text)], None, cx)
                     })
@@ -76,7 +198,12 @@ pub fn change_case(_: &mut Workspace, _: &ChangeCase, cx: &mut ViewContext<Works
 }
 #[cfg(test)]
 mod test {
-    use crate::{state::Mode, test::NeovimBackedTestContext};
+    use indoc::indoc;
+
+    use crate::{
+        state::Mode,
+        test::{NeovimBackedTestContext, VimTestContext},
+    };
 
     #[gpui::test]
     async fn test_change_case(cx: &mut gpui::TestAppContext) {
@@ -113,4 +240,66 @@ mod test {
         cx.simulate_keystroke("~");
         cx.assert_state("aSSˇcdˇE\n", Mode::Normal);
     }
+
+    #[gpui::test]
+    async fn test_case_operators(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state("heˇllo World", Mode::Normal);
+        cx.simulate_keystrokes(["g", "shift-u", "i", "w"]);
+        cx.assert_state("ˇHELLO World", Mode::Normal);
+        cx.simulate_keystrokes(["w", "g", "u", "e"]);
+        cx.assert_state("HELLO ˇworld", Mode::Normal);
+        cx.simulate_keystrokes(["g", "~", "b"]);
+        cx.assert_state("ˇhello world", Mode::Normal);
+        cx.simulate_keystrokes(["g", "?", "$"]);
+        cx.assert_state("ˇuryyb jbeyq", Mode::Normal);
+        cx.simulate_keystrokes(["."]);
+        cx.assert_state("ˇhello world", Mode::Normal);
+    }
+
+    #[gpui::test]
+    async fn test_case_operators_linewise(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state(
+            indoc! {"
+                one
+                tˇwo
+                three"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["2", "g", "shift-u", "shift-u"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                TˇWO
+                THREE"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["g", "~", "~"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                tˇwo
+                THREE"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["g", "shift-u", "k"]);
+        cx.assert_state(
+            indoc! {"
+                OˇNE
+                TWO
+                THREE"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["j", "g", "u", "g", "u", "j", "."]);
+        cx.assert_state(
+            indoc! {"
+                ONE
+                two
+                tˇhree"},
+            Mode::Normal,
+        );
+    }
 }
@@ -24,8 +24,9 @@ pub fn change_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &m
             | Motion::Backspace
             | Motion::StartOfLine { .. }
     );
-    vim.update_active_editor(cx, |editor, cx| {
+    let content = vim.update_active_editor(cx, |editor, cx| {
         let text_layout_details = editor.text_layout_details(cx);
+        let mut content = None;
         editor.transact(cx, |editor, cx| {
             // We are swapping to insert mode anyway. Just set the line end clipping behavior now
             editor.set_clip_at_line_ends(false, cx);
@@ -45,10 +46,14 @@ pub fn change_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &m
                     };
                 });
             });
-            copy_selections_content(editor, motion.linewise(), cx);
+            content = Some(copy_selections_content(editor, motion.linewise(), cx));
             editor.insert("", cx);
         });
+        content
     });
+    if let Some(content) = content.flatten() {
+        vim.write_register(content, false, cx);
+    }
 
     if motion_succeeded {
         vim.switch_mode(Mode::Insert, false, cx)
@@ -59,9 +64,10 @@ pub fn change_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &m
 
 pub fn change_object(vim: &mut Vim, object: Object, around: bool, cx: &mut WindowContext) {
     let mut objects_found = false;
-    vim.update_active_editor(cx, |editor, cx| {
+    let content = vim.update_active_editor(cx, |editor, cx| {
         // We are swapping to insert mode anyway. Just set the line end clipping behavior now
         editor.set_clip_at_line_ends(false, cx);
+        let mut content = None;
         editor.transact(cx, |editor, cx| {
             editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
                 s.move_with(|map, selection| {
@@ -69,11 +75,15 @@ pub fn change_object(vim: &mut Vim, object: Object, around: bool, cx: &mut Windo
                 });
             });
             if objects_found {
-                copy_selections_content(editor, false, cx);
+                content = Some(copy_selections_content(editor, false, cx));
                 editor.insert("", cx);
             }
         });
+        content
     });
+    if let Some(content) = content.flatten() {
+        vim.write_register(content, false, cx);
+    }
 
     if objects_found {
         vim.switch_mode(Mode::Insert, false, cx);
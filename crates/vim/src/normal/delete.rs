@@ -6,8 +6,9 @@ use language::Point;
 
 pub fn delete_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &mut WindowContext) {
     vim.stop_recording();
-    vim.update_active_editor(cx, |editor, cx| {
+    let content = vim.update_active_editor(cx, |editor, cx| {
         let text_layout_details = editor.text_layout_details(cx);
+        let mut content = None;
         editor.transact(cx, |editor, cx| {
             editor.set_clip_at_line_ends(false, cx);
             let mut original_columns: HashMap<_, _> = Default::default();
@@ -39,7 +40,7 @@ pub fn delete_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &m
                     }
                 });
             });
-            copy_selections_content(editor, motion.linewise(), cx);
+            content = Some(copy_selections_content(editor, motion.linewise(), cx));
             editor.insert("", cx);
 
             // Fixup cursor position after the deletion
@@ -57,12 +58,17 @@ pub fn delete_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &m
                 });
             });
         });
+        content
     });
+    if let Some(content) = content.flatten() {
+        vim.write_register(content, false, cx);
+    }
 }
 
 pub fn delete_object(vim: &mut Vim, object: Object, around: bool, cx: &mut WindowContext) {
     vim.stop_recording();
-    vim.update_active_editor(cx, |editor, cx| {
+    let content = vim.update_active_editor(cx, |editor, cx| {
+        let mut content = None;
         editor.transact(cx, |editor, cx| {
             editor.set_clip_at_line_ends(false, cx);
             // Emulates behavior in vim where if we expanded backwards to include a newline
@@ -98,7 +104,7 @@ pub fn delete_object(vim: &mut Vim, object: Object, around: bool, cx: &mut Windo
                     }
                 });
             });
-            copy_selections_content(editor, false, cx);
+            content = Some(copy_selections_content(editor, false, cx));
             editor.insert("", cx);
 
             // Fixup cursor position after the deletion
@@ -114,7 +120,11 @@ pub fn delete_object(vim: &mut Vim, object: Object, around: bool, cx: &mut Windo
                 });
             });
         });
+        content
     });
+    if let Some(content) = content.flatten() {
+        vim.write_register(content, false, cx);
+    }
 }
 
 #[cfg(test)]
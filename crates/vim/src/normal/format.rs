@@ -0,0 +1,326 @@
+use std::ops::Range;
+
+use collections::HashMap;
+use editor::{
+    display_map::ToDisplayPoint, scroll::Autoscroll, Anchor, Editor, MultiBufferSnapshot,
+};
+use gpui::{AppContext, ViewContext, WindowContext};
+use language::{Point, SelectionGoal};
+
+use crate::{
+    motion::{first_non_whitespace, Motion},
+    object::Object,
+    Vim,
+};
+
+pub fn format_motion(
+    vim: &mut Vim,
+    motion: Motion,
+    times: Option<usize>,
+    keep_cursor: bool,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        let text_layout_details = editor.text_layout_details(cx);
+        editor.transact(cx, |editor, cx| {
+            let mut original_positions: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = map
+                        .buffer_snapshot
+                        .anchor_after(selection.head().to_point(map));
+                    original_positions.insert(selection.id, anchor);
+                    motion.expand_selection(map, selection, times, false, &text_layout_details);
+                });
+            });
+            format_selections(editor, original_positions, keep_cursor, cx);
+        });
+    });
+}
+
+pub fn format_object(
+    vim: &mut Vim,
+    object: Object,
+    around: bool,
+    keep_cursor: bool,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        editor.transact(cx, |editor, cx| {
+            let mut original_positions: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = map
+                        .buffer_snapshot
+                        .anchor_after(selection.head().to_point(map));
+                    original_positions.insert(selection.id, anchor);
+                    object.expand_selection(map, selection, around);
+                });
+            });
+            format_selections(editor, original_positions, keep_cursor, cx);
+        });
+    });
+}
+
+/// Rewraps the lines of each selection, then puts the cursor back where it was (`gw`) or on the
+/// first non-blank of the last formatted line (`gq`).
+fn format_selections(
+    editor: &mut Editor,
+    mut original_positions: HashMap<usize, Anchor>,
+    keep_cursor: bool,
+    cx: &mut ViewContext<Editor>,
+) {
+    let snapshot = editor.buffer().read(cx).snapshot(cx);
+    let mut edits = Vec::new();
+    let mut last_lines = HashMap::default();
+    let mut next_row = 0;
+    for selection in editor.selections.all::<Point>(cx) {
+        let mut end_row = selection.end.row;
+        if selection.end.column == 0 && end_row > selection.start.row {
+            end_row -= 1;
+        }
+        let start_row = selection.start.row.max(next_row);
+        let last_word_end = rewrap_rows(&snapshot, start_row..end_row + 1, &mut edits, cx);
+        let last_line = last_word_end.unwrap_or_else(|| snapshot.point_to_offset(selection.end));
+        last_lines.insert(selection.id, snapshot.anchor_before(last_line));
+        next_row = next_row.max(end_row + 1);
+    }
+
+    editor.edit(edits, cx);
+    editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+        s.move_with(|map, selection| {
+            let cursor = if keep_cursor {
+                original_positions
+                    .remove(&selection.id)
+                    .unwrap()
+                    .to_display_point(map)
+            } else {
+                let last_line = last_lines.remove(&selection.id).unwrap();
+                first_non_whitespace(map, false, last_line.to_display_point(map))
+            };
+            selection.collapse_to(cursor, SelectionGoal::None);
+        });
+    });
+}
+
+/// Rewraps each paragraph in the given rows to the preferred line length, by replacing the
+/// whitespace between its words so that the words themselves, and anchors in them, stay put.
+/// Continuation lines get the indentation and line comment prefix of the paragraph's first line.
+///
+/// Returns the offset of the end of the last word.
+fn rewrap_rows(
+    snapshot: &MultiBufferSnapshot,
+    rows: Range<u32>,
+    edits: &mut Vec<(Range<usize>, String)>,
+    cx: &AppContext,
+) -> Option<usize> {
+    let mut last_word_end = None;
+    let mut row = rows.start;
+    while row < rows.end {
+        let line_start = Point::new(row, 0);
+        let line = line_text(snapshot, row);
+        let indent_len = line.len() - line.trim_start().len();
+        let comment_prefix = snapshot.language_scope_at(line_start).and_then(|scope| {
+            scope
+                .line_comment_prefixes()?
+                .iter()
+                .find(|prefix| line[indent_len..].starts_with(prefix.trim_end()))
+                .cloned()
+        });
+        let prefix = format!(
+            "{}{}",
+            &line[..indent_len],
+            comment_prefix.as_deref().unwrap_or_default()
+        );
+        let settings = snapshot.settings_at(line_start, cx);
+        let tab_size = settings.tab_size.get() as usize;
+        let max_column = settings.preferred_line_length as usize;
+
+        let mut words = Vec::new();
+        let mut first_word_column = 0;
+        while row < rows.end {
+            let line = line_text(snapshot, row);
+            let mut content = line.trim_start();
+            if let Some(comment_prefix) = comment_prefix.as_deref() {
+                let Some(rest) = content.strip_prefix(comment_prefix.trim_end()) else {
+                    break;
+                };
+                content = rest;
+            }
+            // Blank lines, and lines holding nothing but a comment prefix, separate paragraphs.
+            if content.trim().is_empty() {
+                if words.is_empty() {
+                    row += 1;
+                }
+                break;
+            }
+
+            let content_column = line.len() - content.len();
+            let content_start = snapshot.point_to_offset(Point::new(row, 0)) + content_column;
+            if words.is_empty() {
+                let first_word = content_column + content.len() - content.trim_start().len();
+                first_word_column = column_width(&line[..first_word], tab_size);
+            }
+            words.extend(
+                word_ranges(content)
+                    .map(|range| content_start + range.start..content_start + range.end),
+            );
+            row += 1;
+        }
+
+        let Some(first_word) = words.first() else {
+            continue;
+        };
+        let prefix_width = column_width(&prefix, tab_size);
+        let mut column = first_word_column + word_width(snapshot, first_word);
+        for pair in words.windows(2) {
+            let (previous, word) = (&pair[0], &pair[1]);
+            let width = word_width(snapshot, word);
+            let separator = if column + 1 + width <= max_column {
+                column += 1 + width;
+                " ".to_string()
+            } else {
+                column = prefix_width + width;
+                format!("\n{prefix}")
+            };
+            let gap = previous.end..word.start;
+            if snapshot.text_for_range(gap.clone()).collect::<String>() != separator {
+                edits.push((gap, separator));
+            }
+        }
+        last_word_end = words.last().map(|word| word.end);
+    }
+    last_word_end
+}
+
+fn line_text(snapshot: &MultiBufferSnapshot, row: u32) -> String {
+    snapshot
+        .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row)))
+        .collect()
+}
+
+fn word_ranges(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut offset = 0;
+    text.split_whitespace().map(move |word| {
+        let start = offset + text[offset..].find(word).unwrap();
+        offset = start + word.len();
+        start..offset
+    })
+}
+
+fn word_width(snapshot: &MultiBufferSnapshot, word: &Range<usize>) -> usize {
+    snapshot
+        .text_for_range(word.clone())
+        .flat_map(str::chars)
+        .count()
+}
+
+fn column_width(text: &str, tab_size: usize) -> usize {
+    text.chars().fold(0, |column, c| {
+        if c == '\t' {
+            column + tab_size - column % tab_size
+        } else {
+            column + 1
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use gpui::TestAppContext;
+    use indoc::indoc;
+    use language::language_settings::AllLanguageSettings;
+    use settings::SettingsStore;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    async fn format_test_context(cx: &mut TestAppContext) -> VimTestContext {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.update(|cx| {
+            cx.update_global(|store: &mut SettingsStore, cx| {
+                store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                    settings.defaults.preferred_line_length = Some(12);
+                });
+            })
+        });
+        cx
+    }
+
+    #[gpui::test]
+    async fn test_format_operator(cx: &mut TestAppContext) {
+        let mut cx = format_test_context(cx).await;
+
+        cx.set_state(
+            indoc! {"
+                one ˇtwo three four
+                five
+                six
+
+                seven"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["g", "q", "j"]);
+        cx.assert_state(
+            indoc! {"
+                one two
+                three four
+                ˇfive
+                six
+
+                seven"},
+            Mode::Normal,
+        );
+
+        // Paragraphs are wrapped separately, and gw keeps the cursor.
+        cx.simulate_keystrokes(["g", "w", "shift-g"]);
+        cx.assert_state(
+            indoc! {"
+                one two
+                three four
+                ˇfive six
+
+                seven"},
+            Mode::Normal,
+        );
+
+        // Continuation lines keep the indentation of the first line.
+        cx.set_state("  ˇa b c d e f g h", Mode::Normal);
+        cx.simulate_keystrokes(["g", "q", "q"]);
+        cx.assert_state("  a b c d e\n  ˇf g h", Mode::Normal);
+    }
+
+    #[gpui::test]
+    async fn test_format_operator_repeat(cx: &mut TestAppContext) {
+        let mut cx = format_test_context(cx).await;
+
+        cx.set_state("one ˇtwo three four", Mode::Normal);
+        cx.simulate_keystrokes(["g", "w", "g", "w"]);
+        cx.assert_state("one ˇtwo\nthree four", Mode::Normal);
+
+        cx.set_state(
+            indoc! {"
+                ˇa b
+                c d
+                e f
+                g h"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["2", "g", "q", "q"]);
+        cx.assert_state(
+            indoc! {"
+                ˇa b c d
+                e f
+                g h"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["j", "."]);
+        cx.assert_state(
+            indoc! {"
+                a b c d
+                ˇe f g h"},
+            Mode::Normal,
+        );
+    }
+}
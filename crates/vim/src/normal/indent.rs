@@ -0,0 +1,217 @@
+use collections::HashMap;
+use editor::{display_map::ToDisplayPoint, Editor};
+use gpui::{ViewContext, WindowContext};
+use language::SelectionGoal;
+
+use crate::{motion::Motion, object::Object, Vim};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IndentDirection {
+    In,
+    Out,
+    Auto,
+}
+
+pub fn indent_motion(
+    vim: &mut Vim,
+    motion: Motion,
+    times: Option<usize>,
+    direction: IndentDirection,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        let text_layout_details = editor.text_layout_details(cx);
+        editor.transact(cx, |editor, cx| {
+            let mut original_positions: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = map
+                        .buffer_snapshot
+                        .anchor_after(selection.head().to_point(map));
+                    original_positions.insert(selection.id, anchor);
+                    motion.expand_selection(map, selection, times, false, &text_layout_details);
+                });
+            });
+            indent_selections(editor, direction, cx);
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = original_positions.remove(&selection.id).unwrap();
+                    selection.collapse_to(anchor.to_display_point(map), SelectionGoal::None);
+                });
+            });
+        });
+    });
+}
+
+pub fn indent_object(
+    vim: &mut Vim,
+    object: Object,
+    around: bool,
+    direction: IndentDirection,
+    cx: &mut WindowContext,
+) {
+    vim.stop_recording();
+    vim.update_active_editor(cx, |editor, cx| {
+        editor.transact(cx, |editor, cx| {
+            let mut original_positions: HashMap<_, _> = Default::default();
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = map
+                        .buffer_snapshot
+                        .anchor_after(selection.head().to_point(map));
+                    original_positions.insert(selection.id, anchor);
+                    object.expand_selection(map, selection, around);
+                });
+            });
+            indent_selections(editor, direction, cx);
+            editor.change_selections(None, cx, |s| {
+                s.move_with(|map, selection| {
+                    let anchor = original_positions.remove(&selection.id).unwrap();
+                    selection.collapse_to(anchor.to_display_point(map), SelectionGoal::None);
+                });
+            });
+        });
+    });
+}
+
+fn indent_selections(
+    editor: &mut Editor,
+    direction: IndentDirection,
+    cx: &mut ViewContext<Editor>,
+) {
+    match direction {
+        IndentDirection::In => editor.indent(&Default::default(), cx),
+        IndentDirection::Out => editor.outdent(&Default::default(), cx),
+        IndentDirection::Auto => {
+            let ranges = editor
+                .selections
+                .all::<usize>(cx)
+                .into_iter()
+                .map(|selection| selection.range())
+                .collect::<Vec<_>>();
+            editor
+                .buffer()
+                .update(cx, |buffer, cx| buffer.autoindent_ranges(ranges, cx));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    #[gpui::test]
+    async fn test_indent_operators(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state(
+            indoc! {"
+                oˇne
+                two
+                three
+                four"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes([">", "j"]);
+        cx.assert_state(
+            indoc! {"
+                    oˇne
+                    two
+                three
+                four"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["3", ">", ">"]);
+        cx.assert_state(
+            indoc! {"
+                        oˇne
+                        two
+                    three
+                four"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["<", "shift-g"]);
+        cx.assert_state(
+            indoc! {"
+                    oˇne
+                    two
+                three
+                four"},
+            Mode::Normal,
+        );
+
+        // Dot repeats the operator with its motion.
+        cx.simulate_keystrokes(["j", "."]);
+        cx.assert_state(
+            indoc! {"
+                    one
+                tˇwo
+                three
+                four"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_autoindent_operator(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state(
+            indoc! {"
+                fn a() {
+                    b();
+                        if c {
+                ˇd();
+                  }
+                }"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["=", "k"]);
+        cx.assert_state(
+            indoc! {"
+                fn a() {
+                    b();
+                    if c {
+                        ˇd();
+                  }
+                }"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["j", "=", "="]);
+        cx.assert_state(
+            indoc! {"
+                fn a() {
+                    b();
+                    if c {
+                        d();
+                    ˇ}
+                }"},
+            Mode::Normal,
+        );
+
+        cx.set_state(
+            indoc! {"
+                fn a() {
+                b();
+                        if c {
+                d();
+                  }
+                ˇ}"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["g", "g", "=", "shift-g"]);
+        cx.assert_state(
+            indoc! {"
+                ˇfn a() {
+                    b();
+                    if c {
+                        d();
+                    }
+                }"},
+            Mode::Normal,
+        );
+    }
+}
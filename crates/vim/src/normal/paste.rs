@@ -28,12 +28,14 @@ pub(crate) fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>
 fn paste(_: &mut Workspace, action: &Paste, cx: &mut ViewContext<Workspace>) {
     Vim::update(cx, |vim, cx| {
         vim.record_current_action(cx);
-        vim.update_active_editor(cx, |editor, cx| {
+        let item = vim.read_register(cx);
+        let replaced = vim.update_active_editor(cx, |editor, cx| {
             let text_layout_details = editor.text_layout_details(cx);
+            let mut replaced = None;
             editor.transact(cx, |editor, cx| {
                 editor.set_clip_at_line_ends(false, cx);
 
-                let Some(item) = cx.read_from_clipboard() else {
+                let Some(item) = item else {
                     return;
                 };
                 let clipboard_text = Cow::Borrowed(item.text());
@@ -42,7 +44,11 @@ fn paste(_: &mut Workspace, action: &Paste, cx: &mut ViewContext<Workspace>) {
                 }
 
                 if !action.preserve_clipboard && vim.state().mode.is_visual() {
-                    copy_selections_content(editor, vim.state().mode == Mode::VisualLine, cx);
+                    replaced = Some(copy_selections_content(
+                        editor,
+                        vim.state().mode == Mode::VisualLine,
+                        cx,
+                    ));
                 }
 
                 // if we are copying from multi-cursor (of visual block mode), we want
@@ -191,7 +197,11 @@ fn paste(_: &mut Workspace, action: &Paste, cx: &mut ViewContext<Workspace>) {
                     });
                 })
             });
+            replaced
         });
+        if let Some(replaced) = replaced.flatten() {
+            vim.write_register(replaced, false, cx);
+        }
         vim.switch_mode(Mode::Normal, true, cx);
     });
 }
@@ -1,14 +1,22 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use crate::{
     insert::NormalBefore,
     motion::Motion,
-    state::{Mode, RecordedSelection, ReplayableAction},
+    replace::ToggleReplace,
+    state::{Mode, Operator, RecordedSelection, ReplayableAction},
     visual::visual_motion,
     Vim,
 };
-use gpui::{actions, Action, ViewContext, WindowContext};
+use editor::Editor;
+use futures::channel::oneshot;
+use gpui::{actions, Action, View, ViewContext, WindowContext};
 use workspace::Workspace;
 
-actions!(vim, [Repeat, EndRepeat]);
+actions!(vim, [Repeat, EndRepeat, ToggleRecord]);
+
+// Guards against macros that replay themselves forever.
+const MAX_REPLAYED_ACTIONS: usize = 10000;
 
 fn should_replay(action: &Box<dyn Action>) -> bool {
     // skip so that we don't leave the character palette open
@@ -31,6 +39,8 @@ fn repeatable_insert(action: &ReplayableAction) -> Option<Box<dyn Action>> {
                 || super::InsertLineBelow.partial_eq(&**action)
             {
                 Some(super::InsertLineBelow.boxed_clone())
+            } else if ToggleReplace.partial_eq(&**action) {
+                Some(ToggleReplace.boxed_clone())
             } else {
                 None
             }
@@ -48,18 +58,71 @@ pub(crate) fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>
     });
 
     workspace.register_action(|_: &mut Workspace, _: &Repeat, cx| repeat(cx, false));
+
+    workspace.register_action(|_: &mut Workspace, _: &ToggleRecord, cx| {
+        Vim::update(cx, |vim, cx| {
+            if vim.workspace_state.recording_register.take().is_some() {
+                vim.clear_operator(cx);
+            } else {
+                vim.push_operator(Operator::RecordRegister, cx);
+            }
+        })
+    });
+}
+
+pub(crate) fn record_register(text: Arc<str>, cx: &mut WindowContext) {
+    let register = text.chars().next().unwrap();
+    Vim::update(cx, |vim, cx| {
+        vim.clear_operator(cx);
+        if !register.is_ascii_alphanumeric() {
+            return;
+        }
+        let recorded = vim
+            .workspace_state
+            .recorded_registers
+            .entry(register.to_ascii_lowercase())
+            .or_default();
+        // Recording into an uppercase register appends to the lowercase one.
+        if !register.is_ascii_uppercase() {
+            recorded.clear();
+        }
+        vim.workspace_state.recording_register = Some(register.to_ascii_lowercase());
+    });
+}
+
+pub(crate) fn replay_register(text: Arc<str>, cx: &mut WindowContext) {
+    let register = text.chars().next().unwrap();
+    let Some(actions) = Vim::update(cx, |vim, cx| {
+        let count = vim.take_count(cx).unwrap_or(1);
+        vim.clear_operator(cx);
+        let register = if register == '@' {
+            vim.workspace_state.last_replayed_register?
+        } else {
+            register.to_ascii_lowercase()
+        };
+        vim.workspace_state.last_replayed_register = Some(register);
+        let actions = vim.workspace_state.recorded_registers.get(&register)?;
+        Some(
+            (0..count)
+                .flat_map(|_| actions.iter().cloned())
+                .collect::<Vec<_>>(),
+        )
+    }) else {
+        return;
+    };
+    replay(actions, cx);
 }
 
 pub(crate) fn repeat(cx: &mut WindowContext, from_insert_mode: bool) {
-    let Some((mut actions, editor, selection)) = Vim::update(cx, |vim, cx| {
+    let Some((mut actions, selection)) = Vim::update(cx, |vim, cx| {
         let actions = vim.workspace_state.recorded_actions.clone();
         if actions.is_empty() {
             return None;
         }
 
-        let Some(editor) = vim.active_editor.clone() else {
+        if vim.active_editor.is_none() {
             return None;
-        };
+        }
         let count = vim.take_count(cx);
 
         let selection = vim.workspace_state.recorded_selection.clone();
@@ -83,7 +146,7 @@ pub(crate) fn repeat(cx: &mut WindowContext, from_insert_mode: bool) {
             }
         }
 
-        Some((actions, editor, selection))
+        Some((actions, selection))
     }) else {
         return;
     };
@@ -166,32 +229,104 @@ pub(crate) fn repeat(cx: &mut WindowContext, from_insert_mode: bool) {
     }
 
     Vim::update(cx, |vim, _| vim.workspace_state.replaying = true);
-    let window = cx.window_handle();
+    actions.push(ReplayableAction::Action(EndRepeat.boxed_clone()));
+    replay(actions, cx);
+}
+
+impl Vim {
+    /// Returns a receiver that resolves once the actions being replayed have all run, or
+    /// straight away if nothing is being replayed.
+    pub(crate) fn replay_finished(&mut self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        if self.workspace_state.replay_queue.is_some() {
+            self.replay_finished.push(tx);
+        } else {
+            tx.send(()).ok();
+        }
+        rx
+    }
+}
+
+/// Replays actions one at a time, as though they had been typed. Actions replayed while others
+/// are being replayed (such as by `.` or `@a` within a macro) run before the remaining ones.
+fn replay(actions: Vec<ReplayableAction>, cx: &mut WindowContext) {
+    let already_replaying = Vim::update(cx, |vim, _| {
+        if let Some(queue) = vim.workspace_state.replay_queue.as_mut() {
+            for action in actions.into_iter().rev() {
+                queue.push_front(action);
+            }
+            true
+        } else {
+            vim.workspace_state.replay_queue = Some(VecDeque::from(actions));
+            false
+        }
+    });
+    if already_replaying {
+        return;
+    }
+
     cx.spawn(move |mut cx| async move {
-        editor.update(&mut cx, |editor, _| {
-            editor.show_local_selections = false;
-        })?;
-        for action in actions {
-            match action {
-                ReplayableAction::Action(action) => {
-                    if should_replay(&action) {
-                        window.update(&mut cx, |_, cx| cx.dispatch_action(action))
-                    } else {
-                        Ok(())
+        let mut replayed = 0;
+        let mut hidden_selections: Option<View<Editor>> = None;
+        let result = async {
+            loop {
+                let Some((action, editor)) = cx.update(|cx| {
+                    Vim::update(cx, |vim, _| {
+                        let action = vim.workspace_state.replay_queue.as_mut()?.pop_front()?;
+                        Some((action, vim.active_editor.clone()?.upgrade()?))
+                    })
+                })?
+                else {
+                    break;
+                };
+                replayed += 1;
+                if replayed > MAX_REPLAYED_ACTIONS {
+                    log::error!("stopped replaying after {MAX_REPLAYED_ACTIONS} actions");
+                    break;
+                }
+
+                if hidden_selections.as_ref() != Some(&editor) {
+                    if let Some(previous) = hidden_selections.replace(editor.clone()) {
+                        previous
+                            .update(&mut cx, |editor, _| editor.show_local_selections = true)?;
                     }
+                    editor.update(&mut cx, |editor, _| editor.show_local_selections = false)?;
                 }
-                ReplayableAction::Insertion {
-                    text,
-                    utf16_range_to_replace,
-                } => editor.update(&mut cx, |editor, cx| {
-                    editor.replay_insert_event(&text, utf16_range_to_replace.clone(), cx)
-                }),
-            }?
+                match action {
+                    ReplayableAction::Action(action) => {
+                        if should_replay(&action) {
+                            cx.update(|cx| cx.dispatch_action(action.boxed_clone()))?;
+                            // Record the action once it has run, just as the keystroke observer
+                            // does, so that `.` can repeat changes made by a macro.
+                            cx.update(|cx| Vim::update(cx, |vim, _| vim.record_action(&*action)))?;
+                        }
+                    }
+                    ReplayableAction::Insertion {
+                        text,
+                        utf16_range_to_replace,
+                    } => editor.update(&mut cx, |editor, cx| {
+                        editor.replay_insert_event(&text, utf16_range_to_replace.clone(), cx)
+                    })?,
+                }
+            }
+            anyhow::Ok(())
         }
-        editor.update(&mut cx, |editor, _| {
-            editor.show_local_selections = true;
+        .await;
+
+        if let Some(editor) = hidden_selections {
+            editor
+                .update(&mut cx, |editor, _| editor.show_local_selections = true)
+                .ok();
+        }
+        cx.update(|cx| {
+            Vim::update(cx, |vim, _| {
+                vim.workspace_state.replay_queue = None;
+                for finished in vim.replay_finished.drain(..) {
+                    finished.send(()).ok();
+                }
+            })
         })?;
-        window.update(&mut cx, |_, cx| cx.dispatch_action(EndRepeat.boxed_clone()))
+        result
     })
     .detach_and_log_err(cx);
 }
@@ -506,4 +641,73 @@ mod test {
         cx.simulate_shared_keystrokes(["."]).await;
         cx.assert_shared_state("ˇx hello\n").await;
     }
+
+    #[gpui::test]
+    async fn test_record_and_replay_macro(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+
+        cx.set_state(
+            indoc! {"
+            ˇone
+            two
+            three
+            four
+            five
+            six"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["q", "a", "shift-a", "!", "escape", "j", "q"]);
+        cx.assert_state(
+            indoc! {"
+            one!
+            twˇo
+            three
+            four
+            five
+            six"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["@", "a"]);
+        cx.run_until_parked();
+        cx.simulate_keystrokes(["@", "@"]);
+        cx.run_until_parked();
+        cx.assert_state(
+            indoc! {"
+            one!
+            two!
+            three!
+            fouˇr
+            five
+            six"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["2", "@", "a"]);
+        cx.run_until_parked();
+        cx.assert_state(
+            indoc! {"
+            one!
+            two!
+            three!
+            four!
+            five!
+            siˇx"},
+            Mode::Normal,
+        );
+
+        // The last change made by the macro can be repeated.
+        cx.simulate_keystrokes(["."]);
+        cx.run_until_parked();
+        cx.assert_state(
+            indoc! {"
+            one!
+            two!
+            three!
+            four!
+            five!
+            sixˇ!"},
+            Mode::Normal,
+        );
+    }
 }
@@ -29,8 +29,9 @@ pub(crate) fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>
 }
 
 pub fn substitute(vim: &mut Vim, count: Option<usize>, line_mode: bool, cx: &mut WindowContext) {
-    vim.update_active_editor(cx, |editor, cx| {
+    let content = vim.update_active_editor(cx, |editor, cx| {
         editor.set_clip_at_line_ends(false, cx);
+        let mut content = None;
         editor.transact(cx, |editor, cx| {
             let text_layout_details = editor.text_layout_details(cx);
             editor.change_selections(None, cx, |s| {
@@ -72,12 +73,16 @@ pub fn substitute(vim: &mut Vim, count: Option<usize>, line_mode: bool, cx: &mut
                     }
                 })
             });
-            copy_selections_content(editor, line_mode, cx);
+            content = Some(copy_selections_content(editor, line_mode, cx));
             let selections = editor.selections.all::<Point>(cx).into_iter();
             let edits = selections.map(|selection| (selection.start..selection.end, ""));
             editor.edit(edits, cx);
         });
+        content
     });
+    if let Some(content) = content.flatten() {
+        vim.write_register(content, false, cx);
+    }
     vim.switch_mode(Mode::Insert, true, cx);
 }
 
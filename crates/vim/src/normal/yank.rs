@@ -3,8 +3,9 @@ use collections::HashMap;
 use gpui::WindowContext;
 
 pub fn yank_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &mut WindowContext) {
-    vim.update_active_editor(cx, |editor, cx| {
+    let yanked = vim.update_active_editor(cx, |editor, cx| {
         let text_layout_details = editor.text_layout_details(cx);
+        let mut yanked = None;
         editor.transact(cx, |editor, cx| {
             editor.set_clip_at_line_ends(false, cx);
             let mut original_positions: HashMap<_, _> = Default::default();
@@ -15,7 +16,9 @@ pub fn yank_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &mut
                     motion.expand_selection(map, selection, times, true, &text_layout_details);
                 });
             });
-            copy_selections_content(editor, motion.linewise(), cx);
+            let content = copy_selections_content(editor, motion.linewise(), cx);
+            let selection = editor.selections.newest_anchor();
+            yanked = Some((content, selection.start..selection.end));
             editor.change_selections(None, cx, |s| {
                 s.move_with(|_, selection| {
                     let (head, goal) = original_positions.remove(&selection.id).unwrap();
@@ -23,11 +26,17 @@ pub fn yank_motion(vim: &mut Vim, motion: Motion, times: Option<usize>, cx: &mut
                 });
             });
         });
+        yanked
     });
+    if let Some((content, range)) = yanked.flatten() {
+        vim.write_register(content, true, cx);
+        vim.set_range_marks('[', ']', range, cx);
+    }
 }
 
 pub fn yank_object(vim: &mut Vim, object: Object, around: bool, cx: &mut WindowContext) {
-    vim.update_active_editor(cx, |editor, cx| {
+    let yanked = vim.update_active_editor(cx, |editor, cx| {
+        let mut yanked = None;
         editor.transact(cx, |editor, cx| {
             editor.set_clip_at_line_ends(false, cx);
             let mut original_positions: HashMap<_, _> = Default::default();
@@ -38,7 +47,9 @@ pub fn yank_object(vim: &mut Vim, object: Object, around: bool, cx: &mut WindowC
                     original_positions.insert(selection.id, original_position);
                 });
             });
-            copy_selections_content(editor, false, cx);
+            let content = copy_selections_content(editor, false, cx);
+            let selection = editor.selections.newest_anchor();
+            yanked = Some((content, selection.start..selection.end));
             editor.change_selections(None, cx, |s| {
                 s.move_with(|_, selection| {
                     let (head, goal) = original_positions.remove(&selection.id).unwrap();
@@ -46,5 +57,10 @@ pub fn yank_object(vim: &mut Vim, object: Object, around: bool, cx: &mut WindowC
                 });
             });
         });
+        yanked
     });
+    if let Some((content, range)) = yanked.flatten() {
+        vim.write_register(content, true, cx);
+        vim.set_range_marks('[', ']', range, cx);
+    }
 }
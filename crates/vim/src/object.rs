@@ -88,8 +88,8 @@ fn object(object: Object, cx: &mut WindowContext) {
     match Vim::read(cx).state().mode {
         Mode::Normal => normal_object(object, cx),
         Mode::Visual | Mode::VisualLine | Mode::VisualBlock => visual_object(object, cx),
-        Mode::Insert => {
-            // Shouldn't execute a text object in insert mode. Ignoring
+        Mode::Insert | Mode::Replace => {
+            // Shouldn't execute a text object in insert or replace mode. Ignoring
         }
     }
 }
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use db::sqlez_macros::sql;
+use db::{define_connection, query};
+
+define_connection!(
+    // Current schema shape using pseudo-rust syntax:
+    // vim_global_marks(
+    //   mark: String,
+    //   path: PathBuf,
+    //   row: u32,
+    //   column: u32,
+    // )
+    pub static ref DB: VimDb<()> =
+        &[sql! (
+            CREATE TABLE vim_global_marks(
+                mark TEXT PRIMARY KEY,
+                path BLOB NOT NULL,
+                row INTEGER NOT NULL,
+                column INTEGER NOT NULL
+            ) STRICT;
+        )];
+);
+
+impl VimDb {
+    query! {
+        pub async fn get_global_marks() -> Result<Vec<(String, PathBuf, u32, u32)>> {
+            SELECT mark, path, row, column FROM vim_global_marks
+        }
+    }
+
+    query! {
+        pub async fn save_global_mark(mark: String, path: PathBuf, row: u32, column: u32) -> Result<()> {
+            INSERT OR REPLACE INTO vim_global_marks
+                (mark, path, row, column)
+            VALUES
+                (?1, ?2, ?3, ?4)
+        }
+    }
+}
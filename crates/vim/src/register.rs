@@ -0,0 +1,238 @@
+use std::{ops::Range, sync::Arc};
+
+use gpui::{ClipboardItem, WindowContext};
+
+use crate::{
+    state::{Mode, ReplayableAction},
+    Vim,
+};
+
+pub(crate) fn select_register(text: Arc<str>, cx: &mut WindowContext) {
+    let register = text.chars().next().unwrap();
+    Vim::update(cx, |vim, cx| {
+        vim.pop_operator(cx);
+        if register.is_ascii_alphanumeric()
+            || matches!(register, '"' | '-' | '_' | '+' | '*' | '.' | '%')
+        {
+            vim.workspace_state.selected_register = Some(register);
+        } else {
+            vim.clear_operator(cx);
+        }
+    });
+}
+
+impl Vim {
+    /// Writes yanked or deleted text to the register selected with `"`, or to the numbered
+    /// registers when none is. Unless it's the `_` register, the text also goes to the unnamed
+    /// register, which is the system clipboard.
+    pub(crate) fn write_register(
+        &mut self,
+        content: ClipboardItem,
+        yank: bool,
+        cx: &mut WindowContext,
+    ) {
+        let registers = &mut self.workspace_state.registers;
+        let content = match self.workspace_state.selected_register.take() {
+            Some('_') => return,
+            Some(register @ 'A'..='Z') => {
+                let register = register.to_ascii_lowercase();
+                let content = match registers.get(&register) {
+                    // Appending whole lines to a register holding part of a line puts them on
+                    // their own lines.
+                    Some(previous)
+                        if content.text().ends_with('\n') && !previous.text().ends_with('\n') =>
+                    {
+                        ClipboardItem::new(format!("{}\n{}", previous.text(), content.text()))
+                    }
+                    Some(previous) => ClipboardItem::new(previous.text().clone() + content.text()),
+                    None => content,
+                };
+                registers.insert(register, content.clone());
+                content
+            }
+            Some(register @ ('a'..='z' | '0'..='9' | '-')) => {
+                registers.insert(register, content.clone());
+                content
+            }
+            Some(_) => content,
+            None if yank => {
+                registers.insert('0', content.clone());
+                content
+            }
+            // Deletions of whole lines shift the numbered registers, while smaller ones go to `-`.
+            None if content.text().contains('\n') => {
+                for n in (1..9).rev() {
+                    if let Some(previous) = registers.remove(&digit(n)) {
+                        registers.insert(digit(n + 1), previous);
+                    }
+                }
+                registers.insert('1', content.clone());
+                content
+            }
+            None => {
+                registers.insert('-', content.clone());
+                content
+            }
+        };
+        cx.write_to_clipboard(content);
+    }
+
+    /// Reads the register selected with `"`, or the unnamed register if none is.
+    pub(crate) fn read_register(&mut self, cx: &mut WindowContext) -> Option<ClipboardItem> {
+        match self.workspace_state.selected_register.take() {
+            None | Some('"' | '+' | '*') => cx.read_from_clipboard(),
+            Some('_') => None,
+            Some('%') => self
+                .update_active_editor(cx, |editor, cx| {
+                    let buffer = editor.buffer().read(cx).as_singleton()?;
+                    let path = buffer.read(cx).file()?.path().to_string_lossy().to_string();
+                    Some(ClipboardItem::new(path))
+                })
+                .flatten(),
+            Some(register) => self
+                .workspace_state
+                .registers
+                .get(&register.to_ascii_lowercase())
+                .cloned(),
+        }
+    }
+
+    /// Records text input into the macro being recorded, and into the `.` register while in
+    /// insert or replace mode.
+    pub(crate) fn record_register_insertion(
+        text: &Arc<str>,
+        range_to_replace: Option<Range<isize>>,
+        cx: &mut WindowContext,
+    ) {
+        Vim::update(cx, |vim, _| {
+            // Replayed input was already recorded as the keystrokes that replayed it.
+            if vim.workspace_state.replay_queue.is_some() {
+                return;
+            }
+            if matches!(vim.state().mode, Mode::Insert | Mode::Replace) {
+                vim.workspace_state.inserted_text.push_str(text);
+            }
+            if let Some(register) = vim.workspace_state.recording_register {
+                vim.workspace_state
+                    .recorded_registers
+                    .entry(register)
+                    .or_default()
+                    .push(ReplayableAction::Insertion {
+                        text: text.clone(),
+                        utf16_range_to_replace: range_to_replace,
+                    });
+            }
+        });
+    }
+}
+
+fn digit(n: u32) -> char {
+    char::from_digit(n, 10).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use gpui::TestAppContext;
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    #[gpui::test]
+    async fn test_named_registers(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["\"", "a", "y", "y", "j", "\"", "b", "y", "w"]);
+        cx.simulate_keystrokes(["\"", "a", "p"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                two
+                ˇone"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["\"", "b", "shift-p"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                two
+                twˇoone"},
+            Mode::Normal,
+        );
+
+        // Uppercase registers append to the lowercase ones.
+        cx.simulate_keystrokes(["\"", "shift-b", "y", "y", "\"", "b", "p"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                two
+                twoone
+                ˇtwo
+                twoone"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_numbered_and_black_hole_registers(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state(
+            indoc! {"
+                ˇone
+                two
+                three"},
+            Mode::Normal,
+        );
+
+        cx.simulate_keystrokes(["y", "y", "d", "d", "d", "d", "x"]);
+        cx.assert_state("ˇhree", Mode::Normal);
+        cx.simulate_keystrokes(["\"", "2", "shift-p"]);
+        cx.assert_state(
+            indoc! {"
+                ˇone
+                hree"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["\"", "1", "shift-p", "\"", "-", "shift-p"]);
+        cx.assert_state(
+            indoc! {"
+                ˇttwo
+                one
+                hree"},
+            Mode::Normal,
+        );
+
+        // Deleting into the black hole register leaves the other registers alone.
+        cx.simulate_keystrokes(["\"", "_", "d", "d", "\"", "0", "p"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇone
+                hree"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["shift-p"]);
+        cx.assert_state(
+            indoc! {"
+                one
+                ˇtone
+                hree"},
+            Mode::Normal,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_read_only_registers(cx: &mut TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state("ˇone", Mode::Normal);
+
+        cx.simulate_keystrokes(["i", "t", "w", "o", " ", "escape"]);
+        cx.simulate_keystrokes(["\"", ".", "p"]);
+        cx.assert_state("two twoˇ one", Mode::Normal);
+    }
+}
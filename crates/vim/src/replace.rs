@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use editor::{scroll::Autoscroll, Bias, ToOffset, ToPoint};
+use gpui::{actions, ViewContext, WindowContext};
+use language::Point;
+use workspace::Workspace;
+
+use crate::{state::Mode, Vim};
+
+actions!(vim, [ToggleReplace, UndoReplace]);
+
+pub fn register(workspace: &mut Workspace, _: &mut ViewContext<Workspace>) {
+    workspace.register_action(|_: &mut Workspace, _: &ToggleReplace, cx| {
+        Vim::update(cx, |vim, cx| {
+            vim.start_recording(cx);
+            vim.update_state(|state| state.replacements.clear());
+            vim.switch_mode(Mode::Replace, false, cx);
+        })
+    });
+
+    workspace.register_action(|_: &mut Workspace, _: &UndoReplace, cx| {
+        Vim::update(cx, |vim, cx| {
+            if vim.state().mode == Mode::Replace {
+                undo_replace(vim, cx);
+            }
+        })
+    });
+}
+
+/// Overwrites the characters after each cursor with the typed text. Newlines, and text typed at
+/// the end of a line, are inserted rather than replacing anything.
+pub(crate) fn multi_replace(text: Arc<str>, cx: &mut WindowContext) {
+    Vim::update(cx, |vim, cx| {
+        let replacements = vim.update_active_editor(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let replaced_len = text.chars().take_while(|c| *c != '\n').count();
+            let edits = editor
+                .selections
+                .all::<usize>(cx)
+                .into_iter()
+                .map(|selection| {
+                    let start = selection.head();
+                    let end = snapshot
+                        .chars_at(start)
+                        .take(replaced_len)
+                        .take_while(|c| *c != '\n')
+                        .fold(start, |end, c| end + c.len_utf8());
+                    (
+                        start..end,
+                        snapshot.text_for_range(start..end).collect::<String>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let mut replacements = Vec::new();
+            editor.transact(cx, |editor, cx| {
+                editor.edit(
+                    edits.iter().map(|(range, _)| (range.clone(), text.clone())),
+                    cx,
+                );
+
+                let snapshot = editor.buffer().read(cx).snapshot(cx);
+                let mut delta = 0isize;
+                for (range, original) in edits {
+                    let start = (range.start as isize + delta) as usize;
+                    let end = start + text.len();
+                    delta += text.len() as isize - range.len() as isize;
+                    replacements.push((
+                        snapshot.anchor_after(start)..snapshot.anchor_before(end),
+                        original,
+                    ));
+                }
+                editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                    s.select_anchor_ranges(
+                        replacements
+                            .iter()
+                            .map(|(range, _)| range.end..range.end)
+                            .collect::<Vec<_>>(),
+                    )
+                });
+            });
+            replacements
+        });
+        if let Some(replacements) = replacements {
+            vim.update_state(|state| state.replacements.extend(replacements));
+        }
+    });
+}
+
+/// Restores the text replaced just before each cursor, or moves the cursor left if the text
+/// before it wasn't replaced.
+fn undo_replace(vim: &mut Vim, cx: &mut WindowContext) {
+    let mut replacements = vim.state().replacements.clone();
+    vim.update_active_editor(cx, |editor, cx| {
+        editor.transact(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let mut edits = Vec::new();
+            let mut cursors = Vec::new();
+            for selection in editor.selections.all::<usize>(cx) {
+                let head = selection.head();
+                let replacement = replacements
+                    .iter()
+                    .rposition(|(range, _)| range.end.to_offset(&snapshot) == head)
+                    .map(|ix| replacements.remove(ix));
+                if let Some((range, original)) = replacement {
+                    let start = range.start.to_offset(&snapshot);
+                    edits.push((start..head, original));
+                    cursors.push(snapshot.anchor_before(start));
+                } else {
+                    let head = head.to_point(&snapshot);
+                    let previous = if head.column > 0 {
+                        snapshot.clip_point(head - Point::new(0, 1), Bias::Left)
+                    } else {
+                        head
+                    };
+                    cursors.push(snapshot.anchor_before(previous));
+                }
+            }
+
+            editor.edit(edits, cx);
+            editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_anchor_ranges(cursors.into_iter().map(|cursor| cursor..cursor))
+            });
+        })
+    });
+    vim.update_state(|state| state.replacements = replacements);
+}
+
+#[cfg(test)]
+mod test {
+    use indoc::indoc;
+
+    use crate::{state::Mode, test::VimTestContext};
+
+    #[gpui::test]
+    async fn test_replace_mode(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state("ˇabcdef", Mode::Normal);
+
+        cx.simulate_keystrokes(["shift-r", "x", "y"]);
+        cx.assert_state("xyˇcdef", Mode::Replace);
+        cx.simulate_keystrokes(["escape"]);
+        cx.assert_state("xˇycdef", Mode::Normal);
+
+        // Text typed at the end of the line is inserted.
+        cx.set_state("abˇc\ndef", Mode::Normal);
+        cx.simulate_keystrokes(["shift-r", "x", "y", "z"]);
+        cx.assert_state("abxyzˇ\ndef", Mode::Replace);
+        cx.simulate_keystrokes(["enter", "w"]);
+        cx.assert_state("abxyz\nwˇ\ndef", Mode::Replace);
+    }
+
+    #[gpui::test]
+    async fn test_replace_mode_backspace(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state("aˇbc", Mode::Normal);
+
+        // Backspace restores the replaced text, then moves over the text before it.
+        cx.simulate_keystrokes(["shift-r", "x", "y", "z"]);
+        cx.assert_state("axyzˇ", Mode::Replace);
+        cx.simulate_keystrokes(["backspace", "backspace"]);
+        cx.assert_state("axˇc", Mode::Replace);
+        cx.simulate_keystrokes(["backspace", "backspace"]);
+        cx.assert_state("ˇabc", Mode::Replace);
+        cx.simulate_keystrokes(["backspace", "escape"]);
+        cx.assert_state("ˇabc", Mode::Normal);
+
+        // Works with multiple cursors (zed only).
+        cx.set_state(
+            indoc! {"
+                ˇone
+                ˇtwo"},
+            Mode::Normal,
+        );
+        cx.simulate_keystrokes(["shift-r", "a", "b", "backspace"]);
+        cx.assert_state(
+            indoc! {"
+                aˇne
+                aˇwo"},
+            Mode::Replace,
+        );
+    }
+
+    #[gpui::test]
+    async fn test_replace_mode_repeat(cx: &mut gpui::TestAppContext) {
+        let mut cx = VimTestContext::new(cx, true).await;
+        cx.set_state("ˇone two three", Mode::Normal);
+
+        cx.simulate_keystrokes(["shift-r", "x", "y", "escape", "w", "."]);
+        cx.assert_state("xye xˇy three", Mode::Normal);
+
+        cx.simulate_keystrokes(["w", "2", "shift-r", "a", "b", "escape"]);
+        cx.assert_state("xye xy abaˇbe", Mode::Normal);
+    }
+}
@@ -1,7 +1,8 @@
-use std::{ops::Range, sync::Arc};
+use std::{collections::VecDeque, ops::Range, path::PathBuf, sync::Arc};
 
-use gpui::{Action, KeyContext};
-use language::CursorShape;
+use collections::HashMap;
+use gpui::{Action, ClipboardItem, KeyContext, WeakModel};
+use language::{Anchor, Buffer, CursorShape, Point};
 use serde::{Deserialize, Serialize};
 use workspace::searchable::Direction;
 
@@ -11,6 +12,7 @@ use crate::motion::Motion;
 pub enum Mode {
     Normal,
     Insert,
+    Replace,
     Visual,
     VisualLine,
     VisualBlock,
@@ -19,7 +21,7 @@ pub enum Mode {
 impl Mode {
     pub fn is_visual(&self) -> bool {
         match self {
-            Mode::Normal | Mode::Insert => false,
+            Mode::Normal | Mode::Insert | Mode::Replace => false,
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => true,
         }
     }
@@ -37,9 +39,22 @@ pub enum Operator {
     Delete,
     Yank,
     Replace,
+    Indent,
+    Outdent,
+    AutoIndent,
+    Format { keep_cursor: bool },
+    Lowercase,
+    Uppercase,
+    OppositeCase,
+    Rot13,
     Object { around: bool },
     FindForward { before: bool },
     FindBackward { after: bool },
+    Mark,
+    Jump { line: bool },
+    Register,
+    RecordRegister,
+    ReplayRegister,
 }
 
 #[derive(Default, Clone)]
@@ -53,6 +68,9 @@ pub struct EditorState {
     pub post_count: Option<usize>,
 
     pub operator_stack: Vec<Operator>,
+
+    /// The text replaced in replace mode, so that backspace can restore it.
+    pub replacements: Vec<(Range<editor::Anchor>, String)>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -86,6 +104,45 @@ pub struct WorkspaceState {
     pub recorded_count: Option<usize>,
     pub recorded_actions: Vec<ReplayableAction>,
     pub recorded_selection: RecordedSelection,
+
+    /// The marks of each buffer, keyed by buffer id.
+    pub marks: HashMap<u64, BufferMarks>,
+    pub global_marks: HashMap<char, GlobalMark>,
+
+    /// The register selected with `"` for the next command.
+    pub selected_register: Option<char>,
+    /// The registers other than the unnamed one, which is the system clipboard.
+    pub registers: HashMap<char, ClipboardItem>,
+    /// The text inserted since entering insert mode, which goes to the `.` register.
+    pub inserted_text: String,
+
+    /// The register that a macro is being recorded into with `q`.
+    pub recording_register: Option<char>,
+    pub recorded_registers: HashMap<char, Vec<ReplayableAction>>,
+    pub last_replayed_register: Option<char>,
+    /// The actions that remain to be replayed, while a macro or a repeated action is replayed.
+    pub replay_queue: Option<VecDeque<ReplayableAction>>,
+
+    /// The last pattern used by an ex command, which an empty pattern such as `:s//x` reuses.
+    pub last_command_pattern: Option<String>,
+}
+
+/// The marks set in a buffer, which are dropped along with the buffer.
+#[derive(Clone)]
+pub struct BufferMarks {
+    pub buffer: WeakModel<Buffer>,
+    pub marks: HashMap<char, Anchor>,
+}
+
+/// A mark set with an uppercase letter, which can be jumped to from any buffer.
+#[derive(Clone)]
+pub struct GlobalMark {
+    /// The path of the file the mark is in, if it's in a file on disk.
+    pub abs_path: Option<PathBuf>,
+    /// The position of the mark in its buffer, for as long as the buffer is open.
+    pub anchor: Option<(WeakModel<Buffer>, Anchor)>,
+    /// The position of the mark when it was set, or loaded from a previous session.
+    pub point: Point,
 }
 
 #[derive(Debug)]
@@ -141,24 +198,33 @@ impl EditorState {
             }
             Mode::Visual | Mode::VisualLine | Mode::VisualBlock => CursorShape::Block,
             Mode::Insert => CursorShape::Bar,
+            Mode::Replace => CursorShape::Underscore,
         }
     }
 
     pub fn vim_controlled(&self) -> bool {
-        !matches!(self.mode, Mode::Insert)
+        !matches!(self.mode, Mode::Insert | Mode::Replace)
             || matches!(
                 self.operator_stack.last(),
                 Some(Operator::FindForward { .. }) | Some(Operator::FindBackward { .. })
             )
     }
 
+    /// Whether typed text goes straight into the editor, rather than to vim. In replace mode
+    /// vim handles the input so that it overwrites the existing text.
+    pub fn editor_input_enabled(&self) -> bool {
+        self.mode == Mode::Insert && !self.vim_controlled()
+    }
+
     pub fn should_autoindent(&self) -> bool {
         !(self.mode == Mode::Insert && self.last_mode == Mode::VisualBlock)
     }
 
     pub fn clip_at_line_ends(&self) -> bool {
         match self.mode {
-            Mode::Insert | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => false,
+            Mode::Insert | Mode::Replace | Mode::Visual | Mode::VisualLine | Mode::VisualBlock => {
+                false
+            }
             Mode::Normal => true,
         }
     }
@@ -176,6 +242,7 @@ impl EditorState {
                 Mode::Normal => "normal",
                 Mode::Visual | Mode::VisualLine | Mode::VisualBlock => "visual",
                 Mode::Insert => "insert",
+                Mode::Replace => "replace",
             },
         );
 
@@ -207,6 +274,8 @@ impl EditorState {
 }
 
 impl Operator {
+    /// The operator's `vim_operator` value in the keymap context. Operators typed as punctuation
+    /// are named instead, as the keymap only matches identifiers.
     pub fn id(&self) -> &'static str {
         match self {
             Operator::Object { around: false } => "i",
@@ -215,19 +284,39 @@ impl Operator {
             Operator::Delete => "d",
             Operator::Yank => "y",
             Operator::Replace => "r",
+            Operator::Indent => "indent",
+            Operator::Outdent => "outdent",
+            Operator::AutoIndent => "autoindent",
+            Operator::Format { keep_cursor: false } => "gq",
+            Operator::Format { keep_cursor: true } => "gw",
+            Operator::Lowercase => "gu",
+            Operator::Uppercase => "gU",
+            Operator::OppositeCase => "opposite_case",
+            Operator::Rot13 => "rot13",
             Operator::FindForward { before: false } => "f",
             Operator::FindForward { before: true } => "t",
             Operator::FindBackward { after: false } => "F",
             Operator::FindBackward { after: true } => "T",
+            Operator::Mark => "m",
+            Operator::Jump { line: true } => "'",
+            Operator::Jump { line: false } => "`",
+            Operator::Register => "\"",
+            Operator::RecordRegister => "q",
+            Operator::ReplayRegister => "@",
         }
     }
 
     pub fn context_flags(&self) -> &'static [&'static str] {
         match self {
             Operator::Object { .. } => &["VimObject"],
-            Operator::FindForward { .. } | Operator::FindBackward { .. } | Operator::Replace => {
-                &["VimWaiting"]
-            }
+            Operator::FindForward { .. }
+            | Operator::FindBackward { .. }
+            | Operator::Replace
+            | Operator::Mark
+            | Operator::Jump { .. }
+            | Operator::Register
+            | Operator::RecordRegister
+            | Operator::ReplayRegister => &["VimWaiting"],
             _ => &[],
         }
     }
@@ -2,7 +2,11 @@ use editor::{ClipboardSelection, Editor};
 use gpui::{AppContext, ClipboardItem};
 use language::{CharKind, Point};
 
-pub fn copy_selections_content(editor: &mut Editor, linewise: bool, cx: &mut AppContext) {
+pub fn copy_selections_content(
+    editor: &mut Editor,
+    linewise: bool,
+    cx: &mut AppContext,
+) -> ClipboardItem {
     let selections = editor.selections.all_adjusted(cx);
     let buffer = editor.buffer().read(cx).snapshot(cx);
     let mut text = String::new();
@@ -46,7 +50,7 @@ pub fn copy_selections_content(editor: &mut Editor, linewise: bool, cx: &mut App
         }
     }
 
-    cx.write_to_clipboard(ClipboardItem::new(text).with_metadata(clipboard_selections));
+    ClipboardItem::new(text).with_metadata(clipboard_selections)
 }
 
 pub fn coerce_punctuation(kind: CharKind, treat_punctuation_as_word: bool) -> CharKind {
@@ -6,10 +6,14 @@ mod test;
 mod command;
 mod editor_events;
 mod insert;
+mod mark;
 mod mode_indicator;
 mod motion;
 mod normal;
 mod object;
+mod persistence;
+mod register;
+mod replace;
 mod state;
 mod utils;
 mod visual;
@@ -19,13 +23,15 @@ use collections::{CommandPaletteFilter, HashMap};
 use command_palette::CommandPaletteInterceptor;
 use editor::{movement, Editor, EditorEvent, EditorMode};
 use gpui::{
-    actions, impl_actions, Action, AppContext, EntityId, KeyContext, Subscription, View,
-    ViewContext, WeakView, WindowContext,
+    actions, impl_actions, Action, AppContext, ClipboardItem, EntityId, KeyContext, Subscription,
+    View, ViewContext, WeakView, WindowContext,
 };
 use language::{CursorShape, Point, Selection, SelectionGoal};
 pub use mode_indicator::ModeIndicator;
 use motion::Motion;
-use normal::normal_replace;
+use normal::{normal_replace, repeat};
+use persistence::DB;
+use replace::multi_replace;
 use serde::Deserialize;
 use settings::{update_settings_file, Settings, SettingsStore};
 use state::{EditorState, Mode, Operator, RecordedSelection, WorkspaceState};
@@ -90,6 +96,12 @@ pub fn init(cx: &mut AppContext) {
         });
     })
     .detach();
+
+    cx.spawn(|mut cx| async move {
+        let marks = DB.get_global_marks().await?;
+        cx.update_global(|vim: &mut Vim, _| vim.load_global_marks(marks))
+    })
+    .detach_and_log_err(cx);
 }
 
 fn register(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
@@ -126,6 +138,7 @@ fn register(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
     motion::register(workspace, cx);
     command::register(workspace, cx);
     object::register(workspace, cx);
+    replace::register(workspace, cx);
     visual::register(workspace, cx);
 }
 
@@ -138,15 +151,13 @@ pub fn observe_keystrokes(cx: &mut WindowContext) {
             .map(|action| action.boxed_clone())
         {
             Vim::update(cx, |vim, _| {
-                if vim.workspace_state.recording {
+                vim.record_action(&*action);
+                if let Some(register) = vim.workspace_state.recording_register {
                     vim.workspace_state
-                        .recorded_actions
+                        .recorded_registers
+                        .entry(register)
+                        .or_default()
                         .push(ReplayableAction::Action(action.boxed_clone()));
-
-                    if vim.workspace_state.stop_recording_after_next_action {
-                        vim.workspace_state.recording = false;
-                        vim.workspace_state.stop_recording_after_next_action = false;
-                    }
                 }
             });
 
@@ -160,7 +171,14 @@ pub fn observe_keystrokes(cx: &mut WindowContext) {
 
         Vim::update(cx, |vim, cx| match vim.active_operator() {
             Some(
-                Operator::FindForward { .. } | Operator::FindBackward { .. } | Operator::Replace,
+                Operator::FindForward { .. }
+                | Operator::FindBackward { .. }
+                | Operator::Replace
+                | Operator::Mark
+                | Operator::Jump { .. }
+                | Operator::Register
+                | Operator::RecordRegister
+                | Operator::ReplayRegister,
             ) => {}
             Some(_) => {
                 vim.clear_operator(cx);
@@ -180,6 +198,8 @@ pub struct Vim {
     editor_states: HashMap<EntityId, EditorState>,
     workspace_state: WorkspaceState,
     default_state: EditorState,
+    /// Notified once the actions being replayed have all run.
+    replay_finished: Vec<oneshot::Sender<()>>,
 }
 
 impl Vim {
@@ -204,14 +224,23 @@ impl Vim {
                     local_selections_changed(newest, cx);
                 }
             }
+            EditorEvent::Edited => Vim::update(cx, |vim, cx| {
+                if vim.enabled {
+                    vim.set_change_marks(&editor, cx)
+                }
+            }),
             EditorEvent::InputIgnored { text } => {
+                Vim::record_register_insertion(text, None, cx);
                 Vim::active_editor_input_ignored(text.clone(), cx);
                 Vim::record_insertion(text, None, cx)
             }
             EditorEvent::InputHandled {
                 text,
                 utf16_range_to_replace: range_to_replace,
-            } => Vim::record_insertion(text, range_to_replace.clone(), cx),
+            } => {
+                Vim::record_register_insertion(text, range_to_replace.clone(), cx);
+                Vim::record_insertion(text, range_to_replace.clone(), cx)
+            }
             _ => {}
         }));
 
@@ -254,6 +283,21 @@ impl Vim {
         });
     }
 
+    /// Records an action that was just dispatched, if an action is being recorded for `.` to
+    /// repeat it.
+    fn record_action(&mut self, action: &dyn Action) {
+        if self.workspace_state.recording {
+            self.workspace_state
+                .recorded_actions
+                .push(ReplayableAction::Action(action.boxed_clone()));
+
+            if self.workspace_state.stop_recording_after_next_action {
+                self.workspace_state.recording = false;
+                self.workspace_state.stop_recording_after_next_action = false;
+            }
+        }
+    }
+
     fn update_active_editor<S>(
         &self,
         cx: &mut WindowContext,
@@ -347,9 +391,15 @@ impl Vim {
             state.mode = mode;
             state.operator_stack.clear();
         });
-        if mode != Mode::Insert {
+        if !matches!(mode, Mode::Insert | Mode::Replace) {
             self.take_count(cx);
         }
+        if matches!(last_mode, Mode::Insert | Mode::Replace) && mode != last_mode {
+            let inserted_text = std::mem::take(&mut self.workspace_state.inserted_text);
+            self.workspace_state
+                .registers
+                .insert('.', ClipboardItem::new(inserted_text));
+        }
 
         // Sync editor settings like clip mode
         self.sync_vim_settings(cx);
@@ -358,6 +408,10 @@ impl Vim {
             return;
         }
 
+        if last_mode.is_visual() && !mode.is_visual() {
+            self.set_visual_marks(cx);
+        }
+
         // Adjust selections
         self.update_active_editor(cx, |editor, cx| {
             if last_mode != Mode::VisualBlock && last_mode.is_visual() && mode == Mode::VisualBlock
@@ -436,7 +490,17 @@ impl Vim {
     fn push_operator(&mut self, operator: Operator, cx: &mut WindowContext) {
         if matches!(
             operator,
-            Operator::Change | Operator::Delete | Operator::Replace
+            Operator::Change
+                | Operator::Delete
+                | Operator::Replace
+                | Operator::Indent
+                | Operator::Outdent
+                | Operator::AutoIndent
+                | Operator::Format { .. }
+                | Operator::Lowercase
+                | Operator::Uppercase
+                | Operator::OppositeCase
+                | Operator::Rot13
         ) {
             self.start_recording(cx)
         };
@@ -456,6 +520,7 @@ impl Vim {
     }
     fn clear_operator(&mut self, cx: &mut WindowContext) {
         self.take_count(cx);
+        self.workspace_state.selected_register = None;
         self.update_state(|state| state.operator_stack.clear());
         self.sync_vim_settings(cx);
     }
@@ -495,7 +560,16 @@ impl Vim {
                 Mode::Visual | Mode::VisualLine | Mode::VisualBlock => visual_replace(text, cx),
                 _ => Vim::update(cx, |vim, cx| vim.clear_operator(cx)),
             },
-            _ => {}
+            Some(Operator::Mark) => mark::create_mark(text, cx),
+            Some(Operator::Jump { line }) => mark::jump(text, line, cx),
+            Some(Operator::Register) => register::select_register(text, cx),
+            Some(Operator::RecordRegister) => repeat::record_register(text, cx),
+            Some(Operator::ReplayRegister) => repeat::replay_register(text, cx),
+            _ => {
+                if Vim::read(cx).state().mode == Mode::Replace {
+                    multi_replace(text, cx)
+                }
+            }
         }
     }
 
@@ -570,7 +644,7 @@ impl Vim {
                 editor.set_cursor_shape(cursor_shape, cx);
                 editor.set_clip_at_line_ends(state.clip_at_line_ends(), cx);
                 editor.set_collapse_matches(true);
-                editor.set_input_enabled(!state.vim_controlled());
+                editor.set_input_enabled(state.editor_input_enabled());
                 editor.set_autoindent(state.should_autoindent());
                 editor.selections.line_mode = matches!(state.mode, Mode::VisualLine);
                 let context_layer = state.keymap_context_layer();
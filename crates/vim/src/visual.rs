@@ -311,9 +311,10 @@ pub fn other_end(_: &mut Workspace, _: &OtherEnd, cx: &mut ViewContext<Workspace
 pub fn delete(_: &mut Workspace, _: &VisualDelete, cx: &mut ViewContext<Workspace>) {
     Vim::update(cx, |vim, cx| {
         vim.record_current_action(cx);
-        vim.update_active_editor(cx, |editor, cx| {
+        let content = vim.update_active_editor(cx, |editor, cx| {
             let mut original_columns: HashMap<_, _> = Default::default();
             let line_mode = editor.selections.line_mode;
+            let mut content = None;
 
             editor.transact(cx, |editor, cx| {
                 editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
@@ -328,7 +329,7 @@ pub fn delete(_: &mut Workspace, _: &VisualDelete, cx: &mut ViewContext<Workspac
                         selection.goal = SelectionGoal::None;
                     });
                 });
-                copy_selections_content(editor, line_mode, cx);
+                content = Some(copy_selections_content(editor, line_mode, cx));
                 editor.insert("", cx);
 
                 // Fixup cursor position after the deletion
@@ -347,17 +348,21 @@ pub fn delete(_: &mut Workspace, _: &VisualDelete, cx: &mut ViewContext<Workspac
                         s.select_anchors(vec![s.first_anchor()])
                     }
                 });
-            })
+            });
+            content
         });
+        if let Some(content) = content.flatten() {
+            vim.write_register(content, false, cx);
+        }
         vim.switch_mode(Mode::Normal, true, cx);
     });
 }
 
 pub fn yank(_: &mut Workspace, _: &VisualYank, cx: &mut ViewContext<Workspace>) {
     Vim::update(cx, |vim, cx| {
-        vim.update_active_editor(cx, |editor, cx| {
+        let content = vim.update_active_editor(cx, |editor, cx| {
             let line_mode = editor.selections.line_mode;
-            copy_selections_content(editor, line_mode, cx);
+            let content = copy_selections_content(editor, line_mode, cx);
             editor.change_selections(None, cx, |s| {
                 s.move_with(|map, selection| {
                     if line_mode {
@@ -369,7 +374,11 @@ pub fn yank(_: &mut Workspace, _: &VisualYank, cx: &mut ViewContext<Workspace>)
                     s.select_anchors(vec![s.first_anchor()])
                 }
             });
+            content
         });
+        if let Some(content) = content {
+            vim.write_register(content, true, cx);
+        }
         vim.switch_mode(Mode::Normal, true, cx);
     });
 }
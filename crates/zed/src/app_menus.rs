@@ -117,8 +117,10 @@ pub fn app_menus() -> Vec<Menu<'static>> {
                 }),
                 MenuItem::separator(),
                 MenuItem::action("Project Panel", project_panel::ToggleFocus),
+                MenuItem::action("Git Panel", git_panel::ToggleFocus),
                 MenuItem::action("Command Palette", command_palette::Toggle),
                 MenuItem::action("Diagnostics", diagnostics::Deploy),
+                MenuItem::action("Merge Conflicts", git_panel::ShowConflicts),
                 MenuItem::separator(),
             ],
         },
@@ -133,6 +135,7 @@ pub fn app_menus() -> Vec<Menu<'static>> {
                 MenuItem::action("Go to Symbol in Editor", outline::Toggle),
                 MenuItem::action("Go to Definition", editor::actions::GoToDefinition),
                 MenuItem::action("Go to Type Definition", editor::actions::GoToTypeDefinition),
+                MenuItem::action("Go to Implementation", editor::actions::GoToImplementation),
                 MenuItem::action("Find All References", editor::actions::FindAllReferences),
                 MenuItem::action("Go to Line/Column", go_to_line::Toggle),
                 MenuItem::separator(),
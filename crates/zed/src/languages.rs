@@ -285,6 +285,7 @@ fn load_queries(name: &str) -> LanguageQueries {
         embedding: load_query(name, "/embedding"),
         injections: load_query(name, "/injections"),
         overrides: load_query(name, "/overrides"),
+        linked_editing: load_query(name, "/linked_editing"),
     }
 }
 
@@ -0,0 +1,3 @@
+(_
+  (start_tag (tag_name) @open)
+  (end_tag (tag_name) @close))
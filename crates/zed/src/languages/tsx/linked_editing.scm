@@ -0,0 +1,3 @@
+(jsx_element
+  (jsx_opening_element name: (_) @open)
+  (jsx_closing_element name: (_) @close))
@@ -8,7 +8,7 @@ use cli::FORCE_CLI_MODE_ENV_VAR_NAME;
 use client::{Client, UserStore};
 use collab_ui::channel_view::ChannelView;
 use db::kvp::KEY_VALUE_STORE;
-use editor::Editor;
+use editor::{handle_snippet_file_changes, Editor};
 use fs::RealFs;
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncAppContext, Context, SemanticVersion, Task};
@@ -21,7 +21,8 @@ use node_runtime::RealNodeRuntime;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use settings::{
-    default_settings, handle_settings_file_changes, watch_config_file, Settings, SettingsStore,
+    default_settings, handle_settings_file_changes, watch_config_dir, watch_config_file, Settings,
+    SettingsStore,
 };
 use simplelog::ConfigBuilder;
 use smol::process::Command;
@@ -89,6 +90,11 @@ fn main() {
         fs.clone(),
         paths::KEYMAP.clone(),
     );
+    let user_snippet_files_rx = watch_config_dir(
+        &app.background_executor(),
+        fs.clone(),
+        paths::SNIPPETS_DIR.clone(),
+    );
 
     let login_shell_env_loaded = if stdout_is_a_pty() {
         Task::ready(())
@@ -132,6 +138,7 @@ fn main() {
         cx.set_global(store);
         handle_settings_file_changes(user_settings_file_rx, cx);
         handle_keymap_file_changes(user_keymap_file_rx, cx);
+        handle_snippet_file_changes(user_snippet_files_rx, cx);
 
         let client = client::Client::new(http.clone(), cx);
         let mut languages = LanguageRegistry::new(login_shell_env_loaded);
@@ -209,9 +216,12 @@ fn main() {
         file_finder::init(cx);
         outline::init(cx);
         project_symbols::init(cx);
+        type_hierarchy::init(cx);
         project_panel::init(Assets, cx);
+        git_panel::init(cx);
         channel::init(&client, user_store.clone(), cx);
         search::init(cx);
+        search::load_search_history(cx);
         semantic_index::init(fs.clone(), http.clone(), languages.clone(), cx);
         vim::init(cx);
         terminal_view::init(cx);
@@ -433,6 +443,7 @@ async fn restore_or_create_workspace(app_state: &Arc<AppState>, cx: AsyncAppCont
 fn init_paths() {
     std::fs::create_dir_all(&*util::paths::CONFIG_DIR).expect("could not create config path");
     std::fs::create_dir_all(&*util::paths::LANGUAGES_DIR).expect("could not create languages path");
+    std::fs::create_dir_all(&*util::paths::SNIPPETS_DIR).expect("could not create snippets path");
     std::fs::create_dir_all(&*util::paths::DB_DIR).expect("could not create database path");
     std::fs::create_dir_all(&*util::paths::LOGS_DIR).expect("could not create logs path");
 }